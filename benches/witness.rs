@@ -0,0 +1,88 @@
+//! Compares the sequential and the parallel witness generation of the kimchi backend,
+//! on a circuit made of thousands of independent multiplications.
+//!
+//! Run with `cargo bench --bench witness`.
+
+use std::time::{Duration, Instant};
+
+use noname::{
+    backends::kimchi::KimchiVesta,
+    compiler::{compile, typecheck_next_file, Sources},
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+const NUM_MULS: usize = 4096;
+
+/// The number of witnesses generated for each variant, of which the fastest is reported.
+const NUM_RUNS: usize = 10;
+
+fn compile_muls(parallel: bool) -> CompiledCircuit<KimchiVesta> {
+    let code = format!(
+        r#"
+fn main(xx: [Field; {NUM_MULS}], yy: [Field; {NUM_MULS}], zz: [Field; {NUM_MULS}]) {{
+    for ii in 0..{NUM_MULS} {{
+        assert_eq(xx[ii] * yy[ii], zz[ii]);
+    }}
+}}
+"#
+    );
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "muls.no".to_string(),
+        code,
+        0,
+    )
+    .unwrap();
+
+    let mut backend = KimchiVesta::new(false);
+    backend.set_parallel_witness(parallel);
+    compile(&sources, tast, backend).unwrap()
+}
+
+fn private_inputs() -> String {
+    let array = |value: fn(usize) -> usize| {
+        (0..NUM_MULS)
+            .map(|ii| format!(r#""{}""#, value(ii)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    format!(
+        r#"{{"xx": [{}], "yy": [{}], "zz": [{}]}}"#,
+        array(|ii| ii),
+        array(|ii| ii + 1),
+        array(|ii| ii * (ii + 1)),
+    )
+}
+
+fn main() {
+    let private_inputs = private_inputs();
+
+    println!(
+        "time to generate a witness of {NUM_MULS} multiplications (fastest of {NUM_RUNS} runs):"
+    );
+    for (name, parallel) in [("sequential", false), ("parallel", true)] {
+        let compiled_circuit = compile_muls(parallel);
+
+        let mut fastest = Duration::MAX;
+        for _ in 0..NUM_RUNS {
+            let public_inputs = parse_inputs("{}").unwrap();
+            let private_inputs = parse_inputs(&private_inputs).unwrap();
+
+            let start = Instant::now();
+            compiled_circuit
+                .generate_witness(public_inputs, private_inputs)
+                .unwrap();
+            fastest = fastest.min(start.elapsed());
+        }
+
+        println!("  {name:<10}: {fastest:?}");
+    }
+}
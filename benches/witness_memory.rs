@@ -0,0 +1,138 @@
+//! Measures the peak memory used to generate a kimchi witness (in kimchi format),
+//! with [CompiledCircuit::generate_witness] followed by a conversion to columns,
+//! and with [CompiledCircuit::generate_witness_streaming], on a circuit made of many multiplications.
+//!
+//! Run with `cargo bench --bench witness_memory`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use noname::{
+    backends::kimchi::KimchiVesta,
+    compiler::{compile, typecheck_next_file, Sources},
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+const NUM_MULS: usize = 1 << 15;
+
+/// The system allocator, keeping track of the memory currently allocated and of its peak.
+struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+/// Returns the peak memory allocated while running `f`, on top of what was allocated before.
+fn peak_memory<T>(f: impl FnOnce() -> T) -> usize {
+    let before = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let res = f();
+    let peak = PEAK.load(Ordering::Relaxed);
+    drop(res);
+    peak - before
+}
+
+fn compile_muls() -> CompiledCircuit<KimchiVesta> {
+    let code = format!(
+        r#"
+fn main(xx: [Field; {NUM_MULS}], yy: [Field; {NUM_MULS}], zz: [Field; {NUM_MULS}]) {{
+    for ii in 0..{NUM_MULS} {{
+        assert_eq(xx[ii] * yy[ii], zz[ii]);
+    }}
+}}
+"#
+    );
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "muls.no".to_string(),
+        code,
+        0,
+    )
+    .unwrap();
+
+    // the values of the variables are computed sequentially,
+    // so that the peak only depends on the format of the witness
+    let mut backend = KimchiVesta::new(false);
+    backend.set_parallel_witness(false);
+    compile(&sources, tast, backend).unwrap()
+}
+
+fn private_inputs() -> String {
+    let array = |value: fn(usize) -> usize| {
+        (0..NUM_MULS)
+            .map(|ii| format!(r#""{}""#, value(ii)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    format!(
+        r#"{{"xx": [{}], "yy": [{}], "zz": [{}]}}"#,
+        array(|ii| ii),
+        array(|ii| ii + 1),
+        array(|ii| ii * (ii + 1)),
+    )
+}
+
+fn main() {
+    let compiled_circuit = compile_muls();
+    let private_inputs = private_inputs();
+    let num_rows = compiled_circuit.num_gates();
+
+    let converted = peak_memory(|| {
+        let generated_witness = compiled_circuit
+            .generate_witness(
+                parse_inputs("{}").unwrap(),
+                parse_inputs(&private_inputs).unwrap(),
+            )
+            .unwrap();
+        generated_witness.all_witness.to_kimchi_witness()
+    });
+
+    let streamed = peak_memory(|| {
+        compiled_circuit
+            .generate_witness_streaming(
+                parse_inputs("{}").unwrap(),
+                parse_inputs(&private_inputs).unwrap(),
+            )
+            .unwrap()
+            .columns
+    });
+
+    let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    println!("peak memory to generate a witness of {num_rows} rows in kimchi format:");
+    println!(
+        "  generate_witness + to_kimchi_witness: {:.1} MiB",
+        mib(converted)
+    );
+    println!(
+        "  generate_witness_streaming:           {:.1} MiB",
+        mib(streamed)
+    );
+}
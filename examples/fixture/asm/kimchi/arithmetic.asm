@@ -6,5 +6,4 @@ DoubleGeneric<0,0,-1,1>
 DoubleGeneric<1,-1>
 (0,0) -> (1,1) -> (2,1)
 (1,0) -> (2,0)
-(1,2) -> (3,0)
-(2,2) -> (3,1)
+(1,2) -> (2,2) -> (3,0) -> (3,1)
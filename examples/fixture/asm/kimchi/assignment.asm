@@ -15,5 +15,4 @@ DoubleGeneric<1,0,0,0,-5>
 (1,2) -> (2,0)
 (3,2) -> (9,0)
 (4,2) -> (10,0)
-(5,2) -> (7,0) -> (8,0)
-(6,2) -> (7,1)
+(5,2) -> (6,2) -> (7,0) -> (7,1) -> (8,0)
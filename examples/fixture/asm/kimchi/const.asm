@@ -6,6 +6,5 @@ DoubleGeneric<1,0,0,0,-1>
 DoubleGeneric<1,0,-1,0,1>
 DoubleGeneric<1,0,0,0,-2>
 DoubleGeneric<1,-1>
-(0,0) -> (5,0)
+(0,0) -> (3,2) -> (4,0) -> (5,0) -> (5,1)
 (1,0) -> (2,0) -> (3,0)
-(3,2) -> (4,0) -> (5,1)
@@ -2,8 +2,7 @@
 
 DoubleGeneric<1>
 DoubleGeneric<1>
-DoubleGeneric<1,1>
-DoubleGeneric<1,1,-1>
+DoubleGeneric<1,-1,-1>
 DoubleGeneric<0,0,-1,1>
 DoubleGeneric<1,1>
 DoubleGeneric<1,0,-1,0,1>
@@ -12,8 +11,7 @@ DoubleGeneric<0,0,-1,1>
 DoubleGeneric<1>
 DoubleGeneric<1,0,0,0,-1>
 DoubleGeneric<1,0,0,0,-3>
-DoubleGeneric<1,1>
-DoubleGeneric<1,1,-1>
+DoubleGeneric<1,-1,-1>
 DoubleGeneric<0,0,-1,1>
 DoubleGeneric<1,1>
 DoubleGeneric<1,0,-1,0,1>
@@ -21,20 +19,16 @@ DoubleGeneric<1,-1>
 DoubleGeneric<0,0,-1,1>
 DoubleGeneric<1>
 DoubleGeneric<1,0,0,0,-1>
-(0,0) -> (2,0) -> (12,0)
-(1,0) -> (3,0)
-(2,1) -> (3,1)
-(3,2) -> (4,1) -> (8,1)
-(4,2) -> (7,0)
-(5,0) -> (8,0) -> (10,0)
-(5,1) -> (6,0)
-(6,2) -> (7,1)
-(8,2) -> (9,0)
-(11,0) -> (13,0)
-(12,1) -> (13,1)
-(13,2) -> (14,1) -> (18,1)
-(14,2) -> (17,0)
-(15,0) -> (18,0) -> (20,0)
-(15,1) -> (16,0)
-(16,2) -> (17,1)
-(18,2) -> (19,0)
+(0,0) -> (2,1) -> (11,1)
+(1,0) -> (2,0)
+(2,2) -> (3,1) -> (7,1)
+(3,2) -> (5,2) -> (6,0) -> (6,1)
+(4,0) -> (7,0) -> (9,0)
+(4,1) -> (5,0)
+(7,2) -> (8,0)
+(10,0) -> (11,0)
+(11,2) -> (12,1) -> (16,1)
+(12,2) -> (14,2) -> (15,0) -> (15,1)
+(13,0) -> (16,0) -> (18,0)
+(13,1) -> (14,0)
+(16,2) -> (17,0)
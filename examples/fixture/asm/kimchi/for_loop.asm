@@ -4,6 +4,5 @@ DoubleGeneric<1>
 DoubleGeneric<1,1,-1>
 DoubleGeneric<1,1,-1>
 DoubleGeneric<1,-1>
-(0,0) -> (3,1)
+(0,0) -> (2,2) -> (3,0) -> (3,1)
 (1,2) -> (2,0)
-(2,2) -> (3,0)
@@ -3,8 +3,7 @@
 DoubleGeneric<1>
 DoubleGeneric<1,0,-1,0,1>
 DoubleGeneric<1,0,0,0,-1>
-DoubleGeneric<1,1>
-DoubleGeneric<1,1,-1>
+DoubleGeneric<1,-1,-1>
 DoubleGeneric<0,0,-1,1>
 DoubleGeneric<1,1>
 DoubleGeneric<1,0,-1,0,1>
@@ -17,18 +16,16 @@ DoubleGeneric<1,0,-1,0,1>
 DoubleGeneric<0,0,-1,1>
 DoubleGeneric<1,1,-1>
 DoubleGeneric<1,0,0,0,-2>
-(0,0) -> (1,0) -> (3,0) -> (14,1)
-(1,2) -> (11,0)
-(2,0) -> (4,0)
-(3,1) -> (4,1)
-(4,2) -> (5,1) -> (9,1)
-(5,2) -> (8,0)
-(6,0) -> (9,0) -> (11,1) -> (12,0)
-(6,1) -> (7,0)
-(7,2) -> (8,1)
-(9,2) -> (10,0)
-(11,2) -> (15,0)
-(12,1) -> (13,0)
-(13,2) -> (14,0)
-(14,2) -> (15,1)
-(15,2) -> (16,0)
+(0,0) -> (1,0) -> (3,1) -> (13,1)
+(1,2) -> (10,0)
+(2,0) -> (3,0)
+(3,2) -> (4,1) -> (8,1)
+(4,2) -> (6,2) -> (7,0) -> (7,1)
+(5,0) -> (8,0) -> (10,1) -> (11,0)
+(5,1) -> (6,0)
+(8,2) -> (9,0)
+(10,2) -> (14,0)
+(11,1) -> (12,0)
+(12,2) -> (13,0)
+(13,2) -> (14,1)
+(14,2) -> (15,0)
@@ -5,7 +5,6 @@ DoubleGeneric<1>
 DoubleGeneric<1,0,-1,0,-1>
 DoubleGeneric<1,0,-1,0,3>
 DoubleGeneric<1,-1>
-(0,0) -> (4,0)
+(0,0) -> (3,2) -> (4,0) -> (4,1)
 (1,0) -> (2,0)
 (2,2) -> (3,0)
-(3,2) -> (4,1)
@@ -180,6 +180,5 @@ Poseidon<c135,c136,c137,c138,c139,c140,c141,c142,c143,c144,c145,c146,c147,c148,c
 Poseidon<c150,c151,c152,c153,c154,c155,c156,c157,c158,c159,c160,c161,c162,c163,c164>
 Zero
 DoubleGeneric<1,-1>
-(0,0) -> (14,1)
+(0,0) -> (13,0) -> (14,0) -> (14,1)
 (1,0) -> (2,2)
-(13,0) -> (14,0)
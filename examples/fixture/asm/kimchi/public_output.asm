@@ -6,7 +6,6 @@ DoubleGeneric<1,1,-1>
 DoubleGeneric<1,0,0,0,-2>
 DoubleGeneric<1,0,-1,0,6>
 DoubleGeneric<1,-1>
-(0,0) -> (5,0)
+(0,0) -> (4,2) -> (5,0) -> (5,1)
 (1,0) -> (2,1)
 (2,2) -> (3,0) -> (4,0)
-(4,2) -> (5,1)
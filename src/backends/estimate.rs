@@ -0,0 +1,739 @@
+//! A cheap and approximate estimation of the size of a circuit.
+//!
+//! Instead of generating constraints, this walks the typed AST and counts the operations that would hit the backend.
+//! Each backend then turns these counts into a number of rows (or constraints) via [Backend::estimate_rows].
+//! This is useful to pick an SRS or domain size before compiling the circuit.
+
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+use num_traits::One as _;
+
+use crate::{
+    cli::packages::UserRepo,
+    imports::FnKind,
+    parser::{
+        types::{FunctionDef, ModulePath, Stmt, StmtKind, TyKind},
+        Expr, ExprKind, Op2,
+    },
+    stdlib::{
+        blake2s,
+        crypto::{is_merkle_verify_with, POSEIDON_RATE},
+        fp2::QUALIFIED_FP2,
+        is_fold, is_select, keccak,
+    },
+    syntax::parse_number,
+    type_checker::{FullyQualified, TypeChecker},
+};
+
+use super::Backend;
+
+/// The number of bits we assume for a bit decomposition whose size is not known statically.
+const DEFAULT_NUM_BITS: usize = 254;
+
+/// Counts of the different kinds of operations performed by a piece of circuit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpCount {
+    /// Additions, subtractions, negations, and multiplications by a constant.
+    pub linear: usize,
+
+    /// Multiplications of two variables.
+    pub mul: usize,
+
+    /// Assertions that a variable is equal to a constant or to another variable.
+    pub assert: usize,
+
+    /// Permutations of the poseidon sponge (one per two elements hashed by the poseidon builtin).
+    pub poseidon: usize,
+
+    /// Calls to the lookup builtin.
+    pub lookup: usize,
+
+    /// Calls to the range_check64 builtin.
+    pub range_check: usize,
+}
+
+impl OpCount {
+    fn linear(linear: usize) -> Self {
+        Self {
+            linear,
+            ..Default::default()
+        }
+    }
+
+    fn mul(mul: usize) -> Self {
+        Self {
+            mul,
+            ..Default::default()
+        }
+    }
+
+    fn assert(assert: usize) -> Self {
+        Self {
+            assert,
+            ..Default::default()
+        }
+    }
+
+    fn times(self, n: usize) -> Self {
+        Self {
+            linear: self.linear * n,
+            mul: self.mul * n,
+            assert: self.assert * n,
+            poseidon: self.poseidon * n,
+            lookup: self.lookup * n,
+            range_check: self.range_check * n,
+        }
+    }
+}
+
+impl std::ops::Add for OpCount {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            linear: self.linear + other.linear,
+            mul: self.mul + other.mul,
+            assert: self.assert + other.assert,
+            poseidon: self.poseidon + other.poseidon,
+            lookup: self.lookup + other.lookup,
+            range_check: self.range_check + other.range_check,
+        }
+    }
+}
+
+impl std::ops::AddAssign for OpCount {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+/// The result of [estimate_costs].
+#[derive(Debug, Default, Clone)]
+pub struct CostEstimate {
+    /// The number of field elements in the public inputs and public outputs of `main`.
+    pub public_io: usize,
+
+    /// The operations performed by the whole circuit.
+    pub ops: OpCount,
+
+    /// The operations performed by a single call of each function reachable from `main`.
+    pub per_fn: HashMap<String, OpCount>,
+}
+
+/// Walks the typed AST, starting from `main`, and counts the operations the circuit will perform.
+/// Loops with constant bounds are unrolled, and constant-only arithmetic is considered free.
+pub fn estimate_costs<B: Backend>(typed: &TypeChecker<B>) -> CostEstimate {
+    let mut estimator = Estimator {
+        typed,
+        per_fn: HashMap::new(),
+        loop_vars: vec![],
+    };
+
+    let qualified = FullyQualified::local("main".to_string());
+    let main = match typed.fn_info(&qualified).map(|info| &info.kind) {
+        Some(FnKind::Native(main)) => main,
+        _ => return CostEstimate::default(),
+    };
+
+    // public inputs and public outputs each take a cell
+    let public_inputs: usize = main
+        .sig
+        .arguments
+        .iter()
+        .filter(|arg| arg.is_public())
+        .map(|arg| typed.size_of(&arg.typ.kind))
+        .sum();
+    let public_outputs = main
+        .sig
+        .return_type
+        .as_ref()
+        .map(|typ| typed.size_of(&typ.kind))
+        .unwrap_or(0);
+
+    // the public outputs are constrained to be equal to the returned values
+    let ops = estimator.function("main".to_string(), main) + OpCount::assert(public_outputs);
+
+    CostEstimate {
+        public_io: public_inputs + public_outputs,
+        ops,
+        per_fn: estimator.per_fn,
+    }
+}
+
+struct Estimator<'a, B: Backend> {
+    typed: &'a TypeChecker<B>,
+    per_fn: HashMap<String, OpCount>,
+
+    /// The variables of the loops we're currently in, which are constants once the loop is unrolled.
+    loop_vars: Vec<String>,
+}
+
+impl<B: Backend> Estimator<'_, B> {
+    fn function(&mut self, name: String, func: &FunctionDef) -> OpCount {
+        if let Some(ops) = self.per_fn.get(&name) {
+            return *ops;
+        }
+
+        let ops = self.stmts(&func.body);
+        self.per_fn.insert(name, ops);
+        ops
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) -> OpCount {
+        let mut ops = OpCount::default();
+
+        for stmt in stmts {
+            ops += match &stmt.kind {
+                StmtKind::Assign { rhs, .. } | StmtKind::AssignTuple { rhs, .. } => self.expr(rhs),
+                StmtKind::Expr(expr) | StmtKind::Return(expr) => self.expr(expr),
+                StmtKind::Comment(_) => OpCount::default(),
+                StmtKind::ForLoop { var, range, body } => {
+                    // the type checker made sure that the bounds are known
+                    let iterations = self
+                        .typed
+                        .range_iterations(range)
+                        .map(|range| range.len())
+                        .unwrap_or(0);
+                    self.loop_vars.push(var.value.clone());
+                    let body = self.stmts(body);
+                    self.loop_vars.pop();
+                    body.times(iterations)
+                }
+                // the selections merging the reassignments and early returns of the body are not counted
+                StmtKind::If { cond, body } => self.expr(cond) + self.stmts(body),
+                // the condition is computed once per iteration, and once more to check that the loop terminated
+                StmtKind::While {
+                    cond,
+                    max_iters,
+                    body,
+                } => {
+                    let iterations = self.typed.bound_value(max_iters).unwrap_or(0) as usize;
+                    (self.expr(cond) + self.stmts(body)).times(iterations) + self.expr(cond)
+                }
+            };
+        }
+
+        ops
+    }
+
+    fn exprs<'e>(&mut self, exprs: impl IntoIterator<Item = &'e Expr>) -> OpCount {
+        let mut ops = OpCount::default();
+        for expr in exprs {
+            ops += self.expr(expr);
+        }
+        ops
+    }
+
+    /// Returns true if the expression is a compile-time constant that gets folded.
+    /// Note that we can't rely on the `BigInt` type for this, as the type checker also uses it for local field variables.
+    fn is_constant(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::BigInt(_) | ExprKind::Bool(_) => true,
+            ExprKind::Variable { module, name } => self
+                .typed
+                .const_info(&FullyQualified::new(module, &name.value))
+                .is_some(),
+            ExprKind::Negated(inner) | ExprKind::Not(inner) => self.is_constant(inner),
+            ExprKind::BinaryOp { lhs, rhs, .. } => self.is_constant(lhs) && self.is_constant(rhs),
+            _ => false,
+        }
+    }
+
+    fn size_of_expr(&self, expr: &Expr) -> usize {
+        self.typed
+            .expr_type(expr)
+            .map(|typ| self.typed.size_of(typ))
+            .unwrap_or(1)
+    }
+
+    fn expr(&mut self, expr: &Expr) -> OpCount {
+        let typed = self.typed;
+
+        match &expr.kind {
+            // `fold(arr, init, f)` calls `f` once per element
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } if is_fold(module, &fn_name.value) => match args.as_slice() {
+                [arr, init, func] => {
+                    let ops = self.expr(arr) + self.expr(init);
+                    let len = match typed.expr_type(arr) {
+                        Some(TyKind::Array(_, len)) => *len as usize,
+                        _ => 0,
+                    };
+
+                    let call = match &func.kind {
+                        ExprKind::Variable { module, name } => {
+                            self.call(expr, module, &name.value, &[])
+                        }
+                        _ => OpCount::default(),
+                    };
+
+                    ops + call.times(len)
+                }
+                _ => self.exprs(args),
+            },
+
+            // `select(cond, a, b)` selects each cell like an `if`/`else` expression
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } if is_select(module, &fn_name.value) => {
+                let cells = args.get(1).map_or(0, |arg| self.size_of_expr(arg));
+                self.exprs(args) + (OpCount::linear(3) + OpCount::mul(2)).times(cells)
+            }
+
+            // `crypto::merkle_verify_with(leaf, path, indices, root, hash)` hashes each level with `hash`
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } if is_merkle_verify_with(module, &fn_name.value) => match args.as_slice() {
+                [leaf, path, indices, root, hash] => {
+                    let ops = self.exprs([leaf, path, indices, root]);
+                    let depth = self.size_of_expr(path);
+
+                    let hash = match &hash.kind {
+                        ExprKind::Variable { module, name } => {
+                            self.call(expr, module, &name.value, &[])
+                        }
+                        _ => OpCount::default(),
+                    };
+
+                    ops + (OpCount::linear(4) + OpCount::mul(1) + hash).times(depth)
+                        + OpCount::assert(1)
+                }
+                _ => self.exprs(args),
+            },
+
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } => self.exprs(args) + self.call(expr, module, &fn_name.value, args),
+
+            ExprKind::MethodCall {
+                lhs,
+                method_name,
+                args,
+            } => {
+                let mut ops = self.expr(lhs) + self.exprs(args);
+
+                if let Some(lhs_typ @ TyKind::Custom { name, .. }) = typed.expr_type(lhs) {
+                    if let Some((method, _)) = typed.method_info(lhs_typ, &method_name.value) {
+                        ops += self.function(format!("{name}.{}", method_name.value), method);
+                    }
+                }
+
+                ops
+            }
+
+            ExprKind::Assignment { lhs, rhs } => self.expr(lhs) + self.expr(rhs),
+
+            ExprKind::FieldAccess { lhs, .. } => self.expr(lhs),
+
+            ExprKind::BinaryOp { op, lhs, rhs, .. } => {
+                let ops = self.expr(lhs) + self.expr(rhs);
+
+                // `lhs + rhs` on a struct calls `lhs.add(rhs)`
+                if let Some(lhs_typ @ TyKind::Custom { name, .. }) = typed.expr_type(lhs) {
+                    if let Some(method) = typed.operator_method(lhs_typ, op) {
+                        let method_name = op.method_name().expect("operator method without a name");
+                        return ops + self.function(format!("{name}.{method_name}"), method);
+                    }
+                }
+
+                let (lhs_cst, rhs_cst) = (self.is_constant(lhs), self.is_constant(rhs));
+                if lhs_cst && rhs_cst {
+                    return ops;
+                }
+
+                ops + match op {
+                    Op2::Addition => OpCount::linear(1),
+                    // a constant minus a var is a negation followed by an addition
+                    Op2::Subtraction if lhs_cst => OpCount::linear(2),
+                    Op2::Subtraction => OpCount::linear(1),
+                    Op2::Multiplication if lhs_cst || rhs_cst => OpCount::linear(1),
+                    Op2::Multiplication => OpCount::mul(1),
+                    // not supported by the circuit writer yet
+                    Op2::Division => OpCount::default(),
+                    // the remainder is smaller than the operands, so it isn't range checked again
+                    Op2::Modulo => {
+                        let lhs_bits = typed.expr_type(lhs).and_then(TyKind::num_bits);
+                        return ops + modulo_cost(lhs_bits, self.constant_of(rhs));
+                    }
+                    // a difference, an inverse check, and a boolean result per cell,
+                    // then the results are and'ed together
+                    Op2::Equality => {
+                        let cells = self.size_of_expr(lhs);
+                        OpCount {
+                            linear: 2 * cells,
+                            mul: 2 * cells + cells.saturating_sub(1),
+                            ..Default::default()
+                        }
+                    }
+                    Op2::BoolAnd => OpCount::mul(1),
+                    // three negations and a multiplication
+                    Op2::BoolOr => OpCount::linear(6) + OpCount::mul(1),
+                } + match typed.expr_type(expr).and_then(TyKind::num_bits) {
+                    // the result of integer arithmetic is range checked
+                    Some(num_bits) => bit_decomposition(num_bits),
+                    None => OpCount::default(),
+                }
+            }
+
+            ExprKind::Negated(inner) => {
+                let ops = self.expr(inner);
+                if self.is_constant(inner) {
+                    ops
+                } else {
+                    ops + OpCount::linear(1)
+                }
+            }
+
+            // `1 - x`
+            ExprKind::Not(inner) => {
+                let ops = self.expr(inner);
+                if self.is_constant(inner) {
+                    ops
+                } else {
+                    ops + OpCount::linear(2)
+                }
+            }
+
+            // a slice refers to the cells of the array
+            ExprKind::ArraySlice { array, .. } => self.expr(array),
+
+            ExprKind::ArrayAccess { array, idx } => {
+                let ops = self.expr(array) + self.expr(idx);
+
+                let is_loop_var = matches!(
+                    &idx.kind,
+                    ExprKind::Variable { name, .. } if self.loop_vars.contains(&name.value)
+                );
+                if self.is_constant(idx) || is_loop_var {
+                    return ops;
+                }
+
+                // a runtime index: an equality check per element,
+                // a multiplication and an addition per cell, and a bound check
+                let (elems, cells) = match typed.expr_type(array) {
+                    Some(TyKind::Array(_, len)) => (*len as usize, self.size_of_expr(array)),
+                    _ => (1, 1),
+                };
+                ops + (OpCount::linear(4) + OpCount::mul(2)).times(elems)
+                    + (OpCount::linear(1) + OpCount::mul(1)).times(cells)
+                    + OpCount::assert(1)
+            }
+
+            ExprKind::ArrayDeclaration(items) | ExprKind::TupleDeclaration(items) => {
+                self.exprs(items)
+            }
+
+            ExprKind::CustomTypeDeclaration { fields, .. } => {
+                self.exprs(fields.iter().map(|(_, expr)| expr))
+            }
+
+            // `cond * then + (1 - cond) * else` for each cell
+            ExprKind::IfElse { cond, then_, else_ } => {
+                let cells = self.size_of_expr(then_);
+                self.expr(cond)
+                    + self.expr(then_)
+                    + self.expr(else_)
+                    + (OpCount::linear(3) + OpCount::mul(2)).times(cells)
+            }
+
+            ExprKind::BigInt(_)
+            | ExprKind::Bool(_)
+            | ExprKind::StringLiteral(_)
+            | ExprKind::Variable { .. } => OpCount::default(),
+        }
+    }
+
+    /// Counts the operations of a call to a function (or to the instance it refers to, if it is generic).
+    fn call(
+        &mut self,
+        call: &Expr,
+        module: &ModulePath,
+        fn_name: &String,
+        args: &[Expr],
+    ) -> OpCount {
+        let typed = self.typed;
+
+        let qualified = typed.called_fn(call, module, fn_name);
+        match typed.fn_info(&qualified).map(|info| &info.kind) {
+            Some(FnKind::BuiltIn(sig, _))
+                if qualified.module == Some(UserRepo::new(QUALIFIED_FP2)) =>
+            {
+                fp2_cost(&sig.name.value)
+            }
+            Some(FnKind::BuiltIn(sig, _)) => self.builtin(&sig.name.value, args),
+            Some(FnKind::Native(func)) => {
+                let name = match &qualified.module {
+                    Some(module) => format!("{module}::{}", qualified.name),
+                    None => qualified.name.clone(),
+                };
+                self.function(name, func)
+            }
+            None => OpCount::default(),
+        }
+    }
+
+    /// The cost of the builtins, mirroring their implementation in [crate::stdlib].
+    fn builtin(&self, name: &str, args: &[Expr]) -> OpCount {
+        match name {
+            "assert" => OpCount::assert(1),
+            // `cond * check = cond`
+            "assert_if" => OpCount::mul(1) + OpCount::assert(1),
+            "assert_eq" => OpCount::assert(args.first().map_or(1, |arg| self.size_of_expr(arg))),
+            "assert_lt" => assert_lt_cost(args.get(1).and_then(|bound| self.constant_of(bound))),
+            "xor" => {
+                let num_bits = self.num_bits_arg(args.get(2));
+
+                // decompose both inputs and the result, and compute `a + b - 2ab` for each bit
+                bit_decomposition(num_bits).times(3)
+                    + (OpCount::linear(3) + OpCount::mul(1)).times(num_bits)
+            }
+            "less_than" | "less_equal" => {
+                let num_bits = self.num_bits_arg(args.get(2));
+
+                // decompose both inputs, and their shifted difference
+                bit_decomposition(num_bits).times(2)
+                    + bit_decomposition(num_bits + 1)
+                    + OpCount::linear(4)
+            }
+            "to_bits" => bit_decomposition(self.num_bits_arg(args.get(1))),
+            // a scaling and an addition per bit
+            "from_bits" => {
+                OpCount::linear(2 * args.first().map_or(0, |arg| self.size_of_expr(arg)))
+            }
+            // `as_field` only reinterprets the cell, `as_bool` checks `x * (x - 1) = 0`
+            "as_bool" => OpCount::linear(1) + OpCount::mul(1) + OpCount::assert(1),
+            // `inv * rhs = 1` and `quotient * rhs = lhs`
+            "div" => OpCount::mul(2) + OpCount::assert(2),
+            // `root * root = value`
+            "sqrt" => OpCount::mul(1) + OpCount::assert(1),
+            // a boolean check, the squaring of the root, the selection of the square, and a zero check
+            "is_square" => OpCount::linear(8) + OpCount::mul(6) + OpCount::assert(3),
+            // square-and-multiply
+            "pow" => match args.get(1).and_then(|exp| self.constant_of(exp)) {
+                Some(exp) if exp.bits() > 0 => {
+                    OpCount::mul((exp.bits() + exp.count_ones()) as usize - 2)
+                }
+                _ => OpCount::default(),
+            },
+            "blake2s" => blake2s_cost(args.first().map_or(0, |arg| self.size_of_expr(arg))),
+            "keccak256" => keccak256_cost(args.first().map_or(0, |arg| self.size_of_expr(arg))),
+            "poseidon" => poseidon_cost(args.first().map_or(0, |arg| self.size_of_expr(arg))),
+            // a selection of the children and a hash per level, then a comparison with the root
+            "merkle_verify" => {
+                let depth = args.get(1).map_or(0, |arg| self.size_of_expr(arg));
+                let level = OpCount::linear(4)
+                    + OpCount::mul(1)
+                    + OpCount {
+                        poseidon: 1,
+                        ..Default::default()
+                    };
+                level.times(depth) + OpCount::assert(1)
+            }
+            "lookup" => OpCount {
+                lookup: 1,
+                ..Default::default()
+            },
+            "range_check64" => OpCount {
+                range_check: 1,
+                ..Default::default()
+            },
+            _ => OpCount::default(),
+        }
+    }
+
+    /// Returns the number of bits given as a constant argument, or a conservative default.
+    fn num_bits_arg(&self, arg: Option<&Expr>) -> usize {
+        arg.and_then(|bits| self.constant_of(bits))
+            .and_then(|bits| usize::try_from(bits).ok())
+            .unwrap_or(DEFAULT_NUM_BITS)
+    }
+
+    /// Returns the value of an expression if it is a literal or a constant.
+    fn constant_of(&self, expr: &Expr) -> Option<BigUint> {
+        match &expr.kind {
+            ExprKind::BigInt(b) => parse_number(b),
+            ExprKind::Variable { module, name } => {
+                let qualified = FullyQualified::new(module, &name.value);
+                match self.typed.const_info(&qualified)?.value.as_slice() {
+                    [cst] => Some((*cst).into()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The cost of decomposing a value into `num_bits` constrained bits, and of recomposing them.
+fn bit_decomposition(num_bits: usize) -> OpCount {
+    // each bit is checked with `b * (b - 1) = 0` and then accumulated
+    (OpCount::linear(3) + OpCount::mul(1) + OpCount::assert(1)).times(num_bits) + OpCount::assert(1)
+}
+
+/// The cost of asserting that a value is smaller than a bound, mirroring [crate::constraints::field::assert_lt_const].
+fn assert_lt_cost(bound: Option<BigUint>) -> OpCount {
+    match bound {
+        Some(bound) if bound.bits() > 0 => {
+            let num_bits = (&bound - 1u32).bits() as usize;
+            let is_power_of_two = (BigUint::one() << num_bits) == bound;
+            let decompositions = if is_power_of_two { 1 } else { 2 };
+            bit_decomposition(num_bits).times(decompositions)
+        }
+        _ => bit_decomposition(DEFAULT_NUM_BITS).times(2),
+    }
+}
+
+/// The cost of `lhs % modulus`, mirroring [crate::constraints::field::modulo].
+fn modulo_cost(lhs_bits: Option<usize>, modulus: Option<BigUint>) -> OpCount {
+    let num_bits = lhs_bits.unwrap_or(DEFAULT_NUM_BITS);
+    let modulus_bits = modulus
+        .as_ref()
+        .map_or(0, |modulus| modulus.bits() as usize);
+    let quotient_bits = (num_bits + 1).saturating_sub(modulus_bits);
+
+    // the quotient and the remainder are range checked, then `q * modulus + r = lhs`
+    bit_decomposition(quotient_bits)
+        + assert_lt_cost(modulus)
+        + OpCount::linear(2)
+        + OpCount::assert(1)
+}
+
+/// The cost of hashing `num_words` words with Blake2s, mirroring [crate::stdlib::blake2s].
+fn blake2s_cost(num_words: usize) -> OpCount {
+    let num_blocks = std::cmp::max(1, num_words.div_ceil(blake2s::BLOCK_WORDS));
+    let recompose = OpCount::linear(2 * blake2s::WORD_BITS);
+    let xor = (OpCount::linear(3) + OpCount::mul(1)).times(blake2s::WORD_BITS);
+
+    // in each mix: two additions of three words, two additions of two words,
+    // each followed by a decomposition that drops the carry,
+    // and four xors (whose results need to be recomposed for the next addition)
+    let additions = (OpCount::linear(8) + bit_decomposition(blake2s::WORD_BITS + 2)).times(2)
+        + (OpCount::linear(4) + bit_decomposition(blake2s::WORD_BITS + 1)).times(2);
+    let mix = additions + (xor + recompose).times(4);
+    let compression = mix.times(8 * blake2s::ROUNDS) + xor.times(2 * blake2s::DIGEST_WORDS);
+
+    bit_decomposition(blake2s::WORD_BITS).times(num_words)
+        + compression.times(num_blocks)
+        + recompose.times(blake2s::DIGEST_WORDS)
+}
+
+/// The cost of hashing `num_elems` field elements with poseidon, mirroring [crate::stdlib::crypto::poseidon_sponge].
+fn poseidon_cost(num_elems: usize) -> OpCount {
+    // the first elements are absorbed in the zero state for free, the next ones are added to the state
+    OpCount {
+        linear: num_elems.saturating_sub(POSEIDON_RATE),
+        poseidon: std::cmp::max(1, num_elems.div_ceil(POSEIDON_RATE)),
+        ..Default::default()
+    }
+}
+
+/// The cost of the builtins of [crate::stdlib::fp2], on two pairs of field elements.
+fn fp2_cost(name: &str) -> OpCount {
+    match name {
+        "add" | "sub" => OpCount::linear(2),
+        "mul" => OpCount::mul(4) + OpCount::linear(2),
+        _ => OpCount::default(),
+    }
+}
+
+/// The cost of hashing `num_bytes` bytes with Keccak-256, mirroring [crate::stdlib::keccak].
+fn keccak256_cost(num_bytes: usize) -> OpCount {
+    let num_blocks = num_bytes / keccak::RATE_BYTES + 1;
+    let xor = (OpCount::linear(3) + OpCount::mul(1)).times(keccak::LANE_BITS);
+
+    // θ computes the parities of the 5 columns (4 xors each), combines them (5 xors),
+    // and xors the result into every lane,
+    // while χ negates, ands and xors every bit
+    let theta = xor.times(5 * 4 + 5 + keccak::STATE_LANES);
+    let chi = (OpCount::linear(4) + OpCount::mul(2)).times(keccak::LANE_BITS * keccak::STATE_LANES);
+    let permutation = (theta + chi).times(keccak::ROUNDS);
+
+    // the first block is xored into the zero state for free
+    let absorb = xor.times(keccak::RATE_BYTES / 8);
+
+    bit_decomposition(8).times(num_bytes)
+        + permutation.times(num_blocks)
+        + absorb.times(num_blocks - 1)
+        + OpCount::linear(2 * 128).times(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backends::{kimchi::KimchiVesta, Backend},
+        compiler::{compile, typecheck_next_file, Sources},
+        type_checker::TypeChecker,
+    };
+
+    fn typecheck(code: &str) -> (Sources, TypeChecker<KimchiVesta>) {
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "inline_test_estimate.no".to_string(),
+            code.to_owned(),
+            0,
+        )
+        .unwrap();
+
+        (sources, tast)
+    }
+
+    #[test]
+    fn test_estimate_rows() {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input + public_input;
+            assert_eq(xx, 2);
+            let yy = xx + 6;
+            return yy;
+        }"#;
+
+        let (sources, tast) = typecheck(code);
+        let backend = KimchiVesta::new(false);
+        let estimate = backend.estimate_rows(&tast);
+
+        // this simple circuit is estimated exactly
+        let compiled_circuit = compile(&sources, tast, backend).unwrap();
+        assert_eq!(estimate, compiled_circuit.num_gates());
+
+        // the double generic gate optimization can only make it smaller
+        let (_, tast) = typecheck(code);
+        assert!(KimchiVesta::new(true).estimate_rows(&tast) < estimate);
+    }
+
+    #[test]
+    fn test_estimate_rows_loops() {
+        let code = |iterations: usize| {
+            format!(
+                r#"fn main(pub xx: Field) -> Field {{
+                    let mut acc = xx;
+                    for ii in 0..{iterations} {{
+                        acc = acc + xx;
+                    }}
+                    return acc;
+                }}"#
+            )
+        };
+
+        let (_, four) = typecheck(&code(4));
+        let (_, eight) = typecheck(&code(8));
+
+        let backend = KimchiVesta::new(false);
+        assert_eq!(
+            backend.estimate_rows(&eight) - backend.estimate_rows(&four),
+            4
+        );
+    }
+}
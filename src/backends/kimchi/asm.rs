@@ -27,9 +27,7 @@ use std::hash::Hash;
 
 use crate::helpers::PrettyField;
 
-use super::VestaField;
-
-pub fn extract_vars_from_coeffs(vars: &mut OrderedHashSet<VestaField>, coeffs: &[VestaField]) {
+pub fn extract_vars_from_coeffs<F: PrettyField>(vars: &mut OrderedHashSet<F>, coeffs: &[F]) {
     for coeff in coeffs {
         let s = coeff.pretty();
         if s.len() >= 5 {
@@ -38,7 +36,7 @@ pub fn extract_vars_from_coeffs(vars: &mut OrderedHashSet<VestaField>, coeffs: &
     }
 }
 
-pub fn parse_coeffs(vars: &OrderedHashSet<VestaField>, coeffs: &[VestaField]) -> Vec<String> {
+pub fn parse_coeffs<F: PrettyField>(vars: &OrderedHashSet<F>, coeffs: &[F]) -> Vec<String> {
     let mut coeffs: Vec<_> = coeffs
         .iter()
         .map(|x| {
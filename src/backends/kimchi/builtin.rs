@@ -1,86 +1,52 @@
 use std::sync::Arc;
 
-use ark_ff::Zero;
 use kimchi::circuits::polynomials::poseidon::{POS_ROWS_PER_HASH, ROUNDS_PER_ROW};
 use kimchi::mina_poseidon::constants::{PlonkSpongeConstantsKimchi, SpongeConstants};
 use kimchi::mina_poseidon::permutation::full_round;
 
-use super::{KimchiCellVar, KimchiVesta, VestaField};
+use super::{curves::KimchiCurveConfig, Kimchi, KimchiCellVar};
 use crate::backends::kimchi::NUM_REGISTERS;
 use crate::backends::Backend;
 
 use crate::{
     circuit_writer::{CircuitWriter, GateKind, VarInfo},
     constants::Span,
-    error::{ErrorKind, Result},
-    parser::types::TyKind,
-    var::{ConstOrCell, Value, Var},
+    error::Result,
+    stdlib::crypto::poseidon_sponge,
+    var::{Value, Var},
 };
 
-pub fn poseidon(
-    compiler: &mut CircuitWriter<KimchiVesta>,
-    vars: &[VarInfo<VestaField, KimchiCellVar>],
+/// The poseidon builtin of kimchi, which constrains each permutation of the sponge with native poseidon gates.
+pub fn poseidon<C>(
+    compiler: &mut CircuitWriter<Kimchi<C>>,
+    vars: &[VarInfo<C::Field, KimchiCellVar>],
     span: Span,
-) -> Result<Option<Var<VestaField, KimchiCellVar>>> {
-    //
-    // sanity checks
-    //
-
-    // only one [Var] is passed
-    assert_eq!(vars.len(), 1);
-    let var_info = &vars[0];
-
-    // an array of length 2
-    match &var_info.typ {
-        Some(TyKind::Array(el_typ, 2)) => {
-            assert!(matches!(&**el_typ, TyKind::Field | TyKind::BigInt));
-        }
-        _ => panic!("wrong type for input to poseidon"),
-    };
-
-    // extract the values
-    let input = &var_info.var;
-    assert_eq!(input.len(), 2);
-
-    // hashing a full-constant input is not a good idea
-    if input[0].is_const() && input[1].is_const() {
-        return Err(compiler.error(
-            ErrorKind::UnexpectedError("cannot hash a full-constant input"),
-            span,
-        ));
-    }
-
-    // IMPORTANT: time to constrain any constants
-    let mut cells = vec![];
-    for const_or_cell in &input.cvars {
-        match const_or_cell {
-            ConstOrCell::Const(cst) => {
-                let cell = compiler.backend.add_constant(
-                    Some("encoding constant input to poseidon"),
-                    *cst,
-                    span,
-                );
-                cells.push(cell);
-            }
-            ConstOrCell::Cell(cell) => cells.push(*cell),
-        }
-    }
+) -> Result<Option<Var<C::Field, KimchiCellVar>>>
+where
+    C: KimchiCurveConfig,
+{
+    poseidon_sponge(compiler, vars, span, |compiler, cells| {
+        permutation(compiler, cells, span)
+    })
+}
 
+/// Constrains the poseidon permutation of a state with 11 poseidon gates (of 5 rounds each),
+/// followed by a zero gate holding the new state.
+fn permutation<C>(
+    compiler: &mut CircuitWriter<Kimchi<C>>,
+    cells: Vec<KimchiCellVar>,
+    span: Span,
+) -> Vec<KimchiCellVar>
+where
+    C: KimchiCurveConfig,
+{
     // get constants needed for poseidon
-    let poseidon_params = kimchi::mina_poseidon::pasta::fp_kimchi::params();
+    let poseidon_params = C::poseidon_params();
 
     let rc = &poseidon_params.round_constants;
     let width = PlonkSpongeConstantsKimchi::SPONGE_WIDTH;
 
-    // pad the input (for the capacity)
-    let zero_var = compiler.backend.add_constant(
-        Some("encoding constant 0 for the capacity of poseidon"),
-        VestaField::zero(),
-        span,
-    );
-    cells.push(zero_var);
-
-    let mut states = vec![cells.clone()];
+    let mut states = vec![cells];
 
     // 0..11
     for row in 0..POS_ROWS_PER_HASH {
@@ -105,8 +71,8 @@ pub fn poseidon(
                         let mut acc = vec![x1, x2, x3];
 
                         // Do one full round on the previous value
-                        full_round::<VestaField, PlonkSpongeConstantsKimchi>(
-                            &kimchi::mina_poseidon::pasta::fp_kimchi::params(),
+                        full_round::<C::Field, PlonkSpongeConstantsKimchi>(
+                            &C::poseidon_params(),
                             &mut acc,
                             offset + i,
                         );
@@ -169,12 +135,5 @@ pub fn poseidon(
         span,
     );
 
-    let vars = final_row
-        .iter()
-        .flatten()
-        .cloned()
-        .map(ConstOrCell::Cell)
-        .collect();
-
-    Ok(Some(Var::new(vars, span)))
+    final_row.into_iter().flatten().collect()
 }
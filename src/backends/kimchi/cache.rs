@@ -0,0 +1,312 @@
+//! An on-disk cache of compiled circuits, so that building an unchanged package doesn't generate its circuit again.
+//!
+//! A circuit is cached under a hash of everything that can change its gates:
+//! the source code of the package and of its dependencies, the [CircuitOptions],
+//! whether two generic gates are packed per row, and the version of noname.
+//! The hash is a SHA-256 of an explicit encoding of these, so that keys don't change between builds of noname.
+//! Only the gates, the lookup tables and the public input size are cached, which is enough to recreate the indexes,
+//! but not to generate a witness (hints can't be serialized).
+//! Circuits with warnings are never cached, as their warnings would be lost on a hit.
+
+use camino::Utf8PathBuf as PathBuf;
+use kimchi::circuits::{gate::CircuitGate, lookup::tables::LookupTable};
+use miette::{Context, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sha2::{Digest, Sha256};
+
+use crate::{circuit_writer::CircuitOptions, compiler::Sources};
+
+use super::{KimchiVesta, VestaField};
+
+/// The version of the format of a [CachedCircuit].
+/// This must be bumped every time [CachedCircuit] changes, or which circuits are cached.
+const CACHE_VERSION: u32 = 3;
+
+/// A circuit, as stored in the cache.
+#[derive(Serialize, Deserialize)]
+pub struct CachedCircuit {
+    version: u32,
+
+    /// The key the circuit was cached under, checked when it is read back.
+    key: u64,
+
+    /// The kimchi gates, wired together.
+    pub(crate) gates: Vec<CircuitGate<VestaField>>,
+
+    /// The lookup tables, in the format of kimchi's constraint system.
+    pub(crate) lookup_tables: Vec<CachedLookupTable>,
+
+    /// The number of public inputs (including public outputs).
+    pub(crate) public_input_size: usize,
+}
+
+impl CachedCircuit {
+    fn new(key: u64, backend: &KimchiVesta) -> Self {
+        Self {
+            version: CACHE_VERSION,
+            key,
+            gates: backend.to_kimchi_gates(),
+            lookup_tables: backend
+                .lookup_tables
+                .values()
+                .map(|table| CachedLookupTable::new(table.to_kimchi_table()))
+                .collect(),
+            public_input_size: backend.public_input_size,
+        }
+    }
+}
+
+/// A lookup table of a [CachedCircuit].
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CachedLookupTable {
+    id: i32,
+
+    #[serde_as(as = "crate::serialization::SerdeAs")]
+    data: Vec<Vec<VestaField>>,
+}
+
+impl CachedLookupTable {
+    fn new(table: LookupTable<VestaField>) -> Self {
+        Self {
+            id: table.id,
+            data: table.data,
+        }
+    }
+
+    pub(crate) fn to_kimchi_table(&self) -> LookupTable<VestaField> {
+        LookupTable {
+            id: self.id,
+            data: self.data.clone(),
+        }
+    }
+}
+
+/// Feeds a length-prefixed byte string to the hasher, so that consecutive strings can't be confused.
+fn hash_bytes(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+/// Returns the key under which the circuit compiled from `sources` is cached.
+pub fn cache_key(
+    sources: &Sources,
+    options: CircuitOptions,
+    double_generic_gate_optimization: bool,
+) -> u64 {
+    let mut hasher = Sha256::new();
+
+    hash_bytes(&mut hasher, env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(CACHE_VERSION.to_le_bytes());
+
+    // the sources, in the order they were type checked
+    let mut ids: Vec<_> = sources.map.keys().collect();
+    ids.sort();
+    for id in ids {
+        let (filename, code) = &sources.map[id];
+        hash_bytes(&mut hasher, filename.as_bytes());
+        hash_bytes(&mut hasher, code.as_bytes());
+    }
+
+    // destructured, so that a new option can't be forgotten here
+    let CircuitOptions {
+        opt_level,
+        loop_budget,
+        relaxed_branches,
+    } = options;
+    hasher.update([opt_level as u8]);
+    hasher.update((loop_budget as u64).to_le_bytes());
+    hasher.update([
+        relaxed_branches as u8,
+        double_generic_gate_optimization as u8,
+    ]);
+
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().unwrap())
+}
+
+/// A directory containing cached circuits.
+pub struct CircuitCache {
+    dir: PathBuf,
+}
+
+impl CircuitCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{key:016x}.circuit"))
+    }
+
+    /// Returns the circuit cached under `key`, if there's one.
+    /// An entry that can't be read (e.g. because it was written by another version of noname) is a miss.
+    pub fn get(&self, key: u64) -> Option<CachedCircuit> {
+        let bytes = std::fs::read(self.path(key)).ok()?;
+        let cached: CachedCircuit = rmp_serde::from_slice(&bytes).ok()?;
+
+        (cached.version == CACHE_VERSION && cached.key == key).then_some(cached)
+    }
+
+    /// Caches the circuit of a backend under `key`.
+    /// This must be called once the circuit has been finalized.
+    pub fn insert(&self, key: u64, backend: &KimchiVesta) -> miette::Result<()> {
+        let cached = CachedCircuit::new(key, backend);
+
+        let bytes = rmp_serde::to_vec(&cached)
+            .into_diagnostic()
+            .wrap_err("could not serialize the circuit")?;
+
+        std::fs::create_dir_all(&self.dir)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("could not create the cache directory `{}`", self.dir))?;
+
+        std::fs::write(self.path(key), bytes)
+            .into_diagnostic()
+            .wrap_err("could not write the circuit to the cache")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        circuit_writer::{CircuitOptions, OptLevel},
+        compiler::{compile_with_options, typecheck_next_file, Sources},
+        type_checker::TypeChecker,
+        witness::CompiledCircuit,
+    };
+
+    use super::super::lookup::LookupTable;
+    use super::*;
+
+    /// A temporary cache directory, deleted when dropped (even if the test fails).
+    struct TempDir(std::path::PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const CODE: &str = r#"fn main(pub xx: Field, yy: Field) -> Field {
+        let zz = xx * yy;
+        assert_eq(zz, 6);
+        return zz + xx;
+    }"#;
+
+    fn compile_code(
+        options: CircuitOptions,
+    ) -> miette::Result<(Sources, CompiledCircuit<KimchiVesta>)> {
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "cache.no".to_string(),
+            CODE.to_string(),
+            0,
+        )?;
+
+        let compiled_circuit =
+            compile_with_options(&sources, tast, KimchiVesta::new(true), options)?;
+        Ok((sources, compiled_circuit))
+    }
+
+    #[test]
+    fn test_cache_hit() -> miette::Result<()> {
+        let dir =
+            TempDir(std::env::temp_dir().join(format!("noname_test_cache_{}", std::process::id())));
+        let cache = CircuitCache::new(dir.0.clone().try_into().unwrap());
+        let options = CircuitOptions::default();
+
+        // the first compilation is a miss
+        let (sources, compiled_circuit) = compile_code(options)?;
+        let key = cache_key(&sources, options, true);
+        assert!(cache.get(key).is_none());
+        cache.insert(key, &compiled_circuit.circuit.backend)?;
+
+        // the second one, with the same inputs, is a hit with the same gates
+        let (sources, compiled_circuit) = compile_code(options)?;
+        let backend = &compiled_circuit.circuit.backend;
+        assert_eq!(cache_key(&sources, options, true), key);
+        let cached = cache.get(key).expect("the circuit should be cached");
+        assert_eq!(
+            rmp_serde::to_vec(&cached.gates).unwrap(),
+            rmp_serde::to_vec(&backend.to_kimchi_gates()).unwrap()
+        );
+        assert_eq!(cached.public_input_size, backend.public_input_size);
+
+        // the cached circuit is enough to recreate the same verifier index
+        let (_cs, cached_verifier_index) = cached.compile_to_indexes()?;
+        let (_prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+        assert_eq!(
+            rmp_serde::to_vec(&cached_verifier_index).unwrap(),
+            rmp_serde::to_vec(&verifier_index).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_lookup_tables() -> miette::Result<()> {
+        let code = r#"fn main(pub byte: Field) -> Field {
+            return lookup(1, byte);
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "cache_lookup.no".to_string(),
+            code.to_string(),
+            0,
+        )?;
+
+        let mut backend = KimchiVesta::new(true);
+        backend.add_lookup_table(LookupTable::range(1, 8));
+        let compiled_circuit =
+            compile_with_options(&sources, tast, backend, CircuitOptions::default())?;
+        let backend = &compiled_circuit.circuit.backend;
+
+        let cached = CachedCircuit::new(0, backend);
+        let cached: CachedCircuit =
+            rmp_serde::from_slice(&rmp_serde::to_vec(&cached).unwrap()).unwrap();
+
+        // the lookup tables are part of the recreated verifier index
+        let (_cs, cached_verifier_index) = cached.compile_to_indexes()?;
+        let (_prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+        assert_eq!(
+            rmp_serde::to_vec(&cached_verifier_index).unwrap(),
+            rmp_serde::to_vec(&verifier_index).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_key_invalidation() {
+        let mut sources = Sources::new();
+        sources.add("cache.no".to_string(), CODE.to_string());
+        let options = CircuitOptions::default();
+        let key = cache_key(&sources, options, true);
+
+        // the packing of generic gates
+        assert_ne!(cache_key(&sources, options, false), key);
+
+        // the optimization level
+        let other_level = CircuitOptions {
+            opt_level: OptLevel::O1,
+            ..options
+        };
+        assert_ne!(cache_key(&sources, other_level, true), key);
+
+        // the source code
+        let mut other_sources = Sources::new();
+        other_sources.add("cache.no".to_string(), CODE.replace('6', "7"));
+        assert_ne!(cache_key(&other_sources, options, true), key);
+    }
+}
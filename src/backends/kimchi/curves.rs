@@ -0,0 +1,307 @@
+//! The curves of the pasta cycle that kimchi circuits can be written for.
+//!
+//! A circuit is written on the scalar field of a curve, and its proofs commit to polynomials with that curve.
+//! [VestaConfig] is the default (circuits over [VestaField]),
+//! and [PallasConfig] is its sibling (circuits over [PallasField]):
+//! as the base field of each curve is the scalar field of the other,
+//! a circuit on one side can verify the proofs created on the other side.
+
+use std::sync::Arc;
+
+use kimchi::{
+    circuits::constraints::{ConstraintSystem, GateError},
+    groupmap::GroupMap,
+    mina_curves::pasta::{Pallas, PallasParameters, Vesta, VestaParameters},
+    mina_poseidon::{
+        constants::PlonkSpongeConstantsKimchi,
+        pasta::{fp_kimchi, fq_kimchi},
+        poseidon::ArithmeticSpongeParams,
+        sponge::{DefaultFqSponge, DefaultFrSponge},
+    },
+    poly_commitment::{commitment::CommitmentCurve, evaluation_proof::OpeningProof, srs::SRS},
+    proof::ProverProof,
+};
+use miette::{Context, IntoDiagnostic};
+use once_cell::sync::Lazy;
+
+use crate::backends::BackendField;
+
+use super::{PallasField, VestaField, NUM_REGISTERS};
+
+/// What the kimchi backend needs to know about a curve:
+/// its scalar field (the field of the circuit), the parameters of poseidon on that field,
+/// and how to create and verify proofs.
+pub trait KimchiCurveConfig: Clone + 'static {
+    /// The field of the circuit.
+    type Field: BackendField;
+
+    /// The kimchi prover index.
+    type ProverIndex;
+
+    /// The kimchi verifier index.
+    type VerifierIndex;
+
+    /// A kimchi proof.
+    type Proof;
+
+    /// The SRS used to commit, which only depends on its size (see [super::srs::SharedSrs]).
+    type Srs: Clone;
+
+    /// The parameters of the poseidon hash used by the poseidon gates.
+    fn poseidon_params() -> ArithmeticSpongeParams<Self::Field>;
+
+    /// Creates an SRS of the given size.
+    /// The SRS only depends on its size, so it is deterministic.
+    fn create_srs(size: usize) -> Self::Srs;
+
+    /// Returns the size of an SRS.
+    fn srs_size(srs: &Self::Srs) -> usize;
+
+    /// Returns the size of the SRS of a prover index.
+    fn prover_srs_size(index: &Self::ProverIndex) -> usize;
+
+    /// Returns the size of the domain of the circuit of a prover index.
+    fn prover_domain_size(index: &Self::ProverIndex) -> usize;
+
+    /// Adds the Lagrange basis of the domain of a constraint system to an SRS.
+    fn add_lagrange_basis(srs: &mut Self::Srs, cs: &ConstraintSystem<Self::Field>);
+
+    /// Creates the prover index of a constraint system,
+    /// with an SRS that contains the Lagrange basis of its domain.
+    fn create_prover_index(
+        cs: ConstraintSystem<Self::Field>,
+        srs: Arc<Self::Srs>,
+    ) -> Self::ProverIndex;
+
+    /// Returns the verifier index associated to a prover index.
+    fn verifier_index(index: &Self::ProverIndex) -> Self::VerifierIndex;
+
+    /// Checks a witness against the constraints of a prover index, for debugging.
+    fn verify_witness(
+        index: &Self::ProverIndex,
+        witness: &[Vec<Self::Field>; NUM_REGISTERS],
+        full_public_inputs: &[Self::Field],
+    ) -> Result<(), GateError>;
+
+    /// Creates a proof.
+    fn prove(
+        index: &Self::ProverIndex,
+        witness: [Vec<Self::Field>; NUM_REGISTERS],
+    ) -> miette::Result<Self::Proof>;
+
+    /// Encodes a proof in MessagePack.
+    fn encode_proof(proof: &Self::Proof) -> Vec<u8>;
+
+    /// Verifies a proof.
+    fn verify(
+        index: &Self::VerifierIndex,
+        proof: &Self::Proof,
+        full_public_inputs: &[Self::Field],
+    ) -> miette::Result<()>;
+}
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+
+//
+// Vesta
+//
+
+/// Circuits over [VestaField], proven with commitments on Vesta.
+#[derive(Clone, Debug, Default)]
+pub struct VestaConfig;
+
+pub(crate) type VestaBaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+pub(crate) type VestaScalarSponge = DefaultFrSponge<VestaField, SpongeParams>;
+
+pub(crate) static VESTA_GROUP_MAP: Lazy<<Vesta as CommitmentCurve>::Map> =
+    Lazy::new(<Vesta as CommitmentCurve>::Map::setup);
+
+impl KimchiCurveConfig for VestaConfig {
+    type Field = VestaField;
+    type ProverIndex = kimchi::prover_index::ProverIndex<Vesta, OpeningProof<Vesta>>;
+    type VerifierIndex = kimchi::verifier_index::VerifierIndex<Vesta, OpeningProof<Vesta>>;
+    type Proof = ProverProof<Vesta, OpeningProof<Vesta>>;
+    type Srs = SRS<Vesta>;
+
+    fn poseidon_params() -> ArithmeticSpongeParams<VestaField> {
+        fp_kimchi::params()
+    }
+
+    fn create_srs(size: usize) -> SRS<Vesta> {
+        // an SRS on vesta, as the circuit is in Fp
+        SRS::<Vesta>::create(size)
+    }
+
+    fn srs_size(srs: &SRS<Vesta>) -> usize {
+        srs.g.len()
+    }
+
+    fn prover_srs_size(index: &Self::ProverIndex) -> usize {
+        index.srs.g.len()
+    }
+
+    fn prover_domain_size(index: &Self::ProverIndex) -> usize {
+        index.cs.domain.d1.size as usize
+    }
+
+    fn add_lagrange_basis(srs: &mut SRS<Vesta>, cs: &ConstraintSystem<VestaField>) {
+        srs.add_lagrange_basis(cs.domain.d1);
+    }
+
+    fn create_prover_index(
+        cs: ConstraintSystem<VestaField>,
+        srs: Arc<SRS<Vesta>>,
+    ) -> Self::ProverIndex {
+        // create indexes
+        let (endo_q, _endo_r) = kimchi::poly_commitment::srs::endos::<Pallas>();
+
+        kimchi::prover_index::ProverIndex::create(cs, endo_q, srs)
+    }
+
+    fn verifier_index(index: &Self::ProverIndex) -> Self::VerifierIndex {
+        index.verifier_index()
+    }
+
+    fn verify_witness(
+        index: &Self::ProverIndex,
+        witness: &[Vec<VestaField>; NUM_REGISTERS],
+        full_public_inputs: &[VestaField],
+    ) -> Result<(), GateError> {
+        index.verify(witness, full_public_inputs)
+    }
+
+    fn prove(
+        index: &Self::ProverIndex,
+        witness: [Vec<VestaField>; NUM_REGISTERS],
+    ) -> miette::Result<Self::Proof> {
+        ProverProof::create::<VestaBaseSponge, VestaScalarSponge>(
+            &VESTA_GROUP_MAP,
+            witness,
+            &[],
+            index,
+        )
+        .into_diagnostic()
+        .wrap_err("kimchi: could not create a proof with the given inputs")
+    }
+
+    fn encode_proof(proof: &Self::Proof) -> Vec<u8> {
+        rmp_serde::to_vec(proof).expect("a kimchi proof can always be serialized")
+    }
+
+    fn verify(
+        index: &Self::VerifierIndex,
+        proof: &Self::Proof,
+        full_public_inputs: &[VestaField],
+    ) -> miette::Result<()> {
+        kimchi::verifier::verify::<Vesta, VestaBaseSponge, VestaScalarSponge, OpeningProof<Vesta>>(
+            &VESTA_GROUP_MAP,
+            index,
+            proof,
+            full_public_inputs,
+        )
+        .into_diagnostic()
+        .wrap_err("kimchi: failed to verify the proof")
+    }
+}
+
+//
+// Pallas
+//
+
+/// Circuits over [PallasField], proven with commitments on Pallas.
+#[derive(Clone, Debug, Default)]
+pub struct PallasConfig;
+
+type PallasBaseSponge = DefaultFqSponge<PallasParameters, SpongeParams>;
+type PallasScalarSponge = DefaultFrSponge<PallasField, SpongeParams>;
+
+static PALLAS_GROUP_MAP: Lazy<<Pallas as CommitmentCurve>::Map> =
+    Lazy::new(<Pallas as CommitmentCurve>::Map::setup);
+
+impl KimchiCurveConfig for PallasConfig {
+    type Field = PallasField;
+    type ProverIndex = kimchi::prover_index::ProverIndex<Pallas, OpeningProof<Pallas>>;
+    type VerifierIndex = kimchi::verifier_index::VerifierIndex<Pallas, OpeningProof<Pallas>>;
+    type Proof = ProverProof<Pallas, OpeningProof<Pallas>>;
+    type Srs = SRS<Pallas>;
+
+    fn poseidon_params() -> ArithmeticSpongeParams<PallasField> {
+        fq_kimchi::params()
+    }
+
+    fn create_srs(size: usize) -> SRS<Pallas> {
+        // an SRS on pallas, as the circuit is in Fq
+        SRS::<Pallas>::create(size)
+    }
+
+    fn srs_size(srs: &SRS<Pallas>) -> usize {
+        srs.g.len()
+    }
+
+    fn prover_srs_size(index: &Self::ProverIndex) -> usize {
+        index.srs.g.len()
+    }
+
+    fn prover_domain_size(index: &Self::ProverIndex) -> usize {
+        index.cs.domain.d1.size as usize
+    }
+
+    fn add_lagrange_basis(srs: &mut SRS<Pallas>, cs: &ConstraintSystem<PallasField>) {
+        srs.add_lagrange_basis(cs.domain.d1);
+    }
+
+    fn create_prover_index(
+        cs: ConstraintSystem<PallasField>,
+        srs: Arc<SRS<Pallas>>,
+    ) -> Self::ProverIndex {
+        // create indexes
+        let (endo_q, _endo_r) = kimchi::poly_commitment::srs::endos::<Vesta>();
+
+        kimchi::prover_index::ProverIndex::create(cs, endo_q, srs)
+    }
+
+    fn verifier_index(index: &Self::ProverIndex) -> Self::VerifierIndex {
+        index.verifier_index()
+    }
+
+    fn verify_witness(
+        index: &Self::ProverIndex,
+        witness: &[Vec<PallasField>; NUM_REGISTERS],
+        full_public_inputs: &[PallasField],
+    ) -> Result<(), GateError> {
+        index.verify(witness, full_public_inputs)
+    }
+
+    fn prove(
+        index: &Self::ProverIndex,
+        witness: [Vec<PallasField>; NUM_REGISTERS],
+    ) -> miette::Result<Self::Proof> {
+        ProverProof::create::<PallasBaseSponge, PallasScalarSponge>(
+            &PALLAS_GROUP_MAP,
+            witness,
+            &[],
+            index,
+        )
+        .into_diagnostic()
+        .wrap_err("kimchi: could not create a proof with the given inputs")
+    }
+
+    fn encode_proof(proof: &Self::Proof) -> Vec<u8> {
+        rmp_serde::to_vec(proof).expect("a kimchi proof can always be serialized")
+    }
+
+    fn verify(
+        index: &Self::VerifierIndex,
+        proof: &Self::Proof,
+        full_public_inputs: &[PallasField],
+    ) -> miette::Result<()> {
+        kimchi::verifier::verify::<
+            Pallas,
+            PallasBaseSponge,
+            PallasScalarSponge,
+            OpeningProof<Pallas>,
+        >(&PALLAS_GROUP_MAP, index, proof, full_public_inputs)
+        .into_diagnostic()
+        .wrap_err("kimchi: failed to verify the proof")
+    }
+}
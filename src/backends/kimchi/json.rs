@@ -0,0 +1,239 @@
+//! A JSON encoding of compiled kimchi circuits,
+//! for external tooling and to diff the circuits produced by different versions of noname.
+//!
+//! Unlike the [cache](super::cache), the JSON is meant to be read by humans and other programs:
+//! coefficients are decimal strings, and the wiring is listed both per cell and per cycle.
+
+use kimchi::circuits::{gate::CircuitGate, wires::Wire};
+use miette::{Context, IntoDiagnostic};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::circuit_writer::{writer::Cell, GateKind, Wiring};
+
+use super::{KimchiVesta, VestaField};
+
+/// The version of the format of a [CircuitJson].
+/// This must be bumped every time [CircuitJson] or [GateJson] changes.
+pub const CIRCUIT_JSON_VERSION: u32 = 1;
+
+/// The JSON representation of a compiled circuit:
+///
+/// - `version`: the version of the format ([CIRCUIT_JSON_VERSION]).
+/// - `public_input_size`: the number of public inputs, including the public outputs (which come first).
+/// - `gates`: the gates, by row (see [GateJson]).
+/// - `wiring`: the wiring cycles, each listing its cells by row and then by column.
+///   The cycles are sorted by their first cell.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitJson {
+    pub version: u32,
+    pub public_input_size: usize,
+    pub gates: Vec<GateJson>,
+    pub wiring: Vec<Vec<Cell>>,
+}
+
+/// The JSON representation of a gate:
+///
+/// - `typ`: the kind of gate (e.g. `DoubleGeneric`).
+/// - `coeffs`: the coefficients of the gate, as decimal strings.
+/// - `wires`: for each column, the cell it is wired to by the permutation
+///   (the cell itself if it is not wired to anything).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GateJson {
+    pub typ: GateKind,
+    #[serde(with = "decimal_fields")]
+    pub coeffs: Vec<VestaField>,
+    pub wires: Vec<Cell>,
+}
+
+impl CircuitJson {
+    /// Returns the JSON encoding of the circuit.
+    pub fn to_json(&self) -> miette::Result<String> {
+        serde_json::to_string_pretty(self)
+            .into_diagnostic()
+            .wrap_err("could not serialize the circuit to JSON")
+    }
+
+    /// Parses a circuit encoded with [Self::to_json].
+    pub fn from_json(json: &str) -> miette::Result<Self> {
+        let circuit: Self = serde_json::from_str(json)
+            .into_diagnostic()
+            .wrap_err("could not parse the JSON circuit")?;
+
+        if circuit.version != CIRCUIT_JSON_VERSION {
+            miette::bail!(
+                "the circuit was exported with version {}, but version {CIRCUIT_JSON_VERSION} was expected",
+                circuit.version
+            );
+        }
+
+        Ok(circuit)
+    }
+
+    /// Converts the circuit back to kimchi gates, wired together.
+    pub fn to_kimchi_gates(&self) -> Vec<CircuitGate<VestaField>> {
+        self.gates
+            .iter()
+            .map(|gate| CircuitGate {
+                typ: gate.typ.into(),
+                wires: std::array::from_fn(|col| {
+                    let Cell { row, col } = gate.wires[col];
+                    Wire { row, col }
+                }),
+                coeffs: gate.coeffs.clone(),
+            })
+            .collect()
+    }
+}
+
+impl KimchiVesta {
+    /// Returns the JSON representation of the circuit (see [CircuitJson] for the schema).
+    /// This must be called once the circuit has been finalized.
+    pub fn to_circuit_json(&self) -> CircuitJson {
+        let gates = self
+            .to_kimchi_gates()
+            .into_iter()
+            .zip(&self.gates)
+            .map(|(kimchi_gate, gate)| GateJson {
+                typ: gate.typ,
+                coeffs: kimchi_gate.coeffs,
+                wires: kimchi_gate
+                    .wires
+                    .iter()
+                    .map(|wire| Cell {
+                        row: wire.row,
+                        col: wire.col,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let mut wiring: Vec<Vec<Cell>> = self
+            .wiring
+            .values()
+            .filter_map(|wiring| match wiring {
+                Wiring::Wired(annotated_cells) => {
+                    let mut cells: Vec<_> = annotated_cells.iter().map(|cell| cell.cell).collect();
+                    cells.sort();
+                    Some(cells)
+                }
+                Wiring::NotWired(_) => None,
+            })
+            .collect();
+        wiring.sort();
+
+        CircuitJson {
+            version: CIRCUIT_JSON_VERSION,
+            public_input_size: self.public_input_size,
+            gates,
+            wiring,
+        }
+    }
+}
+
+/// (De)serializes field elements as decimal strings.
+mod decimal_fields {
+    use std::str::FromStr as _;
+
+    use super::*;
+
+    pub fn serialize<S>(fields: &[VestaField], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(fields.iter().map(|field| BigUint::from(*field).to_string()))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<VestaField>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let strings: Vec<String> = Vec::deserialize(deserializer)?;
+        strings
+            .iter()
+            .map(|ss| {
+                VestaField::from_str(ss)
+                    .map_err(|_| serde::de::Error::custom(format!("invalid field element `{ss}`")))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backends::kimchi::NUM_REGISTERS,
+        compiler::{compile, typecheck_next_file, Sources},
+        type_checker::TypeChecker,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_circuit_json() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, yy: Field) {
+            let zz = xx + yy;
+            assert_eq(zz, 3);
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "inline_test_json.no".to_string(),
+            code.to_owned(),
+            0,
+        )?;
+        let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+        let backend = &compiled_circuit.circuit.backend;
+
+        let circuit = backend.to_circuit_json();
+        let json = circuit.to_json()?;
+
+        // the shape of the JSON
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], CIRCUIT_JSON_VERSION);
+        assert_eq!(value["public_input_size"], 1);
+
+        let gates = value["gates"].as_array().unwrap();
+        assert_eq!(gates.len(), backend.num_gates());
+        for gate in gates {
+            assert_eq!(gate["typ"], "DoubleGeneric");
+            assert_eq!(gate["wires"].as_array().unwrap().len(), NUM_REGISTERS);
+        }
+
+        // `xx + yy - zz = 0`, with `-1` encoded in the field (and the unused coefficients set to zero)
+        let minus_one = BigUint::from(-VestaField::from(1u32)).to_string();
+        assert_eq!(
+            gates[1]["coeffs"],
+            serde_json::json!(["1", "1", minus_one, "0", "0"])
+        );
+
+        // the public input is wired to the addition
+        assert_eq!(
+            value["wiring"][0],
+            serde_json::json!([{ "row": 0, "col": 0 }, { "row": 1, "col": 0 }])
+        );
+        assert_eq!(
+            gates[0]["wires"][0],
+            serde_json::json!({ "row": 1, "col": 0 })
+        );
+
+        // it round trips
+        let parsed = CircuitJson::from_json(&json)?;
+        assert_eq!(parsed, circuit);
+        assert_eq!(parsed.to_kimchi_gates(), backend.to_kimchi_gates());
+
+        // and other versions are rejected
+        let other_version = json.replacen(
+            &format!("\"version\": {CIRCUIT_JSON_VERSION}"),
+            "\"version\": 0",
+            1,
+        );
+        assert!(CircuitJson::from_json(&other_version).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+//! Lookup tables, declared at compile time and checked by kimchi's lookup argument.
+//!
+//! A table is a list of `(key, value)` pairs identified by a number.
+//! Each [crate::circuit_writer::GateKind::Lookup] gate reads the table id in its first register,
+//! followed by three `(key, value)` pairs that must all be in the table.
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::backends::BackendField;
+
+/// The number of `(key, value)` pairs checked by a single lookup gate.
+pub const LOOKUPS_PER_ROW: usize = 3;
+
+/// A table of `(key, value)` pairs.
+#[derive(Debug, Clone)]
+pub struct LookupTable<F>
+where
+    F: BackendField,
+{
+    /// The id of the table, which is what circuits use to refer to it.
+    pub(crate) id: u32,
+
+    /// The pairs, in the order they were declared.
+    entries: Vec<(F, F)>,
+
+    /// The pairs, indexed by key (for witness generation).
+    values: Arc<HashMap<F, F>>,
+}
+
+impl<F> LookupTable<F>
+where
+    F: BackendField,
+{
+    /// Creates a table out of `(key, value)` pairs.
+    ///
+    /// Panics if the id is 0 (kimchi reserves it for its own XOR table),
+    /// if the table does not contain the pair `(0, 0)` (which unused lookups fall back to),
+    /// or if the same key appears twice.
+    pub fn new(id: u32, entries: Vec<(F, F)>) -> Self {
+        assert!(id != 0, "the lookup table id 0 is reserved");
+        assert!(
+            i32::try_from(id).is_ok(),
+            "the lookup table id {id} is too large"
+        );
+
+        let mut values = HashMap::with_capacity(entries.len());
+        for (key, value) in &entries {
+            let prev = values.insert(*key, *value);
+            assert!(prev.is_none(), "the key {key} appears twice in the table");
+        }
+
+        assert_eq!(
+            values.get(&F::zero()),
+            Some(&F::zero()),
+            "a lookup table must contain the pair (0, 0)"
+        );
+
+        Self {
+            id,
+            entries,
+            values: Arc::new(values),
+        }
+    }
+
+    /// Creates a table containing the values `0..2^num_bits`, each mapped to itself.
+    /// Looking a value up in this table checks that it fits in `num_bits` bits.
+    pub fn range(id: u32, num_bits: usize) -> Self {
+        let entries = (0..1u64 << num_bits)
+            .map(|ii| (F::from(ii), F::from(ii)))
+            .collect();
+
+        Self::new(id, entries)
+    }
+
+    /// Returns the value associated to a key, if it's in the table.
+    pub fn get(&self, key: &F) -> Option<F> {
+        self.values.get(key).copied()
+    }
+
+    /// Returns the pairs indexed by key, which can be cheaply moved into a hint.
+    pub(crate) fn values(&self) -> Arc<HashMap<F, F>> {
+        self.values.clone()
+    }
+
+    /// Returns the table in the format expected by kimchi's constraint system.
+    pub(crate) fn to_kimchi_table(&self) -> kimchi::circuits::lookup::tables::LookupTable<F> {
+        let (keys, values) = self.entries.iter().copied().unzip();
+
+        kimchi::circuits::lookup::tables::LookupTable {
+            id: self.id as i32,
+            data: vec![keys, values],
+        }
+    }
+}
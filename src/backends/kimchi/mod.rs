@@ -1,60 +1,224 @@
 pub mod asm;
 pub mod builtin;
+pub mod cache;
+pub mod curves;
+pub mod json;
+pub mod lookup;
+pub mod parallel;
 pub mod prover;
+pub mod recording;
+pub mod srs;
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Write,
     ops::Neg as _,
+    sync::Arc,
 };
 
+use ena::unify::{InPlaceUnificationTable, UnifyKey};
 use itertools::{izip, Itertools};
-use kimchi::circuits::polynomials::generic::{GENERIC_COEFFS, GENERIC_REGISTERS};
+use kimchi::circuits::{
+    lookup::tables::RANGE_CHECK_TABLE_ID,
+    polynomials::{
+        generic::{GENERIC_COEFFS, GENERIC_REGISTERS},
+        poseidon::POS_ROWS_PER_HASH,
+    },
+};
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     backends::kimchi::asm::parse_coeffs,
     circuit_writer::{
-        writer::{AnnotatedCell, Cell, PendingGate},
-        DebugInfo, Gate, GateKind, Wiring,
+        writer::{AnnotatedCell, Cell},
+        Constraint, DebugInfo, Gate, GateKind, OptLevel, Wiring, RANGE_CHECK_LIMBS,
     },
     compiler::Sources,
     constants::Span,
     error::{Error, ErrorKind, Result},
     helpers::PrettyField,
+    type_checker::TypeChecker,
     var::{Value, Var},
     witness::WitnessEnv,
 };
 
 use ark_ff::{One, Zero};
 
-use self::asm::{extract_vars_from_coeffs, OrderedHashSet};
+use self::{
+    asm::{extract_vars_from_coeffs, OrderedHashSet},
+    curves::{KimchiCurveConfig, PallasConfig, VestaConfig},
+    lookup::{LookupTable, LOOKUPS_PER_ROW},
+    recording::{RecordedOp, Recording},
+};
 
 /// We use the scalar field of Vesta as our circuit field.
 pub type VestaField = kimchi::mina_curves::pasta::Fp;
 
+/// The scalar field of Pallas, the circuit field of [KimchiPallas].
+pub type PallasField = kimchi::mina_curves::pasta::Fq;
+
+/// The kimchi backend for circuits over [VestaField].
+pub type KimchiVesta = Kimchi<VestaConfig>;
+
+/// The kimchi backend for circuits over [PallasField].
+/// As the proofs of [KimchiVesta] commit to Vesta points, whose coordinates are in [PallasField],
+/// this is the side on which they can be verified.
+pub type KimchiPallas = Kimchi<PallasConfig>;
+
 /// Number of columns in the execution trace.
 pub const NUM_REGISTERS: usize = kimchi::circuits::wires::COLUMNS;
 
-use super::{Backend, BackendField, BackendVar};
+use super::{
+    check_private_inputs_used, estimate::estimate_costs, unconstrained_var, unused_inputs, Backend,
+    BackendField, BackendVar,
+};
 
 impl BackendField for VestaField {}
+impl BackendField for PallasField {}
 
 #[derive(Debug)]
-pub struct Witness(Vec<[VestaField; NUM_REGISTERS]>);
+pub struct Witness<F>(Vec<[F; NUM_REGISTERS]>)
+where
+    F: BackendField;
 
 // TODO: refine this struct as full_public_inputs and public_outputs overlap with all_witness
-pub struct GeneratedWitness {
+pub struct GeneratedWitness<F>
+where
+    F: BackendField,
+{
     /// contains all the witness values
-    pub all_witness: Witness,
+    pub all_witness: Witness<F>,
     /// contains the public inputs, which are also part of the all_witness
-    pub full_public_inputs: Vec<VestaField>,
+    pub full_public_inputs: Vec<F>,
     /// contains the public outputs, which are also part of the all_witness
-    pub public_outputs: Vec<VestaField>,
+    pub public_outputs: Vec<F>,
+}
+
+/// A witness generated directly in the format of kimchi (one column per register),
+/// which avoids holding both the rows and the columns of the witness in memory
+/// (see [CompiledCircuit::generate_witness_streaming](crate::witness::CompiledCircuit::generate_witness_streaming)).
+pub struct StreamedWitness<F>
+where
+    F: BackendField,
+{
+    /// the witness values, column by column
+    pub columns: [Vec<F>; NUM_REGISTERS],
+    /// contains the public inputs, which are also part of the columns
+    pub full_public_inputs: Vec<F>,
+    /// contains the public outputs, which are also part of the columns
+    pub public_outputs: Vec<F>,
+}
+
+impl<F> StreamedWitness<F>
+where
+    F: BackendField,
+{
+    pub fn debug(&self) {
+        for row in 0..self.columns.row_count() {
+            let values = self.columns.row(row).iter().map(|v| v.pretty()).join(" | ");
+            println!("{row} - {values}");
+        }
+    }
+}
+
+/// Where the rows of a witness are written as they are generated.
+trait WitnessRows<F> {
+    fn push_row(&mut self, values: [F; NUM_REGISTERS]);
+
+    fn set(&mut self, row: usize, col: usize, value: F);
+
+    fn row(&self, row: usize) -> [F; NUM_REGISTERS];
+
+    fn row_count(&self) -> usize;
+}
+
+/// The rows of the witness, one after the other.
+impl<F> WitnessRows<F> for Vec<[F; NUM_REGISTERS]>
+where
+    F: BackendField,
+{
+    fn push_row(&mut self, values: [F; NUM_REGISTERS]) {
+        self.push(values);
+    }
+
+    fn set(&mut self, row: usize, col: usize, value: F) {
+        self[row][col] = value;
+    }
+
+    fn row(&self, row: usize) -> [F; NUM_REGISTERS] {
+        self[row]
+    }
+
+    fn row_count(&self) -> usize {
+        self.len()
+    }
+}
+
+/// The columns of the witness, as kimchi expects them.
+impl<F> WitnessRows<F> for [Vec<F>; NUM_REGISTERS]
+where
+    F: BackendField,
+{
+    fn push_row(&mut self, values: [F; NUM_REGISTERS]) {
+        for (column, value) in self.iter_mut().zip(values) {
+            column.push(value);
+        }
+    }
+
+    fn set(&mut self, row: usize, col: usize, value: F) {
+        self[col][row] = value;
+    }
+
+    fn row(&self, row: usize) -> [F; NUM_REGISTERS] {
+        std::array::from_fn(|col| self[col][row])
+    }
+
+    fn row_count(&self) -> usize {
+        self[0].len()
+    }
+}
+
+/// Statistics on the gates and wiring of a kimchi circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GateStats {
+    /// The total number of rows in the circuit (including the public input rows).
+    pub num_rows: usize,
+
+    /// The number of gates of each kind.
+    pub gates: HashMap<GateKind, usize>,
+
+    /// The number of distinct wiring cycles
+    /// (one per class of variables asserted equal, if they are used in more than one cell).
+    pub num_wiring_cycles: usize,
+}
+
+/// A range of the public input of a kimchi circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicSlot {
+    /// The index of the first field element (which is also its row in the circuit).
+    pub offset: usize,
+
+    /// The number of field elements.
+    pub len: usize,
+}
+
+/// Where the public inputs and the public output of `main` are in the public input of a kimchi circuit
+/// (what the verifier gets as `full_public_inputs`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicLayout {
+    /// The public output, if `main` returns something. It always comes first.
+    pub output: Option<PublicSlot>,
+
+    /// The public inputs, by name, in the order of the arguments of `main`.
+    pub inputs: Vec<(String, PublicSlot)>,
 }
 
 #[derive(Clone)]
-pub struct KimchiVesta {
+pub struct Kimchi<C>
+where
+    C: KimchiCurveConfig,
+{
     /// This is used to give a distinct number to each variable during circuit generation.
     pub(crate) next_variable: usize,
 
@@ -62,6 +226,10 @@ pub struct KimchiVesta {
     /// It is created during circuit generation.
     pub(crate) vars_to_value: HashMap<usize, Value<Self>>,
 
+    /// The span of the code that created each variable (indexed by variable),
+    /// to point at it if it doesn't end up in the circuit.
+    pub(crate) var_spans: Vec<Span>,
+
     /// The execution trace table with vars as placeholders.
     /// It is created during circuit generation,
     /// and used by the witness generator.
@@ -69,26 +237,50 @@ pub struct KimchiVesta {
 
     /// We cache the association between a constant and its _constrained_ variable,
     /// this is to avoid creating a new constraint every time we need to hardcode the same constant.
-    pub(crate) cached_constants: HashMap<VestaField, KimchiCellVar>,
+    pub(crate) cached_constants: HashMap<C::Field, KimchiCellVar>,
+
+    /// The generic gates created by the circuit generation, by row,
+    /// which the optimization passes can deduplicate and pair (see [Backend::optimize]).
+    pub(crate) generic_gates: BTreeMap<usize, GenericGate>,
+
+    /// The variables that were replaced by an identical variable during common subexpression elimination,
+    /// and that don't appear in the circuit anymore.
+    pub(crate) eliminated_vars: HashSet<usize>,
 
     /// The gates created by the circuit generation.
-    gates: Vec<Gate>,
+    pub(crate) gates: Vec<Gate<C::Field>>,
 
-    /// The wiring of the circuit.
+    /// The wiring of the circuit, indexed by the representative of each class of equal variables.
     /// It is created during circuit generation.
     pub(crate) wiring: HashMap<usize, Wiring>,
 
-    /// If set to false, a single generic gate will be used per double generic gate.
+    /// The classes of variables that were asserted equal, which share a single wiring cycle
+    /// (see [Self::equality_class]).
+    pub(crate) equalities: InPlaceUnificationTable<EqualityKey>,
+
+    /// If set to false, a single generic gate will be used per double generic gate,
+    /// and identical generic gates are not deduplicated (see [Self::eliminate_common_subexpressions]),
+    /// so that each operation keeps its own gate.
     /// This can be useful for debugging.
     pub(crate) double_generic_gate_optimization: bool,
 
-    /// This is used to implement the double generic gate,
-    /// which encodes two generic gates.
-    pub(crate) pending_generic_gate: Option<PendingGate>,
+    /// Whether the generic gates being created can be paired (see [Backend::set_double_generic]).
+    /// Unlike [Self::double_generic_gate_optimization], this only applies to a region of the circuit.
+    pub(crate) double_generic: bool,
 
     /// A vector of debug information that maps to each row of the created circuit.
     pub(crate) debug_info: Vec<DebugInfo>,
 
+    /// The debug information of the generic gate created first in each row packing two of them
+    /// (see [Self::pair_generic_gates]), as [Self::debug_info] is the one of the gate created last.
+    pub(crate) paired_debug_info: HashMap<usize, DebugInfo>,
+
+    /// The message attached to the gates being created (see [Backend::set_assert_message]).
+    pub(crate) assert_message: Option<String>,
+
+    /// The label of the gates being created (see [Backend::set_label]).
+    pub(crate) label: Option<String>,
+
     /// Once this is set, you can generate a witness (and can't modify the circuit?)
     // Note: I don't think we need this, but it acts as a nice redundant failsafe.
     pub(crate) finalized: bool,
@@ -96,14 +288,37 @@ pub struct KimchiVesta {
     /// Size of the public input.
     pub(crate) public_input_size: usize,
 
-    /// Indexes used by the private inputs
+    /// Indexes used by the public inputs, along with their names
+    /// (this is useful to warn about the ones that are not used)
+    pub(crate) public_input_indices: Vec<(usize, String, Span)>,
+
+    /// Indexes used by the private inputs, along with their names
     /// (this is useful to check that they appear in the circuit)
-    pub(crate) private_input_indices: Vec<(usize, Span)>,
+    pub(crate) private_input_indices: Vec<(usize, String, Span)>,
+
+    /// The spans of the generic gates that any values satisfy,
+    /// found by [Backend::optimize] (see [Backend::trivially_satisfied_constraints]).
+    pub(crate) trivial_gates: Vec<Span>,
+
+    /// The lookup tables that circuits can use, indexed by their id.
+    pub(crate) lookup_tables: BTreeMap<u32, LookupTable<C::Field>>,
+
+    /// If set to true, the values of the variables are computed in parallel
+    /// during witness generation (see [parallel]).
+    /// This is off by default, until the `witness` benchmark shows that it is faster.
+    pub(crate) parallel_witness: bool,
+
+    /// The recordings in progress, from the outermost function call to the innermost one
+    /// (see [Backend::start_recording]).
+    pub(crate) recordings: Vec<Recording<C>>,
 }
 
-impl Witness {
+impl<F> Witness<F>
+where
+    F: BackendField,
+{
     /// kimchi uses a transposed witness
-    pub fn to_kimchi_witness(&self) -> [Vec<VestaField>; NUM_REGISTERS] {
+    pub fn to_kimchi_witness(&self) -> [Vec<F>; NUM_REGISTERS] {
         let transposed = vec![Vec::with_capacity(self.0.len()); NUM_REGISTERS];
         let mut transposed: [_; NUM_REGISTERS] = transposed.try_into().unwrap();
         for row in &self.0 {
@@ -130,115 +345,955 @@ impl Witness {
     }
 }
 
-impl KimchiVesta {
+impl<C> Kimchi<C>
+where
+    C: KimchiCurveConfig,
+{
     pub fn new(double_generic_gate_optimization: bool) -> Self {
         Self {
             next_variable: 0,
             vars_to_value: HashMap::new(),
+            var_spans: vec![],
             witness_table: vec![],
             cached_constants: HashMap::new(),
+            generic_gates: BTreeMap::new(),
+            eliminated_vars: HashSet::new(),
             gates: vec![],
             wiring: HashMap::new(),
+            equalities: InPlaceUnificationTable::new(),
             double_generic_gate_optimization,
-            pending_generic_gate: None,
+            double_generic: true,
             debug_info: vec![],
+            paired_debug_info: HashMap::new(),
+            assert_message: None,
+            label: None,
             finalized: false,
             public_input_size: 0,
+            public_input_indices: vec![],
             private_input_indices: vec![],
+            trivial_gates: vec![],
+            lookup_tables: BTreeMap::new(),
+            parallel_witness: false,
+            recordings: vec![],
+        }
+    }
+
+    /// Sets whether the values of the variables are computed in parallel during witness generation.
+    /// The witness is the same either way.
+    pub fn set_parallel_witness(&mut self, parallel: bool) {
+        self.parallel_witness = parallel;
+    }
+
+    /// Generates a witness directly in the format of kimchi (see [StreamedWitness]).
+    pub(crate) fn generate_witness_columns(
+        &self,
+        witness_env: &mut WitnessEnv<C::Field>,
+    ) -> Result<StreamedWitness<C::Field>> {
+        let num_rows = self.witness_table.len();
+        let columns = vec![Vec::with_capacity(num_rows); NUM_REGISTERS];
+        let mut columns: [_; NUM_REGISTERS] = columns.try_into().unwrap();
+
+        let (full_public_inputs, public_outputs) = self.write_witness(witness_env, &mut columns)?;
+
+        Ok(StreamedWitness {
+            columns,
+            full_public_inputs,
+            public_outputs,
+        })
+    }
+
+    /// Writes the rows of the witness as they are computed,
+    /// and returns the full public input (containing the public output) and the public output.
+    fn write_witness(
+        &self,
+        witness_env: &mut WitnessEnv<C::Field>,
+        witness: &mut impl WitnessRows<C::Field>,
+    ) -> Result<(Vec<C::Field>, Vec<C::Field>)> {
+        if !self.finalized {
+            unreachable!("the circuit must be finalized before generating a witness");
+        }
+
+        // compute the values of the variables in parallel,
+        // the loop below then finds them in the cache
+        if self.parallel_witness {
+            self.compute_vars_in_parallel(witness_env)?;
+        }
+
+        // compute each rows' vars, except for the deferred ones (public output)
+        let mut public_outputs_vars: HashMap<KimchiCellVar, Vec<(usize, usize)>> = HashMap::new();
+
+        // calculate witness except for public outputs
+        for (row, row_of_vars) in self.witness_table.iter().enumerate() {
+            // create the witness row
+            let mut witness_row = [C::Field::zero(); NUM_REGISTERS];
+
+            for (col, var) in row_of_vars.iter().enumerate() {
+                let val = if let Some(var) = var {
+                    // if it's a public output, defer it's computation
+                    if matches!(
+                        self.vars_to_value.get(&var.index),
+                        Some(Value::PublicOutput(_))
+                    ) {
+                        public_outputs_vars
+                            .entry(*var)
+                            .or_default()
+                            .push((row, col));
+                        C::Field::zero()
+                    } else {
+                        self.compute_var(witness_env, var)?
+                    }
+                } else {
+                    C::Field::zero()
+                };
+                witness_row[col] = val;
+            }
+
+            witness.push_row(witness_row);
+        }
+
+        // compute public output at last
+        // (in the order they were created, which is the field order of the returned type)
+        let mut public_outputs_vars: Vec<_> = public_outputs_vars.into_iter().collect();
+        public_outputs_vars.sort_by_key(|(var, _)| var.index);
+
+        let mut public_outputs = vec![];
+
+        for (var, rows_cols) in public_outputs_vars {
+            let val = self.compute_var(witness_env, &var)?;
+            for (row, col) in rows_cols {
+                witness.set(row, col, val);
+            }
+            public_outputs.push(val);
+        }
+
+        // sanity check the witness
+        for (row, (gate, debug_info)) in self.gates.iter().zip(&self.debug_info).enumerate() {
+            let is_not_public_input = row >= self.public_input_size;
+            let cells = witness.row(row);
+            if is_not_public_input && gate.is_satisfied(&cells) == Some(false) {
+                // a row packing two generic gates fails with the gate that isn't satisfied
+                let debug_info = match self.paired_debug_info.get(&row) {
+                    Some(paired_debug_info) if gate.is_first_half_satisfied(&cells) => {
+                        paired_debug_info
+                    }
+                    _ => debug_info,
+                };
+
+                let kind = match &debug_info.message {
+                    Some(message) => ErrorKind::UnsatisfiedAssertion {
+                        row,
+                        message: message.clone(),
+                    },
+                    None => ErrorKind::UnsatisfiedConstraint {
+                        row,
+                        gate: gate.typ,
+                    },
+                };
+                return Err(Error::new("runtime", kind, debug_info.span));
+            }
+        }
+
+        // extract full public input (containing the public output)
+        let full_public_inputs = (0..self.public_input_size)
+            .map(|row| witness.row(row)[0])
+            .collect();
+
+        // sanity checks
+        assert_eq!(witness.row_count(), self.gates.len());
+        assert_eq!(witness.row_count(), self.witness_table.len());
+
+        Ok((full_public_inputs, public_outputs))
+    }
+
+    /// Adds a lookup table that circuits can then use with the `lookup` builtin.
+    /// Panics if a table with the same id was already added.
+    pub fn add_lookup_table(&mut self, table: LookupTable<C::Field>) {
+        let id = table.id;
+        let prev = self.lookup_tables.insert(id, table);
+        assert!(prev.is_none(), "lookup table {id} was added twice");
+    }
+
+    /// Returns the number of gates (or rows) in the circuit.
+    pub fn num_gates(&self) -> usize {
+        self.gates.len()
+    }
+
+    /// Returns the number of gates of each kind, as well as the number of wiring cycles.
+    pub fn gate_stats(&self) -> GateStats {
+        let mut gates = HashMap::new();
+        for gate in &self.gates {
+            *gates.entry(gate.typ).or_insert(0) += 1;
+        }
+
+        let num_wiring_cycles = self
+            .wiring
+            .values()
+            .filter(|w| matches!(w, Wiring::Wired(_)))
+            .count();
+
+        GateStats {
+            num_rows: self.gates.len(),
+            gates,
+            num_wiring_cycles,
         }
     }
 
+    /// Writes a witness of the circuit (given column by column, as kimchi expects it) as CSV,
+    /// to inspect it in a spreadsheet: a header `row,gate,col0,col1,...`, then one line per row
+    /// with its gate kind and the value of each column in decimal.
+    pub fn witness_to_csv(&self, witness: &[Vec<C::Field>; NUM_REGISTERS]) -> String {
+        assert_eq!(witness.row_count(), self.gates.len());
+
+        let columns = (0..NUM_REGISTERS).map(|col| format!("col{col}")).join(",");
+        let mut res = format!("row,gate,{columns}\n");
+
+        for (row, gate) in self.gates.iter().enumerate() {
+            let values = witness
+                .row(row)
+                .iter()
+                .map(|value| {
+                    let value: BigUint = (*value).into();
+                    value.to_string()
+                })
+                .join(",");
+            writeln!(res, "{row},{:?},{values}", gate.typ).unwrap();
+        }
+
+        res
+    }
+
+    /// Renders the cells of the circuit and their wiring as a [Graphviz](https://graphviz.org) DOT graph.
+    /// Each row is a cluster colored by its gate kind, and each wiring cycle is drawn as a cycle of edges.
+    pub fn to_dot(&self) -> String {
+        // sort the wiring by var, so that the output is deterministic
+        let mut wiring: Vec<_> = self.wiring.iter().collect();
+        wiring.sort_by_key(|(var, _)| **var);
+
+        // collect the cells used in each row, as well as the edges of each cycle
+        let mut rows: Vec<Vec<usize>> = vec![vec![]; self.gates.len()];
+        let mut edges = vec![];
+        for (_, wiring) in wiring {
+            match wiring {
+                Wiring::NotWired(annotated_cell) => {
+                    let Cell { row, col } = annotated_cell.cell;
+                    rows[row].push(col);
+                }
+                Wiring::Wired(annotated_cells) => {
+                    for (idx, annotated_cell) in annotated_cells.iter().enumerate() {
+                        let Cell { row, col } = annotated_cell.cell;
+                        rows[row].push(col);
+
+                        let next = &annotated_cells[(idx + 1) % annotated_cells.len()];
+                        edges.push((annotated_cell.cell, next.cell));
+                    }
+                }
+            }
+        }
+
+        let mut res = "digraph circuit {\n".to_string();
+        writeln!(res, "    node [shape=box, style=filled];").unwrap();
+
+        for (row, (gate, cols)) in self.gates.iter().zip(&mut rows).enumerate() {
+            cols.sort_unstable();
+            cols.dedup();
+
+            let color = match gate.typ {
+                GateKind::Zero => "lightgrey",
+                GateKind::DoubleGeneric => "lightblue",
+                GateKind::Poseidon => "lightpink",
+                GateKind::Lookup => "lightyellow",
+                GateKind::RangeCheck => "lightgreen",
+            };
+
+            writeln!(res, "    subgraph cluster_{row} {{").unwrap();
+            writeln!(res, "        label=\"row {row}: {:?}\";", gate.typ).unwrap();
+
+            for col in cols.iter() {
+                // the coefficient associated to the cell, for generic gates
+                let coeff = match gate.typ {
+                    GateKind::DoubleGeneric if *col < GENERIC_REGISTERS => gate.coeffs.get(*col),
+                    GateKind::DoubleGeneric if *col < 2 * GENERIC_REGISTERS => {
+                        gate.coeffs.get(GENERIC_COEFFS + col - GENERIC_REGISTERS)
+                    }
+                    _ => None,
+                };
+
+                let label = match coeff {
+                    Some(coeff) => format!("({row},{col})\\n{}", coeff.pretty()),
+                    None => format!("({row},{col})"),
+                };
+
+                writeln!(
+                    res,
+                    "        c{row}_{col} [label=\"{label}\", fillcolor={color}];"
+                )
+                .unwrap();
+            }
+
+            writeln!(res, "    }}").unwrap();
+        }
+
+        for (from, to) in edges {
+            writeln!(
+                res,
+                "    c{}_{} -> c{}_{};",
+                from.row, from.col, to.row, to.col
+            )
+            .unwrap();
+        }
+
+        res.push_str("}\n");
+        res
+    }
+
     /// Add a gate to the circuit
     fn add_gate(
         &mut self,
         note: &'static str,
         typ: GateKind,
         vars: Vec<Option<KimchiCellVar>>,
-        coeffs: Vec<VestaField>,
+        coeffs: Vec<C::Field>,
         span: Span,
+    ) {
+        let debug_info = DebugInfo {
+            span,
+            note: self.label.clone().unwrap_or_else(|| note.to_string()),
+            message: self.assert_message.clone(),
+        };
+
+        self.push_gate(typ, vars, coeffs, debug_info);
+    }
+
+    /// Add a gate to the circuit, along with its debug info
+    fn push_gate(
+        &mut self,
+        typ: GateKind,
+        vars: Vec<Option<KimchiCellVar>>,
+        coeffs: Vec<C::Field>,
+        debug_info: DebugInfo,
     ) {
         // sanitize
         assert!(coeffs.len() <= NUM_REGISTERS);
         assert!(vars.len() <= NUM_REGISTERS);
 
-        // construct the execution trace with vars, for the witness generation
-        self.witness_table.push(vars.clone());
+        // get current row
+        // important: do that before adding the gate below
+        let row = self.gates.len();
+
+        self.record(|| RecordedOp::Gate {
+            row,
+            typ,
+            vars: vars.clone(),
+            coeffs: coeffs.clone(),
+            debug_info: debug_info.clone(),
+            generic: None,
+        });
+
+        // construct the execution trace with vars, for the witness generation
+        self.witness_table.push(vars.clone());
+
+        // add gate
+        self.gates.push(Gate { typ, coeffs });
+
+        // add debug info related to that gate
+        self.debug_info.push(debug_info.clone());
+
+        // wiring (based on vars)
+        self.wire_row(row, &vars, &debug_info);
+    }
+
+    /// Wires the cells of a row to the other cells containing the same variables.
+    fn wire_row(&mut self, row: usize, vars: &[Option<KimchiCellVar>], debug_info: &DebugInfo) {
+        for (col, var) in vars.iter().enumerate() {
+            if let Some(var) = var {
+                let curr_cell = Cell { row, col };
+                let annotated_cell = AnnotatedCell {
+                    cell: curr_cell,
+                    debug: debug_info.clone(),
+                };
+
+                let class = self.equality_class(var.index);
+                self.wiring
+                    .entry(class)
+                    .and_modify(|w| match w {
+                        Wiring::NotWired(old_cell) => {
+                            *w = Wiring::Wired(vec![old_cell.clone(), annotated_cell.clone()])
+                        }
+                        Wiring::Wired(ref mut cells) => {
+                            cells.push(annotated_cell.clone());
+                        }
+                    })
+                    .or_insert(Wiring::NotWired(annotated_cell));
+            }
+        }
+    }
+
+    /// Merges the wiring cycle of a class of variables into the one of the class it was merged with.
+    /// Returns the representative of the class of variables asserted equal to a variable.
+    ///
+    /// All the cells of the variables of a class are wired in a single cycle,
+    /// which is enough for kimchi's permutation argument to enforce that they contain the same value.
+    /// This way, transitive equalities collapse into a single cycle,
+    /// and asserting that two variables of the same class are equal doesn't create any constraint.
+    fn equality_class(&mut self, var: usize) -> usize {
+        // the table only contains the variables up to the last one asserted equal to another
+        if var >= self.equalities.len() {
+            return var;
+        }
+        self.equalities.find(EqualityKey(var as u32)).0 as usize
+    }
+
+    /// Merges the classes of two variables.
+    /// Returns `None` if they were already in the same class,
+    /// otherwise returns the representative of the merged class followed by the representative it replaced.
+    fn merge_equality_classes(&mut self, lhs: usize, rhs: usize) -> Option<(usize, usize)> {
+        while self.equalities.len() <= lhs.max(rhs) {
+            self.equalities.new_key(());
+        }
+
+        let lhs = self.equality_class(lhs);
+        let rhs = self.equality_class(rhs);
+        if lhs == rhs {
+            return None;
+        }
+
+        self.equalities
+            .union(EqualityKey(lhs as u32), EqualityKey(rhs as u32));
+        let root = self.equality_class(lhs);
+        let merged = if root == lhs { rhs } else { lhs };
+
+        Some((root, merged))
+    }
+
+    fn merge_wiring(&mut self, root: usize, merged: usize) {
+        let cells = |wiring: Wiring| match wiring {
+            Wiring::NotWired(cell) => vec![cell],
+            Wiring::Wired(cells) => cells,
+        };
+
+        let mut merged_cells = match self.wiring.remove(&merged) {
+            Some(merged_wiring) => cells(merged_wiring),
+            None => return,
+        };
+        if let Some(root_wiring) = self.wiring.remove(&root) {
+            merged_cells.extend(cells(root_wiring));
+        }
+
+        // keep the cells in the order they would have been wired in
+        merged_cells.sort();
+        let wiring = if merged_cells.len() == 1 {
+            Wiring::NotWired(merged_cells.pop().unwrap())
+        } else {
+            Wiring::Wired(merged_cells)
+        };
+        self.wiring.insert(root, wiring);
+    }
+
+    /// Recomputes the wiring of the whole circuit, after rows have been removed or merged.
+    fn rewire(&mut self) {
+        self.wiring.clear();
+
+        let rows = std::mem::take(&mut self.witness_table);
+        let debug_infos = std::mem::take(&mut self.debug_info);
+        for (row, (vars, debug_info)) in rows.iter().zip(&debug_infos).enumerate() {
+            self.wire_row(row, vars, debug_info);
+        }
+
+        self.witness_table = rows;
+        self.debug_info = debug_infos;
+    }
+
+    /// Add a generic gate computing a new variable out of `inputs`,
+    /// with the new variable placed in the cell right after the inputs.
+    fn add_generic_gate_with_output(
+        &mut self,
+        label: &'static str,
+        mut inputs: Vec<Option<KimchiCellVar>>,
+        coeffs: Vec<C::Field>,
+        value: Value<Self>,
+        span: Span,
+    ) -> KimchiCellVar {
+        let output = self.new_internal_var(value, span);
+        let num_inputs = inputs.len();
+        inputs.push(Some(output));
+        self.add_generic_gate(label, inputs, coeffs, span);
+
+        let row = self.gates.len() - 1;
+        self.generic_gates.insert(
+            row,
+            GenericGate {
+                num_inputs,
+                output: Some(output),
+                pairable: self.double_generic,
+            },
+        );
+
+        output
+    }
+
+    /// Add a generic gate that doesn't create any variable (an assertion).
+    fn add_generic_gate_once(
+        &mut self,
+        label: &'static str,
+        vars: Vec<Option<KimchiCellVar>>,
+        coeffs: Vec<C::Field>,
+        span: Span,
+    ) {
+        let num_inputs = vars.len();
+        self.add_generic_gate(label, vars, coeffs, span);
+
+        let row = self.gates.len() - 1;
+        self.generic_gates.insert(
+            row,
+            GenericGate {
+                num_inputs,
+                output: None,
+                pairable: self.double_generic,
+            },
+        );
+    }
+
+    /// Returns the variable constrained to a constant, which is only created (and constrained) once.
+    fn constant(
+        &mut self,
+        label: Option<&'static str>,
+        value: C::Field,
+        span: Span,
+    ) -> KimchiCellVar {
+        if let Some(cvar) = self.cached_constants.get(&value) {
+            return *cvar;
+        }
+
+        let var = self.new_internal_var(Value::Constant(value), span);
+        self.cached_constants.insert(value, var);
+
+        let zero = C::Field::zero();
+
+        let _ = &self.add_generic_gate(
+            label.unwrap_or("hardcode a constant"),
+            vec![Some(var)],
+            vec![C::Field::one(), zero, zero, zero, value.neg()],
+            span,
+        );
+
+        var
+    }
+
+    /// Asserts that two variables are equal (see [Backend::assert_eq_var]).
+    fn assert_vars_eq(&mut self, lhs: &KimchiCellVar, rhs: &KimchiCellVar, span: Span) {
+        // the cells of equal variables are wired together,
+        // so there's nothing to do if the variables are already known to be equal
+        match self.merge_equality_classes(lhs.index, rhs.index) {
+            Some((root, merged)) => self.merge_wiring(root, merged),
+            None => return,
+        }
+
+        // the gate is still needed for the witness generation to point at a failing assertion,
+        // and for variables that aren't used in any other cell
+        self.add_generic_gate_once(
+            "constrain lhs - rhs = 0 to assert that they are equal",
+            vec![Some(*lhs), Some(*rhs)],
+            vec![C::Field::one(), C::Field::one().neg()],
+            span,
+        );
+    }
+
+    /// Add a single generic gate to the circuit
+    /// (the two halves of a double generic gate are paired later, see [Self::pair_generic_gates]).
+    fn add_generic_gate(
+        &mut self,
+        label: &'static str,
+        mut vars: Vec<Option<KimchiCellVar>>,
+        mut coeffs: Vec<C::Field>,
+        span: Span,
+    ) {
+        // padding
+        let coeffs_padding = GENERIC_COEFFS.checked_sub(coeffs.len()).unwrap();
+        coeffs.extend(std::iter::repeat(C::Field::zero()).take(coeffs_padding));
+
+        let vars_padding = GENERIC_REGISTERS.checked_sub(vars.len()).unwrap();
+        vars.extend(std::iter::repeat(None).take(vars_padding));
+
+        self.add_gate(label, GateKind::DoubleGeneric, vars, coeffs, span);
+    }
+
+    /// Removes the generic gates that are identical (same coefficients on the same inputs) to a previous one,
+    /// and replaces their output (if any) with the output of the previous gate in the rest of the circuit.
+    /// As the replaced outputs can make more gates identical, this is done in a single pass over the rows.
+    fn eliminate_common_subexpressions(&mut self) {
+        let gates = std::mem::take(&mut self.gates);
+        let rows = std::mem::take(&mut self.witness_table);
+        let debug_infos = std::mem::take(&mut self.debug_info);
+        let generic_gates = std::mem::take(&mut self.generic_gates);
+
+        let mut seen: HashMap<GateKey<C::Field>, Option<KimchiCellVar>> = HashMap::new();
+        let mut replaced: HashMap<usize, KimchiCellVar> = HashMap::new();
+
+        for (row, (gate, mut vars, debug_info)) in izip!(gates, rows, debug_infos).enumerate() {
+            for var in vars.iter_mut().flatten() {
+                if let Some(new_var) = replaced.get(&var.index) {
+                    *var = *new_var;
+                }
+            }
+
+            if let Some(generic) = generic_gates.get(&row) {
+                let key = GateKey::new(&vars[..generic.num_inputs], &gate.coeffs, generic.output);
+                match seen.get(&key) {
+                    Some(prev_output) => {
+                        if let (Some(output), Some(prev_output)) = (generic.output, prev_output) {
+                            replaced.insert(output.index, *prev_output);
+                            self.eliminated_vars.insert(output.index);
+                            self.merge_equality_classes(output.index, prev_output.index);
+                        }
+                        continue;
+                    }
+                    None => {
+                        seen.insert(key, generic.output);
+                    }
+                }
+
+                self.generic_gates.insert(self.gates.len(), generic.clone());
+            }
+
+            self.gates.push(gate);
+            self.witness_table.push(vars);
+            self.debug_info.push(debug_info);
+        }
+
+        self.rewire();
+    }
+
+    /// Merges each generic gate that defines a variable as a linear function of a single other variable
+    /// (like `x + 3` or `2 * x`) into the next gate, when the next gate is the only one reading that variable:
+    /// the definition is substituted in the next gate, which reads the other variable instead.
+    /// As the merged gate can itself be such a definition, chains of linear operations collapse into a single gate.
+    fn merge_linear_gates(&mut self) {
+        let gates = std::mem::take(&mut self.gates);
+        let rows = std::mem::take(&mut self.witness_table);
+        let debug_infos = std::mem::take(&mut self.debug_info);
+        let generic_gates = std::mem::take(&mut self.generic_gates);
+
+        // the number of cells wired to each variable (including the variables asserted equal to it)
+        let mut num_cells = HashMap::new();
+        for var in rows.iter().flatten().flatten() {
+            let class = self.equality_class(var.index);
+            let num = match self.wiring.get(&class) {
+                Some(Wiring::Wired(cells)) => cells.len(),
+                Some(Wiring::NotWired(_)) | None => 1,
+            };
+            num_cells.insert(var.index, num);
+        }
+
+        // the definition in the last row pushed, which the next gate can absorb
+        let mut mergeable: Option<LinearDefinition<C::Field>> = None;
+
+        for (row, (mut gate, mut vars, mut debug_info)) in
+            izip!(gates, rows, debug_infos).enumerate()
+        {
+            let generic = generic_gates.get(&row);
+
+            if let (Some(definition), Some(_)) = (mergeable.take(), generic) {
+                let reads = vars
+                    .iter()
+                    .filter(|var| var.map(|var| var.index) == Some(definition.output.index))
+                    .count();
+                if reads == 1 {
+                    definition.substitute(&mut gate.coeffs, &mut vars);
+
+                    // the merged gate takes the place of the definition
+                    self.gates.pop();
+                    self.witness_table.pop();
+                    let prev_debug_info = self.debug_info.pop().unwrap();
+                    self.generic_gates.remove(&self.gates.len());
+                    self.eliminated_vars.insert(definition.output.index);
+
+                    // don't lose the message of an assertion merged with another gate
+                    if debug_info.message.is_none() {
+                        debug_info.message = prev_debug_info.message;
+                    }
+                }
+            }
+
+            if let Some(generic) = generic {
+                mergeable = generic
+                    .output
+                    .filter(|output| num_cells.get(&output.index) == Some(&2))
+                    .and_then(|output| LinearDefinition::new(&gate.coeffs, &vars, output));
+                self.generic_gates.insert(self.gates.len(), generic.clone());
+            }
+
+            self.gates.push(gate);
+            self.witness_table.push(vars);
+            self.debug_info.push(debug_info);
+        }
+
+        self.rewire();
+    }
+
+    /// Packs the generic gates two by two in double generic gates.
+    /// A generic gate waits for the next one, and the pair takes the place of the second gate.
+    /// The gates that can't be paired (see [Backend::set_double_generic]) take a row of their own,
+    /// after the gate waiting for a pair (if any), so that no pair crosses the boundary of an unpaired region.
+    fn pair_generic_gates(&mut self) {
+        let gates = std::mem::take(&mut self.gates);
+        let rows = std::mem::take(&mut self.witness_table);
+        let debug_infos = std::mem::take(&mut self.debug_info);
+        let generic_gates = std::mem::take(&mut self.generic_gates);
+
+        let mut pending: Option<(Gate<C::Field>, Vec<Option<KimchiCellVar>>, DebugInfo)> = None;
+
+        for (row, (mut gate, mut vars, debug_info)) in izip!(gates, rows, debug_infos).enumerate() {
+            match generic_gates.get(&row) {
+                Some(generic) if !generic.pairable => {
+                    // flush the gate waiting for a pair
+                    if let Some((pending_gate, pending_vars, pending_debug_info)) = pending.take() {
+                        self.gates.push(pending_gate);
+                        self.witness_table.push(pending_vars);
+                        self.debug_info.push(pending_debug_info);
+                    }
+                }
+                Some(_) => match pending.take() {
+                    Some((pending_gate, pending_vars, pending_debug_info)) => {
+                        gate.coeffs.extend(pending_gate.coeffs);
+                        vars.extend(pending_vars);
+                        self.paired_debug_info
+                            .insert(self.gates.len(), pending_debug_info);
+                    }
+                    None => {
+                        pending = Some((gate, vars, debug_info));
+                        continue;
+                    }
+                },
+                None => (),
+            }
+
+            self.gates.push(gate);
+            self.witness_table.push(vars);
+            self.debug_info.push(debug_info);
+        }
+
+        // there might still be a pending generic gate
+        if let Some((gate, vars, debug_info)) = pending {
+            self.gates.push(gate);
+            self.witness_table.push(vars);
+            self.debug_info.push(debug_info);
+        }
+
+        self.rewire();
+    }
+}
+
+/// A variable in the table of the classes of equal variables (see [Kimchi::equality_class]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct EqualityKey(u32);
+
+impl UnifyKey for EqualityKey {
+    type Value = ();
+
+    fn index(&self) -> u32 {
+        self.0
+    }
+
+    fn from_index(index: u32) -> Self {
+        Self(index)
+    }
+
+    fn tag() -> &'static str {
+        "EqualityKey"
+    }
+
+    // the oldest variable represents the class,
+    // so that the representative doesn't depend on the order of the assertions
+    fn order_roots(lhs: Self, _: &(), rhs: Self, _: &()) -> Option<(Self, Self)> {
+        Some(if lhs.0 < rhs.0 {
+            (lhs, rhs)
+        } else {
+            (rhs, lhs)
+        })
+    }
+}
+
+/// A single generic gate, as created by the circuit generation.
+#[derive(Clone, Debug)]
+pub(crate) struct GenericGate {
+    /// The number of variables read by the gate (the first cells of the row).
+    num_inputs: usize,
 
-        // get current row
-        // important: do that before adding the gate below
-        let row = self.gates.len();
+    /// The variable computed by the gate, if it's not an assertion.
+    output: Option<KimchiCellVar>,
 
-        // add gate
-        self.gates.push(Gate { typ, coeffs });
+    /// Whether the gate can be paired with another one (see [Backend::set_double_generic]).
+    pairable: bool,
+}
 
-        // add debug info related to that gate
-        let debug_info = DebugInfo {
-            span,
-            note: note.to_string(),
-        };
-        self.debug_info.push(debug_info.clone());
+/// Identifies a generic gate by its coefficients and the variables it reads,
+/// which determine its output (if any).
+/// Like constants are cached by value (see [Kimchi::cached_constants]),
+/// the key is normalized (see [normalize_generic]) so that equivalent gates share the same key.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct GateKey<F>
+where
+    F: BackendField,
+{
+    typ: GateKind,
+    inputs: Vec<Option<usize>>,
+    coeffs: Vec<F>,
+    has_output: bool,
+}
 
-        // wiring (based on vars)
-        for (col, var) in vars.iter().enumerate() {
-            if let Some(var) = var {
-                let curr_cell = Cell { row, col };
-                let annotated_cell = AnnotatedCell {
-                    cell: curr_cell,
-                    debug: debug_info.clone(),
-                };
+impl<F> GateKey<F>
+where
+    F: BackendField,
+{
+    fn new(inputs: &[Option<KimchiCellVar>], coeffs: &[F], output: Option<KimchiCellVar>) -> Self {
+        let mut inputs: Vec<_> = inputs.iter().map(|var| var.map(|var| var.index)).collect();
+        let mut coeffs = coeffs.to_vec();
+        normalize_generic(&mut inputs, &mut coeffs);
 
-                self.wiring
-                    .entry(var.index)
-                    .and_modify(|w| match w {
-                        Wiring::NotWired(old_cell) => {
-                            *w = Wiring::Wired(vec![old_cell.clone(), annotated_cell.clone()])
-                        }
-                        Wiring::Wired(ref mut cells) => {
-                            cells.push(annotated_cell.clone());
-                        }
-                    })
-                    .or_insert(Wiring::NotWired(annotated_cell));
-            }
+        Self {
+            typ: GateKind::DoubleGeneric,
+            inputs,
+            coeffs,
+            has_output: output.is_some(),
         }
     }
+}
 
-    /// Add a generic double gate to the circuit
-    fn add_generic_gate(
-        &mut self,
-        label: &'static str,
-        mut vars: Vec<Option<KimchiCellVar>>,
-        mut coeffs: Vec<VestaField>,
-        span: Span,
-    ) {
-        // padding
-        let coeffs_padding = GENERIC_COEFFS.checked_sub(coeffs.len()).unwrap();
-        coeffs.extend(std::iter::repeat(VestaField::zero()).take(coeffs_padding));
+/// The index of the coefficient of the multiplication of the first two cells, in a generic gate.
+const MUL_COEFF: usize = 3;
+
+/// The index of the constant coefficient, in a generic gate.
+const CONST_COEFF: usize = 4;
+
+/// A generic gate defining its output as `scale * input + cst`
+/// (reading a single variable besides its output, and not multiplying it with the output),
+/// which can be substituted in a gate reading the output (see [Kimchi::merge_linear_gates]).
+struct LinearDefinition<F>
+where
+    F: BackendField,
+{
+    input: KimchiCellVar,
+    output: KimchiCellVar,
+    scale: F,
+    cst: F,
+}
 
-        let vars_padding = GENERIC_REGISTERS.checked_sub(vars.len()).unwrap();
-        vars.extend(std::iter::repeat(None).take(vars_padding));
+impl<F> LinearDefinition<F>
+where
+    F: BackendField,
+{
+    fn new(coeffs: &[F], vars: &[Option<KimchiCellVar>], output: KimchiCellVar) -> Option<Self> {
+        let cells: Vec<_> = vars
+            .iter()
+            .enumerate()
+            .filter_map(|(col, var)| var.map(|var| (col, var)))
+            .collect();
+
+        let (input_col, input, output_col) = match cells.as_slice() {
+            [(input_col, input), (output_col, var)] if var.index == output.index => {
+                (*input_col, *input, *output_col)
+            }
+            _ => return None,
+        };
 
-        // if the double gate optimization is not set, just add the gate
-        if !self.double_generic_gate_optimization {
-            self.add_gate(label, GateKind::DoubleGeneric, vars, coeffs, span);
-            return;
+        // `input * output` is not linear
+        let multiplied = input_col < 2 && output_col < 2 && !coeffs[MUL_COEFF].is_zero();
+        if input.index == output.index || multiplied || coeffs[output_col].is_zero() {
+            return None;
         }
 
-        // only add a double generic gate if we have two of them
-        if let Some(generic_gate) = self.pending_generic_gate.take() {
-            coeffs.extend(generic_gate.coeffs);
-            vars.extend(generic_gate.vars);
+        // `c_in * input + c_out * output + c = 0`
+        let inv = coeffs[output_col]
+            .inverse()
+            .expect("a non-zero coefficient is invertible");
+        Some(Self {
+            input,
+            output,
+            scale: -coeffs[input_col] * inv,
+            cst: -coeffs[CONST_COEFF] * inv,
+        })
+    }
+
+    /// Replaces the (single) cell containing the output by the input in a generic gate,
+    /// and updates its coefficients to enforce the same constraint.
+    fn substitute(&self, coeffs: &mut [F], vars: &mut [Option<KimchiCellVar>]) {
+        let col = vars
+            .iter()
+            .position(|var| var.map(|var| var.index) == Some(self.output.index))
+            .expect("kimchi bug: the merged gate doesn't read the definition");
+        vars[col] = Some(self.input);
+
+        // `c * output` becomes `c * scale * input + c * cst`
+        let coeff = coeffs[col];
+        coeffs[col] = coeff * self.scale;
+        coeffs[CONST_COEFF] += coeff * self.cst;
+
+        // `m * output * other` becomes `m * scale * input * other + m * cst * other`
+        if col < 2 {
+            let mul = coeffs[MUL_COEFF];
+            coeffs[MUL_COEFF] = mul * self.scale;
+            coeffs[1 - col] += mul * self.cst;
+        }
+    }
+}
 
-            // TODO: what to do with the label and span?
+/// Returns whether a generic gate is satisfied by any values of its variables:
+/// once the coefficients of the cells containing the same variable are summed,
+/// the polynomial `l * a + r * b + o * c + m * a * b + k` it enforces is zero (the empty cells are zero).
+fn is_trivial_generic<F>(vars: &[Option<KimchiCellVar>], coeffs: &[F]) -> bool
+where
+    F: BackendField,
+{
+    let mut linear: HashMap<usize, F> = HashMap::new();
+    for (var, coeff) in vars.iter().zip(coeffs).take(GENERIC_REGISTERS) {
+        if let Some(var) = var {
+            *linear.entry(var.index).or_insert_with(F::zero) += coeff;
+        }
+    }
 
-            self.add_gate(label, GateKind::DoubleGeneric, vars, coeffs, span);
-        } else {
-            // otherwise queue it
-            self.pending_generic_gate = Some(PendingGate {
-                label,
-                coeffs,
-                vars,
-                span,
-            });
+    let multiplied = vars[0].is_some() && vars[1].is_some() && !coeffs[MUL_COEFF].is_zero();
+    !multiplied && coeffs[CONST_COEFF].is_zero() && linear.values().all(|coeff| coeff.is_zero())
+}
+
+/// Rewrites a generic gate (the variables it reads, and its coefficients) into a canonical form
+/// that enforces the same constraint, so that gates that only differ by a trivial rewriting
+/// (like `xx + xx` and `xx * 2`) are identified as the same gate:
+///
+/// - an input read in the first two cells (and not multiplied) is only read once, with the sum of the coefficients
+/// - the coefficients of the cells that don't contain a variable (which are zero) are set to zero
+/// - the coefficients are scaled so that the first non-zero coefficient is one
+///
+/// The gates of the circuit keep their coefficients: only their key is normalized.
+fn normalize_generic<F>(inputs: &mut [Option<usize>], coeffs: &mut [F])
+where
+    F: BackendField,
+{
+    if inputs.len() >= 2
+        && inputs[0].is_some()
+        && inputs[0] == inputs[1]
+        && coeffs[MUL_COEFF].is_zero()
+    {
+        let rhs_coeff = coeffs[1];
+        coeffs[0] += rhs_coeff;
+        coeffs[1] = F::zero();
+        inputs[1] = None;
+    }
+
+    for (col, input) in inputs.iter().enumerate() {
+        if input.is_none() {
+            coeffs[col] = F::zero();
+            if col < 2 {
+                coeffs[MUL_COEFF] = F::zero();
+            }
+        }
+    }
+
+    if let Some(first) = coeffs.iter().find(|coeff| !coeff.is_zero()) {
+        let scale = first
+            .inverse()
+            .expect("a non-zero coefficient is invertible");
+        for coeff in coeffs.iter_mut() {
+            *coeff *= scale;
         }
     }
 }
@@ -257,22 +1312,28 @@ impl KimchiCellVar {
     }
 }
 
-impl Backend for KimchiVesta {
-    type Field = VestaField;
+impl<C> Backend for Kimchi<C>
+where
+    C: KimchiCurveConfig,
+{
+    type Field = C::Field;
     type Var = KimchiCellVar;
-    type GeneratedWitness = GeneratedWitness;
+    type GeneratedWitness = GeneratedWitness<C::Field>;
+    type Recording = Recording<C>;
 
     fn poseidon() -> crate::imports::FnHandle<Self> {
-        builtin::poseidon
+        builtin::poseidon::<C>
     }
 
-    fn new_internal_var(&mut self, val: Value<KimchiVesta>, span: Span) -> KimchiCellVar {
+    fn new_internal_var(&mut self, val: Value<Self>, span: Span) -> KimchiCellVar {
         // create new var
         let var = KimchiCellVar::new(self.next_variable, span);
         self.next_variable += 1;
 
         // store it in the circuit_writer
+        self.record(|| RecordedOp::Var(var, val.clone()));
         self.vars_to_value.insert(var.index, val);
+        self.var_spans.push(span);
 
         var
     }
@@ -280,26 +1341,172 @@ impl Backend for KimchiVesta {
     fn add_constant(
         &mut self,
         label: Option<&'static str>,
-        value: VestaField,
+        value: C::Field,
         span: Span,
     ) -> KimchiCellVar {
-        if let Some(cvar) = self.cached_constants.get(&value) {
-            return *cvar;
+        // a splice asks for the constant again, instead of creating the recorded variable and gate
+        let var = self.unrecorded(|backend| backend.constant(label, value, span));
+        self.record(|| RecordedOp::Constant {
+            var,
+            label,
+            value,
+            span,
+        });
+        var
+    }
+
+    fn set_assert_message(&mut self, message: Option<String>) {
+        self.assert_message = message;
+    }
+
+    fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+    }
+
+    fn set_double_generic(&mut self, enabled: bool) -> bool {
+        std::mem::replace(&mut self.double_generic, enabled)
+    }
+
+    fn double_generic(&self) -> bool {
+        self.double_generic
+    }
+
+    fn start_recording(&mut self, inputs: &[KimchiCellVar]) {
+        self.push_recording(inputs);
+    }
+
+    fn stop_recording(&mut self) -> Option<Recording<C>> {
+        self.pop_recording()
+    }
+
+    fn splice(
+        &mut self,
+        recording: &Recording<C>,
+        inputs: &[KimchiCellVar],
+        vars: &[KimchiCellVar],
+    ) -> Vec<KimchiCellVar> {
+        self.splice_recording(recording, inputs, vars)
+    }
+
+    fn lookup(&mut self, table_id: u32, key: &KimchiCellVar, span: Span) -> Result<KimchiCellVar> {
+        let values = self
+            .lookup_tables
+            .get(&table_id)
+            .map(LookupTable::values)
+            .ok_or_else(|| {
+                Error::new(
+                    "constraint-generation",
+                    ErrorKind::UnknownLookupTable(table_id),
+                    span,
+                )
+            })?;
+
+        // the value is read from the table during witness generation
+        let key_var = *key;
+        let value = self.new_internal_var(
+            Value::Hint(Arc::new(move |backend, env| {
+                let key = backend.compute_var(env, &key_var)?;
+                values.get(&key).copied().ok_or_else(|| {
+                    Error::new(
+                        "runtime",
+                        ErrorKind::LookupKeyNotFound(table_id, key.pretty()),
+                        span,
+                    )
+                })
+            })),
+            span,
+        );
+
+        let id = self.add_constant(
+            Some("hardcode the id of a lookup table"),
+            C::Field::from(table_id as u64),
+            span,
+        );
+
+        // a lookup gate always checks three pairs, so we repeat the same one
+        let mut vars = vec![Some(id)];
+        for _ in 0..LOOKUPS_PER_ROW {
+            vars.push(Some(*key));
+            vars.push(Some(value));
         }
+        self.add_gate(
+            "lookup of a key in a table",
+            GateKind::Lookup,
+            vars,
+            vec![],
+            span,
+        );
 
-        let var = self.new_internal_var(Value::Constant(value), span);
-        self.cached_constants.insert(value, var);
+        Ok(value)
+    }
 
-        let zero = VestaField::zero();
+    fn range_check64(&mut self, var: &KimchiCellVar, span: Span) -> Result<()> {
+        // the gate looks its 12-bit limbs up in kimchi's own range-check table
+        let reserved_id = RANGE_CHECK_TABLE_ID as u32;
+        if self.lookup_tables.contains_key(&reserved_id) {
+            return Err(Error::new(
+                "constraint-generation",
+                ErrorKind::ReservedLookupTable(reserved_id),
+                span,
+            ));
+        }
 
-        let _ = &self.add_generic_gate(
-            label.unwrap_or("hardcode a constant"),
-            vec![Some(var)],
-            vec![VestaField::one(), zero, zero, zero, value.neg()],
+        let zero = self.add_constant(
+            Some("hardcode zero for the highest limbs of a range check"),
+            C::Field::zero(),
             span,
         );
 
-        var
+        // the limbs are computed during witness generation,
+        // and the two highest limbs are zero for a value that fits in 64 bits
+        let mut vars = vec![Some(*var), Some(zero), Some(zero)];
+        for (offset, bits) in &RANGE_CHECK_LIMBS[2..] {
+            let (offset, bits) = (*offset, *bits);
+            let value_var = *var;
+            let limb = self.new_internal_var(
+                Value::Hint(Arc::new(move |backend, env| {
+                    let value: BigUint = backend.compute_var(env, &value_var)?.into();
+                    let mask = (BigUint::one() << bits) - 1u32;
+                    let limb = u64::try_from((value >> offset) & mask)
+                        .expect("a limb of a range check fits in 64 bits");
+                    Ok(C::Field::from(limb))
+                })),
+                span,
+            );
+            vars.push(Some(limb));
+        }
+
+        self.add_gate(
+            "range check of a 64-bit value",
+            GateKind::RangeCheck,
+            vars,
+            vec![C::Field::zero()],
+            span,
+        );
+
+        Ok(())
+    }
+
+    fn optimize(&mut self, level: OptLevel) {
+        if level >= OptLevel::O2 && self.double_generic_gate_optimization {
+            self.eliminate_common_subexpressions();
+        }
+
+        if level >= OptLevel::O3 {
+            self.merge_linear_gates();
+        }
+
+        // before the generic gates are paired, which moves the debug info of the first one aside
+        self.trivial_gates = self
+            .generic_gates
+            .keys()
+            .filter(|row| is_trivial_generic(&self.witness_table[**row], &self.gates[**row].coeffs))
+            .map(|row| self.debug_info[*row].span)
+            .collect();
+
+        if level >= OptLevel::O1 && self.double_generic_gate_optimization {
+            self.pair_generic_gates();
+        }
     }
 
     fn finalize_circuit(
@@ -308,18 +1515,6 @@ impl Backend for KimchiVesta {
         returned_cells: Option<Vec<KimchiCellVar>>,
         main_span: Span,
     ) -> Result<()> {
-        // TODO: the current tests pass even this is commented out. Add a test case for this one.
-        // important: there might still be a pending generic gate
-        if let Some(pending) = self.pending_generic_gate.take() {
-            self.add_gate(
-                pending.label,
-                GateKind::DoubleGeneric,
-                pending.vars,
-                pending.coeffs,
-                pending.span,
-            );
-        }
-
         // for sanity check, we make sure that every cellvar created has ended up in a gate
         let mut written_vars = HashSet::new();
         for row in self.witness_table.iter() {
@@ -328,23 +1523,17 @@ impl Backend for KimchiVesta {
             });
         }
 
+        check_private_inputs_used(&self.private_input_indices, |var| {
+            written_vars.contains(&var) || self.eliminated_vars.contains(&var)
+        })?;
+
         for var in 0..self.next_variable {
-            if !written_vars.contains(&var) {
-                if let Some((_, private_input_span)) = self
-                    .private_input_indices
-                    .iter()
-                    .find(|(private_input_id, _)| private_input_id == &var)
-                {
-                    // TODO: is this error useful?
-                    let err = Error::new(
-                        "constraint-finalization",
-                        ErrorKind::PrivateInputNotUsed,
-                        *private_input_span,
-                    );
-                    return Err(err);
-                } else {
-                    panic!("there's a bug in the circuit_writer, some cellvar does not end up being a cellvar in the circuit!");
-                }
+            if !written_vars.contains(&var) && !self.eliminated_vars.contains(&var) {
+                return Err(unconstrained_var(
+                    var,
+                    &self.vars_to_value[&var],
+                    self.var_spans[var],
+                ));
             }
         }
 
@@ -372,6 +1561,48 @@ impl Backend for KimchiVesta {
         Ok(())
     }
 
+    fn unused_public_inputs(&self) -> Vec<(String, Span)> {
+        // the first rows only expose the public inputs (and outputs)
+        let used_vars: HashSet<_> = self.witness_table[self.public_input_size..]
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|var| var.index)
+            .collect();
+
+        unused_inputs(&self.public_input_indices, |var| used_vars.contains(&var))
+    }
+
+    fn trivially_satisfied_constraints(&self) -> Vec<Span> {
+        self.trivial_gates.clone()
+    }
+
+    fn constraints(&self) -> Result<Vec<Constraint<C::Field>>> {
+        let mut constraints = vec![];
+
+        // the first rows bind the public inputs (and outputs) to the public values, instead of constraining them
+        for (row, (gate, vars, debug_info)) in
+            izip!(&self.gates, &self.witness_table, &self.debug_info)
+                .enumerate()
+                .skip(self.public_input_size)
+        {
+            let vars: Vec<_> = vars.iter().map(|var| var.map(|var| var.index)).collect();
+            let gate_constraints = gate.constraints(&vars, debug_info.span).ok_or_else(|| {
+                Error::new(
+                    "constraints",
+                    ErrorKind::UnsupportedGateConstraints {
+                        row,
+                        gate: gate.typ,
+                    },
+                    debug_info.span,
+                )
+            })?;
+            constraints.extend(gate_constraints);
+        }
+
+        Ok(constraints)
+    }
+
     fn compute_var(
         &self,
         env: &mut crate::witness::WitnessEnv<Self::Field>,
@@ -383,101 +1614,11 @@ impl Backend for KimchiVesta {
 
     fn generate_witness(
         &self,
-        witness_env: &mut WitnessEnv<VestaField>,
-    ) -> Result<GeneratedWitness> {
-        if !self.finalized {
-            unreachable!("the circuit must be finalized before generating a witness");
-        }
-
-        let mut witness = vec![];
-        // compute each rows' vars, except for the deferred ones (public output)
-        let mut public_outputs_vars: HashMap<KimchiCellVar, Vec<(usize, usize)>> = HashMap::new();
-
-        // calculate witness except for public outputs
-        for (row, row_of_vars) in self.witness_table.iter().enumerate() {
-            // create the witness row
-            let mut witness_row = [Self::Field::zero(); NUM_REGISTERS];
-
-            for (col, var) in row_of_vars.iter().enumerate() {
-                let val = if let Some(var) = var {
-                    // if it's a public output, defer it's computation
-                    if matches!(
-                        self.vars_to_value.get(&var.index),
-                        Some(Value::PublicOutput(_))
-                    ) {
-                        public_outputs_vars
-                            .entry(*var)
-                            .or_default()
-                            .push((row, col));
-                        Self::Field::zero()
-                    } else {
-                        self.compute_var(witness_env, var)?
-                    }
-                } else {
-                    Self::Field::zero()
-                };
-                witness_row[col] = val;
-            }
-
-            witness.push(witness_row);
-        }
-
-        // compute public output at last
-        let mut public_outputs = vec![];
-
-        for (var, rows_cols) in public_outputs_vars {
-            let val = self.compute_var(witness_env, &var)?;
-            for (row, col) in rows_cols {
-                witness[row][col] = val;
-            }
-            public_outputs.push(val);
-        }
-
-        // sanity check the witness
-        for (row, (gate, witness_row, debug_info)) in
-            izip!(self.gates.iter(), &witness, &self.debug_info).enumerate()
-        {
-            let is_not_public_input = row >= self.public_input_size;
-            if is_not_public_input {
-                #[allow(clippy::single_match)]
-                match gate.typ {
-                    // only check the generic gate
-                    crate::circuit_writer::GateKind::DoubleGeneric => {
-                        let c = |i| {
-                            gate.coeffs
-                                .get(i)
-                                .copied()
-                                .unwrap_or_else(Self::Field::zero)
-                        };
-                        let w = &witness_row;
-                        let sum1 =
-                            c(0) * w[0] + c(1) * w[1] + c(2) * w[2] + c(3) * w[0] * w[1] + c(4);
-                        let sum2 =
-                            c(5) * w[3] + c(6) * w[4] + c(7) * w[5] + c(8) * w[3] * w[4] + c(9);
-                        if sum1 != Self::Field::zero() || sum2 != Self::Field::zero() {
-                            return Err(Error::new(
-                                "runtime",
-                                ErrorKind::InvalidWitness(row),
-                                debug_info.span,
-                            ));
-                        }
-                    }
-                    // for all other gates, we trust the gadgets
-                    _ => (),
-                }
-            }
-        }
-
-        // extract full public input (containing the public output)
-        let mut full_public_inputs = Vec::with_capacity(self.public_input_size);
-
-        for witness_row in witness.iter().take(self.public_input_size) {
-            full_public_inputs.push(witness_row[0]);
-        }
-
-        // sanity checks
-        assert_eq!(witness.len(), self.gates.len());
-        assert_eq!(witness.len(), self.witness_table.len());
+        witness_env: &mut WitnessEnv<C::Field>,
+    ) -> Result<GeneratedWitness<C::Field>> {
+        let mut witness: Vec<[C::Field; NUM_REGISTERS]> =
+            Vec::with_capacity(self.witness_table.len());
+        let (full_public_inputs, public_outputs) = self.write_witness(witness_env, &mut witness)?;
 
         // return the public output separately as well
         Ok(GeneratedWitness {
@@ -494,7 +1635,7 @@ impl Backend for KimchiVesta {
         res.push_str(&crate::utils::noname_version());
 
         // vars
-        let mut vars: OrderedHashSet<VestaField> = OrderedHashSet::default();
+        let mut vars: OrderedHashSet<C::Field> = OrderedHashSet::default();
 
         for Gate { coeffs, .. } in self.gates.iter() {
             extract_vars_from_coeffs(&mut vars, coeffs);
@@ -536,6 +1677,12 @@ impl Backend for KimchiVesta {
                 }
             }
 
+            // the code that created the gate
+            if debug {
+                let location = crate::utils::span_location(sources, debug_info.span);
+                write!(res, " @ {location}").unwrap();
+            }
+
             res.push('\n');
 
             if debug {
@@ -592,103 +1739,111 @@ impl Backend for KimchiVesta {
         res
     }
 
+    fn estimate_rows(&self, typed: &TypeChecker<Self>) -> usize {
+        let costs = estimate_costs(typed);
+
+        // every operation is a generic gate, which can be paired with another one
+        let generic = costs.ops.linear + costs.ops.mul + costs.ops.assert;
+        let generic_rows = if self.double_generic_gate_optimization {
+            generic.div_ceil(2)
+        } else {
+            generic
+        };
+
+        // a poseidon hash uses its rounds and a final zero gate
+        let poseidon_rows = costs.ops.poseidon * (POS_ROWS_PER_HASH + 1);
+
+        // public inputs and outputs have their own rows, and each lookup or range check is a single row
+        costs.public_io + generic_rows + poseidon_rows + costs.ops.lookup + costs.ops.range_check
+    }
+
     fn neg(&mut self, var: &KimchiCellVar, span: Span) -> KimchiCellVar {
         let zero = Self::Field::zero();
         let one = Self::Field::one();
 
-        let neg_var = self.new_internal_var(
-            Value::LinearCombination(vec![(one.neg(), *var)], zero),
-            span,
-        );
-        self.add_generic_gate(
+        self.add_generic_gate_with_output(
             "constraint to validate a negation (`x + (-x) = 0`)",
-            vec![Some(*var), Some(neg_var)],
+            vec![Some(*var)],
             vec![one, one],
+            Value::LinearCombination(vec![(one.neg(), *var)], zero),
             span,
-        );
-
-        neg_var
+        )
     }
 
     fn add(&mut self, lhs: &KimchiCellVar, rhs: &KimchiCellVar, span: Span) -> KimchiCellVar {
         let zero = Self::Field::zero();
         let one = Self::Field::one();
 
-        // create a new variable to store the result
-        let res = self.new_internal_var(
-            Value::LinearCombination(vec![(one, *lhs), (one, *rhs)], zero),
-            span,
-        );
-
-        // create a gate to store the result
-        self.add_generic_gate(
+        // create a gate to store the result in a new variable
+        self.add_generic_gate_with_output(
             "add two variables together",
-            vec![Some(*lhs), Some(*rhs), Some(res)],
+            vec![Some(*lhs), Some(*rhs)],
             vec![one, one, one.neg()],
+            Value::LinearCombination(vec![(one, *lhs), (one, *rhs)], zero),
             span,
-        );
+        )
+    }
 
-        res
+    fn sub(&mut self, lhs: &KimchiCellVar, rhs: &KimchiCellVar, span: Span) -> KimchiCellVar {
+        let zero = Self::Field::zero();
+        let one = Self::Field::one();
+
+        // a single gate is enough to subtract two variables (`lhs - rhs - res = 0`)
+        self.add_generic_gate_with_output(
+            "subtract two variables",
+            vec![Some(*lhs), Some(*rhs)],
+            vec![one, one.neg(), one.neg()],
+            Value::LinearCombination(vec![(one, *lhs), (one.neg(), *rhs)], zero),
+            span,
+        )
     }
 
     fn add_const(&mut self, var: &KimchiCellVar, cst: &Self::Field, span: Span) -> KimchiCellVar {
         let zero = Self::Field::zero();
         let one = Self::Field::one();
 
-        // create a new variable to store the result
-        let res = self.new_internal_var(Value::LinearCombination(vec![(one, *var)], *cst), span);
-
-        // create a gate to store the result
+        // create a gate to store the result in a new variable
         // TODO: we should use an add_generic function that takes advantage of the double generic gate
-        self.add_generic_gate(
+        self.add_generic_gate_with_output(
             "add a constant with a variable",
-            vec![Some(*var), None, Some(res)],
+            vec![Some(*var), None],
             vec![one, zero, one.neg(), zero, *cst],
+            Value::LinearCombination(vec![(one, *var)], *cst),
             span,
-        );
-
-        res
+        )
     }
 
     fn mul(&mut self, lhs: &KimchiCellVar, rhs: &KimchiCellVar, span: Span) -> KimchiCellVar {
         let zero = Self::Field::zero();
         let one = Self::Field::one();
 
-        // create a new variable to store the result
-        let res = self.new_internal_var(Value::Mul(*lhs, *rhs), span);
-
-        // create a gate to store the result
-        self.add_generic_gate(
+        // create a gate to store the result in a new variable
+        self.add_generic_gate_with_output(
             "add two variables together",
-            vec![Some(*lhs), Some(*rhs), Some(res)],
+            vec![Some(*lhs), Some(*rhs)],
             vec![zero, zero, one.neg(), one],
+            Value::Mul(*lhs, *rhs),
             span,
-        );
-
-        res
+        )
     }
 
     fn mul_const(&mut self, var: &KimchiCellVar, cst: &Self::Field, span: Span) -> KimchiCellVar {
         let zero = Self::Field::zero();
         let one = Self::Field::one();
 
-        // create a new variable to store the result
-        let res = self.new_internal_var(Value::Scale(*cst, *var), span);
-
-        // create a gate to store the result
+        // create a gate to store the result in a new variable
         // TODO: we should use an add_generic function that takes advantage of the double generic gate
-        self.add_generic_gate(
+        self.add_generic_gate_with_output(
             "add a constant with a variable",
-            vec![Some(*var), None, Some(res)],
+            vec![Some(*var), None],
             vec![*cst, zero, one.neg()],
+            Value::Scale(*cst, *var),
             span,
-        );
-
-        res
+        )
     }
 
     fn assert_eq_const(&mut self, cvar: &KimchiCellVar, cst: Self::Field, span: Span) {
-        self.add_generic_gate(
+        self.add_generic_gate_once(
             "constrain var - cst = 0 to check equality",
             vec![Some(*cvar)],
             vec![
@@ -703,18 +1858,28 @@ impl Backend for KimchiVesta {
     }
 
     fn assert_eq_var(&mut self, lhs: &KimchiCellVar, rhs: &KimchiCellVar, span: Span) {
-        // TODO: use permutation to check that
-        self.add_generic_gate(
-            "constrain lhs - rhs = 0 to assert that they are equal",
-            vec![Some(*lhs), Some(*rhs)],
-            vec![Self::Field::one(), Self::Field::one().neg()],
+        // a splice asserts the equality again, as its gate depends on the equalities known so far
+        let (lhs, rhs) = (*lhs, *rhs);
+        let (label, message) = (self.label.clone(), self.assert_message.clone());
+        self.record(|| RecordedOp::AssertEq {
+            lhs,
+            rhs,
             span,
-        );
+            label,
+            message,
+        });
+        self.unrecorded(|backend| backend.assert_vars_eq(&lhs, &rhs, span));
     }
 
     fn add_public_input(&mut self, val: Value<Self>, span: Span) -> KimchiCellVar {
+        let name = match &val {
+            Value::External(name, _) => name.clone(),
+            _ => unreachable!("public inputs are external values"),
+        };
+
         // create the var
         let cvar = self.new_internal_var(val, span);
+        self.public_input_indices.push((cvar.index, name, span));
 
         // create the associated generic gate
         self.add_gate(
@@ -731,8 +1896,13 @@ impl Backend for KimchiVesta {
     }
 
     fn add_private_input(&mut self, val: Value<Self>, span: Span) -> Self::Var {
+        let name = match &val {
+            Value::External(name, _) => name.clone(),
+            _ => unreachable!("private inputs are external values"),
+        };
         let cvar = self.new_internal_var(val, span);
-        self.private_input_indices.push((cvar.index, cvar.span));
+        self.private_input_indices
+            .push((cvar.index, name, cvar.span));
 
         cvar
     }
@@ -742,8 +1912,11 @@ impl Backend for KimchiVesta {
         let cvar = self.new_internal_var(val, span);
 
         // create the associated generic gate
-        self.add_generic_gate(
+        // (like public inputs, each public output gets its own row
+        // so that the public input size matches the number of rows)
+        self.add_gate(
             "add public output",
+            GateKind::DoubleGeneric,
             vec![Some(cvar)],
             vec![Self::Field::one()],
             span,
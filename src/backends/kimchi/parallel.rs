@@ -0,0 +1,198 @@
+//! Parallel computation of the values of the variables of a kimchi circuit.
+//!
+//! The variables used by the witness table are partitioned into levels,
+//! where a variable only depends on variables of lower levels,
+//! and the variables of a level are computed in parallel.
+//! Hints are closures that can read any variable, so they can't be analyzed:
+//! they are computed sequentially (along with what they read) while the levels are built.
+//! As the value of a variable doesn't depend on the order in which variables are computed,
+//! the witness is the same as the one computed sequentially.
+
+use std::collections::HashMap;
+
+use ark_ff::{One as _, Zero as _};
+use rayon::prelude::*;
+
+use crate::{
+    backends::{Backend, BackendField},
+    error::Result,
+    var::Value,
+    witness::WitnessEnv,
+};
+
+use super::{curves::KimchiCurveConfig, Kimchi};
+
+/// The minimum number of variables computed by each rayon task,
+/// as computing a single variable is cheap compared to scheduling a task.
+const MIN_VARS_PER_TASK: usize = 64;
+
+/// How to compute a variable from variables of lower levels.
+enum Op<F> {
+    LinearCombination(Vec<(F, usize)>, F),
+    Mul(usize, usize),
+    Scale(F, usize),
+    Inverse(usize),
+}
+
+impl<F> Op<F>
+where
+    F: BackendField,
+{
+    fn deps(&self) -> Vec<usize> {
+        match self {
+            Op::LinearCombination(lc, _) => lc.iter().map(|(_, var)| *var).collect(),
+            Op::Mul(lhs, rhs) => vec![*lhs, *rhs],
+            Op::Scale(_, var) | Op::Inverse(var) => vec![*var],
+        }
+    }
+
+    fn eval(&self, values: &HashMap<usize, F>) -> F {
+        match self {
+            Op::LinearCombination(lc, cst) => lc
+                .iter()
+                .fold(*cst, |acc, (coeff, var)| acc + *coeff * values[var]),
+            Op::Mul(lhs, rhs) => values[lhs] * values[rhs],
+            Op::Scale(scalar, var) => *scalar * values[var],
+            Op::Inverse(var) => values[var].inverse().unwrap_or_else(F::zero),
+        }
+    }
+}
+
+impl<C> Kimchi<C>
+where
+    C: KimchiCurveConfig,
+{
+    /// Computes the values of the variables of the witness table level by level,
+    /// and caches them in the witness environment.
+    pub(crate) fn compute_vars_in_parallel(&self, env: &mut WitnessEnv<C::Field>) -> Result<()> {
+        let mut levels = HashMap::new();
+        let mut ops = vec![];
+
+        // like the sequential witness generation, the public outputs are visited last
+        // (so that the first hint failing is the same)
+        let (mut public_outputs, others): (Vec<_>, Vec<_>) = self
+            .witness_table
+            .iter()
+            .flatten()
+            .flatten()
+            .partition(|var| {
+                matches!(
+                    self.vars_to_value.get(&var.index),
+                    Some(Value::PublicOutput(_))
+                )
+            });
+        public_outputs.sort_by_key(|var| var.index);
+
+        for var in others.into_iter().chain(public_outputs) {
+            self.level_of(env, var.index, &mut levels, &mut ops)?;
+        }
+
+        for level in ops {
+            let values = &env.cached_values;
+            let computed: Vec<_> = level
+                .par_iter()
+                .with_min_len(MIN_VARS_PER_TASK)
+                .map(|(var, op)| (*var, op.eval(values)))
+                .collect();
+            env.cached_values.extend(computed);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the level of a variable, after adding it (and the variables it depends on) to the levels to compute.
+    /// The variables that don't depend on other variables (like hints, or linear combinations without terms)
+    /// are computed right away, and are of level 0.
+    fn level_of(
+        &self,
+        env: &mut WitnessEnv<C::Field>,
+        var: usize,
+        levels: &mut HashMap<usize, usize>,
+        ops: &mut Vec<Vec<(usize, Op<C::Field>)>>,
+    ) -> Result<usize> {
+        if let Some(level) = levels.get(&var) {
+            return Ok(*level);
+        }
+
+        let value = &self.vars_to_value[&var];
+        let op = match value {
+            Value::LinearCombination(lc, cst) => Some(Op::LinearCombination(
+                lc.iter().map(|(coeff, var)| (*coeff, var.index)).collect(),
+                *cst,
+            )),
+            Value::Mul(lhs, rhs) => Some(Op::Mul(lhs.index, rhs.index)),
+            Value::Scale(scalar, var) => Some(Op::Scale(*scalar, var.index)),
+            Value::Inverse(var) => Some(Op::Inverse(var.index)),
+            Value::PublicOutput(Some(var)) => Some(Op::LinearCombination(
+                vec![(C::Field::one(), var.index)],
+                C::Field::zero(),
+            )),
+            Value::Hint(_)
+            | Value::Constant(_)
+            | Value::External(..)
+            | Value::PublicOutput(None) => None,
+        };
+
+        let level = match op {
+            None => {
+                let val = self.compute_val(env, value, var)?;
+                env.cached_values.insert(var, val);
+                0
+            }
+            Some(op) => {
+                let mut level = 0;
+                for dep in op.deps() {
+                    level = level.max(self.level_of(env, dep, levels, ops)? + 1);
+                }
+
+                if level == 0 {
+                    let val = op.eval(&env.cached_values);
+                    env.cached_values.insert(var, val);
+                } else {
+                    if ops.len() < level {
+                        ops.resize_with(level, Vec::new);
+                    }
+                    ops[level - 1].push((var, op));
+                }
+
+                level
+            }
+        };
+
+        levels.insert(var, level);
+        Ok(level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{backends::kimchi::KimchiVesta, constants::Span};
+
+    use super::*;
+
+    #[test]
+    fn test_level_of_var_without_deps() -> Result<()> {
+        let mut backend = KimchiVesta::new(false);
+        let span = Span::default();
+
+        // a linear combination without terms is a constant
+        let cst = backend.new_internal_var(Value::LinearCombination(vec![], 5u64.into()), span);
+        let doubled = backend.new_internal_var(
+            Value::LinearCombination(vec![(2u64.into(), cst)], 0u64.into()),
+            span,
+        );
+
+        let mut env = WitnessEnv::default();
+        let mut levels = HashMap::new();
+        let mut ops = vec![];
+        assert_eq!(
+            backend.level_of(&mut env, doubled.index, &mut levels, &mut ops)?,
+            1
+        );
+        assert_eq!(levels[&cst.index], 0);
+        assert_eq!(env.cached_values[&cst.index], 5u64.into());
+        assert_eq!(ops.len(), 1);
+
+        Ok(())
+    }
+}
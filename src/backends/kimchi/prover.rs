@@ -1,74 +1,249 @@
 //! This module contains the prover.
 
-use std::iter::once;
+use std::{collections::BTreeMap, path::Path};
 
 use crate::{
-    backends::kimchi::{KimchiVesta, VestaField},
+    backends::kimchi::{
+        cache::{CachedCircuit, CachedLookupTable},
+        curves::{
+            KimchiCurveConfig, VestaBaseSponge, VestaConfig, VestaScalarSponge, VESTA_GROUP_MAP,
+        },
+        lookup::LookupTable,
+        srs::SharedSrs,
+        GateStats, Kimchi, KimchiVesta, PublicLayout, PublicSlot, StreamedWitness, VestaField,
+    },
     circuit_writer::Wiring,
-    compiler::{generate_witness, Sources},
-    inputs::JsonInputs,
+    compiler::{IntoMiette as _, Sources},
+    error::Result,
+    inputs::{parse_field, JsonInputs, ParsingError},
     witness::CompiledCircuit,
 };
 
 use itertools::chain;
-use kimchi::mina_curves::pasta::{Vesta, VestaParameters};
-use kimchi::mina_poseidon::constants::PlonkSpongeConstantsKimchi;
-use kimchi::mina_poseidon::sponge::{DefaultFqSponge, DefaultFrSponge};
-use kimchi::poly_commitment::commitment::CommitmentCurve;
+use kimchi::mina_curves::pasta::Vesta;
 use kimchi::poly_commitment::evaluation_proof::OpeningProof;
 use kimchi::proof::ProverProof;
 use kimchi::{
-    circuits::constraints::ConstraintSystem, groupmap::GroupMap, mina_curves::pasta::Pallas,
+    circuits::{
+        constraints::{ConstraintSystem, FeatureFlags},
+        gate::CircuitGate,
+        lookup::lookups::{LookupFeatures, LookupPatterns},
+    },
+    mina_curves::pasta::Pallas,
     poly_commitment::srs::SRS,
 };
 
 use miette::{Context, IntoDiagnostic};
-use once_cell::sync::Lazy;
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 
 //
 // aliases
 //
 
+// the JSON export, the binary format of proofs and the batch verification are only available for circuits over Vesta
 type Curve = Vesta;
 type OtherCurve = Pallas;
-type SpongeParams = PlonkSpongeConstantsKimchi;
-type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
-type ScalarSponge = DefaultFrSponge<kimchi::mina_curves::pasta::Fp, SpongeParams>;
 
 //
-// Lazy static
+// Data Structures
 //
 
-static GROUP_MAP: Lazy<<Curve as CommitmentCurve>::Map> =
-    Lazy::new(<Curve as CommitmentCurve>::Map::setup);
+// Note: this can't derive serde traits, as the compiled circuit contains hints (closures).
+// See [ProverIndex::save] and [ProverIndex::load] instead.
+pub struct ProverIndex<C = VestaConfig>
+where
+    C: KimchiCurveConfig,
+{
+    pub(crate) index: C::ProverIndex,
+    pub(crate) compiled_circuit: CompiledCircuit<Kimchi<C>>,
+}
 
-//
-// Data Structures
-//
+/// The sizes of a proof, to keep track of them in benchmarks (see [ProverIndex::metrics]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofMetrics {
+    /// The size in bytes of the proof serialized with [proof_to_bytes].
+    pub proof_size: usize,
+
+    /// The number of group elements of the SRS that the proof commits with,
+    /// which is at least the size of the domain of the circuit (and more with a larger [SharedSrs]).
+    pub srs_size: usize,
+
+    /// The number of points of the evaluation domain of the circuit.
+    pub domain_size: usize,
+}
 
-//#[derive(Serialize, Deserialize)]
-pub struct ProverIndex {
-    index: kimchi::prover_index::ProverIndex<Curve, OpeningProof<Curve>>,
-    compiled_circuit: CompiledCircuit<KimchiVesta>,
+/// The version of the format used to save a [ProverIndex] to disk.
+/// This must be bumped every time [SerializedProverIndex] changes.
+const PROVER_INDEX_VERSION: u32 = 1;
+
+/// What gets written to disk when saving a [ProverIndex].
+/// The SRS is not part of it, as it is deterministically reconstructed from the domain size.
+#[derive(Serialize, Deserialize)]
+struct SerializedProverIndex {
+    version: u32,
+    cs: ConstraintSystem<VestaField>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VerifierIndex<C = VestaConfig>
+where
+    C: KimchiCurveConfig,
+{
+    index: C::VerifierIndex,
 }
 
+/// The version of the JSON format of a [VerifierIndex] (see [VerifierIndex::to_json]).
+/// This must be bumped every time [VerifierIndexJson] changes.
+const VERIFIER_INDEX_JSON_VERSION: u32 = 1;
+
+/// The JSON representation of a [VerifierIndex]:
+///
+/// - `version`: the version of the format ([VERIFIER_INDEX_JSON_VERSION]).
+/// - `domain_size`: the number of rows of the circuit, padded to a power of two.
+/// - `public_input_size`: the number of public inputs, including the public outputs (which come first).
+/// - `max_poly_size`: the maximum size of a committed polynomial (the size of the SRS).
+/// - `commitments`: the commitments to the circuit polynomials, by name
+///   (`sigma_0` to `sigma_6`, `coefficients_0` to `coefficients_14`, `generic`, `psm`, `complete_add`, `mul`, `emul`, `endomul_scalar`).
+///   Each commitment is encoded as kimchi serializes it (its points are compressed and encoded as bytes).
+/// - `shifts`: the shifts of the permutation argument, as decimal strings.
+/// - `index`: the kimchi verifier index, from which [VerifierIndex::from_json] reloads the index.
+///   The other fields must match it.
 #[derive(Serialize, Deserialize)]
-pub struct VerifierIndex {
-    index: kimchi::verifier_index::VerifierIndex<Curve, OpeningProof<Curve>>,
+struct VerifierIndexJson {
+    version: u32,
+    domain_size: u64,
+    public_input_size: usize,
+    max_poly_size: usize,
+    commitments: BTreeMap<String, serde_json::Value>,
+    shifts: Vec<String>,
+    index: serde_json::Value,
+}
+
+/// The magic bytes at the start of a proof serialized with [proof_to_bytes].
+const PROOF_MAGIC: &[u8; 4] = b"nnpf";
+
+/// The version of the binary format of proofs (see [proof_to_bytes]).
+/// This must be bumped every time the encoding of a proof changes,
+/// which includes every upgrade of the `kimchi` dependency (pinned to a git revision in `Cargo.toml`),
+/// as the encoded proof is the serde serialization of kimchi's [ProverProof].
+const PROOF_VERSION: u32 = 1;
+
+/// The size of the header of a serialized proof: the magic bytes, the version, and the length of the encoded proof.
+const PROOF_HEADER_LEN: usize = PROOF_MAGIC.len() + 4 + 8;
+
+/// The JSON representation of a proof, along with the public values it was created for
+/// (as printed by `noname prove --format json`, and read by `noname verify --format json`):
+///
+/// - `proof`: the proof serialized with [proof_to_bytes], as a hexadecimal string.
+/// - `public_inputs`: the public inputs of the circuit, in the order of the arguments of `main`, as decimal strings.
+/// - `public_outputs`: the public output of the circuit, as decimal strings.
+///
+/// Every value is a string, so that JSON parsers don't lose precision on large numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofJson {
+    pub proof: String,
+    pub public_inputs: Vec<String>,
+    pub public_outputs: Vec<String>,
+}
+
+impl ProofJson {
+    /// Creates the JSON representation of a proof from what [ProverIndex::prove] returns.
+    pub fn new(
+        proof: &ProverProof<Curve, OpeningProof<Curve>>,
+        full_public_inputs: &[VestaField],
+        public_output: &[VestaField],
+    ) -> Self {
+        let to_dec = |values: &[VestaField]| {
+            values
+                .iter()
+                .map(|value| BigUint::from(*value).to_string())
+                .collect()
+        };
+
+        // the public output comes first in the public input of the circuit (see [CompiledCircuit::public_layout])
+        let public_inputs = &full_public_inputs[public_output.len()..];
+
+        Self {
+            proof: proof_to_bytes(proof)
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+            public_inputs: to_dec(public_inputs),
+            public_outputs: to_dec(public_output),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a proof can always be serialized to JSON")
+    }
+
+    pub fn from_json(json: &str) -> miette::Result<Self> {
+        serde_json::from_str(json)
+            .into_diagnostic()
+            .wrap_err("could not parse the JSON proof")
+    }
+
+    /// Returns the proof, and the full public input expected by [VerifierIndex::verify]
+    /// (the public output followed by the public inputs).
+    pub fn decode(
+        &self,
+    ) -> miette::Result<(ProverProof<Curve, OpeningProof<Curve>>, Vec<VestaField>)> {
+        if self.proof.len() % 2 != 0 || !self.proof.is_ascii() {
+            miette::bail!("the proof is not a hexadecimal string");
+        }
+        let bytes = (0..self.proof.len())
+            .step_by(2)
+            .map(|ii| u8::from_str_radix(&self.proof[ii..ii + 2], 16))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .into_diagnostic()
+            .wrap_err("the proof is not a hexadecimal string")?;
+        let proof = proof_from_bytes(&bytes)?;
+
+        let full_public_inputs = chain![&self.public_outputs, &self.public_inputs]
+            .map(|value| parse_field(value))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .into_diagnostic()
+            .wrap_err("the public values of the proof are not field elements")?;
+
+        Ok((proof, full_public_inputs))
+    }
 }
 
 //
 // Setup
 //
 
-impl KimchiVesta {
-    pub fn compile_to_indexes(
-        &self,
-    ) -> miette::Result<(
-        kimchi::prover_index::ProverIndex<Curve, OpeningProof<Curve>>,
-        kimchi::verifier_index::VerifierIndex<Curve, OpeningProof<Curve>>,
-    )> {
+impl<C> Kimchi<C>
+where
+    C: KimchiCurveConfig,
+{
+    /// Returns the copy constraints of the circuit, as cycles of `(row, col)` cells
+    /// (ordered by the variables they come from).
+    /// In the kimchi gates, each cell of a cycle is wired to the previous one,
+    /// and the first cell to the last one.
+    pub fn wiring_cycles(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut wirings: Vec<_> = self.wiring.iter().collect();
+        wirings.sort_by_key(|(var, _)| **var);
+
+        wirings
+            .into_iter()
+            .filter_map(|(_, wiring)| match wiring {
+                Wiring::NotWired(_) => None,
+                Wiring::Wired(annotated_cells) => {
+                    assert!(annotated_cells.len() > 1);
+                    let cycle = annotated_cells
+                        .iter()
+                        .map(|annotated_cell| (annotated_cell.cell.row, annotated_cell.cell.col))
+                        .collect();
+                    Some(cycle)
+                }
+            })
+            .collect()
+    }
+
+    /// Converts the gates of the circuit to kimchi gates, wired together.
+    pub(crate) fn to_kimchi_gates(&self) -> Vec<CircuitGate<C::Field>> {
         // convert gates to kimchi gates
         let mut gates: Vec<_> = self
             .gates
@@ -78,56 +253,146 @@ impl KimchiVesta {
             .collect();
 
         // wiring
-        for wiring in self.wiring.values() {
-            if let Wiring::Wired(annotated_cells) = wiring {
-                // all the wired cells form a cycle, remember!
-                let mut wired_cells = annotated_cells
-                    .iter()
-                    .map(|annotated_cell| annotated_cell.cell);
-                assert!(wired_cells.len() > 1);
-
-                let first_cell = wired_cells.next().unwrap(); // for the cycle
-                let mut prev_cell = first_cell;
-
-                for cell in chain![wired_cells, once(first_cell)] {
-                    gates[cell.row].wires[cell.col] = kimchi::circuits::wires::Wire {
-                        row: prev_cell.row,
-                        col: prev_cell.col,
-                    };
-                    prev_cell = cell;
-                }
+        for cycle in self.wiring_cycles() {
+            // all the wired cells form a cycle, remember!
+            let mut prev_cell = *cycle.last().unwrap();
+
+            for (row, col) in cycle {
+                gates[row].wires[col] = kimchi::circuits::wires::Wire {
+                    row: prev_cell.0,
+                    col: prev_cell.1,
+                };
+                prev_cell = (row, col);
             }
         }
 
-        // create constraint system
-        let cs = ConstraintSystem::create(gates)
-            .public(self.public_input_size)
-            .build()
+        gates
+    }
+
+    /// Creates the kimchi constraint system of a circuit.
+    fn create_constraint_system(
+        gates: Vec<CircuitGate<C::Field>>,
+        public_input_size: usize,
+        lookup_tables: Vec<kimchi::circuits::lookup::tables::LookupTable<C::Field>>,
+    ) -> miette::Result<ConstraintSystem<C::Field>> {
+        let mut cs = ConstraintSystem::create(gates).public(public_input_size);
+        if !lookup_tables.is_empty() {
+            cs = cs.lookup(lookup_tables);
+        }
+        cs.build()
             .into_diagnostic()
-            .wrap_err("kimchi: could not create a constraint system with the given circuit and public input size")?;
+            .wrap_err("kimchi: could not create a constraint system with the given circuit and public input size")
+    }
 
-        // create SRS (for vesta, as the circuit is in Fp)
-        let mut srs = SRS::<Curve>::create(cs.domain.d1.size as usize);
-        srs.add_lagrange_basis(cs.domain.d1);
-        let srs = std::sync::Arc::new(srs);
+    /// Creates the indexes of the circuit, with a new SRS of the size of its domain.
+    pub fn compile_to_indexes(&self) -> miette::Result<(C::ProverIndex, C::VerifierIndex)> {
+        let cs = self.constraint_system()?;
+        let srs = SharedSrs::new(cs.domain.d1.size as usize);
+        Self::create_indexes(cs, &srs)
+    }
 
-        println!("using an SRS of size {}", srs.g.len());
+    /// Same as [Self::compile_to_indexes], with an SRS that can be shared with other circuits.
+    pub fn compile_to_indexes_with_srs(
+        &self,
+        srs: &SharedSrs<C>,
+    ) -> miette::Result<(C::ProverIndex, C::VerifierIndex)> {
+        let cs = self.constraint_system()?;
+        Self::create_indexes(cs, srs)
+    }
 
-        // create indexes
-        let (endo_q, _endo_r) = kimchi::poly_commitment::srs::endos::<OtherCurve>();
+    fn constraint_system(&self) -> miette::Result<ConstraintSystem<C::Field>> {
+        let gates = self.to_kimchi_gates();
 
-        let prover_index = kimchi::prover_index::ProverIndex::<Curve, OpeningProof<Curve>>::create(
-            cs, endo_q, srs,
-        );
-        let verifier_index = prover_index.verifier_index();
+        // the lookup tables are only passed to kimchi if there are any
+        let lookup_tables: Vec<_> = self
+            .lookup_tables
+            .values()
+            .map(LookupTable::to_kimchi_table)
+            .collect();
+
+        Self::create_constraint_system(gates, self.public_input_size, lookup_tables)
+    }
+
+    fn create_indexes(
+        cs: ConstraintSystem<C::Field>,
+        srs: &SharedSrs<C>,
+    ) -> miette::Result<(C::ProverIndex, C::VerifierIndex)> {
+        let srs = srs.for_circuit(&cs)?;
+
+        // create indexes
+        let prover_index = C::create_prover_index(cs, srs);
+        let verifier_index = C::verifier_index(&prover_index);
 
         Ok((prover_index, verifier_index))
     }
 }
 
-impl CompiledCircuit<KimchiVesta> {
-    pub fn compile_to_indexes(self) -> miette::Result<(ProverIndex, VerifierIndex)> {
-        let (prover_index, verifier_index) = self.circuit.backend.compile_to_indexes()?;
+/// Fails if `actual` (the number of rows of a circuit) isn't `expected`.
+fn check_num_rows(actual: usize, expected: usize) -> miette::Result<()> {
+    if actual != expected {
+        miette::bail!("the circuit has {actual} rows, but {expected} rows were expected");
+    }
+
+    Ok(())
+}
+
+impl CachedCircuit {
+    /// Same as [CompiledCircuit::assert_rows], for a cached circuit.
+    pub fn assert_rows(&self, expected: usize) -> miette::Result<()> {
+        check_num_rows(self.gates.len(), expected)
+    }
+
+    /// Recreates the indexes of a cached circuit.
+    /// As the cache doesn't contain the hints needed to generate a witness,
+    /// this returns the constraint system of the prover index instead of a [ProverIndex]
+    /// (which is all that [save_prover_params] writes to disk anyway).
+    pub fn compile_to_indexes(
+        &self,
+    ) -> miette::Result<(ConstraintSystem<VestaField>, VerifierIndex)> {
+        let lookup_tables = self
+            .lookup_tables
+            .iter()
+            .map(CachedLookupTable::to_kimchi_table)
+            .collect();
+        let cs = KimchiVesta::create_constraint_system(
+            self.gates.clone(),
+            self.public_input_size,
+            lookup_tables,
+        )?;
+
+        let srs = SharedSrs::<VestaConfig>::new(cs.domain.d1.size as usize).for_circuit(&cs)?;
+        let prover_index = VestaConfig::create_prover_index(cs.clone(), srs);
+        let verifier_index = VerifierIndex {
+            index: VestaConfig::verifier_index(&prover_index),
+        };
+
+        Ok((cs, verifier_index))
+    }
+}
+
+impl<C> CompiledCircuit<Kimchi<C>>
+where
+    C: KimchiCurveConfig,
+{
+    pub fn compile_to_indexes(self) -> miette::Result<(ProverIndex<C>, VerifierIndex<C>)> {
+        let indexes = self.circuit.backend.compile_to_indexes()?;
+        Ok(self.wrap_indexes(indexes))
+    }
+
+    /// Same as [Self::compile_to_indexes], with an SRS that can be reused by other circuits
+    /// (see [SharedSrs]).
+    pub fn compile_to_indexes_with_srs(
+        self,
+        srs: &SharedSrs<C>,
+    ) -> miette::Result<(ProverIndex<C>, VerifierIndex<C>)> {
+        let indexes = self.circuit.backend.compile_to_indexes_with_srs(srs)?;
+        Ok(self.wrap_indexes(indexes))
+    }
+
+    fn wrap_indexes(
+        self,
+        (prover_index, verifier_index): (C::ProverIndex, C::VerifierIndex),
+    ) -> (ProverIndex<C>, VerifierIndex<C>) {
         // wrap
         let prover_index = {
             ProverIndex {
@@ -140,7 +405,73 @@ impl CompiledCircuit<KimchiVesta> {
         };
 
         // return asm + indexes
-        Ok((prover_index, verifier_index))
+        (prover_index, verifier_index)
+    }
+
+    /// Returns the number of gates (or rows) in the circuit.
+    pub fn num_gates(&self) -> usize {
+        self.circuit.backend.num_gates()
+    }
+
+    /// Returns the copy constraints of the circuit, as cycles of `(row, col)` cells
+    /// (see [Kimchi::wiring_cycles]).
+    pub fn wiring_cycles(&self) -> Vec<Vec<(usize, usize)>> {
+        self.circuit.backend.wiring_cycles()
+    }
+
+    /// Fails if the circuit doesn't have exactly `expected` rows,
+    /// so that a change of its size doesn't go unnoticed.
+    pub fn assert_rows(&self, expected: usize) -> miette::Result<()> {
+        check_num_rows(self.num_gates(), expected)
+    }
+
+    /// Returns the number of gates of each kind, as well as the number of wiring cycles.
+    pub fn gate_stats(&self) -> GateStats {
+        self.circuit.backend.gate_stats()
+    }
+
+    /// Renders the circuit and its wiring as a Graphviz DOT graph (e.g. `dot -Tpng`).
+    pub fn to_dot(&self) -> String {
+        self.circuit.backend.to_dot()
+    }
+
+    /// Same as [CompiledCircuit::generate_witness],
+    /// except that the witness is written directly in the format of kimchi as it is generated,
+    /// instead of being converted afterwards (with [super::Witness::to_kimchi_witness], which copies it).
+    /// On large circuits, this avoids holding two copies of the witness in memory
+    /// (see the `witness_memory` benchmark).
+    pub fn generate_witness_streaming(
+        &self,
+        public_inputs: JsonInputs,
+        private_inputs: JsonInputs,
+    ) -> Result<StreamedWitness<C::Field>> {
+        let mut env = self.init_witness_env(public_inputs, private_inputs)?;
+        self.circuit.backend.generate_witness_columns(&mut env)
+    }
+
+    /// Returns where each public input, and the public output, are in the public input of the circuit.
+    pub fn public_layout(&self) -> PublicLayout {
+        let mut offset = 0;
+
+        // the public output is created first
+        let output = self.circuit.public_output.as_ref().map(|public_output| {
+            let len = public_output.len();
+            offset += len;
+            PublicSlot { offset: 0, len }
+        });
+
+        // then the public inputs, in the order of the arguments
+        let main_sig = self.main_info().sig();
+        let mut inputs = vec![];
+        for arg in main_sig.arguments.iter().filter(|arg| arg.is_public()) {
+            let len = self.circuit.size_of(&arg.typ.kind);
+            inputs.push((arg.name.value.clone(), PublicSlot { offset, len }));
+            offset += len;
+        }
+
+        assert_eq!(offset, self.circuit.backend.public_input_size);
+
+        PublicLayout { output, inputs }
     }
 }
 
@@ -148,19 +479,212 @@ impl CompiledCircuit<KimchiVesta> {
 // Proving
 //
 
-impl ProverIndex {
+/// Saves the constraint system of a prover index to disk, in the format read by [ProverIndex::load].
+pub fn save_prover_params(
+    cs: &ConstraintSystem<VestaField>,
+    path: impl AsRef<Path>,
+) -> miette::Result<()> {
+    let serialized = SerializedProverIndex {
+        version: PROVER_INDEX_VERSION,
+        cs: cs.clone(),
+    };
+
+    let bytes = rmp_serde::to_vec(&serialized)
+        .into_diagnostic()
+        .wrap_err("could not serialize the prover index")?;
+
+    std::fs::write(path, bytes)
+        .into_diagnostic()
+        .wrap_err("could not write the prover index to disk")
+}
+
+/// Serializes a proof to bytes, in the format read by [proof_from_bytes]:
+///
+/// - the magic bytes `nnpf`.
+/// - the version of the format ([PROOF_VERSION]), as 4 little-endian bytes.
+/// - the length of the encoded proof, as 8 little-endian bytes.
+/// - the encoded proof: the kimchi proof in MessagePack,
+///   where field elements and curve points use their canonical (compressed) arkworks encoding.
+///
+/// Note that the encoded proof follows the serde implementation of [ProverProof],
+/// so it is only stable for the pinned revision of kimchi: proofs serialized with another [PROOF_VERSION]
+/// (and thus possibly another kimchi) are rejected by [proof_from_bytes].
+pub fn proof_to_bytes(proof: &ProverProof<Curve, OpeningProof<Curve>>) -> Vec<u8> {
+    let encoded = VestaConfig::encode_proof(proof);
+
+    let mut bytes = Vec::with_capacity(PROOF_HEADER_LEN + encoded.len());
+    bytes.extend_from_slice(PROOF_MAGIC);
+    bytes.extend_from_slice(&PROOF_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+    bytes.extend(encoded);
+    bytes
+}
+
+/// Deserializes a proof that was serialized with [proof_to_bytes].
+pub fn proof_from_bytes(bytes: &[u8]) -> miette::Result<ProverProof<Curve, OpeningProof<Curve>>> {
+    if bytes.len() < PROOF_HEADER_LEN {
+        miette::bail!(
+            "the serialized proof is truncated: it has {} bytes, but its header alone has {PROOF_HEADER_LEN} bytes",
+            bytes.len()
+        );
+    }
+
+    let (magic, rest) = bytes.split_at(PROOF_MAGIC.len());
+    if magic != PROOF_MAGIC {
+        miette::bail!("the bytes are not a serialized proof (they don't start with `nnpf`)");
+    }
+
+    let (version, rest) = rest.split_at(4);
+    let version = u32::from_le_bytes(version.try_into().expect("the version has 4 bytes"));
+    if version != PROOF_VERSION {
+        miette::bail!(
+            "the proof was serialized with version {version}, but version {PROOF_VERSION} was expected"
+        );
+    }
+
+    let (len, encoded) = rest.split_at(8);
+    let len = u64::from_le_bytes(len.try_into().expect("the length has 8 bytes"));
+    if encoded.len() as u64 != len {
+        miette::bail!(
+            "the serialized proof is truncated or has trailing bytes: the encoded proof should have {len} bytes, but it has {} bytes",
+            encoded.len()
+        );
+    }
+
+    rmp_serde::from_slice(encoded)
+        .into_diagnostic()
+        .wrap_err("could not deserialize the proof")
+}
+
+impl ProverIndex<VestaConfig> {
+    /// Saves the prover index to disk.
+    /// Note that hints can't be serialized, so the compiled circuit is not saved;
+    /// it must be passed again to [Self::load].
+    pub fn save(&self, path: impl AsRef<Path>) -> miette::Result<()> {
+        save_prover_params(&self.index.cs, path)
+    }
+
+    /// Loads a prover index that was saved with [Self::save].
+    /// The given compiled circuit must be the one that was used to create the saved prover index.
+    pub fn load(
+        path: impl AsRef<Path>,
+        compiled_circuit: CompiledCircuit<KimchiVesta>,
+    ) -> miette::Result<Self> {
+        let bytes = std::fs::read(path)
+            .into_diagnostic()
+            .wrap_err("could not read the prover index from disk")?;
+
+        let serialized: SerializedProverIndex = rmp_serde::from_slice(&bytes)
+            .into_diagnostic()
+            .wrap_err("could not deserialize the prover index")?;
+
+        if serialized.version != PROVER_INDEX_VERSION {
+            miette::bail!(
+                "the prover index was saved with version {}, but version {PROVER_INDEX_VERSION} was expected",
+                serialized.version
+            );
+        }
+
+        // make sure that the saved constraint system matches the compiled circuit
+        // (note that kimchi pads the constraint system with zero gates)
+        let backend = &compiled_circuit.circuit.backend;
+        let gates = backend.to_kimchi_gates();
+        if serialized.cs.public != backend.public_input_size
+            || serialized.cs.gates.len() < gates.len()
+            || serialized.cs.gates[..gates.len()] != gates[..]
+        {
+            miette::bail!("the saved prover index does not match the given compiled circuit");
+        }
+
+        let srs = SharedSrs::<VestaConfig>::new(serialized.cs.domain.d1.size as usize)
+            .for_circuit(&serialized.cs)?;
+        let index = VestaConfig::create_prover_index(serialized.cs, srs);
+
+        Ok(Self {
+            index,
+            compiled_circuit,
+        })
+    }
+
+    /// Returns the constraint system of the prover index (what [Self::save] writes to disk).
+    pub fn constraint_system(&self) -> &ConstraintSystem<VestaField> {
+        &self.index.cs
+    }
+}
+
+impl<C> ProverIndex<C>
+where
+    C: KimchiCurveConfig,
+{
     pub fn asm(&self, sources: &Sources, debug: bool) -> String {
         self.compiled_circuit.asm(sources, debug)
     }
 
     pub fn len(&self) -> usize {
-        self.compiled_circuit.circuit.backend.gates.len()
+        self.compiled_circuit.num_gates()
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
+    pub fn gate_stats(&self) -> GateStats {
+        self.compiled_circuit.gate_stats()
+    }
+
+    /// See [CompiledCircuit::public_layout].
+    pub fn public_layout(&self) -> PublicLayout {
+        self.compiled_circuit.public_layout()
+    }
+
+    /// See [CompiledCircuit::public_output_to_json].
+    pub fn public_output_to_json(
+        &self,
+        public_output: &[C::Field],
+    ) -> std::result::Result<serde_json::Value, ParsingError> {
+        self.compiled_circuit.public_output_to_json(public_output)
+    }
+
+    /// See [CompiledCircuit::encode_public_values].
+    pub fn encode_public_values(
+        &self,
+        public_inputs: JsonInputs,
+        public_output: Option<serde_json::Value>,
+    ) -> std::result::Result<Vec<C::Field>, ParsingError> {
+        self.compiled_circuit
+            .encode_public_values(public_inputs, public_output)
+    }
+
+    /// Returns the sizes of a proof created with this prover index (see [ProofMetrics]).
+    pub fn metrics(&self, proof: &C::Proof) -> ProofMetrics {
+        ProofMetrics {
+            proof_size: PROOF_HEADER_LEN + C::encode_proof(proof).len(),
+            srs_size: C::prover_srs_size(&self.index),
+            domain_size: C::prover_domain_size(&self.index),
+        }
+    }
+
+    /// Generates the witness on the given inputs, directly in kimchi format,
+    /// to inspect it (see [Self::witness_to_csv]) before proving with it (see [Self::prove_witness]).
+    pub fn generate_witness(
+        &self,
+        sources: &Sources,
+        public_inputs: JsonInputs,
+        private_inputs: JsonInputs,
+    ) -> miette::Result<StreamedWitness<C::Field>> {
+        self.compiled_circuit
+            .generate_witness_streaming(public_inputs, private_inputs)
+            .into_miette(sources)
+    }
+
+    /// Writes a witness generated with [Self::generate_witness] as CSV (see [Kimchi::witness_to_csv]).
+    pub fn witness_to_csv(&self, generated_witness: &StreamedWitness<C::Field>) -> String {
+        self.compiled_circuit
+            .circuit
+            .backend
+            .witness_to_csv(&generated_witness.columns)
+    }
+
     /// returns a proof and a public output
     #[allow(clippy::type_complexity)]
     pub fn prove(
@@ -169,39 +693,33 @@ impl ProverIndex {
         public_inputs: JsonInputs,
         private_inputs: JsonInputs,
         debug: bool,
-    ) -> miette::Result<(
-        ProverProof<Curve, OpeningProof<Curve>>,
-        Vec<VestaField>,
-        Vec<VestaField>,
-    )> {
-        // generate the witness
-        let generated_witness = generate_witness(
-            &self.compiled_circuit,
-            sources,
-            public_inputs,
-            private_inputs,
-        )?;
+    ) -> miette::Result<(C::Proof, Vec<C::Field>, Vec<C::Field>)> {
+        let generated_witness = self.generate_witness(sources, public_inputs, private_inputs)?;
+        self.prove_witness(generated_witness, debug)
+    }
 
+    /// Same as [Self::prove], with a witness generated with [Self::generate_witness].
+    #[allow(clippy::type_complexity)]
+    pub fn prove_witness(
+        &self,
+        generated_witness: StreamedWitness<C::Field>,
+        debug: bool,
+    ) -> miette::Result<(C::Proof, Vec<C::Field>, Vec<C::Field>)> {
         if debug {
             println!("# witness\n");
-            generated_witness.all_witness.debug();
+            generated_witness.debug();
         }
 
-        // convert to kimchi format
-        let witness = generated_witness.all_witness.to_kimchi_witness();
+        let witness = generated_witness.columns;
 
         // verify the witness
         if debug {
-            self.index
-                .verify(&witness, &generated_witness.full_public_inputs)
+            C::verify_witness(&self.index, &witness, &generated_witness.full_public_inputs)
                 .unwrap();
         }
 
         // create proof
-        let proof =
-            ProverProof::create::<BaseSponge, ScalarSponge>(&GROUP_MAP, witness, &[], &self.index)
-                .into_diagnostic()
-                .wrap_err("kimchi: could not create a proof with the given inputs")?;
+        let proof = C::prove(&self.index, witness)?;
 
         // return proof + public output
         Ok((
@@ -216,35 +734,222 @@ impl ProverIndex {
 // Verifying
 //
 
-impl VerifierIndex {
-    pub fn verify(
-        &self,
-        full_public_inputs: Vec<VestaField>,
-        proof: ProverProof<Curve, OpeningProof<Curve>>,
-    ) -> miette::Result<()> {
+impl<C> VerifierIndex<C>
+where
+    C: KimchiCurveConfig,
+{
+    pub fn verify(&self, full_public_inputs: Vec<C::Field>, proof: C::Proof) -> miette::Result<()> {
         // verify the proof
-        kimchi::verifier::verify::<Curve, BaseSponge, ScalarSponge, OpeningProof<Curve>>(
-            &GROUP_MAP,
-            &self.index,
-            &proof,
-            &full_public_inputs,
-        )
-        .into_diagnostic()
-        .wrap_err("kimchi: failed to verify the proof")
+        C::verify(&self.index, &proof, &full_public_inputs)
+    }
+}
+
+impl VerifierIndex<VestaConfig> {
+    /// Returns the JSON representation of the verifier index (see [VerifierIndexJson] for the schema),
+    /// which can be consumed by a verifier outside of noname.
+    pub fn to_json(&self) -> miette::Result<String> {
+        let json = self.to_json_repr()?;
+        serde_json::to_string_pretty(&json)
+            .into_diagnostic()
+            .wrap_err("could not serialize the verifier index to JSON")
+    }
+
+    /// Loads a verifier index from its JSON representation (see [Self::to_json]).
+    pub fn from_json(json: &str) -> miette::Result<Self> {
+        let json: VerifierIndexJson = serde_json::from_str(json)
+            .into_diagnostic()
+            .wrap_err("could not parse the JSON verifier index")?;
+
+        if json.version != VERIFIER_INDEX_JSON_VERSION {
+            miette::bail!(
+                "the verifier index was exported with version {}, but version {VERIFIER_INDEX_JSON_VERSION} was expected",
+                json.version
+            );
+        }
+
+        let mut index: kimchi::verifier_index::VerifierIndex<Curve, OpeningProof<Curve>> =
+            serde_json::from_value(json.index.clone())
+                .into_diagnostic()
+                .wrap_err("could not deserialize the kimchi verifier index")?;
+
+        // kimchi doesn't serialize the SRS (which only depends on its size, larger than the domain if it was shared),
+        // nor what can be recomputed from the rest of the index
+        let mut srs = SRS::<Curve>::create(index.max_poly_size);
+        srs.add_lagrange_basis(index.domain);
+        index.srs = std::sync::Arc::new(srs);
+
+        let (endo_q, _endo_r) = kimchi::poly_commitment::srs::endos::<OtherCurve>();
+        index.endo = endo_q;
+
+        let feature_flags = FeatureFlags {
+            range_check0: index.range_check0_comm.is_some(),
+            range_check1: index.range_check1_comm.is_some(),
+            foreign_field_add: index.foreign_field_add_comm.is_some(),
+            foreign_field_mul: index.foreign_field_mul_comm.is_some(),
+            xor: index.xor_comm.is_some(),
+            rot: index.rot_comm.is_some(),
+            lookup_features: index
+                .lookup_index
+                .as_ref()
+                .map(|lookup_index| lookup_index.lookup_info.features)
+                .unwrap_or(LookupFeatures {
+                    patterns: LookupPatterns {
+                        xor: false,
+                        lookup: false,
+                        range_check: false,
+                        foreign_field_mul: false,
+                    },
+                    joint_lookup_used: false,
+                    uses_runtime_tables: false,
+                }),
+        };
+        let (linearization, powers_of_alpha) =
+            kimchi::linearization::expr_linearization(Some(&feature_flags), true);
+        index.linearization = linearization;
+        index.powers_of_alpha = powers_of_alpha;
+
+        // the readable fields must describe the index that was reloaded
+        let verifier_index = Self { index };
+        let expected = verifier_index.to_json_repr()?;
+        if json.domain_size != expected.domain_size
+            || json.public_input_size != expected.public_input_size
+            || json.max_poly_size != expected.max_poly_size
+            || json.commitments != expected.commitments
+            || json.shifts != expected.shifts
+        {
+            miette::bail!("the fields of the JSON verifier index don't match its kimchi index");
+        }
+
+        Ok(verifier_index)
+    }
+
+    fn to_json_repr(&self) -> miette::Result<VerifierIndexJson> {
+        let index = &self.index;
+
+        let to_value = |commitment| {
+            serde_json::to_value(commitment)
+                .into_diagnostic()
+                .wrap_err("could not serialize a commitment of the verifier index")
+        };
+
+        let mut commitments = BTreeMap::new();
+        for (idx, commitment) in index.sigma_comm.iter().enumerate() {
+            commitments.insert(format!("sigma_{idx}"), to_value(commitment)?);
+        }
+        for (idx, commitment) in index.coefficients_comm.iter().enumerate() {
+            commitments.insert(format!("coefficients_{idx}"), to_value(commitment)?);
+        }
+        for (name, commitment) in [
+            ("generic", &index.generic_comm),
+            ("psm", &index.psm_comm),
+            ("complete_add", &index.complete_add_comm),
+            ("mul", &index.mul_comm),
+            ("emul", &index.emul_comm),
+            ("endomul_scalar", &index.endomul_scalar_comm),
+        ] {
+            commitments.insert(name.to_string(), to_value(commitment)?);
+        }
+
+        let shifts = index
+            .shift
+            .iter()
+            .map(|shift| BigUint::from(*shift).to_string())
+            .collect();
+
+        Ok(VerifierIndexJson {
+            version: VERIFIER_INDEX_JSON_VERSION,
+            domain_size: index.domain.size,
+            public_input_size: index.public,
+            max_poly_size: index.max_poly_size,
+            commitments,
+            shifts,
+            index: serde_json::to_value(index)
+                .into_diagnostic()
+                .wrap_err("could not serialize the kimchi verifier index")?,
+        })
+    }
+
+    /// Verifies several proofs of the same circuit at once, combining their MSMs.
+    /// Each proof comes with its full public inputs, and gets its own result:
+    /// if the batch doesn't verify, the proofs are verified one by one to find the bad ones.
+    pub fn verify_batch(
+        &self,
+        items: Vec<(Vec<VestaField>, ProverProof<Curve, OpeningProof<Curve>>)>,
+    ) -> Vec<miette::Result<()>> {
+        let contexts: Vec<_> = items
+            .iter()
+            .map(|(full_public_inputs, proof)| kimchi::verifier::Context {
+                verifier_index: &self.index,
+                proof,
+                public_input: full_public_inputs,
+            })
+            .collect();
+
+        let batch = kimchi::verifier::batch_verify::<
+            Curve,
+            VestaBaseSponge,
+            VestaScalarSponge,
+            OpeningProof<Curve>,
+        >(&VESTA_GROUP_MAP, &contexts);
+
+        if batch.is_ok() {
+            return items.iter().map(|_| Ok(())).collect();
+        }
+
+        items
+            .into_iter()
+            .map(|(full_public_inputs, proof)| self.verify(full_public_inputs, proof))
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use kimchi::circuits::constraints::GateError;
+    use std::{collections::HashMap, sync::Arc};
+
+    use kimchi::circuits::{
+        constraints::GateError, polynomials::poseidon::POS_ROWS_PER_HASH, wires::Wire,
+    };
 
     use crate::{
-        backends::kimchi::{KimchiVesta, VestaField},
-        compiler::{compile, generate_witness, typecheck_next_file, Sources},
-        inputs::parse_inputs,
+        backends::{
+            kimchi::{
+                curves::VestaConfig, srs::SharedSrs, KimchiPallas, KimchiVesta, PallasField,
+                VestaField,
+            },
+            Backend,
+        },
+        circuit_writer::{CircuitOptions, GateKind, OptLevel},
+        compiler::{compile_with_options, generate_witness, typecheck_next_file, Sources},
+        error::{ErrorKind, WarningKind},
+        inputs::{parse_inputs, ExtField},
         type_checker::TypeChecker,
+        witness::CompiledCircuit,
     };
 
+    use super::{proof_from_bytes, proof_to_bytes, ProverIndex, PublicSlot, VerifierIndex};
+
+    /// Type checks and compiles `code`, returning the sources needed to prove it with the compiled circuit.
+    fn compile_code<B: Backend>(
+        code: &str,
+        backend: B,
+        options: CircuitOptions,
+    ) -> miette::Result<(Sources, CompiledCircuit<B>)> {
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "inline_test.no".to_string(),
+            code.to_owned(),
+            0,
+        )?;
+
+        let compiled_circuit = compile_with_options(&sources, tast, backend, options)?;
+        Ok((sources, compiled_circuit))
+    }
+
     #[test]
     fn test_public_output_constraint() -> miette::Result<()> {
         let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
@@ -254,24 +959,19 @@ mod tests {
             return yy;
         }"#;
 
-        let mut sources = Sources::new();
-        let mut tast = TypeChecker::new();
-        let this_module = None;
-        let _node_id = typecheck_next_file(
-            &mut tast,
-            this_module,
-            &mut sources,
-            "inline_test_output.no".to_string(),
-            code.to_owned(),
-            0,
-        )
-        .unwrap();
-
         let kimchi_vesta = KimchiVesta::new(false);
-        let compiled_circuit = compile(&sources, tast, kimchi_vesta)?;
+        let (sources, compiled_circuit) =
+            compile_code(code, kimchi_vesta, CircuitOptions::default())?;
+        assert_eq!(compiled_circuit.num_gates(), 6);
 
         let (prover_index, _) = compiled_circuit.compile_to_indexes().unwrap();
 
+        let stats = prover_index.gate_stats();
+        assert_eq!(stats.num_rows, 6);
+        assert_eq!(stats.gates, HashMap::from([(GateKind::DoubleGeneric, 6)]));
+        // the public output is asserted equal to `yy`, so they share a cycle
+        assert_eq!(stats.num_wiring_cycles, 3);
+
         // parse inputs
         let public_inputs = parse_inputs(r#"{"public_input": "1"}"#).unwrap();
         let private_inputs = parse_inputs(r#"{"private_input": "1"}"#).unwrap();
@@ -361,4 +1061,1072 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_public_layout() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: [Field; 2], private_input: Field) -> Field {
+            let xx = public_input[0] + public_input[1];
+            return xx * private_input;
+        }"#;
+
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+        let (prover_index, _) = compiled_circuit.compile_to_indexes().unwrap();
+
+        // the public output comes first, then the public input
+        let layout = prover_index.public_layout();
+        assert_eq!(layout.output, Some(PublicSlot { offset: 0, len: 1 }));
+        assert_eq!(
+            layout.inputs,
+            vec![("public_input".to_string(), PublicSlot { offset: 1, len: 2 })]
+        );
+
+        // which is where they are in the public input given to the verifier
+        let public_inputs = parse_inputs(r#"{"public_input": ["2", "3"]}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"private_input": "4"}"#).unwrap();
+        let (_proof, full_public_inputs, _public_output) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+
+        let field = |values: &[u64]| -> Vec<VestaField> {
+            values.iter().map(|&v| VestaField::from(v)).collect()
+        };
+        assert_eq!(full_public_inputs[0..1], field(&[20]));
+        assert_eq!(full_public_inputs[1..3], field(&[2, 3]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transitive_equalities_share_a_cycle() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, yy: Field, zz: Field) {
+            assert_eq(xx, yy);
+            assert_eq(yy, zz);
+            assert_eq(zz, xx);
+        }"#;
+
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+
+        // the last assertion is implied by the first two, so it doesn't create a gate
+        assert_eq!(compiled_circuit.num_gates(), 3);
+
+        // and all the cells of the three variables are wired in a single cycle
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+        assert_eq!(prover_index.gate_stats().num_wiring_cycles, 1);
+        let asm = prover_index.asm(&mut Sources::new(), false);
+        assert!(asm.ends_with("(0,0) -> (1,0) -> (1,1) -> (2,0) -> (2,1)\n"));
+
+        let public_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"yy": "3", "zz": "3"}"#).unwrap();
+        let (proof, full_public_inputs, _) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unchecked_public_input() -> miette::Result<()> {
+        let compile = |code| compile_code(code, KimchiVesta::new(false), CircuitOptions::default());
+
+        let checked = r#"fn main(pub flags: [Bool; 3], pub xx: Field) {
+            let yy = if flags[0] { xx } else { 0 };
+            assert_eq(yy, 3);
+        }"#;
+
+        let unchecked = r#"fn main(#[unchecked] pub flags: [Bool; 3], pub xx: Field) {
+            let yy = if flags[0] { xx } else { 0 };
+            assert_eq(yy, 3);
+        }"#;
+
+        let (_, checked_circuit) = compile(checked)?;
+        let (sources, unchecked_circuit) = compile(unchecked)?;
+
+        // checking a boolean takes three generic gates (`x - 1`, `x * (x - 1)`, and the assertion)
+        assert_eq!(
+            unchecked_circuit.num_gates() + 3 * 3,
+            checked_circuit.num_gates()
+        );
+
+        let (prover_index, verifier_index) = unchecked_circuit.compile_to_indexes().unwrap();
+        let public_inputs = parse_inputs(r#"{"flags": [true, false, true], "xx": "3"}"#).unwrap();
+        let private_inputs = parse_inputs("{}").unwrap();
+        let (proof, full_public_inputs, _) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wiring_dot() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input + public_input;
+            assert_eq(xx, 2);
+            let yy = xx + 6;
+            return yy;
+        }"#;
+
+        let (_, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+        let dot = compiled_circuit.to_dot();
+
+        assert!(dot.starts_with("digraph circuit {"));
+
+        // one cluster per row
+        assert_eq!(dot.matches("subgraph cluster_").count(), 6);
+
+        // one node per cell used:
+        // the public output (2 cells), the public input (2 cells), the private input (1 cell),
+        // `xx` (3 cells), and `yy` (2 cells)
+        assert_eq!(dot.matches(" [label=").count(), 10);
+
+        // one edge per cell in each of the 3 wiring cycles
+        assert_eq!(dot.matches(" -> ").count(), 9);
+
+        // the addition gate has its coefficients in the labels
+        assert!(dot.contains(r#"c2_2 [label="(2,2)\n-1", fillcolor=lightblue];"#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_identical_gates_are_deduplicated() -> miette::Result<()> {
+        let compile = |code, double_generic| {
+            compile_code(
+                code,
+                KimchiVesta::new(double_generic),
+                CircuitOptions::default(),
+            )
+        };
+
+        let once = r#"fn poly(xx: Field, cst: Field) -> Field {
+            return (xx * xx) + (xx * cst) + 5;
+        }
+
+        fn main(pub xx: Field) {
+            let aa = poly(xx, 3);
+            assert_eq(aa, 23);
+        }"#;
+
+        let twice = r#"fn poly(xx: Field, cst: Field) -> Field {
+            return (xx * xx) + (xx * cst) + 5;
+        }
+
+        fn main(pub xx: Field) {
+            let aa = poly(xx, 3);
+            let bb = poly(xx, 3);
+            assert_eq(aa, 23);
+            assert_eq(bb, 23);
+        }"#;
+
+        let (_, once_circuit) = compile(once, true)?;
+        let (sources, twice_circuit) = compile(twice, true)?;
+
+        // the second call reuses all the gates of the first one
+        assert_eq!(once_circuit.num_gates(), twice_circuit.num_gates());
+
+        let (prover_index, verifier_index) = twice_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+        let private_inputs = parse_inputs("{}").unwrap();
+        let (proof, full_public_inputs, _) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        // without the double generic gate optimization, each call keeps its own gates
+        let (_, once_circuit) = compile(once, false)?;
+        let (_, twice_circuit) = compile(twice, false)?;
+        assert!(twice_circuit.num_gates() > once_circuit.num_gates());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_equivalent_gates_are_deduplicated() -> miette::Result<()> {
+        let compile = |code| compile_code(code, KimchiVesta::new(true), CircuitOptions::default());
+
+        // `xx + xx` and `xx * 2` are the same gate once normalized
+        let once = r#"fn main(pub xx: Field) {
+            let aa = xx + xx;
+            assert_eq(aa, 6);
+        }"#;
+
+        let equivalent = r#"fn main(pub xx: Field) {
+            let aa = xx + xx;
+            let bb = xx * 2;
+            assert_eq(aa, 6);
+            assert_eq(bb, 6);
+        }"#;
+
+        let (_, once_circuit) = compile(once)?;
+        let (sources, equivalent_circuit) = compile(equivalent)?;
+
+        // the second expression reuses the cell of the first one
+        assert_eq!(once_circuit.num_gates(), equivalent_circuit.num_gates());
+
+        let (prover_index, verifier_index) = equivalent_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+        let private_inputs = parse_inputs("{}").unwrap();
+        let (proof, full_public_inputs, _) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unused_public_input_warning() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, pub dangling: [Field; 2], yy: Field) -> Field {
+            return xx + yy;
+        }"#;
+
+        let (_, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+
+        // the warning names the input once, even though it has two cells
+        assert_eq!(compiled_circuit.warnings.len(), 1);
+        assert!(matches!(
+            &compiled_circuit.warnings[0].kind,
+            WarningKind::UnusedPublicInput(name) if name == "dangling"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbolic_constraints() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, yy: Field) -> Field {
+            return xx * yy;
+        }"#;
+
+        // without packing the generic gates, so that each gate is a row
+        let options = CircuitOptions {
+            opt_level: OptLevel::O0,
+            ..Default::default()
+        };
+        let (_, compiled_circuit) = compile_code(code, KimchiVesta::new(false), options)?;
+
+        // the multiplication gate follows the public input rows, which are not constraints
+        let backend = &compiled_circuit.circuit.backend;
+        let cells: Vec<_> = backend.witness_table[backend.public_input_size]
+            .iter()
+            .map(|var| var.unwrap().index)
+            .collect();
+        let (aa, bb, cc) = (cells[0], cells[1], cells[2]);
+
+        // `aa * bb - cc == 0`
+        let constraints = compiled_circuit.constraints().unwrap();
+        let one = VestaField::from(1);
+        assert_eq!(constraints.len(), 2);
+        assert_eq!(
+            constraints[0].terms,
+            vec![(one, vec![aa, bb]), (-one, vec![cc])]
+        );
+
+        // followed by the public output being asserted equal to the product
+        assert!(constraints[1].terms.contains(&(-one, vec![cc])));
+        assert_eq!(constraints[1].terms.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbolic_constraints_unsupported_gate() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field) {
+            range_check64(xx);
+        }"#;
+
+        let (_, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+
+        // the range check can't be expressed as polynomials of its cells
+        let err = compiled_circuit.constraints().unwrap_err();
+        match err.kind {
+            ErrorKind::UnsupportedGateConstraints { row, gate } => {
+                assert_eq!(gate, GateKind::RangeCheck);
+                assert_eq!(
+                    compiled_circuit.circuit.backend.gates[row].typ,
+                    GateKind::RangeCheck
+                );
+            }
+            kind => panic!("expected an unsupported gate, got {kind:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trivially_satisfied_constraint_warning() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, yy: Field) -> Field {
+            let zz = xx + yy;
+            assert_eq(zz, xx + yy);
+            return zz * yy;
+        }"#;
+
+        let compile = |opt_level| {
+            let options = CircuitOptions {
+                opt_level,
+                ..Default::default()
+            };
+            compile_code(code, KimchiVesta::new(true), options)
+        };
+
+        // once the second addition is replaced by the first one, the assertion is `zz - zz = 0`
+        let (_, compiled_circuit) = compile(OptLevel::O2)?;
+        assert_eq!(compiled_circuit.warnings.len(), 1);
+        assert!(matches!(
+            compiled_circuit.warnings[0].kind,
+            WarningKind::TriviallySatisfiedConstraint
+        ));
+
+        // while it still relates two different variables without the elimination
+        let (_, compiled_circuit) = compile(OptLevel::O1)?;
+        assert!(compiled_circuit.warnings.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_optimize_levels() -> miette::Result<()> {
+        let code = r#"fn poly(xx: Field, cst: Field) -> Field {
+            return (xx * xx) + (xx * cst) + 5;
+        }
+
+        fn main(pub xx: Field) -> Field {
+            let aa = poly(xx, 3);
+            let bb = poly(xx, 3);
+            assert_eq(aa, 23);
+            return aa + bb;
+        }"#;
+
+        let mut num_gates = vec![];
+        for opt_level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+            let options = CircuitOptions {
+                opt_level,
+                ..Default::default()
+            };
+            let (sources, compiled_circuit) = compile_code(code, KimchiVesta::new(true), options)?;
+            num_gates.push(compiled_circuit.num_gates());
+
+            // the circuit is still valid
+            let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+            let public_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+            let private_inputs = parse_inputs("{}").unwrap();
+            let (proof, full_public_inputs, public_output) =
+                prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+            assert_eq!(public_output, vec![VestaField::from(46u64)]);
+            verifier_index.verify(full_public_inputs, proof)?;
+        }
+
+        // each level removes gates
+        assert!(num_gates.windows(2).all(|pair| pair[0] > pair[1]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_linear_gates() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, yy: Field) -> Field {
+            let mut acc = xx;
+            for ii in 0..10 {
+                acc = acc + yy;
+                acc = acc + 1;
+            }
+            return acc;
+        }"#;
+
+        let mut num_gates = vec![];
+        for opt_level in [OptLevel::O2, OptLevel::O3] {
+            // one gate per row, to count the merged gates
+            let options = CircuitOptions {
+                opt_level,
+                ..Default::default()
+            };
+            let (sources, compiled_circuit) = compile_code(code, KimchiVesta::new(false), options)?;
+            num_gates.push(compiled_circuit.num_gates());
+
+            // the same inputs still produce a valid proof
+            let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+            let public_inputs = parse_inputs(r#"{"xx": "1"}"#).unwrap();
+            let private_inputs = parse_inputs(r#"{"yy": "2"}"#).unwrap();
+            let (proof, full_public_inputs, public_output) =
+                prover_index.prove(&sources, public_inputs, private_inputs, true)?;
+            assert_eq!(public_output, vec![VestaField::from(31u64)]);
+            verifier_index.verify(full_public_inputs, proof)?;
+        }
+
+        // the rows of the public input and output, 20 operations, and the assertion on the output:
+        // every `+ 1` but the last one (whose result is asserted equal to the output) is merged into the next addition
+        assert_eq!(num_gates, vec![23, 14]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_double_gate() -> miette::Result<()> {
+        let compile = |code, double_generic| {
+            compile_code(
+                code,
+                KimchiVesta::new(double_generic),
+                CircuitOptions::default(),
+            )
+        };
+
+        // three multiplications and an assertion, after the row of the public input
+        let code = r#"fn main(pub xx: Field, yy: Field) {
+            let aa = xx * yy;
+            let bb = aa * yy;
+            let cc = bb * yy;
+            assert_eq(cc, 54);
+        }"#;
+        let (_, paired) = compile(code, true)?;
+        let (_, unpaired) = compile(code, false)?;
+        assert_eq!(paired.num_gates(), 3);
+        assert_eq!(unpaired.num_gates(), 5);
+
+        // the first multiplication can't wait for the second one, which is alone on its row,
+        // but the last two gates are still paired
+        let code = r#"fn main(pub xx: Field, yy: Field) {
+            let aa = xx * yy;
+            #[no_double_gate]
+            let bb = aa * yy;
+            let cc = bb * yy;
+            assert_eq(cc, 54);
+        }"#;
+        let (sources, compiled_circuit) = compile(code, true)?;
+        assert_eq!(compiled_circuit.num_gates(), 4);
+
+        let debug_info = &compiled_circuit.circuit.backend.debug_info;
+        let spans: Vec<_> = debug_info[1..3]
+            .iter()
+            .map(|info| info.span.start)
+            .collect();
+        assert_eq!(
+            spans,
+            vec![code.find("xx * yy").unwrap(), code.find("aa * yy").unwrap()]
+        );
+
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+        let public_inputs = parse_inputs(r#"{"xx": "2"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"yy": "3"}"#).unwrap();
+        let (proof, full_public_inputs, _public_output) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        // a function marked `#[no_double_gate]` has the layout of a circuit without the optimization
+        let code = r#"#[no_double_gate]
+        fn main(pub xx: Field, yy: Field) {
+            let aa = xx * yy;
+            let bb = aa * yy;
+            let cc = bb * yy;
+            assert_eq(cc, 54);
+        }"#;
+        let (_, compiled_circuit) = compile(code, true)?;
+        assert_eq!(compiled_circuit.num_gates(), unpaired.num_gates());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert_rows() -> miette::Result<()> {
+        let compile = |code| compile_code(code, KimchiVesta::new(false), CircuitOptions::default());
+
+        let code = r#"fn main(pub xx: Field, yy: Field) {
+            assert_eq(xx + yy, 3);
+        }"#;
+        let (_, compiled_circuit) = compile(code)?;
+        let rows = compiled_circuit.num_gates();
+        compiled_circuit.assert_rows(rows)?;
+
+        // an extra operation adds a row, which breaks the assertion
+        let code = r#"fn main(pub xx: Field, yy: Field) {
+            assert_eq(xx + yy + yy, 3);
+        }"#;
+        let (_, compiled_circuit) = compile(code)?;
+        assert_eq!(compiled_circuit.num_gates(), rows + 1);
+        let err = compiled_circuit.assert_rows(rows).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "the circuit has {} rows, but {rows} rows were expected",
+                rows + 1
+            )
+        );
+
+        Ok(())
+    }
+
+    /// A temporary file, removed when dropped (even if the test fails).
+    struct TempFile(std::path::PathBuf);
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_prover_index_save_load() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input + public_input;
+            assert_eq(xx, 2);
+            return xx + 6;
+        }"#;
+
+        let compile = || compile_code(code, KimchiVesta::new(false), CircuitOptions::default());
+
+        // save the prover index
+        let (_, compiled_circuit) = compile()?;
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+        let temp_file = TempFile(std::env::temp_dir().join(format!(
+            "noname_test_prover_index_{}.bin",
+            std::process::id()
+        )));
+        prover_index.save(&temp_file.0)?;
+
+        // load it with a freshly compiled circuit
+        let (sources, compiled_circuit) = compile()?;
+        let loaded_index = ProverIndex::load(&temp_file.0, compiled_circuit)?;
+
+        // prove with the loaded index, and verify with the original verifier index
+        let public_inputs = parse_inputs(r#"{"public_input": "1"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"private_input": "1"}"#).unwrap();
+        let (proof, full_public_inputs, public_output) =
+            loaded_index.prove(&sources, public_inputs, private_inputs, false)?;
+        assert_eq!(public_output, vec![VestaField::from(8u64)]);
+
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verifier_index_json() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input + public_input;
+            assert_eq(xx, 3);
+            return xx * private_input;
+        }"#;
+
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let json = verifier_index.to_json()?;
+
+        // the readable fields
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["public_input_size"], 2);
+        assert!(!value["commitments"]["generic"].is_null());
+
+        // round trip
+        let reloaded = VerifierIndex::from_json(&json)?;
+        assert_eq!(reloaded.to_json()?, json);
+
+        // the reloaded index verifies proofs
+        let public_inputs = parse_inputs(r#"{"public_input": "1"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"private_input": "2"}"#).unwrap();
+        let (proof, full_public_inputs, _) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        reloaded.verify(full_public_inputs, proof)?;
+
+        // the readable fields can't be changed independently of the index
+        let mut tampered = value.clone();
+        tampered["public_input_size"] = 3.into();
+        assert!(VerifierIndex::from_json(&tampered.to_string()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verifier_index_json_shared_srs() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input + public_input;
+            assert_eq(xx, 3);
+            return xx * private_input;
+        }"#;
+
+        // an SRS larger than the domain of the circuit
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+        let srs = SharedSrs::<VestaConfig>::new(1 << 8);
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes_with_srs(&srs)?;
+        assert!(prover_index.constraint_system().domain.d1.size < 1 << 8);
+
+        let json = verifier_index.to_json()?;
+        let reloaded = VerifierIndex::from_json(&json)?;
+        assert_eq!(reloaded.to_json()?, json);
+
+        // the reloaded index verifies proofs made with the shared SRS
+        let public_inputs = parse_inputs(r#"{"public_input": "1"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"private_input": "2"}"#).unwrap();
+        let (proof, full_public_inputs, _) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        reloaded.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_bytes() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input + public_input;
+            assert_eq(xx, 3);
+            return xx * private_input;
+        }"#;
+
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"public_input": "1"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"private_input": "2"}"#).unwrap();
+        let (proof, full_public_inputs, _) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+
+        // round trip
+        let bytes = proof_to_bytes(&proof);
+        assert_eq!(&bytes[..4], b"nnpf");
+        let reloaded = proof_from_bytes(&bytes)?;
+        assert_eq!(proof_to_bytes(&reloaded), bytes);
+        verifier_index.verify(full_public_inputs, reloaded)?;
+
+        // truncated inputs, inside and after the header
+        assert!(proof_from_bytes(&bytes[..10]).is_err());
+        assert!(proof_from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        // another version of the format
+        let mut other_version = bytes.clone();
+        other_version[4] += 1;
+        let err = proof_from_bytes(&other_version).unwrap_err();
+        assert!(err.to_string().contains("version 2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_metrics() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input + public_input;
+            assert_eq(xx, 3);
+            return xx * private_input;
+        }"#;
+
+        let metrics = |srs: Option<SharedSrs<VestaConfig>>| -> miette::Result<_> {
+            let (sources, compiled_circuit) =
+                compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+            let (prover_index, _verifier_index) = match srs {
+                Some(srs) => compiled_circuit.compile_to_indexes_with_srs(&srs)?,
+                None => compiled_circuit.compile_to_indexes()?,
+            };
+
+            let public_inputs = parse_inputs(r#"{"public_input": "1"}"#).unwrap();
+            let private_inputs = parse_inputs(r#"{"private_input": "2"}"#).unwrap();
+            let (proof, _, _) =
+                prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+
+            let domain_size = prover_index.constraint_system().domain.d1.size as usize;
+            assert_eq!(
+                prover_index.metrics(&proof).proof_size,
+                proof_to_bytes(&proof).len()
+            );
+            Ok((prover_index.metrics(&proof), domain_size))
+        };
+
+        // the SRS created for the circuit has as many group elements as the domain has points
+        let (metrics_of_circuit, domain_size) = metrics(None)?;
+        assert_eq!(metrics_of_circuit.domain_size, domain_size);
+        assert_eq!(metrics_of_circuit.srs_size, domain_size);
+
+        // but a shared SRS can be larger
+        let (shared_metrics, _) = metrics(Some(SharedSrs::new(4 * domain_size)))?;
+        assert_eq!(shared_metrics.domain_size, domain_size);
+        assert_eq!(shared_metrics.srs_size, 4 * domain_size);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_poseidon_native_gates() -> miette::Result<()> {
+        let code = r#"use std::crypto;
+
+        fn main(pub digest: Field, preimage: [Field; 2]) {
+            let res = crypto::poseidon(preimage);
+            assert_eq(res, digest);
+        }"#;
+
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+
+        // the hash is not unrolled into generic gates
+        let stats = compiled_circuit.gate_stats();
+        assert_eq!(stats.gates[&GateKind::Poseidon], POS_ROWS_PER_HASH);
+        assert_eq!(stats.gates[&GateKind::Zero], 1);
+
+        // the circuit must agree with the reference implementation of the sponge
+        let digest = crate::helpers::poseidon(&[VestaField::from(3u64), VestaField::from(7u64)]);
+        let public_inputs =
+            parse_inputs(&format!(r#"{{"digest": "{}"}}"#, digest.to_dec_string())).unwrap();
+        let private_inputs = parse_inputs(r#"{"preimage": ["3", "7"]}"#).unwrap();
+
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+        let (proof, full_public_inputs, _public_output) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_poseidon_input_lengths() -> miette::Result<()> {
+        for len in [1, 3, 4, 5] {
+            let code = format!(
+                r#"use std::crypto;
+
+                fn main(pub digest: Field, preimage: [Field; {len}]) {{
+                    assert_eq(crypto::poseidon(preimage), digest);
+                }}"#
+            );
+
+            let (sources, compiled_circuit) =
+                compile_code(&code, KimchiVesta::new(false), CircuitOptions::default())?;
+
+            // the state is permuted once per two elements absorbed
+            let permutations = len.div_ceil(2);
+            let stats = compiled_circuit.gate_stats();
+            assert_eq!(
+                stats.gates[&GateKind::Poseidon],
+                permutations * POS_ROWS_PER_HASH
+            );
+            assert_eq!(stats.gates[&GateKind::Zero], permutations);
+
+            let preimage: Vec<_> = (1..=len as u64).map(VestaField::from).collect();
+            let digest = crate::helpers::poseidon(&preimage);
+            let preimage: Vec<_> = preimage
+                .iter()
+                .map(|elem| format!(r#""{}""#, elem.to_dec_string()))
+                .collect();
+            let public_inputs =
+                parse_inputs(&format!(r#"{{"digest": "{}"}}"#, digest.to_dec_string())).unwrap();
+            let private_inputs =
+                parse_inputs(&format!(r#"{{"preimage": [{}]}}"#, preimage.join(", "))).unwrap();
+
+            let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+            let (proof, full_public_inputs, _public_output) =
+                prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+            verifier_index.verify(full_public_inputs, proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_struct_public_output() -> miette::Result<()> {
+        let code = r#"struct Inner {
+            yy: [Field; 2],
+        }
+
+        struct Thing {
+            xx: Field,
+            inner: Inner,
+        }
+
+        fn main(pub public_input: Field, private_input: Field) -> Thing {
+            let aa = private_input + public_input;
+            let bb = aa + 1;
+            let inner = Inner { yy: [bb, private_input] };
+            return Thing { xx: aa, inner: inner };
+        }"#;
+
+        // the double generic gate optimization must not pack public outputs together
+        let kimchi_vesta = KimchiVesta::new(true);
+        let (sources, compiled_circuit) =
+            compile_code(code, kimchi_vesta, CircuitOptions::default())?;
+
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"public_input": "2"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"private_input": "3"}"#).unwrap();
+
+        let (proof, full_public_inputs, public_outputs) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+
+        // the public output is flattened in field order
+        let expected_outputs: Vec<VestaField> = [5u64, 6, 3].map(VestaField::from).to_vec();
+        assert_eq!(public_outputs, expected_outputs);
+
+        // the full public input contains the expanded public output followed by the public input
+        assert_eq!(full_public_inputs.len(), 4);
+        assert_eq!(full_public_inputs[..3], expected_outputs[..]);
+        assert_eq!(full_public_inputs[3], VestaField::from(2u64));
+
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tuple_public_output() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> (Field, [Field; 2]) {
+            let sum = private_input + public_input;
+            let prod = private_input * public_input;
+            return (prod, [sum, private_input]);
+        }"#;
+
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(true), CircuitOptions::default())?;
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"public_input": "2"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"private_input": "3"}"#).unwrap();
+
+        let (proof, full_public_inputs, public_outputs) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+
+        // the outputs are in declaration order, followed by the public input
+        let expected_outputs: Vec<VestaField> = [6u64, 5, 3].map(VestaField::from).to_vec();
+        assert_eq!(public_outputs, expected_outputs);
+        assert_eq!(full_public_inputs[..3], expected_outputs[..]);
+        assert_eq!(full_public_inputs[3], VestaField::from(2u64));
+
+        // the verifier rejects the outputs in another order
+        let mut swapped = full_public_inputs.clone();
+        swapped.swap(0, 1);
+        assert!(verifier_index.verify(swapped, proof.clone()).is_err());
+
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsatisfied_constraint() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, yy: Field) {
+            let zz = xx * yy;
+            assert_eq(zz, 10);
+        }"#;
+
+        let (_, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+
+        let public_inputs = parse_inputs(r#"{"xx": "2"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"yy": "3"}"#).unwrap();
+        let err = compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap_err();
+
+        assert!(matches!(
+            err.kind,
+            ErrorKind::UnsatisfiedConstraint {
+                gate: GateKind::DoubleGeneric,
+                ..
+            }
+        ));
+
+        // the error points to the assertion
+        let failing_code = &code[err.span.start..err.span.end()];
+        assert!(failing_code.starts_with("assert_eq"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_batch() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input + public_input;
+            assert_eq(xx, 2);
+            return xx * public_input;
+        }"#;
+
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let mut items = vec![];
+        for (public_input, private_input) in [(1, 1), (2, 0)] {
+            let public_inputs =
+                parse_inputs(&format!(r#"{{"public_input": "{public_input}"}}"#)).unwrap();
+            let private_inputs =
+                parse_inputs(&format!(r#"{{"private_input": "{private_input}"}}"#)).unwrap();
+            let (proof, full_public_inputs, _) =
+                prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+            items.push((full_public_inputs, proof));
+        }
+
+        // both proofs are valid
+        let results = verifier_index.verify_batch(items.clone());
+        assert!(results.iter().all(Result::is_ok));
+
+        // tamper with the public output of the second proof
+        items[1].0[0] += VestaField::from(1u64);
+
+        let results = verifier_index.verify_batch(items);
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pallas_prove_and_verify() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input * public_input;
+            assert_eq(xx, 6);
+            return xx + public_input;
+        }"#;
+
+        // the same circuit, over the scalar field of pallas
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiPallas::new(false), CircuitOptions::default())?;
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"public_input": "2"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"private_input": "3"}"#).unwrap();
+        let (proof, full_public_inputs, public_outputs) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        assert_eq!(public_outputs, vec![PallasField::from(8u64)]);
+
+        // the proof doesn't verify with another public output
+        let mut tampered = full_public_inputs.clone();
+        tampered[0] += PallasField::from(1u64);
+        assert!(verifier_index.verify(tampered, proof.clone()).is_err());
+
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_srs() -> miette::Result<()> {
+        let compile = |code| compile_code(code, KimchiVesta::new(false), CircuitOptions::default());
+
+        let small = r#"fn main(pub xx: Field, yy: Field) {
+            assert_eq(xx * yy, 6);
+        }"#;
+        let large = r#"fn main(pub xx: Field, yy: Field) {
+            let mut acc = yy;
+            for ii in 0..40 {
+                acc = acc * yy;
+            }
+            assert_eq(acc, xx);
+        }"#;
+
+        // one SRS for both circuits, with domains of different sizes
+        let srs = SharedSrs::<VestaConfig>::new(1 << 8);
+        let mut domain_sizes = vec![];
+        for (code, public_inputs) in [(small, r#"{"xx": "6"}"#), (large, r#"{"xx": "1"}"#)] {
+            let (sources, compiled_circuit) = compile(code)?;
+            let (prover_index, verifier_index) =
+                compiled_circuit.compile_to_indexes_with_srs(&srs)?;
+            domain_sizes.push(prover_index.constraint_system().domain.d1.size);
+
+            let public_inputs = parse_inputs(public_inputs).unwrap();
+            let private_inputs = parse_inputs(r#"{"yy": "1"}"#).unwrap();
+            let (proof, full_public_inputs, _public_output) =
+                prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+            verifier_index.verify(full_public_inputs, proof)?;
+        }
+        assert!(domain_sizes[0] < domain_sizes[1]);
+        assert_eq!(srs.size(), 1 << 8);
+
+        // circuits with domains of the same size share the SRS with its Lagrange basis
+        let (_sources, compiled_circuit) = compile(small)?;
+        let (first, _) = compiled_circuit.compile_to_indexes_with_srs(&srs)?;
+        let (_sources, compiled_circuit) = compile(small)?;
+        let (second, _) = compiled_circuit.compile_to_indexes_with_srs(&srs)?;
+        assert!(Arc::ptr_eq(&first.index.srs, &second.index.srs));
+
+        // an SRS smaller than the domain of the circuit is rejected
+        let (_sources, compiled_circuit) = compile(large)?;
+        let err = compiled_circuit
+            .compile_to_indexes_with_srs(&SharedSrs::new(4))
+            .err()
+            .expect("expected an error");
+        assert!(err
+            .to_string()
+            .contains("the SRS is too small for this circuit"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wiring_cycles() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, yy: Field) -> Field {
+            let zz = xx * yy;
+            assert_eq(zz + xx, 6);
+            return zz + yy;
+        }"#;
+
+        let (_, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+
+        // every wired cell belongs to a single cycle of at least two cells
+        let cycles = compiled_circuit.wiring_cycles();
+        assert!(!cycles.is_empty());
+        assert!(cycles.iter().all(|cycle| cycle.len() > 1));
+        let mut cells: Vec<_> = cycles.iter().flatten().collect();
+        let num_cells = cells.len();
+        cells.sort();
+        cells.dedup();
+        assert_eq!(cells.len(), num_cells);
+
+        // wiring each cell to the previous one in its cycle gives the wires of the constraint system
+        let mut wires: Vec<_> = (0..compiled_circuit.num_gates())
+            .map(Wire::for_row)
+            .collect();
+        for cycle in &cycles {
+            for (ii, &(row, col)) in cycle.iter().enumerate() {
+                let (prev_row, prev_col) = cycle[(ii + cycle.len() - 1) % cycle.len()];
+                wires[row][col] = Wire {
+                    row: prev_row,
+                    col: prev_col,
+                };
+            }
+        }
+
+        let (prover_index, _verifier_index) = compiled_circuit.compile_to_indexes()?;
+        let gates = &prover_index.constraint_system().gates;
+        for (row, wires) in wires.iter().enumerate() {
+            assert_eq!(&gates[row].wires, wires, "row {row}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_witness_to_csv() -> miette::Result<()> {
+        let code = r#"fn main(pub public_input: Field, private_input: Field) -> Field {
+            let xx = private_input + public_input;
+            assert_eq(xx, 2);
+            let yy = xx + 6;
+            return yy;
+        }"#;
+
+        let (sources, compiled_circuit) =
+            compile_code(code, KimchiVesta::new(false), CircuitOptions::default())?;
+        let (prover_index, _) = compiled_circuit.compile_to_indexes()?;
+
+        let public_inputs = parse_inputs(r#"{"public_input": "1"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"private_input": "1"}"#).unwrap();
+        let witness = prover_index.generate_witness(&sources, public_inputs, private_inputs)?;
+        let csv = prover_index.witness_to_csv(&witness);
+
+        // a header, then one line per row, the first one holding the public output
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "row,gate,col0,col1,col2,col3,col4,col5,col6,col7,col8,col9,col10,col11,col12,col13,col14"
+        );
+        assert_eq!(lines[1], "0,DoubleGeneric,8,0,0,0,0,0,0,0,0,0,0,0,0,0,0");
+        assert_eq!(lines.len(), prover_index.compiled_circuit.num_gates() + 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,267 @@
+//! Recordings of the gates created by function calls, which can be spliced in the circuit again
+//! for another call of the same function (see [Backend::start_recording]).
+//!
+//! A recording lists what the kimchi backend did during the call, in order:
+//! the variables it created, the constants it used, the gates it added, and the variables it asserted equal.
+//! Splicing a recording does the same again, with new variables in place of the ones it created
+//! and the arguments of the new call in place of its inputs.
+//! The rows of the spliced gates follow the current ones, and their cells are wired like the ones of any other gate.
+
+use std::collections::HashMap;
+
+use crate::{
+    backends::Backend,
+    circuit_writer::{DebugInfo, GateKind},
+    constants::Span,
+    var::Value,
+};
+
+use super::{curves::KimchiCurveConfig, GenericGate, Kimchi, KimchiCellVar};
+
+/// The gates created by a function call (see the [module documentation](self)).
+#[derive(Clone)]
+pub struct Recording<C>
+where
+    C: KimchiCurveConfig,
+{
+    /// The variables passed to the function.
+    inputs: Vec<KimchiCellVar>,
+
+    /// The first variable created during the recording.
+    /// The variables before it that are not inputs (like constants) are kept when splicing.
+    first_var: usize,
+
+    /// What the backend did during the recording, in order.
+    ops: Vec<RecordedOp<C>>,
+
+    /// Whether a variable is computed by a hint,
+    /// whose closure refers to the recorded variables and thus can't be spliced.
+    has_hints: bool,
+}
+
+impl<C> std::fmt::Debug for Recording<C>
+where
+    C: KimchiCurveConfig,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recording")
+            .field("inputs", &self.inputs)
+            .field("first_var", &self.first_var)
+            .field("ops", &self.ops.len())
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum RecordedOp<C>
+where
+    C: KimchiCurveConfig,
+{
+    /// A variable created by [Backend::new_internal_var].
+    Var(KimchiCellVar, Value<Kimchi<C>>),
+
+    /// A constant returned by [Backend::add_constant],
+    /// which a splice asks for again so that the constant is still only constrained once.
+    Constant {
+        var: KimchiCellVar,
+        label: Option<&'static str>,
+        value: C::Field,
+        span: Span,
+    },
+
+    /// A gate, along with its generic gate (filled when the recording stops).
+    Gate {
+        row: usize,
+        typ: GateKind,
+        vars: Vec<Option<KimchiCellVar>>,
+        coeffs: Vec<C::Field>,
+        debug_info: DebugInfo,
+        generic: Option<GenericGate>,
+    },
+
+    /// A call to [Backend::assert_eq_var], which a splice makes again
+    /// as its gate depends on whether the variables are already known to be equal.
+    AssertEq {
+        lhs: KimchiCellVar,
+        rhs: KimchiCellVar,
+        span: Span,
+        label: Option<String>,
+        message: Option<String>,
+    },
+}
+
+impl<C> Kimchi<C>
+where
+    C: KimchiCurveConfig,
+{
+    /// Adds an operation to the recordings in progress, if any.
+    /// The operation is only created if it's needed.
+    pub(super) fn record(&mut self, op: impl FnOnce() -> RecordedOp<C>) {
+        if self.recordings.is_empty() {
+            return;
+        }
+
+        let op = op();
+        if let RecordedOp::Var(_, Value::Hint(_)) = &op {
+            for recording in &mut self.recordings {
+                recording.has_hints = true;
+            }
+        }
+        for recording in &mut self.recordings {
+            recording.ops.push(op.clone());
+        }
+    }
+
+    /// Runs `f` without recording what it does, for the operations that are recorded as a whole.
+    pub(super) fn unrecorded<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let recordings = std::mem::take(&mut self.recordings);
+        let res = f(self);
+        self.recordings = recordings;
+        res
+    }
+
+    /// See [Backend::start_recording].
+    pub(super) fn push_recording(&mut self, inputs: &[KimchiCellVar]) {
+        self.recordings.push(Recording {
+            inputs: inputs.to_vec(),
+            first_var: self.next_variable,
+            ops: vec![],
+            has_hints: false,
+        });
+    }
+
+    /// See [Backend::stop_recording].
+    pub(super) fn pop_recording(&mut self) -> Option<Recording<C>> {
+        let mut recording = self
+            .recordings
+            .pop()
+            .expect("kimchi: no recording was started");
+        if recording.has_hints {
+            return None;
+        }
+
+        // the generic gates are registered right after their gate is added
+        for op in &mut recording.ops {
+            if let RecordedOp::Gate { row, generic, .. } = op {
+                *generic = self.generic_gates.get(row).cloned();
+            }
+        }
+
+        Some(recording)
+    }
+
+    /// See [Backend::splice].
+    pub(super) fn splice_recording(
+        &mut self,
+        recording: &Recording<C>,
+        inputs: &[KimchiCellVar],
+        vars: &[KimchiCellVar],
+    ) -> Vec<KimchiCellVar> {
+        assert_eq!(recording.inputs.len(), inputs.len());
+        assert!(!recording.has_hints);
+
+        // the recorded variables, and the ones replacing them
+        let mut renamed: HashMap<usize, KimchiCellVar> = recording
+            .inputs
+            .iter()
+            .map(|var| var.index)
+            .zip(inputs.iter().copied())
+            .collect();
+        let rename = |renamed: &HashMap<usize, KimchiCellVar>, var: &KimchiCellVar| match renamed
+            .get(&var.index)
+        {
+            Some(new_var) => *new_var,
+            None => {
+                assert!(
+                    var.index < recording.first_var,
+                    "kimchi: a recorded variable is used before it is created"
+                );
+                *var
+            }
+        };
+
+        for op in &recording.ops {
+            match op {
+                RecordedOp::Var(var, value) => {
+                    let value = rename_value(value, |var| rename(&renamed, var));
+                    let new_var = self.new_internal_var(value, var.span);
+                    renamed.insert(var.index, new_var);
+                }
+                RecordedOp::Constant {
+                    var,
+                    label,
+                    value,
+                    span,
+                } => {
+                    let new_var = self.add_constant(*label, *value, *span);
+                    renamed.insert(var.index, new_var);
+                }
+                RecordedOp::Gate {
+                    row: _,
+                    typ,
+                    vars,
+                    coeffs,
+                    debug_info,
+                    generic,
+                } => {
+                    let vars = vars
+                        .iter()
+                        .map(|var| var.as_ref().map(|var| rename(&renamed, var)))
+                        .collect();
+
+                    let row = self.gates.len();
+                    self.push_gate(*typ, vars, coeffs.clone(), debug_info.clone());
+
+                    if let Some(generic) = generic {
+                        let generic = GenericGate {
+                            output: generic.output.as_ref().map(|var| rename(&renamed, var)),
+                            ..generic.clone()
+                        };
+                        self.generic_gates.insert(row, generic);
+                    }
+                }
+                RecordedOp::AssertEq {
+                    lhs,
+                    rhs,
+                    span,
+                    label,
+                    message,
+                } => {
+                    let label = std::mem::replace(&mut self.label, label.clone());
+                    let message = std::mem::replace(&mut self.assert_message, message.clone());
+                    self.assert_eq_var(&rename(&renamed, lhs), &rename(&renamed, rhs), *span);
+                    self.label = label;
+                    self.assert_message = message;
+                }
+            }
+        }
+
+        vars.iter().map(|var| rename(&renamed, var)).collect()
+    }
+}
+
+/// Returns the same value, computed from the variables that replace the ones it uses.
+fn rename_value<C>(
+    value: &Value<Kimchi<C>>,
+    rename: impl Fn(&KimchiCellVar) -> KimchiCellVar,
+) -> Value<Kimchi<C>>
+where
+    C: KimchiCurveConfig,
+{
+    match value {
+        Value::Hint(_) => unreachable!("kimchi: recordings with hints can't be spliced"),
+        Value::Constant(cst) => Value::Constant(*cst),
+        Value::LinearCombination(terms, cst) => Value::LinearCombination(
+            terms
+                .iter()
+                .map(|(coeff, var)| (*coeff, rename(var)))
+                .collect(),
+            *cst,
+        ),
+        Value::Mul(lhs, rhs) => Value::Mul(rename(lhs), rename(rhs)),
+        Value::Scale(scalar, var) => Value::Scale(*scalar, rename(var)),
+        Value::Inverse(var) => Value::Inverse(rename(var)),
+        Value::External(name, index) => Value::External(name.clone(), *index),
+        Value::PublicOutput(var) => Value::PublicOutput(var.as_ref().map(rename)),
+    }
+}
@@ -0,0 +1,79 @@
+//! An SRS that is created once, and shared by the prover indexes of many circuits.
+//!
+//! The SRS (structured reference string) used to commit to the polynomials of a circuit only depends on its size,
+//! so a server compiling many circuits can create a single SRS as large as its largest circuit,
+//! and pass it to [crate::witness::CompiledCircuit::compile_to_indexes_with_srs]
+//! instead of creating a new one for each circuit.
+//! The Lagrange basis of a domain (used to commit to the public inputs)
+//! is only computed the first time a circuit with a domain of that size is compiled.
+//! As the prover indexes own the SRS they are created with,
+//! each domain size gets its own copy of the SRS with its Lagrange basis,
+//! which is shared by all the circuits of that size.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use kimchi::circuits::constraints::ConstraintSystem;
+
+use super::curves::KimchiCurveConfig;
+
+/// A handle to an SRS, which can be cloned and shared between threads.
+#[derive(Clone)]
+pub struct SharedSrs<C>
+where
+    C: KimchiCurveConfig,
+{
+    /// The SRS, without any Lagrange basis.
+    srs: Arc<C::Srs>,
+
+    /// The SRS with the Lagrange basis of a domain, for each domain size a circuit was compiled for.
+    with_lagrange_basis: Arc<Mutex<HashMap<usize, Arc<C::Srs>>>>,
+}
+
+impl<C> SharedSrs<C>
+where
+    C: KimchiCurveConfig,
+{
+    /// Creates an SRS for circuits whose domain has up to `size` elements.
+    pub fn new(size: usize) -> Self {
+        Self {
+            srs: Arc::new(C::create_srs(size)),
+            with_lagrange_basis: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the size of the SRS, which is the largest domain it can be used for.
+    pub fn size(&self) -> usize {
+        C::srs_size(&self.srs)
+    }
+
+    /// Returns the SRS to create the prover index of a constraint system with,
+    /// after computing the Lagrange basis of its domain if this wasn't done yet.
+    pub(crate) fn for_circuit(
+        &self,
+        cs: &ConstraintSystem<C::Field>,
+    ) -> miette::Result<Arc<C::Srs>> {
+        let size = self.size();
+        let domain_size = cs.domain.d1.size as usize;
+        if size < domain_size {
+            miette::bail!(
+                "the SRS is too small for this circuit: its size is {size}, but the domain of the circuit has {domain_size} elements"
+            );
+        }
+
+        let mut with_lagrange_basis = self
+            .with_lagrange_basis
+            .lock()
+            .expect("the SRS lock is poisoned");
+        let srs = with_lagrange_basis.entry(domain_size).or_insert_with(|| {
+            // the SRS is only copied (without the Lagrange bases of other domains) once per domain size
+            let mut srs = C::Srs::clone(&self.srs);
+            C::add_lagrange_basis(&mut srs, cs);
+            Arc::new(srs)
+        });
+
+        Ok(Arc::clone(srs))
+    }
+}
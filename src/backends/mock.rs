@@ -0,0 +1,676 @@
+//! A mock backend to quickly test the logic of a circuit.
+//!
+//! It lays out the same generic gates as the kimchi backend, but it never creates a prover index or a proof.
+//! Instead, generating a witness computes the value of every variable
+//! and evaluates every gate against its coefficients, reporting which gates pass or fail.
+
+use std::{collections::HashMap, fmt::Write as _, ops::Neg as _, sync::Arc};
+
+use ark_ff::{One as _, Zero as _};
+use itertools::Itertools as _;
+use kimchi::{
+    circuits::polynomials::poseidon::{POS_ROWS_PER_HASH, ROUNDS_PER_ROW},
+    mina_poseidon::{
+        constants::PlonkSpongeConstantsKimchi, pasta::fp_kimchi, permutation::full_round,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circuit_writer::{CircuitWriter, Constraint, DebugInfo, Gate, GateKind, VarInfo},
+    compiler::Sources,
+    constants::Span,
+    error::{Error, ErrorKind, Result},
+    helpers::PrettyField,
+    imports::FnHandle,
+    stdlib::crypto::{poseidon_sponge, POSEIDON_WIDTH},
+    type_checker::TypeChecker,
+    var::{Value, Var},
+    witness::WitnessEnv,
+};
+
+use super::{
+    check_private_inputs_used, estimate::estimate_costs, kimchi::VestaField, unused_inputs,
+    Backend, BackendVar,
+};
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CellVar {
+    pub index: usize,
+    pub span: Span,
+}
+
+impl BackendVar for CellVar {}
+
+/// A gate of the mock circuit, along with the variables it applies to.
+#[derive(Debug, Clone)]
+struct MockGate {
+    gate: Gate<VestaField>,
+    vars: Vec<Option<CellVar>>,
+    debug_info: DebugInfo,
+}
+
+#[derive(Clone, Default)]
+pub struct Mock {
+    /// How to compute each variable, indexed by the variable index.
+    vars_to_value: Vec<Value<Self>>,
+
+    /// The gates created by the circuit generation.
+    gates: Vec<MockGate>,
+
+    /// We cache constrained constants, like the kimchi backend does.
+    cached_constants: HashMap<VestaField, CellVar>,
+
+    /// The public inputs and outputs, in the order they were created.
+    public_io: Vec<CellVar>,
+
+    /// The public outputs, in the order they were created.
+    public_outputs: Vec<CellVar>,
+
+    /// Indexes used by the public inputs, along with their names
+    /// (this is useful to warn about the ones that are not used)
+    public_input_indices: Vec<(usize, String, Span)>,
+
+    /// Indexes used by the private inputs, along with their names
+    /// (this is useful to check that they appear in the circuit)
+    private_input_indices: Vec<(usize, String, Span)>,
+
+    /// The message attached to the gates being created (see [Backend::set_assert_message]).
+    assert_message: Option<String>,
+
+    /// The label of the gates being created (see [Backend::set_label]).
+    label: Option<String>,
+
+    finalized: bool,
+}
+
+/// The result of checking a single gate against the witness.
+#[derive(Debug, Clone)]
+pub struct GateCheck {
+    pub row: usize,
+    pub typ: GateKind,
+    pub debug_info: DebugInfo,
+    pub satisfied: bool,
+}
+
+pub struct GeneratedWitness {
+    /// The value of every variable, indexed by the variable index.
+    pub witness: Vec<VestaField>,
+
+    /// The public inputs, preceded by the public outputs (same layout as the kimchi backend).
+    pub full_public_inputs: Vec<VestaField>,
+
+    /// The public outputs.
+    pub public_outputs: Vec<VestaField>,
+
+    /// Whether each gate is satisfied or not.
+    pub checks: Vec<GateCheck>,
+}
+
+impl GeneratedWitness {
+    /// Returns the gates that are not satisfied by the witness.
+    pub fn failures(&self) -> impl Iterator<Item = &GateCheck> {
+        self.checks.iter().filter(|check| !check.satisfied)
+    }
+
+    /// Returns an error pointing to the first gate that is not satisfied, if any.
+    pub fn check(&self) -> Result<()> {
+        match self.failures().next() {
+            Some(failure) => {
+                let kind = match &failure.debug_info.message {
+                    Some(message) => ErrorKind::UnsatisfiedAssertion {
+                        row: failure.row,
+                        message: message.clone(),
+                    },
+                    None => ErrorKind::UnsatisfiedConstraint {
+                        row: failure.row,
+                        gate: failure.typ,
+                    },
+                };
+                Err(Error::new("runtime", kind, failure.debug_info.span))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Mock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of gates in the circuit.
+    pub fn num_gates(&self) -> usize {
+        self.gates.len()
+    }
+
+    fn add_gate(
+        &mut self,
+        note: &'static str,
+        typ: GateKind,
+        vars: Vec<Option<CellVar>>,
+        coeffs: Vec<VestaField>,
+        span: Span,
+    ) {
+        assert!(!self.finalized, "the circuit is already finalized");
+
+        self.gates.push(MockGate {
+            gate: Gate { typ, coeffs },
+            vars,
+            debug_info: DebugInfo {
+                span,
+                note: self.label.clone().unwrap_or_else(|| note.to_string()),
+                message: self.assert_message.clone(),
+            },
+        });
+    }
+
+    fn add_generic_gate(
+        &mut self,
+        note: &'static str,
+        vars: Vec<Option<CellVar>>,
+        coeffs: Vec<VestaField>,
+        span: Span,
+    ) {
+        self.add_gate(note, GateKind::DoubleGeneric, vars, coeffs, span);
+    }
+}
+
+/// Applies the poseidon permutation of the kimchi gadget to a state.
+fn poseidon_permutation(mut state: Vec<VestaField>) -> Vec<VestaField> {
+    let params = fp_kimchi::params();
+    for round in 0..POS_ROWS_PER_HASH * ROUNDS_PER_ROW {
+        full_round::<VestaField, PlonkSpongeConstantsKimchi>(&params, &mut state, round);
+    }
+    state
+}
+
+/// The poseidon builtin of the mock backend.
+/// Instead of constraining each round, it creates a single gate per permutation of the sponge,
+/// holding the three elements of the state before and after the permutation,
+/// which gets checked by recomputing the permutation during witness generation.
+fn poseidon(
+    compiler: &mut CircuitWriter<Mock>,
+    vars: &[VarInfo<VestaField, CellVar>],
+    span: Span,
+) -> Result<Option<Var<VestaField, CellVar>>> {
+    poseidon_sponge(compiler, vars, span, |compiler, mut cells| {
+        let state = cells.clone();
+        for col in 0..POSEIDON_WIDTH {
+            let state = state.clone();
+            let output = compiler.backend.new_internal_var(
+                Value::Hint(Arc::new(move |backend, env| {
+                    let state = state
+                        .iter()
+                        .map(|var| backend.compute_var(env, var))
+                        .collect::<Result<_>>()?;
+                    Ok(poseidon_permutation(state)[col])
+                })),
+                span,
+            );
+            cells.push(output);
+        }
+
+        compiler.backend.add_gate(
+            "check the output of a poseidon permutation",
+            GateKind::Poseidon,
+            cells.iter().copied().map(Some).collect(),
+            vec![],
+            span,
+        );
+
+        cells.split_off(POSEIDON_WIDTH)
+    })
+}
+
+impl Backend for Mock {
+    type Field = VestaField;
+    type Var = CellVar;
+    type GeneratedWitness = GeneratedWitness;
+    type Recording = ();
+
+    fn poseidon() -> FnHandle<Self> {
+        poseidon
+    }
+
+    fn new_internal_var(&mut self, val: Value<Self>, span: Span) -> CellVar {
+        let var = CellVar {
+            index: self.vars_to_value.len(),
+            span,
+        };
+        self.vars_to_value.push(val);
+
+        var
+    }
+
+    fn set_assert_message(&mut self, message: Option<String>) {
+        self.assert_message = message;
+    }
+
+    fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+    }
+
+    fn add_constant(
+        &mut self,
+        label: Option<&'static str>,
+        value: VestaField,
+        span: Span,
+    ) -> CellVar {
+        if let Some(cvar) = self.cached_constants.get(&value) {
+            return *cvar;
+        }
+
+        let var = self.new_internal_var(Value::Constant(value), span);
+        self.cached_constants.insert(value, var);
+
+        let zero = VestaField::zero();
+        self.add_generic_gate(
+            label.unwrap_or("hardcode a constant"),
+            vec![Some(var)],
+            vec![VestaField::one(), zero, zero, zero, value.neg()],
+            span,
+        );
+
+        var
+    }
+
+    fn finalize_circuit(
+        &mut self,
+        public_output: Option<Var<Self::Field, Self::Var>>,
+        returned_cells: Option<Vec<CellVar>>,
+        _main_span: Span,
+    ) -> Result<()> {
+        // store the return value in the public output vars
+        // (there's no wiring in this backend, so this is enough to make them equal)
+        if let Some(public_output) = public_output {
+            for (pub_var, ret_var) in public_output.cvars.iter().zip(returned_cells.unwrap()) {
+                let var_idx = pub_var.cvar().unwrap().index;
+                self.vars_to_value[var_idx] = Value::PublicOutput(Some(ret_var));
+            }
+        }
+
+        // every private input must end up in a gate
+        check_private_inputs_used(&self.private_input_indices, |index| {
+            self.gates
+                .iter()
+                .flat_map(|gate| gate.vars.iter().flatten())
+                .any(|var| var.index == index)
+        })?;
+
+        self.finalized = true;
+
+        Ok(())
+    }
+
+    fn unused_public_inputs(&self) -> Vec<(String, Span)> {
+        unused_inputs(&self.public_input_indices, |index| {
+            self.gates
+                .iter()
+                .flat_map(|gate| gate.vars.iter().flatten())
+                .any(|var| var.index == index)
+        })
+    }
+
+    fn constraints(&self) -> Result<Vec<Constraint<VestaField>>> {
+        let mut constraints = vec![];
+
+        for (row, gate) in self.gates.iter().enumerate() {
+            let vars: Vec<_> = gate
+                .vars
+                .iter()
+                .map(|var| var.map(|var| var.index))
+                .collect();
+            let span = gate.debug_info.span;
+            let gate_constraints = gate.gate.constraints(&vars, span).ok_or_else(|| {
+                Error::new(
+                    "constraints",
+                    ErrorKind::UnsupportedGateConstraints {
+                        row,
+                        gate: gate.gate.typ,
+                    },
+                    span,
+                )
+            })?;
+            constraints.extend(gate_constraints);
+        }
+
+        Ok(constraints)
+    }
+
+    fn compute_var(&self, env: &mut WitnessEnv<Self::Field>, var: &CellVar) -> Result<VestaField> {
+        self.compute_val(env, &self.vars_to_value[var.index], var.index)
+    }
+
+    fn generate_witness(
+        &self,
+        witness_env: &mut WitnessEnv<VestaField>,
+    ) -> Result<GeneratedWitness> {
+        assert!(self.finalized, "the circuit is not finalized yet!");
+
+        let witness = (0..self.vars_to_value.len())
+            .map(|index| {
+                let var = CellVar {
+                    index,
+                    span: Span::default(),
+                };
+                self.compute_var(witness_env, &var)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let checks = self
+            .gates
+            .iter()
+            .enumerate()
+            .map(
+                |(
+                    row,
+                    MockGate {
+                        gate,
+                        vars,
+                        debug_info,
+                    },
+                )| {
+                    let cells: Vec<_> = vars
+                        .iter()
+                        .map(|var| var.map_or_else(VestaField::zero, |var| witness[var.index]))
+                        .collect();
+
+                    let satisfied = match gate.is_satisfied(&cells) {
+                        Some(satisfied) => satisfied,
+                        None => match gate.typ {
+                            GateKind::Poseidon => {
+                                poseidon_permutation(cells[..POSEIDON_WIDTH].to_vec())
+                                    == cells[POSEIDON_WIDTH..]
+                            }
+                            _ => true,
+                        },
+                    };
+
+                    GateCheck {
+                        row,
+                        typ: gate.typ,
+                        debug_info: debug_info.clone(),
+                        satisfied,
+                    }
+                },
+            )
+            .collect();
+
+        let value_of = |vars: &[CellVar]| vars.iter().map(|var| witness[var.index]).collect();
+        let full_public_inputs = value_of(&self.public_io);
+        let public_outputs = value_of(&self.public_outputs);
+
+        Ok(GeneratedWitness {
+            witness,
+            full_public_inputs,
+            public_outputs,
+            checks,
+        })
+    }
+
+    fn generate_asm(&self, sources: &Sources, debug: bool) -> String {
+        let mut res = crate::utils::noname_version();
+
+        for (
+            row,
+            MockGate {
+                gate,
+                vars,
+                debug_info,
+            },
+        ) in self.gates.iter().enumerate()
+        {
+            let vars = vars
+                .iter()
+                .map(|var| var.map_or_else(|| "_".to_string(), |var| format!("v_{}", var.index)))
+                .join(", ");
+            let coeffs = gate.coeffs.iter().map(|coeff| coeff.pretty()).join(", ");
+
+            writeln!(res, "{row} │ {:?} │ {vars} │ <{coeffs}>", gate.typ).unwrap();
+
+            if debug {
+                crate::utils::display_source(&mut res, sources, &[debug_info.clone()]);
+            }
+        }
+
+        res
+    }
+
+    fn estimate_rows(&self, typed: &TypeChecker<Self>) -> usize {
+        let costs = estimate_costs(typed);
+
+        // every operation is a single gate, and public inputs and outputs don't have any
+        costs.ops.linear + costs.ops.mul + costs.ops.assert + costs.ops.poseidon
+    }
+
+    fn neg(&mut self, var: &CellVar, span: Span) -> CellVar {
+        let one = VestaField::one();
+
+        let neg_var = self.new_internal_var(
+            Value::LinearCombination(vec![(one.neg(), *var)], VestaField::zero()),
+            span,
+        );
+        self.add_generic_gate(
+            "constraint to validate a negation (`x + (-x) = 0`)",
+            vec![Some(*var), Some(neg_var)],
+            vec![one, one],
+            span,
+        );
+
+        neg_var
+    }
+
+    fn add(&mut self, lhs: &CellVar, rhs: &CellVar, span: Span) -> CellVar {
+        let one = VestaField::one();
+
+        let res = self.new_internal_var(
+            Value::LinearCombination(vec![(one, *lhs), (one, *rhs)], VestaField::zero()),
+            span,
+        );
+        self.add_generic_gate(
+            "add two variables together",
+            vec![Some(*lhs), Some(*rhs), Some(res)],
+            vec![one, one, one.neg()],
+            span,
+        );
+
+        res
+    }
+
+    fn add_const(&mut self, var: &CellVar, cst: &VestaField, span: Span) -> CellVar {
+        let zero = VestaField::zero();
+        let one = VestaField::one();
+
+        let res = self.new_internal_var(Value::LinearCombination(vec![(one, *var)], *cst), span);
+        self.add_generic_gate(
+            "add a constant with a variable",
+            vec![Some(*var), None, Some(res)],
+            vec![one, zero, one.neg(), zero, *cst],
+            span,
+        );
+
+        res
+    }
+
+    fn mul(&mut self, lhs: &CellVar, rhs: &CellVar, span: Span) -> CellVar {
+        let zero = VestaField::zero();
+        let one = VestaField::one();
+
+        let res = self.new_internal_var(Value::Mul(*lhs, *rhs), span);
+        self.add_generic_gate(
+            "multiply two variables together",
+            vec![Some(*lhs), Some(*rhs), Some(res)],
+            vec![zero, zero, one.neg(), one],
+            span,
+        );
+
+        res
+    }
+
+    fn mul_const(&mut self, var: &CellVar, cst: &VestaField, span: Span) -> CellVar {
+        let zero = VestaField::zero();
+        let one = VestaField::one();
+
+        let res = self.new_internal_var(Value::Scale(*cst, *var), span);
+        self.add_generic_gate(
+            "multiply a variable with a constant",
+            vec![Some(*var), None, Some(res)],
+            vec![*cst, zero, one.neg()],
+            span,
+        );
+
+        res
+    }
+
+    fn assert_eq_const(&mut self, cvar: &CellVar, cst: VestaField, span: Span) {
+        let zero = VestaField::zero();
+
+        self.add_generic_gate(
+            "constrain var - cst = 0 to check equality",
+            vec![Some(*cvar)],
+            vec![VestaField::one(), zero, zero, zero, cst.neg()],
+            span,
+        );
+    }
+
+    fn assert_eq_var(&mut self, lhs: &CellVar, rhs: &CellVar, span: Span) {
+        self.add_generic_gate(
+            "constrain lhs - rhs = 0 to assert that they are equal",
+            vec![Some(*lhs), Some(*rhs)],
+            vec![VestaField::one(), VestaField::one().neg()],
+            span,
+        );
+    }
+
+    fn add_public_input(&mut self, val: Value<Self>, span: Span) -> CellVar {
+        let name = match &val {
+            Value::External(name, _) => name.clone(),
+            _ => unreachable!("public inputs are external values"),
+        };
+        let cvar = self.new_internal_var(val, span);
+        self.public_io.push(cvar);
+        self.public_input_indices.push((cvar.index, name, span));
+
+        cvar
+    }
+
+    fn add_private_input(&mut self, val: Value<Self>, span: Span) -> CellVar {
+        let name = match &val {
+            Value::External(name, _) => name.clone(),
+            _ => unreachable!("private inputs are external values"),
+        };
+        let cvar = self.new_internal_var(val, span);
+        self.private_input_indices.push((cvar.index, name, span));
+
+        cvar
+    }
+
+    fn add_public_output(&mut self, val: Value<Self>, span: Span) -> CellVar {
+        let cvar = self.new_internal_var(val, span);
+        self.public_io.push(cvar);
+        self.public_outputs.push(cvar);
+
+        cvar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backends::kimchi::VestaField,
+        compiler::{compile, typecheck_next_file, Sources},
+        inputs::{parse_inputs, ExtField as _},
+        type_checker::TypeChecker,
+        witness::CompiledCircuit,
+    };
+
+    use super::Mock;
+
+    fn compile_code(code: &str) -> miette::Result<CompiledCircuit<Mock>> {
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<Mock>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "mock.no".to_string(),
+            code.to_string(),
+            0,
+        )?;
+        compile(&sources, tast, Mock::new())
+    }
+
+    #[test]
+    fn test_mock_backend() -> miette::Result<()> {
+        let compiled_circuit = compile_code(
+            r#"
+fn main(pub xx: Field, yy: Field) -> Field {
+    let zz = xx * yy;
+    assert_eq(zz, 6);
+    return zz + 1;
+}
+"#,
+        )?;
+
+        let public_inputs = parse_inputs(r#"{"xx": "2"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"yy": "3"}"#).unwrap();
+        let witness = compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap();
+        witness.check().unwrap();
+
+        assert_eq!(
+            witness.checks.len(),
+            compiled_circuit.circuit.backend.num_gates()
+        );
+        assert_eq!(witness.public_outputs, vec![VestaField::from(7)]);
+        assert_eq!(
+            witness.full_public_inputs,
+            vec![VestaField::from(7), VestaField::from(2)]
+        );
+
+        // a wrong input is reported on the gate that fails
+        let public_inputs = parse_inputs(r#"{"xx": "2"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"yy": "4"}"#).unwrap();
+        let witness = compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap();
+        assert!(witness.check().is_err());
+
+        let failures: Vec<_> = witness.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(
+            failures[0].debug_info.note,
+            "constrain var - cst = 0 to check equality"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_poseidon() -> miette::Result<()> {
+        let compiled_circuit = compile_code(
+            r#"
+use std::crypto;
+
+fn main(pub digest: Field, preimage: [Field; 2]) {
+    let res = crypto::poseidon(preimage);
+    assert_eq(res, digest);
+}
+"#,
+        )?;
+
+        let digest = crate::helpers::poseidon(&[1.into(), 1.into()]);
+        let public_inputs =
+            parse_inputs(&format!(r#"{{"digest": "{}"}}"#, digest.to_dec_string())).unwrap();
+        let private_inputs = parse_inputs(r#"{"preimage": ["1", "1"]}"#).unwrap();
+
+        let witness = compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap();
+        witness.check().unwrap();
+
+        Ok(())
+    }
+}
@@ -1,41 +1,53 @@
-use std::{fmt::Debug, hash::Hash, str::FromStr};
+use std::{fmt::Debug, hash::Hash, str::FromStr, sync::Arc};
 
-use ark_ff::{Field, Zero};
+use ark_ff::{Field, SquareRootField, Zero};
 use num_bigint::BigUint;
 
 use crate::{
+    circuit_writer::{Constraint, OptLevel},
     compiler::Sources,
     constants::Span,
     error::{Error, ErrorKind, Result},
     helpers::PrettyField,
     imports::FnHandle,
     parser::FunctionDef,
-    var::{Value, Var},
+    type_checker::TypeChecker,
+    var::{ConstOrCell, Value, Var},
     witness::WitnessEnv,
 };
 
 use self::{
     kimchi::KimchiVesta,
+    mock::Mock,
     r1cs::{R1csBls12381Field, R1csBn254Field, R1CS},
 };
 
+pub mod estimate;
 pub mod kimchi;
+pub mod mock;
 pub mod r1cs;
 
 /// This trait serves as an alias for a bundle of traits
 pub trait BackendField:
-    Field + FromStr + TryFrom<BigUint> + TryInto<BigUint> + Into<BigUint> + PrettyField
+    Field
+    + SquareRootField
+    + FromStr
+    + TryFrom<BigUint>
+    + TryInto<BigUint>
+    + Into<BigUint>
+    + PrettyField
 {
 }
 
 /// This trait allows different backends to have different cell var types.
 /// It is intended to make it opaque to the frondend.
-pub trait BackendVar: Clone + Debug + PartialEq + Eq {}
+pub trait BackendVar: Clone + Debug + PartialEq + Eq + 'static {}
 
 pub enum BackendKind {
     KimchiVesta(KimchiVesta),
     R1csBls12_381(R1CS<R1csBls12381Field>),
     R1csBn254(R1CS<R1csBn254Field>),
+    Mock(Mock),
 }
 
 impl BackendKind {
@@ -50,6 +62,10 @@ impl BackendKind {
     pub fn new_r1cs_bn254() -> Self {
         Self::R1csBn254(R1CS::new())
     }
+
+    pub fn new_mock() -> Self {
+        Self::Mock(Mock::new())
+    }
 }
 
 // TODO: should it be cloneable? It is now so because FnInfo needs to be cloneable.
@@ -64,6 +80,10 @@ pub trait Backend: Clone {
     /// The generated witness type for the backend. Each backend may define its own witness format to be generated.
     type GeneratedWitness;
 
+    /// The constraints created by a function call, which can be spliced in the circuit again
+    /// for another call creating the same constraints (see [Self::start_recording]).
+    type Recording: Debug;
+
     // TODO: as the builtins grows, we might better change this to a crypto struct that holds all the builtin function pointers.
     /// poseidon crypto builtin function for different backends
     fn poseidon() -> FnHandle<Self>;
@@ -77,6 +97,39 @@ pub trait Backend: Clone {
     /// It increments the variable index for look up later.
     fn new_internal_var(&mut self, val: Value<Self>, span: Span) -> Self::Var;
 
+    /// Creates a cell whose value is computed off-circuit by `hint`, from the values of `inputs`,
+    /// when the witness is generated (see [Value::Hint]).
+    /// This is useful when a value is cheaper to check than to compute (e.g. a quotient, or a square root).
+    ///
+    /// No constraint is created: the caller must constrain the cell so that only one value can satisfy the circuit,
+    /// otherwise a dishonest prover can pick any value instead of the one computed by the hint.
+    fn new_hint<H>(
+        &mut self,
+        inputs: Vec<ConstOrCell<Self::Field, Self::Var>>,
+        hint: H,
+        span: Span,
+    ) -> Self::Var
+    where
+        H: Fn(&[Self::Field]) -> Result<Self::Field> + 'static,
+    {
+        self.new_internal_var(
+            Value::Hint(Arc::new(
+                move |backend: &Self, env: &mut WitnessEnv<Self::Field>| {
+                    let mut values = Vec::with_capacity(inputs.len());
+                    for input in &inputs {
+                        values.push(match input {
+                            ConstOrCell::Const(cst) => *cst,
+                            ConstOrCell::Cell(cvar) => backend.compute_var(env, cvar)?,
+                        });
+                    }
+
+                    hint(&values)
+                },
+            )),
+            span,
+        )
+    }
+
     /// negate a var
     fn neg(&mut self, var: &Self::Var, span: Span) -> Self::Var;
 
@@ -122,6 +175,69 @@ pub trait Backend: Clone {
         span: Span,
     ) -> Self::Var;
 
+    /// Sets the message attached to the constraints created from now on (or stops attaching one),
+    /// which is reported if one of them is not satisfied by the witness.
+    fn set_assert_message(&mut self, message: Option<String>);
+
+    /// Sets the label of the constraints created from now on (or goes back to their default notes),
+    /// which the ASM displays instead of the note of each constraint.
+    fn set_label(&mut self, label: Option<String>);
+
+    /// Sets whether the constraints created from now on can be packed together
+    /// (like the generic gates of kimchi, which are paired in double generic gates),
+    /// and returns the previous setting.
+    /// Backends that don't pack constraints ignore this.
+    fn set_double_generic(&mut self, _enabled: bool) -> bool {
+        true
+    }
+
+    /// Returns whether the constraints created from now on can be packed together (see [Self::set_double_generic]).
+    fn double_generic(&self) -> bool {
+        true
+    }
+
+    /// Starts recording the constraints created from now on, by a call to a function with the cells `inputs` as arguments.
+    /// Recordings can be nested, as functions call other functions.
+    fn start_recording(&mut self, _inputs: &[Self::Var]) {}
+
+    /// Stops the innermost recording in progress, and returns it if it can be spliced (see [Self::splice]).
+    /// Backends that don't support this never return a recording.
+    fn stop_recording(&mut self) -> Option<Self::Recording> {
+        None
+    }
+
+    /// Creates the constraints of a recording again, with new cells in place of the cells created during the recording,
+    /// and the cells `inputs` in place of the inputs of the recording (the other cells it uses, like constants, are kept).
+    /// Returns the cells replacing `vars`, which are cells of the recording.
+    fn splice(
+        &mut self,
+        _recording: &Self::Recording,
+        _inputs: &[Self::Var],
+        _vars: &[Self::Var],
+    ) -> Vec<Self::Var> {
+        unreachable!("the backend doesn't return recordings")
+    }
+
+    /// Constrains `key` to be in the lookup table `table_id`, and returns the value associated to it.
+    /// Only backends with a lookup argument support this.
+    fn lookup(&mut self, _table_id: u32, _key: &Self::Var, span: Span) -> Result<Self::Var> {
+        Err(Error::new(
+            "constraint-generation",
+            ErrorKind::LookupsNotSupported,
+            span,
+        ))
+    }
+
+    /// Constrains `var` to fit in 64 bits with a native range-check gate.
+    /// Only backends with such a gate support this.
+    fn range_check64(&mut self, _var: &Self::Var, span: Span) -> Result<()> {
+        Err(Error::new(
+            "constraint-generation",
+            ErrorKind::RangeChecksNotSupported,
+            span,
+        ))
+    }
+
     /// Backends should implement this function to load and compute the value of a CellVar.
     fn compute_var(
         &self,
@@ -141,8 +257,7 @@ pub trait Backend: Clone {
 
         match val {
             Value::Hint(func) => {
-                let res = func(self, env)
-                    .expect("that function doesn't return a var (type checker error)");
+                let res = func(self, env)?;
                 env.cached_values.insert(cache_key, res);
                 Ok(res)
             }
@@ -184,6 +299,10 @@ pub trait Backend: Clone {
         }
     }
 
+    /// Runs the optimization passes enabled by `level` on the gates created so far.
+    /// This is called once the whole circuit has been generated, right before [Self::finalize_circuit].
+    fn optimize(&mut self, _level: OptLevel) {}
+
     /// Finalize the circuit by doing some sanitizing checks.
     fn finalize_circuit(
         &mut self,
@@ -192,6 +311,26 @@ pub trait Backend: Clone {
         main_span: Span,
     ) -> Result<()>;
 
+    /// Returns the public inputs (by name, along with their declaration) that don't appear in any constraint
+    /// besides the one exposing them, which means that the prover could set them to anything.
+    /// This is called once the circuit is finalized.
+    fn unused_public_inputs(&self) -> Vec<(String, Span)>;
+
+    /// Returns the spans of the constraints that are satisfied by any values of their variables (like `x - x = 0`),
+    /// which waste a gate and are often the sign of a logic error (e.g. asserting that a value is equal to itself).
+    /// This is called once the circuit is finalized.
+    fn trivially_satisfied_constraints(&self) -> Vec<Span> {
+        vec![]
+    }
+
+    /// Returns the constraints of the circuit, as polynomials over its variables (see [Constraint]),
+    /// independently of how the backend encodes them.
+    /// This fails with [ErrorKind::UnsupportedGateConstraints] if the circuit contains a gate
+    /// whose constraints aren't polynomials of its cells (e.g. kimchi's Poseidon, lookup or range check gates),
+    /// instead of returning an incomplete relation.
+    /// This is called once the circuit is finalized.
+    fn constraints(&self) -> Result<Vec<Constraint<Self::Field>>>;
+
     /// Generate the witness for a backend.
     fn generate_witness(
         &self,
@@ -200,4 +339,55 @@ pub trait Backend: Clone {
 
     /// Generate the asm for a backend.
     fn generate_asm(&self, sources: &Sources, debug: bool) -> String;
+
+    /// Returns an approximation of the number of rows (or constraints) the circuit will need,
+    /// without generating it (see [estimate::estimate_costs]).
+    fn estimate_rows(&self, typed: &TypeChecker<Self>) -> usize;
+}
+
+/// The error returned when finalizing a circuit where a variable doesn't end up in any constraint,
+/// which means that the circuit writer (or a builtin) forgot to constrain it.
+pub(crate) fn unconstrained_var<B: Backend>(index: usize, value: &Value<B>, span: Span) -> Error {
+    Error::new(
+        "constraint-finalization",
+        ErrorKind::UnconstrainedVar {
+            index,
+            value: format!("{value:?}"),
+        },
+        span,
+    )
+}
+
+/// Returns the inputs (given as `(index, name, span)`) that are not used, once per name,
+/// along with the span of their declaration.
+pub(crate) fn unused_inputs(
+    inputs: &[(usize, String, Span)],
+    is_used: impl Fn(usize) -> bool,
+) -> Vec<(String, Span)> {
+    let mut unused: Vec<(String, Span)> = vec![];
+    for (index, name, span) in inputs {
+        if !is_used(*index) && !unused.iter().any(|(other, _)| other == name) {
+            unused.push((name.clone(), *span));
+        }
+    }
+
+    unused
+}
+
+/// Returns an error naming the private inputs (given as `(index, name, span)`) that don't end up in the circuit,
+/// pointing at the declaration of the first of them.
+pub(crate) fn check_private_inputs_used(
+    private_inputs: &[(usize, String, Span)],
+    is_used: impl Fn(usize) -> bool,
+) -> Result<()> {
+    let unused = unused_inputs(private_inputs, is_used);
+
+    match unused.first() {
+        None => Ok(()),
+        Some((_, span)) => Err(Error::new(
+            "constraint-finalization",
+            ErrorKind::PrivateInputNotUsed(unused.iter().map(|(name, _)| name.clone()).collect()),
+            *span,
+        )),
+    }
 }
@@ -2,21 +2,20 @@ use crate::{
     backends::BackendField,
     circuit_writer::{CircuitWriter, VarInfo},
     constants::Span,
-    error::Result,
+    error::{ErrorKind, Result},
     var::Var,
 };
 
 use super::{LinearCombination, R1CS};
 
-// todo: impl this
+// todo: impl this (it reports an error for now)
 pub fn poseidon<F>(
     compiler: &mut CircuitWriter<R1CS<F>>,
-    vars: &[VarInfo<F, LinearCombination<F>>],
+    _vars: &[VarInfo<F, LinearCombination<F>>],
     span: Span,
 ) -> Result<Option<Var<F, LinearCombination<F>>>>
 where
     F: BackendField,
 {
-    // dummy for now
-    unimplemented!()
+    Err(compiler.error(ErrorKind::Unsupported("poseidon on the r1cs backend"), span))
 }
@@ -12,9 +12,16 @@ use crate::constants::Span;
 use crate::error::{Error, ErrorKind, Result};
 use crate::helpers::PrettyField;
 use crate::parser::FunctionDef;
-use crate::{circuit_writer::DebugInfo, var::Value};
+use crate::type_checker::TypeChecker;
+use crate::{
+    circuit_writer::{self, DebugInfo},
+    var::Value,
+};
 
-use super::{Backend, BackendField, BackendVar};
+use super::{
+    check_private_inputs_used, estimate::estimate_costs, unconstrained_var, unused_inputs, Backend,
+    BackendField, BackendVar,
+};
 
 pub type R1csBls12381Field = ark_bls12_381::Fr;
 pub type R1csBn254Field = ark_bn254::Fr;
@@ -186,6 +193,52 @@ where
     fn as_array(&self) -> [&LinearCombination<F>; 3] {
         [&self.a, &self.b, &self.c]
     }
+
+    /// Returns the constraint `a * b - c = 0` as a polynomial over the variables (see [circuit_writer::Constraint]).
+    fn to_symbolic(&self, span: Span) -> circuit_writer::Constraint<F> {
+        // the constant of a linear combination is a term without variables
+        let terms = |lc: &LinearCombination<F>| -> Vec<(F, Vec<usize>)> {
+            lc.terms
+                .iter()
+                .map(|(var, coeff)| (*coeff, vec![var.index]))
+                .chain([(lc.constant, vec![])])
+                .collect()
+        };
+
+        let product = terms(&self.a)
+            .into_iter()
+            .cartesian_product(terms(&self.b))
+            .map(|((lhs, lhs_vars), (rhs, rhs_vars))| (lhs * rhs, [lhs_vars, rhs_vars].concat()));
+        let c = terms(&self.c)
+            .into_iter()
+            .map(|(coeff, vars)| (-coeff, vars));
+
+        circuit_writer::Constraint::new(product.chain(c), span)
+    }
+
+    /// Returns whether the constraint is satisfied by any values of its variables.
+    /// A product of two linear combinations with variables is quadratic, so `c` can't cancel it:
+    /// one of them must be a constant `k`, and `k * other - c` must be zero.
+    fn is_trivial(&self) -> bool {
+        let as_constant = |lc: &LinearCombination<F>| {
+            lc.terms
+                .values()
+                .all(|coeff| coeff.is_zero())
+                .then_some(lc.constant)
+        };
+
+        let (cst, other) = match (as_constant(&self.a), as_constant(&self.b)) {
+            (Some(cst), _) => (cst, &self.b),
+            (None, Some(cst)) => (cst, &self.a),
+            (None, None) => return false,
+        };
+
+        let span = self.c.span;
+        let diff = other
+            .scale(cst, span)
+            .add(&self.c.scale(F::one().neg(), span), span);
+        diff.constant.is_zero() && diff.terms.values().all(|coeff| coeff.is_zero())
+    }
 }
 
 /// R1CS backend with bls12_381 field.
@@ -197,13 +250,21 @@ where
     /// Constraints in the r1cs.
     constraints: Vec<Constraint<F>>,
     witness_vector: Vec<Value<Self>>,
+    /// The span of the code that created each variable of the witness vector
+    var_spans: Vec<Span>,
     debug_info: Vec<DebugInfo>,
     /// Record the public inputs for reordering the witness vector
     public_inputs: Vec<CellVar>,
+    /// Record the public inputs for checking
+    public_input_indices: Vec<(usize, String, Span)>,
     /// Record the private inputs for checking
-    private_input_indices: Vec<(usize, Span)>,
+    private_input_indices: Vec<(usize, String, Span)>,
     /// Record the public outputs for reordering the witness vector
     public_outputs: Vec<CellVar>,
+    /// The message attached to the constraints being created (see [Backend::set_assert_message])
+    assert_message: Option<String>,
+    /// The label of the constraints being created (see [Backend::set_label])
+    label: Option<String>,
     finalized: bool,
 }
 
@@ -215,10 +276,14 @@ where
         Self {
             constraints: Vec::new(),
             witness_vector: Vec::new(),
+            var_spans: Vec::new(),
             debug_info: Vec::new(),
             public_inputs: Vec::new(),
+            public_input_indices: Vec::new(),
             private_input_indices: Vec::new(),
             public_outputs: Vec::new(),
+            assert_message: None,
+            label: None,
             finalized: false,
         }
     }
@@ -237,8 +302,9 @@ where
     /// This represents one constraint: a * b = c
     fn add_constraint(&mut self, note: &str, c: Constraint<F>, span: Span) {
         let debug_info = DebugInfo {
-            note: note.to_string(),
+            note: self.label.clone().unwrap_or_else(|| note.to_string()),
             span,
+            message: self.assert_message.clone(),
         };
         self.debug_info.push(debug_info);
 
@@ -276,6 +342,8 @@ pub struct GeneratedWitness<F>
 where
     F: BackendField,
 {
+    /// The values of all the vars: the constant `1`, the public outputs, the public inputs,
+    /// and then the private inputs and the intermediate values (see [snarkjs::wtns_to_bytes]).
     pub witness: Vec<F>,
     pub outputs: Vec<F>,
 }
@@ -287,6 +355,7 @@ where
     type Field = F;
     type Var = LinearCombination<F>;
     type GeneratedWitness = GeneratedWitness<F>;
+    type Recording = ();
 
     fn poseidon() -> crate::imports::FnHandle<Self> {
         builtin::poseidon::<F>
@@ -297,6 +366,14 @@ where
         self.new_internal_var(Value::Constant(F::one()), Span::default());
     }
 
+    fn set_assert_message(&mut self, message: Option<String>) {
+        self.assert_message = message;
+    }
+
+    fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+    }
+
     /// Create a new CellVar and record in witness_vector vector.
     /// The underlying type of CellVar is always WitnessVar.
     fn new_internal_var(
@@ -310,6 +387,7 @@ where
         };
 
         self.witness_vector.insert(var.index, val);
+        self.var_spans.push(span);
 
         LinearCombination::from(var)
     }
@@ -359,8 +437,12 @@ where
             }
         }
 
+        check_private_inputs_used(&self.private_input_indices, |index| {
+            written_vars.contains(&index)
+        })?;
+
         // check if every cell vars end up being a cell var in the circuit or public output
-        for (index, _) in self.witness_vector.iter().enumerate() {
+        for (index, value) in self.witness_vector.iter().enumerate() {
             // Skip the first var which is always 1
             // - In a linear combination, each of the vars can be paired with a coefficient.
             // - The first var is assumed to be the factor of the constant of a linear combination.
@@ -369,20 +451,7 @@ where
             }
 
             if !written_vars.contains(&index) {
-                if let Some((_, private_input_span)) = self
-                    .private_input_indices
-                    .iter()
-                    .find(|(private_input_id, _)| private_input_id == &index)
-                {
-                    let err = Error::new(
-                        "constraint-finalization",
-                        ErrorKind::PrivateInputNotUsed,
-                        *private_input_span,
-                    );
-                    return Err(err);
-                } else {
-                    panic!("there's a bug in the circuit_writer, some cellvar does not end up being a cellvar in the circuit!");
-                }
+                return Err(unconstrained_var(index, value, self.var_spans[index]));
             }
         }
 
@@ -391,6 +460,35 @@ where
         Ok(())
     }
 
+    fn unused_public_inputs(&self) -> Vec<(String, Span)> {
+        unused_inputs(&self.public_input_indices, |index| {
+            self.constraints.iter().any(|constraint| {
+                constraint
+                    .as_array()
+                    .iter()
+                    .any(|lc| lc.terms.keys().any(|var| var.index == index))
+            })
+        })
+    }
+
+    fn constraints(&self) -> Result<Vec<circuit_writer::Constraint<F>>> {
+        Ok(self
+            .constraints
+            .iter()
+            .zip(&self.debug_info)
+            .map(|(constraint, debug_info)| constraint.to_symbolic(debug_info.span))
+            .collect())
+    }
+
+    fn trivially_satisfied_constraints(&self) -> Vec<Span> {
+        self.constraints
+            .iter()
+            .zip(&self.debug_info)
+            .filter(|(constraint, _)| constraint.is_trivial())
+            .map(|(_, debug_info)| debug_info.span)
+            .collect()
+    }
+
     fn compute_var(
         &self,
         env: &mut crate::witness::WitnessEnv<Self::Field>,
@@ -398,7 +496,9 @@ where
     ) -> Result<Self::Field> {
         let mut val = lc.constant;
 
-        for (var, factor) in &lc.terms {
+        // the terms are visited by index (and not in the order of the hash map),
+        // so that the hints they depend on are always computed in the same order
+        for (var, factor) in lc.terms.iter().sorted_by_key(|(var, _)| var.index) {
             let var_val = self.witness_vector.get(var.index).unwrap();
             let calc = self.compute_val(env, var_val, var.index)? * factor;
             val += calc;
@@ -445,11 +545,14 @@ where
             let c = constraint.c.evaluate(&witness);
 
             if ab != c {
-                return Err(Error::new(
-                    "runtime",
-                    ErrorKind::InvalidWitness(index),
-                    debug_info.span,
-                ));
+                let kind = match &debug_info.message {
+                    Some(message) => ErrorKind::UnsatisfiedAssertion {
+                        row: index,
+                        message: message.clone(),
+                    },
+                    None => ErrorKind::InvalidWitness(index),
+                };
+                return Err(Error::new("runtime", kind, debug_info.span));
             }
         }
 
@@ -513,7 +616,15 @@ where
             let (a, b, c) = (&fmt_lcs[0], &fmt_lcs[1], &fmt_lcs[2]);
 
             // format an entire constraint
-            res.push_str(&format!("{} == ({}) * ({})\n", c, a, b));
+            res.push_str(&format!("{} == ({}) * ({})", c, a, b));
+
+            // the code that created the constraint
+            if debug {
+                let location = crate::utils::span_location(sources, debug_info.span);
+                res.push_str(&format!(" @ {location}"));
+            }
+
+            res.push('\n');
 
             if debug {
                 // link the constraint to the source code
@@ -524,6 +635,14 @@ where
         res
     }
 
+    fn estimate_rows(&self, typed: &TypeChecker<Self>) -> usize {
+        let costs = estimate_costs(typed);
+
+        // linear combinations are free, only multiplications and assertions create constraints
+        // (poseidon is not supported by this backend yet, so it is not accounted for)
+        costs.ops.mul + costs.ops.assert
+    }
+
     fn neg(&mut self, x: &LinearCombination<F>, span: Span) -> LinearCombination<F> {
         let one = F::one();
         let x = x.clone();
@@ -575,17 +694,27 @@ where
 
     /// Adds the public input cell vars.
     fn add_public_input(&mut self, val: Value<Self>, span: Span) -> LinearCombination<F> {
+        let name = match &val {
+            Value::External(name, _) => name.clone(),
+            _ => unreachable!("public inputs are external values"),
+        };
         let var = self.new_internal_var(val, span);
         self.public_inputs.push(*var.to_cell_var());
+        self.public_input_indices
+            .push((var.to_cell_var().index, name, span));
 
         var
     }
 
     /// Adds the private input cell vars.
     fn add_private_input(&mut self, val: Value<Self>, span: Span) -> LinearCombination<F> {
+        let name = match &val {
+            Value::External(name, _) => name.clone(),
+            _ => unreachable!("private inputs are external values"),
+        };
         let var = self.new_internal_var(val, span);
         self.private_input_indices
-            .push((var.to_cell_var().index, span));
+            .push((var.to_cell_var().index, name, span));
 
         var
     }
@@ -603,11 +732,22 @@ where
 mod tests {
     use crate::{
         backends::{
-            r1cs::{R1csBls12381Field, R1CS},
+            kimchi::KimchiVesta,
+            r1cs::{
+                snarkjs::{wtns_from_bytes, wtns_to_bytes},
+                R1csBls12381Field, R1csBn254Field, R1CS,
+            },
             Backend, BackendKind,
         },
+        circuit_writer::{CircuitOptions, CircuitWriter, GateKind},
+        compiler::{compile, typecheck_next_file, Sources},
+        constants::Span,
+        error::{ErrorKind, WarningKind},
+        inputs::parse_inputs,
         lexer::Token,
         parser::{types::FnSig, FunctionDef, ParserCtx},
+        type_checker::TypeChecker,
+        var::Value,
     };
     use ark_ff::One;
     use rstest::rstest;
@@ -651,4 +791,169 @@ mod tests {
             _ => panic!("unexpected value"),
         }
     }
+
+    #[test]
+    fn test_unconstrained_var() {
+        let mut r1cs: R1CS<R1csBls12381Field> = R1CS::new();
+        r1cs.init_circuit();
+
+        // a variable that a builtin forgot to constrain
+        let span = Span::new(0, 3, 4);
+        r1cs.new_internal_var(Value::Constant(R1csBls12381Field::one()), span);
+
+        let err = r1cs
+            .finalize_circuit(None, None, Span::default())
+            .unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::UnconstrainedVar { index: 1, ref value } if value == "Constant"
+        ));
+        assert_eq!(err.span, span);
+    }
+
+    #[test]
+    fn test_constraints_match_generic_gates() -> miette::Result<()> {
+        // a circuit without any linear combination:
+        // each multiplication and assertion is a constraint in R1CS, and a generic gate in kimchi
+        let code = r#"fn main(pub xx: Field, yy: Field) {
+            let zz = xx * yy;
+            let ww = zz * yy;
+            assert_eq(ww, 18);
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "constraints.no".to_string(),
+            code.to_string(),
+            0,
+        )?;
+        let r1cs_circuit = compile(&sources, tast, R1CS::new())?;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<KimchiVesta>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "constraints.no".to_string(),
+            code.to_string(),
+            0,
+        )?;
+        let kimchi_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+        // the public input rows of kimchi are generic gates too, but they don't constrain anything
+        let stats = kimchi_circuit.gate_stats();
+        let generic_gates = stats.gates[&GateKind::DoubleGeneric];
+        let public_input_size = kimchi_circuit.circuit.backend.public_input_size;
+
+        assert_eq!(
+            r1cs_circuit.circuit.backend.num_constraints(),
+            generic_gates - public_input_size
+        );
+        assert_eq!(r1cs_circuit.circuit.backend.num_constraints(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trivially_satisfied_constraint() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, yy: Field) {
+            assert_eq(xx * yy, 6);
+            assert_eq(xx + yy, yy + xx);
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "trivial.no".to_string(),
+            code.to_string(),
+            0,
+        )?;
+        let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+        // `(xx + yy) * 1 = yy + xx` holds for any values, unlike the first assertion
+        assert_eq!(compiled_circuit.warnings.len(), 1);
+        assert!(matches!(
+            compiled_circuit.warnings[0].kind,
+            WarningKind::TriviallySatisfiedConstraint
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_poseidon_unsupported() {
+        let code = r#"use std::crypto;
+
+        fn main(pub xx: Field, yy: Field) {
+            let digest = crypto::poseidon([xx, yy]);
+            assert_eq(digest, 1);
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "poseidon.no".to_string(),
+            code.to_string(),
+            0,
+        )
+        .unwrap();
+
+        let err =
+            CircuitWriter::generate_circuit(tast, R1CS::new(), CircuitOptions::default(), None)
+                .err()
+                .expect("expected an error");
+        assert!(matches!(err.kind, ErrorKind::Unsupported(_)));
+
+        let start = code.find("crypto::poseidon").unwrap();
+        assert_eq!(err.span.start, start);
+    }
+
+    #[test]
+    fn test_wtns_round_trip() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field, yy: Field) -> Field {
+            let zz = xx * yy;
+            return zz + 1;
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "wtns.no".to_string(),
+            code.to_string(),
+            0,
+        )?;
+        let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+        let public_inputs = parse_inputs(r#"{"xx": "2"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"yy": "3"}"#).unwrap();
+        let generated_witness = compiled_circuit.generate_witness(public_inputs, private_inputs)?;
+        let witness = generated_witness.witness;
+
+        // the constant 1, then the public output, the public input and the private input
+        assert_eq!(witness[..4], [1u64, 7, 2, 3].map(R1csBls12381Field::from));
+
+        let bytes = wtns_to_bytes(&witness);
+        assert_eq!(&bytes[..4], b"wtns");
+        assert_eq!(wtns_from_bytes::<R1csBls12381Field>(&bytes)?, witness);
+
+        // a truncated file, or a witness over another field, is rejected
+        assert!(wtns_from_bytes::<R1csBls12381Field>(&bytes[..bytes.len() - 1]).is_err());
+        assert!(wtns_from_bytes::<R1csBn254Field>(&bytes).is_err());
+
+        Ok(())
+    }
 }
@@ -1,13 +1,12 @@
 use crate::backends::BackendField;
-use crate::error::Result;
 use constraint_writers::r1cs_writer::{ConstraintSection, HeaderData, R1CSWriter};
 
+use ark_ff::FpParameters;
 use std::collections::HashMap;
-use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Seek, SeekFrom, Write};
 use std::vec;
 
 use super::{GeneratedWitness, LinearCombination, R1CS};
+use num_bigint::BigUint;
 use num_bigint_dig::BigInt;
 
 #[derive(Debug)]
@@ -94,16 +93,6 @@ where
         constraints
     }
 
-    /// Restructure the witness vector
-    fn restructure_witness(&self, generated_witness: GeneratedWitness<F>) -> Vec<BigInt> {
-        // convert the witness to BigInt
-        generated_witness
-            .witness
-            .iter()
-            .map(|value| Self::convert_to_bigint(value))
-            .collect()
-    }
-
     /// Generate the r1cs file in snarkjs format.
     /// It uses the circom rust library to generate the r1cs file.
     /// The binary format spec: https://github.com/iden3/r1csfile/blob/master/doc/r1cs_bin_format.md
@@ -150,13 +139,9 @@ where
         R1CSWriter::finish_writing(r1cs);
     }
 
-    /// Generate the wtns file in snarkjs format.
+    /// Generate the wtns file in snarkjs format (see [wtns_to_bytes]).
     pub fn gen_wtns_file(&self, file: &str, witness: GeneratedWitness<F>) {
-        let restructured_witness = self.restructure_witness(witness);
-
-        let mut witness_writer = WitnessWriter::new(file).unwrap();
-
-        witness_writer.write(restructured_witness, &self.backend_prime());
+        std::fs::write(file, wtns_to_bytes(&witness.witness)).unwrap();
     }
 
     fn backend_prime(&self) -> BigInt {
@@ -174,123 +159,148 @@ where
     }
 }
 
-/// Witness writer for generating the wtns file in snarkjs format.
+/// The version of the wtns format written by [wtns_to_bytes].
+const WTNS_VERSION: u32 = 2;
+
+/// Serializes a witness vector to the wtns format of snarkjs, which is read back by [wtns_from_bytes].
 /// The circom rust lib seems not to have the API to generate the wtns file, so we create our own based on the snarkjs lib.
 /// The implementation follows: https://github.com/iden3/snarkjs/blob/577b3f358016a486402050d3b7242876082c085f/src/wtns_utils.js#L25
-/// It uses the same binary format as the r1cs file relies on.
-/// Although it is the same binary file protocol, but the witness file has simpler data points than the r1cs file.
-struct WitnessWriter {
-    inner: BufWriter<File>,
-    writing_section: Option<WritingSection>,
-    section_size_position: u64,
-}
+/// It uses the same binary format as the r1cs file relies on:
+///
+/// - the magic bytes `wtns`, the version ([WTNS_VERSION]) and the number of sections (2), as 4 little-endian bytes each.
+/// - each section starts with its id, as 4 little-endian bytes, and its size in bytes, as 8 little-endian bytes.
+/// - the header section (id 1) contains the size `n8` of a field element in bytes (as 4 little-endian bytes),
+///   the prime in `n8` little-endian bytes, and the number of witness values (as 4 little-endian bytes).
+/// - the witness section (id 2) contains the witness values, each in `n8` little-endian bytes.
+///
+/// The witness vector of the R1CS backend (see [GeneratedWitness]) is ordered like circom's:
+/// the constant `1`, the public outputs, the public inputs,
+/// and then the private inputs and the intermediate values, in the order they were created.
+pub fn wtns_to_bytes<F>(witness: &[F]) -> Vec<u8>
+where
+    F: BackendField,
+{
+    let prime = field_prime::<F>();
+    let field_n_bytes = field_size(&prime);
+
+    let mut bytes = vec![];
+    bytes.extend_from_slice(b"wtns");
+    bytes.extend_from_slice(&WTNS_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&2u32.to_le_bytes());
+
+    // the header section
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(&((4 + field_n_bytes + 4) as u64).to_le_bytes());
+    bytes.extend_from_slice(&(field_n_bytes as u32).to_le_bytes());
+    write_big_int(&mut bytes, &prime, field_n_bytes);
+    bytes.extend_from_slice(&(witness.len() as u32).to_le_bytes());
+
+    // the witness section, where each value occupies the same number of bytes as the prime
+    bytes.extend_from_slice(&2u32.to_le_bytes());
+    bytes.extend_from_slice(&((witness.len() * field_n_bytes) as u64).to_le_bytes());
+    for value in witness {
+        write_big_int(
+            &mut bytes,
+            &SnarkjsExporter::<F>::convert_to_bigint(value),
+            field_n_bytes,
+        );
+    }
 
-/// A struct to represent a section in the snarkjs binary format.
-struct WritingSection;
-
-impl WitnessWriter {
-    // Initialize a FileWriter
-    fn new(path: &str) -> Result<WitnessWriter> {
-        // file type for the witness file
-        let file_type = "wtns";
-        // version of the file format
-        let version = 2u32;
-        // total number of sections
-        let n_sections = 2u32;
-
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(path)
-            .unwrap();
-        let mut writer = BufWriter::new(file);
-
-        // Write the file type (magic string) as bytes
-        let file_type_bytes = file_type.as_bytes();
-        if file_type_bytes.len() != 4 {
-            panic!("File type must be 4 characters long");
-        }
-        writer.write_all(file_type_bytes);
+    bytes
+}
 
-        // Write the version as a 32-bit unsigned integer in little endian
-        writer.write_all(&version.to_le_bytes());
+/// Deserializes a witness vector that was serialized with [wtns_to_bytes] (or generated by snarkjs).
+/// The witness must be over the field `F`.
+pub fn wtns_from_bytes<F>(bytes: &[u8]) -> miette::Result<Vec<F>>
+where
+    F: BackendField,
+{
+    let mut rest = bytes;
 
-        // Write the number of sections as a 32-bit unsigned integer in little endian
-        writer.write_all(&n_sections.to_le_bytes());
+    if take(&mut rest, 4)? != b"wtns" {
+        miette::bail!("the bytes are not a witness file (they don't start with `wtns`)");
+    }
 
-        let current_position = writer.stream_position().unwrap();
+    let version = read_u32(&mut rest)?;
+    if version != WTNS_VERSION {
+        miette::bail!(
+            "the witness file has version {version}, but version {WTNS_VERSION} was expected"
+        );
+    }
 
-        Ok(WitnessWriter {
-            inner: writer,
-            writing_section: None,
-            section_size_position: current_position,
-        })
+    // the sections can come in any order
+    let n_sections = read_u32(&mut rest)?;
+    let mut sections = HashMap::new();
+    for _ in 0..n_sections {
+        let id = read_u32(&mut rest)?;
+        let size = u64::from_le_bytes(take(&mut rest, 8)?.try_into().unwrap());
+        sections.insert(id, take(&mut rest, size as usize)?);
     }
+    if !rest.is_empty() {
+        miette::bail!("the witness file has trailing bytes");
+    }
+
+    let (mut header, mut values) = match (sections.get(&1), sections.get(&2)) {
+        (Some(header), Some(values)) => (*header, *values),
+        _ => miette::bail!("the witness file is missing its header or its witness section"),
+    };
 
-    /// Start a new section for writing.
-    fn start_write_section(&mut self, id_section: u32) {
-        // Write the section ID as ULE32
-        self.inner.write_all(&id_section.to_le_bytes());
-        // Get the current position
-        self.section_size_position = self.inner.stream_position().unwrap();
-        // Temporarily write 0 as ULE64 for the section size
-        self.inner.write_all(&0u64.to_le_bytes());
-        self.writing_section = Some(WritingSection);
+    let field_n_bytes = read_u32(&mut header)? as usize;
+    let prime = BigUint::from_bytes_le(take(&mut header, field_n_bytes)?);
+    let expected_prime: BigUint = F::Params::MODULUS.into();
+    if prime != expected_prime {
+        miette::bail!("the witness is over the field of prime {prime}, but the prime {expected_prime} was expected");
     }
 
-    /// End the current section
-    fn end_write_section(&mut self) {
-        let current_pos = self.inner.stream_position().unwrap();
-        // Calculate the size of the section
-        let section_size = current_pos - self.section_size_position - 8;
-
-        // Move back to where the size needs to be written
-        self.inner.seek(SeekFrom::Start(self.section_size_position));
-        // Write the actual section size
-        self.inner.write_all(&section_size.to_le_bytes());
-        // Return to the end of the section
-        self.inner.seek(SeekFrom::Start(current_pos));
-        // Flush the buffer to ensure all data is written to the file
-        self.inner.flush();
-
-        self.writing_section = None;
+    let len = read_u32(&mut header)? as usize;
+    if values.len() != len * field_n_bytes {
+        miette::bail!(
+            "the witness section should have {len} values of {field_n_bytes} bytes, but it has {} bytes",
+            values.len()
+        );
     }
 
-    /// Write the witness to the file
-    /// It stores the two sections:
-    /// - Header section: describes the field size, prime field, and the number of witnesses.
-    /// - Witness section: contains the witness values.
-    fn write(&mut self, witness: Vec<BigInt>, prime: &BigInt) {
-        // Start writing the first section
-        self.start_write_section(1);
-        // Write field size in number of bytes
-        let field_n_bytes = field_size(prime);
-        self.inner.write_all(&(field_n_bytes as u32).to_le_bytes());
-        // Write the prime field in bytes
-        self.write_big_int(prime.clone(), field_n_bytes);
-        // Write the number of witnesses
-        self.inner.write_all(&(witness.len() as u32).to_le_bytes());
-
-        self.end_write_section();
-
-        // Start writing the second section
-        self.start_write_section(2);
-
-        /// Write the witness values to the file
-        /// Each witness value occupies the same number of bytes as the prime field
-        for value in witness {
-            self.write_big_int(value.clone(), field_n_bytes as usize);
+    let mut witness = Vec::with_capacity(len);
+    for _ in 0..len {
+        let value = BigUint::from_bytes_le(take(&mut values, field_n_bytes)?);
+        match F::try_from(value) {
+            Ok(value) => witness.push(value),
+            Err(_) => miette::bail!("a witness value is not in the field"),
         }
-        self.end_write_section();
     }
 
-    /// Write a BigInt to the file
-    fn write_big_int(&mut self, value: BigInt, size: usize) {
-        let bytes = value.to_bytes_le().1;
+    Ok(witness)
+}
 
-        let mut buffer = vec![0u8; size];
-        buffer[..bytes.len()].copy_from_slice(&bytes);
-        self.inner.write_all(&buffer);
+/// Returns the prime of the field `F`.
+fn field_prime<F>() -> BigInt
+where
+    F: BackendField,
+{
+    let prime: BigUint = F::Params::MODULUS.into();
+    BigInt::from_bytes_le(num_bigint_dig::Sign::Plus, &prime.to_bytes_le())
+}
+
+/// Write a BigInt in `size` little-endian bytes.
+fn write_big_int(bytes: &mut Vec<u8>, value: &BigInt, size: usize) {
+    let value_bytes = value.to_bytes_le().1;
+
+    let mut buffer = vec![0u8; size];
+    buffer[..value_bytes.len()].copy_from_slice(&value_bytes);
+    bytes.extend(buffer);
+}
+
+/// Splits the first `len` bytes off `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> miette::Result<&'a [u8]> {
+    if bytes.len() < len {
+        miette::bail!("the witness file is truncated");
     }
+    let (head, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(head)
+}
+
+/// Reads a 4-byte little-endian integer off `bytes`.
+fn read_u32(bytes: &mut &[u8]) -> miette::Result<u32> {
+    Ok(u32::from_le_bytes(take(bytes, 4)?.try_into().unwrap()))
 }
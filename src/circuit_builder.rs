@@ -0,0 +1,196 @@
+//! A Rust API to build circuits by hand, without writing (and compiling) noname code.
+//!
+//! [CircuitBuilder] is a thin facade over a [Backend]:
+//! inputs are declared by name, and each operation returns the cell holding its result,
+//! which can in turn be passed to other operations.
+//! Once the circuit is [finalized](CircuitBuilder::finalize),
+//! a witness can be generated from the values of the inputs.
+//!
+//! As an example, the following builds the `1 + 1 = 2` circuit on kimchi:
+//!
+//! ```
+//! use noname::{
+//!     backends::kimchi::{KimchiVesta, VestaField},
+//!     circuit_builder::CircuitBuilder,
+//! };
+//!
+//! let mut builder = CircuitBuilder::new(KimchiVesta::new(false));
+//!
+//! // public inputs must be declared first
+//! let two = builder.public_input("two");
+//! let one = builder.private_input("one");
+//!
+//! let sum = builder.add(&one, &one);
+//! builder.assert_eq(&sum, &two);
+//!
+//! let circuit = builder.finalize().unwrap();
+//! let witness = circuit
+//!     .generate_witness([("two", VestaField::from(2u64)), ("one", VestaField::from(1u64))])
+//!     .unwrap();
+//! assert_eq!(witness.full_public_inputs, vec![VestaField::from(2u64)]);
+//! ```
+//!
+//! Every cell must end up in a constraint, like for compiled circuits.
+//! Values that are cheaper to check than to compute can be given by [hints](CircuitBuilder::hint),
+//! as long as the circuit constrains them fully.
+//! Public outputs, builtins and assertion messages are not exposed (yet).
+
+use std::collections::HashMap;
+
+use crate::{
+    backends::Backend,
+    constants::Span,
+    error::Result,
+    var::{ConstOrCell, Value},
+    witness::WitnessEnv,
+};
+
+/// Builds a circuit gate by gate (see the [module documentation](self)).
+pub struct CircuitBuilder<B>
+where
+    B: Backend,
+{
+    backend: B,
+
+    /// Whether something else than a public input was added,
+    /// as backends like kimchi need the public inputs to come first.
+    started: bool,
+}
+
+impl<B> CircuitBuilder<B>
+where
+    B: Backend,
+{
+    pub fn new(mut backend: B) -> Self {
+        backend.init_circuit();
+        Self {
+            backend,
+            started: false,
+        }
+    }
+
+    /// Declares a public input, whose value is given by name when generating the witness.
+    /// Public inputs must be declared before any other cell.
+    pub fn public_input(&mut self, name: &str) -> B::Var {
+        assert!(
+            !self.started,
+            "public inputs must be declared before any other cell"
+        );
+        self.backend
+            .add_public_input(Value::External(name.to_string(), 0), Span::default())
+    }
+
+    /// Declares a private input, whose value is given by name when generating the witness.
+    pub fn private_input(&mut self, name: &str) -> B::Var {
+        self.started = true;
+        self.backend
+            .add_private_input(Value::External(name.to_string(), 0), Span::default())
+    }
+
+    /// Returns a cell constrained to a constant.
+    pub fn constant(&mut self, value: B::Field) -> B::Var {
+        self.started = true;
+        self.backend.add_constant(None, value, Span::default())
+    }
+
+    /// Returns `lhs + rhs`.
+    pub fn add(&mut self, lhs: &B::Var, rhs: &B::Var) -> B::Var {
+        self.started = true;
+        self.backend.add(lhs, rhs, Span::default())
+    }
+
+    /// Returns `lhs - rhs`.
+    pub fn sub(&mut self, lhs: &B::Var, rhs: &B::Var) -> B::Var {
+        self.started = true;
+        self.backend.sub(lhs, rhs, Span::default())
+    }
+
+    /// Returns `lhs * rhs`.
+    pub fn mul(&mut self, lhs: &B::Var, rhs: &B::Var) -> B::Var {
+        self.started = true;
+        self.backend.mul(lhs, rhs, Span::default())
+    }
+
+    /// Returns `-var`.
+    pub fn neg(&mut self, var: &B::Var) -> B::Var {
+        self.started = true;
+        self.backend.neg(var, Span::default())
+    }
+
+    /// Returns `var + cst`.
+    pub fn add_const(&mut self, var: &B::Var, cst: B::Field) -> B::Var {
+        self.started = true;
+        self.backend.add_const(var, &cst, Span::default())
+    }
+
+    /// Returns `var * cst`.
+    pub fn mul_const(&mut self, var: &B::Var, cst: B::Field) -> B::Var {
+        self.started = true;
+        self.backend.mul_const(var, &cst, Span::default())
+    }
+
+    /// Returns a cell computed off-circuit by `hint`, from the values of `inputs`,
+    /// when the witness is generated (see [Backend::new_hint]).
+    /// The cell is not constrained: the other operations of the circuit must pin its value.
+    pub fn hint<H>(&mut self, inputs: &[&B::Var], hint: H) -> B::Var
+    where
+        H: Fn(&[B::Field]) -> Result<B::Field> + 'static,
+    {
+        self.started = true;
+        let inputs = inputs
+            .iter()
+            .map(|&var| ConstOrCell::Cell(var.clone()))
+            .collect();
+        self.backend.new_hint(inputs, hint, Span::default())
+    }
+
+    /// Constrains two cells to be equal.
+    pub fn assert_eq(&mut self, lhs: &B::Var, rhs: &B::Var) {
+        self.started = true;
+        self.backend.assert_eq_var(lhs, rhs, Span::default());
+    }
+
+    /// Constrains a cell to be equal to a constant.
+    pub fn assert_eq_const(&mut self, var: &B::Var, cst: B::Field) {
+        self.started = true;
+        self.backend.assert_eq_const(var, cst, Span::default());
+    }
+
+    /// Finalizes the circuit, which fails if a cell doesn't end up in any constraint.
+    pub fn finalize(mut self) -> Result<BuiltCircuit<B>> {
+        self.backend.finalize_circuit(None, None, Span::default())?;
+        Ok(BuiltCircuit {
+            backend: self.backend,
+        })
+    }
+}
+
+/// A circuit built (and finalized) with a [CircuitBuilder].
+pub struct BuiltCircuit<B>
+where
+    B: Backend,
+{
+    pub backend: B,
+}
+
+impl<B> BuiltCircuit<B>
+where
+    B: Backend,
+{
+    /// Generates the witness of the circuit from the values of its inputs (public and private), by name.
+    pub fn generate_witness<'a>(
+        &self,
+        inputs: impl IntoIterator<Item = (&'a str, B::Field)>,
+    ) -> Result<B::GeneratedWitness> {
+        let mut env = WitnessEnv {
+            var_values: HashMap::from_iter(
+                inputs
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), vec![value])),
+            ),
+            ..Default::default()
+        };
+
+        self.backend.generate_witness(&mut env)
+    }
+}
@@ -0,0 +1,145 @@
+//! A cache of the constraints created by function calls.
+//!
+//! Two calls to the same function create the same constraints, up to the cells they are passed,
+//! as long as they are passed the same constants and compiled in the same context (see [FnCallKey]).
+//! So the constraints of the first call are recorded by the backend (see [Backend::start_recording]),
+//! and spliced in the circuit again (with new cells) for the next calls, instead of compiling the function again.
+//! Backends that can't record constraints, and calls that create cells computed by hints, are always compiled.
+
+use crate::{
+    backends::Backend,
+    constants::Span,
+    parser::types::{FunctionDef, TyKind},
+    var::{ConstOrCell, Var},
+};
+
+use super::{CircuitWriter, VarInfo};
+
+/// Identifies the calls to a function that create the same constraints, up to the cells they are passed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct FnCallKey<F> {
+    /// Where the function is defined, which identifies its source.
+    span: Span,
+
+    /// The types of the arguments, which tell apart the instances of a generic function.
+    arguments: Vec<TyKind>,
+
+    /// Each field element passed to the function.
+    args: Vec<ArgCell<F>>,
+
+    /// The label of the constraints (see [Backend::set_label]).
+    label: Option<String>,
+
+    /// Whether the constraints can be packed (see [Backend::set_double_generic]).
+    double_generic: bool,
+}
+
+/// A field element passed to a function.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ArgCell<F> {
+    /// A constant, which the function might fold.
+    Const(F),
+
+    /// A cell, given by its position among the distinct cells passed,
+    /// as the function might create other constraints if it's passed the same cell twice.
+    Cell(usize),
+}
+
+/// A call whose constraints can be spliced for the next calls with the same [FnCallKey].
+#[derive(Debug)]
+pub(crate) struct CachedFnCall<B>
+where
+    B: Backend,
+{
+    pub(crate) recording: B::Recording,
+
+    /// The value returned by the call.
+    pub(crate) returned: Option<Var<B::Field, B::Var>>,
+
+    /// The number of loop iterations unrolled by the call (see [super::CircuitOptions::loop_budget]).
+    pub(crate) loop_iterations: usize,
+}
+
+impl<B: Backend> CachedFnCall<B> {
+    /// Splices the constraints of the call, with the cells `inputs` as arguments, and returns the value it returns.
+    pub(crate) fn splice(
+        &self,
+        backend: &mut B,
+        inputs: &[B::Var],
+    ) -> Option<Var<B::Field, B::Var>> {
+        let returned_cells: Vec<_> = self
+            .returned
+            .iter()
+            .flat_map(|var| &var.cvars)
+            .filter_map(|cvar| match cvar {
+                ConstOrCell::Const(_) => None,
+                ConstOrCell::Cell(cell) => Some(cell.clone()),
+            })
+            .collect();
+        let mut spliced = backend
+            .splice(&self.recording, inputs, &returned_cells)
+            .into_iter();
+
+        self.returned.as_ref().map(|var| {
+            let cvars = var
+                .cvars
+                .iter()
+                .map(|cvar| match cvar {
+                    ConstOrCell::Const(cst) => ConstOrCell::Const(*cst),
+                    ConstOrCell::Cell(_) => ConstOrCell::Cell(
+                        spliced
+                            .next()
+                            .expect("the backend splices as many cells as it's given"),
+                    ),
+                })
+                .collect();
+            Var::new(cvars, var.span)
+        })
+    }
+}
+
+impl<B: Backend> CircuitWriter<B> {
+    /// Returns the key of a call to a function (see [FnCallKey]), along with the distinct cells passed to it,
+    /// or `None` if the constraints of the call can't be reused.
+    pub(crate) fn fn_call_key(
+        &self,
+        function: &FunctionDef,
+        args: &[VarInfo<B::Field, B::Var>],
+    ) -> Option<(FnCallKey<B::Field>, Vec<B::Var>)> {
+        // the assertions of a function called in a branch depend on the condition of the branch
+        if !self.branch_conditions.is_empty() {
+            return None;
+        }
+
+        let mut inputs: Vec<B::Var> = vec![];
+        let mut arg_cells = vec![];
+        for cvar in args.iter().flat_map(|arg| &arg.var.cvars) {
+            let arg_cell = match cvar {
+                ConstOrCell::Const(cst) => ArgCell::Const(*cst),
+                ConstOrCell::Cell(cell) => match inputs.iter().position(|input| input == cell) {
+                    Some(position) => ArgCell::Cell(position),
+                    None => {
+                        inputs.push(cell.clone());
+                        ArgCell::Cell(inputs.len() - 1)
+                    }
+                },
+            };
+            arg_cells.push(arg_cell);
+        }
+
+        let key = FnCallKey {
+            span: function.span,
+            arguments: function
+                .sig
+                .arguments
+                .iter()
+                .map(|arg| arg.typ.kind.clone())
+                .collect(),
+            args: arg_cells,
+            label: self.labels.last().cloned(),
+            double_generic: self.backend.double_generic(),
+        };
+
+        Some((key, inputs))
+    }
+}
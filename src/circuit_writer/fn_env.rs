@@ -75,6 +75,14 @@ where
     /// This needs to be garbage collected when we exit a scope.
     /// Note: The `usize` is the scope in which the variable was created.
     vars: HashMap<String, (usize, VarInfo<F, C>)>,
+
+    /// The last value bound to each variable name, including the ones that went out of scope.
+    /// This is only used to retrieve the values of variables when debugging a witness.
+    bindings: HashMap<String, Var<F, C>>,
+
+    /// Once a `return` has been compiled in the body of an `if` statement,
+    /// a boolean cell that is set if the function returned early, and the value it returned.
+    pub(crate) early_return: Option<(C, Var<F, C>)>,
 }
 
 impl<F: BackendField, C: BackendVar> FnEnv<F, C> {
@@ -83,6 +91,8 @@ impl<F: BackendField, C: BackendVar> FnEnv<F, C> {
         Self {
             current_scope: 0,
             vars: HashMap::new(),
+            bindings: HashMap::new(),
+            early_return: None,
         }
     }
 
@@ -119,6 +129,8 @@ impl<F: BackendField, C: BackendVar> FnEnv<F, C> {
     pub fn add_local_var(&mut self, var_name: String, var_info: VarInfo<F, C>) {
         let scope = self.current_scope;
 
+        self.bindings.insert(var_name.clone(), var_info.var.clone());
+
         if self
             .vars
             .insert(var_name.clone(), (scope, var_info))
@@ -159,6 +171,8 @@ impl<F: BackendField, C: BackendVar> FnEnv<F, C> {
         }
 
         let var_info = var_info.reassign(var);
+        self.bindings
+            .insert(var_name.to_string(), var_info.var.clone());
         self.vars.insert(var_name.to_string(), (*scope, var_info));
     }
 
@@ -179,6 +193,26 @@ impl<F: BackendField, C: BackendVar> FnEnv<F, C> {
         }
 
         let var_info = var_info.reassign_range(var, start, len);
+        self.bindings
+            .insert(var_name.to_string(), var_info.var.clone());
         self.vars.insert(var_name.to_string(), (*scope, var_info));
     }
+
+    /// Returns the values of the mutable variables in scope, sorted by name
+    /// (so that the constraints created from them are always the same).
+    pub fn mutable_vars(&self) -> Vec<(String, Var<F, C>)> {
+        let mut vars: Vec<_> = self
+            .vars
+            .iter()
+            .filter(|(_, (scope, var_info))| self.is_in_scope(*scope) && var_info.mutable)
+            .map(|(name, (_, var_info))| (name.clone(), var_info.var.clone()))
+            .collect();
+        vars.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+        vars
+    }
+
+    /// Returns the last value bound to each variable name seen in this environment.
+    pub fn take_bindings(&mut self) -> HashMap<String, Var<F, C>> {
+        std::mem::take(&mut self.bindings)
+    }
 }
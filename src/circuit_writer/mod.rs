@@ -1,24 +1,100 @@
+use std::collections::{HashMap, HashSet};
+
 use crate::{
     backends::Backend,
     constants::Span,
-    error::{Error, ErrorKind, Result},
+    error::{Error, ErrorKind, Result, Warning, WarningKind},
     parser::{
-        types::{AttributeKind, FnArg, TyKind},
+        types::{AttributeKind, FnArg, ModulePath, Range, RangeBound, TyKind},
         Expr,
     },
-    type_checker::{ConstInfo, FnInfo, FullyQualified, StructInfo, TypeChecker},
-    var::Var,
+    type_checker::{ConstInfo, ConstOverride, FnInfo, FullyQualified, StructInfo, TypeChecker},
+    var::{ConstOrCell, Var},
     witness::{CompiledCircuit, WitnessEnv},
 };
 
+use fn_cache::{CachedFnCall, FnCallKey};
 pub use fn_env::{FnEnv, VarInfo};
 use serde::{Deserialize, Serialize};
 //use serde::{Deserialize, Serialize};
-pub use writer::{Gate, GateKind, Wiring};
+pub use writer::{Constraint, Gate, GateKind, Wiring, RANGE_CHECK_LIMBS};
 
+pub mod fn_cache;
 pub mod fn_env;
 pub mod writer;
 
+/// The optimization passes run on the gates once the circuit has been generated
+/// (see [Backend::optimize]).
+/// Note that constant expressions are always folded, whatever the level,
+/// as some operations (like negation and division) are only supported on constants.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OptLevel {
+    /// No optimization.
+    O0,
+
+    /// Gates are packed whenever the backend allows it (e.g. two generic gates per row in kimchi).
+    O1,
+
+    /// Identical gates are also deduplicated, and their outputs reused.
+    #[default]
+    O2,
+
+    /// Gates computing a linear function of a single variable (like `x + 1`) are also merged
+    /// into the next gate when it's the only one reading their result.
+    O3,
+}
+
+impl OptLevel {
+    /// Returns the level corresponding to a number, if there's one.
+    pub fn from_u8(level: u8) -> Option<Self> {
+        match level {
+            0 => Some(Self::O0),
+            1 => Some(Self::O1),
+            2 => Some(Self::O2),
+            3 => Some(Self::O3),
+            _ => None,
+        }
+    }
+}
+
+/// The default maximum number of loop iterations unrolled in a circuit (see [CircuitOptions::loop_budget]).
+pub const DEFAULT_LOOP_BUDGET: usize = 100_000;
+
+/// The options of the circuit generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CircuitOptions {
+    /// The optimization passes run on the gates.
+    pub opt_level: OptLevel,
+
+    /// The maximum number of loop iterations unrolled in the whole circuit,
+    /// so that huge loops are reported instead of hanging the compiler.
+    /// Iterations of nested loops and of loops in called functions all count.
+    pub loop_budget: usize,
+
+    /// An advanced mode, which weakens the circuit: if set, all the equality constraints created in a branch
+    /// (of an `if`/`else` expression or an `if` statement, or a value passed to `select`) are multiplied
+    /// by the condition of the branch, so that they're vacuously satisfied when the branch isn't taken.
+    /// By default, only assertions (and the constraints of `div` and `sqrt`) are conditioned, and the other constraints of a branch
+    /// (like range checks or the bounds of an array access) must hold in both branches,
+    /// so the prover must be able to compute a valid witness for the branch that isn't taken.
+    ///
+    /// The branch taken is still fully constrained, but the values computed in the other branch are not:
+    /// they must not be used outside of it, apart from being discarded by the selection of the result.
+    /// Lookups and kimchi's custom gates (range checks and Poseidon rounds), which aren't equality constraints,
+    /// are still enforced in both branches, and each relaxed constraint costs a multiplication by the condition.
+    pub relaxed_branches: bool,
+}
+
+impl Default for CircuitOptions {
+    fn default() -> Self {
+        Self {
+            opt_level: OptLevel::default(),
+            loop_budget: DEFAULT_LOOP_BUDGET,
+            relaxed_branches: false,
+        }
+    }
+}
+
 //#[derive(Debug, Serialize, Deserialize)]
 #[derive(Debug)]
 pub struct CircuitWriter<B>
@@ -45,6 +121,42 @@ where
     /// 3. During witness generation, the public output computation
     ///    is delayed until the very end.
     pub(crate) public_output: Option<Var<B::Field, B::Var>>,
+
+    /// The conditions of the `if`/`else` branches being compiled (from outermost to innermost),
+    /// along with the value of the condition for which each branch is taken.
+    /// Assertions use them so that they only have to hold in the branches that are taken
+    /// (see [CircuitWriter::branch_condition]).
+    pub(crate) branch_conditions: Vec<(B::Var, bool)>,
+
+    /// The labels of the statements being compiled (from outermost to innermost).
+    /// The innermost one labels the constraints created (see [Backend::set_label]).
+    pub(crate) labels: Vec<String>,
+
+    /// The arguments and local variables of the main function, by name
+    /// (see [crate::witness::CompiledCircuit::witness_env]).
+    pub(crate) main_bindings: HashMap<String, Var<B::Field, B::Var>>,
+
+    /// The maximum number of loop iterations that can be unrolled (see [CircuitOptions::loop_budget]).
+    pub(crate) loop_budget: usize,
+
+    /// The number of loop iterations unrolled so far.
+    pub(crate) loop_iterations: usize,
+
+    /// The optimization level (see [CircuitOptions::opt_level]),
+    /// which also decides whether `debug_assert`s are compiled.
+    pub(crate) opt_level: OptLevel,
+
+    /// Whether the constraints of the branches are relaxed (see [CircuitOptions::relaxed_branches]).
+    pub(crate) relaxed_branches: bool,
+
+    /// The warnings emitted while compiling the circuit.
+    pub(crate) warnings: Vec<Warning>,
+
+    /// The function calls whose constraints can be spliced for the next calls (see [fn_cache]).
+    pub(crate) fn_cache: HashMap<FnCallKey<B::Field>, CachedFnCall<B>>,
+
+    /// The number of function calls that were spliced from [Self::fn_cache] instead of being compiled.
+    pub(crate) fn_cache_hits: usize,
 }
 
 /// Debug information related to a single row in a circuit.
@@ -55,6 +167,9 @@ pub struct DebugInfo {
 
     /// A note on why this was added
     pub note: String,
+
+    /// The message of the assertion that created this, if any (e.g. `assert(cond, "message")`).
+    pub message: Option<String>,
 }
 
 impl<B: Backend> CircuitWriter<B> {
@@ -75,6 +190,10 @@ impl<B: Backend> CircuitWriter<B> {
         self.typed.fn_info(qualified)
     }
 
+    pub fn called_fn(&self, call: &Expr, module: &ModulePath, fn_name: &String) -> FullyQualified {
+        self.typed.called_fn(call, module, fn_name)
+    }
+
     pub fn const_info(&self, qualified: &FullyQualified) -> Option<&ConstInfo<B::Field>> {
         self.typed.const_info(qualified)
     }
@@ -83,6 +202,14 @@ impl<B: Backend> CircuitWriter<B> {
         self.typed.size_of(typ)
     }
 
+    pub fn range_iterations(&self, range: &Range) -> Result<std::ops::Range<u32>> {
+        self.typed.range_iterations(range)
+    }
+
+    pub fn bound_value(&self, bound: &RangeBound) -> Result<u32> {
+        self.typed.bound_value(bound)
+    }
+
     pub fn add_local_var(
         &self,
         fn_env: &mut FnEnv<B::Field, B::Var>,
@@ -110,7 +237,7 @@ impl<B: Backend> CircuitWriter<B> {
         let qualified = FullyQualified::local(var_name.to_string());
         if let Some(cst_info) = self.typed.const_info(&qualified) {
             let var = Var::new_constant_typ(cst_info, cst_info.typ.span);
-            return VarInfo::new(var, false, Some(TyKind::Field));
+            return VarInfo::new(var, false, Some(cst_info.typ.kind.clone()));
         }
 
         // then check for local variables
@@ -134,17 +261,39 @@ impl<B: Backend> CircuitWriter<B> {
 
 impl<B: Backend> CircuitWriter<B> {
     /// Creates a global environment from the one created by the type checker.
-    fn new(typed: TypeChecker<B>, backend: B) -> Self {
+    fn new(typed: TypeChecker<B>, backend: B, options: CircuitOptions) -> Self {
         Self {
             typed,
             backend,
             public_output: None,
+            branch_conditions: vec![],
+            labels: vec![],
+            main_bindings: HashMap::new(),
+            loop_budget: options.loop_budget,
+            loop_iterations: 0,
+            opt_level: options.opt_level,
+            relaxed_branches: options.relaxed_branches,
+            warnings: vec![],
+            fn_cache: HashMap::new(),
+            fn_cache_hits: 0,
         }
     }
 
-    pub fn generate_circuit(typed: TypeChecker<B>, backend: B) -> Result<CompiledCircuit<B>> {
+    /// Compiles the type checked program to a circuit.
+    /// The values of some constants of the main module can be overridden,
+    /// to compile different instances of the same program (see [TypeChecker::override_constants]).
+    pub fn generate_circuit(
+        mut typed: TypeChecker<B>,
+        backend: B,
+        options: CircuitOptions,
+        constants: Option<&HashMap<String, ConstOverride<B::Field>>>,
+    ) -> Result<CompiledCircuit<B>> {
+        if let Some(constants) = constants {
+            typed.override_constants(constants)?;
+        }
+
         // create circuit writer
-        let mut circuit_writer = CircuitWriter::new(typed, backend);
+        let mut circuit_writer = CircuitWriter::new(typed, backend, options);
 
         // get main function
         let qualified = FullyQualified::local("main".to_string());
@@ -163,12 +312,8 @@ impl<B: Backend> CircuitWriter<B> {
 
         // create public output
         if let Some(typ) = &function.sig.return_type {
-            if typ.kind != TyKind::Field {
-                unimplemented!();
-            }
-
-            // create it
-            circuit_writer.add_public_outputs(1, typ.span);
+            // create it (one cell per field element of the returned type)
+            circuit_writer.add_public_outputs(&typ.kind, typ.span);
         }
 
         // public inputs should be handled first
@@ -176,9 +321,8 @@ impl<B: Backend> CircuitWriter<B> {
             match &arg.attribute {
                 Some(attr) => {
                     if !matches!(attr.kind, AttributeKind::Pub) {
-                        return Err(
-                            circuit_writer.error(ErrorKind::InvalidAttribute(attr.kind), attr.span)
-                        );
+                        return Err(circuit_writer
+                            .error(ErrorKind::InvalidAttribute(attr.kind.clone()), attr.span));
                     }
                 }
                 None => panic!("public arguments must have a pub attribute"),
@@ -193,6 +337,7 @@ impl<B: Backend> CircuitWriter<B> {
 
         // compile function
         let returned_cells = circuit_writer.compile_main_function(fn_env, &function)?;
+        circuit_writer.main_bindings = fn_env.take_bindings();
         let main_span = circuit_writer.main_info().unwrap().span;
         let public_output = circuit_writer.public_output.clone();
 
@@ -200,19 +345,73 @@ impl<B: Backend> CircuitWriter<B> {
         if let Some(public_output) = &public_output {
             let cvars = &public_output.cvars;
 
-            for (pub_var, ret_var) in cvars.iter().zip(&returned_cells.clone().unwrap()) {
+            // every cell of the public output must be set to a returned cell
+            let returned = returned_cells.as_deref().unwrap_or_default();
+            if returned.len() != cvars.len() {
+                return Err(circuit_writer.error(ErrorKind::MissingReturn, main_span));
+            }
+
+            for (pub_var, ret_var) in cvars.iter().zip(returned) {
                 circuit_writer
                     .backend
                     .assert_eq_var(pub_var.cvar().unwrap(), ret_var, main_span);
             }
         }
 
+        circuit_writer.backend.optimize(options.opt_level);
+
+        // private inputs that were only used in stripped `debug_assert`s are not in the circuit anymore
+        let stripped_debug_asserts = circuit_writer
+            .warnings
+            .iter()
+            .any(|warning| matches!(warning.kind, WarningKind::DebugAssertStripped));
         circuit_writer
             .backend
-            .finalize_circuit(public_output, returned_cells, main_span)?;
+            .finalize_circuit(public_output, returned_cells, main_span)
+            .map_err(|err| match err.kind {
+                ErrorKind::PrivateInputNotUsed(names) if stripped_debug_asserts => Error::new(
+                    err.label,
+                    ErrorKind::PrivateInputOnlyUsedInDebugAssert(names),
+                    err.span,
+                ),
+                _ => err,
+            })?;
+
+        // the prover can set the public inputs that aren't constrained to anything
+        let mut warnings = std::mem::take(&mut circuit_writer.warnings);
+        warnings.extend(
+            circuit_writer
+                .backend
+                .unused_public_inputs()
+                .into_iter()
+                .map(|(name, span)| {
+                    Warning::new(
+                        "constraint-finalization",
+                        WarningKind::UnusedPublicInput(name),
+                        span,
+                    )
+                }),
+        );
+
+        // the constraints that can't fail, once per span (e.g. for a function called many times)
+        let mut trivial_spans = HashSet::new();
+        warnings.extend(
+            circuit_writer
+                .backend
+                .trivially_satisfied_constraints()
+                .into_iter()
+                .filter(|span| trivial_spans.insert(*span))
+                .map(|span| {
+                    Warning::new(
+                        "constraint-finalization",
+                        WarningKind::TriviallySatisfiedConstraint,
+                        span,
+                    )
+                }),
+        );
 
         //
-        Ok(CompiledCircuit::new(circuit_writer))
+        Ok(CompiledCircuit::new(circuit_writer, warnings))
     }
 
     /// A wrapper for the backend generate_witness
@@ -223,6 +422,28 @@ impl<B: Backend> CircuitWriter<B> {
         self.backend.generate_witness(witness_env)
     }
 
+    /// Computes the values of the arguments and local variables of the main function,
+    /// and stores them in the witness environment.
+    pub(crate) fn compute_named_values(
+        &self,
+        witness_env: &mut WitnessEnv<B::Field>,
+    ) -> Result<()> {
+        for (name, var) in &self.main_bindings {
+            let values = var
+                .cvars
+                .iter()
+                .map(|cvar| match cvar {
+                    ConstOrCell::Const(cst) => Ok(*cst),
+                    ConstOrCell::Cell(cvar) => self.backend.compute_var(witness_env, cvar),
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            witness_env.named_values.insert(name.clone(), values);
+        }
+
+        Ok(())
+    }
+
     fn handle_arg(
         &mut self,
         arg: &FnArg,
@@ -234,12 +455,7 @@ impl<B: Backend> CircuitWriter<B> {
         // get length
         let len = match &typ.kind {
             TyKind::Field => 1,
-            TyKind::Array(typ, len) => {
-                if !matches!(**typ, TyKind::Field) {
-                    unimplemented!();
-                }
-                *len as usize
-            }
+            TyKind::Array(el_typ, len) => (*len as usize) * self.size_of(el_typ),
             TyKind::Bool => 1,
             typ => self.size_of(typ),
         };
@@ -254,11 +470,14 @@ impl<B: Backend> CircuitWriter<B> {
         // but we are being extra cautious due to attacks
         // where the prover gives the verifier malformed inputs that look legit.
         // (See short address attacks in Ethereum.)
-        self.constrain_inputs_to_main(&var.cvars, &typ.kind, typ.span)?;
+        // public inputs annotated with `#[unchecked]` opt out of this,
+        // leaving it to the verifier to check them.
+        if arg.unchecked.is_none() {
+            self.constrain_inputs_to_main(&var.cvars, &typ.kind, typ.span)?;
+        }
 
         // add argument variable to the ast env
-        let mutable = false; // TODO: should we add a mut keyword in arguments as well?
-        let var_info = VarInfo::new(var, mutable, Some(typ.kind.clone()));
+        let var_info = VarInfo::new(var, arg.mutable, Some(typ.kind.clone()));
         self.add_local_var(fn_env, name.value.clone(), var_info);
 
         Ok(())
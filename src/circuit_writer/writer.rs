@@ -1,23 +1,29 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
 
 use ark_ff::{One, Zero};
 use kimchi::circuits::wires::Wire;
 use num_bigint::BigUint;
-use num_traits::Num as _;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    backends::{kimchi::VestaField, Backend},
-    circuit_writer::{CircuitWriter, DebugInfo, FnEnv, VarInfo},
+    backends::{Backend, BackendField},
+    circuit_writer::{fn_cache::CachedFnCall, CircuitWriter, DebugInfo, FnEnv, OptLevel, VarInfo},
     constants::Span,
     constraints::{boolean, field},
-    error::{ErrorKind, Result},
+    error::{ErrorKind, Result, Warning, WarningKind},
     imports::FnKind,
     parser::{
         types::{FunctionDef, Stmt, StmtKind, TyKind},
         Expr, ExprKind, Op2,
     },
-    syntax::is_type,
+    stdlib::{
+        self, crypto::is_merkle_verify_with, is_debug_assert, is_fold, is_select,
+        split_assert_message,
+    },
+    syntax::{is_type, parse_number},
     type_checker::FullyQualified,
     var::{ConstOrCell, Value, Var, VarOrRef},
 };
@@ -26,11 +32,13 @@ use crate::{
 // Data structures
 //
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GateKind {
     Zero,
     DoubleGeneric,
     Poseidon,
+    Lookup,
+    RangeCheck,
 }
 
 impl From<GateKind> for kimchi::circuits::gate::GateType {
@@ -40,29 +48,179 @@ impl From<GateKind> for kimchi::circuits::gate::GateType {
             GateKind::Zero => Zero,
             GateKind::DoubleGeneric => Generic,
             GateKind::Poseidon => Poseidon,
+            GateKind::Lookup => Lookup,
+            GateKind::RangeCheck => RangeCheck0,
         }
     }
 }
 
+/// The `(offset, bits)` of the limbs that a [GateKind::RangeCheck] gate decomposes the value of its first cell into,
+/// from its second cell onwards.
+/// The gate checks 88 bits (the six 12-bit limbs are looked up in a table),
+/// so the two highest limbs are wired to zero to check 64 bits.
+pub const RANGE_CHECK_LIMBS: [(usize, u64); 14] = [
+    (76, 12),
+    (64, 12),
+    (52, 12),
+    (40, 12),
+    (28, 12),
+    (16, 12),
+    (14, 2),
+    (12, 2),
+    (10, 2),
+    (8, 2),
+    (6, 2),
+    (4, 2),
+    (2, 2),
+    (0, 2),
+];
+
 // TODO: this could also contain the span that defined the gate!
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Gate {
+pub struct Gate<F>
+where
+    F: BackendField,
+{
     /// Type of gate
     pub typ: GateKind,
 
     /// Coefficients
     #[serde(skip)]
-    pub coeffs: Vec<VestaField>,
+    pub coeffs: Vec<F>,
 }
 
-impl Gate {
-    pub fn to_kimchi_gate(&self, row: usize) -> kimchi::circuits::gate::CircuitGate<VestaField> {
+impl<F> Gate<F>
+where
+    F: BackendField,
+{
+    pub fn to_kimchi_gate(&self, row: usize) -> kimchi::circuits::gate::CircuitGate<F> {
         kimchi::circuits::gate::CircuitGate {
             typ: self.typ.into(),
             wires: Wire::for_row(row),
             coeffs: self.coeffs.clone(),
         }
     }
+
+    /// Checks the gate against the values of its cells (missing cells are treated as zero).
+    /// Only generic gates and range checks can be checked from their cells alone,
+    /// for all other gates this returns `None` and we trust the gadgets that created them.
+    pub fn is_satisfied(&self, cells: &[F]) -> Option<bool> {
+        match self.typ {
+            GateKind::DoubleGeneric => {
+                let c = |i| self.coeffs.get(i).copied().unwrap_or_else(F::zero);
+                let w = |i| cells.get(i).copied().unwrap_or_else(F::zero);
+
+                let sum2 = c(5) * w(3) + c(6) * w(4) + c(7) * w(5) + c(8) * w(3) * w(4) + c(9);
+
+                Some(self.is_first_half_satisfied(cells) && sum2.is_zero())
+            }
+            GateKind::RangeCheck => {
+                let w = |i| -> BigUint { cells.get(i).copied().unwrap_or_else(F::zero).into() };
+
+                let mut sum = BigUint::zero();
+                for (col, (offset, bits)) in (1..).zip(RANGE_CHECK_LIMBS) {
+                    let limb = w(col);
+                    if limb.bits() > bits {
+                        return Some(false);
+                    }
+                    sum += limb << offset;
+                }
+
+                // the two highest limbs are wired to zero
+                Some(sum == w(0) && w(1).is_zero() && w(2).is_zero())
+            }
+            GateKind::Zero | GateKind::Poseidon | GateKind::Lookup => None,
+        }
+    }
+
+    /// Checks the first of the two generic gates of a [GateKind::DoubleGeneric] row,
+    /// which reads the first three cells and the first five coefficients.
+    pub fn is_first_half_satisfied(&self, cells: &[F]) -> bool {
+        let c = |i| self.coeffs.get(i).copied().unwrap_or_else(F::zero);
+        let w = |i| cells.get(i).copied().unwrap_or_else(F::zero);
+
+        (c(0) * w(0) + c(1) * w(1) + c(2) * w(2) + c(3) * w(0) * w(1) + c(4)).is_zero()
+    }
+
+    /// Returns the constraints enforced by the gate (see [Constraint]),
+    /// given the variables in its cells (the empty cells are zero).
+    /// Only generic gates are polynomials of their cells (and zero gates constrain nothing),
+    /// for all other gates this returns `None`.
+    pub fn constraints(&self, vars: &[Option<usize>], span: Span) -> Option<Vec<Constraint<F>>> {
+        match self.typ {
+            GateKind::DoubleGeneric => {
+                let c = |i| self.coeffs.get(i).copied().unwrap_or_else(F::zero);
+                let w = |i| vars.get(i).copied().flatten();
+
+                // `c0 * w0 + c1 * w1 + c2 * w2 + c3 * w0 * w1 + c4` for each half,
+                // given by the index of its first coefficient and of its first cell
+                let constraints = [(0, 0), (5, 3)]
+                    .into_iter()
+                    .map(|(c0, w0)| {
+                        let terms = [
+                            (c(c0), vec![w(w0)]),
+                            (c(c0 + 1), vec![w(w0 + 1)]),
+                            (c(c0 + 2), vec![w(w0 + 2)]),
+                            (c(c0 + 3), vec![w(w0), w(w0 + 1)]),
+                            (c(c0 + 4), vec![]),
+                        ];
+
+                        // a term reading an empty cell is zero
+                        let terms = terms.into_iter().filter_map(|(coeff, vars)| {
+                            vars.into_iter()
+                                .collect::<Option<Vec<_>>>()
+                                .map(|vars| (coeff, vars))
+                        });
+                        Constraint::new(terms, span)
+                    })
+                    .filter(|constraint| !constraint.terms.is_empty())
+                    .collect();
+
+                Some(constraints)
+            }
+            GateKind::Zero => Some(vec![]),
+            GateKind::Poseidon | GateKind::Lookup | GateKind::RangeCheck => None,
+        }
+    }
+}
+
+/// A constraint of the circuit, as a polynomial over its variables that must be zero:
+/// a sum of terms, each a coefficient times a product of variables (given by their index).
+/// For example, `[(1, [0, 1]), (-1, [2])]` is the constraint `v0 * v1 - v2 = 0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint<F>
+where
+    F: BackendField,
+{
+    /// The terms of the polynomial, in the order of their (sorted) variables.
+    /// A term without variables is a constant.
+    pub terms: Vec<(F, Vec<usize>)>,
+
+    /// Where the constraint comes from in the source.
+    pub span: Span,
+}
+
+impl<F> Constraint<F>
+where
+    F: BackendField,
+{
+    /// Creates a constraint from terms in any order,
+    /// by summing the terms that are a product of the same variables and removing the zero terms.
+    pub(crate) fn new(terms: impl IntoIterator<Item = (F, Vec<usize>)>, span: Span) -> Self {
+        let mut monomials: BTreeMap<Vec<usize>, F> = BTreeMap::new();
+        for (coeff, mut vars) in terms {
+            vars.sort_unstable();
+            *monomials.entry(vars).or_insert_with(F::zero) += coeff;
+        }
+
+        let terms = monomials
+            .into_iter()
+            .filter(|(_, coeff)| !coeff.is_zero())
+            .map(|(vars, coeff)| (coeff, vars))
+            .collect();
+
+        Self { terms, span }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -118,9 +276,69 @@ impl<B: Backend> CircuitWriter<B> {
         &mut self,
         fn_env: &mut FnEnv<B::Field, B::Var>,
         stmt: &Stmt,
+    ) -> Result<Option<VarOrRef<B>>> {
+        if !stmt.no_double_gate {
+            return self.compile_labeled_stmt(fn_env, stmt);
+        }
+
+        let double_generic = self.backend.set_double_generic(false);
+        let res = self.compile_labeled_stmt(fn_env, stmt);
+        self.backend.set_double_generic(double_generic);
+
+        res
+    }
+
+    /// Compiles the body of a function, without packing its constraints if it is marked `#[no_double_gate]`.
+    fn compile_fn_body(
+        &mut self,
+        fn_env: &mut FnEnv<B::Field, B::Var>,
+        function: &FunctionDef,
+    ) -> Result<Option<Var<B::Field, B::Var>>> {
+        if !function.no_double_gate() {
+            return self.compile_block(fn_env, &function.body);
+        }
+
+        let double_generic = self.backend.set_double_generic(false);
+        let res = self.compile_block(fn_env, &function.body);
+        self.backend.set_double_generic(double_generic);
+
+        res
+    }
+
+    fn compile_labeled_stmt(
+        &mut self,
+        fn_env: &mut FnEnv<B::Field, B::Var>,
+        stmt: &Stmt,
+    ) -> Result<Option<VarOrRef<B>>> {
+        let label = match &stmt.label {
+            Some(label) => label,
+            None => return self.compile_stmt_kind(fn_env, stmt),
+        };
+
+        // the constraints created by a labeled statement carry its label
+        self.labels.push(label.clone());
+        self.backend.set_label(Some(label.clone()));
+
+        let res = self.compile_stmt_kind(fn_env, stmt);
+
+        self.labels.pop();
+        self.backend.set_label(self.labels.last().cloned());
+
+        res
+    }
+
+    fn compile_stmt_kind(
+        &mut self,
+        fn_env: &mut FnEnv<B::Field, B::Var>,
+        stmt: &Stmt,
     ) -> Result<Option<VarOrRef<B>>> {
         match &stmt.kind {
-            StmtKind::Assign { mutable, lhs, rhs } => {
+            StmtKind::Assign {
+                mutable,
+                lhs,
+                typ: annotation,
+                rhs,
+            } => {
                 // compute the rhs
                 let rhs_var = self
                     .compute_expr(fn_env, rhs)?
@@ -129,7 +347,14 @@ impl<B: Backend> CircuitWriter<B> {
                 // obtain the actual values
                 let rhs_var = rhs_var.value(self, fn_env);
 
-                let typ = self.expr_type(rhs).cloned();
+                let mut typ = self.expr_type(rhs).cloned();
+
+                // a constant bound to an integer must fit in it
+                if let (Some(annotation), Some(TyKind::BigInt)) = (annotation, &typ) {
+                    self.constrain_int(&rhs_var[0], &annotation.kind, rhs.span)?;
+                    typ = Some(annotation.kind.clone());
+                }
+
                 let var_info = VarInfo::new(rhs_var, *mutable, typ);
 
                 // store the new variable
@@ -137,8 +362,48 @@ impl<B: Backend> CircuitWriter<B> {
                 self.add_local_var(fn_env, lhs.value.clone(), var_info);
             }
 
+            StmtKind::AssignTuple { lhs, rhs } => {
+                // compute the rhs
+                let rhs_var = self
+                    .compute_expr(fn_env, rhs)?
+                    .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, stmt.span))?;
+                let rhs_var = rhs_var.value(self, fn_env);
+
+                let typs = match self.expr_type(rhs) {
+                    Some(TyKind::Tuple(typs)) => typs.clone(),
+                    _ => {
+                        return Err(self.error(
+                            ErrorKind::UnexpectedError("destructuring a non-tuple"),
+                            stmt.span,
+                        ))
+                    }
+                };
+
+                // split the cvars of the tuple according to the size of each member
+                let mut offset = 0;
+                for (var_name, typ) in lhs.iter().zip(typs) {
+                    let len = self.size_of(&typ);
+                    let var = Var::new(rhs_var.range(offset, len).to_vec(), var_name.span);
+                    offset += len;
+
+                    let var_info = VarInfo::new(var, false, Some(typ));
+                    self.add_local_var(fn_env, var_name.value.clone(), var_info);
+                }
+            }
+
             StmtKind::ForLoop { var, range, body } => {
-                for ii in range.range() {
+                let range = self.range_iterations(range)?;
+
+                // the loop is unrolled, so make sure it doesn't take forever
+                let iterations = range.len();
+                if self.loop_iterations + iterations > self.loop_budget {
+                    return Err(
+                        self.error(ErrorKind::LoopBudgetExceeded(self.loop_budget), stmt.span)
+                    );
+                }
+                self.loop_iterations += iterations;
+
+                for ii in range {
                     fn_env.nest();
 
                     let cst_var = Var::new_constant(ii.into(), var.span);
@@ -150,6 +415,55 @@ impl<B: Backend> CircuitWriter<B> {
                     fn_env.pop();
                 }
             }
+            StmtKind::If { cond, body } => {
+                let cond_var = self.compute_condition(fn_env, cond)?;
+                self.compile_conditional_block(fn_env, &cond_var, body, stmt.span)?;
+            }
+            StmtKind::While {
+                cond,
+                max_iters,
+                body,
+            } => {
+                let max_iters = self.bound_value(max_iters)?;
+
+                // the loop is unrolled `max_iters` times, so it counts as many iterations as a for loop
+                let iterations = max_iters as usize;
+                if self.loop_iterations + iterations > self.loop_budget {
+                    return Err(
+                        self.error(ErrorKind::LoopBudgetExceeded(self.loop_budget), stmt.span)
+                    );
+                }
+                self.loop_iterations += iterations;
+
+                // each iteration only has an effect if the condition is still set
+                // (once it's not, the variables don't change anymore, and neither does the condition)
+                for _ in 0..max_iters {
+                    let cond_var = self.compute_condition(fn_env, cond)?;
+                    if matches!(&cond_var[0], ConstOrCell::Const(cst) if cst.is_zero()) {
+                        break;
+                    }
+
+                    self.compile_conditional_block(fn_env, &cond_var, body, stmt.span)?;
+                }
+
+                // the loop must have terminated after `max_iters` iterations
+                let cond_var = self.compute_condition(fn_env, cond)?;
+                match &cond_var[0] {
+                    ConstOrCell::Const(cst) if cst.is_zero() => (),
+                    ConstOrCell::Const(_) if self.branch_conditions.is_empty() => {
+                        return Err(
+                            self.error(ErrorKind::WhileLoopNotTerminated(max_iters), stmt.span)
+                        )
+                    }
+                    cond_cell => {
+                        let message = ErrorKind::WhileLoopNotTerminated(max_iters).to_string();
+                        self.backend.set_assert_message(Some(message));
+                        let res = stdlib::assert_zero(self, cond_cell, stmt.span);
+                        self.backend.set_assert_message(None);
+                        res?;
+                    }
+                }
+            }
             StmtKind::Expr(expr) => {
                 // compute the expression
                 let var = self.compute_expr(fn_env, expr)?;
@@ -171,6 +485,65 @@ impl<B: Backend> CircuitWriter<B> {
         Ok(None)
     }
 
+    /// Computes the condition of an `if` statement or a `while` loop, which is a single boolean.
+    fn compute_condition(
+        &mut self,
+        fn_env: &mut FnEnv<B::Field, B::Var>,
+        cond: &Expr,
+    ) -> Result<Var<B::Field, B::Var>> {
+        let cond_var = self
+            .compute_expr(fn_env, cond)?
+            .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, cond.span))?
+            .value(self, fn_env);
+        assert_eq!(cond_var.len(), 1);
+
+        Ok(cond_var)
+    }
+
+    /// Compiles a block that only has an effect if `cond_var` is set
+    /// (the body of an `if` statement, or an iteration of a `while` loop).
+    fn compile_conditional_block(
+        &mut self,
+        fn_env: &mut FnEnv<B::Field, B::Var>,
+        cond_var: &Var<B::Field, B::Var>,
+        body: &[Stmt],
+        span: Span,
+    ) -> Result<()> {
+        // a body that is never executed doesn't need to be compiled,
+        // otherwise the condition needs a cell to condition the body on
+        let cond_cell = match &cond_var[0] {
+            ConstOrCell::Const(cst) if cst.is_zero() => return Ok(()),
+            ConstOrCell::Const(cst) => self.backend.add_constant(
+                Some("hardcode the condition of an if statement"),
+                *cst,
+                cond_var.span,
+            ),
+            ConstOrCell::Cell(cell) => cell.clone(),
+        };
+
+        // like the branches of an if/else expression,
+        // the body is compiled but its assertions only have to hold if the condition is set
+        let mutable_vars = fn_env.mutable_vars();
+        self.branch_conditions.push((cond_cell, true));
+        let returned = self.compile_block(fn_env, body);
+        if let Ok(Some(value)) = &returned {
+            self.record_early_return(fn_env, value.clone(), span);
+        }
+        self.branch_conditions.pop();
+        returned?;
+
+        // the variables reassigned in the body keep their previous values if the condition is not set
+        for (var_name, before) in mutable_vars {
+            let after = fn_env.get_local_var(&var_name).var;
+            if after.cvars != before.cvars {
+                let merged = field::if_else(self, cond_var, &after, &before, span);
+                fn_env.reassign_local_var(&var_name, merged);
+            }
+        }
+
+        Ok(())
+    }
+
     /// might return something?
     fn compile_block(
         &mut self,
@@ -178,18 +551,98 @@ impl<B: Backend> CircuitWriter<B> {
         stmts: &[Stmt],
     ) -> Result<Option<Var<B::Field, B::Var>>> {
         fn_env.nest();
+
+        // once the function may have returned early,
+        // the statements that follow only have to hold if it didn't
+        let mut not_returned = None;
+
+        let mut returned = None;
         for stmt in stmts {
+            if let Some((flag, _)) = &fn_env.early_return {
+                let conditioned = self
+                    .branch_conditions
+                    .iter()
+                    .any(|(cond, taken_if)| cond == flag && !taken_if);
+                if !conditioned {
+                    if not_returned.is_some() {
+                        self.branch_conditions.pop();
+                    }
+                    self.branch_conditions.push((flag.clone(), false));
+                    not_returned = Some(flag.clone());
+                }
+            }
+
             let res = self.compile_stmt(fn_env, stmt)?;
             if let Some(var) = res {
                 // a block doesn't return a pointer, only values
                 let var = var.value(self, fn_env);
 
                 // we already checked for early returns in type checking
-                return Ok(Some(var));
+                returned = Some(var);
+                break;
             }
         }
+
+        if not_returned.is_some() {
+            self.branch_conditions.pop();
+        }
         fn_env.pop();
-        Ok(None)
+
+        Ok(returned)
+    }
+
+    /// Records a `return` in the body of an `if` statement:
+    /// the function returns `value` if all the branches being compiled are taken
+    /// (which implies that it didn't return earlier).
+    fn record_early_return(
+        &mut self,
+        fn_env: &mut FnEnv<B::Field, B::Var>,
+        value: Var<B::Field, B::Var>,
+        span: Span,
+    ) {
+        let taken = self
+            .branch_condition(span)
+            .expect("compiler bug: early return outside of an if statement");
+
+        let early_return = match fn_env.early_return.take() {
+            None => (taken, value),
+            Some((flag, prev)) => {
+                // the two conditions are exclusive, so their sum is a boolean
+                let flag = field::add(
+                    self,
+                    &ConstOrCell::Cell(flag),
+                    &ConstOrCell::Cell(taken.clone()),
+                    span,
+                );
+                let flag = match &flag[0] {
+                    ConstOrCell::Cell(flag) => flag.clone(),
+                    ConstOrCell::Const(_) => unreachable!("the sum of two cells is a cell"),
+                };
+
+                let taken = Var::new_var(taken, span);
+                let value = field::if_else(self, &taken, &value, &prev, span);
+
+                (flag, value)
+            }
+        };
+
+        fn_env.early_return = Some(early_return);
+    }
+
+    /// Returns the value returned by a function,
+    /// which is the value returned early if the function did return early.
+    fn merge_early_returns(
+        &mut self,
+        fn_env: &FnEnv<B::Field, B::Var>,
+        returned: Option<Var<B::Field, B::Var>>,
+    ) -> Option<Var<B::Field, B::Var>> {
+        match (&fn_env.early_return, returned) {
+            (Some((flag, value)), Some(returned)) => {
+                let flag = Var::new_var(flag.clone(), returned.span);
+                Some(field::if_else(self, &flag, value, &returned, returned.span))
+            }
+            (_, returned) => returned,
+        }
     }
 
     fn compile_native_function_call(
@@ -199,18 +652,100 @@ impl<B: Backend> CircuitWriter<B> {
     ) -> Result<Option<Var<B::Field, B::Var>>> {
         assert!(!function.is_main());
 
+        // a call creating the same constraints as a previous one splices them instead (see the fn_cache module)
+        let key = self.fn_call_key(function, &args);
+        if let Some((key, inputs)) = &key {
+            if let Some(cached) = self.fn_cache.get(key) {
+                // a call that would exceed the loop budget is compiled, to report where
+                if self.loop_iterations + cached.loop_iterations <= self.loop_budget {
+                    self.loop_iterations += cached.loop_iterations;
+                    self.fn_cache_hits += 1;
+                    return Ok(cached.splice(&mut self.backend, inputs));
+                }
+            }
+        }
+
+        let key = match key {
+            Some((key, inputs)) => {
+                self.backend.start_recording(&inputs);
+                Some(key)
+            }
+            None => None,
+        };
+        let loop_iterations = self.loop_iterations;
+
+        let res = self.compile_fn_call(function, args);
+
+        if let Some(key) = key {
+            if let (Some(recording), Ok(returned)) = (self.backend.stop_recording(), &res) {
+                let cached = CachedFnCall {
+                    recording,
+                    returned: returned.clone(),
+                    loop_iterations: self.loop_iterations - loop_iterations,
+                };
+                self.fn_cache.insert(key, cached);
+            }
+        }
+
+        res
+    }
+
+    /// Compiles a call to a function, with the given arguments.
+    fn compile_fn_call(
+        &mut self,
+        function: &FunctionDef,
+        args: Vec<VarInfo<B::Field, B::Var>>,
+    ) -> Result<Option<Var<B::Field, B::Var>>> {
         // create new fn_env
         let fn_env = &mut FnEnv::new();
 
         // set arguments
         assert_eq!(function.sig.arguments.len(), args.len());
 
-        for (name, var_info) in function.sig.arguments.iter().zip(args) {
-            self.add_local_var(fn_env, name.name.value.clone(), var_info);
+        // arguments are passed by value, so a `mut` argument is a local copy that the function can reassign
+        for (arg, mut var_info) in function.sig.arguments.iter().zip(args) {
+            var_info.mutable = arg.mutable;
+            self.add_local_var(fn_env, arg.name.value.clone(), var_info);
         }
 
         // compile it and potentially return a return value
-        self.compile_block(fn_env, &function.body)
+        let returned = self.compile_fn_body(fn_env, function)?;
+        Ok(self.merge_early_returns(fn_env, returned))
+    }
+
+    /// Constrains a value of an integer type (e.g. `U32`) to fit in its number of bits,
+    /// which fails with [ErrorKind::IntegerOverflow] if it doesn't:
+    /// right away for a constant, or during witness generation for a cell.
+    /// Values of other types are not constrained.
+    pub(crate) fn constrain_int(
+        &mut self,
+        var: &ConstOrCell<B::Field, B::Var>,
+        typ: &TyKind,
+        span: Span,
+    ) -> Result<()> {
+        let num_bits = match typ.num_bits() {
+            Some(num_bits) => num_bits,
+            None => return Ok(()),
+        };
+        let overflow = ErrorKind::IntegerOverflow(typ.clone());
+
+        match var {
+            ConstOrCell::Const(cst) => {
+                let value: BigUint = (*cst).into();
+                if value.bits() as usize > num_bits {
+                    return Err(self.error(overflow, span));
+                }
+            }
+            ConstOrCell::Cell(_) => {
+                // the decomposition carries the error, like the constraints of an assertion
+                self.backend.set_assert_message(Some(overflow.to_string()));
+                let res = field::to_bits(self, var, num_bits, span);
+                self.backend.set_assert_message(None);
+                res?;
+            }
+        }
+
+        Ok(())
     }
 
     pub(crate) fn constrain_inputs_to_main(
@@ -225,6 +760,10 @@ impl<B: Backend> CircuitWriter<B> {
                 assert_eq!(input.len(), 1);
                 boolean::check(self, &input[0], span);
             }
+            TyKind::U32 | TyKind::U64 => {
+                assert_eq!(input.len(), 1);
+                self.constrain_int(&input[0], input_typ, span)?;
+            }
             TyKind::Array(tykind, _) => {
                 let el_size = self.size_of(tykind);
                 for el in input.chunks(el_size) {
@@ -249,7 +788,17 @@ impl<B: Backend> CircuitWriter<B> {
                     offset += len;
                 }
             }
-            TyKind::BigInt => unreachable!(),
+            TyKind::Tuple(typs) => {
+                let mut offset = 0;
+                for typ in typs {
+                    let len = self.size_of(typ);
+                    let range = offset..(offset + len);
+                    self.constrain_inputs_to_main(&input[range], typ, span)?;
+                    offset += len;
+                }
+            }
+            // the main function can't be generic
+            TyKind::BigInt | TyKind::GenericArray(..) => unreachable!(),
         };
         Ok(())
     }
@@ -263,12 +812,13 @@ impl<B: Backend> CircuitWriter<B> {
         assert!(function.is_main());
 
         // compile the block
-        let returned = self.compile_block(fn_env, &function.body)?;
+        let returned = self.compile_fn_body(fn_env, function)?;
+        let returned = self.merge_early_returns(fn_env, returned);
 
         // we're expecting something returned?
         match (function.sig.return_type.as_ref(), returned) {
             (None, None) => Ok(None),
-            (Some(expected), None) => Err(self.error(ErrorKind::MissingReturn, expected.span)),
+            (Some(_expected), None) => Err(self.error(ErrorKind::MissingReturn, function.span)),
             (None, Some(returned)) => Err(self.error(ErrorKind::UnexpectedReturn, returned.span)),
             (Some(_expected), Some(returned)) => {
                 // make sure there are no constants in the returned value
@@ -291,12 +841,389 @@ impl<B: Backend> CircuitWriter<B> {
         }
     }
 
+    /// Evaluates an expression at compile time if it only involves constants.
+    /// Returns `None` if any of the sub-expressions depends on a cell var.
+    fn fold_constant(&self, fn_env: &FnEnv<B::Field, B::Var>, expr: &Expr) -> Option<B::Field> {
+        match &expr.kind {
+            ExprKind::BigInt(b) => {
+                let biguint = parse_number(b)?;
+                B::Field::try_from(biguint).ok()
+            }
+
+            ExprKind::Bool(b) => Some(if *b {
+                B::Field::one()
+            } else {
+                B::Field::zero()
+            }),
+
+            ExprKind::Variable { module, name } => {
+                if is_type(&name.value) {
+                    return None;
+                }
+
+                let qualified = FullyQualified::new(module, &name.value);
+                if let Some(cst_info) = self.const_info(&qualified) {
+                    return match cst_info.value.as_slice() {
+                        [cst] => Some(*cst),
+                        _ => None,
+                    };
+                }
+
+                self.get_local_var(fn_env, &name.value).var.constant()
+            }
+
+            ExprKind::Negated(inner) => Some(-self.fold_constant(fn_env, inner)?),
+
+            ExprKind::Not(inner) => {
+                let cst = self.fold_constant(fn_env, inner)?;
+                Some(B::Field::one() - cst)
+            }
+
+            ExprKind::BinaryOp { op, lhs, rhs, .. } => {
+                let lhs = self.fold_constant(fn_env, lhs)?;
+                let rhs = self.fold_constant(fn_env, rhs)?;
+
+                match op {
+                    Op2::Addition => Some(lhs + rhs),
+                    Op2::Subtraction => Some(lhs - rhs),
+                    Op2::Multiplication => Some(lhs * rhs),
+                    // division by zero is left to the non-folded path
+                    Op2::Division => rhs.inverse().map(|inv| lhs * inv),
+                    // so is a zero modulus
+                    Op2::Modulo if rhs.is_zero() => None,
+                    Op2::Modulo => {
+                        let (lhs, rhs): (BigUint, BigUint) = (lhs.into(), rhs.into());
+                        Some(B::Field::from(lhs % rhs))
+                    }
+                    Op2::Equality => Some(if lhs == rhs {
+                        B::Field::one()
+                    } else {
+                        B::Field::zero()
+                    }),
+                    Op2::BoolAnd => Some(lhs * rhs),
+                    Op2::BoolOr => Some(lhs + rhs - lhs * rhs),
+                }
+            }
+
+            _ => None,
+        }
+    }
+
+    /// Computes one branch of an `if`/`else` expression,
+    /// which is taken when `cond` is equal to `taken_if`.
+    fn compute_branch(
+        &mut self,
+        fn_env: &mut FnEnv<B::Field, B::Var>,
+        branch: &Expr,
+        cond: &B::Var,
+        taken_if: bool,
+    ) -> Result<Var<B::Field, B::Var>> {
+        self.branch_conditions.push((cond.clone(), taken_if));
+        let res = self.compute_expr(fn_env, branch);
+        self.branch_conditions.pop();
+
+        Ok(res?
+            .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, branch.span))?
+            .value(self, fn_env))
+    }
+
+    /// Returns a boolean cell that is set only if all the `if`/`else` branches
+    /// currently being compiled are taken, or `None` outside of any branch.
+    /// The cell is created lazily, so that branches without assertions don't cost anything.
+    pub(crate) fn branch_condition(&mut self, span: Span) -> Option<B::Var> {
+        let mut res: Option<ConstOrCell<B::Field, B::Var>> = None;
+
+        for (cond, taken_if) in self.branch_conditions.clone() {
+            let cond = ConstOrCell::Cell(cond);
+            let cond = if taken_if {
+                cond
+            } else {
+                boolean::not(self, &cond, span)[0].clone()
+            };
+
+            res = Some(match res {
+                Some(outer) => boolean::and(self, &outer, &cond, span)[0].clone(),
+                None => cond,
+            });
+        }
+
+        res.map(|cond| match cond {
+            ConstOrCell::Cell(cond) => cond,
+            ConstOrCell::Const(_) => unreachable!("the condition of a branch is never a constant"),
+        })
+    }
+
+    /// Returns the condition of the branches being compiled, if any,
+    /// when [CircuitOptions::relaxed_branches](super::CircuitOptions::relaxed_branches) is set
+    /// (the condition that the constraints of gadgets are multiplied by).
+    pub(crate) fn relaxed_branch_condition(&mut self, span: Span) -> Option<B::Var> {
+        if self.relaxed_branches {
+            self.branch_condition(span)
+        } else {
+            None
+        }
+    }
+
+    /// Constrains a cell to be equal to a value, on behalf of a gadget.
+    /// With [CircuitOptions::relaxed_branches](super::CircuitOptions::relaxed_branches),
+    /// this only holds if the branches being compiled are taken (see [Self::assert_eq_cell_if]).
+    pub(crate) fn assert_eq_cell(
+        &mut self,
+        var: &B::Var,
+        value: &ConstOrCell<B::Field, B::Var>,
+        span: Span,
+    ) {
+        let branch_cond = self.relaxed_branch_condition(span);
+        self.assert_eq_cell_if(branch_cond, var, value, span);
+    }
+
+    /// Constrains a cell to be equal to a value.
+    /// Given a condition, this only holds when the condition is set, as `cond * (var - value) = 0`.
+    pub(crate) fn assert_eq_cell_if(
+        &mut self,
+        cond: Option<B::Var>,
+        var: &B::Var,
+        value: &ConstOrCell<B::Field, B::Var>,
+        span: Span,
+    ) {
+        match (cond, value) {
+            (Some(cond), _) => {
+                let diff = field::sub(self, &ConstOrCell::Cell(var.clone()), value, span);
+                let gated = field::mul(self, &ConstOrCell::Cell(cond), &diff[0], span);
+
+                // a constant here can only be zero, as `cond` is not a constant
+                if let ConstOrCell::Cell(gated) = &gated[0] {
+                    self.backend.assert_eq_const(gated, B::Field::zero(), span);
+                }
+            }
+            (None, ConstOrCell::Const(cst)) => self.backend.assert_eq_const(var, *cst, span),
+            (None, ConstOrCell::Cell(value)) => self.backend.assert_eq_var(var, value, span),
+        }
+    }
+
+    /// Compiles `fold(arr, init, f)` into a chain of calls to `f`, one per element of the array.
+    fn compile_fold(
+        &mut self,
+        fn_env: &mut FnEnv<B::Field, B::Var>,
+        args: &[Expr],
+        call: &Expr,
+    ) -> Result<Option<Var<B::Field, B::Var>>> {
+        let (arr, init, func) = match args {
+            [arr, init, func] => (arr, init, func),
+            _ => unreachable!("the type checker made sure that `fold` takes three arguments"),
+        };
+
+        // retrieve the function
+        // (for a generic function, the instance that this call refers to)
+        let (module, fn_name) = match &func.kind {
+            ExprKind::Variable { module, name } => (module, name),
+            _ => unreachable!("the type checker made sure that `fold` takes a function name"),
+        };
+        let qualified = self.called_fn(call, module, &fn_name.value);
+        let fn_info = self
+            .fn_info(&qualified)
+            .ok_or_else(|| {
+                self.error(
+                    ErrorKind::UndefinedFunction(fn_name.value.clone()),
+                    fn_name.span,
+                )
+            })?
+            .clone();
+        let acc_typ = fn_info.sig().arguments[0].typ.kind.clone();
+
+        let elem_typ = match self.expr_type(arr) {
+            Some(TyKind::Array(elem_typ, _)) => *elem_typ.clone(),
+            _ => unreachable!("the type checker made sure that `fold` takes an array"),
+        };
+        let elem_size = self.size_of(&elem_typ);
+
+        // compute the array and the initial accumulator
+        let arr_var = self
+            .compute_expr(fn_env, arr)?
+            .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, arr.span))?
+            .value(self, fn_env);
+
+        let mut acc = self
+            .compute_expr(fn_env, init)?
+            .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, init.span))?
+            .value(self, fn_env);
+
+        // acc = f(acc, elem) for each element
+        for elem in arr_var.cvars.chunks(elem_size) {
+            let vars = vec![
+                VarInfo::new(acc, false, Some(acc_typ.clone())),
+                VarInfo::new(
+                    Var::new(elem.to_vec(), arr.span),
+                    false,
+                    Some(elem_typ.clone()),
+                ),
+            ];
+
+            let res = match &fn_info.kind {
+                FnKind::BuiltIn(_sig, handle) => handle(self, &vars, call.span)?,
+                FnKind::Native(function) => self.compile_native_function_call(function, vars)?,
+            };
+
+            acc = res.ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, call.span))?;
+        }
+
+        Ok(Some(acc))
+    }
+
+    /// Compiles a call to `crypto::merkle_verify_with(leaf, path, indices, root, hash)`,
+    /// where `hash` is called on each pair of children.
+    fn compile_merkle_verify_with(
+        &mut self,
+        fn_env: &mut FnEnv<B::Field, B::Var>,
+        args: &[Expr],
+        call: &Expr,
+    ) -> Result<Option<Var<B::Field, B::Var>>> {
+        let (hash, args) = match args.split_last() {
+            Some((hash, args)) if args.len() == 4 => (hash, args),
+            _ => unreachable!(
+                "the type checker made sure that `merkle_verify_with` takes five arguments"
+            ),
+        };
+
+        // retrieve the hash function
+        let (module, fn_name) = match &hash.kind {
+            ExprKind::Variable { module, name } => (module, name),
+            _ => unreachable!(
+                "the type checker made sure that `merkle_verify_with` takes a function name"
+            ),
+        };
+        let qualified = FullyQualified::new(module, &fn_name.value);
+        let fn_info = self
+            .fn_info(&qualified)
+            .ok_or_else(|| {
+                self.error(
+                    ErrorKind::UndefinedFunction(fn_name.value.clone()),
+                    fn_name.span,
+                )
+            })?
+            .clone();
+
+        // compute the leaf, the path, the indices and the root
+        let mut vars = Vec::with_capacity(args.len());
+        for arg in args {
+            let var = self
+                .compute_expr(fn_env, arg)?
+                .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, arg.span))?
+                .value(self, fn_env);
+            vars.push(VarInfo::new(var, false, self.expr_type(arg).cloned()));
+        }
+
+        stdlib::crypto::merkle_verify_with(self, &vars, call.span, |compiler, left, right| {
+            let vars = vec![
+                VarInfo::new(Var::new_cvar(left, call.span), false, Some(TyKind::Field)),
+                VarInfo::new(Var::new_cvar(right, call.span), false, Some(TyKind::Field)),
+            ];
+
+            let res = match &fn_info.kind {
+                FnKind::BuiltIn(_sig, handle) => handle(compiler, &vars, call.span)?,
+                FnKind::Native(function) => {
+                    compiler.compile_native_function_call(function, vars)?
+                }
+            };
+
+            let parent =
+                res.ok_or_else(|| compiler.error(ErrorKind::CannotComputeExpression, call.span))?;
+            Ok(parent.cvars[0].clone())
+        })
+    }
+
     fn compute_expr(
         &mut self,
         fn_env: &mut FnEnv<B::Field, B::Var>,
         expr: &Expr,
     ) -> Result<Option<VarOrRef<B>>> {
         match &expr.kind {
+            // `fold(arr, init, f)`
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } if is_fold(module, &fn_name.value) => self
+                .compile_fold(fn_env, args, expr)
+                .map(|r| r.map(VarOrRef::Var)),
+
+            // `crypto::merkle_verify_with(leaf, path, indices, root, hash)`
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } if is_merkle_verify_with(module, &fn_name.value) => self
+                .compile_merkle_verify_with(fn_env, args, expr)
+                .map(|r| r.map(VarOrRef::Var)),
+
+            // `select(cond, a, b)`
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } if is_select(module, &fn_name.value) => {
+                let (cond, lhs, rhs) = match args.as_slice() {
+                    [cond, lhs, rhs] => (cond, lhs, rhs),
+                    _ => unreachable!(
+                        "the type checker made sure that `select` takes three arguments"
+                    ),
+                };
+
+                let cond_var = self.compute_condition(fn_env, cond)?;
+
+                // unlike an `if`/`else` expression, both values are always computed (and constrained),
+                // unless the branches are relaxed
+                let (lhs_var, rhs_var) = match &cond_var[0] {
+                    ConstOrCell::Cell(cond_cell) if self.relaxed_branches => {
+                        let cond_cell = cond_cell.clone();
+                        (
+                            self.compute_branch(fn_env, lhs, &cond_cell, true)?,
+                            self.compute_branch(fn_env, rhs, &cond_cell, false)?,
+                        )
+                    }
+                    _ => {
+                        let mut vars = Vec::with_capacity(2);
+                        for arg in [lhs, rhs] {
+                            let var = self
+                                .compute_expr(fn_env, arg)?
+                                .ok_or_else(|| {
+                                    self.error(ErrorKind::CannotComputeExpression, arg.span)
+                                })?
+                                .value(self, fn_env);
+                            vars.push(var);
+                        }
+                        let rhs_var = vars.pop().unwrap();
+                        (vars.pop().unwrap(), rhs_var)
+                    }
+                };
+
+                let res = field::if_else(self, &cond_var, &lhs_var, &rhs_var, expr.span);
+
+                Ok(Some(VarOrRef::Var(res)))
+            }
+
+            // `debug_assert(cond)`, whose condition isn't even computed in optimized circuits
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args: _,
+            } if is_debug_assert(module, &fn_name.value) && self.opt_level >= OptLevel::O2 => {
+                // a single warning per assertion, even if it's in a loop or a function called many times
+                if !self
+                    .warnings
+                    .iter()
+                    .any(|warning| warning.span == expr.span)
+                {
+                    self.warnings.push(Warning::new(
+                        "constraint-generation",
+                        WarningKind::DebugAssertStripped,
+                        expr.span,
+                    ));
+                }
+
+                Ok(None)
+            }
+
             // `module::fn_name(args)`
             ExprKind::FnCall {
                 module,
@@ -309,7 +1236,8 @@ impl<B: Backend> CircuitWriter<B> {
                 }
 
                 // retrieve the function in the env
-                let qualified = FullyQualified::new(module, &fn_name.value);
+                // (for a generic function, the instance that this call refers to)
+                let qualified = self.called_fn(expr, module, &fn_name.value);
                 let fn_info = self
                     .fn_info(&qualified)
                     .ok_or_else(|| {
@@ -320,6 +1248,12 @@ impl<B: Backend> CircuitWriter<B> {
                     })?
                     .clone();
 
+                // the message of an assertion is not an argument of the builtin
+                let (args, message) = match &fn_info.kind {
+                    FnKind::BuiltIn(sig, _) => split_assert_message(&sig.name.value, args),
+                    FnKind::Native(_) => (args.as_slice(), None),
+                };
+
                 // compute the arguments
                 // module::fn_name(args)
                 //                 ^^^^
@@ -343,7 +1277,11 @@ impl<B: Backend> CircuitWriter<B> {
                 let res = match &fn_info.kind {
                     // assert() <-- for example
                     FnKind::BuiltIn(_sig, handle) => {
+                        // the constraints of the assertion carry its message
+                        self.backend.set_assert_message(message.map(str::to_string));
                         let res = handle(self, &vars, expr.span);
+                        self.backend.set_assert_message(None);
+
                         res.map(|r| r.map(VarOrRef::Var))
                     }
 
@@ -410,8 +1348,8 @@ impl<B: Backend> CircuitWriter<B> {
                 // figure out the name of the custom struct
                 let lhs_typ = self.expr_type(lhs).expect("method call on what?").clone();
 
-                let (module, struct_name) = match &lhs_typ {
-                    TyKind::Custom { module, name } => (module, name),
+                let struct_name = match &lhs_typ {
+                    TyKind::Custom { name, .. } => name,
                     _ => {
                         return Err(self.error(
                             ErrorKind::UnexpectedError("method call only work on custom types"),
@@ -425,35 +1363,34 @@ impl<B: Backend> CircuitWriter<B> {
                 let self_var = self.compute_expr(fn_env, lhs)?;
 
                 // find method info
-                let qualified = FullyQualified::new(module, struct_name);
-                let struct_info = self
-                    .struct_info(&qualified)
-                    .ok_or(self.error(
-                        ErrorKind::UnexpectedError("struct not found"),
-                        method_name.span,
-                    ))?
-                    .clone();
-                let func = struct_info
-                    .methods
-                    .get(&method_name.value)
-                    .expect("could not find method");
+                // (a method of the struct, or a function of its module taking `self` as first argument)
+                let (func, passes_self) = self
+                    .typed
+                    .method_info(&lhs_typ, &method_name.value)
+                    .map(|(func, passes_self)| (func.clone(), passes_self))
+                    .ok_or_else(|| {
+                        self.error(
+                            ErrorKind::UndefinedMethod(
+                                struct_name.clone(),
+                                method_name.value.clone(),
+                            ),
+                            method_name.span,
+                        )
+                    })?;
 
                 // if method has a `self` argument, manually add it to the list of argument
                 let mut vars = vec![];
-                if let Some(first_arg) = func.sig.arguments.first() {
-                    if first_arg.name.value == "self" {
-                        let self_var = self_var.ok_or_else(|| {
-                            self.error(ErrorKind::NotAStaticMethod, method_name.span)
-                        })?;
-
-                        // TODO: for now we pass `self` by value as well
-                        let mutable = false;
-                        let self_var = self_var.value(self, fn_env);
-
-                        let self_var_info = VarInfo::new(self_var, mutable, Some(lhs_typ.clone()));
-                        vars.insert(0, self_var_info);
-                    }
-                } else {
+                if passes_self {
+                    let self_var = self_var
+                        .ok_or_else(|| self.error(ErrorKind::NotAStaticMethod, method_name.span))?;
+
+                    // TODO: for now we pass `self` by value as well
+                    let mutable = false;
+                    let self_var = self_var.value(self, fn_env);
+
+                    let self_var_info = VarInfo::new(self_var, mutable, Some(lhs_typ.clone()));
+                    vars.insert(0, self_var_info);
+                } else if func.sig.arguments.is_empty() {
                     assert!(self_var.is_none());
                 }
 
@@ -483,14 +1420,21 @@ impl<B: Backend> CircuitWriter<B> {
                     .compute_expr(fn_env, cond)?
                     .unwrap()
                     .value(self, fn_env);
-                let then_ = self
-                    .compute_expr(fn_env, then_)?
-                    .unwrap()
-                    .value(self, fn_env);
-                let else_ = self
-                    .compute_expr(fn_env, else_)?
-                    .unwrap()
-                    .value(self, fn_env);
+                assert_eq!(cond.len(), 1);
+
+                // with a constant condition, only the branch taken is compiled
+                let cond_cell = match &cond[0] {
+                    ConstOrCell::Const(cst) => {
+                        let branch = if cst.is_one() { then_ } else { else_ };
+                        return self.compute_expr(fn_env, branch);
+                    }
+                    ConstOrCell::Cell(cell) => cell.clone(),
+                };
+
+                // otherwise both branches are compiled,
+                // and assertions only have to hold in the branch that is taken
+                let then_ = self.compute_branch(fn_env, then_, &cond_cell, true)?;
+                let else_ = self.compute_branch(fn_env, else_, &cond_cell, false)?;
 
                 let res = field::if_else(self, &cond, &then_, &else_, expr.span);
 
@@ -498,6 +1442,50 @@ impl<B: Backend> CircuitWriter<B> {
             }
 
             ExprKind::Assignment { lhs, rhs } => {
+                // `array[idx] = <rhs>` with an index only known at runtime
+                // updates every element of the array conditionally
+                if let ExprKind::ArrayAccess { array, idx } = &lhs.kind {
+                    let idx_var = self
+                        .compute_expr(fn_env, idx)?
+                        .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, idx.span))?
+                        .value(self, fn_env);
+
+                    if let ConstOrCell::Cell(idx_cvar) = &idx_var[0] {
+                        let array_ref = self.compute_expr(fn_env, array)?.unwrap();
+                        let (var_name, start, len) = match &array_ref {
+                            VarOrRef::Ref {
+                                var_name,
+                                start,
+                                len,
+                            } => (var_name.clone(), *start, *len),
+                            VarOrRef::Var(_) => {
+                                return Err(self
+                                    .error(ErrorKind::IndirectRuntimeIndexAssignment, expr.span))
+                            }
+                        };
+                        let array_var = array_ref.value(self, fn_env);
+
+                        let elem_len = match self.expr_type(array) {
+                            Some(TyKind::Array(elem_typ, _)) => self.size_of(elem_typ),
+                            _ => panic!("expected array"),
+                        };
+
+                        let rhs_var = self
+                            .compute_expr(fn_env, rhs)?
+                            .ok_or_else(|| {
+                                self.error(ErrorKind::CannotComputeExpression, rhs.span)
+                            })?
+                            .value(self, fn_env);
+
+                        let updated = field::update_element(
+                            self, &array_var, idx_cvar, elem_len, &rhs_var, expr.span,
+                        );
+                        fn_env.reassign_var_range(&var_name, updated, start, len);
+
+                        return Ok(None);
+                    }
+                }
+
                 // figure out the local var  of lhs
                 let lhs = self.compute_expr(fn_env, lhs)?.unwrap();
 
@@ -518,7 +1506,11 @@ impl<B: Backend> CircuitWriter<B> {
 
                 // replace the left with the right
                 match lhs {
-                    VarOrRef::Var(_) => panic!("can't reassign this non-mutable variable"),
+                    // the type checker made sure that the variable is mutable,
+                    // so this can only be an array element selected by an index known at runtime
+                    VarOrRef::Var(_) => {
+                        return Err(self.error(ErrorKind::IndirectRuntimeIndexAssignment, expr.span))
+                    }
                     VarOrRef::Ref {
                         var_name,
                         start,
@@ -532,12 +1524,54 @@ impl<B: Backend> CircuitWriter<B> {
             }
 
             ExprKind::BinaryOp { op, lhs, rhs, .. } => {
+                // `lhs + rhs` on a struct is a call to `lhs.add(rhs)`
+                let method = self
+                    .expr_type(lhs)
+                    .and_then(|typ| self.typed.operator_method(typ, op))
+                    .cloned();
+                if let Some(method) = method {
+                    let mut vars = Vec::with_capacity(2);
+                    for operand in [lhs, rhs] {
+                        let var = self
+                            .compute_expr(fn_env, operand)?
+                            .ok_or_else(|| {
+                                self.error(ErrorKind::CannotComputeExpression, operand.span)
+                            })?
+                            .value(self, fn_env);
+                        let typ = self.expr_type(operand).cloned();
+                        vars.push(VarInfo::new(var, false, typ));
+                    }
+
+                    return self
+                        .compile_native_function_call(&method, vars)
+                        .map(|r| r.map(VarOrRef::Var));
+                }
+
+                // the arithmetic of integers must not wrap around
+                let typ = self.expr_type(expr).cloned().unwrap_or(TyKind::Field);
+
+                // constant-only expressions don't need to hit the backend
+                if let Some(cst) = self.fold_constant(fn_env, expr) {
+                    self.constrain_int(&ConstOrCell::Const(cst), &typ, expr.span)?;
+                    return Ok(Some(VarOrRef::Var(Var::new_constant(cst, expr.span))));
+                }
+
+                let lhs_bits = self.expr_type(lhs).and_then(TyKind::num_bits);
                 let lhs = self.compute_expr(fn_env, lhs)?.unwrap();
                 let rhs = self.compute_expr(fn_env, rhs)?.unwrap();
 
                 let lhs = lhs.value(self, fn_env);
                 let rhs = rhs.value(self, fn_env);
 
+                // the remainder is smaller than the modulus and at most `lhs`, so it fits in the type of the result
+                if let Op2::Modulo = op {
+                    let modulus = rhs
+                        .constant()
+                        .ok_or_else(|| self.error(ErrorKind::NonConstantModulus, expr.span))?;
+                    let res = field::modulo(self, &lhs[0], modulus, lhs_bits, expr.span)?;
+                    return Ok(Some(VarOrRef::Var(res)));
+                }
+
                 let res = match op {
                     Op2::Addition => field::add(self, &lhs[0], &rhs[0], expr.span),
                     Op2::Subtraction => field::sub(self, &lhs[0], &rhs[0], expr.span),
@@ -545,18 +1579,29 @@ impl<B: Backend> CircuitWriter<B> {
                     Op2::Equality => field::equal(self, &lhs, &rhs, expr.span),
                     Op2::BoolAnd => boolean::and(self, &lhs[0], &rhs[0], expr.span),
                     Op2::BoolOr => boolean::or(self, &lhs[0], &rhs[0], expr.span),
-                    Op2::Division => todo!(),
+                    // only constant divisions are folded, `div` constrains the others
+                    Op2::Division => {
+                        let kind = ErrorKind::Unsupported("`/` on variables (use `div`)");
+                        return Err(self.error(kind, expr.span));
+                    }
+                    Op2::Modulo => unreachable!("`%` is computed above"),
                 };
+                self.constrain_int(&res[0], &typ, expr.span)?;
 
                 Ok(Some(VarOrRef::Var(res)))
             }
 
             ExprKind::Negated(b) => {
+                if let Some(cst) = self.fold_constant(fn_env, expr) {
+                    return Ok(Some(VarOrRef::Var(Var::new_constant(cst, expr.span))));
+                }
+
                 let var = self.compute_expr(fn_env, b)?.unwrap();
 
                 let var = var.value(self, fn_env);
+                let res = field::neg(self, &var[0], expr.span);
 
-                todo!()
+                Ok(Some(VarOrRef::Var(res)))
             }
 
             ExprKind::Not(b) => {
@@ -569,7 +1614,7 @@ impl<B: Backend> CircuitWriter<B> {
             }
 
             ExprKind::BigInt(b) => {
-                let biguint = BigUint::from_str_radix(b, 10).expect("failed to parse number.");
+                let biguint = parse_number(b).expect("failed to parse number.");
                 let ff = B::Field::try_from(biguint).map_err(|_| {
                     self.error(ErrorKind::CannotConvertToField(b.to_string()), expr.span)
                 })?;
@@ -578,6 +1623,10 @@ impl<B: Backend> CircuitWriter<B> {
                 Ok(Some(res))
             }
 
+            ExprKind::StringLiteral(_) => {
+                Err(self.error(ErrorKind::UnexpectedStringLiteral, expr.span))
+            }
+
             ExprKind::Bool(b) => {
                 let value = if *b {
                     B::Field::one()
@@ -620,31 +1669,39 @@ impl<B: Backend> CircuitWriter<B> {
                 let idx_var = self
                     .compute_expr(fn_env, idx)?
                     .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, expr.span))?;
-                let idx = idx_var
-                    .constant()
-                    .ok_or_else(|| self.error(ErrorKind::ExpectedConstant, expr.span))?;
-                let idx: BigUint = idx.into();
-                let idx: usize = idx.try_into().unwrap();
 
                 // retrieve the type of the elements in the array
                 let array_typ = self.expr_type(array).expect("cannot find type of array");
 
-                let elem_type = match array_typ {
-                    TyKind::Array(ty, array_len) => {
-                        if idx >= (*array_len as usize) {
-                            return Err(self.error(
-                                ErrorKind::ArrayIndexOutOfBounds(idx, *array_len as usize - 1),
-                                expr.span,
-                            ));
-                        }
-                        ty
-                    }
+                let (elem_type, array_len) = match array_typ {
+                    TyKind::Array(ty, array_len) => (ty, *array_len as usize),
                     _ => panic!("expected array"),
                 };
 
                 // compute the size of each element in the array
                 let len = self.size_of(elem_type);
 
+                let idx_var = idx_var.value(self, fn_env);
+                let idx = match &idx_var[0] {
+                    ConstOrCell::Const(idx) => *idx,
+
+                    // the index is only known at runtime, so we have to select the element in the circuit
+                    ConstOrCell::Cell(idx_cvar) => {
+                        let array_var = var.value(self, fn_env);
+                        let res = field::select_element(self, &array_var, idx_cvar, len, expr.span);
+                        return Ok(Some(VarOrRef::Var(res)));
+                    }
+                };
+                let idx: BigUint = idx.into();
+                let idx: usize = idx.try_into().unwrap();
+
+                if idx >= array_len {
+                    return Err(self.error(
+                        ErrorKind::ArrayIndexOutOfBounds(idx, array_len - 1),
+                        expr.span,
+                    ));
+                }
+
                 // compute the real index
                 let start = idx * len;
 
@@ -663,7 +1720,30 @@ impl<B: Backend> CircuitWriter<B> {
                 Ok(Some(var))
             }
 
-            ExprKind::ArrayDeclaration(items) => {
+            ExprKind::ArraySlice { array, range } => {
+                // retrieve var of array
+                let var = self
+                    .compute_expr(fn_env, array)?
+                    .expect("array slice on non-array");
+
+                // retrieve the type of the elements in the array
+                let array_typ = self.expr_type(array).expect("cannot find type of array");
+                let elem_type = match array_typ {
+                    TyKind::Array(ty, _) => ty,
+                    _ => panic!("expected array"),
+                };
+                let len = self.size_of(elem_type);
+
+                // the type checker already checked that the slice is within the array,
+                // so the slice simply refers to the cells of its elements
+                let slice = self.range_iterations(range)?;
+                let start = slice.start as usize * len;
+                let var = var.narrow(start, slice.len() * len);
+
+                Ok(Some(var))
+            }
+
+            ExprKind::ArrayDeclaration(items) | ExprKind::TupleDeclaration(items) => {
                 let mut cvars = vec![];
 
                 for item in items {
@@ -711,9 +1791,13 @@ impl<B: Backend> CircuitWriter<B> {
         Var::new(cvars, span)
     }
 
-    pub fn add_public_outputs(&mut self, num: usize, span: Span) {
+    /// Creates the public output cells for a `main` function returning a value of type `typ`.
+    /// Structs and arrays are flattened in field order, so the output takes [Self::size_of] cells.
+    pub fn add_public_outputs(&mut self, typ: &TyKind, span: Span) {
         assert!(self.public_output.is_none());
 
+        let num = self.size_of(typ);
+
         let mut cvars = Vec::with_capacity(num);
         for _ in 0..num {
             let cvar = self
@@ -746,12 +1830,3 @@ impl<B: Backend> CircuitWriter<B> {
         Var::new(cvars, span)
     }
 }
-
-#[derive(Clone, Default, Debug, Serialize, Deserialize)]
-pub(crate) struct PendingGate {
-    pub label: &'static str,
-    #[serde(skip)]
-    pub coeffs: Vec<VestaField>,
-    pub vars: Vec<Option<crate::backends::kimchi::KimchiCellVar>>,
-    pub span: Span,
-}
@@ -1,22 +1,32 @@
 use std::collections::HashMap;
 
 use camino::Utf8PathBuf as PathBuf;
+use kimchi::circuits::constraints::ConstraintSystem;
 use miette::{Context, IntoDiagnostic};
 use once_cell::sync::Lazy;
 
 use crate::{
     backends::{
         kimchi::{
-            prover::{ProverIndex, VerifierIndex},
-            KimchiVesta,
+            cache::{cache_key, CircuitCache},
+            prover::{save_prover_params, ProverIndex, VerifierIndex},
+            KimchiVesta, VestaField,
         },
+        mock::Mock,
         r1cs::{snarkjs::SnarkjsExporter, R1CS},
         Backend, BackendField, BackendKind,
     },
+    circuit_writer::{CircuitOptions, OptLevel},
     cli::packages::path_to_package,
-    compiler::{compile, generate_witness, typecheck_next_file, Sources},
+    compiler::{
+        compile, compile_with_options, generate_witness, typecheck_next_file, IntoMiette as _,
+        Sources,
+    },
+    error::{Error, ErrorKind},
+    helpers::PrettyField as _,
     inputs::{parse_inputs, JsonInputs},
     type_checker::TypeChecker,
+    witness::CompiledCircuit,
 };
 
 use super::packages::{
@@ -25,11 +35,15 @@ use super::packages::{
 
 const COMPILED_DIR: &str = "compiled";
 
+/// The directory, inside [COMPILED_DIR], where compiled circuits are cached.
+const CACHE_DIR: &str = "cache";
+
 #[derive(Clone)]
 enum BackendOpt {
     KimchiVesta,
     R1csBls12_381,
     R1csBn254,
+    Mock,
 }
 
 impl BackendOpt {
@@ -46,9 +60,28 @@ static BACKEND_OPT_MAP: Lazy<HashMap<&'static str, BackendOpt>> = Lazy::new(|| {
     m.insert("kimchi-vesta", BackendOpt::KimchiVesta);
     m.insert("r1cs-bls12-381", BackendOpt::R1csBls12_381);
     m.insert("r1cs-bn254", BackendOpt::R1csBn254);
+    // the curve used by Groth16 provers like snarkjs
+    m.insert("r1cs", BackendOpt::R1csBn254);
+    m.insert("mock", BackendOpt::Mock);
     m
 });
 
+/// The formats in which `noname build --emit` can write out the compiled circuit.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum EmitFormat {
+    /// The gates and the wiring as JSON (see [crate::backends::kimchi::json::CircuitJson]), written to `circuit.json`.
+    Json,
+}
+
+/// Parses the `--optimize-level` flag.
+fn parse_opt_level(level: &str) -> Result<OptLevel, String> {
+    level
+        .parse()
+        .ok()
+        .and_then(OptLevel::from_u8)
+        .ok_or_else(|| format!("unknown optimization level `{level}` (expected 0, 1, 2 or 3)"))
+}
+
 static SUPPORTED_BACKENDS: Lazy<String> = Lazy::new(|| {
     format!(
         "Supported backends: `{}`",
@@ -74,6 +107,22 @@ pub struct CmdBuild {
     #[clap(long)]
     debug: bool,
 
+    /// Writes out the compiled circuit in a machine-readable format, in the `compiled` directory.
+    #[clap(long, value_enum)]
+    emit: Option<EmitFormat>,
+
+    /// The optimization passes to run on the gates:
+    /// 0 disables them, 1 packs two generic gates per row, 2 also removes duplicate gates,
+    /// 3 also merges linear gates into the gate reading their result.
+    /// Constant expressions are folded at every level, including 0.
+    #[clap(long, value_parser = parse_opt_level, default_value = "2")]
+    optimize_level: OptLevel,
+
+    /// Fails if the compiled circuit doesn't have exactly this number of rows,
+    /// to catch changes of its size (e.g. in CI).
+    #[clap(long, value_parser)]
+    assert_rows: Option<usize>,
+
     /// In case the path points to a binary,
     /// outputs the prover parameters to the given file.
     /// Defaults to `prover.nope`
@@ -92,14 +141,27 @@ pub fn cmd_build(args: CmdBuild) -> miette::Result<()> {
         .path
         .unwrap_or_else(|| std::env::current_dir().unwrap().try_into().unwrap());
 
-    let (sources, prover_index, verifier_index) = build(&curr_dir, args.asm, args.debug)?;
-
     // create COMPILED_DIR
     let compiled_path = curr_dir.join(COMPILED_DIR);
     if compiled_path.exists() && !compiled_path.is_dir() {
         miette::bail!("There's a filename called `{}` which collides with noname. Please delete that file first.", compiled_path);
     }
 
+    let cache = CircuitCache::new(compiled_path.join(CACHE_DIR));
+    let json_path = match args.emit {
+        Some(EmitFormat::Json) => Some(compiled_path.join("circuit.json")),
+        None => None,
+    };
+    let (cs, verifier_index) = build_cached(
+        &curr_dir,
+        &cache,
+        args.asm,
+        args.debug,
+        args.optimize_level,
+        json_path.as_ref(),
+        args.assert_rows,
+    )?;
+
     if args.prover_params.is_none() && args.verifier_params.is_none() && !compiled_path.exists() {
         std::fs::create_dir(&compiled_path)
             .into_diagnostic()
@@ -110,13 +172,9 @@ pub fn cmd_build(args: CmdBuild) -> miette::Result<()> {
     let prover_params = args
         .prover_params
         .unwrap_or(compiled_path.join("prover.nope"));
-    /*
-    std::fs::write(&prover_params, rmp_serde::to_vec(&prover_index).unwrap())
-        .into_diagnostic()
-        .wrap_err(format!(
-            "could not write prover params to `{prover_params}`"
-        ))?;
-        */
+    save_prover_params(&cs, &prover_params).wrap_err(format!(
+        "could not write prover params to `{prover_params}`"
+    ))?;
 
     // write verifier
     let verifier_params = args
@@ -228,30 +286,126 @@ fn produce_all_asts<B: Backend>(path: &PathBuf) -> miette::Result<(Sources, Type
     Ok((sources, tast))
 }
 
+/// Prints the warnings emitted while compiling a circuit (see [CompiledCircuit::warnings]).
+fn print_warnings<B: Backend>(sources: &Sources, compiled_circuit: &CompiledCircuit<B>) {
+    for warning in &compiled_circuit.warnings {
+        let (filename, source) = sources
+            .get(&warning.span.filename_id)
+            .expect("couldn't find source")
+            .clone();
+        let report = miette::Report::new(warning.clone());
+        eprintln!(
+            "{:?}",
+            report.with_source_code(miette::NamedSource::new(filename, source))
+        );
+    }
+}
+
+/// Compiles the circuit of a package, printing its assembly if `asm` is set.
+fn compile_package(
+    sources: &Sources,
+    tast: TypeChecker<KimchiVesta>,
+    asm: bool,
+    debug: bool,
+    opt_level: OptLevel,
+) -> miette::Result<CompiledCircuit<KimchiVesta>> {
+    let double_generic_gate_optimization = opt_level >= OptLevel::O1;
+
+    let kimchi_vesta = KimchiVesta::new(double_generic_gate_optimization);
+    let options = CircuitOptions {
+        opt_level,
+        ..Default::default()
+    };
+    let compiled_circuit = compile_with_options(sources, tast, kimchi_vesta, options)?;
+    print_warnings(sources, &compiled_circuit);
+
+    if asm {
+        println!("{}", compiled_circuit.asm(sources, debug));
+    }
+
+    Ok(compiled_circuit)
+}
+
 pub fn build(
     curr_dir: &PathBuf,
     asm: bool,
     debug: bool,
+    opt_level: OptLevel,
 ) -> miette::Result<(Sources, ProverIndex, VerifierIndex)> {
     // produce all TASTs
     let (sources, tast) = produce_all_asts(curr_dir)?;
 
+    let compiled_circuit = compile_package(&sources, tast, asm, debug, opt_level)?;
+
     // produce indexes
-    let double_generic_gate_optimization = false;
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
 
-    let kimchi_vesta = KimchiVesta::new(double_generic_gate_optimization);
-    let compiled_circuit = compile(&sources, tast, kimchi_vesta)?;
+    Ok((sources, prover_index, verifier_index))
+}
 
-    if asm {
-        println!("{}", compiled_circuit.asm(&sources, debug));
+/// Same as [build], but reuses the circuit in the cache if neither the sources nor the options changed.
+/// As the cache can't be used to generate witnesses, this only returns the constraint system of the prover index.
+/// The cache is bypassed when the assembly or the JSON encoding of the circuit (written to `json_path`) is requested.
+/// Circuits with warnings are not cached, so that their warnings are printed on every build.
+/// If `assert_rows` is set, this fails if the circuit doesn't have exactly that number of rows.
+fn build_cached(
+    curr_dir: &PathBuf,
+    cache: &CircuitCache,
+    asm: bool,
+    debug: bool,
+    opt_level: OptLevel,
+    json_path: Option<&PathBuf>,
+    assert_rows: Option<usize>,
+) -> miette::Result<(ConstraintSystem<VestaField>, VerifierIndex)> {
+    // produce all TASTs
+    let (sources, tast) = produce_all_asts(curr_dir)?;
+
+    let options = CircuitOptions {
+        opt_level,
+        ..Default::default()
+    };
+    let key = cache_key(&sources, options, opt_level >= OptLevel::O1);
+
+    if !asm && json_path.is_none() {
+        if let Some(cached) = cache.get(key) {
+            println!("the circuit hasn't changed, using the cached one");
+            if let Some(expected) = assert_rows {
+                cached.assert_rows(expected)?;
+            }
+            return cached.compile_to_indexes();
+        }
     }
 
-    // TODO: cache artifacts
+    let compiled_circuit = compile_package(&sources, tast, asm, debug, opt_level)?;
+    if compiled_circuit.warnings.is_empty() {
+        cache.insert(key, &compiled_circuit.circuit.backend)?;
+    }
+
+    if let Some(expected) = assert_rows {
+        compiled_circuit.assert_rows(expected)?;
+    }
+
+    if let Some(json_path) = json_path {
+        let json = compiled_circuit
+            .circuit
+            .backend
+            .to_circuit_json()
+            .to_json()?;
+        if let Some(dir) = json_path.parent() {
+            std::fs::create_dir_all(dir)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("could not create dir at `{dir}`"))?;
+        }
+        std::fs::write(json_path, json)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("could not write the circuit to `{json_path}`"))?;
+        println!("circuit written to `{json_path}`");
+    }
 
     // produce indexes
     let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
 
-    Ok((sources, prover_index, verifier_index))
+    Ok((prover_index.constraint_system().clone(), verifier_index))
 }
 
 #[derive(clap::Parser)]
@@ -261,13 +415,14 @@ pub struct CmdTest {
     path: PathBuf,
 
     /// Backend to use for running the noname file.
+    /// Without a backend, the functions of the file annotated with `#[test(...)]` are run with the mock backend.
     #[clap(
         short,
         long,
         value_parser,
         help = SUPPORTED_BACKENDS.as_str()
     )]
-    backend: String,
+    backend: Option<String>,
 
     /// public inputs in a JSON format using decimal values (e.g. {"a": "1", "b": "2"})
     #[clap(long)]
@@ -281,13 +436,16 @@ pub struct CmdTest {
     #[clap(short, long)]
     debug: bool,
 
-    /// enable the double generic gate optimization of kimchi (by default noname uses that optimization)
-    #[clap(long)]
-    double: bool,
+    /// the optimization passes to run on the gates (0 to 3, see `noname build --help`)
+    #[clap(long, value_parser = parse_opt_level, default_value = "2")]
+    optimize_level: OptLevel,
 }
 
 pub fn cmd_test(args: CmdTest) -> miette::Result<()> {
-    let backend = args.backend;
+    let backend = match args.backend {
+        Some(backend) => backend,
+        None => return cmd_test_fns(&args.path),
+    };
 
     // parse inputs
     let public_inputs = if let Some(s) = args.public_inputs {
@@ -306,13 +464,21 @@ pub fn cmd_test(args: CmdTest) -> miette::Result<()> {
         BackendOpt::KimchiVesta => BackendKind::new_kimchi_vesta(false),
         BackendOpt::R1csBls12_381 => BackendKind::new_r1cs_bls12_381(),
         BackendOpt::R1csBn254 => BackendKind::new_r1cs_bn254(),
+        BackendOpt::Mock => BackendKind::new_mock(),
     };
 
     match backend_kind {
         BackendKind::KimchiVesta(_) => {
             let (tast, sources) = typecheck_file(&args.path)?;
-            let kimchi_vesta = KimchiVesta::new(args.double);
-            let compiled_circuit = compile(&sources, tast, kimchi_vesta)?;
+            // like `noname build`, the optimization level enables the double generic gate optimization
+            let double_generic_gate_optimization = args.optimize_level >= OptLevel::O1;
+            let kimchi_vesta = KimchiVesta::new(double_generic_gate_optimization);
+            let options = CircuitOptions {
+                opt_level: args.optimize_level,
+                ..Default::default()
+            };
+            let compiled_circuit = compile_with_options(&sources, tast, kimchi_vesta, options)?;
+            print_warnings(&sources, &compiled_circuit);
 
             let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
             println!("successfully compiled");
@@ -336,6 +502,10 @@ pub fn cmd_test(args: CmdTest) -> miette::Result<()> {
         BackendKind::R1csBn254(r1cs) => {
             test_r1cs_backend(r1cs, &args.path, public_inputs, private_inputs, args.debug)?;
         }
+        BackendKind::Mock(mock) => {
+            let (tast, sources) = typecheck_file(&args.path)?;
+            check_mock_backend(mock, &sources, tast, public_inputs, private_inputs)?;
+        }
     }
 
     Ok(())
@@ -389,6 +559,7 @@ pub fn cmd_run(args: CmdRun) -> miette::Result<()> {
         BackendOpt::KimchiVesta => BackendKind::new_kimchi_vesta(false),
         BackendOpt::R1csBls12_381 => BackendKind::new_r1cs_bls12_381(),
         BackendOpt::R1csBn254 => BackendKind::new_r1cs_bn254(),
+        BackendOpt::Mock => BackendKind::new_mock(),
     };
 
     match backend_kind {
@@ -401,6 +572,10 @@ pub fn cmd_run(args: CmdRun) -> miette::Result<()> {
         BackendKind::R1csBn254(r1cs) => {
             run_r1cs_backend(r1cs, &curr_dir, public_inputs, private_inputs)?
         }
+        BackendKind::Mock(mock) => {
+            let (sources, tast) = produce_all_asts(&curr_dir)?;
+            check_mock_backend(mock, &sources, tast, public_inputs, private_inputs)?
+        }
     }
 
     Ok(())
@@ -419,6 +594,7 @@ where
     let (sources, tast) = produce_all_asts(curr_dir)?;
 
     let compiled_circuit = compile(&sources, tast, r1cs)?;
+    print_warnings(&sources, &compiled_circuit);
 
     let generated_witness =
         generate_witness(&compiled_circuit, &sources, public_inputs, private_inputs)?;
@@ -452,6 +628,7 @@ where
     let (tast, sources) = typecheck_file(path)?;
 
     let compiled_circuit = compile(&sources, tast, r1cs)?;
+    print_warnings(&sources, &compiled_circuit);
 
     generate_witness(&compiled_circuit, &sources, public_inputs, private_inputs)?;
 
@@ -462,14 +639,188 @@ where
     Ok(())
 }
 
+/// Runs the test functions of a file, and reports which ones pass.
+fn cmd_test_fns(path: &PathBuf) -> miette::Result<()> {
+    let code = std::fs::read_to_string(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("could not read file: `{}` (are you sure it exists?)", path))?;
+
+    let reports = run_test_fns(path.as_str(), &code)?;
+    println!("running {} tests", reports.len());
+
+    let mut failures = vec![];
+    for report in &reports {
+        match &report.result {
+            Ok(()) => println!("test {} ... ok", report.name),
+            Err(err) => {
+                println!("test {} ... FAILED", report.name);
+                failures.push((&report.name, err));
+            }
+        }
+    }
+
+    for (name, err) in &failures {
+        println!("\n---- {name} ----\n{err:?}");
+    }
+
+    let passed = reports.len() - failures.len();
+    if failures.is_empty() {
+        println!("\ntest result: ok. {passed} passed; 0 failed");
+        Ok(())
+    } else {
+        println!(
+            "\ntest result: FAILED. {passed} passed; {} failed",
+            failures.len()
+        );
+        Err(miette::miette!(
+            "{} of {} tests failed",
+            failures.len(),
+            reports.len()
+        ))
+    }
+}
+
+/// The result of running a test function (see [run_test_fns]).
+pub struct TestReport {
+    pub name: String,
+    pub result: miette::Result<()>,
+}
+
+/// Runs the functions of a noname file that have a `#[test(...)]` attribute, in the order in which they are declared.
+/// Each test function is compiled with the mock backend in place of the main function,
+/// and the witness is generated with the inputs of its attribute:
+/// a test passes if all the gates are satisfied and the function returns the expected output.
+pub fn run_test_fns(filename: &str, code: &str) -> miette::Result<Vec<TestReport>> {
+    let (tast, _sources) = typecheck_code::<Mock>(filename, code)?;
+    let names: Vec<_> = tast
+        .test_fns()
+        .iter()
+        .map(|function| function.sig.name.value.clone())
+        .collect();
+
+    let reports = names
+        .into_iter()
+        .map(|name| {
+            let result = run_test_fn(filename, code, &name);
+            TestReport { name, result }
+        })
+        .collect();
+
+    Ok(reports)
+}
+
+fn run_test_fn(filename: &str, code: &str, name: &str) -> miette::Result<()> {
+    // compiling consumes the type checker, so each test is type checked again
+    let (mut tast, sources) = typecheck_code::<Mock>(filename, code)?;
+    let function = tast
+        .test_fns()
+        .into_iter()
+        .find(|function| function.sig.name.value == name)
+        .cloned()
+        .expect("test function not found");
+    let (inputs, output) = function
+        .test_case()
+        .expect("test functions have a test attribute");
+    let span = function.attribute.as_ref().unwrap().span;
+
+    // the inputs of a test are all private inputs
+    let private_inputs = JsonInputs(
+        function
+            .sig
+            .arguments
+            .iter()
+            .map(|arg| arg.name.value.clone())
+            .zip(inputs.iter().cloned())
+            .collect(),
+    );
+
+    tast.use_test_as_main(name).into_miette(&sources)?;
+    let compiled_circuit = compile(&sources, tast, Mock::new())?;
+    print_warnings(&sources, &compiled_circuit);
+    let generated_witness = generate_witness(
+        &compiled_circuit,
+        &sources,
+        JsonInputs::default(),
+        private_inputs,
+    )?;
+    generated_witness.check().into_miette(&sources)?;
+
+    if let (Some(output), Some(return_type)) = (output, &function.sig.return_type) {
+        let expected = compiled_circuit
+            .parse_single_input(output.clone(), &return_type.kind)
+            .map_err(|e| Error::new("runtime", ErrorKind::ParsingError(e), span))
+            .into_miette(&sources)?;
+
+        if expected != generated_witness.public_outputs {
+            let kind = ErrorKind::UnexpectedTestOutput {
+                expected: pretty_values(&expected),
+                observed: pretty_values(&generated_witness.public_outputs),
+            };
+            return Err(Error::new("runtime", kind, span)).into_miette(&sources);
+        }
+    }
+
+    Ok(())
+}
+
+/// Displays the field elements of a value, like `5` or `[1, 2]`.
+fn pretty_values(values: &[VestaField]) -> String {
+    let values: Vec<_> = values.iter().map(|value| value.pretty()).collect();
+    match values.as_slice() {
+        [value] => value.clone(),
+        _ => format!("[{}]", values.join(", ")),
+    }
+}
+
+/// Generates the witness with the mock backend and reports every gate that is not satisfied.
+fn check_mock_backend(
+    mock: Mock,
+    sources: &Sources,
+    tast: TypeChecker<Mock>,
+    public_inputs: JsonInputs,
+    private_inputs: JsonInputs,
+) -> miette::Result<()> {
+    let compiled_circuit = compile(sources, tast, mock)?;
+    print_warnings(sources, &compiled_circuit);
+
+    let generated_witness =
+        generate_witness(&compiled_circuit, sources, public_inputs, private_inputs)?;
+
+    for failure in generated_witness.failures() {
+        println!(
+            "gate {} ({:?}) is not satisfied: {}",
+            failure.row, failure.typ, failure.debug_info.note
+        );
+    }
+    generated_witness.check().into_miette(sources)?;
+
+    println!("all {} gates are satisfied", generated_witness.checks.len());
+
+    Ok(())
+}
+
 fn typecheck_file<B: Backend>(path: &PathBuf) -> miette::Result<(TypeChecker<B>, Sources)> {
     let code = std::fs::read_to_string(path)
         .into_diagnostic()
         .wrap_err_with(|| format!("could not read file: `{}` (are you sure it exists?)", path))?;
 
+    typecheck_code(path.as_str(), &code)
+}
+
+fn typecheck_code<B: Backend>(
+    filename: &str,
+    code: &str,
+) -> miette::Result<(TypeChecker<B>, Sources)> {
     let mut sources = Sources::new();
     let mut tast = TypeChecker::<B>::new();
-    let _node_id = typecheck_next_file(&mut tast, None, &mut sources, path.to_string(), code, 0)?;
+    let _node_id = typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        filename.to_string(),
+        code.to_string(),
+        0,
+    )?;
 
     Ok((tast, sources))
 }
@@ -1,10 +1,33 @@
 use camino::Utf8PathBuf as PathBuf;
 use miette::{Context, IntoDiagnostic};
 
-use crate::inputs::parse_inputs;
+use crate::{
+    backends::kimchi::prover::{proof_from_bytes, proof_to_bytes, ProofJson},
+    circuit_writer::OptLevel,
+    inputs::{parse_input_schema, parse_inputs, parse_inputs_with_schema, InputSchema},
+};
 
 use super::cmd_build_and_check::build;
 
+/// The formats in which `noname prove` outputs the proof, and in which `noname verify` reads it.
+#[derive(Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProofFormat {
+    /// The binary encoding of the proof (see [proof_to_bytes]), and human-readable messages.
+    #[default]
+    Text,
+
+    /// The proof along with its public inputs and outputs, as JSON (see [ProofJson]).
+    Json,
+}
+
+/// The formats in which `noname prove --emit-witness` can write out the witness.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WitnessFormat {
+    /// One line per row, with its gate kind and its values in decimal, written to `witness.csv` in the directory of the proof
+    /// (see [crate::backends::kimchi::Kimchi::witness_to_csv]).
+    Csv,
+}
+
 #[derive(clap::Parser)]
 pub struct CmdProve {
     /// Path to the directory to create.
@@ -15,7 +38,7 @@ pub struct CmdProve {
     #[clap(long)]
     debug: bool,
 
-    /// Path to the resulting proof. Defaults to `proof.nope`.
+    /// Path to the resulting proof. Defaults to `proof.nope` (or `proof.json` with `--format json`).
     #[clap(long, value_parser)]
     proof_path: Option<PathBuf>,
 
@@ -26,6 +49,20 @@ pub struct CmdProve {
     /// JSON encoding of the private inputs. Similar to `--public-inputs` but for private inputs.
     #[clap(long, value_parser, default_value = "{}")]
     private_inputs: String,
+
+    /// Path to a JSON file mapping input names to bounds (e.g. `{"age": {"bits": 8}, "level": {"min": "1", "max": "10"}}`),
+    /// which the inputs are checked against before generating the witness.
+    #[clap(long, value_parser)]
+    input_schema: Option<PathBuf>,
+
+    /// Writes out the witness in the given format, next to the proof, to inspect it in a spreadsheet.
+    #[clap(long, value_enum)]
+    emit_witness: Option<WitnessFormat>,
+
+    /// With `json`, writes the proof and its public inputs and outputs as JSON to the proof path
+    /// (`proof.json` by default), and prints the same JSON instead of the other messages.
+    #[clap(long, value_enum, default_value = "text")]
+    format: ProofFormat,
 }
 
 pub fn cmd_prove(args: CmdProve) -> miette::Result<()> {
@@ -33,35 +70,86 @@ pub fn cmd_prove(args: CmdProve) -> miette::Result<()> {
         .path
         .unwrap_or_else(|| std::env::current_dir().unwrap().try_into().unwrap());
 
-    let (sources, prover_index, verifier_index) = build(&curr_dir, false, args.debug)?;
+    let (sources, prover_index, verifier_index) =
+        build(&curr_dir, false, args.debug, OptLevel::default())?;
 
     // parse inputs
-    let public_inputs = parse_inputs(&args.public_inputs).unwrap();
-    let private_inputs = parse_inputs(&args.private_inputs).unwrap();
+    let schema = match &args.input_schema {
+        Some(schema_path) => {
+            let schema = std::fs::read_to_string(schema_path)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("could not read the input schema at `{schema_path}`"))?;
+            parse_input_schema(&schema)?
+        }
+        None => InputSchema::default(),
+    };
+    let public_inputs = parse_inputs_with_schema(&args.public_inputs, &schema)?;
+    let private_inputs = parse_inputs_with_schema(&args.private_inputs, &schema)?;
+
+    let proof_path = args.proof_path.unwrap_or_else(|| match args.format {
+        ProofFormat::Text => curr_dir.join("proof.nope"),
+        ProofFormat::Json => curr_dir.join("proof.json"),
+    });
+
+    // generate the witness
+    let witness = prover_index.generate_witness(&sources, public_inputs, private_inputs)?;
+
+    // write out the witness, next to the proof
+    let witness_path = match args.emit_witness {
+        Some(WitnessFormat::Csv) => {
+            let csv = prover_index.witness_to_csv(&witness);
+            let witness_path = proof_path.parent().map_or_else(
+                || PathBuf::from("witness.csv"),
+                |dir| dir.join("witness.csv"),
+            );
+            std::fs::write(&witness_path, csv)
+                .into_diagnostic()
+                .wrap_err(format!("could not write the witness to `{witness_path}`"))?;
+            Some(witness_path)
+        }
+        None => None,
+    };
 
     // create proof
     let (proof, full_public_inputs, public_output) =
-        prover_index.prove(&sources, public_inputs, private_inputs, args.debug)?;
+        prover_index.prove_witness(witness, args.debug)?;
 
     // verify proof
     if args.debug {
-        verifier_index.verify(full_public_inputs, proof.clone())?;
+        verifier_index.verify(full_public_inputs.clone(), proof.clone())?;
     }
 
     // serialize proof
-    let proof_path = args
-        .proof_path
-        .unwrap_or_else(|| curr_dir.join("proof.nope"));
-    std::fs::write(&proof_path, rmp_serde::to_vec(&proof).unwrap())
+    if args.format == ProofFormat::Json {
+        let json = ProofJson::new(&proof, &full_public_inputs, &public_output).to_json();
+        std::fs::write(&proof_path, &json)
+            .into_diagnostic()
+            .wrap_err(format!("could not write the proof to `{proof_path}`"))?;
+
+        println!("{json}");
+        return Ok(());
+    }
+
+    std::fs::write(&proof_path, proof_to_bytes(&proof))
         .into_diagnostic()
         .wrap_err(format!("could not write the proof to `{proof_path}`"))?;
 
     // notification
+    if let Some(witness_path) = witness_path {
+        println!("the witness was written to `{witness_path}`");
+    }
+    let metrics = prover_index.metrics(&proof);
+    println!(
+        "the proof takes {} bytes, with an SRS of size {} (for a domain of size {})",
+        metrics.proof_size, metrics.srs_size, metrics.domain_size
+    );
     if public_output.is_empty() {
         println!(
             "proof created at path `{proof_path}`. You can use `noname --verify` to verify it. Note that you will need to pass the same JSON-encoded public inputs as you did when creating the proof. (If you didn't use the `--public-inputs` flag, then you don't need to pass any public inputs.)",
         );
     } else {
+        let json = prover_index.public_output_to_json(&public_output)?;
+        println!("the public output is `{json}`");
         println!("proof created at path `{proof_path}`. Since running the proof produced a  public output `{public:?}`, you will need to also pass the expected public output to the verifier (who can run `noname verify --public-output '{public:?}'`).", public=public_output);
     }
 
@@ -75,17 +163,24 @@ pub struct CmdVerify {
     #[clap(short, long, value_parser)]
     path: Option<PathBuf>,
 
-    /// Path to the proof to verify. Defaults to `proof.nope`.
+    /// Path to the proof to verify. Defaults to `proof.nope` (or `proof.json` with `--format json`).
     #[clap(short, long, value_parser)]
     proof_path: Option<PathBuf>,
 
     /// JSON encoding of the public inputs. For example: `--public-inputs {"a": "1", "b": ["2", "3"]}`.
     #[clap(short, long, value_parser)]
-    public_inputs: String,
+    public_inputs: Option<String>,
 
     /// An optional expected public output, in JSON format.
+    /// With `--format json`, it is required if the circuit returns a value.
     #[clap(short, long, value_parser)]
     public_output: Option<String>,
+
+    /// With `json`, reads the proof from the JSON written by `noname prove --format json` (at `proof.json` by default).
+    /// The public values stored in the JSON must match the ones given with `--public-inputs` and `--public-output`,
+    /// which the proof is verified against.
+    #[clap(long, value_enum, default_value = "text")]
+    format: ProofFormat,
 }
 
 pub fn cmd_verify(args: CmdVerify) -> miette::Result<()> {
@@ -93,10 +188,47 @@ pub fn cmd_verify(args: CmdVerify) -> miette::Result<()> {
         .path
         .unwrap_or_else(|| std::env::current_dir().unwrap().try_into().unwrap());
 
-    let (_sources, _prover_index, verifier_index) = build(&curr_dir, false, false)?;
+    let (_sources, prover_index, verifier_index) =
+        build(&curr_dir, false, false, OptLevel::default())?;
+
+    if args.format == ProofFormat::Json {
+        let proof_path = args
+            .proof_path
+            .unwrap_or_else(|| curr_dir.join("proof.json"));
+        let json = std::fs::read_to_string(&proof_path)
+            .into_diagnostic()
+            .wrap_err(format!("could not read the proof at `{proof_path}`"))?;
+        let (proof, proof_public_inputs) = ProofJson::from_json(&json)?.decode()?;
+
+        // the public values of the proof come from the prover, so they are checked against the expected ones
+        let public_inputs = args.public_inputs.ok_or_else(|| {
+            miette::miette!("the public inputs must be given with `--public-inputs`")
+        })?;
+        let public_output = args
+            .public_output
+            .map(|output| serde_json::from_str(&output))
+            .transpose()
+            .into_diagnostic()
+            .wrap_err("could not parse the public output")?;
+        let full_public_inputs =
+            prover_index.encode_public_values(parse_inputs(&public_inputs)?, public_output)?;
+        if full_public_inputs != proof_public_inputs {
+            miette::bail!("the public values of the proof at `{proof_path}` are not the ones given with `--public-inputs` and `--public-output`");
+        }
+
+        verifier_index
+            .verify(full_public_inputs, proof)
+            .wrap_err("failed to verify the proof")?;
+
+        println!("the proof at `{proof_path}` is valid");
+        return Ok(());
+    }
 
     // parse inputs
-    let mut public_inputs = parse_inputs(&args.public_inputs).unwrap();
+    let public_inputs = args
+        .public_inputs
+        .ok_or_else(|| miette::miette!("the public inputs must be given with `--public-inputs`"))?;
+    let mut public_inputs = parse_inputs(&public_inputs).unwrap();
 
     if let Some(public_output) = &args.public_output {
         let public_output = parse_inputs(public_output).unwrap();
@@ -114,11 +246,12 @@ pub fn cmd_verify(args: CmdVerify) -> miette::Result<()> {
         miette::bail!("proof does not exist at path `{proof_path}`. Perhaps pass the correct path via the `--proof-path` flag?");
     }
 
-    let proof = rmp_serde::from_read(std::fs::File::open(&proof_path).unwrap())
+    let bytes = std::fs::read(&proof_path)
         .into_diagnostic()
-        .wrap_err(format!(
-            "could not deserialize the given proof at `{proof_path}`"
-        ))?;
+        .wrap_err(format!("could not read the proof at `{proof_path}`"))?;
+    let proof = proof_from_bytes(&bytes).wrap_err(format!(
+        "could not deserialize the given proof at `{proof_path}`"
+    ))?;
 
     // verify proof
     unimplemented!();
@@ -132,3 +265,103 @@ pub fn cmd_verify(args: CmdVerify) -> miette::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::backends::kimchi::VestaField;
+
+    use super::*;
+
+    /// A temporary directory, removed when dropped (even if the test fails).
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_json_proof_round_trip() -> miette::Result<()> {
+        let temp_dir = TempDir(
+            std::env::temp_dir()
+                .join(format!("noname_test_json_proof_{}", std::process::id()))
+                .try_into()
+                .unwrap(),
+        );
+        let dir = temp_dir.0.clone();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("Noname.toml"),
+            "[package]\nname = \"test/json_proof\"\nversion = \"0.1.0\"\ndependencies = []\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("src").join("main.no"),
+            "fn main(pub xx: Field, yy: Field) -> Field {\n    return xx * yy;\n}\n",
+        )
+        .unwrap();
+
+        // the largest field element, so that the output doesn't fit in a JSON number
+        let minus_one = "0x40000000000000000000000000000000224698fc094cf91b992d30ed00000000";
+        cmd_prove(CmdProve {
+            path: Some(dir.clone()),
+            debug: false,
+            proof_path: None,
+            public_inputs: r#"{"xx": "2"}"#.to_string(),
+            private_inputs: format!(r#"{{"yy": "{minus_one}"}}"#),
+            input_schema: None,
+            emit_witness: None,
+            format: ProofFormat::Json,
+        })?;
+
+        let proof_path = dir.join("proof.json");
+        let json = std::fs::read_to_string(&proof_path).unwrap();
+        let proof_json = ProofJson::from_json(&json)?;
+        assert_eq!(proof_json.public_inputs, vec!["2".to_string()]);
+
+        let minus_two = -VestaField::from(2u64);
+        assert_eq!(
+            proof_json.public_outputs,
+            vec![num_bigint::BigUint::from(minus_two).to_string()]
+        );
+
+        // the proof survives a round trip
+        let (proof, full_public_inputs) = proof_json.decode()?;
+        assert_eq!(full_public_inputs, vec![minus_two, VestaField::from(2u64)]);
+        assert_eq!(
+            ProofJson::new(&proof, &full_public_inputs, &full_public_inputs[..1]),
+            proof_json
+        );
+
+        let output = format!(r#""{}""#, proof_json.public_outputs[0]);
+        let verify =
+            |proof_path: &PathBuf, public_inputs: Option<&str>, public_output: Option<&str>| {
+                cmd_verify(CmdVerify {
+                    path: Some(dir.clone()),
+                    proof_path: Some(proof_path.clone()),
+                    public_inputs: public_inputs.map(str::to_string),
+                    public_output: public_output.map(str::to_string),
+                    format: ProofFormat::Json,
+                })
+            };
+        verify(&proof_path, Some(r#"{"xx": "2"}"#), Some(&output))?;
+
+        // the public values must be given, and match the ones of the proof
+        assert!(verify(&proof_path, None, Some(&output)).is_err());
+        assert!(verify(&proof_path, Some(r#"{"xx": "2"}"#), None).is_err());
+        assert!(verify(&proof_path, Some(r#"{"xx": "3"}"#), Some(&output)).is_err());
+        assert!(verify(&proof_path, Some(r#"{"xx": "2"}"#), Some(r#""1""#)).is_err());
+
+        // a proof with other public values doesn't verify
+        let tampered = ProofJson {
+            public_inputs: vec!["3".to_string()],
+            ..proof_json.clone()
+        };
+        let tampered_path = dir.join("tampered.json");
+        std::fs::write(&tampered_path, tampered.to_json()).unwrap();
+        assert!(verify(&tampered_path, Some(r#"{"xx": "3"}"#), Some(&output)).is_err());
+
+        Ok(())
+    }
+}
@@ -116,7 +116,10 @@ impl DependencyGraph {
             }
 
             // extract dependencies
-            get_deps_of_package(&manifest)
+            // (and cache them, as a package can be reached through different paths)
+            let deps = get_deps_of_package(&manifest);
+            self.cached_manifests.insert(package.clone(), deps.clone());
+            deps
         };
 
         // recursively do the same
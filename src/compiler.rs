@@ -9,9 +9,16 @@ use std::collections::HashMap;
 use miette::NamedSource;
 
 use crate::{
-    backends::Backend, circuit_writer::CircuitWriter, cli::packages::UserRepo, error::Result,
-    inputs::JsonInputs, lexer::Token, name_resolution::NAST, parser::AST,
-    type_checker::TypeChecker, witness::CompiledCircuit,
+    backends::Backend,
+    circuit_writer::{CircuitOptions, CircuitWriter},
+    cli::packages::UserRepo,
+    error::Result,
+    inputs::JsonInputs,
+    lexer::Token,
+    name_resolution::NAST,
+    parser::AST,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
 };
 
 /// Contains the association between a counter and the corresponding filename and source code.
@@ -99,7 +106,7 @@ pub fn typecheck_next_file_inner<B: Backend>(
     let (nast, new_node_id) = get_nast(this_module, sources, filename, code, node_id)?;
 
     // type checker
-    typechecker.analyze(nast, is_lib)?;
+    let new_node_id = typechecker.analyze(nast, is_lib, new_node_id)?;
 
     Ok(new_node_id)
 }
@@ -141,7 +148,18 @@ pub fn compile<B: Backend>(
     tast: TypeChecker<B>,
     backend: B,
 ) -> miette::Result<CompiledCircuit<B>> {
-    CircuitWriter::generate_circuit(tast, backend).into_miette(sources)
+    compile_with_options(sources, tast, backend, CircuitOptions::default())
+}
+
+/// Same as [compile], with specific options (see [CircuitOptions]).
+/// The warnings of the compiled circuit are not printed, but returned in [CompiledCircuit::warnings].
+pub fn compile_with_options<B: Backend>(
+    sources: &Sources,
+    tast: TypeChecker<B>,
+    backend: B,
+    options: CircuitOptions,
+) -> miette::Result<CompiledCircuit<B>> {
+    CircuitWriter::generate_circuit(tast, backend, options, None).into_miette(sources)
 }
 
 pub fn generate_witness<B: Backend>(
@@ -30,9 +30,7 @@ pub fn check<B: Backend>(
             // x * (x - 1)
             let x_1 = compiler.backend.add_const(x, &one.neg(), span);
             let res = compiler.backend.mul(x, &x_1, span);
-            compiler
-                .backend
-                .assert_eq_const(&res, B::Field::zero(), span);
+            compiler.assert_eq_cell(&res, &ConstOrCell::Const(B::Field::zero()), span);
         }
     };
 }
@@ -2,14 +2,17 @@ use crate::{
     backends::Backend,
     circuit_writer::CircuitWriter,
     constants::Span,
+    error::{ErrorKind, Result},
     var::{ConstOrCell, Value, Var},
+    witness::WitnessEnv,
 };
 
 use super::boolean;
 
 use ark_ff::{One, Zero};
+use num_bigint::BigUint;
 
-use std::ops::Neg;
+use std::{ops::Neg, sync::Arc};
 
 /// Negates a field element
 pub fn neg<B: Backend>(
@@ -64,8 +67,36 @@ pub fn sub<B: Backend>(
     rhs: &ConstOrCell<B::Field, B::Var>,
     span: Span,
 ) -> Var<B::Field, B::Var> {
-    let neg_rhs = neg(compiler, rhs, span);
-    add(compiler, lhs, &neg_rhs.cvars[0], span)
+    match (lhs, rhs) {
+        // 2 constants
+        (ConstOrCell::Const(lhs), ConstOrCell::Const(rhs)) => Var::new_constant(*lhs - *rhs, span),
+
+        // a var minus a constant is the var plus the negated constant
+        (ConstOrCell::Cell(cvar), ConstOrCell::Const(cst)) => {
+            if cst.is_zero() {
+                return Var::new_var(cvar.clone(), span);
+            }
+
+            let res = compiler.backend.add_const(cvar, &cst.neg(), span);
+            Var::new_var(res, span)
+        }
+
+        // a constant minus a var is the negated var plus the constant
+        (ConstOrCell::Const(cst), ConstOrCell::Cell(cvar)) => {
+            let neg_cvar = compiler.backend.neg(cvar, span);
+            if cst.is_zero() {
+                return Var::new_var(neg_cvar, span);
+            }
+
+            let res = compiler.backend.add_const(&neg_cvar, cst, span);
+            Var::new_var(res, span)
+        }
+
+        (ConstOrCell::Cell(lhs), ConstOrCell::Cell(rhs)) => {
+            let res = compiler.backend.sub(lhs, rhs, span);
+            Var::new_var(res, span)
+        }
+    }
 }
 
 /// Multiplies two field elements
@@ -207,19 +238,99 @@ fn equal_cells<B: Backend>(
             );
             let neg_res = compiler.backend.neg(&res, span);
             let one_minus_res = compiler.backend.add_const(&neg_res, &one, span);
-            compiler
-                .backend
-                .assert_eq_var(&diff_inv_mul_diff, &one_minus_res, span);
+            compiler.assert_eq_cell(&diff_inv_mul_diff, &ConstOrCell::Cell(one_minus_res), span);
 
             // 4. res * diff = 0
             let res_mul_diff = compiler.backend.mul(&res, &diff, span);
-            compiler.backend.assert_eq_const(&res_mul_diff, zero, span);
+            compiler.assert_eq_cell(&res_mul_diff, &ConstOrCell::Const(zero), span);
 
             Var::new_var(res, span)
         }
     }
 }
 
+/// Selects the element at position `idx` in `array`, where each element is made of `elem_len` cells.
+/// This is used when the index is only known at runtime:
+/// every element is compared to the index, and the selected element is the sum of `(idx == i) * array[i]`.
+/// The index is also constrained to be in bounds (exactly one comparison must succeed).
+pub fn select_element<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    array: &Var<B::Field, B::Var>,
+    idx: &B::Var,
+    elem_len: usize,
+    span: Span,
+) -> Var<B::Field, B::Var> {
+    // sanity check
+    assert!(elem_len > 0 && array.len() % elem_len == 0);
+
+    let zero = B::Field::zero();
+    let idx = ConstOrCell::Cell(idx.clone());
+
+    let mut res = vec![ConstOrCell::Const(zero); elem_len];
+    let mut hits = ConstOrCell::Const(zero);
+
+    for (ii, elem) in array.cvars.chunks(elem_len).enumerate() {
+        let ii = ConstOrCell::Const(B::Field::from(ii as u64));
+        let is_ii = equal_cells(compiler, &idx, &ii, span);
+
+        hits = add(compiler, &hits, &is_ii[0], span)[0].clone();
+
+        for (acc, cell) in res.iter_mut().zip(elem) {
+            let selected = mul(compiler, &is_ii[0], cell, span);
+            *acc = add(compiler, acc, &selected[0], span)[0].clone();
+        }
+    }
+
+    // the index must match exactly one of the positions
+    if let ConstOrCell::Cell(hits) = &hits {
+        compiler.assert_eq_cell(hits, &ConstOrCell::Const(B::Field::one()), span);
+    }
+
+    Var::new(res, span)
+}
+
+/// Returns a copy of `array` where the element at position `idx` (made of `elem_len` cells) is replaced by `value`,
+/// when the index is only known at runtime:
+/// every element becomes `(idx == i) ? value : array[i]`.
+/// As with [select_element], the index is constrained to be in bounds.
+pub fn update_element<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    array: &Var<B::Field, B::Var>,
+    idx: &B::Var,
+    elem_len: usize,
+    value: &Var<B::Field, B::Var>,
+    span: Span,
+) -> Var<B::Field, B::Var> {
+    // sanity check
+    assert!(elem_len > 0 && array.len() % elem_len == 0);
+    assert_eq!(value.len(), elem_len);
+
+    let zero = B::Field::zero();
+    let idx = ConstOrCell::Cell(idx.clone());
+
+    let mut res = Vec::with_capacity(array.len());
+    let mut hits = ConstOrCell::Const(zero);
+
+    for (ii, elem) in array.cvars.chunks(elem_len).enumerate() {
+        let ii = ConstOrCell::Const(B::Field::from(ii as u64));
+        let is_ii = equal_cells(compiler, &idx, &ii, span);
+
+        hits = add(compiler, &hits, &is_ii[0], span)[0].clone();
+
+        for (new, old) in value.cvars.iter().zip(elem) {
+            let updated = if_else_inner(compiler, &is_ii[0], new, old, span);
+            res.push(updated[0].clone());
+        }
+    }
+
+    // the index must match exactly one of the positions
+    if let ConstOrCell::Cell(hits) = &hits {
+        compiler.assert_eq_cell(hits, &ConstOrCell::Const(B::Field::one()), span);
+    }
+
+    Var::new(res, span)
+}
+
 pub fn if_else<B: Backend>(
     compiler: &mut CircuitWriter<B>,
     cond: &Var<B::Field, B::Var>,
@@ -270,3 +381,195 @@ pub fn if_else_inner<B: Backend>(
     let temp = mul(compiler, &one_minus_cond[0], else_, span);
     add(compiler, &cond_then[0], &temp[0], span)
 }
+
+/// Decomposes a field element into `num_bits` bits (little-endian),
+/// each constrained to be a boolean, and constrains them to recompose into the original value.
+/// This means that a value that does not fit in `num_bits` bits will fail to produce a valid witness.
+pub fn to_bits<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    var: &ConstOrCell<B::Field, B::Var>,
+    num_bits: usize,
+    span: Span,
+) -> Result<Vec<ConstOrCell<B::Field, B::Var>>> {
+    let zero = B::Field::zero();
+    let one = B::Field::one();
+
+    // the recomposition must not wrap around the modulus
+    let max_value: BigUint = (zero - one).into();
+    if num_bits >= max_value.bits() as usize {
+        return Err(compiler.error(ErrorKind::InvalidBitSize(num_bits), span));
+    }
+
+    match var {
+        ConstOrCell::Const(cst) => {
+            let value: BigUint = (*cst).into();
+            if value.bits() as usize > num_bits {
+                return Err(compiler.error(ErrorKind::ValueTooLarge(num_bits), span));
+            }
+
+            let bits = (0..num_bits)
+                .map(|ii| ConstOrCell::Const(if value.bit(ii as u64) { one } else { zero }))
+                .collect();
+
+            Ok(bits)
+        }
+        ConstOrCell::Cell(cvar) => {
+            let mut bits = Vec::with_capacity(num_bits);
+
+            for ii in 0..num_bits {
+                let cvar = cvar.clone();
+                let bit = compiler.backend.new_internal_var(
+                    Value::Hint(Arc::new(
+                        move |backend: &B, env: &mut WitnessEnv<B::Field>| {
+                            let value: BigUint = backend.compute_var(env, &cvar)?.into();
+                            if value.bit(ii as u64) {
+                                Ok(B::Field::one())
+                            } else {
+                                Ok(B::Field::zero())
+                            }
+                        },
+                    )),
+                    span,
+                );
+
+                let bit = ConstOrCell::Cell(bit);
+                boolean::check(compiler, &bit, span);
+                bits.push(bit);
+            }
+
+            // the bits must recompose into the original value
+            match from_bits(compiler, &bits, span) {
+                ConstOrCell::Const(cst) => {
+                    compiler.assert_eq_cell(cvar, &ConstOrCell::Const(cst), span)
+                }
+                ConstOrCell::Cell(recomposed) => {
+                    compiler.assert_eq_cell(&recomposed, &ConstOrCell::Cell(cvar.clone()), span)
+                }
+            }
+
+            Ok(bits)
+        }
+    }
+}
+
+/// Recomposes a field element from its bits (little-endian).
+/// Note that this does not check that the bits are booleans.
+pub fn from_bits<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    bits: &[ConstOrCell<B::Field, B::Var>],
+    span: Span,
+) -> ConstOrCell<B::Field, B::Var> {
+    let mut acc = ConstOrCell::Const(B::Field::zero());
+    let mut power = B::Field::one();
+
+    for bit in bits {
+        let term = if power.is_one() {
+            bit.clone()
+        } else {
+            mul(compiler, bit, &ConstOrCell::Const(power), span)[0].clone()
+        };
+        acc = add(compiler, &acc, &term, span)[0].clone();
+        power = power + power;
+    }
+
+    acc
+}
+
+/// Asserts that a field element is strictly smaller than a constant bound.
+pub fn assert_lt_const<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    var: &ConstOrCell<B::Field, B::Var>,
+    bound: &BigUint,
+    span: Span,
+) -> Result<()> {
+    // nothing is smaller than zero
+    if bound.is_zero() {
+        return Err(compiler.error(ErrorKind::AssertionFailed, span));
+    }
+
+    // the smallest number of bits such that `bound <= 2^num_bits`
+    let num_bits = (bound - 1u32).bits() as usize;
+
+    // var < 2^num_bits
+    // (this errors if the bound is too close to the modulus)
+    to_bits(compiler, var, num_bits, span)?;
+
+    // if the bound is not a power of two, we also need var + (2^num_bits - bound) < 2^num_bits
+    let offset = (BigUint::one() << num_bits) - bound;
+    if !offset.is_zero() {
+        let offset = B::Field::try_from(offset).map_err(|_| {
+            compiler.error(
+                ErrorKind::UnexpectedError("bound offset doesn't fit in the field"),
+                span,
+            )
+        })?;
+        let shifted = add(compiler, var, &ConstOrCell::Const(offset), span);
+        to_bits(compiler, &shifted[0], num_bits, span)?;
+    }
+
+    Ok(())
+}
+
+/// Computes the remainder of the division of `lhs` by a constant modulus,
+/// by constraining a quotient `q` and a remainder `r` such that `lhs = q * modulus + r` and `r < modulus`.
+/// `lhs_bits` is the number of bits `lhs` is known to fit in (like the size of an integer type),
+/// otherwise `lhs` must fit in 3 bits less than the modulus of the field for the witness to be valid.
+pub fn modulo<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    lhs: &ConstOrCell<B::Field, B::Var>,
+    modulus: B::Field,
+    lhs_bits: Option<usize>,
+    span: Span,
+) -> Result<Var<B::Field, B::Var>> {
+    if modulus.is_zero() {
+        return Err(compiler.error(ErrorKind::ZeroModulus, span));
+    }
+    let modulus_big: BigUint = modulus.into();
+
+    let lhs_var = match lhs {
+        ConstOrCell::Const(cst) => {
+            let lhs_big: BigUint = (*cst).into();
+            return Ok(Var::new_constant(
+                B::Field::from(lhs_big % modulus_big),
+                span,
+            ));
+        }
+        ConstOrCell::Cell(var) => var,
+    };
+
+    // with `lhs < 2^num_bits`, the quotient fits in `num_bits + 1 - bits(modulus)` bits,
+    // so `q * modulus + r < 2^(num_bits + 1) + modulus` can't wrap around the field
+    let max_value: BigUint = (B::Field::zero() - B::Field::one()).into();
+    let num_bits = lhs_bits.unwrap_or(max_value.bits() as usize - 3);
+    let quotient_bits = (num_bits + 1).saturating_sub(modulus_big.bits() as usize);
+
+    let hint = |compute: fn(BigUint, &BigUint) -> BigUint| {
+        let lhs_var = lhs_var.clone();
+        let modulus_big = modulus_big.clone();
+        Value::Hint(Arc::new(
+            move |backend: &B, env: &mut WitnessEnv<B::Field>| {
+                let value: BigUint = backend.compute_var(env, &lhs_var)?.into();
+                Ok(B::Field::from(compute(value, &modulus_big)))
+            },
+        ))
+    };
+    let quotient = compiler
+        .backend
+        .new_internal_var(hint(|value, modulus| value / modulus), span);
+    let remainder = compiler
+        .backend
+        .new_internal_var(hint(|value, modulus| value % modulus), span);
+    let quotient = ConstOrCell::Cell(quotient);
+    let remainder = ConstOrCell::Cell(remainder);
+
+    // 0 <= q < 2^quotient_bits and 0 <= r < modulus
+    to_bits(compiler, &quotient, quotient_bits, span)?;
+    assert_lt_const(compiler, &remainder, &modulus_big, span)?;
+
+    // lhs = q * modulus + r
+    let scaled = mul(compiler, &quotient, &ConstOrCell::Const(modulus), span);
+    let recomposed = add(compiler, &scaled[0], &remainder, span);
+    compiler.assert_eq_cell(lhs_var, &recomposed[0], span);
+
+    Ok(Var::new_cvar(remainder, span))
+}
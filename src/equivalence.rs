@@ -0,0 +1,203 @@
+//! Checks that two versions of a circuit compute the same relation, for example before and after a refactoring.
+//!
+//! [check_equivalence] generates the witness of both circuits (compiled with the [Mock] backend)
+//! on the same random inputs, and reports the first inputs on which they diverge:
+//! either both circuits accept the inputs but return different public outputs,
+//! or only one of them accepts the inputs.
+//! The inputs are only sampled, so finding no divergence is not a proof that the circuits are equivalent.
+//!
+//! ```
+//! use noname::{
+//!     backends::mock::Mock,
+//!     compiler::{compile, typecheck_next_file, Sources},
+//!     equivalence::check_equivalence,
+//!     type_checker::TypeChecker,
+//! };
+//!
+//! let compile_code = |code: &str| {
+//!     let mut sources = Sources::new();
+//!     let mut tast = TypeChecker::<Mock>::new();
+//!     typecheck_next_file(&mut tast, None, &mut sources, "main.no".to_string(), code.to_string(), 0)
+//!         .unwrap();
+//!     compile(&sources, tast, Mock::new()).unwrap()
+//! };
+//!
+//! let old = compile_code("fn main(pub xx: Field) -> Field { return xx + xx; }");
+//! let new = compile_code("fn main(pub xx: Field) -> Field { return xx * 2; }");
+//! assert!(check_equivalence(&old, &new, 10, 0).unwrap().is_none());
+//! ```
+
+use ark_ff::PrimeField as _;
+use num_bigint::BigUint;
+use serde_json::{Map, Value};
+
+use crate::{
+    backends::{kimchi::VestaField, mock::Mock},
+    inputs::JsonInputs,
+    parser::types::{FnSig, TyKind},
+    type_checker::FullyQualified,
+    witness::CompiledCircuit,
+};
+
+/// Inputs on which two circuits diverge (see [check_equivalence]).
+#[derive(Debug)]
+pub struct Divergence {
+    /// The public inputs, in the JSON format of [crate::inputs::parse_inputs].
+    pub public_inputs: Value,
+
+    /// The private inputs, in the JSON format of [crate::inputs::parse_inputs].
+    pub private_inputs: Value,
+
+    /// The public outputs of the first circuit, or `None` if it rejects the inputs.
+    pub lhs_outputs: Option<Vec<VestaField>>,
+
+    /// The public outputs of the second circuit, or `None` if it rejects the inputs.
+    pub rhs_outputs: Option<Vec<VestaField>>,
+}
+
+/// Generates the witness of both circuits on `samples` random inputs, derived from `seed`,
+/// and returns the first inputs on which they diverge (see the [module documentation](self)).
+/// The main functions of both circuits must take the same arguments (names, types and visibility)
+/// and return the same type.
+pub fn check_equivalence(
+    lhs: &CompiledCircuit<Mock>,
+    rhs: &CompiledCircuit<Mock>,
+    samples: usize,
+    seed: u64,
+) -> miette::Result<Option<Divergence>> {
+    let sig = lhs.main_info().sig();
+    if !same_interface(sig, rhs.main_info().sig()) {
+        miette::bail!(
+            "the main functions of the two circuits don't have the same arguments and return type"
+        );
+    }
+
+    let mut rng = Rng(seed);
+    for _ in 0..samples {
+        let mut public_inputs = Map::new();
+        let mut private_inputs = Map::new();
+        for arg in &sig.arguments {
+            let value = random_value(lhs, &mut rng, &arg.typ.kind);
+            if arg.is_public() {
+                public_inputs.insert(arg.name.value.clone(), value);
+            } else {
+                private_inputs.insert(arg.name.value.clone(), value);
+            }
+        }
+
+        let lhs_outputs = public_outputs(lhs, &public_inputs, &private_inputs);
+        let rhs_outputs = public_outputs(rhs, &public_inputs, &private_inputs);
+        if lhs_outputs != rhs_outputs {
+            return Ok(Some(Divergence {
+                public_inputs: Value::Object(public_inputs),
+                private_inputs: Value::Object(private_inputs),
+                lhs_outputs,
+                rhs_outputs,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+fn same_interface(lhs: &FnSig, rhs: &FnSig) -> bool {
+    let same_arguments = lhs.arguments.len() == rhs.arguments.len()
+        && lhs.arguments.iter().zip(&rhs.arguments).all(|(lhs, rhs)| {
+            lhs.name.value == rhs.name.value
+                && lhs.is_public() == rhs.is_public()
+                && lhs.typ.kind == rhs.typ.kind
+        });
+
+    let return_type = |sig: &FnSig| sig.return_type.as_ref().map(|typ| typ.kind.clone());
+    same_arguments && return_type(lhs) == return_type(rhs)
+}
+
+/// Returns the public outputs of the circuit on the given inputs,
+/// or `None` if the witness can't be generated or doesn't satisfy all the gates.
+fn public_outputs(
+    circuit: &CompiledCircuit<Mock>,
+    public_inputs: &Map<String, Value>,
+    private_inputs: &Map<String, Value>,
+) -> Option<Vec<VestaField>> {
+    let to_inputs = |inputs: &Map<String, Value>| JsonInputs(inputs.clone().into_iter().collect());
+
+    let witness = circuit
+        .generate_witness(to_inputs(public_inputs), to_inputs(private_inputs))
+        .ok()?;
+    witness.check().ok()?;
+
+    Some(witness.public_outputs)
+}
+
+/// Returns a random value of the given type, in the JSON format of the inputs.
+/// Half of the numbers are small, as circuits often only accept (or treat differently) small values.
+fn random_value(circuit: &CompiledCircuit<Mock>, rng: &mut Rng, typ: &TyKind) -> Value {
+    match typ {
+        TyKind::Field | TyKind::BigInt => {
+            let value = if rng.next_bool() {
+                VestaField::from(rng.next_u64() % 16)
+            } else {
+                let bytes: Vec<u8> = (0..4).flat_map(|_| rng.next_u64().to_le_bytes()).collect();
+                VestaField::from_le_bytes_mod_order(&bytes)
+            };
+            let value: BigUint = value.into();
+            Value::String(value.to_string())
+        }
+        TyKind::U32 | TyKind::U64 => {
+            let value = match (rng.next_bool(), typ) {
+                (true, _) => rng.next_u64() % 16,
+                (false, TyKind::U32) => rng.next_u64() % (1 << 32),
+                (false, _) => rng.next_u64(),
+            };
+            Value::String(value.to_string())
+        }
+        TyKind::Bool => Value::Bool(rng.next_bool()),
+        TyKind::Array(el_typ, size) => Value::Array(
+            (0..*size)
+                .map(|_| random_value(circuit, rng, el_typ))
+                .collect(),
+        ),
+        TyKind::Tuple(typs) => Value::Array(
+            typs.iter()
+                .map(|typ| random_value(circuit, rng, typ))
+                .collect(),
+        ),
+        TyKind::Custom { module, name } => {
+            let qualified = FullyQualified::new(module, name);
+            let struct_info = circuit
+                .circuit
+                .struct_info(&qualified)
+                .expect("compiler bug: couldn't find struct given as input");
+
+            Value::Object(
+                struct_info
+                    .fields
+                    .iter()
+                    .map(|(field_name, field_ty)| {
+                        (field_name.clone(), random_value(circuit, rng, field_ty))
+                    })
+                    .collect(),
+            )
+        }
+        TyKind::GenericArray(_, _) => {
+            unreachable!("the main function can't be generic")
+        }
+    }
+}
+
+/// A small pseudo-random number generator (splitmix64), so that the samples only depend on the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_bool(&mut self) -> bool {
+        self.next_u64() % 2 == 0
+    }
+}
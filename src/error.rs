@@ -2,6 +2,7 @@ use miette::Diagnostic;
 use thiserror::Error;
 
 use crate::{
+    circuit_writer::GateKind,
     constants::Span,
     inputs::ParsingError,
     lexer::TokenKind,
@@ -77,9 +78,27 @@ pub enum ErrorKind {
     #[error("{0} arguments are passed when {1} were expected")]
     MismatchFunctionArguments(usize, usize),
 
+    #[error("the first argument of `fold` must be an array, but it has type `{0}`")]
+    FoldOnNonArray(TyKind),
+
+    #[error("the last argument of `fold` must be the name of a function")]
+    FoldFunctionNotNamed,
+
+    #[error("the function passed to `fold` must have the signature `(acc, elem) -> acc`, where the accumulator has type `{0}` and the elements have type `{1}`")]
+    FoldSignatureMismatch(TyKind, TyKind),
+
+    #[error("the last argument of `merkle_verify_with` must be the name of a function with the signature `(Field, Field) -> Field`")]
+    InvalidMerkleHash,
+
+    #[error("the values passed to `select` must have the same type, but they have types `{0}` and `{1}`")]
+    SelectTypeMismatch(TyKind, TyKind),
+
     #[error("constants must be declared before any structs or functions")]
     OrderOfConstDeclaration,
 
+    #[error("the value of the constant `{0}` depends on itself")]
+    CyclicConstant(String),
+
     #[error(
         "the `use` keyword must be used before anything else (consts, structs, functions, etc.)"
     )]
@@ -91,12 +110,27 @@ pub enum ErrorKind {
     #[error("the `pub` keyword is reserved for arguments of the main function")]
     PubArgumentOutsideMain,
 
+    #[error("unknown annotation `#[{0}]` (the supported annotations are `#[unchecked]` on arguments and `#[test(...)]` on functions)")]
+    UnknownAnnotation(String),
+
+    #[error("invalid test function: {0}")]
+    InvalidTestFunction(&'static str),
+
+    #[error("the test function returned {observed} instead of {expected}")]
+    UnexpectedTestOutput { expected: String, observed: String },
+
+    #[error("`#[unchecked]` can only be used on public inputs of the main function: it skips the constraints checking that an input is well-formed (for example, that a boolean is 0 or 1), which is only sound if the verifier checks the value itself")]
+    UncheckedNotPublicInput,
+
     #[error("the function main is not recursive")]
     RecursiveMain,
 
     #[error("invalid token")]
     InvalidToken,
 
+    #[error("this string literal is missing its closing quote")]
+    UnterminatedStringLiteral,
+
     #[error("missing type")]
     MissingType,
 
@@ -130,12 +164,45 @@ pub enum ErrorKind {
     #[error("invalid array size, expected [_; x] with x in [0,2^32]")]
     InvalidArraySize,
 
+    #[error("the array size `{0}` is not a generic parameter of the function (e.g. `fn sum[{0}](arr: [Field; {0}])`)")]
+    UndefinedGeneric(String),
+
+    #[error("the generic parameter `{0}` must be used as an array size in the type of an argument, or be a constant argument, so that it can be inferred")]
+    UninferableGeneric(String),
+
+    #[error("the argument `{0}` must be known at compile time, as it is the size of an array")]
+    GenericNotConstant(String),
+
+    #[error("the generic parameter `{0}` is inferred to be both {1} and {2}")]
+    ConflictingGeneric(String, u32, u32),
+
     #[error("the value passed could not be converted to a field element")]
     InvalidField(String),
 
     #[error("invalid range size, expected x..y with x and y integers in [0,2^32]")]
     InvalidRangeSize,
 
+    #[error("the range bound `{0}` is not a constant field element in [0,2^32]")]
+    InvalidRangeBound(String),
+
+    #[error("the range {0}..{1} ends before it starts")]
+    DecreasingRange(u32, u32),
+
+    #[error("unrolling this loop would exceed the maximum number of loop iterations in the circuit ({0})")]
+    LoopBudgetExceeded(usize),
+
+    #[error("computing this constant would run more than {0} loop iterations")]
+    ConstLoopBudgetExceeded(usize),
+
+    #[error("a `while` loop must be bounded with a `#[max_iters(N)]` attribute")]
+    MissingMaxIters,
+
+    #[error("the condition of a `while` loop must be a boolean, but it has type `{0}`")]
+    WhileConditionNotBool(TyKind),
+
+    #[error("the `while` loop did not terminate within {0} iterations")]
+    WhileLoopNotTerminated(u32),
+
     #[error("invalid statement")]
     InvalidStatement,
 
@@ -168,6 +235,12 @@ pub enum ErrorKind {
     #[error("type '{0}' and '{1}' are not compatible")]
     MismatchType(TyKind, TyKind),
 
+    #[error("the condition of an `if` expression must be a boolean, but it has type `{0}`")]
+    IfConditionNotBool(TyKind),
+
+    #[error("the `if` branch has type `{0}` but the `else` branch has type `{1}`")]
+    IfElseTypeMismatch(TyKind, TyKind),
+
     #[error("variable used is not defined anywhere")]
     UndefinedVariable,
 
@@ -183,7 +256,7 @@ pub enum ErrorKind {
     #[error("you need to pass the following private argument: `{0}`")]
     MissingPrivateArg(String),
 
-    #[error("cannot convert `{0}` to field element")]
+    #[error("cannot convert `{0}` to field element, as it is not smaller than the field modulus")]
     CannotConvertToField(String),
 
     #[error("a return value was expected by the function signature")]
@@ -192,6 +265,9 @@ pub enum ErrorKind {
     #[error("no return value was expected as part of this function signature")]
     NoReturnExpected,
 
+    #[error("a `return` must be the last statement of the body of an `if` statement")]
+    StatementAfterReturn,
+
     #[error("the `self` argument cannot have attributes")]
     SelfHasAttribute,
 
@@ -207,6 +283,12 @@ pub enum ErrorKind {
     #[error("tried to import the same module `{0}` twice")]
     DuplicateModule(String),
 
+    #[error("tried to import a function named `{0}` twice")]
+    DuplicateImport(String),
+
+    #[error("function `{0}` is both imported and defined in this module")]
+    ImportedFnCollision(String),
+
     #[error("`{0}` is a reserved argument name")]
     PublicOutputReserved(String),
 
@@ -225,6 +307,12 @@ pub enum ErrorKind {
     #[error("array accessed at index {0} is out of bounds (max allowed index is {1})")]
     ArrayIndexOutOfBounds(usize, usize),
 
+    #[error("the slice {0}..{1} is reversed (its start must not be greater than its end)")]
+    ReversedSlice(u32, u32),
+
+    #[error("the slice {0}..{1} is out of bounds of an array of length {2}")]
+    SliceOutOfBounds(u32, u32, u32),
+
     #[error(
         "one-letter variables or types are not allowed. Best practice is to use descriptive names"
     )]
@@ -233,6 +321,9 @@ pub enum ErrorKind {
     #[error("array indexes must be constants in circuits")]
     ExpectedConstant,
 
+    #[error("an array element selected by an index known at runtime can only be assigned directly (like `arr[idx] = value`), and not through another access")]
+    IndirectRuntimeIndexAssignment,
+
     #[error("kimchi setup: {0}")]
     KimchiSetup(#[from] kimchi::error::SetupError),
 
@@ -245,15 +336,36 @@ pub enum ErrorKind {
     #[error("the program did not run to completion with the given private and/or public inputs (row {0} of the witness failed to verify)")]
     InvalidWitness(usize),
 
+    #[error("the constraint created here is not satisfied by the given inputs (row {row} of the witness, {gate:?} gate)")]
+    UnsatisfiedConstraint { row: usize, gate: GateKind },
+
+    #[error("assertion failed: {message} (row {row} of the witness)")]
+    UnsatisfiedAssertion { row: usize, message: String },
+
+    #[error("the constraints of the {gate:?} gate created here (row {row}) are not polynomials of its cells, so the constraints of the circuit can't be returned")]
+    UnsupportedGateConstraints { row: usize, gate: GateKind },
+
     #[error("user provided input `{0}` is not defined in the main function's arguments")]
     UnusedInput(String),
 
-    #[error("private input not used in the circuit")]
-    PrivateInputNotUsed,
+    #[error("private input not used in the circuit: `{}`", .0.join("`, `"))]
+    PrivateInputNotUsed(Vec<String>),
+
+    #[error("private input not used in the circuit: `{}` (the uses in a `debug_assert` don't count, as it is stripped at optimization level 2 and above)", .0.join("`, `"))]
+    PrivateInputOnlyUsedInDebugAssert(Vec<String>),
+
+    #[error("internal error: the variable {index} (a `{value}`) created here does not end up in the circuit, this is a bug in the circuit writer")]
+    UnconstrainedVar { index: usize, value: String },
 
     #[error("the variable `{0}` is declared twice")]
     DuplicateDefinition(String),
 
+    #[error("the type `{0}` is defined twice")]
+    DuplicateType(String),
+
+    #[error("the type alias `{0}` refers to itself")]
+    RecursiveTypeAlias(String),
+
     #[error("only variables and arrays can be mutated")]
     InvalidAssignmentExpression,
 
@@ -278,6 +390,9 @@ pub enum ErrorKind {
     #[error("method call can only be applied on custom structs")]
     MethodCallOnNonCustomStruct,
 
+    #[error("the struct `{0}` has no method `{1}(self, rhs)`, which is required to use this operator on it")]
+    MissingOperatorMethod(String, &'static str),
+
     #[error("array access can only be performed on arrays")]
     ArrayAccessOnNonArray,
 
@@ -287,12 +402,122 @@ pub enum ErrorKind {
     #[error("struct `{0}` does not have a field called `{1}`")]
     UndefinedField(String, String),
 
+    #[error("struct `{0}` has no method `{1}`, and its module has no function `{1}` taking it as first argument")]
+    UndefinedMethod(String, String),
+
     #[error("this assertion failed")]
     AssertionFailed,
 
-    #[error("constants can only have a literal decimal value")]
+    #[error("division by zero")]
+    DivisionByZero,
+
+    #[error("the modulus of `%` must be known at compile time")]
+    NonConstantModulus,
+
+    #[error("the modulus of `%` can't be zero")]
+    ZeroModulus,
+
+    #[error("this value has no square root in the field")]
+    NotASquare,
+
+    #[error("constants can only be a field element or an array of field elements")]
     InvalidConstType,
 
+    #[error("cannot override the constant `{0}`, as it is not defined in the main module")]
+    UndefinedConstantOverride(String),
+
+    #[error("cannot override the constant `{0}`, as its value was already used during type checking (by another constant, a `const fn`, or the bounds of a slice)")]
+    ConstantUsedAtCompileTime(String),
+
+    #[error("this cannot be evaluated at compile time: {0}")]
+    NotConstEvaluable(&'static str),
+
+    #[error("string literals can only be used as the message of an assertion (e.g. `assert(cond, \"message\")`)")]
+    UnexpectedStringLiteral,
+
+    #[error("a string constant can't be empty")]
+    EmptyStringLiteral,
+
     #[error("cannot compile a module without a main function")]
     NoMainFunction,
+
+    #[error("the argument `{0}` must be a constant")]
+    ExpectedConstantArgument(&'static str),
+
+    #[error("cannot decompose a value in {0} bits (the number of bits must be strictly smaller than the bit size of the field)")]
+    InvalidBitSize(usize),
+
+    #[error("the value does not fit in {0} bits")]
+    ValueTooLarge(usize),
+
+    #[error("integer overflow: the value doesn't fit in a `{0}`")]
+    IntegerOverflow(TyKind),
+
+    #[error("the value is not a boolean (it must be 0 or 1)")]
+    NotABoolean,
+
+    #[error("cannot destructure {0} into {1} variables")]
+    MismatchTupleDestructuring(TyKind, usize),
+
+    #[error("this backend does not support lookup tables")]
+    LookupsNotSupported,
+
+    #[error("{0} is not supported yet")]
+    Unsupported(&'static str),
+
+    #[error("lookup table {0} was never added to the backend")]
+    UnknownLookupTable(u32),
+
+    #[error("the key {1} is not in lookup table {0}")]
+    LookupKeyNotFound(u32, String),
+
+    #[error("lookups cannot be used inside a branch that depends on a runtime condition")]
+    LookupInBranch,
+
+    #[error("this backend does not support range-check gates")]
+    RangeChecksNotSupported,
+
+    #[error("range checks cannot be used inside a branch that depends on a runtime condition")]
+    RangeCheckInBranch,
+
+    #[error("range checks use kimchi's lookup table {0}, which conflicts with a table added to the backend")]
+    ReservedLookupTable(u32),
+}
+
+/// A warning in noname: something that is likely a mistake,
+/// but that doesn't prevent the circuit from being compiled.
+#[derive(Diagnostic, Debug, Error, Clone)]
+#[error("Looks like something might be wrong in {label}")]
+#[diagnostic(severity(Warning))]
+pub struct Warning {
+    /// A hint as to where the warning was emitted (e.g. constraint-finalization).
+    pub label: &'static str,
+
+    /// The type of warning.
+    #[help]
+    pub kind: WarningKind,
+
+    /// Indicate where the problem is in the source code.
+    #[label("here")]
+    pub span: Span,
+}
+
+impl Warning {
+    /// Creates a new [Warning] from a [WarningKind].
+    pub fn new(label: &'static str, kind: WarningKind, span: Span) -> Self {
+        Self { label, kind, span }
+    }
+}
+
+/// The type of warning.
+#[derive(Error, Diagnostic, Debug, Clone)]
+pub enum WarningKind {
+    #[error("the public input `{0}` is not used in any constraint, so the prover can set it to any value")]
+    UnusedPublicInput(String),
+
+    #[error("this `debug_assert` is not part of the circuit, as it is stripped at optimization level 2 and above (so a private input only used here is reported as unused)")]
+    DebugAssertStripped,
+
+    #[error("this constraint is satisfied by any values, so it wastes a gate without checking anything (is it asserting what was meant?)")]
+    TriviallySatisfiedConstraint,
 }
@@ -1,14 +1,15 @@
 //! Used to parse public and private inputs to a program.
 
-use std::{collections::HashMap, str::FromStr};
+use std::collections::HashMap;
 
 use ark_ff::{One, PrimeField, Zero};
 use miette::Diagnostic;
 use num_bigint::BigUint;
+use num_traits::Num as _;
 use thiserror::Error;
 
 use crate::{
-    backends::{kimchi::VestaField, Backend},
+    backends::{kimchi::VestaField, Backend, BackendField},
     parser::types::TyKind,
     type_checker::FullyQualified,
     witness::CompiledCircuit,
@@ -29,8 +30,71 @@ pub enum ParsingError {
     #[error("couldn't convert given field element `{0}`")]
     InvalidField(String),
 
+    #[error("the field element `{value}` given for `{key}` is larger than the field modulus")]
+    FieldOverflow { key: String, value: String },
+
+    #[error("the integer `{value}` given for `{key}` doesn't fit in a {typ}")]
+    IntegerOverflow {
+        key: String,
+        typ: TyKind,
+        value: String,
+    },
+
     #[error("mismatch between expected argument format ({0}), and given argument in JSON (`{1}`)")]
     MismatchJsonArgument(TyKind, serde_json::Value),
+
+    #[error("the field `{key}` is missing from the JSON input")]
+    MissingStructField { key: String },
+
+    #[error("the JSON input has a field `{key}` that is not in the struct")]
+    UnknownStructField { key: String },
+
+    #[error("the value `{value}` given for `{key}` is out of the bounds of the input schema (it must {bound})")]
+    InputOutOfBounds {
+        key: String,
+        value: String,
+        bound: String,
+    },
+
+    #[error("the public input `{0}` is missing")]
+    MissingPublicInput(String),
+
+    #[error("`{0}` is not a public input of the circuit")]
+    UnknownPublicInput(String),
+
+    #[error("the expected public output must be given, as the circuit returns a value")]
+    MissingPublicOutput,
+
+    #[error("expected {expected} field elements for an output of type {typ}, but got {observed}")]
+    MismatchOutputSize {
+        typ: TyKind,
+        expected: usize,
+        observed: usize,
+    },
+}
+
+impl ParsingError {
+    /// Prepends the path of the parent input (e.g. `points` or `[1]`) to the key of the error, if it has one.
+    pub(crate) fn with_key_prefix(self, prefix: &str) -> Self {
+        match self {
+            ParsingError::FieldOverflow { key, value } => ParsingError::FieldOverflow {
+                key: format!("{prefix}{key}"),
+                value,
+            },
+            ParsingError::IntegerOverflow { key, typ, value } => ParsingError::IntegerOverflow {
+                key: format!("{prefix}{key}"),
+                typ,
+                value,
+            },
+            ParsingError::MissingStructField { key } => ParsingError::MissingStructField {
+                key: format!("{prefix}{key}"),
+            },
+            ParsingError::UnknownStructField { key } => ParsingError::UnknownStructField {
+                key: format!("{prefix}{key}"),
+            },
+            err => err,
+        }
+    }
 }
 
 //
@@ -38,7 +102,7 @@ pub enum ParsingError {
 // (arguments to more stuff)
 //
 
-/// An input is a name, and a list of field elements (in decimal).
+/// An input is a name, and a list of field elements (in decimal, or in hexadecimal if prefixed with `0x`).
 #[derive(Default, serde::Deserialize, Clone)]
 pub struct JsonInputs(pub HashMap<String, serde_json::Value>);
 
@@ -47,6 +111,111 @@ pub fn parse_inputs(s: &str) -> Result<JsonInputs, ParsingError> {
     Ok(json_inputs)
 }
 
+/// Same as [parse_inputs], but rejects the inputs that are out of the bounds of the schema.
+pub fn parse_inputs_with_schema(s: &str, schema: &InputSchema) -> Result<JsonInputs, ParsingError> {
+    let json_inputs = parse_inputs(s)?;
+    schema.check(&json_inputs)?;
+    Ok(json_inputs)
+}
+
+//
+// Bounds of the inputs
+//
+
+/// Bounds on the values of named inputs, which are checked when parsing them (see [parse_inputs_with_schema]),
+/// so that an out-of-range value is rejected with its name before generating the witness,
+/// instead of failing a constraint.
+/// The schema maps input names to their bounds, for example
+/// `{"age": {"bits": 8}, "levels": {"min": "1", "max": "10"}}`.
+/// The bounds of an input apply to each of its field elements (e.g. each element of an array).
+#[derive(Default, serde::Deserialize, Clone, Debug)]
+pub struct InputSchema(pub HashMap<String, InputBounds>);
+
+/// The bounds of an input (see [InputSchema]).
+/// The values are compared as integers, given in decimal, or in hexadecimal if prefixed with `0x`.
+#[derive(Default, serde::Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct InputBounds {
+    /// The smallest value allowed.
+    pub min: Option<String>,
+
+    /// The largest value allowed.
+    pub max: Option<String>,
+
+    /// The number of bits that the values must fit in.
+    pub bits: Option<u64>,
+}
+
+pub fn parse_input_schema(s: &str) -> Result<InputSchema, ParsingError> {
+    let schema: InputSchema = serde_json::from_str(s)?;
+    Ok(schema)
+}
+
+impl InputSchema {
+    /// Checks that the inputs named in the schema are within their bounds.
+    /// The inputs that are not in the schema, and the values that are not numbers, are not checked.
+    pub fn check(&self, inputs: &JsonInputs) -> Result<(), ParsingError> {
+        let mut names: Vec<_> = self.0.keys().collect();
+        names.sort();
+
+        for name in names {
+            if let Some(value) = inputs.0.get(name) {
+                self.0[name].check(name, value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl InputBounds {
+    fn check(&self, key: &str, value: &serde_json::Value) -> Result<(), ParsingError> {
+        use serde_json::Value;
+
+        match value {
+            Value::String(ss) => {
+                let out_of_bounds = |bound: String| ParsingError::InputOutOfBounds {
+                    key: key.to_string(),
+                    value: ss.clone(),
+                    bound,
+                };
+
+                let int = parse_biguint(ss)?;
+                if let Some(min) = &self.min {
+                    if int < parse_biguint(min)? {
+                        return Err(out_of_bounds(format!("be at least {min}")));
+                    }
+                }
+                if let Some(max) = &self.max {
+                    if int > parse_biguint(max)? {
+                        return Err(out_of_bounds(format!("be at most {max}")));
+                    }
+                }
+                if let Some(bits) = self.bits {
+                    if int.bits() > bits {
+                        return Err(out_of_bounds(format!("fit in {bits} bits")));
+                    }
+                }
+
+                Ok(())
+            }
+            Value::Array(values) => {
+                for (idx, value) in values.iter().enumerate() {
+                    self.check(&format!("{key}[{idx}]"), value)?;
+                }
+                Ok(())
+            }
+            Value::Object(map) => {
+                for (field_name, value) in map {
+                    self.check(&format!("{key}.{field_name}"), value)?;
+                }
+                Ok(())
+            }
+            Value::Null | Value::Bool(_) | Value::Number(_) => Ok(()),
+        }
+    }
+}
+
 //
 // JSON deserialization of a single input
 //
@@ -62,8 +231,20 @@ impl<B: Backend> CompiledCircuit<B> {
         match (expected_input, input) {
             (TyKind::BigInt, _) => unreachable!(),
             (TyKind::Field, Value::String(ss)) => {
-                let cell_value =
-                    B::Field::from_str(&ss).map_err(|_| ParsingError::InvalidField(ss))?;
+                let cell_value = parse_field::<B::Field>(&ss)?;
+                Ok(vec![cell_value])
+            }
+            (TyKind::U32 | TyKind::U64, Value::String(ss)) => {
+                let cell_value = parse_field::<B::Field>(&ss)?;
+                let num_bits = expected_input.num_bits().unwrap();
+                let value: BigUint = cell_value.into();
+                if value.bits() as usize > num_bits {
+                    return Err(ParsingError::IntegerOverflow {
+                        key: "".to_string(),
+                        typ: expected_input.clone(),
+                        value: ss,
+                    });
+                }
                 Ok(vec![cell_value])
             }
             (TyKind::Bool, Value::Bool(bb)) => {
@@ -77,11 +258,35 @@ impl<B: Backend> CompiledCircuit<B> {
 
             (TyKind::Array(el_typ, size), Value::Array(values)) => {
                 if values.len() != (*size as usize) {
-                    panic!("wrong size of array");
+                    return Err(ParsingError::MismatchJsonArgument(
+                        expected_input.clone(),
+                        Value::Array(values),
+                    ));
                 }
                 let mut res = vec![];
-                for value in values {
-                    let el = self.parse_single_input(value, el_typ)?;
+                for (idx, value) in values.into_iter().enumerate() {
+                    let el = self
+                        .parse_single_input(value, el_typ)
+                        .map_err(|e| e.with_key_prefix(&format!("[{idx}]")))?;
+                    res.extend(el);
+                }
+
+                Ok(res)
+            }
+            (TyKind::Tuple(typs), Value::Array(values)) => {
+                if values.len() != typs.len() {
+                    return Err(ParsingError::MismatchJsonArgument(
+                        expected_input.clone(),
+                        Value::Array(values),
+                    ));
+                }
+
+                // members are laid out in declaration order
+                let mut res = vec![];
+                for (idx, (value, typ)) in values.into_iter().zip(typs).enumerate() {
+                    let el = self
+                        .parse_single_input(value, typ)
+                        .map_err(|e| e.with_key_prefix(&format!("[{idx}]")))?;
                     res.extend(el);
                 }
 
@@ -102,24 +307,27 @@ impl<B: Backend> CompiledCircuit<B> {
                     .expect("compiler bug: couldn't find struct given as input");
                 let fields = &struct_info.fields;
 
-                // make sure that they're the same length
-                if fields.len() != map.len() {
-                    panic!("wrong number of fields in struct (TODO: better error)");
-                }
-
-                // parse each field
+                // parse each field, in declaration order
                 let mut res = vec![];
                 for (field_name, field_ty) in fields {
-                    let value = map
-                    .remove(field_name)
-                    .ok_or_else(|| {
-                        format!("couldn't find field `{field_name}` in given JSON input (TODO: better error)")
-                    })
-                    .unwrap();
-                    let parsed = self.parse_single_input(value, field_ty)?;
+                    let value =
+                        map.remove(field_name)
+                            .ok_or_else(|| ParsingError::MissingStructField {
+                                key: format!(".{field_name}"),
+                            })?;
+                    let parsed = self
+                        .parse_single_input(value, field_ty)
+                        .map_err(|e| e.with_key_prefix(&format!(".{field_name}")))?;
                     res.extend(parsed);
                 }
 
+                // any field left is not part of the struct
+                if let Some(unknown) = map.keys().min() {
+                    return Err(ParsingError::UnknownStructField {
+                        key: format!(".{unknown}"),
+                    });
+                }
+
                 Ok(res)
             }
             (expected, observed) => {
@@ -132,10 +340,166 @@ impl<B: Backend> CompiledCircuit<B> {
     }
 }
 
+impl<B: Backend> CompiledCircuit<B> {
+    /// Encodes the public inputs, and the expected public output (if `main` returns a value),
+    /// as the full public input that proofs are verified against:
+    /// the public output followed by the public inputs, in the order of the arguments of `main`.
+    pub fn encode_public_values(
+        &self,
+        mut public_inputs: JsonInputs,
+        public_output: Option<serde_json::Value>,
+    ) -> Result<Vec<B::Field>, ParsingError> {
+        let main_sig = self.main_info().sig();
+
+        let mut res = match (&main_sig.return_type, public_output) {
+            (Some(typ), Some(output)) => self.parse_single_input(output, &typ.kind)?,
+            (Some(_), None) => return Err(ParsingError::MissingPublicOutput),
+            (None, Some(output)) => {
+                return Err(ParsingError::MismatchJsonArgument(
+                    TyKind::Tuple(vec![]),
+                    output,
+                ))
+            }
+            (None, None) => vec![],
+        };
+
+        for arg in main_sig.arguments.iter().filter(|arg| arg.is_public()) {
+            let name = &arg.name.value;
+            let input = public_inputs
+                .0
+                .remove(name)
+                .ok_or_else(|| ParsingError::MissingPublicInput(name.clone()))?;
+            let fields = self
+                .parse_single_input(input, &arg.typ.kind)
+                .map_err(|e| e.with_key_prefix(name))?;
+            res.extend(fields);
+        }
+
+        if let Some(name) = public_inputs.0.keys().min() {
+            return Err(ParsingError::UnknownPublicInput(name.clone()));
+        }
+
+        Ok(res)
+    }
+}
+
+//
+// JSON serialization of outputs
+//
+
+impl<B: Backend> CompiledCircuit<B> {
+    /// Reassembles the public output of the circuit (e.g. as returned by the prover),
+    /// given as a flat vector of field elements, into the JSON shape of the return type of `main`
+    /// (`null` if `main` doesn't return anything).
+    pub fn public_output_to_json(
+        &self,
+        values: &[B::Field],
+    ) -> Result<serde_json::Value, ParsingError> {
+        match &self.main_info().sig().return_type {
+            Some(typ) => self.output_to_json(values, &typ.kind),
+            None if values.is_empty() => Ok(serde_json::Value::Null),
+            None => Err(ParsingError::MismatchOutputSize {
+                typ: TyKind::Tuple(vec![]),
+                expected: 0,
+                observed: values.len(),
+            }),
+        }
+    }
+
+    /// Reassembles a value of the given type from its field elements,
+    /// which is the inverse of [Self::parse_single_input]:
+    /// arrays and tuples are JSON arrays, structs are JSON objects (with the names of their fields),
+    /// field elements are strings (in decimal), and booleans are booleans.
+    pub fn output_to_json(
+        &self,
+        values: &[B::Field],
+        typ: &TyKind,
+    ) -> Result<serde_json::Value, ParsingError> {
+        let expected = self.circuit.size_of(typ);
+        if values.len() != expected {
+            return Err(ParsingError::MismatchOutputSize {
+                typ: typ.clone(),
+                expected,
+                observed: values.len(),
+            });
+        }
+
+        Ok(self.value_to_json(&mut values.iter(), typ))
+    }
+
+    /// Consumes the field elements of a value of the given type (there must be enough of them).
+    fn value_to_json<'a>(
+        &self,
+        values: &mut impl Iterator<Item = &'a B::Field>,
+        typ: &TyKind,
+    ) -> serde_json::Value {
+        use serde_json::Value;
+
+        match typ {
+            TyKind::Field | TyKind::BigInt | TyKind::U32 | TyKind::U64 => {
+                let value: BigUint = (*values.next().expect("not enough field elements")).into();
+                Value::String(value.to_string())
+            }
+            TyKind::Bool => Value::Bool(values.next().expect("not enough field elements").is_one()),
+            TyKind::Array(el_typ, size) => Value::Array(
+                (0..*size)
+                    .map(|_| self.value_to_json(values, el_typ))
+                    .collect(),
+            ),
+            TyKind::Tuple(typs) => Value::Array(
+                typs.iter()
+                    .map(|typ| self.value_to_json(values, typ))
+                    .collect(),
+            ),
+            TyKind::Custom { module, name } => {
+                let qualified = FullyQualified::new(module, name);
+                let struct_info = self
+                    .circuit
+                    .struct_info(&qualified)
+                    .expect("compiler bug: couldn't find struct given as output");
+
+                // fields are laid out in declaration order
+                Value::Object(
+                    struct_info
+                        .fields
+                        .iter()
+                        .map(|(field_name, field_ty)| {
+                            (field_name.clone(), self.value_to_json(values, field_ty))
+                        })
+                        .collect(),
+                )
+            }
+            TyKind::GenericArray(_, _) => {
+                unreachable!("the main function can't be generic")
+            }
+        }
+    }
+}
+
 //
 // Helpers
 //
 
+/// Parses a field element given in decimal (`"42"`) or in hexadecimal (`"0x2a"`).
+pub(crate) fn parse_field<F: BackendField>(ss: &str) -> Result<F, ParsingError> {
+    let biguint = parse_biguint(ss)?;
+
+    F::try_from(biguint).map_err(|_| ParsingError::FieldOverflow {
+        key: "".to_string(),
+        value: ss.to_string(),
+    })
+}
+
+/// Parses an integer in decimal, or in hexadecimal if prefixed with `0x`.
+fn parse_biguint(ss: &str) -> Result<BigUint, ParsingError> {
+    let (digits, radix) = match ss.strip_prefix("0x") {
+        Some(hex) => (hex, 16),
+        None => (ss, 10),
+    };
+
+    BigUint::from_str_radix(digits, radix).map_err(|_| ParsingError::InvalidField(ss.to_string()))
+}
+
 pub trait ExtField /* : PrimeField*/ {
     fn to_dec_string(&self) -> String;
 }
@@ -149,6 +513,13 @@ impl ExtField for VestaField {
 
 #[cfg(test)]
 mod tests {
+    use crate::{
+        backends::kimchi::KimchiVesta,
+        compiler::{compile, generate_witness, typecheck_next_file, Sources},
+        parser::types::ModulePath,
+        type_checker::TypeChecker,
+    };
+
     use super::*;
 
     #[test]
@@ -156,4 +527,317 @@ mod tests {
         let field = VestaField::from(42);
         assert_eq!(field.to_dec_string(), "42");
     }
+
+    #[test]
+    fn test_input_schema() {
+        let schema =
+            parse_input_schema(r#"{"xx": {"bits": 8}, "yy": {"min": "1", "max": "0x10"}}"#)
+                .unwrap();
+
+        // the inputs within their bounds are parsed
+        let inputs = r#"{"xx": "255", "yy": ["1", "16"], "zz": "1000"}"#;
+        assert!(parse_inputs_with_schema(inputs, &schema).is_ok());
+
+        // an input exceeding its bit-width is rejected
+        let err = parse_inputs_with_schema(r#"{"xx": "256"}"#, &schema).unwrap_err();
+        assert!(matches!(
+            err,
+            ParsingError::InputOutOfBounds { key, value, .. } if key == "xx" && value == "256"
+        ));
+
+        // the bounds apply to each element of an array
+        let err = parse_inputs_with_schema(r#"{"yy": ["2", "17"]}"#, &schema).unwrap_err();
+        assert!(matches!(
+            err,
+            ParsingError::InputOutOfBounds { key, .. } if key == "yy[1]"
+        ));
+        let err = parse_inputs_with_schema(r#"{"yy": ["0"]}"#, &schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the value `0` given for `yy[0]` is out of the bounds of the input schema (it must be at least 1)"
+        );
+    }
+
+    #[test]
+    fn test_non_field_array_inputs() -> miette::Result<()> {
+        let code = r#"struct Point {
+            xx: Field,
+            yy: Field,
+        }
+
+        fn main(pub flags: [Bool; 3], points: [Point; 2]) {
+            assert(flags[0]);
+            assert(!flags[1]);
+            assert(flags[2]);
+
+            let lhs = points[0].xx + points[0].yy;
+            let rhs = points[1].xx + points[1].yy;
+            assert_eq(lhs, rhs);
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        let _node_id = typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "inline_test_array_inputs.no".to_string(),
+            code.to_owned(),
+            0,
+        )
+        .unwrap();
+
+        let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+        let public_inputs = parse_inputs(r#"{"flags": [true, false, true]}"#).unwrap();
+        let private_inputs =
+            parse_inputs(r#"{"points": [{"xx": "1", "yy": "4"}, {"xx": "2", "yy": "3"}]}"#)
+                .unwrap();
+
+        // each boolean of the array takes one public input
+        let generated_witness = generate_witness(
+            &compiled_circuit,
+            &sources,
+            public_inputs.clone(),
+            private_inputs.clone(),
+        )?;
+        assert_eq!(
+            generated_witness.full_public_inputs,
+            vec![VestaField::one(), VestaField::zero(), VestaField::one()]
+        );
+
+        // a JSON array that doesn't match the declared length is rejected
+        let short_flags = parse_inputs(r#"{"flags": [true, false]}"#).unwrap();
+        let res = compiled_circuit.generate_witness(short_flags, private_inputs.clone());
+        assert!(res.is_err());
+
+        // prove and verify
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+        let (proof, full_public_inputs, _public_output) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_struct_inputs() -> miette::Result<()> {
+        let code = r#"struct Inner {
+            xx: Field,
+            yy: Field,
+        }
+
+        struct Outer {
+            inner: Inner,
+            zz: Field,
+        }
+
+        fn main(pub outer: Outer) {
+            let sum = outer.inner.xx + outer.inner.yy;
+            assert_eq(sum, outer.zz);
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "inline_test_nested_struct_inputs.no".to_string(),
+            code.to_owned(),
+            0,
+        )
+        .unwrap();
+
+        let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+        let witness = |public: &str| {
+            let public_inputs = parse_inputs(public).unwrap();
+            compiled_circuit.generate_witness(public_inputs, JsonInputs::default())
+        };
+
+        // the fields are flattened in declaration order, whatever the order of the JSON keys
+        let generated_witness =
+            witness(r#"{"outer": {"zz": "3", "inner": {"yy": "2", "xx": "1"}}}"#).unwrap();
+        assert_eq!(
+            generated_witness.full_public_inputs,
+            vec![
+                VestaField::from(1u64),
+                VestaField::from(2u64),
+                VestaField::from(3u64)
+            ]
+        );
+
+        // a missing field
+        let err = witness(r#"{"outer": {"zz": "3", "inner": {"xx": "1"}}}"#).unwrap_err();
+        match err.kind {
+            crate::error::ErrorKind::ParsingError(ParsingError::MissingStructField { key }) => {
+                assert_eq!(key, "outer.inner.yy");
+            }
+            kind => panic!("unexpected error: {kind}"),
+        }
+
+        // a field that is not in the struct
+        let err = witness(r#"{"outer": {"zz": "3", "inner": {"xx": "1", "yy": "2", "ww": "4"}}}"#)
+            .unwrap_err();
+        match err.kind {
+            crate::error::ErrorKind::ParsingError(ParsingError::UnknownStructField { key }) => {
+                assert_eq!(key, "outer.inner.ww");
+            }
+            kind => panic!("unexpected error: {kind}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hex_inputs() -> miette::Result<()> {
+        let code = r#"struct Point {
+            xx: Field,
+            yy: Field,
+        }
+
+        fn main(pub total: Field, points: [Point; 2], scalars: [Field; 2]) {
+            let lhs = points[0].xx + points[0].yy + scalars[0];
+            let rhs = points[1].xx + points[1].yy + scalars[1];
+            assert_eq(lhs + rhs, total);
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "inline_test_hex_inputs.no".to_string(),
+            code.to_owned(),
+            0,
+        )
+        .unwrap();
+
+        let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+        let witness = |public: &str, private: &str| {
+            let public_inputs = parse_inputs(public).unwrap();
+            let private_inputs = parse_inputs(private).unwrap();
+            generate_witness(&compiled_circuit, &sources, public_inputs, private_inputs)
+        };
+
+        // the same values, in decimal only, and then mixing hex and decimal
+        let decimal = witness(
+            r#"{"total": "90"}"#,
+            r#"{"points": [{"xx": "26", "yy": "4"}, {"xx": "2", "yy": "3"}], "scalars": ["16", "39"]}"#,
+        )?;
+        let mixed = witness(
+            r#"{"total": "0x5a"}"#,
+            r#"{"points": [{"xx": "0x1a", "yy": "4"}, {"xx": "2", "yy": "0x3"}], "scalars": ["0x10", "39"]}"#,
+        )?;
+
+        assert_eq!(decimal.full_public_inputs, mixed.full_public_inputs);
+        assert_eq!(
+            decimal.all_witness.to_kimchi_witness(),
+            mixed.all_witness.to_kimchi_witness()
+        );
+
+        // a value larger than the modulus is rejected, and the error points to the input
+        let too_large = format!("0x{}", "f".repeat(64));
+        let private_inputs = parse_inputs(&format!(
+            r#"{{"points": [{{"xx": "26", "yy": "4"}}, {{"xx": "2", "yy": "{too_large}"}}], "scalars": ["16", "39"]}}"#
+        ))
+        .unwrap();
+        let public_inputs = parse_inputs(r#"{"total": "90"}"#).unwrap();
+        let err = compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap_err();
+
+        match err.kind {
+            crate::error::ErrorKind::ParsingError(ParsingError::FieldOverflow { key, value }) => {
+                assert_eq!(key, "points[1].yy");
+                assert_eq!(value, too_large);
+            }
+            kind => panic!("unexpected error: {kind}"),
+        }
+
+        // invalid hex is still an invalid field element
+        let public_inputs = parse_inputs(r#"{"total": "0xzz"}"#).unwrap();
+        let private_inputs = parse_inputs(
+            r#"{"points": [{"xx": "26", "yy": "4"}, {"xx": "2", "yy": "3"}], "scalars": ["16", "39"]}"#,
+        )
+        .unwrap();
+        assert!(compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_array_of_struct_outputs() -> miette::Result<()> {
+        let code = r#"struct Point {
+            xx: Field,
+            yy: Field,
+        }
+
+        struct Empty {}
+
+        fn main(pub xx: Field, yy: Field) -> [Point; 2] {
+            let first = Point { xx: xx, yy: yy };
+            let second = Point { xx: xx + yy, yy: yy * 2 };
+            return [first, second];
+        }"#;
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "inline_test_array_of_struct_outputs.no".to_string(),
+            code.to_owned(),
+            0,
+        )
+        .unwrap();
+
+        let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+        let public_inputs = parse_inputs(r#"{"xx": "1"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"yy": "2"}"#).unwrap();
+        let generated_witness =
+            generate_witness(&compiled_circuit, &sources, public_inputs, private_inputs)?;
+
+        // the flat output is reassembled with the names of the fields
+        let json = compiled_circuit.public_output_to_json(&generated_witness.public_outputs)?;
+        assert_eq!(
+            json,
+            serde_json::json!([{"xx": "1", "yy": "2"}, {"xx": "3", "yy": "4"}])
+        );
+
+        // a vector that doesn't match the return type is rejected
+        let err = compiled_circuit
+            .public_output_to_json(&generated_witness.public_outputs[1..])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ParsingError::MismatchOutputSize {
+                expected: 4,
+                observed: 3,
+                ..
+            }
+        ));
+
+        // empty arrays and zero-sized structs don't consume any field element
+        let empty = TyKind::Custom {
+            module: ModulePath::Local,
+            name: "Empty".to_string(),
+        };
+        let typ = TyKind::Tuple(vec![
+            TyKind::Array(Box::new(TyKind::Field), 0),
+            TyKind::Array(Box::new(empty), 2),
+            TyKind::Bool,
+        ]);
+        let json = compiled_circuit.output_to_json(&[VestaField::one()], &typ)?;
+        assert_eq!(json, serde_json::json!([[], [{}, {}], true]));
+
+        Ok(())
+    }
 }
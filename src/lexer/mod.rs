@@ -3,7 +3,7 @@ use std::fmt::Display;
 use crate::{
     constants::Span,
     error::{Error, ErrorKind, Result},
-    syntax::{is_hexadecimal, is_identifier_or_type, is_numeric},
+    syntax::{is_binary, is_hexadecimal, is_identifier_or_type, is_numeric},
 };
 
 pub use tokens::Tokens;
@@ -62,10 +62,14 @@ pub enum Keyword {
     For,
     /// The `in` keyword for iterating
     In,
+    /// The `while` keyword
+    While,
     /// Allows custom structs to be defined
     Struct,
     /// Allows constants to be defined
     Const,
+    /// Allows type aliases to be defined
+    Type,
 }
 
 impl Keyword {
@@ -83,8 +87,10 @@ impl Keyword {
             "else" => Some(Self::Else),
             "for" => Some(Self::For),
             "in" => Some(Self::In),
+            "while" => Some(Self::While),
             "struct" => Some(Self::Struct),
             "const" => Some(Self::Const),
+            "type" => Some(Self::Type),
             _ => None,
         }
     }
@@ -105,8 +111,10 @@ impl Display for Keyword {
             Self::Else => "else",
             Self::For => "for",
             Self::In => "in",
+            Self::While => "while",
             Self::Struct => "struct",
             Self::Const => "const",
+            Self::Type => "type",
         };
 
         write!(f, "{}", desc)
@@ -115,39 +123,41 @@ impl Display for Keyword {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenKind {
-    Keyword(Keyword),   // reserved keywords
-    Identifier(String), // [a-zA-Z](A-Za-z0-9_)*
-    BigInt(String),     // (0-9)*
-    Hex(String),        // 0x[0-9a-fA-F]+
-    Dot,                // .
-    DoubleDot,          // ..
-    Comma,              // ,
-    Colon,              // :
-    DoubleColon,        // ::
-    LeftParen,          // (
-    RightParen,         // )
-    LeftBracket,        // [
-    RightBracket,       // ]
-    LeftCurlyBracket,   // {
-    RightCurlyBracket,  // }
-    SemiColon,          // ;
-    Slash,              // /
-    Comment(String),    // // comment
-    Greater,            // >
-    Less,               // <
-    Equal,              // =
-    DoubleEqual,        // ==
-    Plus,               // +
-    Minus,              // -
-    RightArrow,         // ->
-    Star,               // *
-    Ampersand,          // &
-    DoubleAmpersand,    // &&
-    Pipe,               // |
-    DoublePipe,         // ||
-    Exclamation,        // !
-    Question,           // ?
-                        //    Literal,               // "thing"
+    Keyword(Keyword),      // reserved keywords
+    Identifier(String),    // [a-zA-Z](A-Za-z0-9_)*
+    BigInt(String),        // (0-9)*, 0x[0-9a-fA-F]+ or 0b[01]+
+    Dot,                   // .
+    DoubleDot,             // ..
+    Comma,                 // ,
+    Colon,                 // :
+    DoubleColon,           // ::
+    LeftParen,             // (
+    RightParen,            // )
+    LeftBracket,           // [
+    RightBracket,          // ]
+    LeftCurlyBracket,      // {
+    RightCurlyBracket,     // }
+    SemiColon,             // ;
+    Slash,                 // /
+    Percent,               // %
+    Comment(String),       // // comment
+    DocComment(String),    // /// label of the next statement
+    Greater,               // >
+    Less,                  // <
+    Equal,                 // =
+    DoubleEqual,           // ==
+    Plus,                  // +
+    Minus,                 // -
+    RightArrow,            // ->
+    Star,                  // *
+    Ampersand,             // &
+    DoubleAmpersand,       // &&
+    Pipe,                  // |
+    DoublePipe,            // ||
+    Exclamation,           // !
+    Question,              // ?
+    Pound,                 // #
+    StringLiteral(String), // "thing" or b"thing"
 }
 
 impl Display for TokenKind {
@@ -159,7 +169,6 @@ impl Display for TokenKind {
                 "a lowercase alphanumeric (including underscore) string starting with a letter"
             }
             BigInt(_) => "a number",
-            Hex(_) => "a hexadecimal string, starting with 0x",
             Dot => ".",
             DoubleDot => "..",
             Comma => "`,`",
@@ -173,7 +182,9 @@ impl Display for TokenKind {
             RightCurlyBracket => "`}`",
             SemiColon => "`;`",
             Slash => "`/`",
+            Percent => "`%`",
             Comment(_) => "`//`",
+            DocComment(_) => "`///`",
             Greater => "`>`",
             Less => "`<`",
             Equal => "`=`",
@@ -188,7 +199,8 @@ impl Display for TokenKind {
             DoublePipe => "`||`",
             Exclamation => "`!`",
             Question => "`?`",
-            //            TokenType::Literal => "`\"something\"",
+            Pound => "`#`",
+            StringLiteral(_) => "a string literal (`\"something\"` or `b\"something\"`)",
         };
 
         write!(f, "{}", desc)
@@ -227,10 +239,12 @@ impl Token {
                 if let Some(keyword) = Keyword::parse(&ident_or_number) {
                     tokens.push(TokenKind::Keyword(keyword).new_token(ctx, len));
                 } else {
-                    let token_type = if is_numeric(&ident_or_number) {
+                    // numbers are kept as written, and parsed with [crate::syntax::parse_number]
+                    let token_type = if is_numeric(&ident_or_number)
+                        || is_hexadecimal(&ident_or_number)
+                        || is_binary(&ident_or_number)
+                    {
                         TokenKind::BigInt(ident_or_number)
-                    } else if is_hexadecimal(&ident_or_number) {
-                        TokenKind::Hex(ident_or_number)
                     } else if is_identifier_or_type(&ident_or_number) {
                         if ident_or_number.len() < 2 {
                             return Err(ctx.error(
@@ -268,9 +282,15 @@ impl Token {
             };
 
             // where we in the middle of parsing an ident or number?
+            // (a `b` right before a quote is the prefix of a byte string)
+            let mut byte_prefix = false;
             if !c.is_alphanumeric() && c != '_' {
                 if let Some(ident_or_number) = ident_or_number.take() {
-                    add_thing(ctx, &mut tokens, ident_or_number)?;
+                    if c == '"' && ident_or_number == "b" {
+                        byte_prefix = true;
+                    } else {
+                        add_thing(ctx, &mut tokens, ident_or_number)?;
+                    }
                 }
             }
 
@@ -331,6 +351,16 @@ impl Token {
                     if matches!(next_c, Some(&'/')) {
                         chars.next(); // ignore the second /
 
+                        // a doc comment labels the statement that follows it
+                        if matches!(chars.peek(), Some(&'/')) {
+                            chars.next();
+                            let comment = chars.collect::<String>();
+                            let len = comment.len();
+                            let label = comment.trim().to_string();
+                            tokens.push(TokenKind::DocComment(label).new_token(ctx, 3 + len));
+                            break;
+                        }
+
                         // TODO: why can't I call chars.as_str().to_string()
                         let comment = chars.collect::<String>();
                         let len = comment.len();
@@ -376,6 +406,9 @@ impl Token {
                 '*' => {
                     tokens.push(TokenKind::Star.new_token(ctx, 1));
                 }
+                '%' => {
+                    tokens.push(TokenKind::Percent.new_token(ctx, 1));
+                }
                 '&' => {
                     let next_c = chars.peek();
                     if matches!(next_c, Some(&'&')) {
@@ -401,6 +434,31 @@ impl Token {
                 '?' => {
                     tokens.push(TokenKind::Question.new_token(ctx, 1));
                 }
+                '#' => {
+                    tokens.push(TokenKind::Pound.new_token(ctx, 1));
+                }
+                '"' => {
+                    // everything until the closing quote (on the same line)
+                    let mut literal = String::new();
+                    let mut terminated = false;
+                    for c in chars.by_ref() {
+                        if c == '"' {
+                            terminated = true;
+                            break;
+                        }
+                        literal.push(c);
+                    }
+
+                    let len = usize::from(byte_prefix) + literal.len() + 2;
+                    if !terminated {
+                        return Err(ctx.error(
+                            ErrorKind::UnterminatedStringLiteral,
+                            Span::new(ctx.filename_id, ctx.offset, len - 1),
+                        ));
+                    }
+
+                    tokens.push(TokenKind::StringLiteral(literal).new_token(ctx, len));
+                }
                 ' ' => ctx.offset += 1,
                 _ => {
                     return Err(ctx.error(
@@ -15,6 +15,10 @@ use crate::{
 pub struct Tokens {
     pub peeked: Option<Token>,
     inner: IntoIter<Token>,
+
+    /// The doc comment (`/// ...`) seen right before the peeked token, if any.
+    /// Doc comments are not returned as tokens: they can only label a statement (see [Self::take_label]).
+    label: Option<String>,
 }
 
 impl Tokens {
@@ -22,9 +26,36 @@ impl Tokens {
         Self {
             peeked: None,
             inner: tokens.into_iter(),
+            label: None,
         }
     }
 
+    /// Returns the next token of the iterator, remembering the doc comments that precede it.
+    fn next_token(&mut self) -> Option<Token> {
+        self.label = None;
+        loop {
+            match self.inner.next() {
+                Some(Token {
+                    kind: TokenKind::DocComment(label),
+                    ..
+                }) => {
+                    // consecutive doc comments form a single label
+                    self.label = match self.label.take() {
+                        Some(prev) => Some(format!("{prev} {label}")),
+                        None => Some(label),
+                    };
+                }
+                token => return token,
+            }
+        }
+    }
+
+    /// Returns the label of the next token,
+    /// which is the doc comment that precedes it (if it was seen by [Self::peek]).
+    pub fn take_label(&mut self) -> Option<String> {
+        self.label.take()
+    }
+
     /// Peeks into the next token without advancing the iterator.
     pub fn peek(&mut self) -> Option<Token> {
         // something in the peeked
@@ -32,7 +63,7 @@ impl Tokens {
             Some(token.clone())
         } else {
             // otherwise get from iterator and store in peeked
-            let token = self.inner.next();
+            let token = self.next_token();
             self.peeked = token.clone();
             token
         }
@@ -45,7 +76,7 @@ impl Tokens {
             ctx.last_token = Some(token.clone());
             Some(token)
         } else {
-            let token = self.inner.next();
+            let token = self.next_token();
             if token.is_some() {
                 ctx.last_token = token.clone();
             }
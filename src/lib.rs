@@ -3,11 +3,13 @@
 //!
 
 pub mod backends;
+pub mod circuit_builder;
 pub mod circuit_writer;
 pub mod cli;
 pub mod compiler;
 pub mod constants;
 pub mod constraints;
+pub mod equivalence;
 pub mod error;
 pub mod imports;
 pub mod inputs;
@@ -40,7 +42,7 @@ pub mod helpers {
     };
 
     use crate::backends::{
-        kimchi::VestaField,
+        kimchi::{PallasField, VestaField},
         r1cs::{R1csBls12381Field, R1csBn254Field},
     };
 
@@ -59,10 +61,12 @@ pub mod helpers {
     }
 
     impl PrettyField for VestaField {}
+    impl PrettyField for PallasField {}
     impl PrettyField for R1csBls12381Field {}
     impl PrettyField for R1csBn254Field {}
 
-    pub fn poseidon(input: [VestaField; 2]) -> VestaField {
+    /// The poseidon hash of field elements, as computed by the `poseidon` builtin.
+    pub fn poseidon(input: &[VestaField]) -> VestaField {
         let mut sponge: ArithmeticSponge<VestaField, PlonkSpongeConstantsKimchi> =
             ArithmeticSponge::new(fp_kimchi::static_params());
         sponge.absorb(&input);
@@ -7,8 +7,8 @@ use crate::{
     constants::Span,
     error::{Error, ErrorKind, Result},
     parser::{
-        types::{FnArg, FnSig, FuncOrMethod, ModulePath, Stmt, StmtKind, TyKind},
-        ConstDef, CustomType, FunctionDef, StructDef, UsePath,
+        types::{FnArg, FnSig, FuncOrMethod, ModulePath, RangeBound, Stmt, StmtKind, TyKind},
+        ConstDef, ConstValue, CustomType, FunctionDef, StructDef, TypeAliasDef, UsePath,
     },
 };
 
@@ -18,6 +18,12 @@ pub struct NameResCtx {
 
     /// maps `module` to its original `use a::module`
     pub modules: HashMap<String, UsePath>,
+
+    /// maps the name of a function to its original `use a::module::{name}`
+    pub imported_fns: HashMap<String, UsePath>,
+
+    /// maps the name of a type alias of this module to the (resolved) type it stands for
+    pub type_aliases: HashMap<String, TyKind>,
 }
 
 impl NameResCtx {
@@ -25,6 +31,8 @@ impl NameResCtx {
         Self {
             this_module,
             modules: HashMap::new(),
+            imported_fns: HashMap::new(),
+            type_aliases: HashMap::new(),
         }
     }
 
@@ -58,15 +66,102 @@ impl NameResCtx {
                 }
             }
 
-            // fully-qualified modules are not allowed in noname. You need to import the repo first.
+            // a module written with its full path doesn't need to be imported,
+            // the type checker will find its items as long as it was type checked as a (possibly transitive) dependency
+            ModulePath::Qualified(_, _) if local => unreachable!(),
+            ModulePath::Qualified(user, repo) => {
+                *module = ModulePath::Absolute(UserRepo {
+                    user: user.value.clone(),
+                    repo: repo.value.clone(),
+                });
+            }
+
+            // name resolution only runs once
             ModulePath::Absolute(_) => unreachable!(),
         };
 
         Ok(())
     }
 
+    /// Expands the type aliases of the module (which can refer to one another),
+    /// so that [Self::resolve_typ_kind] can replace them by the types they stand for.
+    pub(crate) fn add_type_aliases(&mut self, aliases: &[&TypeAliasDef]) -> Result<()> {
+        let defs: HashMap<_, _> = aliases
+            .iter()
+            .map(|alias| (alias.name.name.clone(), *alias))
+            .collect();
+
+        for alias in aliases {
+            let mut typ = self.expand_alias(alias, &defs, &mut vec![])?;
+            self.resolve_typ_kind(&mut typ)?;
+            self.type_aliases.insert(alias.name.name.clone(), typ);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the type an alias stands for, where the aliases it uses are expanded.
+    /// `stack` contains the aliases being expanded, to detect an alias referring to itself.
+    fn expand_alias(
+        &self,
+        alias: &TypeAliasDef,
+        defs: &HashMap<String, &TypeAliasDef>,
+        stack: &mut Vec<String>,
+    ) -> Result<TyKind> {
+        let name = &alias.name.name;
+        if stack.contains(name) {
+            return Err(self.error(ErrorKind::RecursiveTypeAlias(name.clone()), alias.span));
+        }
+
+        stack.push(name.clone());
+        let mut typ = alias.typ.kind.clone();
+        self.expand_typ(&mut typ, defs, stack)?;
+        stack.pop();
+
+        Ok(typ)
+    }
+
+    fn expand_typ(
+        &self,
+        typ_kind: &mut TyKind,
+        defs: &HashMap<String, &TypeAliasDef>,
+        stack: &mut Vec<String>,
+    ) -> Result<()> {
+        match typ_kind {
+            TyKind::Custom {
+                module: ModulePath::Local,
+                name,
+            } if defs.contains_key(name.as_str()) => {
+                let expanded = self.expand_alias(defs[name.as_str()], defs, stack)?;
+                *typ_kind = expanded;
+            }
+            TyKind::Array(typ_kind, _) | TyKind::GenericArray(typ_kind, _) => {
+                self.expand_typ(typ_kind, defs, stack)?
+            }
+            TyKind::Tuple(typ_kinds) => {
+                for typ_kind in typ_kinds {
+                    self.expand_typ(typ_kind, defs, stack)?;
+                }
+            }
+            TyKind::Field
+            | TyKind::Custom { .. }
+            | TyKind::BigInt
+            | TyKind::Bool
+            | TyKind::U32
+            | TyKind::U64 => (),
+        };
+
+        Ok(())
+    }
+
     pub(crate) fn resolve_fn_def(&self, fn_def: &mut FunctionDef) -> Result<()> {
-        let FunctionDef { sig, body, span: _ } = fn_def;
+        let FunctionDef {
+            sig,
+            body,
+            is_const: _,
+            attribute: _,
+            span: _,
+        } = fn_def;
 
         //
         // signature
@@ -75,6 +170,7 @@ impl NameResCtx {
         let FnSig {
             kind,
             name: _,
+            generics: _,
             arguments,
             return_type,
         } = sig;
@@ -103,6 +199,8 @@ impl NameResCtx {
                 name: _,
                 typ,
                 attribute: _,
+                unchecked: _,
+                mutable: _,
                 span: _,
             } = arg;
             self.resolve_typ_kind(&mut typ.kind)?;
@@ -126,12 +224,27 @@ impl NameResCtx {
     fn resolve_typ_kind(&self, typ_kind: &mut TyKind) -> Result<()> {
         match typ_kind {
             TyKind::Field => (),
+            // a type alias is replaced by the type it stands for
+            TyKind::Custom {
+                module: ModulePath::Local,
+                name,
+            } if self.type_aliases.contains_key(name.as_str()) => {
+                *typ_kind = self.type_aliases[name.as_str()].clone();
+            }
             TyKind::Custom { module, name: _ } => {
                 self.resolve(module, false)?;
             }
             TyKind::BigInt => (),
-            TyKind::Array(typ_kind, _) => self.resolve_typ_kind(typ_kind)?,
+            TyKind::Array(typ_kind, _) | TyKind::GenericArray(typ_kind, _) => {
+                self.resolve_typ_kind(typ_kind)?
+            }
             TyKind::Bool => (),
+            TyKind::Tuple(typ_kinds) => {
+                for typ_kind in typ_kinds {
+                    self.resolve_typ_kind(typ_kind)?;
+                }
+            }
+            TyKind::U32 | TyKind::U64 => (),
         };
 
         Ok(())
@@ -160,34 +273,81 @@ impl NameResCtx {
         let ConstDef {
             module,
             name: _,
-            value: _,
+            value,
             span: _,
         } = cst_def;
 
         self.resolve(module, true)?;
 
+        // the initializer of a computed constant can refer to other modules
+        if let ConstValue::Computed {
+            expr,
+            annotation: _,
+        } = value
+        {
+            self.resolve_expr(expr)?;
+        }
+
         Ok(())
     }
 
     fn resolve_stmt(&self, stmt: &mut Stmt) -> Result<()> {
-        let Stmt { kind, span: _ } = stmt;
+        let Stmt {
+            kind,
+            span: _,
+            label: _,
+            no_double_gate: _,
+        } = stmt;
 
         match kind {
             StmtKind::Assign {
                 mutable: _,
                 lhs: _,
+                typ,
                 rhs,
             } => {
+                if let Some(typ) = typ {
+                    self.resolve_typ_kind(&mut typ.kind)?;
+                }
                 self.resolve_expr(rhs)?;
             }
+            StmtKind::AssignTuple { lhs: _, rhs } => self.resolve_expr(rhs)?,
             StmtKind::Expr(expr) => self.resolve_expr(expr)?,
             StmtKind::Return(expr) => self.resolve_expr(expr)?,
             StmtKind::Comment(_) => (),
             StmtKind::ForLoop {
                 var: _,
-                range: _,
+                range,
+                body,
+            } => {
+                for bound in [&mut range.start, &mut range.end] {
+                    if let RangeBound::Const { module, name: _ } = bound {
+                        self.resolve(module, false)?;
+                    }
+                }
+
+                for stmt in body {
+                    self.resolve_stmt(stmt)?;
+                }
+            }
+            StmtKind::If { cond, body } => {
+                self.resolve_expr(cond)?;
+
+                for stmt in body {
+                    self.resolve_stmt(stmt)?;
+                }
+            }
+            StmtKind::While {
+                cond,
+                max_iters,
                 body,
             } => {
+                self.resolve_expr(cond)?;
+
+                if let RangeBound::Const { module, name: _ } = max_iters {
+                    self.resolve(module, false)?;
+                }
+
                 for stmt in body {
                     self.resolve_stmt(stmt)?;
                 }
@@ -1,7 +1,10 @@
 use crate::{
     cli::packages::UserRepo,
     error::Result,
-    parser::{types::ModulePath, CustomType, Expr, ExprKind},
+    parser::{
+        types::{ModulePath, RangeBound},
+        CustomType, Expr, ExprKind,
+    },
     stdlib::{BUILTIN_FN_NAMES, QUALIFIED_BUILTINS},
 };
 
@@ -21,7 +24,16 @@ impl NameResCtx {
                 fn_name,
                 args,
             } => {
-                if matches!(module, ModulePath::Local) && BUILTIN_FN_NAMES.contains(&fn_name.value)
+                let imported = match module {
+                    ModulePath::Local => self.imported_fns.get(&fn_name.value),
+                    _ => None,
+                };
+
+                if let Some(use_path) = imported {
+                    // if it's an imported function, qualify it with the module it comes from
+                    *module = ModulePath::Absolute(use_path.into());
+                } else if matches!(module, ModulePath::Local)
+                    && BUILTIN_FN_NAMES.contains(&fn_name.value)
                 {
                     // if it's a builtin, use `std::builtin`
                     *module = ModulePath::Absolute(UserRepo::new(QUALIFIED_BUILTINS));
@@ -65,7 +77,7 @@ impl NameResCtx {
             ExprKind::Not(expr) => {
                 self.resolve_expr(expr)?;
             }
-            ExprKind::BigInt(_) => {}
+            ExprKind::BigInt(_) | ExprKind::StringLiteral(_) => {}
             ExprKind::Variable { module, name: _ } => {
                 self.resolve(module, false)?;
             }
@@ -73,7 +85,15 @@ impl NameResCtx {
                 self.resolve_expr(array)?;
                 self.resolve_expr(idx)?;
             }
-            ExprKind::ArrayDeclaration(items) => {
+            ExprKind::ArraySlice { array, range } => {
+                self.resolve_expr(array)?;
+                for bound in [&mut range.start, &mut range.end] {
+                    if let RangeBound::Const { module, name: _ } = bound {
+                        self.resolve(module, false)?;
+                    }
+                }
+            }
+            ExprKind::ArrayDeclaration(items) | ExprKind::TupleDeclaration(items) => {
                 for expr in items {
                     self.resolve_expr(expr)?;
                 }
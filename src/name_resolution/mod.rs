@@ -1,8 +1,13 @@
+use std::collections::HashSet;
+
 use crate::{
     backends::Backend,
     cli::packages::UserRepo,
     error::{Error, ErrorKind, Result},
-    parser::{ConstDef, FunctionDef, RootKind, StructDef, AST},
+    parser::{
+        types::{FnSig, FuncOrMethod},
+        ConstDef, FunctionDef, RootKind, StructDef, TypeAliasDef, AST,
+    },
 };
 
 use self::context::NameResCtx;
@@ -40,6 +45,23 @@ impl<B: Backend> NAST<B> {
                         ));
                     }
 
+                    // `use user::repo::{foo, bar};` only brings the functions in scope
+                    if !path.items.is_empty() {
+                        for item in &path.items {
+                            if ctx
+                                .imported_fns
+                                .insert(item.value.clone(), path.clone())
+                                .is_some()
+                            {
+                                return Err(ctx.error(
+                                    ErrorKind::DuplicateImport(item.value.clone()),
+                                    item.span,
+                                ));
+                            }
+                        }
+                        continue;
+                    }
+
                     // insert and detect duplicates
                     if ctx
                         .modules
@@ -54,18 +76,64 @@ impl<B: Backend> NAST<B> {
                 }
                 RootKind::FunctionDef(FunctionDef { span, .. })
                 | RootKind::StructDef(StructDef { span, .. })
-                | RootKind::ConstDef(ConstDef { span, .. }) => abort = Some(*span),
+                | RootKind::ConstDef(ConstDef { span, .. })
+                | RootKind::TypeAlias(TypeAliasDef { span, .. }) => abort = Some(*span),
                 RootKind::Comment(_) => (),
             }
         }
 
+        // an imported function can't share its name with a function of the module
+        for root in &ast.0 {
+            if let RootKind::FunctionDef(FunctionDef {
+                sig:
+                    FnSig {
+                        kind: FuncOrMethod::Function(_),
+                        name,
+                        ..
+                    },
+                ..
+            }) = &root.kind
+            {
+                if ctx.imported_fns.contains_key(&name.value) {
+                    return Err(ctx.error(
+                        ErrorKind::ImportedFnCollision(name.value.clone()),
+                        name.span,
+                    ));
+                }
+            }
+        }
+
+        // collect the type aliases, which can't share their name with another type of the module
+        let mut type_names = HashSet::new();
+        let mut aliases = vec![];
+        for root in &ast.0 {
+            let name = match &root.kind {
+                RootKind::TypeAlias(alias) => {
+                    aliases.push(alias);
+                    &alias.name
+                }
+                RootKind::StructDef(struct_def) => &struct_def.name,
+                RootKind::Use(_)
+                | RootKind::FunctionDef(_)
+                | RootKind::ConstDef(_)
+                | RootKind::Comment(_) => continue,
+            };
+
+            if !type_names.insert(name.name.clone()) {
+                return Err(ctx.error(ErrorKind::DuplicateType(name.name.clone()), name.span));
+            }
+        }
+
+        // the type aliases are expanded wherever they are used
+        ctx.add_type_aliases(&aliases)?;
+
         // now go through the AST and mutate any module to its fully-qualified path
         for root in &mut ast.0 {
             match &mut root.kind {
                 RootKind::FunctionDef(f) => ctx.resolve_fn_def(f)?,
                 RootKind::StructDef(s) => ctx.resolve_struct_def(s)?,
                 RootKind::ConstDef(c) => ctx.resolve_const_def(c)?,
-                RootKind::Use(_) | RootKind::Comment(_) => (),
+                RootKind::Use(_) | RootKind::TypeAlias(_) | RootKind::Comment(_) => (),
             }
         }
 
@@ -1,7 +1,9 @@
 use crate::{
     backends::kimchi::KimchiVesta,
+    circuit_writer::{CircuitOptions, CircuitWriter},
     compiler::{typecheck_next_file_inner, Sources},
     error::ErrorKind,
+    parser::types::TyKind,
     type_checker::TypeChecker,
 };
 
@@ -73,3 +75,163 @@ fn test_return_mismatch() {
         ErrorKind::ReturnTypeMismatch(..)
     ));
 }
+
+#[test]
+fn test_if_condition_not_bool() {
+    let code = r#"
+        fn thing(xx: Field) -> Field {
+            return if xx { xx } else { 1 };
+        }
+        "#;
+
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let res = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut Sources::new(),
+        "example.no".to_string(),
+        code.to_string(),
+        0,
+    );
+
+    assert!(matches!(
+        res.unwrap_err().kind,
+        ErrorKind::IfConditionNotBool(TyKind::Field)
+    ));
+}
+
+#[test]
+fn test_if_else_type_mismatch() {
+    let code = r#"
+        fn thing(xx: Field) -> Field {
+            let cond = xx == 1;
+            return if cond { xx + 1 } else { cond };
+        }
+        "#;
+
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let res = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut Sources::new(),
+        "example.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .unwrap_err();
+
+    assert!(matches!(
+        res.kind,
+        ErrorKind::IfElseTypeMismatch(TyKind::Field, TyKind::Bool)
+    ));
+
+    // the error points at the `else` branch
+    assert_eq!(&code[res.span.start..res.span.end()], "cond");
+}
+
+#[test]
+fn test_unchecked_private_input() {
+    let code = r#"
+    fn main(pub xx: Field, #[unchecked] flag: Bool) {
+        assert(flag);
+        assert_eq(xx, 1);
+    }
+    "#;
+
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let res = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut Sources::new(),
+        "example.no".to_string(),
+        code.to_string(),
+        0,
+    );
+
+    assert!(matches!(
+        res.unwrap_err().kind,
+        ErrorKind::UncheckedNotPublicInput
+    ));
+}
+
+#[test]
+fn test_assign_to_immutable_argument() {
+    let code = r#"
+    fn set(arr: [Field; 3], idx: Field) -> [Field; 3] {
+        arr[idx] = 0;
+        return arr;
+    }
+    "#;
+
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let res = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut Sources::new(),
+        "example.no".to_string(),
+        code.to_string(),
+        0,
+    );
+
+    assert!(matches!(
+        res.unwrap_err().kind,
+        ErrorKind::AssignmentToImmutableVariable
+    ));
+}
+
+#[test]
+fn test_string_literal_outside_of_assertion() {
+    let code = r#"
+    fn thing(xx: Field) -> Field {
+        return xx + "one";
+    }
+    "#;
+
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let res = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut Sources::new(),
+        "example.no".to_string(),
+        code.to_string(),
+        0,
+    );
+
+    assert!(matches!(
+        res.unwrap_err().kind,
+        ErrorKind::UnexpectedStringLiteral
+    ));
+}
+
+#[test]
+fn test_unused_private_input() {
+    let code = r#"
+    fn main(pub zz: Field, xx: Field, yy: Field) {
+        assert_eq(xx, zz);
+    }
+    "#;
+
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut Sources::new(),
+        "example.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .unwrap();
+
+    let err = CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        None,
+    )
+    .err()
+    .expect("expected an error");
+
+    // the error names the unused input, and points at its declaration
+    assert!(matches!(&err.kind, ErrorKind::PrivateInputNotUsed(names) if names == &["yy"]));
+    assert_eq!(&code[err.span.start..err.span.end()], "yy");
+}
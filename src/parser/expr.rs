@@ -8,7 +8,7 @@ use crate::{
 use serde::{Deserialize, Serialize};
 
 use super::{
-    types::{parse_fn_call_args, parse_type_declaration, Ident, ModulePath},
+    types::{parse_fn_call_args, parse_type_declaration, Ident, ModulePath, Range, RangeBound},
     CustomType, ParserCtx,
 };
 
@@ -25,6 +25,7 @@ use super::{
 //~     | ident
 //~     | fn_call
 //~     | array_access
+//~     | array_slice
 //~ bin_op ::= "+" | "-" | "/" | "*" | "=="
 //~ numeric ::= /[0-9]+/
 //~ ident ::= /[A-Za-z_][A-Za-z_0-9]*/
@@ -91,6 +92,9 @@ pub enum ExprKind {
     /// any numbers
     BigInt(String),
 
+    /// `"message"`, which can only be the message of an assertion (e.g. `assert(cond, "message")`)
+    StringLiteral(String),
+
     /// a variable or a type. For example, `mod::A`, `x`, `y`, etc.
     // TODO: change to `identifier` or `path`?
     Variable { module: ModulePath, name: Ident },
@@ -99,9 +103,16 @@ pub enum ExprKind {
     /// `lhs[idx]`
     ArrayAccess { array: Box<Expr>, idx: Box<Expr> },
 
+    /// A slice of an array, with bounds known at compile time, for example:
+    /// `lhs[1..3]`
+    ArraySlice { array: Box<Expr>, range: Range },
+
     /// `[ ... ]`
     ArrayDeclaration(Vec<Expr>),
 
+    /// `( ..., ... )`
+    TupleDeclaration(Vec<Expr>),
+
     /// `name { fields }`
     CustomTypeDeclaration {
         custom: CustomType,
@@ -125,11 +136,26 @@ pub enum Op2 {
     Subtraction,
     Multiplication,
     Division,
+    /// `%`, the remainder of the integer division by a constant modulus.
+    Modulo,
     Equality,
     BoolAnd,
     BoolOr,
 }
 
+impl Op2 {
+    /// The name of the method that overloads the operator on custom structs,
+    /// if it can be overloaded (e.g. `lhs + rhs` calls `lhs.add(rhs)`).
+    pub fn method_name(&self) -> Option<&'static str> {
+        match self {
+            Op2::Addition => Some("add"),
+            Op2::Subtraction => Some("sub"),
+            Op2::Multiplication => Some("mul"),
+            Op2::Division | Op2::Modulo | Op2::Equality | Op2::BoolAnd | Op2::BoolOr => None,
+        }
+    }
+}
+
 impl Expr {
     /// Parses until it finds something it doesn't know, then returns without consuming the token it doesn't know (the caller will have to make sense of it)
     pub fn parse(ctx: &mut ParserCtx, tokens: &mut Tokens) -> Result<Self> {
@@ -140,6 +166,9 @@ impl Expr {
             // numeric
             TokenKind::BigInt(b) => Expr::new(ctx, ExprKind::BigInt(b), span),
 
+            // `"message"`
+            TokenKind::StringLiteral(s) => Expr::new(ctx, ExprKind::StringLiteral(s), span),
+
             // identifier
             TokenKind::Identifier(value) => {
                 let maybe_module = Ident::new(value, span);
@@ -164,14 +193,26 @@ impl Expr {
                             _ => panic!("cannot qualify a non-identifier"),
                         };
 
-                        Expr::new(
-                            ctx,
-                            ExprKind::Variable {
-                                module: ModulePath::Alias(maybe_module),
-                                name,
-                            },
-                            span,
-                        )
+                        // user::repo::expr
+                        //           ^^
+                        let (module, name) = match tokens.peek() {
+                            Some(Token {
+                                kind: TokenKind::DoubleColon,
+                                ..
+                            }) => {
+                                tokens.bump(ctx); // ::
+
+                                let item = tokens.bump_ident(
+                                    ctx,
+                                    ErrorKind::InvalidPath("expected an item after `user::repo::`"),
+                                )?;
+
+                                (ModulePath::Qualified(maybe_module, name), item)
+                            }
+                            _ => (ModulePath::Alias(maybe_module), name),
+                        };
+
+                        Expr::new(ctx, ExprKind::Variable { module, name }, span)
                     }
 
                     // just an identifier
@@ -189,20 +230,75 @@ impl Expr {
             // negated expr
             TokenKind::Minus => {
                 let expr = Expr::parse(ctx, tokens)?;
+                let span = span.merge_with(expr.span);
 
-                Expr::new(ctx, ExprKind::Negated(Box::new(expr)), span)
+                // `-a - b` is parsed as `-(a - b)`,
+                // so the negation is pushed down to the left operand: `(-a) - b`
+                match expr.kind {
+                    ExprKind::BinaryOp {
+                        op,
+                        lhs,
+                        rhs,
+                        protected: false,
+                    } => {
+                        let lhs_span = span.merge_with(lhs.span);
+                        let lhs = Expr::new(ctx, ExprKind::Negated(lhs), lhs_span);
+                        Expr::new(
+                            ctx,
+                            ExprKind::BinaryOp {
+                                op,
+                                lhs: Box::new(lhs),
+                                rhs,
+                                protected: false,
+                            },
+                            span,
+                        )
+                    }
+                    kind => {
+                        let expr = Expr { kind, ..expr };
+                        Expr::new(ctx, ExprKind::Negated(Box::new(expr)), span)
+                    }
+                }
             }
 
-            // parenthesis
+            // parenthesis or tuple
             TokenKind::LeftParen => {
                 let mut expr = Expr::parse(ctx, tokens)?;
-                tokens.bump_expected(ctx, TokenKind::RightParen)?;
 
-                if let ExprKind::BinaryOp { protected, .. } = &mut expr.kind {
-                    *protected = true;
-                }
+                // (a, b)
+                //   ^
+                if matches!(
+                    tokens.peek(),
+                    Some(Token {
+                        kind: TokenKind::Comma,
+                        ..
+                    })
+                ) {
+                    let mut items = vec![expr];
+                    while matches!(
+                        tokens.peek(),
+                        Some(Token {
+                            kind: TokenKind::Comma,
+                            ..
+                        })
+                    ) {
+                        tokens.bump(ctx);
+                        items.push(Expr::parse(ctx, tokens)?);
+                    }
+
+                    let end = tokens.bump_expected(ctx, TokenKind::RightParen)?;
+                    let span = span.merge_with(end.span);
+
+                    Expr::new(ctx, ExprKind::TupleDeclaration(items), span)
+                } else {
+                    tokens.bump_expected(ctx, TokenKind::RightParen)?;
+
+                    if let ExprKind::BinaryOp { protected, .. } = &mut expr.kind {
+                        *protected = true;
+                    }
 
-                expr
+                    expr
+                }
             }
 
             // true or false
@@ -226,17 +322,6 @@ impl Expr {
                 //           ^^^^^
                 let then_ = Box::new(Expr::parse(ctx, tokens)?);
 
-                if !matches!(
-                    &then_.kind,
-                    ExprKind::Variable { .. }
-                        | ExprKind::Bool { .. }
-                        | ExprKind::BigInt { .. }
-                        | ExprKind::FieldAccess { .. }
-                        | ExprKind::ArrayAccess { .. }
-                ) {
-                    panic!("_then_ branch of ternary operator cannot be more than a variable")
-                }
-
                 // if cond { expr1 } else { expr2 }
                 //                 ^
                 tokens.bump_expected(ctx, TokenKind::RightCurlyBracket)?;
@@ -253,17 +338,6 @@ impl Expr {
                 //                          ^^^^^
                 let else_ = Box::new(Expr::parse(ctx, tokens)?);
 
-                if !matches!(
-                    &else_.kind,
-                    ExprKind::Variable { .. }
-                        | ExprKind::Bool { .. }
-                        | ExprKind::BigInt { .. }
-                        | ExprKind::FieldAccess { .. }
-                        | ExprKind::ArrayAccess { .. }
-                ) {
-                    panic!("_else_ branch of ternary operator cannot be more than a variable")
-                }
-
                 // if cond { expr1 } else { expr2 }
                 //                                ^
                 let end = tokens.bump_expected(ctx, TokenKind::RightCurlyBracket)?;
@@ -390,6 +464,7 @@ impl Expr {
                     | TokenKind::Minus
                     | TokenKind::Star
                     | TokenKind::Slash
+                    | TokenKind::Percent
                     | TokenKind::DoubleEqual
                     | TokenKind::DoubleAmpersand
                     | TokenKind::DoublePipe
@@ -403,6 +478,7 @@ impl Expr {
                     TokenKind::Minus => Op2::Subtraction,
                     TokenKind::Star => Op2::Multiplication,
                     TokenKind::Slash => Op2::Division,
+                    TokenKind::Percent => Op2::Modulo,
                     TokenKind::DoubleEqual => Op2::Equality,
                     TokenKind::DoubleAmpersand => Op2::BoolAnd,
                     TokenKind::DoublePipe => Op2::BoolOr,
@@ -474,9 +550,13 @@ impl Expr {
                 tokens.bump(ctx); // [
 
                 // sanity check
+                // (chained accesses like `array[i][j]` are allowed for multi-dimensional arrays)
                 if !matches!(
                     self.kind,
-                    ExprKind::Variable { .. } | ExprKind::FieldAccess { .. }
+                    ExprKind::Variable { .. }
+                        | ExprKind::FieldAccess { .. }
+                        | ExprKind::ArrayAccess { .. }
+                        | ExprKind::ArraySlice { .. }
                 ) {
                     panic!("an array access can only follow a variable");
                 }
@@ -485,20 +565,58 @@ impl Expr {
                 //       ^^^
                 let idx = Expr::parse(ctx, tokens)?;
 
-                // array[idx]
-                //          ^
-                tokens.bump_expected(ctx, TokenKind::RightBracket)?;
+                // array[start..end]
+                //            ^^
+                if matches!(
+                    tokens.peek(),
+                    Some(Token {
+                        kind: TokenKind::DoubleDot,
+                        ..
+                    })
+                ) {
+                    tokens.bump(ctx); // ..
+
+                    // array[start..end]
+                    //              ^^^
+                    let start_span = idx.span;
+                    let start = RangeBound::from_expr(ctx, idx)?;
+                    let (end, end_span) = RangeBound::parse(ctx, tokens)?;
+
+                    // array[start..end]
+                    //                 ^
+                    let right_bracket = tokens.bump_expected(ctx, TokenKind::RightBracket)?;
+
+                    let range = Range {
+                        start,
+                        end,
+                        span: start_span.merge_with(end_span),
+                    };
+                    let span = self.span.merge_with(right_bracket.span);
 
-                let span = self.span.merge_with(idx.span);
+                    Expr::new(
+                        ctx,
+                        ExprKind::ArraySlice {
+                            array: Box::new(self),
+                            range,
+                        },
+                        span,
+                    )
+                } else {
+                    // array[idx]
+                    //          ^
+                    tokens.bump_expected(ctx, TokenKind::RightBracket)?;
 
-                Expr::new(
-                    ctx,
-                    ExprKind::ArrayAccess {
-                        array: Box::new(self),
-                        idx: Box::new(idx),
-                    },
-                    span,
-                )
+                    let span = self.span.merge_with(idx.span);
+
+                    Expr::new(
+                        ctx,
+                        ExprKind::ArrayAccess {
+                            array: Box::new(self),
+                            idx: Box::new(idx),
+                        },
+                        span,
+                    )
+                }
             }
 
             // fn call
@@ -543,6 +661,7 @@ impl Expr {
                     ExprKind::FieldAccess { .. }
                         | ExprKind::Variable { .. }
                         | ExprKind::ArrayAccess { .. }
+                        | ExprKind::ArraySlice { .. }
                 ) {
                     let span = self.span.merge_with(period.span);
                     return Err(ctx.error(ErrorKind::InvalidFieldAccessExpression, span));
@@ -5,7 +5,7 @@ use crate::{
     lexer::{Keyword, Token, TokenKind, Tokens},
 };
 
-pub use self::types::{ConstDef, FunctionDef, Root, RootKind, UsePath};
+pub use self::types::{ConstDef, ConstValue, FunctionDef, Root, RootKind, TypeAliasDef, UsePath};
 
 pub mod expr;
 pub mod structs;
@@ -59,6 +59,10 @@ pub struct ParserCtx {
 
     /// The file we're parsing
     pub filename_id: usize,
+
+    /// The generic parameters of the function signature being parsed,
+    /// which can be used as array sizes (e.g. `[Field; N]`).
+    pub generics: Vec<String>,
 }
 
 impl ParserCtx {
@@ -67,6 +71,7 @@ impl ParserCtx {
             node_id,
             last_token: None,
             filename_id,
+            generics: vec![],
         }
     }
 
@@ -137,6 +142,27 @@ impl<B: Backend> AST<B> {
                     }
                 }
 
+                // `const fn pow2(exp: Field) -> Field { }`
+                TokenKind::Keyword(Keyword::Const)
+                    if matches!(
+                        tokens.peek(),
+                        Some(Token {
+                            kind: TokenKind::Keyword(Keyword::Fn),
+                            ..
+                        })
+                    ) =>
+                {
+                    function_observed = true;
+                    tokens.bump(ctx);
+
+                    let mut func = FunctionDef::parse(ctx, &mut tokens)?;
+                    func.is_const = true;
+                    ast.push(Root {
+                        kind: RootKind::FunctionDef(func),
+                        span: token.span,
+                    });
+                }
+
                 // `const FOO = 42;`
                 TokenKind::Keyword(Keyword::Const) => {
                     let cst = ConstDef::parse(ctx, &mut tokens)?;
@@ -158,6 +184,21 @@ impl<B: Backend> AST<B> {
                     });
                 }
 
+                // `#[test(inputs = [2, 3], output = 5)] fn add(xx: Field, yy: Field) -> Field { }`
+                TokenKind::Pound => {
+                    function_observed = true;
+
+                    let attribute = types::Attribute::parse_fn(ctx, &mut tokens, token.span)?;
+                    tokens.bump_expected(ctx, TokenKind::Keyword(Keyword::Fn))?;
+
+                    let mut func = FunctionDef::parse(ctx, &mut tokens)?;
+                    func.attribute = Some(attribute);
+                    ast.push(Root {
+                        kind: RootKind::FunctionDef(func),
+                        span: token.span,
+                    });
+                }
+
                 // `struct Foo { a: Field, b: Field }`
                 TokenKind::Keyword(Keyword::Struct) => {
                     let s = StructDef::parse(ctx, &mut tokens)?;
@@ -167,6 +208,15 @@ impl<B: Backend> AST<B> {
                     });
                 }
 
+                // `type Hash = [Field; 4];`
+                TokenKind::Keyword(Keyword::Type) => {
+                    let alias = TypeAliasDef::parse(ctx, &mut tokens)?;
+                    ast.push(Root {
+                        kind: RootKind::TypeAlias(alias),
+                        span: token.span,
+                    });
+                }
+
                 // `// some comment`
                 TokenKind::Comment(comment) => {
                     ast.push(Root {
@@ -191,7 +241,10 @@ impl<B: Backend> AST<B> {
 //
 #[cfg(test)]
 mod tests {
-    use crate::parser::types::Stmt;
+    use crate::{
+        backends::kimchi::{KimchiVesta, VestaField},
+        parser::types::{ConstValue, RangeBound, RootKind, Stmt, StmtKind, TyKind},
+    };
 
     use super::*;
 
@@ -221,4 +274,90 @@ mod tests {
         let parsed = Stmt::parse(ctx, tokens).unwrap();
         println!("{:?}", parsed);
     }
+
+    fn parse_code(code: &str) -> Result<AST<KimchiVesta>> {
+        let tokens = Token::parse(0, code)?;
+        AST::parse(0, tokens, 0).map(|(ast, _node_id)| ast)
+    }
+
+    #[test]
+    fn number_literals() {
+        let code = r#"
+const dec = 255;
+const hex = 0xFF;
+const bin = 0b11111111;
+const mixed = [0x0a, 0b1010, 10];
+
+fn main(pub xx: [Field; 0x4]) {
+    for ii in 0..0b11 {
+        assert_eq(xx[ii], 0xff);
+    }
+}
+"#;
+        let ast = parse_code(code).unwrap();
+
+        let mut consts = vec![];
+        let mut main = None;
+        for root in &ast.0 {
+            match &root.kind {
+                RootKind::ConstDef(cst) => match &cst.value {
+                    ConstValue::Literal { value, .. } => consts.push(value.clone()),
+                    ConstValue::Computed { .. } => panic!("expected a literal constant"),
+                },
+                RootKind::FunctionDef(func) => main = Some(func),
+                _ => (),
+            }
+        }
+
+        // constants
+        let field = |values: &[u64]| -> Vec<VestaField> {
+            values
+                .iter()
+                .map(|value| VestaField::from(*value))
+                .collect()
+        };
+        assert_eq!(
+            consts,
+            vec![
+                field(&[255]),
+                field(&[255]),
+                field(&[255]),
+                field(&[10, 10, 10])
+            ]
+        );
+
+        // array sizes
+        let main = main.unwrap();
+        assert!(matches!(
+            main.sig.arguments[0].typ.kind,
+            TyKind::Array(_, 4)
+        ));
+
+        // range bounds
+        match &main.body[0].kind {
+            StmtKind::ForLoop { range, .. } => {
+                assert!(matches!(range.end, RangeBound::Literal(3)))
+            }
+            _ => panic!("expected a for loop"),
+        }
+    }
+
+    #[test]
+    fn number_literals_too_large() {
+        // the modulus of the field is smaller than 2^255
+        let code = format!("const too_large = 0x8{};", "0".repeat(63));
+        let err = parse_code(&code).err().expect("expected an error");
+        assert!(
+            matches!(err.kind, ErrorKind::CannotConvertToField(literal) if literal.starts_with("0x8"))
+        );
+
+        // array sizes must fit in 32 bits
+        let code = "fn main(pub xx: [Field; 0x100000000]) {}";
+        let err = parse_code(code).err().expect("expected an error");
+        assert!(matches!(err.kind, ErrorKind::InvalidArraySize));
+
+        // not a binary number
+        let code = "const bad = 0b102;";
+        assert!(parse_code(code).is_err());
+    }
 }
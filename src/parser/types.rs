@@ -1,15 +1,17 @@
-use std::{fmt::Display, str::FromStr};
+use std::fmt::Display;
 
 use ark_ff::Field;
+use num_traits::ToPrimitive as _;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    backends::BackendField,
     cli::packages::UserRepo,
     constants::Span,
     error::{ErrorKind, Result},
     lexer::{Keyword, Token, TokenKind, Tokens},
     stdlib::BUILTIN_FN_NAMES,
-    syntax::is_type,
+    syntax::{is_type, parse_number},
 };
 
 use super::{CustomType, Expr, ExprKind, ParserCtx, StructDef};
@@ -162,6 +164,10 @@ pub enum ModulePath {
     /// This is a type imported from another module.
     Alias(Ident),
 
+    /// This is a type from another module, written with its full `user::repo` path
+    /// (for example `mimoo::liblib::Lol`), which doesn't need to be imported.
+    Qualified(Ident, Ident),
+
     /// This is a type imported from another module,
     /// fully-qualified (as `user::repo`) thanks to the name resolution pass of the compiler.
     Absolute(UserRepo),
@@ -172,6 +178,9 @@ impl PartialEq for ModulePath {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (ModulePath::Alias(a), ModulePath::Alias(b)) => a.value == b.value,
+            (ModulePath::Qualified(a1, a2), ModulePath::Qualified(b1, b2)) => {
+                a1.value == b1.value && a2.value == b2.value
+            }
             (ModulePath::Local, ModulePath::Local) => true,
             (ModulePath::Absolute(a), ModulePath::Absolute(b)) => a == b,
             _ => false,
@@ -195,23 +204,41 @@ pub enum TyKind {
     /// An array of a fixed size.
     Array(Box<TyKind>, u32),
 
+    /// An array whose size is a generic parameter of a function (e.g. `[Field; N]`).
+    /// It only appears in the signature of a generic function,
+    /// and is replaced by a [TyKind::Array] in each of its instances.
+    GenericArray(Box<TyKind>, String),
+
     /// A boolean (`true` or `false`).
     Bool,
-    // Tuple(Vec<TyKind>),
-    // Bool,
+
+    /// A tuple of types (e.g. `(Field, [Bool; 2])`), laid out in declaration order.
+    Tuple(Vec<TyKind>),
+
+    /// An unsigned integer of 32 bits, stored in a single field element.
+    /// The results of its arithmetic are range checked, so that they can't wrap around.
+    U32,
+
+    /// An unsigned integer of 64 bits (see [TyKind::U32]).
+    U64,
     // U8,
     // U16,
-    // U32,
-    // U64,
 }
 
 impl TyKind {
     pub fn match_expected(&self, expected: &TyKind) -> bool {
         match (self, expected) {
             (TyKind::BigInt, TyKind::Field) => true,
+            // a constant can be used as an integer (it is range checked when it is bound),
+            // and an integer can always be used as a field element
+            (TyKind::BigInt, TyKind::U32 | TyKind::U64) => true,
+            (TyKind::U32 | TyKind::U64, TyKind::Field) => true,
             (TyKind::Array(lhs, lhs_size), TyKind::Array(rhs, rhs_size)) => {
                 lhs_size == rhs_size && lhs.match_expected(rhs)
             }
+            (TyKind::Tuple(lhs), TyKind::Tuple(rhs)) => {
+                lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(l, r)| l.match_expected(r))
+            }
             (
                 TyKind::Custom { module, name },
                 TyKind::Custom {
@@ -224,12 +251,41 @@ impl TyKind {
         }
     }
 
+    /// Returns whether the type uses the given generic parameter as an array size.
+    pub fn uses_generic(&self, generic: &str) -> bool {
+        match self {
+            TyKind::GenericArray(typ, name) => name == generic || typ.uses_generic(generic),
+            TyKind::Array(typ, _) => typ.uses_generic(generic),
+            TyKind::Tuple(typs) => typs.iter().any(|typ| typ.uses_generic(generic)),
+            TyKind::Field
+            | TyKind::Custom { .. }
+            | TyKind::BigInt
+            | TyKind::Bool
+            | TyKind::U32
+            | TyKind::U64 => false,
+        }
+    }
+
+    /// Returns the number of bits of an integer type (e.g. 32 for `U32`).
+    pub fn num_bits(&self) -> Option<usize> {
+        match self {
+            TyKind::U32 => Some(32),
+            TyKind::U64 => Some(64),
+            _ => None,
+        }
+    }
+
     pub fn same_as(&self, other: &TyKind) -> bool {
         match (self, other) {
             (TyKind::BigInt, TyKind::Field) | (TyKind::Field, TyKind::BigInt) => true,
+            (TyKind::BigInt, TyKind::U32 | TyKind::U64)
+            | (TyKind::U32 | TyKind::U64, TyKind::BigInt) => true,
             (TyKind::Array(lhs, lhs_size), TyKind::Array(rhs, rhs_size)) => {
                 lhs_size == rhs_size && lhs.match_expected(rhs)
             }
+            (TyKind::Tuple(lhs), TyKind::Tuple(rhs)) => {
+                lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(l, r)| l.same_as(r))
+            }
             (
                 TyKind::Custom { module, name },
                 TyKind::Custom {
@@ -260,12 +316,26 @@ impl Display for TyKind {
                     name = name,
                     module = module.value
                 ),
+                ModulePath::Qualified(module, submodule) => write!(
+                    f,
+                    "a `{module}::{submodule}::{name}` struct",
+                    name = name,
+                    module = module.value,
+                    submodule = submodule.value
+                ),
                 ModulePath::Local => write!(f, "a `{}` struct", name),
             },
             TyKind::Field => write!(f, "Field"),
             TyKind::BigInt => write!(f, "BigInt"),
             TyKind::Array(ty, size) => write!(f, "[{}; {}]", ty, size),
+            TyKind::GenericArray(ty, size) => write!(f, "[{}; {}]", ty, size),
             TyKind::Bool => write!(f, "Bool"),
+            TyKind::Tuple(types) => {
+                let types: Vec<_> = types.iter().map(|ty| ty.to_string()).collect();
+                write!(f, "({})", types.join(", "))
+            }
+            TyKind::U32 => write!(f, "U32"),
+            TyKind::U64 => write!(f, "U64"),
         }
     }
 }
@@ -273,11 +343,13 @@ impl Display for TyKind {
 impl Ty {
     pub fn reserved_types(module: ModulePath, name: Ident) -> TyKind {
         match name.value.as_ref() {
-            "Field" | "Bool" if !matches!(module, ModulePath::Local) => {
+            "Field" | "Bool" | "U32" | "U64" if !matches!(module, ModulePath::Local) => {
                 panic!("reserved types cannot be in a module (TODO: better error)")
             }
             "Field" => TyKind::Field,
             "Bool" => TyKind::Bool,
+            "U32" => TyKind::U32,
+            "U64" => TyKind::U64,
             _ => TyKind::Custom {
                 module,
                 name: name.value,
@@ -313,9 +385,19 @@ impl Ty {
                     };
 
                     let name = Ident::new(name, span);
-                    let span = token.span.merge_with(span);
 
-                    (ModulePath::Alias(maybe_module), name, span)
+                    if is_type(&name.value) {
+                        let span = token.span.merge_with(span);
+                        (ModulePath::Alias(maybe_module), name, span)
+                    } else {
+                        // user::repo::Type
+                        //           ^^^^^^
+                        tokens.bump_expected(ctx, TokenKind::DoubleColon)?;
+                        let ty_name = tokens.bump_ident(ctx, ErrorKind::MissingType)?;
+                        let span = token.span.merge_with(ty_name.span);
+
+                        (ModulePath::Qualified(maybe_module, name), ty_name, span)
+                    }
                 };
 
                 let ty_kind = Self::reserved_types(module, name);
@@ -343,10 +425,23 @@ impl Ty {
                 // [type; size]
                 //         ^
                 let siz = tokens.bump_err(ctx, ErrorKind::InvalidToken)?;
-                let siz: u32 = match siz.kind {
-                    TokenKind::BigInt(s) => s
-                        .parse()
-                        .map_err(|_e| ctx.error(ErrorKind::InvalidArraySize, siz.span))?,
+                let kind = match siz.kind {
+                    TokenKind::BigInt(s) => {
+                        let siz = parse_number(&s)
+                            .and_then(|size| size.to_u32())
+                            .ok_or_else(|| ctx.error(ErrorKind::InvalidArraySize, siz.span))?;
+                        TyKind::Array(Box::new(ty.kind), siz)
+                    }
+
+                    // [type; N]
+                    //        ^
+                    TokenKind::Identifier(name) => {
+                        if !ctx.generics.contains(&name) {
+                            return Err(ctx.error(ErrorKind::UndefinedGeneric(name), siz.span));
+                        }
+                        TyKind::GenericArray(Box::new(ty.kind), name)
+                    }
+
                     _ => {
                         return Err(ctx.error(
                             ErrorKind::ExpectedToken(TokenKind::BigInt("".to_string())),
@@ -361,10 +456,46 @@ impl Ty {
 
                 let span = span.merge_with(right_paren.span);
 
-                Ok(Ty {
-                    kind: TyKind::Array(Box::new(ty.kind), siz),
-                    span,
-                })
+                Ok(Ty { kind, span })
+            }
+
+            // tuple
+            // (type, type)
+            // ^
+            TokenKind::LeftParen => {
+                let span = token.span;
+                let mut types = vec![];
+
+                loop {
+                    // (type, type)
+                    //  ^^^^  ^^^^
+                    let ty = Ty::parse(ctx, tokens)?;
+                    types.push(ty.kind);
+
+                    // (type, type)
+                    //      ^     ^
+                    match tokens.bump(ctx) {
+                        Some(Token {
+                            kind: TokenKind::Comma,
+                            ..
+                        }) => (),
+                        Some(Token {
+                            kind: TokenKind::RightParen,
+                            span: end,
+                        }) => {
+                            return Ok(Ty {
+                                kind: TyKind::Tuple(types),
+                                span: span.merge_with(end),
+                            });
+                        }
+                        _ => {
+                            return Err(ctx.error(
+                                ErrorKind::ExpectedToken(TokenKind::RightParen),
+                                ctx.last_span(),
+                            ))
+                        }
+                    }
+                }
             }
 
             // unrecognized
@@ -378,7 +509,8 @@ impl Ty {
 //~
 //~ Backus–Naur Form (BNF) grammar:
 //~
-//~ fn_sig ::= ident "(" param { "," param } ")" [ return_val ]
+//~ fn_sig ::= ident [ generics ] "(" param { "," param } ")" [ return_val ]
+//~ generics ::= "[" ident { "," ident } "]"
 //~ return_val ::= "->" type
 //~ param ::= { "pub" } ident ":" type
 //~
@@ -387,17 +519,104 @@ impl FnSig {
     pub fn parse(ctx: &mut ParserCtx, tokens: &mut Tokens) -> Result<Self> {
         let (name, kind) = FuncOrMethod::parse(ctx, tokens)?;
 
+        // fn sum[N](
+        //       ^^^
+        let generics = Self::parse_generics(ctx, tokens)?;
+        if let Some(generic) = generics.first() {
+            if matches!(kind, FuncOrMethod::Method(_)) || name.value == "main" {
+                return Err(ctx.error(
+                    ErrorKind::InvalidFunctionSignature(
+                        "only functions other than `main` can have generic parameters",
+                    ),
+                    generic.span,
+                ));
+            }
+        }
+
+        // the generic parameters can be used as array sizes in the signature
+        ctx.generics = generics
+            .iter()
+            .map(|generic| generic.value.clone())
+            .collect();
+
         let arguments = FunctionDef::parse_args(ctx, tokens, &kind)?;
 
         let return_type = FunctionDef::parse_fn_return_type(ctx, tokens)?;
 
+        ctx.generics.clear();
+
+        // the generic parameters are inferred from the arguments at each call site,
+        // so they must all appear in the types of the arguments,
+        // or be given by a constant argument of the same name (e.g. `const N: Field`)
+        for generic in &generics {
+            if !arguments.iter().any(|arg| {
+                arg.typ.kind.uses_generic(&generic.value)
+                    || (arg.is_constant() && arg.name.value == generic.value)
+            }) {
+                return Err(ctx.error(
+                    ErrorKind::UninferableGeneric(generic.value.clone()),
+                    generic.span,
+                ));
+            }
+        }
+
         Ok(Self {
             kind,
             name,
+            generics,
             arguments,
             return_type,
         })
     }
+
+    /// Parses the generic parameters of a function (`[N, M]`), if there are any.
+    fn parse_generics(ctx: &mut ParserCtx, tokens: &mut Tokens) -> Result<Vec<Ident>> {
+        let mut generics: Vec<Ident> = vec![];
+
+        if !matches!(
+            tokens.peek(),
+            Some(Token {
+                kind: TokenKind::LeftBracket,
+                ..
+            })
+        ) {
+            return Ok(generics);
+        }
+        tokens.bump(ctx);
+
+        loop {
+            // [N, M]
+            //  ^  ^
+            let generic = tokens.bump_ident(
+                ctx,
+                ErrorKind::InvalidFunctionSignature("expected a generic parameter"),
+            )?;
+
+            if generics.iter().any(|other| other.value == generic.value) {
+                return Err(ctx.error(ErrorKind::DuplicateDefinition(generic.value), generic.span));
+            }
+            generics.push(generic);
+
+            // [N, M]
+            //   ^  ^
+            let separator = tokens.bump_err(
+                ctx,
+                ErrorKind::InvalidFunctionSignature("expected end of generic parameters"),
+            )?;
+            match separator.kind {
+                TokenKind::Comma => (),
+                TokenKind::RightBracket => break,
+                _ => {
+                    return Err(ctx.error(
+                        ErrorKind::InvalidFunctionSignature("expected end of generic parameters"),
+                        separator.span,
+                    ))
+                }
+            }
+        }
+
+        Ok(generics)
+    }
 }
 
 /// Any kind of text that can represent a type, a variable, a function name, etc.
@@ -428,10 +647,23 @@ impl Ident {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AttributeKind {
     Pub,
     Const,
+    /// `#[test(inputs = [2, 3], output = 5)]` on a function,
+    /// which can then be run with `noname test`.
+    /// The literals are stored in the JSON format of the inputs of a circuit
+    /// (see [crate::inputs::JsonInputs]).
+    Test {
+        /// The value given to each argument of the function, in order.
+        inputs: Vec<serde_json::Value>,
+        /// The value that the function is expected to return, if it returns something.
+        output: Option<serde_json::Value>,
+    },
+    /// `#[no_double_gate]` on a function,
+    /// whose generic gates are not paired in double generic gates (see [Stmt::no_double_gate]).
+    NoDoubleGate,
 }
 
 impl AttributeKind {
@@ -442,6 +674,14 @@ impl AttributeKind {
     pub fn is_constant(&self) -> bool {
         matches!(self, Self::Const)
     }
+
+    pub fn is_test(&self) -> bool {
+        matches!(self, Self::Test { .. })
+    }
+
+    pub fn is_no_double_gate(&self) -> bool {
+        matches!(self, Self::NoDoubleGate)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -458,12 +698,161 @@ impl Attribute {
     pub fn is_constant(&self) -> bool {
         self.kind.is_constant()
     }
+
+    /// Parses the attribute of a function (`#[test(...)]` or `#[no_double_gate]`),
+    /// after the `#` (whose span is given).
+    pub fn parse_fn(ctx: &mut ParserCtx, tokens: &mut Tokens, pound: Span) -> Result<Self> {
+        // #[test(inputs = [2, 3], output = 5)]
+        //  ^^^^^
+        tokens.bump_expected(ctx, TokenKind::LeftBracket)?;
+        let annotation = Ident::parse(ctx, tokens)?;
+        match annotation.value.as_str() {
+            "test" => (),
+            "no_double_gate" => {
+                let end = tokens.bump_expected(ctx, TokenKind::RightBracket)?;
+                return Ok(Self {
+                    kind: AttributeKind::NoDoubleGate,
+                    span: pound.merge_with(end.span),
+                });
+            }
+            _ => {
+                return Err(ctx.error(
+                    ErrorKind::UnknownAnnotation(annotation.value),
+                    annotation.span,
+                ))
+            }
+        }
+
+        // #[test(inputs = [2, 3], output = 5)]
+        //       ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+        tokens.bump_expected(ctx, TokenKind::LeftParen)?;
+
+        let mut inputs = None;
+        let mut output = None;
+        loop {
+            let key = Ident::parse(ctx, tokens)?;
+            tokens.bump_expected(ctx, TokenKind::Equal)?;
+            let value = parse_test_value(ctx, tokens)?;
+
+            let duplicate = match key.value.as_str() {
+                "inputs" => match value {
+                    serde_json::Value::Array(values) => inputs.replace(values).is_some(),
+                    _ => {
+                        return Err(ctx.error(
+                            ErrorKind::InvalidTestFunction(
+                                "the inputs of a test must be a list (e.g. `inputs = [2, 3]`)",
+                            ),
+                            key.span,
+                        ))
+                    }
+                },
+                "output" => output.replace(value).is_some(),
+                _ => {
+                    return Err(ctx.error(
+                        ErrorKind::InvalidTestFunction("expected `inputs` or `output`"),
+                        key.span,
+                    ))
+                }
+            };
+            if duplicate {
+                return Err(ctx.error(
+                    ErrorKind::InvalidTestFunction("`inputs` and `output` can only be given once"),
+                    key.span,
+                ));
+            }
+
+            // , or )
+            let separator = tokens.bump_err(ctx, ErrorKind::MissingToken)?;
+            match separator.kind {
+                TokenKind::Comma => (),
+                TokenKind::RightParen => break,
+                _ => {
+                    return Err(ctx.error(
+                        ErrorKind::ExpectedToken(TokenKind::RightParen),
+                        separator.span,
+                    ))
+                }
+            }
+        }
+
+        let end = tokens.bump_expected(ctx, TokenKind::RightBracket)?;
+
+        Ok(Self {
+            kind: AttributeKind::Test {
+                inputs: inputs.unwrap_or_default(),
+                output,
+            },
+            span: pound.merge_with(end.span),
+        })
+    }
+}
+
+/// Parses a literal given to, or expected from, a test function:
+/// a number, a boolean, or an array of literals.
+fn parse_test_value(ctx: &mut ParserCtx, tokens: &mut Tokens) -> Result<serde_json::Value> {
+    use serde_json::Value;
+
+    let token = tokens.bump_err(ctx, ErrorKind::MissingToken)?;
+    match token.kind {
+        // numbers are given in decimal to the witness generation
+        TokenKind::BigInt(number) => {
+            let number = parse_number(&number).ok_or_else(|| {
+                ctx.error(ErrorKind::InvalidTestFunction("invalid number"), token.span)
+            })?;
+            Ok(Value::String(number.to_string()))
+        }
+        TokenKind::Keyword(Keyword::True) => Ok(Value::Bool(true)),
+        TokenKind::Keyword(Keyword::False) => Ok(Value::Bool(false)),
+        TokenKind::LeftBracket => {
+            let mut values = vec![];
+
+            if matches!(
+                tokens.peek(),
+                Some(Token {
+                    kind: TokenKind::RightBracket,
+                    ..
+                })
+            ) {
+                tokens.bump(ctx);
+                return Ok(Value::Array(values));
+            }
+
+            loop {
+                values.push(parse_test_value(ctx, tokens)?);
+
+                // , or ]
+                let separator = tokens.bump_err(ctx, ErrorKind::MissingToken)?;
+                match separator.kind {
+                    TokenKind::Comma => (),
+                    TokenKind::RightBracket => break,
+                    _ => {
+                        return Err(ctx.error(
+                            ErrorKind::ExpectedToken(TokenKind::RightBracket),
+                            separator.span,
+                        ))
+                    }
+                }
+            }
+
+            Ok(Value::Array(values))
+        }
+        _ => Err(ctx.error(
+            ErrorKind::InvalidTestFunction(
+                "the inputs and output of a test can only be numbers, booleans or arrays",
+            ),
+            token.span,
+        )),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionDef {
     pub sig: FnSig,
     pub body: Vec<Stmt>,
+    /// Whether the function was declared with `const fn`, in which case it can also be used to compute constants.
+    pub is_const: bool,
+    /// The `#[test(...)]` attribute of a test function, or the `#[no_double_gate]` attribute.
+    pub attribute: Option<Attribute>,
     pub span: Span,
 }
 
@@ -489,6 +878,9 @@ impl Default for FuncOrMethod {
 pub struct FnSig {
     pub kind: FuncOrMethod,
     pub name: Ident,
+    /// The generic parameters of the function (e.g. `N` in `fn sum[N](arr: [Field; N])`),
+    /// which stand for array sizes that are inferred at each call site.
+    pub generics: Vec<Ident>,
     /// (pub, ident, type)
     pub arguments: Vec<FnArg>,
     pub return_type: Option<Ty>,
@@ -513,6 +905,10 @@ pub struct FnArg {
     pub name: Ident,
     pub typ: Ty,
     pub attribute: Option<Attribute>,
+    /// The span of the `#[unchecked]` annotation, if the argument has one.
+    pub unchecked: Option<Span>,
+    /// Whether the argument was declared with `mut`, and can be reassigned in the function.
+    pub mutable: bool,
     pub span: Span,
 }
 
@@ -581,6 +977,25 @@ impl FunctionDef {
         self.sig.name.value == "main"
     }
 
+    /// Whether the function was declared with `#[no_double_gate]`.
+    pub fn no_double_gate(&self) -> bool {
+        self.attribute
+            .as_ref()
+            .map(|attr| attr.kind.is_no_double_gate())
+            .unwrap_or(false)
+    }
+
+    /// Returns the inputs and the expected output of a test function.
+    pub fn test_case(&self) -> Option<(&[serde_json::Value], Option<&serde_json::Value>)> {
+        match &self.attribute {
+            Some(Attribute {
+                kind: AttributeKind::Test { inputs, output },
+                ..
+            }) => Some((inputs, output.as_ref())),
+            _ => None,
+        }
+    }
+
     pub fn parse_args(
         ctx: &mut ParserCtx,
         tokens: &mut Tokens,
@@ -597,11 +1012,45 @@ impl FunctionDef {
         loop {
             // `pub arg1: type1`
             //   ^   ^
-            let token = tokens.bump_err(
+            let mut token = tokens.bump_err(
                 ctx,
                 ErrorKind::InvalidFunctionSignature("expected function arguments"),
             )?;
 
+            // `#[unchecked] pub arg1: type1`
+            //  ^^^^^^^^^^^^
+            let unchecked = if matches!(token.kind, TokenKind::Pound) {
+                tokens.bump_expected(ctx, TokenKind::LeftBracket)?;
+                let annotation = Ident::parse(ctx, tokens)?;
+                if annotation.value != "unchecked" {
+                    return Err(ctx.error(
+                        ErrorKind::UnknownAnnotation(annotation.value),
+                        annotation.span,
+                    ));
+                }
+                let end = tokens.bump_expected(ctx, TokenKind::RightBracket)?;
+                let span = token.span.merge_with(end.span);
+
+                token = tokens.bump_err(
+                    ctx,
+                    ErrorKind::InvalidFunctionSignature("expected function arguments"),
+                )?;
+
+                Some(span)
+            } else {
+                None
+            };
+
+            // `mut arg1: type1`
+            //  ^^^
+            let mutable = matches!(token.kind, TokenKind::Keyword(Keyword::Mut));
+            if mutable {
+                token = tokens.bump_err(
+                    ctx,
+                    ErrorKind::InvalidFunctionSignature("expected function arguments"),
+                )?;
+            }
+
             let (attribute, arg_name) = match token.kind {
                 TokenKind::RightParen => break,
                 // public input
@@ -687,6 +1136,13 @@ impl FunctionDef {
             let span = if let Some(attr) = &attribute {
                 if &arg_name.value == "self" {
                     return Err(ctx.error(ErrorKind::SelfHasAttribute, arg_name.span));
+                } else if mutable {
+                    return Err(ctx.error(
+                        ErrorKind::InvalidFunctionSignature(
+                            "`mut` can only be used on private arguments",
+                        ),
+                        attr.span,
+                    ));
                 } else {
                     attr.span.merge_with(arg_typ.span)
                 }
@@ -702,6 +1158,8 @@ impl FunctionDef {
                 name: arg_name,
                 typ: arg_typ,
                 attribute,
+                unchecked,
+                mutable,
                 span,
             };
             args.push(arg);
@@ -807,7 +1265,13 @@ impl FunctionDef {
             ));
         }
 
-        let func = Self { sig, body, span };
+        let func = Self {
+            sig,
+            body,
+            is_const: false,
+            attribute: None,
+            span,
+        };
 
         Ok(func)
     }
@@ -861,14 +1325,85 @@ pub fn is_valid_fn_type(name: &str) -> bool {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Range {
-    pub start: u32,
-    pub end: u32,
+    pub start: RangeBound,
+    pub end: RangeBound,
     pub span: Span,
 }
 
-impl Range {
-    pub fn range(&self) -> std::ops::Range<u32> {
-        self.start..self.end
+/// A bound of a [Range], which must be known at compile time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RangeBound {
+    /// `for i in 0..5`
+    Literal(u32),
+
+    /// `for i in 0..size` or `for i in 0..module::size`, where `size` is a constant.
+    Const { module: ModulePath, name: Ident },
+}
+
+impl RangeBound {
+    /// Parses a literal or a (potentially qualified) constant, and returns it along with its span.
+    pub(crate) fn parse(ctx: &mut ParserCtx, tokens: &mut Tokens) -> Result<(Self, Span)> {
+        match tokens.bump(ctx) {
+            Some(Token {
+                kind: TokenKind::BigInt(n),
+                span,
+            }) => {
+                let bound = parse_number(&n)
+                    .and_then(|bound| bound.to_u32())
+                    .ok_or_else(|| ctx.error(ErrorKind::InvalidRangeSize, span))?;
+                Ok((Self::Literal(bound), span))
+            }
+            Some(Token {
+                kind: TokenKind::Identifier(value),
+                span,
+            }) => {
+                let ident = Ident::new(value, span);
+
+                // module::size
+                //       ^^
+                if matches!(
+                    tokens.peek(),
+                    Some(Token {
+                        kind: TokenKind::DoubleColon,
+                        ..
+                    })
+                ) {
+                    tokens.bump(ctx);
+                    let name = tokens.bump_ident(
+                        ctx,
+                        ErrorKind::InvalidPath("expected a constant after `module::`"),
+                    )?;
+                    let span = span.merge_with(name.span);
+                    let bound = Self::Const {
+                        module: ModulePath::Alias(ident),
+                        name,
+                    };
+                    Ok((bound, span))
+                } else {
+                    let bound = Self::Const {
+                        module: ModulePath::Local,
+                        name: ident,
+                    };
+                    Ok((bound, span))
+                }
+            }
+            _ => Err(ctx.error(
+                ErrorKind::ExpectedToken(TokenKind::BigInt("".to_string())),
+                ctx.last_span(),
+            )),
+        }
+    }
+
+    /// Converts an expression already parsed (like the start of a slice `array[start..end]`) to a bound.
+    pub(crate) fn from_expr(ctx: &mut ParserCtx, expr: Expr) -> Result<Self> {
+        match expr.kind {
+            ExprKind::BigInt(n) => parse_number(&n)
+                .and_then(|bound| bound.to_u32())
+                .map(Self::Literal)
+                .ok_or_else(|| ctx.error(ErrorKind::InvalidRangeSize, expr.span)),
+            ExprKind::Variable { module, name } => Ok(Self::Const { module, name }),
+            _ => Err(ctx.error(ErrorKind::InvalidRangeSize, expr.span)),
+        }
     }
 }
 
@@ -876,13 +1411,31 @@ impl Range {
 pub struct Stmt {
     pub kind: StmtKind,
     pub span: Span,
+
+    /// The doc comment (`/// ...`) preceding the statement, if any,
+    /// which labels the constraints it creates.
+    pub label: Option<String>,
+
+    /// Whether the statement is preceded by `#[no_double_gate]`,
+    /// in which case each of its generic gates takes a row of its own,
+    /// even when the circuit pairs the other ones in double generic gates.
+    /// This is useful to debug a part of a circuit.
+    pub no_double_gate: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StmtKind {
+    // `let mut lhs: <typ> = <rhs>;`
     Assign {
         mutable: bool,
         lhs: Ident,
+        typ: Option<Ty>,
+        rhs: Box<Expr>,
+    },
+
+    // `let (a, b) = <rhs>;`
+    AssignTuple {
+        lhs: Vec<Ident>,
         rhs: Box<Expr>,
     },
     Expr(Box<Expr>),
@@ -895,11 +1448,34 @@ pub enum StmtKind {
         range: Range,
         body: Vec<Stmt>,
     },
+
+    // `if cond { <body> }`
+    If {
+        cond: Box<Expr>,
+        body: Vec<Stmt>,
+    },
+
+    // `#[max_iters(10)] while cond { <body> }`
+    While {
+        cond: Box<Expr>,
+        max_iters: RangeBound,
+        body: Vec<Stmt>,
+    },
 }
 
 impl Stmt {
     /// Returns a list of statement parsed until seeing the end of a block (`}`).
     pub fn parse(ctx: &mut ParserCtx, tokens: &mut Tokens) -> Result<Self> {
+        // the doc comment preceding the statement is seen when peeking its first token
+        tokens.peek();
+        let label = tokens.take_label();
+
+        let mut stmt = Self::parse_unlabeled(ctx, tokens)?;
+        stmt.label = label;
+        Ok(stmt)
+    }
+
+    fn parse_unlabeled(ctx: &mut ParserCtx, tokens: &mut Tokens) -> Result<Self> {
         match tokens.peek() {
             None => Err(ctx.error(ErrorKind::InvalidStatement, ctx.last_span())),
             // assignment
@@ -913,6 +1489,62 @@ impl Stmt {
                 // let mut x = 5;
                 //     ^^^
 
+                // let (a, b) = f();
+                //     ^
+                if matches!(
+                    tokens.peek(),
+                    Some(Token {
+                        kind: TokenKind::LeftParen,
+                        ..
+                    })
+                ) {
+                    tokens.bump(ctx);
+
+                    // let (a, b) = f();
+                    //      ^  ^
+                    let mut lhs = vec![];
+                    loop {
+                        lhs.push(Ident::parse(ctx, tokens)?);
+
+                        match tokens.bump(ctx) {
+                            Some(Token {
+                                kind: TokenKind::Comma,
+                                ..
+                            }) => (),
+                            Some(Token {
+                                kind: TokenKind::RightParen,
+                                ..
+                            }) => break,
+                            _ => {
+                                return Err(ctx.error(
+                                    ErrorKind::ExpectedToken(TokenKind::RightParen),
+                                    ctx.last_span(),
+                                ))
+                            }
+                        }
+                    }
+
+                    // let (a, b) = f();
+                    //            ^
+                    tokens.bump_expected(ctx, TokenKind::Equal)?;
+
+                    // let (a, b) = f();
+                    //              ^^^
+                    let rhs = Box::new(Expr::parse(ctx, tokens)?);
+                    span = span.merge_with(rhs.span);
+
+                    // let (a, b) = f();
+                    //                 ^
+                    tokens.bump_expected(ctx, TokenKind::SemiColon)?;
+
+                    return Ok(Stmt {
+                        kind: StmtKind::AssignTuple { lhs, rhs },
+                        span,
+                        label: None,
+                        no_double_gate: false,
+                    });
+                }
+
                 let mutable = if matches!(
                     tokens.peek(),
                     Some(Token {
@@ -930,6 +1562,21 @@ impl Stmt {
                 //         ^
                 let lhs = Ident::parse(ctx, tokens)?;
 
+                // let mut x: Field = 5;
+                //          ^^^^^^^
+                let typ = if matches!(
+                    tokens.peek(),
+                    Some(Token {
+                        kind: TokenKind::Colon,
+                        ..
+                    })
+                ) {
+                    tokens.bump(ctx);
+                    Some(Ty::parse(ctx, tokens)?)
+                } else {
+                    None
+                };
+
                 // let mut x = 5;
                 //           ^
                 tokens.bump_expected(ctx, TokenKind::Equal)?;
@@ -945,8 +1592,15 @@ impl Stmt {
 
                 //
                 Ok(Stmt {
-                    kind: StmtKind::Assign { mutable, lhs, rhs },
+                    kind: StmtKind::Assign {
+                        mutable,
+                        lhs,
+                        typ,
+                        rhs,
+                    },
                     span,
+                    label: None,
+                    no_double_gate: false,
                 })
             }
 
@@ -967,23 +1621,7 @@ impl Stmt {
 
                 // for i in 0..5 { ... }
                 //          ^
-                let (start, start_span) = match tokens.bump(ctx) {
-                    Some(Token {
-                        kind: TokenKind::BigInt(n),
-                        span,
-                    }) => {
-                        let start: u32 = n
-                            .parse()
-                            .map_err(|_e| ctx.error(ErrorKind::InvalidRangeSize, span))?;
-                        (start, span)
-                    }
-                    _ => {
-                        return Err(ctx.error(
-                            ErrorKind::ExpectedToken(TokenKind::BigInt("".to_string())),
-                            ctx.last_span(),
-                        ))
-                    }
-                };
+                let (start, start_span) = RangeBound::parse(ctx, tokens)?;
 
                 // for i in 0..5 { ... }
                 //           ^^
@@ -991,23 +1629,7 @@ impl Stmt {
 
                 // for i in 0..5 { ... }
                 //             ^
-                let (end, end_span) = match tokens.bump(ctx) {
-                    Some(Token {
-                        kind: TokenKind::BigInt(n),
-                        span,
-                    }) => {
-                        let end: u32 = n
-                            .parse()
-                            .map_err(|_e| ctx.error(ErrorKind::InvalidRangeSize, span))?;
-                        (end, span)
-                    }
-                    _ => {
-                        return Err(ctx.error(
-                            ErrorKind::ExpectedToken(TokenKind::BigInt("".to_string())),
-                            ctx.last_span(),
-                        ))
-                    }
-                };
+                let (end, end_span) = RangeBound::parse(ctx, tokens)?;
 
                 let range = Range {
                     start,
@@ -1049,18 +1671,147 @@ impl Stmt {
                 Ok(Stmt {
                     kind: StmtKind::ForLoop { var, range, body },
                     span,
+                    label: None,
+                    no_double_gate: false,
                 })
             }
 
-            // if/else
+            // if statement (if/else expressions are parsed as expressions)
             Some(Token {
                 kind: TokenKind::Keyword(Keyword::If),
-                span: _,
+                span,
             }) => {
-                // TODO: wait, this should be implemented as an expression! not a statement
-                panic!("if statements are not implemented yet. Use if expressions instead (e.g. `x = if cond {{ 1 }} else {{ 2 }};`)");
+                tokens.bump(ctx);
+
+                // if cond { ... }
+                //    ^^^^
+                let cond = Box::new(Expr::parse(ctx, tokens)?);
+
+                // if cond { ... }
+                //         ^
+                tokens.bump_expected(ctx, TokenKind::LeftCurlyBracket)?;
+
+                // if cond { ... }
+                //           ^^^
+                let mut body = vec![];
+
+                loop {
+                    // if cond { ... }
+                    //               ^
+                    let next_token = tokens.peek();
+                    if matches!(
+                        next_token,
+                        Some(Token {
+                            kind: TokenKind::RightCurlyBracket,
+                            ..
+                        })
+                    ) {
+                        tokens.bump(ctx);
+                        break;
+                    }
+
+                    let statement = Stmt::parse(ctx, tokens)?;
+                    body.push(statement);
+                }
+
+                Ok(Stmt {
+                    kind: StmtKind::If { cond, body },
+                    span,
+                    label: None,
+                    no_double_gate: false,
+                })
             }
 
+            // bounded while loop
+            Some(Token {
+                kind: TokenKind::Pound,
+                ..
+            }) => {
+                tokens.bump(ctx);
+
+                // #[max_iters(10)] while cond { ... }
+                //  ^^^^^^^^^^
+                tokens.bump_expected(ctx, TokenKind::LeftBracket)?;
+                let annotation = Ident::parse(ctx, tokens)?;
+
+                // #[no_double_gate] for ii in 0..3 { ... }
+                //                   ^^^^^^^^^^^^^^^^^^^^^^
+                if annotation.value == "no_double_gate" {
+                    tokens.bump_expected(ctx, TokenKind::RightBracket)?;
+                    let mut stmt = Self::parse_unlabeled(ctx, tokens)?;
+                    stmt.no_double_gate = true;
+                    return Ok(stmt);
+                }
+
+                if annotation.value != "max_iters" {
+                    return Err(ctx.error(
+                        ErrorKind::UnknownAnnotation(annotation.value),
+                        annotation.span,
+                    ));
+                }
+
+                // #[max_iters(10)] while cond { ... }
+                //            ^^^^^
+                tokens.bump_expected(ctx, TokenKind::LeftParen)?;
+                let (max_iters, _) = RangeBound::parse(ctx, tokens)?;
+                tokens.bump_expected(ctx, TokenKind::RightParen)?;
+                tokens.bump_expected(ctx, TokenKind::RightBracket)?;
+
+                // #[max_iters(10)] while cond { ... }
+                //                  ^^^^^
+                let span = tokens
+                    .bump_expected(ctx, TokenKind::Keyword(Keyword::While))?
+                    .span;
+
+                // #[max_iters(10)] while cond { ... }
+                //                        ^^^^
+                let cond = Box::new(Expr::parse(ctx, tokens)?);
+
+                // #[max_iters(10)] while cond { ... }
+                //                             ^
+                tokens.bump_expected(ctx, TokenKind::LeftCurlyBracket)?;
+
+                // #[max_iters(10)] while cond { ... }
+                //                               ^^^
+                let mut body = vec![];
+
+                loop {
+                    // #[max_iters(10)] while cond { ... }
+                    //                                   ^
+                    let next_token = tokens.peek();
+                    if matches!(
+                        next_token,
+                        Some(Token {
+                            kind: TokenKind::RightCurlyBracket,
+                            ..
+                        })
+                    ) {
+                        tokens.bump(ctx);
+                        break;
+                    }
+
+                    let statement = Stmt::parse(ctx, tokens)?;
+                    body.push(statement);
+                }
+
+                Ok(Stmt {
+                    kind: StmtKind::While {
+                        cond,
+                        max_iters,
+                        body,
+                    },
+                    span,
+                    label: None,
+                    no_double_gate: false,
+                })
+            }
+
+            // a while loop needs a bound
+            Some(Token {
+                kind: TokenKind::Keyword(Keyword::While),
+                span,
+            }) => Err(ctx.error(ErrorKind::MissingMaxIters, span)),
+
             // return
             Some(Token {
                 kind: TokenKind::Keyword(Keyword::Return),
@@ -1079,6 +1830,8 @@ impl Stmt {
                 Ok(Stmt {
                     kind: StmtKind::Return(Box::new(expr)),
                     span,
+                    label: None,
+                    no_double_gate: false,
                 })
             }
 
@@ -1091,6 +1844,8 @@ impl Stmt {
                 Ok(Stmt {
                     kind: StmtKind::Comment(c),
                     span,
+                    label: None,
+                    no_double_gate: false,
                 })
             }
 
@@ -1104,6 +1859,8 @@ impl Stmt {
                 Ok(Stmt {
                     kind: StmtKind::Expr(Box::new(expr)),
                     span,
+                    label: None,
+                    no_double_gate: false,
                 })
             }
         }
@@ -1130,6 +1887,12 @@ where
 pub struct UsePath {
     pub module: Ident,
     pub submodule: Ident,
+
+    /// The functions imported from the module (`use user::repo::{foo, bar};`),
+    /// which can then be called without qualifying them.
+    /// If empty, the module itself is imported (`use user::repo;`).
+    pub items: Vec<Ident>,
+
     pub span: Span,
 }
 
@@ -1165,10 +1928,55 @@ impl UsePath {
             ),
         )?;
 
-        let span = span.merge_with(submodule.span);
+        let mut span = span.merge_with(submodule.span);
+
+        // `use user::repo::{foo, bar};`
+        let mut items = vec![];
+        if matches!(
+            tokens.peek(),
+            Some(Token {
+                kind: TokenKind::DoubleColon,
+                ..
+            })
+        ) {
+            tokens.bump(ctx);
+            tokens.bump_expected(ctx, TokenKind::LeftCurlyBracket)?;
+
+            loop {
+                let item = tokens.bump_ident(
+                    ctx,
+                    ErrorKind::InvalidPath("wrong path: expected the name of a function to import"),
+                )?;
+                items.push(item);
+
+                let next = tokens.bump_err(ctx, ErrorKind::InvalidEndOfLine)?;
+                match next.kind {
+                    TokenKind::Comma
+                        if matches!(
+                            tokens.peek(),
+                            Some(Token {
+                                kind: TokenKind::RightCurlyBracket,
+                                ..
+                            })
+                        ) =>
+                    {
+                        span = span.merge_with(tokens.bump(ctx).unwrap().span);
+                        break;
+                    }
+                    TokenKind::Comma => (),
+                    TokenKind::RightCurlyBracket => {
+                        span = span.merge_with(next.span);
+                        break;
+                    }
+                    _ => return Err(ctx.error(ErrorKind::InvalidEndOfLine, next.span)),
+                }
+            }
+        }
+
         Ok(UsePath {
             module,
             submodule,
+            items,
             span,
         })
     }
@@ -1181,12 +1989,66 @@ pub enum RootKind<F: Field> {
     Comment(String),
     StructDef(StructDef),
     ConstDef(ConstDef<F>),
+    TypeAlias(TypeAliasDef),
+}
+
+//
+// Type alias
+//
+
+/// `type Hash = [Field; 4];`
+/// Type aliases are expanded during name resolution, so the type checker never sees them.
+#[derive(Debug)]
+pub struct TypeAliasDef {
+    pub name: CustomType,
+    pub typ: Ty,
+    pub span: Span,
+}
+
+impl TypeAliasDef {
+    pub fn parse(ctx: &mut ParserCtx, tokens: &mut Tokens) -> Result<Self> {
+        // type Hash = [Field; 4];
+        //      ^^^^
+        let name = CustomType::parse(ctx, tokens)?;
+
+        // type Hash = [Field; 4];
+        //           ^
+        tokens.bump_expected(ctx, TokenKind::Equal)?;
+
+        // type Hash = [Field; 4];
+        //             ^^^^^^^^^^
+        let typ = Ty::parse(ctx, tokens)?;
+
+        // type Hash = [Field; 4];
+        //                       ^
+        tokens.bump_expected(ctx, TokenKind::SemiColon)?;
+
+        let span = name.span.merge_with(typ.span);
+        Ok(Self { name, typ, span })
+    }
 }
 
 //
 // Const
 //
 
+/// The number of bytes packed in each field element by string constants.
+/// 31 bytes always fit in the fields supported by noname (which have more than 248 bits).
+pub const BYTES_PER_FIELD: usize = 31;
+
+/// Packs bytes into field elements: each chunk of [BYTES_PER_FIELD] bytes (the last one being possibly shorter)
+/// is read as a little-endian integer.
+pub fn pack_bytes<F: Field>(bytes: &[u8]) -> Vec<F> {
+    bytes
+        .chunks(BYTES_PER_FIELD)
+        .map(|chunk| {
+            chunk.iter().rev().fold(F::zero(), |acc, byte| {
+                acc * F::from(256u64) + F::from(*byte as u64)
+            })
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 pub struct ConstDef<F>
 where
@@ -1194,32 +2056,140 @@ where
 {
     pub module: ModulePath, // name resolution
     pub name: Ident,
-    pub value: F,
+    pub value: ConstValue<F>,
     pub span: Span,
 }
 
-impl<F: Field + FromStr> ConstDef<F> {
+/// The value of a [ConstDef].
+#[derive(Debug)]
+pub enum ConstValue<F>
+where
+    F: Field,
+{
+    /// `const foo = 42;`, `const foo = [1, 2];` or `const foo = "bar";`, known right away.
+    Literal { value: Vec<F>, typ: Ty },
+
+    /// `const foo: Field = pow2(8) - 1;`, evaluated by the type checker once the previous constants are known.
+    Computed { expr: Expr, annotation: Option<Ty> },
+}
+
+impl<F: BackendField> ConstDef<F> {
     pub fn parse(ctx: &mut ParserCtx, tokens: &mut Tokens) -> Result<Self> {
         // const foo = 42;
         //       ^^^
         let name = Ident::parse(ctx, tokens)?;
 
+        // const foo: [Field; 2] = [1, 2];
+        //          ^^^^^^^^^^^^
+        let annotation = if matches!(
+            tokens.peek(),
+            Some(Token {
+                kind: TokenKind::Colon,
+                ..
+            })
+        ) {
+            tokens.bump(ctx);
+            Some(Ty::parse(ctx, tokens)?)
+        } else {
+            None
+        };
+
         // const foo = 42;
         //           ^
         tokens.bump_expected(ctx, TokenKind::Equal)?;
 
+        // const foo = "bar";
+        //             ^^^^^
+        if let Some(Token {
+            kind: TokenKind::StringLiteral(literal),
+            span: literal_span,
+        }) = tokens.peek()
+        {
+            tokens.bump(ctx);
+
+            let value = pack_bytes(literal.as_bytes());
+            if value.is_empty() {
+                return Err(ctx.error(ErrorKind::EmptyStringLiteral, literal_span));
+            }
+
+            let len: u32 = value.len().try_into().expect("string too large");
+            let kind = TyKind::Array(Box::new(TyKind::Field), len);
+
+            return Self::finish_parse(ctx, tokens, name, annotation, value, kind, literal_span);
+        }
+
         // const foo = 42;
         //             ^^
-        let value = Expr::parse(ctx, tokens)?;
-        let value = match &value.kind {
-            ExprKind::BigInt(s) => s
-                .parse()
-                .map_err(|_e| ctx.error(ErrorKind::InvalidField(s.clone()), value.span))?,
+        let value_expr = Expr::parse(ctx, tokens)?;
+        let parse_field = |ctx: &mut ParserCtx, s: &String, span: Span| -> Result<F> {
+            parse_number(s)
+                .and_then(|biguint| F::try_from(biguint).ok())
+                .ok_or_else(|| ctx.error(ErrorKind::CannotConvertToField(s.clone()), span))
+        };
+
+        let (value, kind) = match &value_expr.kind {
+            ExprKind::BigInt(s) => (vec![parse_field(ctx, s, value_expr.span)?], TyKind::Field),
+
+            // const foo = [1, 2];
+            //             ^^^^^^
+            ExprKind::ArrayDeclaration(items)
+                if items
+                    .iter()
+                    .all(|item| matches!(item.kind, ExprKind::BigInt(_))) =>
+            {
+                let mut value = Vec::with_capacity(items.len());
+                for item in items {
+                    if let ExprKind::BigInt(s) = &item.kind {
+                        value.push(parse_field(ctx, s, item.span)?);
+                    }
+                }
+                let len: u32 = items.len().try_into().expect("array too large");
+                (value, TyKind::Array(Box::new(TyKind::Field), len))
+            }
+
+            // const foo: Field = pow2(8) - 1;
+            //                    ^^^^^^^^^^^
             _ => {
-                return Err(ctx.error(ErrorKind::InvalidConstType, value.span));
+                // const foo = 42;
+                //               ^
+                tokens.bump_expected(ctx, TokenKind::SemiColon)?;
+
+                let span = name.span;
+                return Ok(ConstDef {
+                    module: ModulePath::Local,
+                    name,
+                    value: ConstValue::Computed {
+                        expr: value_expr,
+                        annotation,
+                    },
+                    span,
+                });
             }
         };
 
+        Self::finish_parse(ctx, tokens, name, annotation, value, kind, value_expr.span)
+    }
+
+    /// Checks the type annotation (if any) against the parsed value, and parses the end of the definition.
+    fn finish_parse(
+        ctx: &mut ParserCtx,
+        tokens: &mut Tokens,
+        name: Ident,
+        annotation: Option<Ty>,
+        value: Vec<F>,
+        kind: TyKind,
+        value_span: Span,
+    ) -> Result<Self> {
+        // the type annotation, if any, must match the value
+        if let Some(annotation) = &annotation {
+            if !kind.same_as(&annotation.kind) {
+                return Err(ctx.error(
+                    ErrorKind::MismatchType(annotation.kind.clone(), kind),
+                    value_span,
+                ));
+            }
+        }
+
         // const foo = 42;
         //               ^
         tokens.bump_expected(ctx, TokenKind::SemiColon)?;
@@ -1229,7 +2199,10 @@ impl<F: Field + FromStr> ConstDef<F> {
         Ok(ConstDef {
             module: ModulePath::Local,
             name,
-            value,
+            value: ConstValue::Literal {
+                value,
+                typ: Ty { kind, span },
+            },
             span,
         })
     }
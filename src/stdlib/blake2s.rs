@@ -0,0 +1,319 @@
+//! The Blake2s hash function ([RFC 7693](https://www.rfc-editor.org/rfc/rfc7693)),
+//! constrained with field operations only (no custom gate).
+//!
+//! Each 32-bit word is represented by its 32 little-endian bits, so that XORs and rotations are cheap.
+//! Additions modulo 2^32 are performed on the recomposed values,
+//! and the result is decomposed again to drop the carry.
+
+use ark_ff::{Field, One, Zero};
+
+use crate::{
+    backends::Backend,
+    circuit_writer::CircuitWriter,
+    constants::Span,
+    constraints::{boolean, field},
+    error::Result,
+    var::ConstOrCell,
+};
+
+/// The number of bits in a word.
+pub(crate) const WORD_BITS: usize = 32;
+
+/// The number of words in a block.
+pub(crate) const BLOCK_WORDS: usize = 16;
+
+/// The number of words in the digest.
+pub(crate) const DIGEST_WORDS: usize = 8;
+
+/// The number of rounds of the compression function.
+pub(crate) const ROUNDS: usize = 10;
+
+/// The initialization vector (same as SHA-256).
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A, 0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
+];
+
+/// The message word permutations of each round.
+const SIGMA: [[usize; 16]; ROUNDS] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// The parameter block XORed into the first word of the state:
+/// no key, and a 32-byte digest.
+const PARAMS: u32 = 0x0101_0000 | (DIGEST_WORDS as u32 * 4);
+
+/// A 32-bit word, along with its value once it has been computed.
+#[derive(Clone)]
+struct Word<B: Backend> {
+    /// The bits of the word (little-endian).
+    bits: Vec<ConstOrCell<B::Field, B::Var>>,
+
+    /// The value of the word, if it's already available as a cell or a constant.
+    value: Option<ConstOrCell<B::Field, B::Var>>,
+}
+
+impl<B: Backend> Word<B> {
+    fn constant(value: u32) -> Self {
+        let bits = (0..WORD_BITS)
+            .map(|ii| {
+                ConstOrCell::Const(if (value >> ii) & 1 == 1 {
+                    B::Field::one()
+                } else {
+                    B::Field::zero()
+                })
+            })
+            .collect();
+
+        Self {
+            bits,
+            value: Some(ConstOrCell::Const(B::Field::from(value as u64))),
+        }
+    }
+
+    /// Constrains a field element to be a 32-bit word.
+    fn from_field(
+        compiler: &mut CircuitWriter<B>,
+        value: &ConstOrCell<B::Field, B::Var>,
+        span: Span,
+    ) -> Result<Self> {
+        let bits = field::to_bits(compiler, value, WORD_BITS, span)?;
+        Ok(Self {
+            bits,
+            value: Some(value.clone()),
+        })
+    }
+
+    /// Returns the value of the word, recomposing it from its bits if needed.
+    fn value(
+        &mut self,
+        compiler: &mut CircuitWriter<B>,
+        span: Span,
+    ) -> ConstOrCell<B::Field, B::Var> {
+        if self.value.is_none() {
+            self.value = Some(field::from_bits(compiler, &self.bits, span));
+        }
+        self.value.clone().unwrap()
+    }
+
+    fn xor(&self, compiler: &mut CircuitWriter<B>, other: &Self, span: Span) -> Self {
+        let bits = self
+            .bits
+            .iter()
+            .zip(&other.bits)
+            .map(|(aa, bb)| xor_bits(compiler, aa, bb, span))
+            .collect();
+
+        Self { bits, value: None }
+    }
+
+    /// Rotates the word to the right.
+    fn rotate_right(&self, shift: usize) -> Self {
+        let mut bits = self.bits.clone();
+        bits.rotate_left(shift);
+
+        Self { bits, value: None }
+    }
+}
+
+/// `a + b - 2ab`, without creating any gate when one of the bits is a constant.
+pub(super) fn xor_bits<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    aa: &ConstOrCell<B::Field, B::Var>,
+    bb: &ConstOrCell<B::Field, B::Var>,
+    span: Span,
+) -> ConstOrCell<B::Field, B::Var> {
+    match (aa, bb) {
+        (ConstOrCell::Const(cst), other) | (other, ConstOrCell::Const(cst)) => {
+            if cst.is_zero() {
+                other.clone()
+            } else {
+                boolean::not(compiler, other, span)[0].clone()
+            }
+        }
+        (ConstOrCell::Cell(_), ConstOrCell::Cell(_)) => {
+            let minus_two = ConstOrCell::Const(-(B::Field::one() + B::Field::one()));
+            let sum = field::add(compiler, aa, bb, span);
+            let prod = field::mul(compiler, aa, bb, span);
+            let prod = field::mul(compiler, &prod[0], &minus_two, span);
+            field::add(compiler, &sum[0], &prod[0], span)[0].clone()
+        }
+    }
+}
+
+/// Adds words modulo 2^32.
+fn add_words<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    words: &mut [&mut Word<B>],
+    span: Span,
+) -> Result<Word<B>> {
+    let mut sum = ConstOrCell::Const(B::Field::zero());
+    for word in words.iter_mut() {
+        let value = word.value(compiler, span);
+        sum = field::add(compiler, &sum, &value, span)[0].clone();
+    }
+
+    // the carry fits in as many bits as needed to count the words
+    let carry_bits = (usize::BITS - (words.len() - 1).leading_zeros()) as usize;
+    let mut bits = field::to_bits(compiler, &sum, WORD_BITS + carry_bits, span)?;
+
+    // remove the carry from the sum
+    let mut value = sum;
+    let mut power = B::Field::from(1u64 << WORD_BITS);
+    for carry in &bits[WORD_BITS..] {
+        let carry = field::mul(compiler, carry, &ConstOrCell::Const(power), span);
+        value = field::sub(compiler, &value, &carry[0], span)[0].clone();
+        power.double_in_place();
+    }
+    bits.truncate(WORD_BITS);
+
+    Ok(Word {
+        bits,
+        value: Some(value),
+    })
+}
+
+/// Sets `state[lhs]` to `state[lhs] + state[rhs] (+ msg)`.
+fn add_to_state<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    state: &mut [Word<B>],
+    (lhs, rhs): (usize, usize),
+    msg: Option<&mut Word<B>>,
+    span: Span,
+) -> Result<()> {
+    let mut lhs_word = state[lhs].clone();
+    let mut rhs_word = state[rhs].clone();
+    let res = match msg {
+        Some(msg) => add_words(compiler, &mut [&mut lhs_word, &mut rhs_word, msg], span)?,
+        None => add_words(compiler, &mut [&mut lhs_word, &mut rhs_word], span)?,
+    };
+
+    // keep the value of `rhs` if it had to be recomposed
+    state[rhs] = rhs_word;
+    state[lhs] = res;
+
+    Ok(())
+}
+
+/// The mixing function G.
+fn mix<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    state: &mut [Word<B>],
+    (aa, bb, cc, dd): (usize, usize, usize, usize),
+    xx: &mut Word<B>,
+    yy: &mut Word<B>,
+    span: Span,
+) -> Result<()> {
+    add_to_state(compiler, state, (aa, bb), Some(xx), span)?;
+    state[dd] = state[dd].xor(compiler, &state[aa], span).rotate_right(16);
+    add_to_state(compiler, state, (cc, dd), None, span)?;
+    state[bb] = state[bb].xor(compiler, &state[cc], span).rotate_right(12);
+
+    add_to_state(compiler, state, (aa, bb), Some(yy), span)?;
+    state[dd] = state[dd].xor(compiler, &state[aa], span).rotate_right(8);
+    add_to_state(compiler, state, (cc, dd), None, span)?;
+    state[bb] = state[bb].xor(compiler, &state[cc], span).rotate_right(7);
+
+    Ok(())
+}
+
+/// The compression function F.
+fn compress<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    hash: &mut [Word<B>],
+    block: &mut [Word<B>],
+    counter: u64,
+    last: bool,
+    span: Span,
+) -> Result<()> {
+    let mut state: Vec<Word<B>> = hash
+        .iter()
+        .cloned()
+        .chain(IV.iter().map(|iv| Word::constant(*iv)))
+        .collect();
+
+    state[12] = state[12].xor(compiler, &Word::constant(counter as u32), span);
+    state[13] = state[13].xor(compiler, &Word::constant((counter >> 32) as u32), span);
+    if last {
+        state[14] = state[14].xor(compiler, &Word::constant(u32::MAX), span);
+    }
+
+    for sigma in &SIGMA {
+        for (ii, indexes) in [
+            (0, 4, 8, 12),
+            (1, 5, 9, 13),
+            (2, 6, 10, 14),
+            (3, 7, 11, 15),
+            (0, 5, 10, 15),
+            (1, 6, 11, 12),
+            (2, 7, 8, 13),
+            (3, 4, 9, 14),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut xx = block[sigma[2 * ii]].clone();
+            let mut yy = block[sigma[2 * ii + 1]].clone();
+            mix(compiler, &mut state, indexes, &mut xx, &mut yy, span)?;
+
+            // keep any recomposed value around
+            block[sigma[2 * ii]] = xx;
+            block[sigma[2 * ii + 1]] = yy;
+        }
+    }
+
+    for ii in 0..DIGEST_WORDS {
+        let mixed = state[ii].xor(compiler, &state[ii + DIGEST_WORDS], span);
+        hash[ii] = hash[ii].xor(compiler, &mixed, span);
+    }
+
+    Ok(())
+}
+
+/// Hashes a message made of 32-bit words (each word being encoded in little-endian in the message),
+/// and returns the 32-byte digest as 8 words.
+/// This fails to produce a valid witness if one of the inputs doesn't fit in 32 bits.
+pub(crate) fn hash<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    input: &[ConstOrCell<B::Field, B::Var>],
+    span: Span,
+) -> Result<Vec<ConstOrCell<B::Field, B::Var>>> {
+    let mut words = input
+        .iter()
+        .map(|word| Word::from_field(compiler, word, span))
+        .collect::<Result<Vec<_>>>()?;
+
+    // the message is padded with zeros to a multiple of the block size (with at least one block)
+    let num_blocks = std::cmp::max(1, words.len().div_ceil(BLOCK_WORDS));
+    let message_len = 4 * words.len() as u64;
+    words.resize(num_blocks * BLOCK_WORDS, Word::constant(0));
+
+    let mut hash: Vec<Word<B>> = IV.iter().map(|iv| Word::constant(*iv)).collect();
+    hash[0] = hash[0].xor(compiler, &Word::constant(PARAMS), span);
+
+    for (idx, block) in words.chunks_mut(BLOCK_WORDS).enumerate() {
+        let last = idx + 1 == num_blocks;
+
+        // the number of bytes hashed so far
+        let counter = if last {
+            message_len
+        } else {
+            ((idx + 1) * BLOCK_WORDS * 4) as u64
+        };
+
+        compress(compiler, &mut hash, block, counter, last, span)?;
+    }
+
+    Ok(hash
+        .iter_mut()
+        .map(|word| word.value(compiler, span))
+        .collect())
+}
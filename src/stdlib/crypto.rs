@@ -1,14 +1,39 @@
+use ark_ff::Zero;
+
 use crate::backends::Backend;
+use crate::circuit_writer::{CircuitWriter, VarInfo};
+use crate::cli::packages::UserRepo;
 use crate::constants::Span;
+use crate::constraints::field;
+use crate::error::{ErrorKind, Result};
 use crate::imports::FnKind;
 use crate::lexer::Token;
-use crate::parser::types::FnSig;
+use crate::parser::types::{FnSig, ModulePath, TyKind};
 use crate::parser::ParserCtx;
 use crate::type_checker::FnInfo;
+use crate::var::{ConstOrCell, Var};
+
+const POSEIDON_FN: &str = "poseidon[N](input: [Field; N]) -> Field";
+const BLAKE2S_FN: &str = "blake2s[N](input: [Field; N]) -> [Field; 8]";
+const KECCAK256_FN: &str = "keccak256[N](input: [Field; N]) -> [Field; 2]";
+const MERKLE_VERIFY_FN: &str =
+    "merkle_verify[D](leaf: Field, path: [Field; D], indices: [Bool; D], root: Field)";
+
+pub const CRYPTO_SIGS: &[&str] = &[POSEIDON_FN, BLAKE2S_FN, KECCAK256_FN, MERKLE_VERIFY_FN];
+
+pub const QUALIFIED_CRYPTO: &str = "std/crypto";
 
-const POSEIDON_FN: &str = "poseidon(input: [Field; 2]) -> [Field; 3]";
+/// `merkle_verify_with(leaf, path, indices, root, hash)` is [merkle_verify] where a parent is `hash(left, right)`,
+/// and `hash` is the name of a function with the signature `(Field, Field) -> Field`.
+/// Like `fold`, this can't be expressed as a signature, so the type checker and the circuit writer
+/// handle calls to it themselves (see [is_merkle_verify_with]).
+const MERKLE_VERIFY_WITH_FN: &str = "merkle_verify_with";
 
-pub const CRYPTO_SIGS: &[&str] = &[POSEIDON_FN];
+/// Returns true if a (resolved) function call is a call to `crypto::merkle_verify_with`.
+pub(crate) fn is_merkle_verify_with(module: &ModulePath, fn_name: &str) -> bool {
+    fn_name == MERKLE_VERIFY_WITH_FN
+        && matches!(module, ModulePath::Absolute(user_repo) if user_repo == &UserRepo::new(QUALIFIED_CRYPTO))
+}
 
 pub fn get_crypto_fn<B: Backend>(name: &str) -> Option<FnInfo<B>> {
     let ctx = &mut ParserCtx::default();
@@ -17,6 +42,9 @@ pub fn get_crypto_fn<B: Backend>(name: &str) -> Option<FnInfo<B>> {
 
     let fn_handle = match name {
         POSEIDON_FN => B::poseidon(),
+        BLAKE2S_FN => blake2s,
+        KECCAK256_FN => keccak256,
+        MERKLE_VERIFY_FN => merkle_verify,
         _ => return None,
     };
 
@@ -33,3 +61,166 @@ pub fn crypto_fns<B: Backend>() -> Vec<FnInfo<B>> {
         .map(|sig| get_crypto_fn(sig).unwrap())
         .collect()
 }
+
+/// The number of field elements absorbed between two permutations of the poseidon sponge.
+pub(crate) const POSEIDON_RATE: usize = 2;
+
+/// The number of field elements in the state of the poseidon sponge (its rate, and a capacity of one element).
+pub(crate) const POSEIDON_WIDTH: usize = 3;
+
+/// Hashes field elements with the poseidon sponge of kimchi (see [crate::helpers::poseidon]), for the poseidon builtin of a backend.
+/// The elements are added to the state two at a time (starting from a zero state), the state is permuted after each pair,
+/// and the digest is the first element of the final state.
+/// `permute` constrains a permutation of the state, and returns the new state.
+pub(crate) fn poseidon_sponge<B, P>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+    mut permute: P,
+) -> Result<Option<Var<B::Field, B::Var>>>
+where
+    B: Backend,
+    P: FnMut(&mut CircuitWriter<B>, Vec<B::Var>) -> Vec<B::Var>,
+{
+    // only one [Var] is passed, an array of field elements
+    assert_eq!(vars.len(), 1);
+    let input = &vars[0].var;
+
+    // hashing a full-constant input is not a good idea
+    if input.cvars.iter().all(ConstOrCell::is_const) {
+        return Err(compiler.error(
+            ErrorKind::UnexpectedError("cannot hash a full-constant input"),
+            span,
+        ));
+    }
+
+    let mut state: Vec<ConstOrCell<B::Field, B::Var>> =
+        vec![ConstOrCell::Const(B::Field::zero()); POSEIDON_WIDTH];
+    let mut chunks = input.cvars.chunks(POSEIDON_RATE).peekable();
+    loop {
+        // absorb the next elements (if any)
+        if let Some(chunk) = chunks.next() {
+            for (cell, elem) in state.iter_mut().zip(chunk) {
+                *cell = field::add(compiler, cell, elem, span).cvars[0].clone();
+            }
+        }
+
+        // IMPORTANT: time to constrain any constants
+        let cells = state
+            .iter()
+            .map(|cell| match cell {
+                ConstOrCell::Const(cst) => compiler.backend.add_constant(
+                    Some("encoding constant input to poseidon"),
+                    *cst,
+                    span,
+                ),
+                ConstOrCell::Cell(cell) => cell.clone(),
+            })
+            .collect();
+
+        state = permute(compiler, cells)
+            .into_iter()
+            .map(ConstOrCell::Cell)
+            .collect();
+
+        if chunks.peek().is_none() {
+            break;
+        }
+    }
+
+    Ok(Some(Var::new_cvar(state[0].clone(), span)))
+}
+
+/// Hashes 32-bit words with Blake2s (each word being encoded in little-endian), and returns the digest as 8 32-bit words.
+/// This is expensive (tens of thousands of constraints per block of 16 words), as it is only made of generic operations.
+fn blake2s<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a single array of words
+    assert_eq!(vars.len(), 1);
+
+    let input = &vars[0].var;
+    let digest = super::blake2s::hash(compiler, &input.cvars, span)?;
+
+    Ok(Some(Var::new(digest, span)))
+}
+
+/// Hashes bytes with Keccak-256 (as used by Ethereum), and returns the digest as two 128-bit halves (in big-endian).
+/// Like [blake2s], this is very expensive (hundreds of thousands of constraints per block of 136 bytes).
+fn keccak256<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a single array of bytes
+    assert_eq!(vars.len(), 1);
+
+    let input = &vars[0].var;
+    let digest = super::keccak::hash(compiler, &input.cvars, span)?;
+
+    Ok(Some(Var::new(digest, span)))
+}
+
+/// Constrains `leaf` to be in the Merkle tree of root `root`,
+/// given the siblings of the nodes from the leaf up to the root (`path`),
+/// and whether each of these nodes is the right child of its parent (`indices`).
+/// A parent is the poseidon digest of its two children, like `crypto::poseidon([left, right])`.
+fn merkle_verify<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    let poseidon = B::poseidon();
+    merkle_verify_with(compiler, vars, span, |compiler, left, right| {
+        let input = VarInfo::new(
+            Var::new(vec![left, right], span),
+            false,
+            Some(TyKind::Array(Box::new(TyKind::Field), 2)),
+        );
+        let digest = poseidon(compiler, &[input], span)?
+            .ok_or_else(|| compiler.error(ErrorKind::CannotComputeExpression, span))?;
+        Ok(digest[0].clone())
+    })
+}
+
+/// [merkle_verify] where a parent is computed from its left and right children by `hash`
+/// (see [is_merkle_verify_with]).
+pub(crate) fn merkle_verify_with<B, H>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+    mut hash: H,
+) -> Result<Option<Var<B::Field, B::Var>>>
+where
+    B: Backend,
+    H: FnMut(
+        &mut CircuitWriter<B>,
+        ConstOrCell<B::Field, B::Var>,
+        ConstOrCell<B::Field, B::Var>,
+    ) -> Result<ConstOrCell<B::Field, B::Var>>,
+{
+    // we get the leaf, the path, the indices and the root
+    assert_eq!(vars.len(), 4);
+
+    let path = &vars[1].var;
+    let indices = &vars[2].var;
+    assert_eq!(path.len(), indices.len());
+
+    let mut node = vars[0].var[0].clone();
+    for (sibling, is_right) in path.cvars.iter().zip(&indices.cvars) {
+        // left = node + is_right * (sibling - node), and right is whichever of the two is left
+        let diff = field::sub(compiler, sibling, &node, span);
+        let offset = field::mul(compiler, is_right, &diff[0], span);
+        let left = field::add(compiler, &node, &offset[0], span);
+        let sum = field::add(compiler, &node, sibling, span);
+        let right = field::sub(compiler, &sum[0], &left[0], span);
+
+        node = hash(compiler, left[0].clone(), right[0].clone())?;
+    }
+
+    // the last node is the root
+    let node = VarInfo::new(Var::new_cvar(node, span), false, Some(TyKind::Field));
+    super::assert_eq(compiler, &[node, vars[3].clone()], span)
+}
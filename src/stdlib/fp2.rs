@@ -0,0 +1,175 @@
+//! Arithmetic on pairs of field elements `re + im * i`, where `i^2 = -1`,
+//! like the complex numbers (and the quadratic extension `Fp2` used by pairings, when `-1` is not a square).
+//!
+//! The module defines the struct `Fp2 { re: Field, im: Field }`,
+//! which is type checked like any other struct with two fields,
+//! and builtins to create and combine such pairs with generic gates.
+
+use std::collections::HashMap;
+
+use crate::{
+    backends::Backend,
+    circuit_writer::{CircuitWriter, VarInfo},
+    cli::packages::UserRepo,
+    constants::Span,
+    constraints::field,
+    error::Result,
+    imports::FnKind,
+    lexer::Token,
+    parser::{
+        types::{FnSig, ModulePath, TyKind},
+        ParserCtx,
+    },
+    type_checker::{FnInfo, StructInfo},
+    var::{ConstOrCell, Var},
+};
+
+pub const QUALIFIED_FP2: &str = "std/fp2";
+
+/// The name of the struct, whose fields are (in order) the real and imaginary parts.
+pub const FP2_STRUCT: &str = "Fp2";
+
+const NEW_FN: &str = "new(re: Field, im: Field) -> Fp2";
+const ADD_FN: &str = "add(lhs: Fp2, rhs: Fp2) -> Fp2";
+const SUB_FN: &str = "sub(lhs: Fp2, rhs: Fp2) -> Fp2";
+const MUL_FN: &str = "mul(lhs: Fp2, rhs: Fp2) -> Fp2";
+
+pub const FP2_SIGS: &[&str] = &[NEW_FN, ADD_FN, SUB_FN, MUL_FN];
+
+pub fn get_fp2_fn<B: Backend>(name: &str) -> Option<FnInfo<B>> {
+    let ctx = &mut ParserCtx::default();
+    let mut tokens = Token::parse(0, name).unwrap();
+    let mut sig = FnSig::parse(ctx, &mut tokens).unwrap();
+
+    // the struct is defined in this module, not in the module calling the builtins
+    for arg in &mut sig.arguments {
+        qualify(&mut arg.typ.kind);
+    }
+    if let Some(return_type) = &mut sig.return_type {
+        qualify(&mut return_type.kind);
+    }
+
+    let fn_handle = match name {
+        NEW_FN => new,
+        ADD_FN => add,
+        SUB_FN => sub,
+        MUL_FN => mul,
+        _ => return None,
+    };
+
+    Some(FnInfo {
+        kind: FnKind::BuiltIn(sig, fn_handle),
+        span: Span::default(),
+    })
+}
+
+/// a function returns the fp2 functions
+pub fn fp2_fns<B: Backend>() -> Vec<FnInfo<B>> {
+    FP2_SIGS
+        .iter()
+        .map(|sig| get_fp2_fn(sig).unwrap())
+        .collect()
+}
+
+/// The definition of the `Fp2` struct.
+pub fn fp2_struct() -> StructInfo {
+    StructInfo {
+        name: FP2_STRUCT.to_string(),
+        fields: vec![
+            ("re".to_string(), TyKind::Field),
+            ("im".to_string(), TyKind::Field),
+        ],
+        methods: HashMap::new(),
+    }
+}
+
+/// Points the `Fp2` type of a signature to this module.
+fn qualify(typ: &mut TyKind) {
+    if let TyKind::Custom { module, name } = typ {
+        if name == FP2_STRUCT {
+            *module = ModulePath::Absolute(UserRepo::new(QUALIFIED_FP2));
+        }
+    }
+}
+
+/// Returns the real and imaginary parts of an `Fp2` argument.
+fn parts<B: Backend>(
+    var_info: &VarInfo<B::Field, B::Var>,
+) -> (
+    &ConstOrCell<B::Field, B::Var>,
+    &ConstOrCell<B::Field, B::Var>,
+) {
+    let cvars = &var_info.var.cvars;
+    assert_eq!(cvars.len(), 2);
+    (&cvars[0], &cvars[1])
+}
+
+/// Creates `re + im * i`.
+fn new<B: Backend>(
+    _compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get two field elements
+    assert_eq!(vars.len(), 2);
+
+    let re = vars[0].var[0].clone();
+    let im = vars[1].var[0].clone();
+
+    Ok(Some(Var::new(vec![re, im], span)))
+}
+
+/// Returns `(a + bi) + (c + di) = (a + c) + (b + d)i`.
+fn add<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    assert_eq!(vars.len(), 2);
+    let (aa, bb) = parts::<B>(&vars[0]);
+    let (cc, dd) = parts::<B>(&vars[1]);
+
+    let re = field::add(compiler, aa, cc, span)[0].clone();
+    let im = field::add(compiler, bb, dd, span)[0].clone();
+
+    Ok(Some(Var::new(vec![re, im], span)))
+}
+
+/// Returns `(a + bi) - (c + di) = (a - c) + (b - d)i`.
+fn sub<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    assert_eq!(vars.len(), 2);
+    let (aa, bb) = parts::<B>(&vars[0]);
+    let (cc, dd) = parts::<B>(&vars[1]);
+
+    let re = field::sub(compiler, aa, cc, span)[0].clone();
+    let im = field::sub(compiler, bb, dd, span)[0].clone();
+
+    Ok(Some(Var::new(vec![re, im], span)))
+}
+
+/// Returns `(a + bi)(c + di) = (ac - bd) + (ad + bc)i`.
+/// This costs four multiplications and two additions,
+/// which takes fewer gates than Karatsuba's three multiplications and five additions.
+fn mul<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    assert_eq!(vars.len(), 2);
+    let (aa, bb) = parts::<B>(&vars[0]);
+    let (cc, dd) = parts::<B>(&vars[1]);
+
+    let ac = field::mul(compiler, aa, cc, span);
+    let bd = field::mul(compiler, bb, dd, span);
+    let ad = field::mul(compiler, aa, dd, span);
+    let bc = field::mul(compiler, bb, cc, span);
+
+    let re = field::sub(compiler, &ac[0], &bd[0], span)[0].clone();
+    let im = field::add(compiler, &ad[0], &bc[0], span)[0].clone();
+
+    Ok(Some(Var::new(vec![re, im], span)))
+}
@@ -0,0 +1,211 @@
+//! The Keccak-256 hash function used by Ethereum
+//! (the original Keccak submission, which pads differently than the standardized SHA3-256),
+//! constrained with field operations only (no custom gate).
+//!
+//! Each 64-bit lane of the state is represented by its 64 little-endian bits,
+//! so that the rotations of the permutation are free and only XORs, ANDs and NOTs are constrained.
+
+use ark_ff::{One, Zero};
+
+use crate::{
+    backends::Backend,
+    circuit_writer::CircuitWriter,
+    constants::Span,
+    constraints::{boolean, field},
+    error::Result,
+    var::ConstOrCell,
+};
+
+use super::blake2s::xor_bits;
+
+/// The number of bits in a lane.
+pub(crate) const LANE_BITS: usize = 64;
+
+/// The number of lanes in the state (a 5x5 matrix).
+pub(crate) const STATE_LANES: usize = 25;
+
+/// The number of bytes absorbed per permutation (1088 bits for a 256-bit digest).
+pub(crate) const RATE_BYTES: usize = 136;
+
+/// The number of rounds of the Keccak-f[1600] permutation.
+pub(crate) const ROUNDS: usize = 24;
+
+/// The number of bytes in the digest.
+const DIGEST_BYTES: usize = 32;
+
+/// The round constants XORed into the first lane.
+const ROUND_CONSTANTS: [u64; ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// The rotation offsets of the lanes, indexed by `[x][y]`.
+const ROTATIONS: [[u32; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// The bits of a lane (little-endian).
+type Lane<B> = Vec<ConstOrCell<<B as Backend>::Field, <B as Backend>::Var>>;
+
+/// The `num_bits` bits (little-endian) of a constant.
+fn constant_bits<B: Backend>(value: u64, num_bits: usize) -> Lane<B> {
+    (0..num_bits)
+        .map(|ii| {
+            ConstOrCell::Const(if (value >> ii) & 1 == 1 {
+                B::Field::one()
+            } else {
+                B::Field::zero()
+            })
+        })
+        .collect()
+}
+
+fn xor_lanes<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    lhs: &[ConstOrCell<B::Field, B::Var>],
+    rhs: &[ConstOrCell<B::Field, B::Var>],
+    span: Span,
+) -> Lane<B> {
+    lhs.iter()
+        .zip(rhs)
+        .map(|(aa, bb)| xor_bits(compiler, aa, bb, span))
+        .collect()
+}
+
+/// Rotates a lane to the left (towards the most significant bits).
+fn rotate_left<B: Backend>(lane: &[ConstOrCell<B::Field, B::Var>], shift: u32) -> Lane<B> {
+    let mut bits = lane.to_vec();
+    bits.rotate_right(shift as usize);
+    bits
+}
+
+/// The Keccak-f[1600] permutation, on a state indexed by `x + 5 * y`.
+fn permute<B: Backend>(compiler: &mut CircuitWriter<B>, state: &mut [Lane<B>], span: Span) {
+    for round_constant in ROUND_CONSTANTS {
+        // θ: XOR each lane with the parities of two neighbouring columns
+        let mut parities = Vec::with_capacity(5);
+        for xx in 0..5 {
+            let mut parity = state[xx].clone();
+            for yy in 1..5 {
+                parity = xor_lanes(compiler, &parity, &state[xx + 5 * yy], span);
+            }
+            parities.push(parity);
+        }
+
+        for xx in 0..5 {
+            let rotated = rotate_left::<B>(&parities[(xx + 1) % 5], 1);
+            let diff = xor_lanes(compiler, &parities[(xx + 4) % 5], &rotated, span);
+            for yy in 0..5 {
+                state[xx + 5 * yy] = xor_lanes(compiler, &state[xx + 5 * yy], &diff, span);
+            }
+        }
+
+        // ρ and π: rotate each lane and move it to `(y, 2x + 3y)`
+        let mut moved = state.to_vec();
+        for xx in 0..5 {
+            for yy in 0..5 {
+                moved[yy + 5 * ((2 * xx + 3 * yy) % 5)] =
+                    rotate_left::<B>(&state[xx + 5 * yy], ROTATIONS[xx][yy]);
+            }
+        }
+
+        // χ: `a ^ (!b & c)` along the rows
+        for yy in 0..5 {
+            for xx in 0..5 {
+                let lhs = &moved[xx + 5 * yy];
+                let mid = &moved[(xx + 1) % 5 + 5 * yy];
+                let rhs = &moved[(xx + 2) % 5 + 5 * yy];
+
+                state[xx + 5 * yy] = (0..LANE_BITS)
+                    .map(|ii| {
+                        let not_mid = boolean::not(compiler, &mid[ii], span);
+                        let masked = boolean::and(compiler, &not_mid[0], &rhs[ii], span);
+                        xor_bits(compiler, &lhs[ii], &masked[0], span)
+                    })
+                    .collect();
+            }
+        }
+
+        // ι: XOR the round constant into the first lane
+        state[0] = xor_lanes(
+            compiler,
+            &state[0],
+            &constant_bits::<B>(round_constant, LANE_BITS),
+            span,
+        );
+    }
+}
+
+/// Hashes a message made of bytes, and returns the 32-byte digest as two 128-bit halves,
+/// each interpreted in big-endian (so that the digest is `digest[0] * 2^128 + digest[1]`).
+/// This fails to produce a valid witness if one of the inputs doesn't fit in a byte.
+pub(crate) fn hash<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    input: &[ConstOrCell<B::Field, B::Var>],
+    span: Span,
+) -> Result<Vec<ConstOrCell<B::Field, B::Var>>> {
+    let mut bytes = Vec::with_capacity(input.len());
+    for byte in input {
+        bytes.push(field::to_bits(compiler, byte, 8, span)?);
+    }
+
+    // the message is padded with `0x01 0x00 .. 0x00 0x80` to a multiple of the rate
+    let num_blocks = input.len() / RATE_BYTES + 1;
+    bytes.push(constant_bits::<B>(0x01, 8));
+    bytes.resize_with(num_blocks * RATE_BYTES, || constant_bits::<B>(0, 8));
+    let last = bytes.last_mut().unwrap();
+    last[7] = ConstOrCell::Const(B::Field::one());
+
+    // the bytes of a lane are in little-endian, like the bits of each byte
+    let bits: Vec<_> = bytes.into_iter().flatten().collect();
+
+    let mut state: Vec<Lane<B>> = (0..STATE_LANES)
+        .map(|_| constant_bits::<B>(0, LANE_BITS))
+        .collect();
+    for block in bits.chunks(RATE_BYTES * 8) {
+        for (lane, block_lane) in state.iter_mut().zip(block.chunks(LANE_BITS)) {
+            *lane = xor_lanes(compiler, lane, block_lane, span);
+        }
+
+        permute(compiler, &mut state, span);
+    }
+
+    // the digest is made of the first bytes of the state
+    let digest: Vec<_> = state.into_iter().flatten().take(DIGEST_BYTES * 8).collect();
+    let halves = digest
+        .chunks(DIGEST_BYTES * 4)
+        .map(|half| {
+            // reverse the order of the bytes, but not of their bits
+            let bits: Vec<_> = half.chunks(8).rev().flatten().cloned().collect();
+            field::from_bits(compiler, &bits, span)
+        })
+        .collect();
+
+    Ok(halves)
+}
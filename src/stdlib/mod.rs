@@ -1,24 +1,31 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, sync::Arc};
 
-use ark_ff::One;
+use ark_ff::{One, Zero};
+use num_bigint::BigUint;
 use once_cell::sync::Lazy;
 
 use crate::{
-    backends::Backend,
+    backends::{Backend, BackendField},
     circuit_writer::{CircuitWriter, VarInfo},
+    cli::packages::UserRepo,
     constants::Span,
+    constraints::{boolean, field},
     error::{Error, ErrorKind, Result},
     imports::FnKind,
     lexer::Token,
     parser::{
-        types::{FnSig, TyKind},
-        ParserCtx,
+        types::{FnSig, ModulePath, TyKind},
+        Expr, ExprKind, ParserCtx,
     },
     type_checker::FnInfo,
-    var::{ConstOrCell, Var},
+    var::{ConstOrCell, Value, Var},
+    witness::WitnessEnv,
 };
 
+pub(crate) mod blake2s;
 pub mod crypto;
+pub mod fp2;
+pub(crate) mod keccak;
 
 //
 // Builtins or utils (imported by default)
@@ -28,10 +35,56 @@ pub mod crypto;
 pub const QUALIFIED_BUILTINS: &str = "std/builtins";
 
 const ASSERT_FN: &str = "assert(condition: Bool)";
+const DEBUG_ASSERT_FN: &str = "debug_assert(condition: Bool)";
 const ASSERT_EQ_FN: &str = "assert_eq(lhs: Field, rhs: Field)";
+const ASSERT_LT_FN: &str = "assert_lt(lhs: Field, bound: Field)";
+const ASSERT_IF_FN: &str = "assert_if(cond: Bool, check: Bool)";
+const XOR_FN: &str = "xor(lhs: Field, rhs: Field, bits: Field) -> Field";
+const LESS_THAN_FN: &str = "less_than(lhs: Field, rhs: Field, bits: Field) -> Bool";
+const LESS_EQUAL_FN: &str = "less_equal(lhs: Field, rhs: Field, bits: Field) -> Bool";
+const AS_FIELD_FN: &str = "as_field(value: Bool) -> Field";
+const AS_BOOL_FN: &str = "as_bool(value: Field) -> Bool";
+const LOOKUP_FN: &str = "lookup(table_id: Field, key: Field) -> Field";
+const DIV_FN: &str = "div(lhs: Field, rhs: Field) -> Field";
+const SQRT_FN: &str = "sqrt(value: Field) -> Field";
+const IS_SQUARE_FN: &str = "is_square(value: Field) -> Bool";
+const POW_FN: &str = "pow(base: Field, exp: Field) -> Field";
+const RANGE_CHECK64_FN: &str = "range_check64(value: Field)";
+const TO_BITS_FN: &str = "to_bits[N](value: Field, const N: Field) -> [Bool; N]";
+const FROM_BITS_FN: &str = "from_bits[N](bits: [Bool; N]) -> Field";
 
 /// List of builtin function signatures.
-pub const BUILTIN_SIGS: &[&str] = &[ASSERT_FN, ASSERT_EQ_FN];
+pub const BUILTIN_SIGS: &[&str] = &[
+    ASSERT_FN,
+    DEBUG_ASSERT_FN,
+    ASSERT_EQ_FN,
+    ASSERT_LT_FN,
+    ASSERT_IF_FN,
+    XOR_FN,
+    LESS_THAN_FN,
+    LESS_EQUAL_FN,
+    AS_FIELD_FN,
+    AS_BOOL_FN,
+    LOOKUP_FN,
+    DIV_FN,
+    SQRT_FN,
+    IS_SQUARE_FN,
+    POW_FN,
+    RANGE_CHECK64_FN,
+    TO_BITS_FN,
+    FROM_BITS_FN,
+];
+
+/// `fold(arr, init, f)` computes `f(..f(f(init, arr[0]), arr[1]).., arr[N - 1])`,
+/// where `f` is the name of a function with the signature `(acc, elem) -> acc`.
+/// This can't be expressed as a signature, so the type checker and the circuit writer
+/// handle calls to `fold` themselves (see [is_fold]).
+const FOLD_FN: &str = "fold";
+
+/// `select(cond, a, b)` returns `a` if `cond` is true and `b` otherwise,
+/// where `a` and `b` can have any type (as long as it's the same), and are selected field by field.
+/// Like `fold`, this can't be expressed as a signature (see [is_select]).
+const SELECT_FN: &str = "select";
 
 // Unique set of builtin function names, derived from function signatures.
 pub static BUILTIN_FN_NAMES: Lazy<HashSet<String>> = Lazy::new(|| {
@@ -43,9 +96,29 @@ pub static BUILTIN_FN_NAMES: Lazy<HashSet<String>> = Lazy::new(|| {
             let sig = FnSig::parse(ctx, &mut tokens).unwrap();
             sig.name.value
         })
+        .chain([FOLD_FN.to_string(), SELECT_FN.to_string()])
         .collect()
 });
 
+/// Returns true if a (resolved) function call is a call to the `fold` builtin.
+pub(crate) fn is_fold(module: &ModulePath, fn_name: &str) -> bool {
+    fn_name == FOLD_FN
+        && matches!(module, ModulePath::Absolute(user_repo) if user_repo == &UserRepo::new(QUALIFIED_BUILTINS))
+}
+
+/// Returns true if a (resolved) function call is a call to the `debug_assert` builtin,
+/// which is only compiled at optimization levels 0 and 1.
+pub(crate) fn is_debug_assert(module: &ModulePath, fn_name: &str) -> bool {
+    fn_name == "debug_assert"
+        && matches!(module, ModulePath::Absolute(user_repo) if user_repo == &UserRepo::new(QUALIFIED_BUILTINS))
+}
+
+/// Returns true if a (resolved) function call is a call to the `select` builtin.
+pub(crate) fn is_select(module: &ModulePath, fn_name: &str) -> bool {
+    fn_name == SELECT_FN
+        && matches!(module, ModulePath::Absolute(user_repo) if user_repo == &UserRepo::new(QUALIFIED_BUILTINS))
+}
+
 pub fn get_builtin_fn<B: Backend>(name: &str) -> Option<FnInfo<B>> {
     let ctx = &mut ParserCtx::default();
     let mut tokens = Token::parse(0, name).unwrap();
@@ -53,7 +126,24 @@ pub fn get_builtin_fn<B: Backend>(name: &str) -> Option<FnInfo<B>> {
 
     let fn_handle = match name {
         ASSERT_FN => assert,
+        // stripped by the circuit writer at optimization level 2 and above (see [is_debug_assert])
+        DEBUG_ASSERT_FN => assert,
         ASSERT_EQ_FN => assert_eq,
+        ASSERT_LT_FN => assert_lt,
+        ASSERT_IF_FN => assert_if,
+        XOR_FN => xor,
+        LESS_THAN_FN => less_than,
+        LESS_EQUAL_FN => less_equal,
+        AS_FIELD_FN => as_field,
+        AS_BOOL_FN => as_bool,
+        LOOKUP_FN => lookup,
+        DIV_FN => div,
+        SQRT_FN => sqrt,
+        IS_SQUARE_FN => is_square,
+        POW_FN => pow,
+        RANGE_CHECK64_FN => range_check64,
+        TO_BITS_FN => to_bits,
+        FROM_BITS_FN => from_bits,
         _ => return None,
     };
 
@@ -63,6 +153,33 @@ pub fn get_builtin_fn<B: Backend>(name: &str) -> Option<FnInfo<B>> {
     })
 }
 
+/// The builtins that can take a message as last argument (e.g. `assert(cond, "message")`),
+/// which is reported if the assertion is not satisfied by the witness.
+const ASSERTIONS_WITH_MESSAGE: &[&str] = &[
+    "assert",
+    "debug_assert",
+    "assert_eq",
+    "assert_lt",
+    "assert_if",
+];
+
+/// Splits the message of a call to a builtin assertion, if there's one, from its other arguments.
+pub(crate) fn split_assert_message<'a>(
+    fn_name: &str,
+    args: &'a [Expr],
+) -> (&'a [Expr], Option<&'a str>) {
+    match args.split_last() {
+        Some((
+            Expr {
+                kind: ExprKind::StringLiteral(message),
+                ..
+            },
+            rest,
+        )) if ASSERTIONS_WITH_MESSAGE.contains(&fn_name) => (rest, Some(message)),
+        _ => (args, None),
+    }
+}
+
 /// a function returns builtin functions
 pub fn builtin_fns<B: Backend>() -> Vec<FnInfo<B>> {
     BUILTIN_SIGS
@@ -82,15 +199,21 @@ fn assert_eq<B: Backend>(
     let lhs_info = &vars[0];
     let rhs_info = &vars[1];
 
-    // they are both of type field
-    if !matches!(lhs_info.typ, Some(TyKind::Field | TyKind::BigInt)) {
+    // they are both of type field (integers are field elements too)
+    if !matches!(
+        lhs_info.typ,
+        Some(TyKind::Field | TyKind::BigInt | TyKind::U32 | TyKind::U64)
+    ) {
         panic!(
             "the lhs of assert_eq must be of type Field or BigInt. It was of type {:?}",
             lhs_info.typ
         );
     }
 
-    if !matches!(rhs_info.typ, Some(TyKind::Field | TyKind::BigInt)) {
+    if !matches!(
+        rhs_info.typ,
+        Some(TyKind::Field | TyKind::BigInt | TyKind::U32 | TyKind::U64)
+    ) {
         panic!(
             "the rhs of assert_eq must be of type Field or BigInt. It was of type {:?}",
             rhs_info.typ
@@ -106,29 +229,63 @@ fn assert_eq<B: Backend>(
     assert_eq!(rhs_var.len(), 1);
     let rhs_cvar = &rhs_var[0];
 
-    match (lhs_cvar, rhs_cvar) {
+    // within a branch of an `if`/`else`, we only enforce `cond * (lhs - rhs) = 0`
+    let branch_cond = compiler.branch_condition(span);
+    constrain_eq(compiler, lhs_cvar, rhs_cvar, branch_cond, span)?;
+
+    Ok(None)
+}
+
+/// Constrains two field elements to be equal,
+/// only when the branch is taken if a branch condition is given (see [CircuitWriter::assert_eq_cell_if]).
+fn constrain_eq<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    lhs: &ConstOrCell<B::Field, B::Var>,
+    rhs: &ConstOrCell<B::Field, B::Var>,
+    branch_cond: Option<B::Var>,
+    span: Span,
+) -> Result<()> {
+    match (lhs, rhs) {
         // two constants
         (ConstOrCell::Const(a), ConstOrCell::Const(b)) => {
             if a != b {
-                return Err(Error::new(
-                    "constraint-generation",
-                    ErrorKind::AssertionFailed,
-                    span,
-                ));
+                match branch_cond {
+                    // the branch can't be taken
+                    Some(branch_cond) => {
+                        let zero = ConstOrCell::Const(B::Field::zero());
+                        compiler.assert_eq_cell_if(None, &branch_cond, &zero, span);
+                    }
+                    None => {
+                        return Err(Error::new(
+                            "constraint-generation",
+                            ErrorKind::AssertionFailed,
+                            span,
+                        ))
+                    }
+                }
             }
         }
 
-        // a const and a var
-        (ConstOrCell::Const(cst), ConstOrCell::Cell(cvar))
-        | (ConstOrCell::Cell(cvar), ConstOrCell::Const(cst)) => {
-            compiler.backend.assert_eq_const(cvar, *cst, span)
-        }
-        (ConstOrCell::Cell(lhs), ConstOrCell::Cell(rhs)) => {
-            compiler.backend.assert_eq_var(lhs, rhs, span)
+        // a var and a const or a var
+        (ConstOrCell::Const(_), ConstOrCell::Cell(cvar)) => {
+            compiler.assert_eq_cell_if(branch_cond, cvar, lhs, span)
         }
+        (ConstOrCell::Cell(cvar), _) => compiler.assert_eq_cell_if(branch_cond, cvar, rhs, span),
     }
 
-    Ok(None)
+    Ok(())
+}
+
+/// Asserts that a value is zero (only when the current branch is taken, if any),
+/// e.g. the condition of a `while` loop once it's unrolled.
+pub(crate) fn assert_zero<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    value: &ConstOrCell<B::Field, B::Var>,
+    span: Span,
+) -> Result<()> {
+    let branch_cond = compiler.branch_condition(span);
+    let zero = ConstOrCell::Const(B::Field::zero());
+    constrain_eq(compiler, value, &zero, branch_cond, span)
 }
 
 /// Asserts that a condition is true.
@@ -149,15 +306,1098 @@ fn assert<B: Backend>(
     assert_eq!(var.len(), 1);
     let cond = &var[0];
 
-    match cond {
-        ConstOrCell::Const(cst) => {
-            assert!(cst.is_one());
+    // within a branch of an `if`/`else`, we only enforce `branch_cond * (cond - 1) = 0`
+    let branch_cond = compiler.branch_condition(span);
+    let one = ConstOrCell::Const(B::Field::one());
+    constrain_eq(compiler, cond, &one, branch_cond, span)?;
+
+    Ok(None)
+}
+
+/// Asserts that `check` is true whenever `cond` is true.
+fn assert_if<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get two vars
+    assert_eq!(vars.len(), 2);
+
+    // of type bool
+    assert!(matches!(vars[0].typ, Some(TyKind::Bool)));
+    assert!(matches!(vars[1].typ, Some(TyKind::Bool)));
+
+    // of only one field element
+    assert_eq!(vars[0].var.len(), 1);
+    assert_eq!(vars[1].var.len(), 1);
+    let cond = &vars[0].var[0];
+    let check = &vars[1].var[0];
+
+    // `cond * (1 - check) = 0` is enforced as `cond * check = cond`,
+    // which only takes a multiplication
+    let gated = field::mul(compiler, cond, check, span);
+
+    // within a branch of an `if`/`else`, we only enforce `branch_cond * (cond * check - cond) = 0`
+    let branch_cond = compiler.branch_condition(span);
+    constrain_eq(compiler, &gated[0], cond, branch_cond, span)?;
+
+    Ok(None)
+}
+
+/// Asserts that a field element is strictly smaller than a constant bound.
+fn assert_lt<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a field element and a bound
+    assert_eq!(vars.len(), 2);
+
+    let lhs_var = &vars[0].var;
+    assert_eq!(lhs_var.len(), 1);
+
+    // the bound must be known at compile time
+    let bound: BigUint = vars[1]
+        .var
+        .constant()
+        .ok_or_else(|| compiler.error(ErrorKind::ExpectedConstantArgument("bound"), span))?
+        .into();
+
+    field::assert_lt_const(compiler, &lhs_var[0], &bound, span)?;
+
+    Ok(None)
+}
+
+/// Computes the bitwise XOR of two field elements that fit in `bits` bits.
+fn xor<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get two field elements and a number of bits
+    assert_eq!(vars.len(), 3);
+
+    let lhs_var = &vars[0].var;
+    assert_eq!(lhs_var.len(), 1);
+
+    let rhs_var = &vars[1].var;
+    assert_eq!(rhs_var.len(), 1);
+
+    // the number of bits must be known at compile time
+    let num_bits: BigUint = vars[2]
+        .var
+        .constant()
+        .ok_or_else(|| compiler.error(ErrorKind::ExpectedConstantArgument("bits"), span))?
+        .into();
+    let num_bits: usize = num_bits.try_into().unwrap_or(usize::MAX);
+
+    // decompose both operands
+    // (this fails if one of them doesn't fit in `num_bits` bits)
+    let lhs_bits = field::to_bits(compiler, &lhs_var[0], num_bits, span)?;
+    let rhs_bits = field::to_bits(compiler, &rhs_var[0], num_bits, span)?;
+
+    // xor each pair of bits: a + b - 2ab
+    let minus_two = ConstOrCell::Const(-(B::Field::one() + B::Field::one()));
+    let mut res_bits = Vec::with_capacity(num_bits);
+    for (aa, bb) in lhs_bits.iter().zip(&rhs_bits) {
+        let sum = field::add(compiler, aa, bb, span);
+        let prod = field::mul(compiler, aa, bb, span);
+        let prod = field::mul(compiler, &prod[0], &minus_two, span);
+        let res = field::add(compiler, &sum[0], &prod[0], span);
+        res_bits.push(res[0].clone());
+    }
+
+    // recompose the result
+    let res = field::from_bits(compiler, &res_bits, span);
+
+    Ok(Some(Var::new_cvar(res, span)))
+}
+
+/// Returns a boolean set to one if `lhs < rhs` when both are interpreted as `bits`-bit unsigned integers.
+fn less_than<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    compare(compiler, vars, true, span)
+}
+
+/// Returns a boolean set to one if `lhs <= rhs` when both are interpreted as `bits`-bit unsigned integers.
+fn less_equal<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    compare(compiler, vars, false, span)
+}
+
+/// Compares two `bits`-bit unsigned integers, strictly or not.
+fn compare<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    strict: bool,
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get two field elements and a number of bits
+    assert_eq!(vars.len(), 3);
+
+    let lhs_var = &vars[0].var;
+    assert_eq!(lhs_var.len(), 1);
+
+    let rhs_var = &vars[1].var;
+    assert_eq!(rhs_var.len(), 1);
+
+    // the number of bits must be known at compile time
+    let num_bits: BigUint = vars[2]
+        .var
+        .constant()
+        .ok_or_else(|| compiler.error(ErrorKind::ExpectedConstantArgument("bits"), span))?
+        .into();
+    let num_bits: usize = num_bits.try_into().unwrap_or(usize::MAX);
+
+    // both operands must fit in `num_bits` bits
+    field::to_bits(compiler, &lhs_var[0], num_bits, span)?;
+    field::to_bits(compiler, &rhs_var[0], num_bits, span)?;
+
+    // `lhs < rhs` iff `lhs - rhs` borrows, and `lhs <= rhs` iff `rhs - lhs` doesn't
+    let (minuend, subtrahend) = if strict {
+        (&lhs_var[0], &rhs_var[0])
+    } else {
+        (&rhs_var[0], &lhs_var[0])
+    };
+
+    // `2^num_bits + minuend - subtrahend` fits in `num_bits + 1` bits,
+    // and its most significant bit is set iff the subtraction doesn't borrow
+    let two_to_bits = B::Field::try_from(BigUint::one() << num_bits)
+        .map_err(|_| compiler.error(ErrorKind::InvalidBitSize(num_bits), span))?;
+    let diff = field::sub(compiler, minuend, subtrahend, span);
+    let diff = field::add(compiler, &diff[0], &ConstOrCell::Const(two_to_bits), span);
+    let diff_bits = field::to_bits(compiler, &diff[0], num_bits + 1, span)?;
+    let no_borrow = &diff_bits[num_bits];
+
+    let res = if strict {
+        boolean::not(compiler, no_borrow, span)
+    } else {
+        Var::new_cvar(no_borrow.clone(), span)
+    };
+
+    Ok(Some(res))
+}
+
+/// Casts a boolean to a field element (0 or 1).
+/// The boolean is already constrained, so this doesn't create any constraint.
+fn as_field<B: Backend>(
+    _compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a single boolean
+    assert_eq!(vars.len(), 1);
+
+    let var = &vars[0].var;
+    assert_eq!(var.len(), 1);
+
+    Ok(Some(Var::new_cvar(var[0].clone(), span)))
+}
+
+/// Casts a field element to a boolean, constraining it to be 0 or 1.
+fn as_bool<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a single field element
+    assert_eq!(vars.len(), 1);
+
+    let var = &vars[0].var;
+    assert_eq!(var.len(), 1);
+    let value = &var[0];
+
+    match value {
+        ConstOrCell::Const(cst) if !boolean::is_valid(*cst) => {
+            return Err(compiler.error(ErrorKind::NotABoolean, span));
         }
-        ConstOrCell::Cell(cvar) => {
-            let one = B::Field::one();
-            compiler.backend.assert_eq_const(cvar, one, span);
+        ConstOrCell::Const(_) => (),
+        // x * (x - 1) = 0
+        ConstOrCell::Cell(_) => boolean::check(compiler, value, span),
+    }
+
+    Ok(Some(Var::new_cvar(value.clone(), span)))
+}
+
+/// Returns the value associated to `key` in a lookup table,
+/// and fails to produce a valid witness if the key is not in the table.
+fn lookup<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a table id and a key
+    assert_eq!(vars.len(), 2);
+
+    // the table must be known at compile time
+    let table_id: BigUint = vars[0]
+        .var
+        .constant()
+        .ok_or_else(|| compiler.error(ErrorKind::ExpectedConstantArgument("table_id"), span))?
+        .into();
+    let table_id: u32 = table_id.try_into().unwrap_or(u32::MAX);
+
+    let key_var = &vars[1].var;
+    assert_eq!(key_var.len(), 1);
+
+    // a lookup can't be disabled, so it would also be enforced in the branch that is not taken
+    if !compiler.branch_conditions.is_empty() {
+        return Err(compiler.error(ErrorKind::LookupInBranch, span));
+    }
+
+    let key = match &key_var[0] {
+        ConstOrCell::Const(cst) => {
+            compiler
+                .backend
+                .add_constant(Some("hardcode the key of a lookup"), *cst, span)
         }
+        ConstOrCell::Cell(cvar) => cvar.clone(),
+    };
+
+    let value = compiler.backend.lookup(table_id, &key, span)?;
+
+    Ok(Some(Var::new_var(value, span)))
+}
+
+/// Checks that a value fits in 64 bits with the native range-check gate of the backend.
+fn range_check64<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a single field element
+    assert_eq!(vars.len(), 1);
+
+    let value_var = &vars[0].var;
+    assert_eq!(value_var.len(), 1);
+
+    // like a lookup, a range check can't be disabled
+    if !compiler.branch_conditions.is_empty() {
+        return Err(compiler.error(ErrorKind::RangeCheckInBranch, span));
     }
 
+    let value = match &value_var[0] {
+        ConstOrCell::Const(cst) => {
+            compiler
+                .backend
+                .add_constant(Some("hardcode the value of a range check"), *cst, span)
+        }
+        ConstOrCell::Cell(cvar) => cvar.clone(),
+    };
+
+    compiler.backend.range_check64(&value, span)?;
+
     Ok(None)
 }
+
+/// Decomposes a field element into `N` bits, starting with the least significant one.
+/// The bits are constrained to be booleans and to recompose to the value,
+/// which fails to produce a witness if the value doesn't fit in `N` bits.
+fn to_bits<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a field element and a number of bits
+    assert_eq!(vars.len(), 2);
+
+    let value_var = &vars[0].var;
+    assert_eq!(value_var.len(), 1);
+
+    // the number of bits was already needed to type the result, so it is a constant
+    let num_bits: BigUint = vars[1]
+        .var
+        .constant()
+        .ok_or_else(|| compiler.error(ErrorKind::ExpectedConstantArgument("N"), span))?
+        .into();
+    let num_bits: usize = num_bits.try_into().unwrap_or(usize::MAX);
+
+    let bits = field::to_bits(compiler, &value_var[0], num_bits, span)?;
+
+    Ok(Some(Var::new(bits, span)))
+}
+
+/// Recomposes a field element from its bits, starting with the least significant one.
+fn from_bits<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get an array of booleans
+    assert_eq!(vars.len(), 1);
+
+    let res = field::from_bits(compiler, &vars[0].var.cvars, span);
+
+    Ok(Some(Var::new_cvar(res, span)))
+}
+
+/// Divides two field elements.
+/// The inverse of `rhs` is computed during witness generation and constrained with `rhs * inv = 1`,
+/// which fails to produce a witness if `rhs` is zero, and the quotient is the output of `lhs * inv`.
+/// Within a branch, the constraint on the inverse only holds when the branch is taken.
+fn div<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get two field elements
+    assert_eq!(vars.len(), 2);
+
+    let lhs_var = &vars[0].var;
+    assert_eq!(lhs_var.len(), 1);
+    let lhs = lhs_var[0].clone();
+
+    let rhs_var = &vars[1].var;
+    assert_eq!(rhs_var.len(), 1);
+    let rhs = rhs_var[0].clone();
+
+    // dividing by a constant is a multiplication by its inverse
+    if let ConstOrCell::Const(cst) = rhs {
+        let inv = cst
+            .inverse()
+            .ok_or_else(|| compiler.error(ErrorKind::DivisionByZero, span))?;
+        return Ok(Some(field::mul(
+            compiler,
+            &lhs,
+            &ConstOrCell::Const(inv),
+            span,
+        )));
+    }
+
+    // within a branch that is not taken, the divisor can be zero
+    let branch_cond = compiler.branch_condition(span);
+
+    // the inverse of the divisor, witnessing that it's not zero
+    let hint_cond = branch_cond.clone();
+    let inv = compiler.backend.new_internal_var(
+        Value::Hint(Arc::new(
+            move |backend: &B, env: &mut WitnessEnv<B::Field>| {
+                if let Some(cond) = &hint_cond {
+                    if backend.compute_var(env, cond)?.is_zero() {
+                        return Ok(B::Field::zero());
+                    }
+                }
+
+                let rhs = match &rhs {
+                    ConstOrCell::Const(cst) => *cst,
+                    ConstOrCell::Cell(cvar) => backend.compute_var(env, cvar)?,
+                };
+                rhs.inverse()
+                    .ok_or_else(|| Error::new("runtime", ErrorKind::DivisionByZero, span))
+            },
+        )),
+        span,
+    );
+
+    // rhs * inv = 1
+    let one = ConstOrCell::Const(B::Field::one());
+    let prod = field::mul(compiler, &ConstOrCell::Cell(inv.clone()), &rhs_var[0], span);
+    constrain_eq(compiler, &prod[0], &one, branch_cond, span)?;
+
+    // quotient = lhs * inv
+    Ok(Some(field::mul(
+        compiler,
+        &lhs,
+        &ConstOrCell::Cell(inv),
+        span,
+    )))
+}
+
+/// Returns the square root of a field element whose integer representation is even.
+fn even_sqrt<F: BackendField>(value: F) -> Option<F> {
+    let root = value.sqrt()?;
+    let repr: BigUint = root.into();
+    Some(if repr.bit(0) { -root } else { root })
+}
+
+/// Returns a small quadratic non-residue of the field.
+fn non_residue<F: BackendField>() -> F {
+    (2u64..)
+        .map(F::from)
+        .find(|candidate| candidate.legendre().is_qnr())
+        .expect("every field has a non-residue")
+}
+
+/// Returns the square root of a field element, the one whose integer representation is even.
+/// The root is computed during witness generation and constrained with `root * root = value`,
+/// which fails to produce a witness if the value is not a square.
+/// Note that only the squaring is constrained, not the choice of the root.
+fn sqrt<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a single field element
+    assert_eq!(vars.len(), 1);
+
+    let var = &vars[0].var;
+    assert_eq!(var.len(), 1);
+    let value = var[0].clone();
+
+    let value_cell = match &value {
+        ConstOrCell::Const(cst) => {
+            let root =
+                even_sqrt(*cst).ok_or_else(|| compiler.error(ErrorKind::NotASquare, span))?;
+            return Ok(Some(Var::new_constant(root, span)));
+        }
+        ConstOrCell::Cell(cvar) => cvar.clone(),
+    };
+
+    // within a branch that is not taken, the value doesn't have to be a square
+    let branch_cond = compiler.branch_condition(span);
+
+    let hint_cond = branch_cond.clone();
+    let root = compiler.backend.new_internal_var(
+        Value::Hint(Arc::new(
+            move |backend: &B, env: &mut WitnessEnv<B::Field>| {
+                if let Some(cond) = &hint_cond {
+                    if backend.compute_var(env, cond)?.is_zero() {
+                        return Ok(B::Field::zero());
+                    }
+                }
+
+                let value = backend.compute_var(env, &value_cell)?;
+                even_sqrt(value).ok_or_else(|| Error::new("runtime", ErrorKind::NotASquare, span))
+            },
+        )),
+        span,
+    );
+
+    // root * root = value
+    let root = ConstOrCell::Cell(root);
+    let square = field::mul(compiler, &root, &root, span);
+    constrain_eq(compiler, &square[0], &value, branch_cond, span)?;
+
+    Ok(Some(Var::new_cvar(root, span)))
+}
+
+/// Returns a boolean set to one if a field element is a square (zero included).
+///
+/// With `n` a non-residue, exactly one of `value` and `n * value` is a square when `value` isn't zero.
+/// The witness contains the result `res` and a square root of `res * value + (1 - res) * n * value`,
+/// which can only be computed if `res` is correct (zero is forced to be a square separately).
+fn is_square<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a single field element
+    assert_eq!(vars.len(), 1);
+
+    let var = &vars[0].var;
+    assert_eq!(var.len(), 1);
+    let value = var[0].clone();
+
+    let value_cell = match &value {
+        ConstOrCell::Const(cst) => {
+            let res = if cst.legendre().is_qnr() {
+                B::Field::zero()
+            } else {
+                B::Field::one()
+            };
+            return Ok(Some(Var::new_constant(res, span)));
+        }
+        ConstOrCell::Cell(cvar) => cvar.clone(),
+    };
+
+    let non_residue: B::Field = non_residue();
+
+    let inputs = vec![ConstOrCell::Cell(value_cell)];
+    let res = compiler.backend.new_hint(
+        inputs.clone(),
+        |values: &[B::Field]| {
+            Ok(if values[0].legendre().is_qnr() {
+                B::Field::zero()
+            } else {
+                B::Field::one()
+            })
+        },
+        span,
+    );
+
+    let root = compiler.backend.new_hint(
+        inputs,
+        move |values: &[B::Field]| {
+            let square = if values[0].legendre().is_qnr() {
+                non_residue * values[0]
+            } else {
+                values[0]
+            };
+            Ok(square.sqrt().expect("one of them is a square"))
+        },
+        span,
+    );
+
+    // res is a boolean
+    let res = ConstOrCell::Cell(res);
+    boolean::check(compiler, &res, span);
+
+    // like the constraints of the gadgets used here, these are only conditioned with relaxed branches
+    let branch_cond = compiler.relaxed_branch_condition(span);
+
+    // root * root = n * value + res * (1 - n) * value
+    let root = ConstOrCell::Cell(root);
+    let square = field::mul(compiler, &root, &root, span);
+    let scaled = field::mul(compiler, &value, &ConstOrCell::Const(non_residue), span);
+    let selected = field::mul(compiler, &res, &value, span);
+    let selected = field::mul(
+        compiler,
+        &selected[0],
+        &ConstOrCell::Const(B::Field::one() - non_residue),
+        span,
+    );
+    let expected = field::add(compiler, &scaled[0], &selected[0], span);
+    constrain_eq(
+        compiler,
+        &square[0],
+        &expected[0],
+        branch_cond.clone(),
+        span,
+    )?;
+
+    // zero is a square: is_zero * (1 - res) = 0
+    let zero = Var::new_constant(B::Field::zero(), span);
+    let is_zero = field::equal(compiler, &Var::new_cvar(value, span), &zero, span);
+    let not_res = boolean::not(compiler, &res, span);
+    let zero_not_square = field::mul(compiler, &is_zero[0], &not_res[0], span);
+    let zero = ConstOrCell::Const(B::Field::zero());
+    constrain_eq(compiler, &zero_not_square[0], &zero, branch_cond, span)?;
+
+    Ok(Some(Var::new_cvar(res, span)))
+}
+
+/// Raises a field element to a constant power, with square-and-multiply:
+/// an exponent of `n` bits, `k` of which are set, costs `n - 1` squarings and `k - 1` multiplications.
+fn pow<B: Backend>(
+    compiler: &mut CircuitWriter<B>,
+    vars: &[VarInfo<B::Field, B::Var>],
+    span: Span,
+) -> Result<Option<Var<B::Field, B::Var>>> {
+    // we get a field element and an exponent
+    assert_eq!(vars.len(), 2);
+
+    let base_var = &vars[0].var;
+    assert_eq!(base_var.len(), 1);
+    let base = &base_var[0];
+
+    // the exponent must be known at compile time, as it determines the shape of the circuit
+    let exp: BigUint = vars[1]
+        .var
+        .constant()
+        .ok_or_else(|| compiler.error(ErrorKind::ExpectedConstantArgument("exp"), span))?
+        .into();
+
+    if exp.is_zero() {
+        return Ok(Some(Var::new_constant(B::Field::one(), span)));
+    }
+
+    // the most significant bit is set, so we start from the base
+    let mut res = base.clone();
+    for bit in (0..exp.bits() - 1).rev() {
+        res = field::mul(compiler, &res, &res, span)[0].clone();
+        if exp.bit(bit) {
+            res = field::mul(compiler, &res, base, span)[0].clone();
+        }
+    }
+
+    Ok(Some(Var::new_cvar(res, span)))
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ff::One;
+    use num_bigint::BigUint;
+
+    use crate::{
+        backends::kimchi::{KimchiVesta, VestaField},
+        circuit_writer::{CircuitOptions, CircuitWriter},
+        compiler::{typecheck_next_file_inner, Sources},
+        error::{ErrorKind, Result},
+        inputs::parse_inputs,
+        type_checker::TypeChecker,
+        witness::CompiledCircuit,
+    };
+
+    const XOR_CODE: &str = r#"fn main(pub expected: Field, lhs: Field, rhs: Field) {
+        let res = xor(lhs, rhs, 4);
+        assert_eq(res, expected);
+    }"#;
+
+    fn compile_code(code: &str) -> Result<(Sources, CompiledCircuit<KimchiVesta>)> {
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::new();
+        typecheck_next_file_inner(
+            &mut tast,
+            None,
+            &mut sources,
+            "inline_test.no".to_string(),
+            code.to_string(),
+            0,
+        )?;
+
+        let compiled_circuit = CircuitWriter::generate_circuit(
+            tast,
+            KimchiVesta::new(false),
+            CircuitOptions::default(),
+            None,
+        )?;
+        Ok((sources, compiled_circuit))
+    }
+
+    #[test]
+    fn test_xor() -> miette::Result<()> {
+        let (sources, compiled_circuit) = compile_code(XOR_CODE).unwrap();
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"expected": "6"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"lhs": "5", "rhs": "3"}"#).unwrap();
+
+        let (proof, full_public_inputs, _public_output) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xor_input_too_large() {
+        let (_sources, compiled_circuit) = compile_code(XOR_CODE).unwrap();
+
+        // 16 does not fit in 4 bits, so it can't be decomposed
+        let public_inputs = parse_inputs(r#"{"expected": "3"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"lhs": "16", "rhs": "3"}"#).unwrap();
+
+        let res = compiled_circuit.generate_witness(public_inputs, private_inputs);
+        assert!(res.is_err());
+    }
+
+    const BITS_CODE: &str = r#"fn main(pub xx: Field) -> Field {
+        let bits = to_bits(xx, 8);
+        assert_eq(from_bits(bits), xx);
+        return as_field(bits[0]);
+    }"#;
+
+    #[test]
+    fn test_to_bits_round_trip() -> miette::Result<()> {
+        let (sources, compiled_circuit) = compile_code(BITS_CODE).unwrap();
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        // the bits are little-endian
+        for (xx, lsb) in [(0, 0u64), (1, 1), (6, 0), (255, 1)] {
+            let public_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+            let (proof, full_public_inputs, public_output) =
+                prover_index.prove(&sources, public_inputs, parse_inputs("{}").unwrap(), false)?;
+            assert_eq!(public_output, vec![VestaField::from(lsb)]);
+            verifier_index.verify(full_public_inputs, proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_bits_value_too_large() {
+        let (_sources, compiled_circuit) = compile_code(BITS_CODE).unwrap();
+
+        // 256 does not fit in 8 bits, so the bits can't recompose to it
+        let public_inputs = parse_inputs(r#"{"xx": "256"}"#).unwrap();
+        let res = compiled_circuit.generate_witness(public_inputs, parse_inputs("{}").unwrap());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_to_bits_size_not_constant() {
+        let code = r#"fn main(pub xx: Field, nn: Field) {
+            let bits = to_bits(xx, nn);
+        }"#;
+
+        let res = compile_code(code);
+        assert!(matches!(
+            res.err().unwrap().kind,
+            ErrorKind::GenericNotConstant(name) if name == "N"
+        ));
+    }
+
+    const ASSERT_LT_CODE: &str = r#"fn main(pub xx: Field, yy: Field) {
+        assert_lt(xx, 10);
+        assert_lt(yy, 16);
+    }"#;
+
+    #[test]
+    fn test_assert_lt() {
+        let (_sources, compiled_circuit) = compile_code(ASSERT_LT_CODE).unwrap();
+
+        let witness = |xx: &str, yy: &str| {
+            let public_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+            let private_inputs = parse_inputs(&format!(r#"{{"yy": "{yy}"}}"#)).unwrap();
+            compiled_circuit.generate_witness(public_inputs, private_inputs)
+        };
+
+        // in range
+        assert!(witness("9", "15").is_ok());
+        assert!(witness("0", "0").is_ok());
+
+        // the bound itself is out of range (including when it's a power of two)
+        assert!(witness("10", "15").is_err());
+        assert!(witness("9", "16").is_err());
+
+        // a value above the bound of a power of two
+        assert!(witness("9", "100").is_err());
+
+        // a value that wraps around the modulus once shifted: -1 + (16 - 10) = 5 fits in 4 bits,
+        // but -1 itself doesn't
+        let minus_one: BigUint = (-VestaField::one()).into();
+        assert!(witness(&minus_one.to_string(), "0").is_err());
+    }
+
+    #[test]
+    fn test_assert_lt_bound_too_large() {
+        // 2^255 is larger than the number of bits of the field
+        let code = r#"fn main(pub xx: Field) {
+            assert_lt(xx, 57896044618658097711785492504343953926634992332820282019728792003956564819968);
+        }"#;
+
+        let res = compile_code(code);
+        assert!(matches!(
+            res.err().unwrap().kind,
+            ErrorKind::InvalidBitSize(..) | ErrorKind::CannotConvertToField(..)
+        ));
+    }
+
+    const ASSERT_IF_CODE: &str = r#"fn main(pub cond: Bool, check: Bool) {
+        assert_if(cond, check);
+    }"#;
+
+    #[test]
+    fn test_assert_if() -> miette::Result<()> {
+        let (sources, compiled_circuit) = compile_code(ASSERT_IF_CODE).unwrap();
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        // the check only has to hold when the condition is true
+        for (cond, check) in [(false, false), (false, true), (true, true)] {
+            let public_inputs = parse_inputs(&format!(r#"{{"cond": {cond}}}"#)).unwrap();
+            let private_inputs = parse_inputs(&format!(r#"{{"check": {check}}}"#)).unwrap();
+            let (proof, full_public_inputs, _public_output) =
+                prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+            verifier_index.verify(full_public_inputs, proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert_if_fails() {
+        let (_sources, compiled_circuit) = compile_code(ASSERT_IF_CODE).unwrap();
+
+        // the condition holds but the check doesn't
+        let public_inputs = parse_inputs(r#"{"cond": true}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"check": false}"#).unwrap();
+        let res = compiled_circuit.generate_witness(public_inputs, private_inputs);
+        assert!(res.is_err());
+
+        // with constants, this is caught at compile time
+        let code = r#"fn main(pub xx: Field) {
+            assert_if(true, false);
+            assert_eq(xx, 1);
+        }"#;
+        let res = compile_code(code);
+        assert!(matches!(
+            res.err().unwrap().kind,
+            ErrorKind::AssertionFailed
+        ));
+
+        // and both arguments must be booleans
+        let code = r#"fn main(pub xx: Field, yy: Bool) {
+            assert_if(yy, xx);
+        }"#;
+        let res = compile_code(code);
+        assert!(matches!(
+            res.err().unwrap().kind,
+            ErrorKind::ArgumentTypeMismatch(..)
+        ));
+    }
+
+    const COMPARISON_CODE: &str = r#"fn main(pub xx: Field, yy: Field) {
+        assert(less_than(xx, yy, 8));
+        assert(!less_than(yy, xx, 8));
+        assert(less_equal(xx, yy, 8));
+        assert(!less_equal(yy, xx, 8));
+
+        // equality
+        let lt = less_than(xx, xx, 8);
+        let le = less_equal(yy, yy, 8);
+        assert(!lt);
+        assert(le);
+    }"#;
+
+    #[test]
+    fn test_comparisons() {
+        let (sources, compiled_circuit) = compile_code(COMPARISON_CODE).unwrap();
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        for (xx, yy) in [("3", "200"), ("0", "255"), ("254", "255")] {
+            let public_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+            let private_inputs = parse_inputs(&format!(r#"{{"yy": "{yy}"}}"#)).unwrap();
+            let (proof, full_public_inputs, _) = prover_index
+                .prove(&sources, public_inputs, private_inputs, false)
+                .unwrap();
+            verifier_index.verify(full_public_inputs, proof).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_comparisons_wrong_order_or_too_large() {
+        let (_sources, compiled_circuit) = compile_code(COMPARISON_CODE).unwrap();
+
+        let witness = |xx: &str, yy: &str| {
+            let public_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+            let private_inputs = parse_inputs(&format!(r#"{{"yy": "{yy}"}}"#)).unwrap();
+            compiled_circuit.generate_witness(public_inputs, private_inputs)
+        };
+
+        // the asserts don't hold
+        assert!(witness("200", "3").is_err());
+        assert!(witness("7", "7").is_err());
+
+        // an operand doesn't fit in 8 bits
+        assert!(witness("3", "256").is_err());
+    }
+
+    const CAST_CODE: &str = r#"fn main(pub xx: Field, yy: Field) -> Field {
+        // a comparison used arithmetically
+        let smaller = as_field(less_than(xx, 10, 8));
+        let doubled = smaller * 2;
+
+        // a field element used as a condition
+        let flag = as_bool(yy);
+        let is_smaller = smaller == 1;
+        assert(flag || is_smaller);
+
+        return doubled + as_field(flag);
+    }"#;
+
+    #[test]
+    fn test_casts() -> miette::Result<()> {
+        let (sources, compiled_circuit) = compile_code(CAST_CODE).unwrap();
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        for (xx, yy, expected) in [("3", "0", 2u64), ("3", "1", 3), ("20", "1", 1)] {
+            let public_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+            let private_inputs = parse_inputs(&format!(r#"{{"yy": "{yy}"}}"#)).unwrap();
+            let (proof, full_public_inputs, public_output) =
+                prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+            assert_eq!(public_output, vec![VestaField::from(expected)]);
+            verifier_index.verify(full_public_inputs, proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_as_field_is_free() {
+        let with_cast = r#"fn main(pub xx: Field) {
+            let res = as_field(xx == 1);
+            assert_eq(res, 1);
+        }"#;
+        let without_cast = r#"fn main(pub xx: Field) {
+            let res = xx == 1;
+            assert(res);
+        }"#;
+
+        let (_, with_cast) = compile_code(with_cast).unwrap();
+        let (_, without_cast) = compile_code(without_cast).unwrap();
+        assert_eq!(with_cast.num_gates(), without_cast.num_gates());
+    }
+
+    #[test]
+    fn test_as_bool_not_a_boolean() {
+        let (_sources, compiled_circuit) = compile_code(CAST_CODE).unwrap();
+
+        // 2 is not a boolean
+        let public_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"yy": "2"}"#).unwrap();
+        let res = compiled_circuit.generate_witness(public_inputs, private_inputs);
+        assert!(res.is_err());
+
+        // and a constant that is not a boolean is rejected at compile time
+        let code = r#"fn main(pub xx: Field) {
+            let flag = as_bool(2);
+            assert(flag);
+        }"#;
+        let res = compile_code(code);
+        assert!(matches!(res.err().unwrap().kind, ErrorKind::NotABoolean));
+    }
+
+    const DIV_CODE: &str = r#"fn main(pub expected: Field, lhs: Field, rhs: Field) -> Field {
+        let res = div(lhs, rhs);
+        assert_eq(res, expected);
+
+        // by a constant
+        let half = div(lhs, 2);
+        return half + div(6, rhs);
+    }"#;
+
+    #[test]
+    fn test_div() -> miette::Result<()> {
+        let (sources, compiled_circuit) = compile_code(DIV_CODE).unwrap();
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"expected": "4"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"lhs": "12", "rhs": "3"}"#).unwrap();
+
+        let (proof, full_public_inputs, public_output) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        assert_eq!(public_output, vec![VestaField::from(8u64)]);
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_div_by_zero() {
+        let (_sources, compiled_circuit) = compile_code(DIV_CODE).unwrap();
+
+        let public_inputs = parse_inputs(r#"{"expected": "4"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"lhs": "12", "rhs": "0"}"#).unwrap();
+        let err = compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::DivisionByZero));
+
+        // the error points at the division
+        let start = DIV_CODE.find("div(lhs, rhs)").unwrap();
+        assert_eq!(err.span.start, start);
+
+        // and dividing by the constant zero is rejected at compile time
+        let code = r#"fn main(pub xx: Field) -> Field {
+            return div(xx, 0);
+        }"#;
+        let res = compile_code(code);
+        assert!(matches!(res.err().unwrap().kind, ErrorKind::DivisionByZero));
+    }
+
+    const SQRT_CODE: &str = r#"fn main(pub xx: Field) -> [Field; 2] {
+        let root = sqrt(xx);
+        return [root, sqrt(16)];
+    }"#;
+
+    #[test]
+    fn test_sqrt() -> miette::Result<()> {
+        let (sources, compiled_circuit) = compile_code(SQRT_CODE).unwrap();
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"xx": "9"}"#).unwrap();
+        let private_inputs = parse_inputs("{}").unwrap();
+
+        let (proof, full_public_inputs, public_output) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+
+        // 3 is odd, so the even root of 9 is -3
+        assert_eq!(
+            public_output,
+            vec![-VestaField::from(3u64), VestaField::from(4u64)]
+        );
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sqrt_not_a_square() {
+        let (_sources, compiled_circuit) = compile_code(SQRT_CODE).unwrap();
+
+        // 5 generates the multiplicative group of the field, so it's not a square
+        let public_inputs = parse_inputs(r#"{"xx": "5"}"#).unwrap();
+        let private_inputs = parse_inputs("{}").unwrap();
+        let err = compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::NotASquare));
+        assert_eq!(err.span.start, SQRT_CODE.find("sqrt(xx)").unwrap());
+    }
+
+    #[test]
+    fn test_is_square() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field) -> Field {
+            assert(is_square(4));
+            assert(!is_square(5));
+            return as_field(is_square(xx));
+        }"#;
+        let (sources, compiled_circuit) = compile_code(code).unwrap();
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        for (xx, expected) in [("9", 1u64), ("5", 0), ("0", 1)] {
+            let public_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+            let private_inputs = parse_inputs("{}").unwrap();
+            let (proof, full_public_inputs, public_output) =
+                prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+            assert_eq!(public_output, vec![VestaField::from(expected)]);
+            verifier_index.verify(full_public_inputs, proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pow() -> miette::Result<()> {
+        let code = r#"fn main(pub xx: Field) -> Field {
+            assert_eq(pow(3, 0), 1);
+            assert_eq(pow(2, 10), 1024);
+            return pow(xx, 10);
+        }"#;
+        let (sources, compiled_circuit) = compile_code(code).unwrap();
+        let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes().unwrap();
+
+        let public_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+        let private_inputs = parse_inputs("{}").unwrap();
+        let (proof, full_public_inputs, public_output) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        assert_eq!(public_output, vec![VestaField::from(59049u64)]);
+        verifier_index.verify(full_public_inputs, proof)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pow_gates() {
+        // 10 = 0b1010: three squarings and one multiplication,
+        // while the exponents 1 and 0 don't need any gate
+        let (_sources, with_pow) = compile_code(
+            r#"fn main(pub xx: Field) -> Field {
+            return pow(xx, 10) + pow(xx, 1) + pow(xx, 0);
+        }"#,
+        )
+        .unwrap();
+
+        let (_sources, by_hand) = compile_code(
+            r#"fn main(pub xx: Field) -> Field {
+            let x2 = xx * xx;
+            let x4 = x2 * x2;
+            let x5 = x4 * xx;
+            let x10 = x5 * x5;
+            return x10 + xx + 1;
+        }"#,
+        )
+        .unwrap();
+
+        assert_eq!(with_pow.num_gates(), by_hand.num_gates());
+    }
+
+    #[test]
+    fn test_pow_non_constant_exponent() {
+        let code = r#"fn main(pub xx: Field, exp: Field) -> Field {
+            return pow(xx, exp);
+        }"#;
+
+        let err = compile_code(code).err().expect("expected an error");
+        assert!(matches!(
+            err.kind,
+            ErrorKind::ExpectedConstantArgument("exp")
+        ));
+    }
+}
@@ -1,5 +1,8 @@
 //! A number of helper function to check the syntax of some types.
 
+use num_bigint::BigUint;
+use num_traits::Num as _;
+
 /// Returns true if the given string is a number in decimal.
 pub fn is_numeric(s: &str) -> bool {
     s.chars().all(|c| c.is_ascii_digit())
@@ -7,16 +10,33 @@ pub fn is_numeric(s: &str) -> bool {
 
 /// Returns true if the given string is an hexadecimal string (0x...)
 pub fn is_hexadecimal(s: &str) -> bool {
-    let mut s = s.chars();
-    let s0 = s.next();
-    let s1 = s.next();
-    if matches!((s0, s1), (Some('0'), Some('x') | Some('X'))) {
-        s.all(|c| c.is_ascii_hexdigit())
-    } else {
-        false
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(digits) => !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
     }
 }
 
+/// Returns true if the given string is a binary string (0b...)
+pub fn is_binary(s: &str) -> bool {
+    match s.strip_prefix("0b").or_else(|| s.strip_prefix("0B")) {
+        Some(digits) => !digits.is_empty() && digits.chars().all(|c| c == '0' || c == '1'),
+        None => false,
+    }
+}
+
+/// Parses a numeric literal given in decimal (`42`), hexadecimal (`0x2a`) or binary (`0b101010`).
+pub fn parse_number(s: &str) -> Option<BigUint> {
+    let (digits, radix) = if is_hexadecimal(s) {
+        (&s[2..], 16)
+    } else if is_binary(s) {
+        (&s[2..], 2)
+    } else {
+        (s, 10)
+    };
+
+    BigUint::from_str_radix(digits, radix).ok()
+}
+
 /// Returns true if the given string is an identifier or type
 pub fn is_identifier_or_type(s: &str) -> bool {
     let mut chars = s.chars();
@@ -65,4 +85,19 @@ mod tests {
         assert!(is_identifier("cond2"));
         assert!(is_type("Cond2"));
     }
+
+    #[test]
+    fn test_number_literals() {
+        assert!(is_hexadecimal("0xFF"));
+        assert!(!is_hexadecimal("0x"));
+        assert!(!is_hexadecimal("0xFG"));
+        assert!(is_binary("0b1010"));
+        assert!(!is_binary("0b"));
+        assert!(!is_binary("0b102"));
+
+        assert_eq!(parse_number("255"), Some(BigUint::from(255u32)));
+        assert_eq!(parse_number("0xFF"), Some(BigUint::from(255u32)));
+        assert_eq!(parse_number("0b1010"), Some(BigUint::from(10u32)));
+        assert_eq!(parse_number("0b2"), None);
+    }
 }
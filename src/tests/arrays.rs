@@ -0,0 +1,509 @@
+use std::str::FromStr;
+
+use crate::{
+    backends::{
+        kimchi::{KimchiVesta, VestaField},
+        r1cs::{R1csBls12381Field, R1CS},
+    },
+    circuit_writer::{CircuitOptions, CircuitWriter},
+    compiler::{compile, typecheck_next_file, typecheck_next_file_inner, Sources},
+    error::ErrorKind,
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+//
+// Multi-dimensional arrays
+//
+
+const SUM_2D: &str = r#"
+struct Grid {
+    cells: [[Field; 3]; 2],
+}
+
+fn sum(matrix: [[Field; 3]; 2]) -> Field {
+    let mut acc = 0;
+    for ii in 0..2 {
+        for jj in 0..3 {
+            acc = acc + matrix[ii][jj];
+        }
+    }
+    return acc;
+}
+
+fn main(pub expected: Field, matrix: [[Field; 3]; 2]) {
+    // as a function argument
+    let res = sum(matrix);
+    assert_eq(res, expected);
+
+    // as a struct field
+    let grid = Grid { cells: matrix };
+    assert_eq(grid.cells[1][2], 6);
+
+    // as a local variable
+    let swapped = [matrix[1], matrix[0]];
+    assert_eq(swapped[1][1], 2);
+}
+"#;
+
+const PUBLIC_INPUTS: &str = r#"{"expected": "21"}"#;
+const PRIVATE_INPUTS: &str = r#"{"matrix": [["1", "2", "3"], ["4", "5", "6"]]}"#;
+
+#[test]
+fn test_2d_array_kimchi() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "sum_2d.no".to_string(),
+        SUM_2D.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    let public_inputs = parse_inputs(PUBLIC_INPUTS).unwrap();
+    let private_inputs = parse_inputs(PRIVATE_INPUTS).unwrap();
+
+    let (proof, full_public_inputs, _public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_2d_array_r1cs() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "sum_2d.no".to_string(),
+        SUM_2D.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+    let public_inputs = parse_inputs(PUBLIC_INPUTS).unwrap();
+    let private_inputs = parse_inputs(PRIVATE_INPUTS).unwrap();
+
+    // this checks the constraints
+    compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .unwrap();
+
+    Ok(())
+}
+
+//
+// Const arrays
+//
+
+const CONST_ARRAY: &str = r#"
+const lookup: [Field; 4] = [1, 2, 4, 8];
+
+fn main(pub expected: Field, idx: Field) {
+    // indexing with a loop variable is resolved at compile time
+    let mut acc = 0;
+    for ii in 0..4 {
+        acc = acc + lookup[ii];
+    }
+    assert_eq(acc, 15);
+
+    // indexing with a runtime value selects the element in the circuit
+    assert_eq(lookup[idx], expected);
+}
+"#;
+
+#[test]
+fn test_const_array() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "const_array.no".to_string(),
+        CONST_ARRAY.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    let public_inputs = parse_inputs(r#"{"expected": "4"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"idx": "2"}"#).unwrap();
+
+    let (proof, full_public_inputs, _public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_const_array_loop_is_free() -> miette::Result<()> {
+    let compile_code = |code: &str| -> miette::Result<usize> {
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<KimchiVesta>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "const_array.no".to_string(),
+            code.to_string(),
+            0,
+        )?;
+        let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+        Ok(compiled_circuit.num_gates())
+    };
+
+    let looped = compile_code(
+        r#"
+const lookup: [Field; 4] = [1, 2, 4, 8];
+
+fn main(pub xx: Field) {
+    let mut acc = 0;
+    for ii in 0..4 {
+        acc = acc + lookup[ii];
+    }
+    assert_eq(xx, acc);
+}
+"#,
+    )?;
+
+    let literal = compile_code(
+        r#"
+fn main(pub xx: Field) {
+    assert_eq(xx, 15);
+}
+"#,
+    )?;
+
+    assert_eq!(looped, literal);
+
+    Ok(())
+}
+
+#[test]
+fn test_const_array_runtime_index_out_of_bounds() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "const_array.no".to_string(),
+        CONST_ARRAY.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+    // in bounds
+    let public_inputs = parse_inputs(r#"{"expected": "8"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"idx": "3"}"#).unwrap();
+    compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .unwrap();
+
+    // out of bounds: no element is selected
+    let public_inputs = parse_inputs(r#"{"expected": "0"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"idx": "4"}"#).unwrap();
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    Ok(())
+}
+
+//
+// String constants
+//
+
+const STRING_CONST: &str = r#"
+const tag = b"noname";
+const sentence: [Field; 2] = "the quick brown fox jumps over the lazy dog";
+
+fn main(pub expected: Field) -> [Field; 3] {
+    assert_eq(tag[0], expected);
+    return [tag[0], sentence[0], sentence[1]];
+}
+"#;
+
+#[test]
+fn test_string_const() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "string_const.no".to_string(),
+        STRING_CONST.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    // the bytes of "noname", read as a little-endian integer
+    let public_inputs = parse_inputs(r#"{"expected": "111520460468078"}"#).unwrap();
+    let private_inputs = parse_inputs("{}").unwrap();
+
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+
+    // the 43 bytes of the sentence are split into 31 bytes, then 12
+    let field = |value: &str| VestaField::from_str(value).unwrap();
+    assert_eq!(
+        public_output,
+        vec![
+            field("111520460468078"),
+            field("57328640583621120029295763847239700394047764668332841871130683017487804532"),
+            field("32011619613273279183047714932"),
+        ]
+    );
+
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_string_const_wrong_length() {
+    let code = r#"
+const tag: [Field; 2] = b"noname";
+
+fn main(pub xx: Field) {
+    assert_eq(tag[0], xx);
+}
+"#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let res = typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "string_const.no".to_string(),
+        code.to_string(),
+        0,
+    );
+    assert!(res.is_err());
+}
+
+//
+// Mutable arguments
+//
+
+const UPDATE_ARRAY: &str = r#"
+fn set(mut arr: [Field; 3], idx: Field, value: Field) -> [Field; 3] {
+    arr[idx] = value;
+    return arr;
+}
+
+fn main(pub expected: [Field; 3], idx: Field) {
+    let arr = [1, 2, 3];
+    let updated = set(arr, idx, 7);
+    for ii in 0..3 {
+        assert_eq(updated[ii], expected[ii]);
+    }
+
+    // arguments are passed by value
+    assert_eq(arr[1], 2);
+}
+"#;
+
+#[test]
+fn test_update_array_runtime_index() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "update_array.no".to_string(),
+        UPDATE_ARRAY.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    let public_inputs = parse_inputs(r#"{"expected": ["1", "7", "3"]}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"idx": "1"}"#).unwrap();
+
+    let (proof, full_public_inputs, _public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_update_array_indirect_runtime_index() {
+    // the element selected at runtime is a copy, so it can't be updated
+    let code = r#"
+fn main(pub value: Field, idx: Field) {
+    let mut grid = [[1, 2], [3, 4]];
+    grid[idx][0] = value;
+}
+"#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "update_array.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .unwrap();
+
+    let err = CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        None,
+    )
+    .err()
+    .expect("expected an error");
+    assert!(matches!(
+        err.kind,
+        ErrorKind::IndirectRuntimeIndexAssignment
+    ));
+}
+
+#[test]
+fn test_update_array_runtime_index_out_of_bounds() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "update_array.no".to_string(),
+        UPDATE_ARRAY.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+    // only the element at the index is updated
+    let public_inputs = parse_inputs(r#"{"expected": ["1", "2", "7"]}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"idx": "2"}"#).unwrap();
+    compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .unwrap();
+
+    // out of bounds: no element is updated
+    let public_inputs = parse_inputs(r#"{"expected": ["1", "2", "3"]}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"idx": "3"}"#).unwrap();
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    Ok(())
+}
+
+//
+// Slices
+//
+
+const SUM_SLICE: &str = r#"
+fn sum(arr: [Field; 2]) -> Field {
+    return arr[0] + arr[1];
+}
+
+fn main(pub expected: Field, arr: [Field; 4]) {
+    let middle = arr[1..3];
+    assert_eq(sum(middle), expected);
+}
+"#;
+
+fn compile_slice_code(code: &str) -> miette::Result<(Sources, CompiledCircuit<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "slice.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    Ok((sources, compiled_circuit))
+}
+
+#[test]
+fn test_sum_slice() -> miette::Result<()> {
+    let (sources, compiled_circuit) = compile_slice_code(SUM_SLICE)?;
+
+    // the slice doesn't create any gate
+    let (_, direct) = compile_slice_code(
+        r#"
+fn main(pub expected: Field, arr: [Field; 4]) {
+    assert_eq(arr[1] + arr[2], expected);
+}
+"#,
+    )?;
+    assert_eq!(compiled_circuit.num_gates(), direct.num_gates());
+
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    let public_inputs = parse_inputs(r#"{"expected": "5"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"arr": ["1", "2", "3", "4"]}"#).unwrap();
+
+    let (proof, full_public_inputs, _public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_slices() {
+    let typecheck_code = |code: &str| {
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<KimchiVesta>::new();
+        typecheck_next_file_inner(
+            &mut tast,
+            None,
+            &mut sources,
+            "slice.no".to_string(),
+            code.to_string(),
+            0,
+        )
+        .err()
+        .expect("expected an error")
+    };
+
+    let code = r#"
+fn main(pub arr: [Field; 4]) {
+    let slice = arr[3..1];
+}
+"#;
+    let err = typecheck_code(code);
+    assert!(matches!(err.kind, ErrorKind::ReversedSlice(3, 1)));
+    assert_eq!(&code[err.span.start..err.span.end()], "3..1");
+
+    let code = r#"
+fn main(pub arr: [Field; 4]) {
+    let slice = arr[2..5];
+}
+"#;
+    let err = typecheck_code(code);
+    assert!(matches!(err.kind, ErrorKind::SliceOutOfBounds(2, 5, 4)));
+    assert_eq!(&code[err.span.start..err.span.end()], "2..5");
+}
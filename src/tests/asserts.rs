@@ -0,0 +1,220 @@
+use crate::{
+    backends::kimchi::KimchiVesta,
+    circuit_writer::{CircuitOptions, CircuitWriter, OptLevel},
+    compiler::{typecheck_next_file_inner, Sources},
+    error::{ErrorKind, Result, WarningKind},
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+fn compile_code(
+    code: &str,
+    backend: KimchiVesta,
+    opt_level: OptLevel,
+) -> Result<CompiledCircuit<KimchiVesta>> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "asserts.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    let options = CircuitOptions {
+        opt_level,
+        ..Default::default()
+    };
+    CircuitWriter::generate_circuit(tast, backend, options, None)
+}
+
+//
+// debug_assert
+//
+
+#[test]
+fn test_debug_assert() {
+    let code = r#"
+fn main(pub xx: Field, yy: Field) {
+    debug_assert(xx * yy == 2, "xx * yy should be 2");
+    assert_eq(xx + yy, 3);
+}
+"#;
+    let without_debug_assert = r#"
+fn main(pub xx: Field, yy: Field) {
+    assert_eq(xx + yy, 3);
+}
+"#;
+    let compile = |code, opt_level| compile_code(code, KimchiVesta::new(false), opt_level);
+
+    // the assertion is a real constraint at level 1
+    let compiled_circuit = compile(code, OptLevel::O1).unwrap();
+    let baseline = compile(without_debug_assert, OptLevel::O1).unwrap();
+    assert!(compiled_circuit.num_gates() > baseline.num_gates());
+    assert!(compiled_circuit.warnings.is_empty());
+
+    let public_inputs = parse_inputs(r#"{"xx": "0"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "3"}"#).unwrap();
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    // but it is stripped at level 2, with a warning
+    let compiled_circuit = compile(code, OptLevel::O2).unwrap();
+    let baseline = compile(without_debug_assert, OptLevel::O2).unwrap();
+    assert_eq!(compiled_circuit.num_gates(), baseline.num_gates());
+    assert_eq!(compiled_circuit.warnings.len(), 1);
+    assert!(matches!(
+        compiled_circuit.warnings[0].kind,
+        WarningKind::DebugAssertStripped
+    ));
+
+    let public_inputs = parse_inputs(r#"{"xx": "0"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "3"}"#).unwrap();
+    compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .unwrap();
+}
+
+#[test]
+fn test_private_input_only_used_in_debug_assert() {
+    let code = r#"
+fn main(pub xx: Field, yy: Field) {
+    debug_assert(yy == 2);
+    assert_eq(xx, 3);
+}
+"#;
+    let compile = |opt_level| compile_code(code, KimchiVesta::new(false), opt_level);
+
+    // the private input is constrained by the assertion at level 1
+    assert!(compile(OptLevel::O1).is_ok());
+
+    // but not anymore once it is stripped
+    let err = compile(OptLevel::O2).err().expect("expected an error");
+    match &err.kind {
+        ErrorKind::PrivateInputOnlyUsedInDebugAssert(names) => {
+            assert_eq!(names, &vec!["yy".to_string()])
+        }
+        kind => panic!("expected an unused private input, got {kind:?}"),
+    }
+}
+
+//
+// Assertion messages
+//
+
+#[test]
+fn test_assert_message() {
+    let code = r#"
+fn main(pub xx: Field, yy: Field) {
+    let zz = xx * yy;
+    assert(zz == 6, "the product should be 6");
+}
+"#;
+    let compiled_circuit =
+        compile_code(code, KimchiVesta::new(false), OptLevel::default()).unwrap();
+
+    let public_inputs = parse_inputs(r#"{"xx": "2"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "4"}"#).unwrap();
+    let err = compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .unwrap_err();
+
+    match &err.kind {
+        ErrorKind::UnsatisfiedAssertion { message, .. } => {
+            assert_eq!(message, "the product should be 6")
+        }
+        kind => panic!("expected an unsatisfied assertion, got {kind:?}"),
+    }
+    assert!(err.kind.to_string().contains("the product should be 6"));
+
+    // the error points to the assertion
+    let failing_code = &code[err.span.start..err.span.end()];
+    assert!(failing_code.starts_with("assert("));
+}
+
+#[test]
+fn test_assert_message_of_paired_gates() {
+    let code = r#"
+fn main(pub xx: Field, yy: Field) {
+    assert_eq(xx, 1, "xx should be 1");
+    assert_eq(yy, 2, "yy should be 2");
+    assert_eq(xx * yy, 2);
+}
+"#;
+
+    // the two assertions are packed in the same row
+    let compiled_circuit = compile_code(code, KimchiVesta::new(true), OptLevel::default()).unwrap();
+    let backend = &compiled_circuit.circuit.backend;
+    let row = backend
+        .paired_debug_info
+        .iter()
+        .find(|(_, debug_info)| debug_info.message.is_some())
+        .map(|(row, _)| *row)
+        .expect("the assertions should be paired");
+    assert_eq!(
+        backend.debug_info[row].message.as_deref(),
+        Some("yy should be 2")
+    );
+
+    // each failing assertion is reported with its own message and span
+    for (xx, yy, expected) in [
+        ("0", "2", r#"assert_eq(xx, 1, "xx should be 1")"#),
+        ("1", "0", r#"assert_eq(yy, 2, "yy should be 2")"#),
+    ] {
+        let public_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+        let private_inputs = parse_inputs(&format!(r#"{{"yy": "{yy}"}}"#)).unwrap();
+        let err = compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap_err();
+
+        match &err.kind {
+            ErrorKind::UnsatisfiedAssertion {
+                row: failing_row,
+                message,
+            } => {
+                assert_eq!(*failing_row, row);
+                assert!(expected.contains(message.as_str()));
+            }
+            kind => panic!("expected an unsatisfied assertion, got {kind:?}"),
+        }
+        let failing_code = &code[err.span.start..err.span.end()];
+        assert!(expected.starts_with(failing_code));
+    }
+}
+
+#[test]
+fn test_constant_assertion() {
+    // a constant assertion that doesn't hold is reported at compile time
+    let code = r#"
+fn main(pub xx: Field) {
+    assert_eq(1, 2);
+    assert_eq(xx, 1);
+}
+"#;
+    let err = compile_code(code, KimchiVesta::new(false), OptLevel::default())
+        .err()
+        .expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::AssertionFailed));
+
+    // but in a branch, it only means that the branch can't be taken
+    let code = r#"
+fn main(pub xx: Field, flag: Bool) {
+    if flag {
+        assert_eq(1, 2);
+    }
+    assert_eq(xx, 1);
+}
+"#;
+    let compiled_circuit =
+        compile_code(code, KimchiVesta::new(false), OptLevel::default()).unwrap();
+    for (flag, satisfied) in [(false, true), (true, false)] {
+        let public_inputs = parse_inputs(r#"{"xx": "1"}"#).unwrap();
+        let private_inputs = parse_inputs(&format!(r#"{{"flag": {flag}}}"#)).unwrap();
+        let res = compiled_circuit.generate_witness(public_inputs, private_inputs);
+        assert_eq!(res.is_ok(), satisfied);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    backends::{
+        kimchi::KimchiVesta,
+        r1cs::{R1csBls12381Field, R1CS},
+    },
+    circuit_writer::GateKind,
+    compiler::{compile, typecheck_next_file, Sources},
+    inputs::{parse_inputs, JsonInputs},
+    type_checker::TypeChecker,
+};
+
+//
+// Blake2s
+//
+
+const BLAKE2S_CODE: &str = r#"
+use std::crypto;
+
+fn main(pub expected: [Field; 8], input: [Field; 16]) {
+    let digest = crypto::blake2s(input);
+    for ii in 0..8 {
+        assert_eq(digest[ii], expected[ii]);
+    }
+}
+"#;
+
+/// Test vectors computed with a reference implementation
+/// (hashing the 64 bytes obtained by encoding each input word in little-endian).
+const TEST_VECTORS: &[([u32; 16], [u32; 8])] = &[
+    (
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+        [
+            3326383972, 2824266489, 500530935, 523308720, 3179499642, 2847185110, 947390885,
+            642414640,
+        ],
+    ),
+    (
+        [u32::MAX; 16],
+        [
+            3621360366, 1422305893, 2821588668, 3142993122, 1724412698, 2125249859, 4247715780,
+            1662022273,
+        ],
+    ),
+];
+
+/// Test vectors for messages of other lengths, computed the same way.
+const OTHER_LENGTHS: &[(&[u32], [u32; 8])] = &[
+    (
+        &[1, 2, 3],
+        [
+            416172437, 3346217480, 872329364, 2879665542, 1043550217, 423571147, 3690442053,
+            3505655391,
+        ],
+    ),
+    (
+        // more than a block
+        &[
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+        ],
+        [
+            2000932794, 3497405459, 4239981206, 1488733704, 425539561, 3276294669, 1636215135,
+            357140199,
+        ],
+    ),
+];
+
+fn to_json(words: &[u32]) -> String {
+    let words: Vec<_> = words.iter().map(|word| format!(r#""{word}""#)).collect();
+    format!("[{}]", words.join(", "))
+}
+
+fn inputs(input: &[u32], expected: &[u32]) -> (JsonInputs, JsonInputs) {
+    let public_inputs = parse_inputs(&format!(r#"{{"expected": {}}}"#, to_json(expected))).unwrap();
+    let private_inputs = parse_inputs(&format!(r#"{{"input": {}}}"#, to_json(input))).unwrap();
+    (public_inputs, private_inputs)
+}
+
+#[test]
+fn test_blake2s_r1cs() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "blake2s.no".to_string(),
+        BLAKE2S_CODE.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+    for (input, expected) in TEST_VECTORS {
+        let (public_inputs, private_inputs) = inputs(input, expected);
+        compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap();
+    }
+
+    // wrong digest
+    let (input, expected) = TEST_VECTORS[0];
+    let (public_inputs, private_inputs) = inputs(&input, &TEST_VECTORS[1].1);
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    // an input word that doesn't fit in 32 bits
+    let (public_inputs, _) = inputs(&input, &expected);
+    let mut words: Vec<_> = input.iter().map(|word| format!(r#""{word}""#)).collect();
+    words[0] = r#""4294967296""#.to_string();
+    let private_inputs = parse_inputs(&format!(r#"{{"input": [{}]}}"#, words.join(", "))).unwrap();
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_blake2s_kimchi() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "blake2s.no".to_string(),
+        BLAKE2S_CODE.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(true))?;
+
+    // only generic gates are used
+    let stats = compiled_circuit.gate_stats();
+    assert!(!stats.gates.contains_key(&GateKind::Poseidon));
+
+    // the witness satisfies every generic gate
+    let (input, expected) = TEST_VECTORS[0];
+    let (public_inputs, private_inputs) = inputs(&input, &expected);
+    compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .unwrap();
+
+    Ok(())
+}
+
+#[test]
+fn test_blake2s_input_lengths() -> miette::Result<()> {
+    for (input, expected) in OTHER_LENGTHS {
+        let code = BLAKE2S_CODE.replace("[Field; 16]", &format!("[Field; {}]", input.len()));
+
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "blake2s.no".to_string(),
+            code,
+            0,
+        )?;
+
+        let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+        let (public_inputs, private_inputs) = inputs(input, expected);
+        compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap();
+    }
+
+    Ok(())
+}
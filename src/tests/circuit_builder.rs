@@ -0,0 +1,145 @@
+use num_bigint::BigUint;
+
+use crate::{
+    backends::{
+        kimchi::{curves::KimchiCurveConfig, curves::VestaConfig, KimchiVesta, VestaField},
+        r1cs::{R1csBn254Field, R1CS},
+        Backend,
+    },
+    circuit_builder::CircuitBuilder,
+    error::ErrorKind,
+};
+
+#[test]
+fn test_builder_one_plus_one_kimchi() -> miette::Result<()> {
+    let mut builder = CircuitBuilder::new(KimchiVesta::new(false));
+    let two = builder.public_input("two");
+    let one = builder.private_input("one");
+    let sum = builder.add(&one, &one);
+    builder.assert_eq(&sum, &two);
+
+    let circuit = builder.finalize().unwrap();
+    let (prover_index, verifier_index) = circuit.backend.compile_to_indexes()?;
+
+    let witness = circuit
+        .generate_witness([
+            ("two", VestaField::from(2u64)),
+            ("one", VestaField::from(1u64)),
+        ])
+        .unwrap();
+    let proof = VestaConfig::prove(&prover_index, witness.all_witness.to_kimchi_witness())?;
+    VestaConfig::verify(&verifier_index, &proof, &witness.full_public_inputs)?;
+
+    // 1 + 1 is not 3
+    let witness = circuit
+        .generate_witness([
+            ("two", VestaField::from(3u64)),
+            ("one", VestaField::from(1u64)),
+        ])
+        .unwrap();
+    assert!(VestaConfig::verify_witness(
+        &prover_index,
+        &witness.all_witness.to_kimchi_witness(),
+        &witness.full_public_inputs
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_r1cs() {
+    // xx * (xx + 3) = 10
+    let mut builder = CircuitBuilder::new(R1CS::<R1csBn254Field>::new());
+    let xx = builder.private_input("xx");
+    let shifted = builder.add_const(&xx, R1csBn254Field::from(3u64));
+    let prod = builder.mul(&xx, &shifted);
+    builder.assert_eq_const(&prod, R1csBn254Field::from(10u64));
+
+    let circuit = builder.finalize().unwrap();
+    circuit
+        .generate_witness([("xx", R1csBn254Field::from(2u64))])
+        .unwrap();
+    assert!(circuit
+        .generate_witness([("xx", R1csBn254Field::from(3u64))])
+        .is_err());
+}
+
+#[test]
+fn test_builder_unconstrained_cell() {
+    let mut builder = CircuitBuilder::new(R1CS::<R1csBn254Field>::new());
+    let xx = builder.private_input("xx");
+    let yy = builder.private_input("yy");
+    let sum = builder.add(&xx, &yy);
+    builder.assert_eq_const(&sum, R1csBn254Field::from(10u64));
+
+    // never constrained
+    builder.private_input("zz");
+
+    let err = builder.finalize().err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::PrivateInputNotUsed(..)));
+}
+
+/// Constrains a cell to fit in `num_bits` bits, by decomposing it into bits given by hints (in little-endian).
+fn to_bits(
+    builder: &mut CircuitBuilder<R1CS<R1csBn254Field>>,
+    value: &<R1CS<R1csBn254Field> as Backend>::Var,
+    num_bits: usize,
+) {
+    let mut acc = builder.constant(R1csBn254Field::from(0u64));
+    for ii in 0..num_bits {
+        let bit = builder.hint(&[value], move |values| {
+            let value: BigUint = values[0].into();
+            Ok(R1csBn254Field::from(value.bit(ii as u64)))
+        });
+
+        // bit * bit = bit
+        let square = builder.mul(&bit, &bit);
+        builder.assert_eq(&square, &bit);
+
+        let scaled = builder.mul_const(&bit, R1csBn254Field::from(1u64 << ii));
+        acc = builder.add(&acc, &scaled);
+    }
+    builder.assert_eq(&acc, value);
+}
+
+#[test]
+fn test_builder_hint_modular_reduction() {
+    // xx = 8 * quotient + remainder, for an 8-bit xx
+    let mut builder = CircuitBuilder::new(R1CS::<R1csBn254Field>::new());
+    let remainder_expected = builder.public_input("remainder");
+    let xx = builder.private_input("xx");
+
+    let quotient = builder.hint(&[&xx], |values| {
+        let value: BigUint = values[0].into();
+        Ok(R1csBn254Field::try_from(value / 8u32).unwrap())
+    });
+    let remainder = builder.hint(&[&xx], |values| {
+        let value: BigUint = values[0].into();
+        Ok(R1csBn254Field::try_from(value % 8u32).unwrap())
+    });
+
+    // the hints are only pinned if both the quotient and the remainder are range-checked,
+    // otherwise any remainder could be compensated by a (huge) quotient
+    to_bits(&mut builder, &xx, 8);
+    to_bits(&mut builder, &quotient, 5);
+    to_bits(&mut builder, &remainder, 3);
+
+    let scaled = builder.mul_const(&quotient, R1csBn254Field::from(8u64));
+    let recomposed = builder.add(&scaled, &remainder);
+    builder.assert_eq(&recomposed, &xx);
+    builder.assert_eq(&remainder, &remainder_expected);
+
+    let circuit = builder.finalize().unwrap();
+    let witness = |xx: u64, remainder: u64| {
+        circuit.generate_witness([
+            ("xx", R1csBn254Field::from(xx)),
+            ("remainder", R1csBn254Field::from(remainder)),
+        ])
+    };
+
+    for xx in [0, 7, 8, 100, 255] {
+        witness(xx, xx % 8).unwrap();
+    }
+    assert!(witness(100, 5).is_err());
+}
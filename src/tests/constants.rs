@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    circuit_writer::{CircuitOptions, CircuitWriter},
+    compiler::{typecheck_next_file_inner, Sources},
+    error::{ErrorKind, Result},
+    inputs::parse_inputs,
+    parser::types::TyKind,
+    type_checker::{ConstOverride, TypeChecker},
+    witness::CompiledCircuit,
+};
+
+fn compile_code(
+    code: &str,
+    constants: &[(&str, ConstOverride<VestaField>)],
+) -> Result<CompiledCircuit<KimchiVesta>> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "constants.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    let constants: HashMap<_, _> = constants
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect();
+    CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        Some(&constants),
+    )
+}
+
+const REPEATED_SQUARING: &str = r#"
+const depth = 2;
+
+fn main(pub expected: Field, leaf: Field) {
+    let mut acc = leaf;
+    for ii in 0..depth {
+        acc = acc * acc;
+    }
+    assert_eq(acc, expected);
+}
+"#;
+
+#[test]
+fn test_override_constant() -> miette::Result<()> {
+    let depth = |depth: u64| [("depth", ConstOverride::Field(VestaField::from(depth)))];
+
+    let shallow = compile_code(REPEATED_SQUARING, &depth(2)).unwrap();
+    let deep = compile_code(REPEATED_SQUARING, &depth(5)).unwrap();
+
+    // the override takes precedence over the value in the source
+    assert!(shallow.num_gates() < deep.num_gates());
+
+    let public_inputs = parse_inputs(r#"{"expected": "256"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"leaf": "2"}"#).unwrap();
+    compile_code(REPEATED_SQUARING, &depth(3))
+        .unwrap()
+        .generate_witness(public_inputs, private_inputs)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_constant_overrides() {
+    let field = ConstOverride::Field(VestaField::from(3u64));
+
+    // the constant must exist
+    let err = compile_code(REPEATED_SQUARING, &[("width", field.clone())])
+        .err()
+        .expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::UndefinedConstantOverride(name) if name == "width"));
+    assert_eq!(err.span.start, REPEATED_SQUARING.find("main").unwrap());
+
+    // with the same type
+    let array = ConstOverride::Array(vec![VestaField::from(3u64); 2]);
+    let err = compile_code(REPEATED_SQUARING, &[("depth", array)])
+        .err()
+        .expect("expected an error");
+    assert!(matches!(
+        err.kind,
+        ErrorKind::MismatchType(TyKind::Field, TyKind::Array(_, 2))
+    ));
+
+    // and its value must not have been used to compute another constant
+    let code = r#"
+const depth = 2;
+const width = depth * 2;
+
+fn main(pub xx: Field) {
+    assert_eq(xx, width);
+}
+"#;
+    let err = compile_code(code, &[("depth", field)])
+        .err()
+        .expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::ConstantUsedAtCompileTime(name) if name == "depth"));
+}
+
+#[test]
+fn test_override_loop_bounds() {
+    // the loops are checked again with the overridden bounds
+    let code = r#"
+const start = 2;
+const iterations = 3;
+
+fn main(pub xx: Field) {
+    let mut acc = xx;
+    for ii in start..4 {
+        acc = acc + ii;
+    }
+    let mut jj = 0;
+    #[max_iters(iterations)]
+    while !(jj == 1) {
+        jj = jj + 1;
+    }
+    assert_eq(acc, 5);
+}
+"#;
+    let field = |value: u64| ConstOverride::Field(VestaField::from(value));
+    compile_code(code, &[("start", field(3)), ("iterations", field(4))]).unwrap();
+
+    let err = compile_code(code, &[("start", field(5))])
+        .err()
+        .expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::DecreasingRange(5, 4)));
+    assert_eq!(err.span.start, code.find("start..4").unwrap());
+
+    let too_large = ConstOverride::Field(VestaField::from(1u64 << 32));
+    let err = compile_code(code, &[("iterations", too_large)])
+        .err()
+        .expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::InvalidRangeBound(name) if name == "iterations"));
+}
+
+#[test]
+fn test_constant_dependencies() -> miette::Result<()> {
+    // constants can refer to the constants declared after them
+    let code = r#"
+const doubled: Field = base * 2;
+const base: Field = 10;
+const both: [Field; 2] = [base, doubled];
+
+fn main(pub xx: Field) {
+    assert_eq(xx, both[0] + both[1]);
+}
+"#;
+
+    let public_inputs = parse_inputs(r#"{"xx": "30"}"#).unwrap();
+    compile_code(code, &[])
+        .unwrap()
+        .generate_witness(public_inputs, parse_inputs("{}").unwrap())?;
+
+    Ok(())
+}
+
+#[test]
+fn test_cyclic_constants() {
+    let code = r#"
+const aa: Field = bb + 1;
+const bb: Field = aa * 2;
+
+fn main(pub xx: Field) {
+    assert_eq(xx, aa);
+}
+"#;
+
+    let err = compile_code(code, &[]).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::CyclicConstant(name) if name == "aa"));
+    assert_eq!(err.span.start, code.find("aa").unwrap());
+}
+
+#[test]
+fn test_constant_dependencies_in_const_fn() -> miette::Result<()> {
+    // the constants read by the `const fn`s called are evaluated first
+    let code = r#"
+const scaled: Field = scale(3);
+
+const fn scale(value: Field) -> Field {
+    return value * factor;
+}
+
+const factor: Field = 5;
+
+fn main(pub xx: Field) {
+    assert_eq(xx, scaled);
+}
+"#;
+
+    let public_inputs = parse_inputs(r#"{"xx": "15"}"#).unwrap();
+    compile_code(code, &[])
+        .unwrap()
+        .generate_witness(public_inputs, parse_inputs("{}").unwrap())?;
+
+    // and so are the cycles through them
+    let code = r#"
+const aa: Field = twice();
+
+const fn twice() -> Field {
+    return aa * 2;
+}
+
+fn main(pub xx: Field) {
+    assert_eq(xx, aa);
+}
+"#;
+
+    let err = compile_code(code, &[]).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::CyclicConstant(name) if name == "aa"));
+
+    Ok(())
+}
+
+#[test]
+fn test_constant_folding() -> miette::Result<()> {
+    let folded = r#"
+fn main(pub xx: Field) {
+    let yy = (2 * 3 + 4) * 5 - 1;
+    let zz = -(6 / 3);
+    assert_eq(xx, yy + zz);
+}
+"#;
+    let literal = r#"
+fn main(pub xx: Field) {
+    assert_eq(xx, 47);
+}
+"#;
+    let folded_circuit = compile_code(folded, &[]).unwrap();
+    let literal_circuit = compile_code(literal, &[]).unwrap();
+
+    // the constant arithmetic doesn't take any gate
+    assert_eq!(folded_circuit.num_gates(), literal_circuit.num_gates());
+
+    // and every gate is satisfied by the folded value
+    let public_inputs = parse_inputs(r#"{"xx": "47"}"#).unwrap();
+    folded_circuit.generate_witness(public_inputs, parse_inputs("{}").unwrap())?;
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use crate::{
+    backends::mock::Mock,
+    compiler::{compile, typecheck_next_file, Sources},
+    equivalence::check_equivalence,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+const ORIGINAL: &str = r#"
+fn main(pub xx: Field, yy: Field) -> Field {
+    let sum = xx + yy;
+    return sum * sum;
+}
+"#;
+
+fn compile_code(code: &str) -> miette::Result<CompiledCircuit<Mock>> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<Mock>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "equivalence.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+    compile(&sources, tast, Mock::new())
+}
+
+#[test]
+fn test_equivalent_rewrite() -> miette::Result<()> {
+    let rewrite = r#"
+fn main(pub xx: Field, yy: Field) -> Field {
+    return xx * xx + 2 * xx * yy + yy * yy;
+}
+"#;
+
+    let original = compile_code(ORIGINAL)?;
+    let rewrite = compile_code(rewrite)?;
+    assert!(check_equivalence(&original, &rewrite, 20, 0)?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_divergent_rewrite() -> miette::Result<()> {
+    // forgets the `2 * xx * yy` term
+    let rewrite = r#"
+fn main(pub xx: Field, yy: Field) -> Field {
+    return xx * xx + yy * yy;
+}
+"#;
+
+    let original = compile_code(ORIGINAL)?;
+    let rewrite = compile_code(rewrite)?;
+    let divergence =
+        check_equivalence(&original, &rewrite, 20, 0)?.expect("the rewrite should diverge");
+
+    // both circuits accept the inputs, but return different outputs
+    let lhs_outputs = divergence
+        .lhs_outputs
+        .expect("the original accepts all inputs");
+    let rhs_outputs = divergence
+        .rhs_outputs
+        .expect("the rewrite accepts all inputs");
+    assert_ne!(lhs_outputs, rhs_outputs);
+
+    Ok(())
+}
+
+#[test]
+fn test_divergent_assertion() -> miette::Result<()> {
+    // rejects a zero `xx`
+    let rewrite = r#"
+fn main(pub xx: Field, yy: Field) -> Field {
+    let is_zero = xx == 0;
+    assert(!is_zero);
+    let sum = xx + yy;
+    return sum * sum;
+}
+"#;
+
+    let original = compile_code(ORIGINAL)?;
+    let rewrite = compile_code(rewrite)?;
+    let divergence =
+        check_equivalence(&original, &rewrite, 100, 0)?.expect("the rewrite should diverge");
+    assert!(divergence.lhs_outputs.is_some());
+    assert!(divergence.rhs_outputs.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_different_interfaces() -> miette::Result<()> {
+    // `yy` is public
+    let rewrite = r#"
+fn main(pub xx: Field, pub yy: Field) -> Field {
+    let sum = xx + yy;
+    return sum * sum;
+}
+"#;
+
+    let original = compile_code(ORIGINAL)?;
+    let rewrite = compile_code(rewrite)?;
+    assert!(check_equivalence(&original, &rewrite, 20, 0).is_err());
+
+    Ok(())
+}
@@ -8,6 +8,7 @@ use crate::{
         r1cs::R1CS,
         BackendKind,
     },
+    cli::cmd_build_and_check::run_test_fns,
     compiler::{compile, typecheck_next_file, Sources},
     inputs::{parse_inputs, ExtField},
     type_checker::TypeChecker,
@@ -152,6 +153,46 @@ fn test_file(
             }
         }
         BackendKind::R1csBn254(_) => todo!(),
+        BackendKind::Mock(mock) => {
+            // compile
+            let mut sources = Sources::new();
+            let mut tast = TypeChecker::new();
+            let _node_id = typecheck_next_file(
+                &mut tast,
+                None,
+                &mut sources,
+                file_name.to_string(),
+                code.clone(),
+                0,
+            )
+            .unwrap();
+
+            let compiled_circuit = compile(&sources, tast, mock)?;
+
+            // check every gate
+            let generated_witness = compiled_circuit
+                .generate_witness(public_inputs.clone(), private_inputs.clone())
+                .unwrap();
+            generated_witness.check().unwrap();
+
+            let expected_public_output = expected_public_output
+                .iter()
+                .map(|x| VestaField::from_str(x).unwrap())
+                .collect::<Vec<_>>();
+
+            if generated_witness.public_outputs != expected_public_output {
+                eprintln!("obtained by executing the circuit:");
+                generated_witness
+                    .public_outputs
+                    .iter()
+                    .for_each(|x| eprintln!("- {x}"));
+                eprintln!("passed as output by the verifier:");
+                expected_public_output
+                    .iter()
+                    .for_each(|x| eprintln!("- {x}"));
+                panic!("Obtained output does not match expected output");
+            }
+        }
     }
 
     Ok(())
@@ -159,6 +200,7 @@ fn test_file(
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_arithmetic(#[case] backend: BackendKind) -> miette::Result<()> {
     let public_inputs = r#"{"public_input": "2"}"#;
@@ -171,6 +213,7 @@ fn test_arithmetic(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_public_output(#[case] backend: BackendKind) -> miette::Result<()> {
     let public_inputs = r#"{"public_input": "1"}"#;
@@ -189,6 +232,7 @@ fn test_public_output(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_lc_return(#[case] backend: BackendKind) -> miette::Result<()> {
     let public_inputs = r#"{"public_input": "1"}"#;
@@ -207,11 +251,11 @@ fn test_lc_return(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 //todo: #[case::r1cs(BackendKind::R1CS(R1CS::new()))]
 fn test_poseidon(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{"private_input": ["1", "1"]}"#;
-    let private_input = [1.into(), 1.into()];
-    let digest = crate::helpers::poseidon(private_input.clone());
+    let digest = crate::helpers::poseidon(&[1.into(), 1.into()]);
     let digest_dec = digest.to_dec_string();
     assert_eq!(
         "3654913405619483358804575553468071097765421484960111776885779739261304758583",
@@ -227,6 +271,7 @@ fn test_poseidon(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_bool(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{"private_input": false}"#;
@@ -239,6 +284,7 @@ fn test_bool(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_mutable(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{"xx": "2", "yy": "3"}"#;
@@ -251,6 +297,7 @@ fn test_mutable(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_for_loop(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{"private_input": ["2", "3", "4"]}"#;
@@ -263,6 +310,7 @@ fn test_for_loop(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_array(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -275,6 +323,7 @@ fn test_array(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_equals(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -287,6 +336,7 @@ fn test_equals(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_types(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -299,6 +349,7 @@ fn test_types(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_const(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -318,6 +369,7 @@ fn test_const(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_functions(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -330,6 +382,7 @@ fn test_functions(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_methods(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -342,6 +395,7 @@ fn test_methods(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_types_array(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -360,6 +414,7 @@ fn test_types_array(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_iterate(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -379,6 +434,7 @@ fn test_iterate(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_assignment(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -391,6 +447,7 @@ fn test_assignment(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_if_else(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{}"#;
@@ -403,6 +460,7 @@ fn test_if_else(#[case] backend: BackendKind) -> miette::Result<()> {
 
 #[rstest]
 #[case::kimchi_vesta(BackendKind::KimchiVesta(KimchiVesta::new(false)))]
+#[case::mock(BackendKind::new_mock())]
 #[case::r1cs(BackendKind::R1csBls12_381(R1CS::new()))]
 fn test_sudoku(#[case] backend: BackendKind) -> miette::Result<()> {
     let private_inputs = r#"{"solution": { "inner": ["9", "5", "3", "6", "2", "1", "7", "8", "4", "1", "4", "8", "7", "5", "9", "2", "6", "3", "2", "7", "6", "8", "3", "4", "9", "5", "1", "3", "6", "9", "2", "7", "5", "4", "1", "8", "4", "8", "5", "9", "1", "6", "3", "7", "2", "7", "1", "2", "3", "4", "8", "6", "9", "5", "6", "3", "7", "1", "8", "2", "5", "4", "9", "5", "2", "1", "4", "9", "7", "8", "3", "6", "8", "9", "4", "5", "6", "3", "1", "2", "7"] }}"#;
@@ -412,3 +470,97 @@ fn test_sudoku(#[case] backend: BackendKind) -> miette::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_debug_asm_provenance() -> miette::Result<()> {
+    let prefix_examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let code = std::fs::read_to_string(prefix_examples.join("arithmetic.no")).unwrap();
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "arithmetic.no".to_string(),
+        code,
+        0,
+    )?;
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    // each gate is annotated with the location of the code that created it
+    let obtained: String = compiled_circuit
+        .asm(&sources, true)
+        .lines()
+        .filter(|line| line.starts_with("│ GATE"))
+        .map(|line| format!("{line}\n"))
+        .collect();
+
+    let expected =
+        std::fs::read_to_string(prefix_examples.join("fixture/asm/kimchi/debug/arithmetic.gates"))
+            .unwrap();
+    assert_eq!(obtained, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_debug_asm_label() -> miette::Result<()> {
+    let code = r#"
+fn main(pub xx: Field, yy: Field) {
+    let zz = yy + 1;
+    /// xx is the square of zz
+    assert_eq(xx, zz * zz);
+}
+"#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "label.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    let asm = compiled_circuit.asm(&sources, true);
+
+    // the gates created by the labeled statement are annotated with its label instead of their notes
+    let notes: Vec<_> = asm
+        .lines()
+        .filter_map(|line| line.strip_prefix("    ╰── "))
+        .collect();
+    assert!(notes.contains(&"xx is the square of zz"));
+    assert!(notes.iter().any(|note| *note != "xx is the square of zz"));
+
+    Ok(())
+}
+
+#[test]
+fn test_test_fns() -> miette::Result<()> {
+    let prefix_examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let code = std::fs::read_to_string(prefix_examples.join("test_fns.no")).unwrap();
+
+    let reports = run_test_fns("test_fns.no", &code)?;
+    let names: Vec<_> = reports.iter().map(|report| report.name.as_str()).collect();
+    assert_eq!(names, ["add", "sum_of_squares"]);
+    for report in &reports {
+        assert!(report.result.is_ok(), "test `{}` failed", report.name);
+    }
+
+    // a wrong expected value makes that test fail
+    let code = code.replace("output = 5", "output = 6");
+    let reports = run_test_fns("test_fns.no", &code)?;
+
+    let err = reports[0].result.as_ref().unwrap_err();
+    let help = err.help().map(|help| help.to_string());
+    assert_eq!(
+        help.as_deref(),
+        Some("the test function returned 5 instead of 6")
+    );
+    assert!(reports[1].result.is_ok());
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    circuit_writer::{CircuitOptions, CircuitWriter},
+    compiler::{typecheck_next_file_inner, Sources},
+    error::{ErrorKind, Result},
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+};
+
+fn typecheck_code(code: &str) -> Result<(Sources, TypeChecker<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "fold.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    Ok((sources, tast))
+}
+
+const FOLD_SUM: &str = r#"
+fn add(acc: Field, elem: Field) -> Field {
+    return acc + elem;
+}
+
+fn main(pub expected: Field, xx: [Field; 4]) -> Field {
+    let total = fold(xx, 0, add);
+    assert_eq(total, expected);
+    return total;
+}
+"#;
+
+#[test]
+fn test_fold_sum() -> miette::Result<()> {
+    let (sources, tast) = typecheck_code(FOLD_SUM).unwrap();
+
+    let compiled_circuit = CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        None,
+    )
+    .unwrap();
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    let public_inputs = parse_inputs(r#"{"expected": "10"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"xx": ["1", "2", "3", "4"]}"#).unwrap();
+
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    assert_eq!(public_output, vec![VestaField::from(10u64)]);
+
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    // the sum is constrained
+    let public_inputs = parse_inputs(r#"{"expected": "11"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"xx": ["1", "2", "3", "4"]}"#).unwrap();
+    assert!(prover_index
+        .prove(&sources, public_inputs, private_inputs, false)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_fold_signature_mismatch() {
+    let code = r#"
+fn add(acc: Field, elem: Bool) -> Field {
+    return acc + as_field(elem);
+}
+
+fn main(pub xx: [Field; 4]) -> Field {
+    return fold(xx, 0, add);
+}
+"#;
+
+    let err = typecheck_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::FoldSignatureMismatch(..)));
+}
+
+#[test]
+fn test_fold_not_a_function_name() {
+    let code = r#"
+fn main(pub xx: [Field; 4]) -> Field {
+    return fold(xx, 0, 1);
+}
+"#;
+
+    let err = typecheck_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::FoldFunctionNotNamed));
+}
@@ -0,0 +1,107 @@
+use num_bigint::BigUint;
+
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    compiler::{compile, typecheck_next_file, Sources},
+    inputs::{parse_inputs, JsonInputs},
+    type_checker::TypeChecker,
+};
+
+const FP2_CODE: &str = r#"
+use std::fp2;
+
+fn main(pub sum: [Field; 2], pub diff: [Field; 2], pub prod: [Field; 2], lhs: [Field; 2], rhs: [Field; 2]) {
+    let xx = fp2::new(lhs[0], lhs[1]);
+    let yy = fp2::new(rhs[0], rhs[1]);
+
+    let zz = fp2::add(xx, yy);
+    assert_eq(zz.re, sum[0]);
+    assert_eq(zz.im, sum[1]);
+
+    let zz = fp2::sub(xx, yy);
+    assert_eq(zz.re, diff[0]);
+    assert_eq(zz.im, diff[1]);
+
+    let zz = fp2::mul(xx, yy);
+    assert_eq(zz.re, prod[0]);
+    assert_eq(zz.im, prod[1]);
+}
+"#;
+
+type Fp2 = (VestaField, VestaField);
+
+/// The reference arithmetic, on `re + im * i` with `i^2 = -1`.
+fn reference(lhs: Fp2, rhs: Fp2) -> [Fp2; 3] {
+    let (aa, bb) = lhs;
+    let (cc, dd) = rhs;
+    [
+        (aa + cc, bb + dd),
+        (aa - cc, bb - dd),
+        (aa * cc - bb * dd, aa * dd + bb * cc),
+    ]
+}
+
+fn to_json((re, im): Fp2) -> String {
+    format!(r#"["{}", "{}"]"#, BigUint::from(re), BigUint::from(im))
+}
+
+fn inputs(lhs: Fp2, rhs: Fp2, expected: [Fp2; 3]) -> (JsonInputs, JsonInputs) {
+    let [sum, diff, prod] = expected;
+    let public_inputs = parse_inputs(&format!(
+        r#"{{"sum": {}, "diff": {}, "prod": {}}}"#,
+        to_json(sum),
+        to_json(diff),
+        to_json(prod)
+    ))
+    .unwrap();
+    let private_inputs = parse_inputs(&format!(
+        r#"{{"lhs": {}, "rhs": {}}}"#,
+        to_json(lhs),
+        to_json(rhs)
+    ))
+    .unwrap();
+    (public_inputs, private_inputs)
+}
+
+#[test]
+fn test_fp2_arithmetic() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "fp2.no".to_string(),
+        FP2_CODE.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    let test_vectors = [
+        ((3u64, 5u64), (7u64, 11u64)),
+        ((0, 1), (0, 1)),
+        ((u64::MAX, 2), (u64::MAX, u64::MAX)),
+    ];
+    for (lhs, rhs) in test_vectors {
+        let lhs = (VestaField::from(lhs.0), VestaField::from(lhs.1));
+        let rhs = (VestaField::from(rhs.0), VestaField::from(rhs.1));
+
+        let (public_inputs, private_inputs) = inputs(lhs, rhs, reference(lhs, rhs));
+        compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap();
+    }
+
+    // `i * i = -1`, and not `1`
+    let one = VestaField::from(1u64);
+    let ii = (VestaField::from(0u64), one);
+    let mut expected = reference(ii, ii);
+    expected[2] = (one, VestaField::from(0u64));
+    let (public_inputs, private_inputs) = inputs(ii, ii, expected);
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    Ok(())
+}
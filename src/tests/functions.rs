@@ -0,0 +1,104 @@
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    compiler::{compile, typecheck_next_file, Sources},
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+fn compile_code(code: &str) -> miette::Result<(Sources, CompiledCircuit<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "functions.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+    let compiled = compile(&sources, tast, KimchiVesta::new(false))?;
+    Ok((sources, compiled))
+}
+
+#[test]
+fn test_spliced_function_calls() -> miette::Result<()> {
+    let calls = r#"
+fn mul_add(xx: Field, yy: Field) -> Field {
+    let zz = xx * yy + xx;
+    let ww = yy * xx + xx;
+    assert_eq(zz, ww);
+    return ww;
+}
+
+fn main(pub aa: Field, bb: Field) -> Field {
+    let xx = mul_add(aa, bb);
+    let yy = mul_add(xx, bb);
+    return mul_add(yy, aa);
+}
+"#;
+    let inlined = r#"
+fn main(pub aa: Field, bb: Field) -> Field {
+    let z1 = aa * bb + aa;
+    let xx = bb * aa + aa;
+    assert_eq(z1, xx);
+    let z2 = xx * bb + xx;
+    let yy = bb * xx + xx;
+    assert_eq(z2, yy);
+    let z3 = yy * aa + yy;
+    let ww = aa * yy + yy;
+    assert_eq(z3, ww);
+    return ww;
+}
+"#;
+
+    // the last two calls are spliced from the first one
+    let (sources, calls) = compile_code(calls)?;
+    assert_eq!(calls.circuit.fn_cache_hits, 2);
+
+    // with the same gates, cells and wiring as if the function was inlined
+    let (inlined_sources, inlined) = compile_code(inlined)?;
+    assert_eq!(
+        calls.asm(&sources, false),
+        inlined.asm(&inlined_sources, false)
+    );
+    assert_eq!(calls.wiring_cycles(), inlined.wiring_cycles());
+
+    // and the spliced cells are computed (and wired) correctly
+    let (prover_index, verifier_index) = calls.compile_to_indexes()?;
+    let public_inputs = parse_inputs(r#"{"aa": "2"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"bb": "3"}"#).unwrap();
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+
+    // xx = 2 * 3 + 2, yy = 8 * 3 + 8, ww = 32 * 2 + 32
+    assert_eq!(public_output, vec![VestaField::from(96)]);
+    verifier_index.verify(full_public_inputs, proof)
+}
+
+#[test]
+fn test_function_calls_with_other_constants() -> miette::Result<()> {
+    let code = r#"
+fn scale(xx: Field, factor: Field) -> Field {
+    return xx * factor;
+}
+
+fn main(pub aa: Field, bb: Field) -> Field {
+    let xx = scale(aa, 2);
+    let yy = scale(bb, 3);
+    let zz = scale(xx, 2);
+    return zz + yy;
+}
+"#;
+
+    // only the calls passed the same constant create the same constraints
+    let (_, compiled) = compile_code(code)?;
+    assert_eq!(compiled.circuit.fn_cache_hits, 1);
+
+    let public_inputs = parse_inputs(r#"{"aa": "1"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"bb": "5"}"#).unwrap();
+    let witness = compiled.generate_witness(public_inputs, private_inputs)?;
+    assert_eq!(witness.public_outputs, vec![VestaField::from(19)]);
+
+    Ok(())
+}
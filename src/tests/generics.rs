@@ -0,0 +1,108 @@
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    circuit_writer::{CircuitOptions, CircuitWriter},
+    compiler::{typecheck_next_file_inner, Sources},
+    error::{ErrorKind, Result},
+    inputs::parse_inputs,
+    type_checker::{FullyQualified, TypeChecker},
+};
+
+fn typecheck_code(code: &str) -> Result<(Sources, TypeChecker<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "generics.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    Ok((sources, tast))
+}
+
+const GENERIC_SUM: &str = r#"
+fn sum[N](arr: [Field; N]) -> Field {
+    let mut acc = 0;
+    for ii in 0..N {
+        acc = acc + arr[ii];
+    }
+    return acc;
+}
+
+fn main(pub expected: Field, xx: [Field; 3], yy: [Field; 2]) -> Field {
+    let total = sum(xx) + sum(yy);
+    assert_eq(total, expected);
+    return total;
+}
+"#;
+
+#[test]
+fn test_generic_fn_with_two_sizes() -> miette::Result<()> {
+    let (sources, tast) = typecheck_code(GENERIC_SUM).unwrap();
+
+    // each size gets its own instance
+    for instance in ["sum[3]", "sum[2]"] {
+        let fn_info = tast
+            .fn_info(&FullyQualified::local(instance.to_string()))
+            .expect("the instance should have been type checked");
+        assert!(fn_info.sig().generics.is_empty());
+    }
+
+    let compiled_circuit = CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        None,
+    )
+    .unwrap();
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    let public_inputs = parse_inputs(r#"{"expected": "15"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"xx": ["1", "2", "3"], "yy": ["4", "5"]}"#).unwrap();
+
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    assert_eq!(public_output, vec![VestaField::from(15u64)]);
+
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_generic_conflicting_sizes() {
+    let code = r#"
+fn dot[N](lhs: [Field; N], rhs: [Field; N]) -> Field {
+    let mut acc = 0;
+    for ii in 0..N {
+        acc = acc + (lhs[ii] * rhs[ii]);
+    }
+    return acc;
+}
+
+fn main(pub xx: [Field; 3], yy: [Field; 2]) -> Field {
+    return dot(xx, yy);
+}
+"#;
+
+    let err = typecheck_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::ConflictingGeneric(name, 3, 2) if name == "N"));
+}
+
+#[test]
+fn test_undefined_generic() {
+    let code = r#"
+fn sum(arr: [Field; N]) -> Field {
+    return arr[0];
+}
+
+fn main(pub xx: [Field; 3]) -> Field {
+    return sum(xx);
+}
+"#;
+
+    let err = typecheck_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::UndefinedGeneric(name) if name == "N"));
+}
@@ -0,0 +1,494 @@
+use crate::{
+    backends::{
+        kimchi::{KimchiVesta, VestaField},
+        r1cs::{R1csBls12381Field, R1CS},
+    },
+    circuit_writer::CircuitOptions,
+    compiler::{
+        compile, compile_with_options, typecheck_next_file, typecheck_next_file_inner, Sources,
+    },
+    error::ErrorKind,
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+//
+// `if`/`else` expressions with computed branches
+//
+
+const IF_ELSE_EXPR: &str = r#"
+fn checked_double(xx: Field) -> Field {
+    // only holds when this branch is taken
+    assert_eq(xx, 1);
+    return xx * 2;
+}
+
+fn main(pub expected: Field, xx: Field) {
+    let is_one = xx == 1;
+    let yy = if is_one { checked_double(xx) } else { xx + 10 };
+
+    // branches can be nested
+    let is_big = xx == 5;
+    let zz = if is_one { yy } else { if is_big { yy * 2 } else { 0 } };
+
+    assert_eq(yy + zz, expected);
+}
+"#;
+
+#[test]
+fn test_if_else_expr_kimchi() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "if_else_expr.no".to_string(),
+        IF_ELSE_EXPR.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    // the `else` branch is taken, so the assertion of the `if` branch is not enforced
+    let public_inputs = parse_inputs(r#"{"expected": "45"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"xx": "5"}"#).unwrap();
+
+    let (proof, full_public_inputs, _public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_if_else_expr_r1cs() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "if_else_expr.no".to_string(),
+        IF_ELSE_EXPR.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+    for (xx, expected) in [("1", "4"), ("5", "45"), ("7", "17")] {
+        let public_inputs = parse_inputs(&format!(r#"{{"expected": "{expected}"}}"#)).unwrap();
+        let private_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+        compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap();
+    }
+
+    // the selected value is still constrained
+    let public_inputs = parse_inputs(r#"{"expected": "0"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"xx": "7"}"#).unwrap();
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_if_else_expr_assertion_in_taken_branch() -> miette::Result<()> {
+    let code = r#"
+    fn assert_and_return(val: Field, expected: Field) -> Field {
+        assert_eq(val, expected);
+        return val;
+    }
+
+    fn main(pub xx: Field) -> Field {
+        let big = xx == 5;
+        let yy = if big { xx } else { xx + 1 };
+
+        // fails whenever the `if` branch is taken
+        let zz = if big { assert_and_return(yy, 6) } else { yy };
+        return zz;
+    }
+    "#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "assertion_in_branch.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+    let public_inputs = parse_inputs(r#"{"xx": "1"}"#).unwrap();
+    compiled_circuit
+        .generate_witness(public_inputs, parse_inputs("{}").unwrap())
+        .unwrap();
+
+    let public_inputs = parse_inputs(r#"{"xx": "5"}"#).unwrap();
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, parse_inputs("{}").unwrap())
+        .is_err());
+
+    Ok(())
+}
+
+//
+// early returns in `if` statements
+//
+
+const EARLY_RETURN: &str = r#"
+fn choose(cond: Bool, aa: Field, bb: Field) -> Field {
+    if cond {
+        return aa;
+    }
+    return bb;
+}
+
+fn main(pub aa: Field, pub bb: Field, cond: Bool) -> Field {
+    return choose(cond, aa, bb);
+}
+"#;
+
+#[test]
+fn test_early_return() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "early_return.no".to_string(),
+        EARLY_RETURN.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    for (cond, expected) in [(true, 3u64), (false, 5)] {
+        let public_inputs = parse_inputs(r#"{"aa": "3", "bb": "5"}"#).unwrap();
+        let private_inputs = parse_inputs(&format!(r#"{{"cond": {cond}}}"#)).unwrap();
+
+        let (proof, full_public_inputs, public_output) =
+            prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+        assert_eq!(public_output, vec![VestaField::from(expected)]);
+        verifier_index.verify(full_public_inputs, proof)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_early_return_skips_what_follows() -> miette::Result<()> {
+    let code = r#"
+    fn main(pub xx: Field) -> Field {
+        let mut acc = xx;
+        if xx == 3 {
+            acc = acc + 1;
+        }
+
+        // the division only has to hold if the function didn't return
+        if xx == 0 {
+            return acc;
+        }
+        return div(acc, xx);
+    }
+    "#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "early_return_division.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    let four_thirds = VestaField::from(4u64) / VestaField::from(3u64);
+    for (xx, expected) in [
+        (0u64, VestaField::from(0u64)),
+        (3, four_thirds),
+        (6, VestaField::from(1u64)),
+    ] {
+        let public_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+        let witness = compiled_circuit
+            .generate_witness(public_inputs, parse_inputs("{}").unwrap())
+            .unwrap();
+        assert_eq!(witness.public_outputs, vec![expected]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_statement_after_early_return() {
+    let code = r#"
+    fn main(pub xx: Field) -> Field {
+        if xx == 1 {
+            return xx;
+            assert_eq(xx, 1);
+        }
+        return 0;
+    }
+    "#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let err = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "statement_after_return.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::StatementAfterReturn));
+}
+
+#[test]
+fn test_missing_return_after_early_return() {
+    // nothing is returned when `xx` isn't 1
+    let code = r#"
+    fn one_or_nothing(xx: Field) -> Field {
+        if xx == 1 {
+            return xx;
+        }
+    }
+
+    fn main(pub xx: Field) {
+        assert_eq(one_or_nothing(xx), 1);
+    }
+    "#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let err = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "missing_return.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::MissingReturn));
+
+    // the error points at the function
+    assert_eq!(err.span.start, code.find("one_or_nothing").unwrap());
+}
+
+//
+// `select(cond, a, b)`
+//
+
+const SELECT_POINTS: &str = r#"
+struct Point {
+    xx: Field,
+    yy: Field,
+}
+
+fn main(pub expected_x: Field, pub expected_y: Field, cond: Bool, aa: [Field; 2], bb: [Field; 2]) {
+    let lhs = Point { xx: aa[0], yy: aa[1] };
+    let rhs = Point { xx: bb[0], yy: bb[1] };
+
+    let res = select(cond, lhs, rhs);
+    assert_eq(res.xx, expected_x);
+    assert_eq(res.yy, expected_y);
+}
+"#;
+
+#[test]
+fn test_select_structs() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "select.no".to_string(),
+        SELECT_POINTS.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    let prove = |cond: bool, expected: (u64, u64)| {
+        let public_inputs = parse_inputs(&format!(
+            r#"{{"expected_x": "{}", "expected_y": "{}"}}"#,
+            expected.0, expected.1
+        ))
+        .unwrap();
+        let private_inputs = parse_inputs(&format!(
+            r#"{{"cond": {cond}, "aa": ["1", "2"], "bb": ["3", "4"]}}"#
+        ))
+        .unwrap();
+        prover_index.prove(&sources, public_inputs, private_inputs, false)
+    };
+
+    // each coordinate comes from the selected point
+    for (cond, expected) in [(true, (1, 2)), (false, (3, 4))] {
+        let (proof, full_public_inputs, _) = prove(cond, expected)?;
+        verifier_index.verify(full_public_inputs, proof)?;
+    }
+
+    // and not from the other one
+    assert!(prove(true, (1, 4)).is_err());
+    assert!(prove(false, (3, 2)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_select_type_mismatch() {
+    let code = r#"
+    fn main(pub cond: Bool, xx: Field, yy: [Field; 2]) {
+        let res = select(cond, xx, yy);
+    }
+    "#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let err = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "select_mismatch.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::SelectTypeMismatch(..)));
+}
+
+#[test]
+fn test_select_is_reserved() {
+    // a function of the user can't be called `select` anymore, as calls to it would refer to the builtin
+    let code = r#"
+    fn select(cond: Bool, xx: Field, yy: Field) -> Field {
+        let res = if cond { xx } else { yy };
+        return res;
+    }
+
+    fn main(pub cond: Bool, xx: Field) {
+        assert_eq(select(cond, xx, 1), xx);
+    }
+    "#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let err = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "select_reserved.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::ShadowingBuiltIn(name) if name == "select"));
+    assert_eq!(&code[err.span.start..err.span.end()], "select");
+}
+
+//
+// relaxed branches
+//
+
+const RELAXED_BRANCHES: &str = r#"
+fn main(pub xx: U32, pub idx: Field, pub flag: Bool) -> (U32, Field) {
+    // underflows if `xx < 10`
+    let yy = if flag { xx - 10 } else { xx + 1 };
+
+    // out of bounds if `idx > 2`
+    let arr: [Field; 3] = [1, 2, 3];
+    let none: Field = 0;
+    let zz = select(flag, arr[idx], none);
+
+    return (yy, zz);
+}
+"#;
+
+fn compile_relaxed_branches(
+    relaxed_branches: bool,
+) -> miette::Result<(Sources, CompiledCircuit<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "relaxed_branches.no".to_string(),
+        RELAXED_BRANCHES.to_string(),
+        0,
+    )?;
+
+    let options = CircuitOptions {
+        relaxed_branches,
+        ..Default::default()
+    };
+    let compiled_circuit = compile_with_options(&sources, tast, KimchiVesta::new(false), options)?;
+
+    Ok((sources, compiled_circuit))
+}
+
+#[test]
+fn test_relaxed_branches() -> miette::Result<()> {
+    let outputs =
+        |compiled_circuit: &CompiledCircuit<KimchiVesta>, xx: u64, idx: u64, flag: bool| {
+            let public_inputs = parse_inputs(&format!(
+                r#"{{"xx": "{xx}", "idx": "{idx}", "flag": {flag}}}"#
+            ))
+            .unwrap();
+            let private_inputs = parse_inputs("{}").unwrap();
+            compiled_circuit
+                .generate_witness(public_inputs, private_inputs)
+                .map(|witness| witness.public_outputs)
+        };
+
+    // by default, the branch that isn't taken must be satisfied too
+    let (_, strict) = compile_relaxed_branches(false)?;
+    assert!(outputs(&strict, 3, 1, false).is_err());
+    assert!(outputs(&strict, 13, 7, false).is_err());
+    assert_eq!(
+        outputs(&strict, 13, 1, true).unwrap(),
+        vec![VestaField::from(3u64), VestaField::from(2u64)]
+    );
+
+    // relaxed, only the branch taken must be
+    let (sources, relaxed) = compile_relaxed_branches(true)?;
+    assert_eq!(
+        outputs(&relaxed, 3, 7, false).unwrap(),
+        vec![VestaField::from(4u64), VestaField::from(0u64)]
+    );
+    assert_eq!(
+        outputs(&relaxed, 13, 1, true).unwrap(),
+        vec![VestaField::from(3u64), VestaField::from(2u64)]
+    );
+
+    // the branch taken is still fully constrained
+    assert!(outputs(&relaxed, 3, 1, true).is_err());
+    assert!(outputs(&relaxed, 13, 7, true).is_err());
+
+    // and the proof of a relaxed witness verifies
+    let (prover_index, verifier_index) = relaxed.compile_to_indexes()?;
+    let public_inputs = parse_inputs(r#"{"xx": "3", "idx": "7", "flag": false}"#).unwrap();
+    let private_inputs = parse_inputs("{}").unwrap();
+    let (proof, full_public_inputs, _) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    circuit_writer::{CircuitOptions, CircuitWriter},
+    compiler::{typecheck_next_file_inner, Sources},
+    error::{ErrorKind, Result},
+    inputs::parse_inputs,
+    parser::types::TyKind,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+use super::is_forged_witness_rejected;
+
+const SUM_CODE: &str = r#"
+fn main(pub xx: U32, yy: U32) -> U32 {
+    let zz = xx + yy;
+    return zz;
+}
+"#;
+
+fn compile_code(code: &str) -> Result<CompiledCircuit<KimchiVesta>> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "integers.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+    CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        None,
+    )
+}
+
+#[test]
+fn test_u32_addition() -> miette::Result<()> {
+    let compiled_circuit = compile_code(SUM_CODE).unwrap();
+
+    // the largest `U32` is `2^32 - 1`
+    let public_inputs = parse_inputs(r#"{"xx": "4294967290"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "5"}"#).unwrap();
+    let witness = compiled_circuit.generate_witness(public_inputs, private_inputs)?;
+    assert_eq!(
+        witness.public_outputs,
+        vec![VestaField::from(4294967295u64)]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_u32_addition_overflow() {
+    let compiled_circuit = compile_code(SUM_CODE).unwrap();
+
+    let public_inputs = parse_inputs(r#"{"xx": "4294967295"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "1"}"#).unwrap();
+    let err = compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .err()
+        .expect("expected an error");
+
+    // the error points at the addition
+    let overflow = ErrorKind::IntegerOverflow(TyKind::U32).to_string();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::UnsatisfiedAssertion { message, .. } if message == overflow
+    ));
+    assert_eq!(err.span.start, SUM_CODE.find("xx + yy").unwrap());
+
+    // an input that doesn't fit is rejected before generating the witness
+    let public_inputs = parse_inputs(r#"{"xx": "4294967296"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "1"}"#).unwrap();
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+}
+
+#[test]
+fn test_integer_constant_overflow() {
+    let code = r#"
+fn main(pub xx: U64) {
+    let max: U64 = 18446744073709551616;
+    assert_eq(xx, max);
+}
+"#;
+
+    let err = compile_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::IntegerOverflow(TyKind::U64)));
+    assert_eq!(err.span.start, code.find("18446744073709551616").unwrap());
+}
+
+#[test]
+fn test_integer_constant_coercion_overflow() {
+    // a literal passed as an argument
+    let code = r#"
+fn double(xx: U32) -> U32 {
+    return xx + xx;
+}
+
+fn main(pub xx: U32) {
+    assert_eq(double(4294967296), xx);
+}
+"#;
+
+    let err = compile_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::IntegerOverflow(TyKind::U32)));
+    assert_eq!(err.span.start, code.find("4294967296").unwrap());
+
+    // a literal used as a field of a struct
+    let code = r#"
+struct Counter {
+    count: U64,
+}
+
+fn main(pub xx: U64) {
+    let counter = Counter { count: 18446744073709551616 };
+    assert_eq(counter.count, xx);
+}
+"#;
+
+    let err = compile_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::IntegerOverflow(TyKind::U64)));
+    assert_eq!(err.span.start, code.find("18446744073709551616").unwrap());
+
+    // the largest values fit
+    let code = r#"
+fn double(xx: U32) -> U32 {
+    return xx + 0;
+}
+
+fn main(pub xx: U32) {
+    assert_eq(double(4294967295), xx);
+}
+"#;
+    assert!(compile_code(code).is_ok());
+}
+
+#[test]
+fn test_u32_modulo() -> miette::Result<()> {
+    let code = r#"
+fn main(pub xx: U32) -> U32 {
+    return xx % 5;
+}
+"#;
+    let compiled_circuit = compile_code(code).unwrap();
+
+    for (xx, expected) in [(17u64, 2u64), (15, 0), (3, 3), (4294967295, 0)] {
+        let public_inputs = parse_inputs(&format!(r#"{{"xx": "{xx}"}}"#)).unwrap();
+        let private_inputs = parse_inputs("{}").unwrap();
+        let witness = compiled_circuit.generate_witness(public_inputs, private_inputs)?;
+        assert_eq!(witness.public_outputs, vec![VestaField::from(expected)]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_modulo_range_checks_the_remainder() {
+    let num_gates = |modulus: u64| {
+        let code = format!("fn main(pub xx: U32) -> U32 {{ return xx % {modulus}; }}");
+        compile_code(&code).unwrap().circuit.backend.num_gates()
+    };
+
+    // both quotients fit in 29 bits, but `r < 9` needs a second decomposition of the remainder
+    // while `r < 8` only needs one
+    assert!(num_gates(8) < num_gates(9));
+}
+
+#[test]
+fn test_modulo_errors() {
+    let code = r#"
+fn main(pub xx: U32, yy: U32) -> U32 {
+    return xx % yy;
+}
+"#;
+    let err = compile_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::NonConstantModulus));
+    assert_eq!(err.span.start, code.find("xx % yy").unwrap());
+
+    let code = r#"
+fn main(pub xx: U32) -> U32 {
+    return xx % 0;
+}
+"#;
+    let err = compile_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::ZeroModulus));
+
+    let code = r#"
+const zero: Field = 4 % 0;
+
+fn main(pub xx: Field) {
+    assert_eq(xx, zero);
+}
+"#;
+    let err = compile_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::ZeroModulus));
+}
+
+#[test]
+fn test_modulo_forged_remainder() -> miette::Result<()> {
+    let code = r#"
+fn main(pub xx: Field) -> Field {
+    return xx % 5;
+}
+"#;
+    let (prover_index, _verifier_index) = compile_code(code).unwrap().compile_to_indexes()?;
+
+    // the first hints are the quotient and the remainder
+    let hints =
+        |quotient: u64, remainder: u64| [VestaField::from(quotient), VestaField::from(remainder)];
+    assert!(!is_forged_witness_rejected(
+        &prover_index,
+        r#"{"xx": "13"}"#,
+        "{}",
+        &hints(2, 3)
+    ));
+
+    // `13 = 1 * 5 + 8 = 0 * 5 + 13`, but the remainders are not smaller than the modulus
+    for (quotient, remainder) in [(1, 8), (0, 13)] {
+        assert!(is_forged_witness_rejected(
+            &prover_index,
+            r#"{"xx": "13"}"#,
+            "{}",
+            &hints(quotient, remainder)
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use crate::{
+    backends::{
+        kimchi::KimchiVesta,
+        r1cs::{R1csBls12381Field, R1CS},
+    },
+    circuit_writer::GateKind,
+    compiler::{compile, typecheck_next_file, Sources},
+    inputs::{parse_inputs, JsonInputs},
+    type_checker::TypeChecker,
+};
+
+//
+// Keccak-256
+//
+
+const KECCAK256_CODE: &str = r#"
+use std::crypto;
+
+fn main(pub expected: [Field; 2], input: [Field; 3]) {
+    let digest = crypto::keccak256(input);
+    assert_eq(digest[0], expected[0]);
+    assert_eq(digest[1], expected[1]);
+}
+"#;
+
+/// Test vectors computed with a reference implementation,
+/// where the digest is split into its two 128-bit halves (in big-endian).
+const TEST_VECTORS: &[([u64; 3], [&str; 2])] = &[
+    // keccak256("abc") = 0x4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45
+    (
+        [97, 98, 99],
+        [
+            "103697418823508545859720359434091288167",
+            "256301648191211136937481889424564120645",
+        ],
+    ),
+    (
+        [255, 255, 255],
+        [
+            "169609462188638650022837534593344953088",
+            "276010848164630983876799103105423213722",
+        ],
+    ),
+];
+
+fn inputs(input: &[u64], expected: &[&str; 2]) -> (JsonInputs, JsonInputs) {
+    let public_inputs = parse_inputs(&format!(
+        r#"{{"expected": ["{}", "{}"]}}"#,
+        expected[0], expected[1]
+    ))
+    .unwrap();
+    let input: Vec<_> = input.iter().map(|byte| format!(r#""{byte}""#)).collect();
+    let private_inputs = parse_inputs(&format!(r#"{{"input": [{}]}}"#, input.join(", "))).unwrap();
+    (public_inputs, private_inputs)
+}
+
+#[test]
+fn test_keccak256_r1cs() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "keccak.no".to_string(),
+        KECCAK256_CODE.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+    for (input, expected) in TEST_VECTORS {
+        let (public_inputs, private_inputs) = inputs(input, expected);
+        compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap();
+    }
+
+    // wrong digest
+    let (input, _) = TEST_VECTORS[0];
+    let (public_inputs, private_inputs) = inputs(&input, &TEST_VECTORS[1].1);
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    // an input that doesn't fit in a byte
+    let (public_inputs, private_inputs) = inputs(&[97, 98, 256], &TEST_VECTORS[0].1);
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_keccak256_kimchi() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "keccak.no".to_string(),
+        KECCAK256_CODE.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(true))?;
+
+    // only generic gates are used
+    let stats = compiled_circuit.gate_stats();
+    assert!(!stats.gates.contains_key(&GateKind::Poseidon));
+
+    // the proof of a known digest verifies
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+    let (input, expected) = TEST_VECTORS[0];
+    let (public_inputs, private_inputs) = inputs(&input, &expected);
+    let (proof, full_public_inputs, _public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use crate::{
+    backends::kimchi::KimchiVesta,
+    compiler::{typecheck_next_file_inner, Sources},
+    error::{ErrorKind, Result},
+    parser::types::TyKind,
+    type_checker::TypeChecker,
+};
+
+fn typecheck_code(code: &str) -> Result<TypeChecker<KimchiVesta>> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "let_annotations.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    Ok(tast)
+}
+
+#[test]
+fn test_annotated_let() {
+    let code = r#"
+type Triple = [Field; 3];
+
+struct Thing {
+    xx: Field,
+}
+
+fn triple(xx: Field) -> [Field; 3] {
+    return [xx, xx, xx];
+}
+
+fn main(pub xx: Field) {
+    let arr: [Field; 3] = triple(xx);
+    let aliased: Triple = arr;
+    let thing: Thing = Thing { xx: aliased[0] };
+    let mut acc: Field = 0;
+    acc = acc + thing.xx;
+    let is_one: Bool = acc == 1;
+    assert(is_one);
+}
+"#;
+
+    typecheck_code(code).unwrap();
+}
+
+#[test]
+fn test_annotated_let_mismatch() {
+    let code = r#"
+fn main(pub xx: Field) {
+    let yy: Bool = xx + 1;
+    assert(yy);
+}
+"#;
+
+    let err = typecheck_code(code).err().expect("expected an error");
+    assert!(matches!(
+        err.kind,
+        ErrorKind::MismatchType(TyKind::Bool, TyKind::Field)
+    ));
+
+    // the error points at the value that doesn't match the annotation
+    assert_eq!(err.span.start, code.find("xx + 1").unwrap());
+}
+
+#[test]
+fn test_annotated_let_keeps_inferred_type() {
+    // `yy` is still a `U32` (as in the circuit), so it can be passed as one
+    let code = r#"
+fn double(xx: U32) -> U32 {
+    return xx + xx;
+}
+
+fn main(pub xx: U32) {
+    let yy: Field = xx;
+    let zz = double(yy);
+    assert_eq(zz, 4);
+}
+"#;
+
+    typecheck_code(code).unwrap();
+
+    // while a constant takes the annotated type
+    let code = r#"
+fn main(pub xx: Field) {
+    let mut acc: Field = 0;
+    acc = acc + xx;
+    assert_eq(acc, 1);
+}
+"#;
+
+    typecheck_code(code).unwrap();
+}
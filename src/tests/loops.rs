@@ -0,0 +1,318 @@
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    circuit_writer::{CircuitOptions, CircuitWriter, DEFAULT_LOOP_BUDGET},
+    compiler::{typecheck_next_file_inner, Sources},
+    error::{ErrorKind, Result},
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+fn compile_code(
+    code: &str,
+    options: CircuitOptions,
+) -> Result<(Sources, CompiledCircuit<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "loops.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit =
+        CircuitWriter::generate_circuit(tast, KimchiVesta::new(false), options, None)?;
+    Ok((sources, compiled_circuit))
+}
+
+//
+// Constant bounds
+//
+
+const CONST_BOUNDS: &str = r#"
+const start = 1;
+const size = 5;
+
+fn main(pub expected: Field, xx: Field) -> Field {
+    let mut acc = 0;
+    for ii in start..size {
+        acc = acc + xx;
+    }
+    assert_eq(acc, expected);
+    return acc;
+}
+"#;
+
+#[test]
+fn test_loop_with_constant_bounds() -> miette::Result<()> {
+    let (sources, compiled_circuit) =
+        compile_code(CONST_BOUNDS, CircuitOptions::default()).unwrap();
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    // the loop is unrolled 4 times
+    let public_inputs = parse_inputs(r#"{"expected": "12"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    assert_eq!(public_output, vec![VestaField::from(12u64)]);
+
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_loop_with_constant_bounds_matches_literals() {
+    let literal = CONST_BOUNDS.replace("start..size", "1..5");
+
+    let (_, with_consts) = compile_code(CONST_BOUNDS, CircuitOptions::default()).unwrap();
+    let (_, with_literals) = compile_code(&literal, CircuitOptions::default()).unwrap();
+
+    assert_eq!(
+        with_consts.circuit.backend.num_gates(),
+        with_literals.circuit.backend.num_gates()
+    );
+}
+
+//
+// Loop budget
+//
+
+#[test]
+fn test_loop_budget_exceeded() {
+    let code = r#"
+const huge = 1000000;
+
+fn main(pub xx: Field) {
+    let mut acc = 0;
+    for ii in 0..huge {
+        acc = acc + xx;
+    }
+    assert_eq(acc, 0);
+}
+"#;
+
+    let res = compile_code(code, CircuitOptions::default());
+    let err = res.err().expect("the loop should exceed the budget");
+    assert!(matches!(err.kind, ErrorKind::LoopBudgetExceeded(_)));
+}
+
+#[test]
+fn test_loop_budget_counts_every_loop() {
+    let code = r#"
+fn main(pub xx: Field) {
+    let mut acc = 0;
+    for ii in 0..3 {
+        for jj in 0..3 {
+            acc = acc + xx;
+        }
+    }
+    assert_eq(acc, 0);
+}
+"#;
+
+    // 3 iterations of the outer loop, plus 3 * 3 of the inner one
+    let options = |loop_budget| CircuitOptions {
+        loop_budget,
+        ..Default::default()
+    };
+    assert!(compile_code(code, options(12)).is_ok());
+
+    let err = compile_code(code, options(11)).err().unwrap();
+    assert!(matches!(err.kind, ErrorKind::LoopBudgetExceeded(11)));
+}
+
+//
+// Computed constants
+//
+
+const COMPUTED_CONST: &str = r#"
+const size = 6;
+
+const fn factorial() -> Field {
+    let mut acc = 1;
+    for ii in 1..size {
+        acc = acc * ii;
+    }
+    return acc;
+}
+
+const fn pow2(exp: Field) -> Field {
+    return if exp == 0 { 1 } else { 2 * pow2(exp - 1) };
+}
+
+const fact: Field = factorial();
+const mask: Field = pow2(8) - 1;
+
+fn main(pub xx: Field) -> Field {
+    assert_eq(xx, fact);
+    return xx + mask;
+}
+"#;
+
+#[test]
+fn test_computed_const() -> miette::Result<()> {
+    let (sources, compiled_circuit) =
+        compile_code(COMPUTED_CONST, CircuitOptions::default()).unwrap();
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    // 5! = 120, and 120 + 255 = 375
+    let public_inputs = parse_inputs(r#"{"xx": "120"}"#).unwrap();
+    let private_inputs = parse_inputs("{}").unwrap();
+
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    assert_eq!(public_output, vec![VestaField::from(375u64)]);
+
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_computed_const_not_evaluable() {
+    let code = r#"
+const fn checked() -> Field {
+    assert_eq(1, 1);
+    return 1;
+}
+
+const one = checked();
+
+fn main(pub xx: Field) {
+    assert_eq(xx, one);
+}
+"#;
+
+    let err = compile_code(code, CircuitOptions::default()).err().unwrap();
+    assert!(matches!(err.kind, ErrorKind::NotConstEvaluable(_)));
+}
+
+#[test]
+fn test_computed_const_loop_budget() {
+    // the iterations of the `const fn`s called are counted as well
+    let code = r#"
+const fn inner() -> Field {
+    let mut acc = 0;
+    for ii in 0..1000 {
+        acc = acc + 1;
+    }
+    return acc;
+}
+
+const fn outer() -> Field {
+    let mut acc = 0;
+    for ii in 0..1000 {
+        acc = acc + inner();
+    }
+    return acc;
+}
+
+const total = outer();
+
+fn main(pub xx: Field) {
+    assert_eq(xx, total);
+}
+"#;
+
+    let err = compile_code(code, CircuitOptions::default()).err().unwrap();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::ConstLoopBudgetExceeded(DEFAULT_LOOP_BUDGET)
+    ));
+}
+
+//
+// While loops
+//
+
+const GCD: &str = r#"
+fn gcd(aa: Field, bb: Field) -> Field {
+    let mut xx = aa;
+    let mut yy = bb;
+    #[max_iters(MAX_ITERS)]
+    while !(xx == yy) {
+        let xx_smaller = less_than(xx, yy, 8);
+        if xx_smaller {
+            yy = yy - xx;
+        }
+        if !xx_smaller {
+            xx = xx - yy;
+        }
+    }
+    return xx;
+}
+
+fn main(pub aa: Field, bb: Field) -> Field {
+    return gcd(aa, bb);
+}
+"#;
+
+fn gcd_code(max_iters: u32) -> String {
+    GCD.replace("MAX_ITERS", &max_iters.to_string())
+}
+
+#[test]
+fn test_while_loop_gcd() -> miette::Result<()> {
+    let code = gcd_code(16);
+    let (sources, compiled_circuit) = compile_code(&code, CircuitOptions::default()).unwrap();
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    // 12, 18 -> 12, 6 -> 6, 6
+    let public_inputs = parse_inputs(r#"{"aa": "12"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"bb": "18"}"#).unwrap();
+
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    assert_eq!(public_output, vec![VestaField::from(6u64)]);
+
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_while_loop_not_terminated() {
+    // gcd(1, 10) takes 9 iterations
+    let code = gcd_code(2);
+    let (_sources, compiled_circuit) = compile_code(&code, CircuitOptions::default()).unwrap();
+
+    let public_inputs = parse_inputs(r#"{"aa": "1"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"bb": "10"}"#).unwrap();
+    let err = compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .unwrap_err();
+
+    match &err.kind {
+        ErrorKind::UnsatisfiedAssertion { message, .. } => assert_eq!(
+            message,
+            "the `while` loop did not terminate within 2 iterations"
+        ),
+        kind => panic!("expected an unsatisfied assertion, got {kind:?}"),
+    }
+
+    // the error points to the loop
+    let failing_code = &code[err.span.start..err.span.end()];
+    assert!(failing_code.starts_with("while"));
+}
+
+#[test]
+fn test_while_loop_without_max_iters() {
+    let code = r#"
+fn main(pub xx: Field) {
+    let mut yy = xx;
+    while !(yy == 0) {
+        yy = yy - 1;
+    }
+}
+"#;
+
+    let err = compile_code(code, CircuitOptions::default())
+        .err()
+        .expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::MissingMaxIters));
+}
@@ -0,0 +1,218 @@
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    compiler::{compile, typecheck_next_file, typecheck_next_file_inner, Sources},
+    error::ErrorKind,
+    helpers::poseidon,
+    inputs::{parse_inputs, ExtField, JsonInputs},
+    type_checker::TypeChecker,
+};
+
+const MERKLE_CODE: &str = r#"
+use std::crypto;
+
+fn main(pub root: Field, leaf: Field, path: [Field; 3], indices: [Bool; 3]) {
+    crypto::merkle_verify(leaf, path, indices, root);
+}
+"#;
+
+fn inputs(
+    root: VestaField,
+    leaf: VestaField,
+    path: [VestaField; 3],
+    indices: [bool; 3],
+) -> (JsonInputs, JsonInputs) {
+    let public_inputs =
+        parse_inputs(&format!(r#"{{"root": "{}"}}"#, root.to_dec_string())).unwrap();
+    let path: Vec<_> = path
+        .iter()
+        .map(|node| format!(r#""{}""#, node.to_dec_string()))
+        .collect();
+    let indices: Vec<_> = indices.iter().map(|index| index.to_string()).collect();
+    let private_inputs = parse_inputs(&format!(
+        r#"{{"leaf": "{}", "path": [{}], "indices": [{}]}}"#,
+        leaf.to_dec_string(),
+        path.join(", "),
+        indices.join(", ")
+    ))
+    .unwrap();
+    (public_inputs, private_inputs)
+}
+
+#[test]
+fn test_merkle_verify() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "merkle.no".to_string(),
+        MERKLE_CODE.to_string(),
+        0,
+    )?;
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    // a tree of depth 3
+    let leaves: Vec<VestaField> = (1..=8u64).map(VestaField::from).collect();
+    let level1: Vec<_> = leaves.chunks(2).map(poseidon).collect();
+    let level2: Vec<_> = level1.chunks(2).map(poseidon).collect();
+    let root = poseidon(&level2);
+
+    // the leaf at index 5 (0b101) is a right, left, then right child
+    let path = [leaves[4], level1[3], level2[0]];
+    let indices = [true, false, true];
+
+    let (public_inputs, private_inputs) = inputs(root, leaves[5], path, indices);
+    compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .unwrap();
+
+    // the same path with a wrong direction
+    let (public_inputs, private_inputs) = inputs(root, leaves[5], path, [false, false, true]);
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    // another leaf
+    let (public_inputs, private_inputs) = inputs(root, leaves[4], path, indices);
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_merkle_verify_mismatched_depth() {
+    let code = r#"
+use std::crypto;
+
+fn main(pub root: Field, leaf: Field, path: [Field; 3], indices: [Bool; 2]) {
+    crypto::merkle_verify(leaf, path, indices, root);
+}
+"#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let err = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "merkle.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .err()
+    .expect("expected an error");
+
+    assert!(matches!(
+        err.kind,
+        ErrorKind::ConflictingGeneric(name, 3, 2) if name == "D"
+    ));
+}
+
+const MERKLE_WITH_CODE: &str = r#"
+use std::crypto;
+
+fn poseidon_hash(left: Field, right: Field) -> Field {
+    return crypto::poseidon([left, right]);
+}
+
+fn affine_hash(left: Field, right: Field) -> Field {
+    return left * 3 + right * 7 + left * right;
+}
+
+fn main(pub root: Field, leaf: Field, path: [Field; 3], indices: [Bool; 3]) {
+    crypto::merkle_verify_with(leaf, path, indices, root, HASH);
+}
+"#;
+
+#[test]
+fn test_merkle_verify_with() -> miette::Result<()> {
+    let affine_hash = |[left, right]: [VestaField; 2]| {
+        left * VestaField::from(3u64) + right * VestaField::from(7u64) + left * right
+    };
+
+    // the root of a tree of depth 3
+    let leaves: Vec<VestaField> = (1..=8u64).map(VestaField::from).collect();
+    let tree = |hash: &dyn Fn([VestaField; 2]) -> VestaField| {
+        let level1: Vec<_> = leaves
+            .chunks(2)
+            .map(|pair| hash([pair[0], pair[1]]))
+            .collect();
+        let level2: Vec<_> = level1
+            .chunks(2)
+            .map(|pair| hash([pair[0], pair[1]]))
+            .collect();
+        let root = hash([level2[0], level2[1]]);
+
+        // the path of the leaf at index 5 (0b101)
+        (root, [leaves[4], level1[3], level2[0]])
+    };
+    let indices = [true, false, true];
+
+    let (poseidon_root, poseidon_path) = tree(&|input| poseidon(&input));
+    let (affine_root, affine_path) = tree(&affine_hash);
+    assert_ne!(poseidon_root, affine_root);
+
+    for (hash, root, path, other_root) in [
+        ("poseidon_hash", poseidon_root, poseidon_path, affine_root),
+        ("affine_hash", affine_root, affine_path, poseidon_root),
+    ] {
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<KimchiVesta>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "merkle.no".to_string(),
+            MERKLE_WITH_CODE.replace("HASH", hash),
+            0,
+        )?;
+        let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+        let (public_inputs, private_inputs) = inputs(root, leaves[5], path, indices);
+        compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .unwrap();
+
+        // the root of the tree built with the other hash
+        let (public_inputs, private_inputs) = inputs(other_root, leaves[5], path, indices);
+        assert!(compiled_circuit
+            .generate_witness(public_inputs, private_inputs)
+            .is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_merkle_verify_with_invalid_hash() {
+    let code = r#"
+use std::crypto;
+
+fn double(xx: Field) -> Field {
+    return xx + xx;
+}
+
+fn main(pub root: Field, leaf: Field, path: [Field; 3], indices: [Bool; 3]) {
+    crypto::merkle_verify_with(leaf, path, indices, root, double);
+}
+"#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let err = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "merkle.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .err()
+    .expect("expected an error");
+
+    assert!(matches!(err.kind, ErrorKind::InvalidMerkleHash));
+    assert_eq!(err.span.start, code.find("double);").unwrap());
+}
@@ -1,2 +1,68 @@
+mod arrays;
+mod asserts;
+mod blake2s;
+mod circuit_builder;
+mod constants;
+mod equivalence;
 mod examples;
+mod fold;
+mod fp2;
+mod functions;
+mod generics;
+mod if_else;
+mod integers;
+mod keccak;
+mod let_annotations;
+mod loops;
+mod merkle;
 mod modules;
+mod operators;
+mod range_checks;
+mod tuples;
+mod type_aliases;
+mod witness;
+
+use crate::{
+    backends::kimchi::{prover::ProverIndex, VestaField},
+    inputs::parse_inputs,
+    var::Value,
+};
+
+/// Generates a witness as a malicious prover could, with the first hints of the circuit (in the order they were created)
+/// returning `forged_hints` instead of the values computed by their closures,
+/// and returns whether it is rejected, during the witness generation or by kimchi's own checks of the witness.
+pub(crate) fn is_forged_witness_rejected(
+    prover_index: &ProverIndex,
+    public_inputs: &str,
+    private_inputs: &str,
+    forged_hints: &[VestaField],
+) -> bool {
+    let compiled_circuit = &prover_index.compiled_circuit;
+    let backend = &compiled_circuit.circuit.backend;
+    let mut env = compiled_circuit
+        .init_witness_env(
+            parse_inputs(public_inputs).unwrap(),
+            parse_inputs(private_inputs).unwrap(),
+        )
+        .unwrap();
+
+    // the cached values of variables are used instead of computing them
+    let mut hints: Vec<_> = backend
+        .vars_to_value
+        .iter()
+        .filter(|(_, value)| matches!(value, Value::Hint(_)))
+        .map(|(var, _)| *var)
+        .collect();
+    hints.sort();
+    assert!(hints.len() >= forged_hints.len());
+    env.cached_values
+        .extend(hints.into_iter().zip(forged_hints.iter().copied()));
+
+    match backend.generate_witness_columns(&mut env) {
+        Err(_) => true,
+        Ok(witness) => prover_index
+            .index
+            .verify(&witness.columns, &witness.full_public_inputs)
+            .is_err(),
+    }
+}
@@ -1,7 +1,12 @@
 use crate::{
     backends::kimchi::KimchiVesta,
     cli::packages::UserRepo,
-    compiler::{compile, typecheck_next_file, Sources},
+    compiler::{
+        compile, generate_witness, get_nast, typecheck_next_file, typecheck_next_file_inner,
+        Sources,
+    },
+    error::ErrorKind,
+    inputs::parse_inputs,
     type_checker::TypeChecker,
 };
 
@@ -125,3 +130,272 @@ fn test_simple_module() -> miette::Result<()> {
 
     Ok(())
 }
+
+//
+// MAIN -> LEFT -> LEAF
+//      -> RIGHT -> LEAF
+//
+
+const LEAF: &str = "
+struct Pair {
+    aa: Field,
+    bb: Field,
+}
+
+fn new_pair(aa: Field, bb: Field) -> Pair {
+    return Pair { aa: aa, bb: bb };
+}
+
+fn Pair.sum(self) -> Field {
+    return self.aa + self.bb;
+}
+
+fn double(xx: Field) -> Field {
+    return xx * 2;
+}
+";
+
+const LEFT: &str = r#"
+use mimoo::leaf;
+
+fn pair(xx: Field) -> leaf::Pair {
+    return leaf::new_pair(xx, xx);
+}
+"#;
+
+const RIGHT: &str = r#"
+use mimoo::leaf;
+
+fn sum(pair: leaf::Pair) -> Field {
+    return pair.sum();
+}
+
+fn double(xx: Field) -> Field {
+    return leaf::double(xx);
+}
+"#;
+
+const DIAMOND_MAIN: &str = r#"
+use mimoo::left;
+use mimoo::right;
+
+fn main(pub xx: Field, yy: Field) {
+    // a struct created through one path can be used through the other
+    let pair = left::pair(xx);
+    assert_eq(right::sum(pair), yy);
+
+    // the leaf can also be used with its full path, without importing it
+    assert_eq(mimoo::leaf::double(xx), yy);
+    assert_eq(right::double(xx), yy);
+}
+"#;
+
+const LEAF_MAIN: &str = r#"
+use mimoo::leaf;
+
+fn main(pub xx: Field, yy: Field) {
+    let pair = leaf::new_pair(xx, xx);
+    assert_eq(pair.sum(), yy);
+
+    assert_eq(leaf::double(xx), yy);
+    assert_eq(leaf::double(xx), yy);
+}
+"#;
+
+fn typecheck_modules(
+    deps: &[(&str, &str)],
+    main: &str,
+) -> miette::Result<(Sources, TypeChecker<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let mut node_id = 0;
+
+    // dependencies are type checked from the leaves to the roots, once each
+    for (name, code) in deps {
+        node_id = typecheck_next_file(
+            &mut tast,
+            Some(UserRepo::new(&format!("mimoo/{name}"))),
+            &mut sources,
+            format!("{name}.no"),
+            code.to_string(),
+            node_id,
+        )?;
+    }
+
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "main.no".to_string(),
+        main.to_string(),
+        node_id,
+    )?;
+
+    Ok((sources, tast))
+}
+
+#[test]
+fn test_diamond_dependency() -> miette::Result<()> {
+    let (sources, tast) = typecheck_modules(
+        &[("leaf", LEAF), ("left", LEFT), ("right", RIGHT)],
+        DIAMOND_MAIN,
+    )?;
+    let diamond = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    // the leaf module only exists once, so going through either path creates the same gates
+    let (direct_sources, tast) = typecheck_modules(&[("leaf", LEAF)], LEAF_MAIN)?;
+    let direct = compile(&direct_sources, tast, KimchiVesta::new(false))?;
+    assert_eq!(diamond.num_gates(), direct.num_gates());
+
+    let public_inputs = parse_inputs(r#"{"xx": "2"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "4"}"#).unwrap();
+    generate_witness(&diamond, &sources, public_inputs, private_inputs)?;
+
+    Ok(())
+}
+
+//
+// MAIN -> LEAF, importing functions
+//
+
+const IMPORT_MAIN: &str = r#"
+use mimoo::leaf::{new_pair, double};
+
+fn main(pub xx: Field, yy: Field) {
+    // the imported functions are called without their module
+    let pair = new_pair(xx, xx);
+    assert_eq(pair.sum(), yy);
+
+    assert_eq(double(xx), yy);
+    assert_eq(double(xx), yy);
+}
+"#;
+
+#[test]
+fn test_import_functions() -> miette::Result<()> {
+    let (sources, tast) = typecheck_modules(&[("leaf", LEAF)], IMPORT_MAIN)?;
+    let imported = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    // calling the functions through their module creates the same circuit
+    let (direct_sources, tast) = typecheck_modules(&[("leaf", LEAF)], LEAF_MAIN)?;
+    let direct = compile(&direct_sources, tast, KimchiVesta::new(false))?;
+    assert_eq!(imported.num_gates(), direct.num_gates());
+
+    let public_inputs = parse_inputs(r#"{"xx": "2"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "4"}"#).unwrap();
+    generate_witness(&imported, &sources, public_inputs, private_inputs)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_imported_fn_collision() {
+    let code = r#"
+use mimoo::leaf::{double};
+
+fn double(xx: Field) -> Field {
+    return xx + xx;
+}
+
+fn main(pub xx: Field) {
+    assert_eq(double(xx), 4);
+}
+"#;
+
+    let res = get_nast::<KimchiVesta>(
+        None,
+        &mut Sources::new(),
+        "main.no".to_string(),
+        code.to_string(),
+        0,
+    );
+    let err = res.err().expect("the imported function should collide");
+    assert!(matches!(err.kind, ErrorKind::ImportedFnCollision(name) if name == "double"));
+}
+
+//
+// MAIN -> GEOMETRY, calling functions with the method syntax
+//
+
+const GEOMETRY: &str = "
+struct Point {
+    xx: Field,
+    yy: Field,
+}
+
+fn new_point(xx: Field, yy: Field) -> Point {
+    return Point { xx: xx, yy: yy };
+}
+
+fn Point.norm_sq(self) -> Field {
+    return self.xx * self.xx + self.yy * self.yy;
+}
+
+// not a method, but it takes a `Point` as first argument
+fn dot(lhs: Point, rhs: Point) -> Field {
+    return lhs.xx * rhs.xx + lhs.yy * rhs.yy;
+}
+";
+
+const GEOMETRY_MAIN: &str = r#"
+use mimoo::geometry;
+
+fn main(pub xx: Field, yy: Field) {
+    let pp = geometry::new_point(xx, yy);
+    assert_eq(pp.norm_sq(), 25);
+
+    // `pp.dot(pp)` is `geometry::dot(pp, pp)`
+    assert_eq(pp.dot(pp), geometry::dot(pp, pp));
+}
+"#;
+
+#[test]
+fn test_method_syntax() -> miette::Result<()> {
+    let (sources, tast) = typecheck_modules(&[("geometry", GEOMETRY)], GEOMETRY_MAIN)?;
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    let public_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "4"}"#).unwrap();
+    generate_witness(&compiled_circuit, &sources, public_inputs, private_inputs)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_undefined_method() {
+    let code = r#"
+struct Point {
+    xx: Field,
+    yy: Field,
+}
+
+// the struct comes first, so this is not a method of `Point`
+fn scale(factor: Field, pp: Point) -> Point {
+    return Point { xx: pp.xx * factor, yy: pp.yy * factor };
+}
+
+fn main(pub xx: Field) {
+    let pp = Point { xx: xx, yy: xx };
+    let scaled = pp.scale(2);
+    assert_eq(scaled.xx, 4);
+}
+"#;
+
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let err = typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut Sources::new(),
+        "main.no".to_string(),
+        code.to_string(),
+        0,
+    )
+    .err()
+    .expect("expected an error");
+    assert!(matches!(
+        err.kind,
+        ErrorKind::UndefinedMethod(struct_name, method_name)
+            if struct_name == "Point" && method_name == "scale"
+    ));
+    assert_eq!(err.span.start, code.find("scale(2)").unwrap());
+}
@@ -0,0 +1,225 @@
+use ark_ff::Field as _;
+
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    circuit_writer::{CircuitOptions, CircuitWriter},
+    compiler::{typecheck_next_file_inner, Sources},
+    error::{ErrorKind, Result},
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+};
+
+use super::is_forged_witness_rejected;
+
+fn typecheck_code(code: &str) -> Result<(Sources, TypeChecker<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "operators.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    Ok((sources, tast))
+}
+
+const POINT_OPERATORS: &str = r#"
+struct Point {
+    xx: Field,
+    yy: Field,
+}
+
+fn Point.add(self, other: Point) -> Point {
+    return Point { xx: self.xx + other.xx, yy: self.yy + other.yy };
+}
+
+fn Point.sub(self, other: Point) -> Point {
+    return Point { xx: self.xx - other.xx, yy: self.yy - other.yy };
+}
+
+fn Point.mul(self, other: Point) -> Point {
+    return Point { xx: self.xx * other.xx, yy: self.yy * other.yy };
+}
+
+fn main(pub xx: Field, yy: Field) -> Field {
+    let pp = Point { xx: xx, yy: yy };
+    let qq = Point { xx: 3, yy: 4 };
+
+    let sum = pp + qq;
+    assert_eq(sum.xx, xx + 3);
+    assert_eq(sum.yy, yy + 4);
+
+    let diff = sum - qq;
+    assert_eq(diff.xx, xx);
+    assert_eq(diff.yy, yy);
+
+    let prod = pp * qq;
+    return prod.xx + prod.yy;
+}
+"#;
+
+#[test]
+fn test_point_operators() -> miette::Result<()> {
+    let (sources, tast) = typecheck_code(POINT_OPERATORS).unwrap();
+
+    let compiled_circuit = CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        None,
+    )
+    .unwrap();
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    let public_inputs = parse_inputs(r#"{"xx": "2"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "5"}"#).unwrap();
+
+    // (2 * 3) + (5 * 4)
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    assert_eq!(public_output, vec![VestaField::from(26u64)]);
+
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_missing_operator_method() {
+    let code = r#"
+struct Point {
+    xx: Field,
+    yy: Field,
+}
+
+fn Point.add(self, other: Point) -> Point {
+    return Point { xx: self.xx + other.xx, yy: self.yy + other.yy };
+}
+
+fn main(pub xx: Field) {
+    let pp = Point { xx: xx, yy: 1 };
+    let diff = pp - pp;
+}
+"#;
+
+    let err = typecheck_code(code).err().expect("expected an error");
+    assert!(matches!(
+        err.kind,
+        ErrorKind::MissingOperatorMethod(name, "sub") if name == "Point"
+    ));
+}
+
+#[test]
+fn test_division_by_variable_unsupported() {
+    let code = r#"
+fn main(pub xx: Field, yy: Field) {
+    assert_eq(xx / yy, 1);
+}
+"#;
+
+    let (_, tast) = typecheck_code(code).unwrap();
+    let err = CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        None,
+    )
+    .err()
+    .expect("expected an error");
+
+    assert!(matches!(err.kind, ErrorKind::Unsupported(_)));
+    assert_eq!(err.span.start, code.find("xx / yy").unwrap());
+}
+
+#[test]
+fn test_subtraction_gates() -> miette::Result<()> {
+    let compile_code = |code: &str| {
+        let (sources, tast) = typecheck_code(code).unwrap();
+        let compiled_circuit = CircuitWriter::generate_circuit(
+            tast,
+            KimchiVesta::new(false),
+            CircuitOptions::default(),
+            None,
+        )
+        .unwrap();
+        (sources, compiled_circuit)
+    };
+
+    // a negated constant is folded, so only the public input and the assertion remain
+    let (_, circuit) = compile_code("fn main(pub xx: Field) { assert_eq(xx, -5); }");
+    assert_eq!(circuit.num_gates(), 2);
+
+    // subtracting a constant is a single addition
+    let (_, circuit) = compile_code("fn main(pub xx: Field) { assert_eq(xx - 5, 2); }");
+    assert_eq!(circuit.num_gates(), 3);
+
+    // subtracting two variables is a single gate
+    let (_, circuit) = compile_code("fn main(pub xx: Field, yy: Field) { assert_eq(xx - yy, 2); }");
+    assert_eq!(circuit.num_gates(), 3);
+
+    // `-xx - yy` is `(-xx) - yy`
+    let (sources, circuit) =
+        compile_code("fn main(pub xx: Field, yy: Field) { assert_eq(-xx - yy, -7); }");
+    assert_eq!(circuit.num_gates(), 4);
+
+    let (prover_index, verifier_index) = circuit.compile_to_indexes()?;
+    let public_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": "4"}"#).unwrap();
+    let (proof, full_public_inputs, _) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_div_forged_quotient() -> miette::Result<()> {
+    let code = r#"
+fn main(pub xx: Field, yy: Field) -> Field {
+    return div(xx, yy);
+}
+"#;
+
+    let (_, tast) = typecheck_code(code).unwrap();
+    let compiled_circuit = CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        None,
+    )
+    .unwrap();
+    let (prover_index, _verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    // the only hint is the inverse of the divisor, which the quotient is computed from
+    let inv_three = VestaField::from(3u64).inverse().unwrap();
+    assert!(!is_forged_witness_rejected(
+        &prover_index,
+        r#"{"xx": "6"}"#,
+        r#"{"yy": "3"}"#,
+        &[inv_three]
+    ));
+
+    // another inverse would give another quotient
+    let inv_two = VestaField::from(2u64).inverse().unwrap();
+    assert!(is_forged_witness_rejected(
+        &prover_index,
+        r#"{"xx": "6"}"#,
+        r#"{"yy": "3"}"#,
+        &[inv_two]
+    ));
+
+    // and zero has no inverse
+    for inv in [VestaField::from(0u64), VestaField::from(1u64)] {
+        assert!(is_forged_witness_rejected(
+            &prover_index,
+            r#"{"xx": "0"}"#,
+            r#"{"yy": "0"}"#,
+            &[inv]
+        ));
+    }
+
+    Ok(())
+}
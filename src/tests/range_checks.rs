@@ -0,0 +1,156 @@
+use crate::{
+    backends::kimchi::{lookup::LookupTable, KimchiVesta, VestaField},
+    circuit_writer::GateKind,
+    compiler::{compile, typecheck_next_file, Sources},
+    error::ErrorKind,
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+    witness::CompiledCircuit,
+};
+
+use super::is_forged_witness_rejected;
+
+fn compile_code(
+    code: &str,
+    backend: KimchiVesta,
+) -> miette::Result<(Sources, CompiledCircuit<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "range_checks.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, backend)?;
+    Ok((sources, compiled_circuit))
+}
+
+//
+// Lookup tables
+//
+
+#[test]
+fn test_lookup_range_table() -> miette::Result<()> {
+    let code = r#"
+fn main(pub byte: Field) -> Field {
+    let same = lookup(1, byte);
+    return same + 1;
+}
+"#;
+
+    // an 8-bit range table
+    let mut kimchi_vesta = KimchiVesta::new(false);
+    kimchi_vesta.add_lookup_table(LookupTable::range(1, 8));
+    let (sources, compiled_circuit) = compile_code(code, kimchi_vesta)?;
+
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+    assert_eq!(prover_index.gate_stats().gates[&GateKind::Lookup], 1);
+
+    let public_inputs = parse_inputs(r#"{"byte": "200"}"#).unwrap();
+    let private_inputs = parse_inputs("{}").unwrap();
+    let (proof, full_public_inputs, public_outputs) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    assert_eq!(public_outputs, vec![VestaField::from(201u64)]);
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    // a value that doesn't fit in 8 bits is not in the table
+    let public_inputs = parse_inputs(r#"{"byte": "256"}"#).unwrap();
+    let private_inputs = parse_inputs("{}").unwrap();
+    assert!(prover_index
+        .generate_witness(&sources, public_inputs, private_inputs)
+        .is_err());
+
+    Ok(())
+}
+
+//
+// range_check64
+//
+
+const RANGE_CHECK64: &str = r#"
+fn main(pub value: Field) {
+    range_check64(value);
+}
+"#;
+
+#[test]
+fn test_range_check64() -> miette::Result<()> {
+    let (sources, compiled_circuit) = compile_code(RANGE_CHECK64, KimchiVesta::new(false))?;
+
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+    assert_eq!(prover_index.gate_stats().gates[&GateKind::RangeCheck], 1);
+
+    // the largest 64-bit value
+    let public_inputs = parse_inputs(r#"{"value": "18446744073709551615"}"#).unwrap();
+    let private_inputs = parse_inputs("{}").unwrap();
+    let (proof, full_public_inputs, _) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    // 2^64 doesn't fit in 64 bits
+    let public_inputs = parse_inputs(r#"{"value": "18446744073709551616"}"#).unwrap();
+    let private_inputs = parse_inputs("{}").unwrap();
+    let err = prover_index
+        .compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::UnsatisfiedConstraint {
+            gate: GateKind::RangeCheck,
+            ..
+        }
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_range_check64_forged_limbs() -> miette::Result<()> {
+    let (_, compiled_circuit) = compile_code(RANGE_CHECK64, KimchiVesta::new(false))?;
+    let (prover_index, _verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    // 2^64 doesn't fit in the limbs, unless the highest 12-bit one (the first hint) overflows
+    let forged_hints = [VestaField::from(1u64 << 12)];
+    assert!(is_forged_witness_rejected(
+        &prover_index,
+        r#"{"value": "18446744073709551616"}"#,
+        "{}",
+        &forged_hints
+    ));
+
+    // kimchi rejects limbs that sum to the value but don't fit in their bits,
+    // even if they bypass the checks of the witness generation
+    let backend = &prover_index.compiled_circuit.circuit.backend;
+    let mut env = prover_index
+        .compiled_circuit
+        .init_witness_env(
+            parse_inputs(r#"{"value": "0"}"#).unwrap(),
+            parse_inputs("{}").unwrap(),
+        )
+        .unwrap();
+    let mut witness = backend.generate_witness_columns(&mut env).unwrap();
+    prover_index
+        .index
+        .verify(&witness.columns, &witness.full_public_inputs)
+        .unwrap();
+
+    let row = backend
+        .gates
+        .iter()
+        .position(|gate| gate.typ == GateKind::RangeCheck)
+        .unwrap();
+    // the two lowest 2-bit limbs are in the last two columns: -4 + 4 * 1 = 0
+    witness.columns[13][row] = VestaField::from(1u64);
+    witness.columns[14][row] = -VestaField::from(4u64);
+    assert!(prover_index
+        .index
+        .verify(&witness.columns, &witness.full_public_inputs)
+        .is_err());
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    compiler::{compile, typecheck_next_file, Sources},
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+};
+
+//
+// Tuples
+//
+
+const TUPLES: &str = r#"
+fn split(pair: (Field, [Field; 2])) -> ((Field, Field), Field) {
+    let (head, tail) = pair;
+    return ((head, tail[0]), tail[1]);
+}
+
+fn main(pub expected: (Field, Field), pair: (Field, [Field; 2])) -> ((Field, Field), [Field; 2]) {
+    // nested tuples returned by a function
+    let (nested, last) = split(pair);
+    let (first, second) = nested;
+
+    // tuple as a public input
+    let (expected_first, expected_last) = expected;
+    assert_eq(first, expected_first);
+    assert_eq(last, expected_last);
+
+    // tuple containing an array as a public output
+    return ((second, first), [last, 7]);
+}
+"#;
+
+#[test]
+fn test_tuples() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "tuples.no".to_string(),
+        TUPLES.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+
+    let public_inputs = parse_inputs(r#"{"expected": ["1", "3"]}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"pair": ["1", ["2", "3"]]}"#).unwrap();
+
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+
+    // tuples are flattened in declaration order: outputs first, then inputs
+    let field = |values: &[u64]| -> Vec<VestaField> {
+        values.iter().map(|&v| VestaField::from(v)).collect()
+    };
+    assert_eq!(public_output, field(&[2, 1, 3, 7]));
+    assert_eq!(full_public_inputs, field(&[2, 1, 3, 7, 1, 3]));
+
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_tuple_destructuring_mismatch() {
+    let code = r#"
+fn main(pub xx: Field) {
+    let (aa, bb, cc) = (xx, xx);
+}
+"#;
+
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    let res = typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "tuples.no".to_string(),
+        code.to_string(),
+        0,
+    );
+
+    assert!(res.is_err());
+}
@@ -0,0 +1,132 @@
+use crate::{
+    backends::kimchi::{KimchiVesta, VestaField},
+    circuit_writer::{CircuitOptions, CircuitWriter},
+    compiler::{typecheck_next_file_inner, Sources},
+    error::{ErrorKind, Result},
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+};
+
+fn typecheck_code(code: &str) -> Result<(Sources, TypeChecker<KimchiVesta>)> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::new();
+    typecheck_next_file_inner(
+        &mut tast,
+        None,
+        &mut sources,
+        "type_aliases.no".to_string(),
+        code.to_string(),
+        0,
+    )?;
+
+    Ok((sources, tast))
+}
+
+const ALIASED: &str = r#"
+type Hash = [Field; 4];
+type Pair = [Hash; 2];
+
+struct Node {
+    children: Pair,
+}
+
+fn sum(hash: Hash) -> Field {
+    let mut acc = 0;
+    for ii in 0..4 {
+        acc = acc + hash[ii];
+    }
+    return acc;
+}
+
+fn main(pub xx: Hash, yy: Hash) -> Field {
+    let node = Node { children: [xx, yy] };
+    return sum(node.children[0]) + sum(node.children[1]);
+}
+"#;
+
+const INLINE: &str = r#"
+struct Node {
+    children: [[Field; 4]; 2],
+}
+
+fn sum(hash: [Field; 4]) -> Field {
+    let mut acc = 0;
+    for ii in 0..4 {
+        acc = acc + hash[ii];
+    }
+    return acc;
+}
+
+fn main(pub xx: [Field; 4], yy: [Field; 4]) -> Field {
+    let node = Node { children: [xx, yy] };
+    return sum(node.children[0]) + sum(node.children[1]);
+}
+"#;
+
+/// Compiles and proves the code, and returns its asm and public output.
+fn prove_code(code: &str) -> miette::Result<(String, Vec<VestaField>)> {
+    let (sources, tast) = typecheck_code(code).unwrap();
+    let compiled_circuit = CircuitWriter::generate_circuit(
+        tast,
+        KimchiVesta::new(false),
+        CircuitOptions::default(),
+        None,
+    )
+    .unwrap();
+    let (prover_index, verifier_index) = compiled_circuit.compile_to_indexes()?;
+    let asm = prover_index.asm(&sources, false);
+
+    let public_inputs = parse_inputs(r#"{"xx": ["1", "2", "3", "4"]}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"yy": ["5", "6", "7", "8"]}"#).unwrap();
+
+    let (proof, full_public_inputs, public_output) =
+        prover_index.prove(&sources, public_inputs, private_inputs, false)?;
+    verifier_index.verify(full_public_inputs, proof)?;
+
+    Ok((asm, public_output))
+}
+
+#[test]
+fn test_type_alias_as_inline_type() -> miette::Result<()> {
+    let (aliased_asm, aliased_output) = prove_code(ALIASED)?;
+    let (inline_asm, inline_output) = prove_code(INLINE)?;
+
+    assert_eq!(aliased_output, vec![VestaField::from(36u64)]);
+    assert_eq!(aliased_output, inline_output);
+    assert_eq!(aliased_asm, inline_asm);
+
+    Ok(())
+}
+
+#[test]
+fn test_recursive_type_alias() {
+    let code = r#"
+type Tree = [Branch; 2];
+type Branch = [Tree; 2];
+
+fn main(pub xx: Tree) {
+    assert_eq(xx[0][0][0], 1);
+}
+"#;
+
+    let err = typecheck_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::RecursiveTypeAlias(name) if name == "Tree"));
+}
+
+#[test]
+fn test_type_alias_with_struct_name() {
+    let code = r#"
+struct Hash {
+    inner: Field,
+}
+
+type Hash = [Field; 4];
+
+fn main(pub xx: Field) {
+    assert_eq(xx, 1);
+}
+"#;
+
+    let err = typecheck_code(code).err().expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::DuplicateType(name) if name == "Hash"));
+}
@@ -0,0 +1,293 @@
+use ark_ff::{BigInteger as _, PrimeField};
+
+use crate::{
+    backends::{
+        kimchi::{KimchiVesta, VestaField},
+        r1cs::{R1csBls12381Field, R1CS},
+    },
+    compiler::{compile, typecheck_next_file, Sources},
+    error::ErrorKind,
+    inputs::parse_inputs,
+    type_checker::TypeChecker,
+};
+
+//
+// Querying the values of named variables
+//
+
+const DEBUG_CODE: &str = r#"
+fn double(xx: Field) -> Field {
+    let res = xx + xx;
+    return res;
+}
+
+fn main(pub xx: Field, pair: [Field; 2]) {
+    let mut acc = 0;
+    for ii in 0..2 {
+        let tmp = pair[ii] * xx;
+        acc = acc + tmp;
+    }
+    let doubled = double(acc);
+
+    // fails unless `doubled` is 10
+    assert_eq(doubled, 10);
+}
+"#;
+
+#[test]
+fn test_value_of_on_failing_witness() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "debug.no".to_string(),
+        DEBUG_CODE.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    let inputs = || {
+        (
+            parse_inputs(r#"{"xx": "2"}"#).unwrap(),
+            parse_inputs(r#"{"pair": ["1", "3"]}"#).unwrap(),
+        )
+    };
+
+    // the assertion fails
+    let (public_inputs, private_inputs) = inputs();
+    assert!(compiled_circuit
+        .generate_witness(public_inputs, private_inputs)
+        .is_err());
+
+    // but we can still find out why
+    let (public_inputs, private_inputs) = inputs();
+    let env = compiled_circuit.witness_env(public_inputs, private_inputs)?;
+
+    let value_of = |name: &str| env.value_of(name);
+    assert_eq!(value_of("xx"), Some(VestaField::from(2u64)));
+    assert_eq!(value_of("acc"), Some(VestaField::from(8u64)));
+    assert_eq!(value_of("tmp"), Some(VestaField::from(6u64)));
+    assert_eq!(value_of("doubled"), Some(VestaField::from(16u64)));
+
+    // arrays are made of several field elements
+    assert_eq!(value_of("pair"), None);
+    assert_eq!(
+        env.values_of("pair"),
+        Some([VestaField::from(1u64), VestaField::from(3u64)].as_slice())
+    );
+
+    // only the variables of the main function are available
+    assert_eq!(value_of("res"), None);
+    assert_eq!(value_of("unknown"), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_value_of_r1cs() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "debug.no".to_string(),
+        DEBUG_CODE.to_string(),
+        0,
+    )?;
+
+    let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+    let public_inputs = parse_inputs(r#"{"xx": "1"}"#).unwrap();
+    let private_inputs = parse_inputs(r#"{"pair": ["2", "3"]}"#).unwrap();
+    let env = compiled_circuit.witness_env(public_inputs, private_inputs)?;
+
+    assert_eq!(
+        env.value_of("doubled"),
+        Some(R1csBls12381Field::from(10u64))
+    );
+
+    Ok(())
+}
+
+//
+// Parallel witness generation
+//
+
+const PARALLEL_CODE: &str = r#"
+fn main(pub xx: Field, yy: [Field; 4]) -> [Field; 2] {
+    let mut acc = 0;
+    let mut count = 0;
+    for ii in 0..4 {
+        let square = yy[ii] * yy[ii];
+        acc = acc + (square * xx);
+        let found = if yy[ii] == xx { 1 } else { 0 };
+        count = count + found;
+    }
+    assert_eq(count, 1);
+    return [acc, count];
+}
+"#;
+
+#[test]
+fn test_parallel_witness_matches_sequential() -> miette::Result<()> {
+    let generate = |parallel: bool| -> miette::Result<_> {
+        let mut sources = Sources::new();
+        let mut tast = TypeChecker::<KimchiVesta>::new();
+        typecheck_next_file(
+            &mut tast,
+            None,
+            &mut sources,
+            "parallel.no".to_string(),
+            PARALLEL_CODE.to_string(),
+            0,
+        )?;
+
+        let mut backend = KimchiVesta::new(false);
+        backend.set_parallel_witness(parallel);
+        let compiled_circuit = compile(&sources, tast, backend)?;
+
+        let public_inputs = parse_inputs(r#"{"xx": "3"}"#).unwrap();
+        let private_inputs = parse_inputs(r#"{"yy": ["1", "2", "3", "4"]}"#).unwrap();
+        Ok(compiled_circuit.generate_witness(public_inputs, private_inputs)?)
+    };
+
+    let sequential = generate(false)?;
+    let parallel = generate(true)?;
+
+    assert_eq!(
+        parallel.all_witness.to_kimchi_witness(),
+        sequential.all_witness.to_kimchi_witness()
+    );
+    assert_eq!(parallel.full_public_inputs, sequential.full_public_inputs);
+    assert_eq!(
+        parallel.public_outputs,
+        vec![VestaField::from(90u64), VestaField::from(1u64)]
+    );
+    assert_eq!(parallel.public_outputs, sequential.public_outputs);
+
+    Ok(())
+}
+
+//
+// Streaming witness generation
+//
+
+#[test]
+fn test_streaming_witness_matches_generated_witness() -> miette::Result<()> {
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "streaming.no".to_string(),
+        PARALLEL_CODE.to_string(),
+        0,
+    )?;
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    let inputs = || {
+        (
+            parse_inputs(r#"{"xx": "3"}"#).unwrap(),
+            parse_inputs(r#"{"yy": ["1", "2", "3", "4"]}"#).unwrap(),
+        )
+    };
+
+    let (public_inputs, private_inputs) = inputs();
+    let generated = compiled_circuit.generate_witness(public_inputs, private_inputs)?;
+    let (public_inputs, private_inputs) = inputs();
+    let streamed = compiled_circuit.generate_witness_streaming(public_inputs, private_inputs)?;
+
+    assert_eq!(streamed.columns, generated.all_witness.to_kimchi_witness());
+    assert_eq!(streamed.full_public_inputs, generated.full_public_inputs);
+    assert_eq!(streamed.public_outputs, generated.public_outputs);
+
+    // an unsatisfied constraint is reported the same way
+    let (public_inputs, _) = inputs();
+    let private_inputs = parse_inputs(r#"{"yy": ["1", "2", "4", "5"]}"#).unwrap();
+    let err = compiled_circuit
+        .generate_witness_streaming(public_inputs, private_inputs)
+        .err()
+        .expect("expected an error");
+    assert!(matches!(err.kind, ErrorKind::UnsatisfiedConstraint { .. }));
+
+    Ok(())
+}
+
+//
+// Determinism
+//
+
+const HINTS_CODE: &str = r#"
+fn main(pub xx: Field, yy: [Field; 3]) -> Field {
+    let mut acc = 0;
+    for ii in 0..3 {
+        acc = acc + div(yy[ii], xx) + sqrt(yy[ii] * yy[ii]);
+    }
+    return acc;
+}
+"#;
+
+/// Returns the little-endian bytes of field elements.
+fn to_bytes<F: PrimeField>(values: &[F]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.into_repr().to_bytes_le())
+        .collect()
+}
+
+#[test]
+fn test_deterministic_witness() -> miette::Result<()> {
+    let inputs = || {
+        (
+            parse_inputs(r#"{"xx": "3"}"#).unwrap(),
+            parse_inputs(r#"{"yy": ["4", "9", "16"]}"#).unwrap(),
+        )
+    };
+
+    // kimchi
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<KimchiVesta>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "hints.no".to_string(),
+        HINTS_CODE.to_string(),
+        0,
+    )?;
+    let compiled_circuit = compile(&sources, tast, KimchiVesta::new(false))?;
+
+    let kimchi_witness = || -> miette::Result<Vec<u8>> {
+        let (public_inputs, private_inputs) = inputs();
+        let witness = compiled_circuit.generate_witness(public_inputs, private_inputs)?;
+        Ok(to_bytes(&witness.all_witness.to_kimchi_witness().concat()))
+    };
+    assert_eq!(kimchi_witness()?, kimchi_witness()?);
+
+    // r1cs
+    let mut sources = Sources::new();
+    let mut tast = TypeChecker::<R1CS<R1csBls12381Field>>::new();
+    typecheck_next_file(
+        &mut tast,
+        None,
+        &mut sources,
+        "hints.no".to_string(),
+        HINTS_CODE.to_string(),
+        0,
+    )?;
+    let compiled_circuit = compile(&sources, tast, R1CS::new())?;
+
+    let r1cs_witness = || -> miette::Result<Vec<u8>> {
+        let (public_inputs, private_inputs) = inputs();
+        let witness = compiled_circuit.generate_witness(public_inputs, private_inputs)?;
+        Ok(to_bytes(&witness.witness))
+    };
+    assert_eq!(r1cs_witness()?, r1cs_witness()?);
+
+    Ok(())
+}
@@ -4,14 +4,19 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     backends::Backend,
+    cli::packages::UserRepo,
     constants::Span,
     error::{ErrorKind, Result},
     imports::FnKind,
     parser::{
-        types::{FnSig, FunctionDef, Stmt, StmtKind, Ty, TyKind},
+        types::{FnSig, FunctionDef, ModulePath, Stmt, StmtKind, Ty, TyKind},
         CustomType, Expr, ExprKind, Op2,
     },
-    syntax::is_type,
+    stdlib::{
+        crypto::{is_merkle_verify_with, QUALIFIED_CRYPTO},
+        is_fold, is_select, split_assert_message,
+    },
+    syntax::{is_type, parse_number},
 };
 
 use super::{FullyQualified, TypeChecker, TypeInfo, TypedFnEnv};
@@ -110,6 +115,36 @@ impl<B: Backend> TypeChecker<B> {
                 Some(ExprTyInfo::new(lhs_node.var_name, res))
             }
 
+            // `fold(arr, init, f)`
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } if is_fold(module, &fn_name.value) => Some(ExprTyInfo::new_anon(self.check_fold(
+                typed_fn_env,
+                args,
+                expr,
+            )?)),
+
+            // `select(cond, a, b)`
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } if is_select(module, &fn_name.value) => Some(ExprTyInfo::new_anon(
+                self.check_select(typed_fn_env, args, expr)?,
+            )),
+
+            // `crypto::merkle_verify_with(leaf, path, indices, root, hash)`
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } if is_merkle_verify_with(module, &fn_name.value) => {
+                self.check_merkle_verify_with(typed_fn_env, args, expr)?;
+                None
+            }
+
             // `module::fn_name(args)`
             ExprKind::FnCall {
                 module,
@@ -126,6 +161,39 @@ impl<B: Backend> TypeChecker<B> {
                 })?;
                 let fn_sig = fn_info.sig().clone();
 
+                // the message of an assertion is not an argument of the builtin
+                let args = match fn_info.kind {
+                    FnKind::BuiltIn(..) => split_assert_message(&fn_sig.name.value, args).0,
+                    FnKind::Native(_) => args,
+                };
+
+                // a call to a generic function is type checked against the instance it refers to
+                let generic_fn = match &fn_info.kind {
+                    FnKind::Native(func) if !func.sig.generics.is_empty() => Some(func.clone()),
+                    _ => None,
+                };
+                let fn_sig = if fn_sig.generics.is_empty() {
+                    fn_sig
+                } else {
+                    let values = self.const_generics(&fn_sig, args)?;
+
+                    let mut observed = Vec::with_capacity(args.len());
+                    for arg in args {
+                        let node = self.compute_type(arg, typed_fn_env)?.ok_or_else(|| {
+                            self.error(ErrorKind::CannotComputeExpression, arg.span)
+                        })?;
+                        observed.push((node.typ, arg.span));
+                    }
+
+                    match generic_fn {
+                        Some(function) => self.instantiate_generic_fn(
+                            &qualified, &function, &observed, values, expr,
+                        )?,
+                        // generic builtins only have their signature instantiated
+                        None => self.instantiate_generic_builtin(fn_sig, &observed, values)?,
+                    }
+                };
+
                 // type check the function call
                 let method_call = false;
                 let res = self.check_fn_call(typed_fn_env, method_call, fn_sig, args, expr.span)?;
@@ -146,28 +214,40 @@ impl<B: Backend> TypeChecker<B> {
                     _ => return Err(self.error(ErrorKind::MethodCallOnNonCustomStruct, expr.span)),
                 };
 
-                // get struct info
+                // check that the struct exists
                 let qualified = FullyQualified::new(&module, &struct_name);
-                let struct_info = self
-                    .struct_info(&qualified)
-                    .ok_or(self.error(ErrorKind::UndefinedStruct(struct_name.clone()), lhs.span))?
-                    .clone();
+                if self.struct_info(&qualified).is_none() {
+                    return Err(
+                        self.error(ErrorKind::UndefinedStruct(struct_name.clone()), lhs.span)
+                    );
+                }
 
                 // get method info
-                let method_type = struct_info
-                    .methods
-                    .get(&method_name.value)
-                    .expect("method not found on custom struct (TODO: better error)");
-
-                // type check the method call
-                let method_call = true;
-                let res = self.check_fn_call(
-                    typed_fn_env,
-                    method_call,
-                    method_type.sig.clone(),
-                    args,
-                    expr.span,
-                )?;
+                // (the method can also be a function of the struct's module, taking `lhs` as first argument)
+                let lhs_typ = TyKind::Custom {
+                    module,
+                    name: struct_name.clone(),
+                };
+                let (method_type, passes_lhs) = self
+                    .method_info(&lhs_typ, &method_name.value)
+                    .map(|(method, passes_lhs)| (method.clone(), passes_lhs))
+                    .ok_or_else(|| {
+                        self.error(
+                            ErrorKind::UndefinedMethod(
+                                struct_name.clone(),
+                                method_name.value.clone(),
+                            ),
+                            method_name.span,
+                        )
+                    })?;
+
+                // type check the method call, where `lhs` is not part of the arguments
+                let mut sig = method_type.sig;
+                if passes_lhs {
+                    sig.arguments.remove(0);
+                }
+                let method_call = false;
+                let res = self.check_fn_call(typed_fn_env, method_call, sig, args, expr.span)?;
 
                 res.map(|ty| ExprTyInfo::new(None, ty))
             }
@@ -240,6 +320,7 @@ impl<B: Backend> TypeChecker<B> {
                 if !rhs_typ.typ.match_expected(&lhs_node.typ) {
                     panic!("lhs type doesn't match rhs type (TODO: replace with error)");
                 }
+                self.check_int_coercion(rhs, &lhs_node.typ)?;
 
                 None
             }
@@ -248,34 +329,62 @@ impl<B: Backend> TypeChecker<B> {
                 let lhs_node = self
                     .compute_type(lhs, typed_fn_env)?
                     .expect("type-checker bug");
-                let rhs_node = self
-                    .compute_type(rhs, typed_fn_env)?
-                    .expect("type-checker bug");
 
-                if lhs_node.typ != rhs_node.typ {
-                    // only allow bigint mixed with field
-                    match (&lhs_node.typ, &rhs_node.typ) {
-                        (TyKind::BigInt, TyKind::Field) | (TyKind::Field, TyKind::BigInt) => (),
-                        _ => {
-                            return Err(self.error(
-                                ErrorKind::MismatchType(lhs_node.typ.clone(), rhs_node.typ.clone()),
-                                expr.span,
-                            ))
+                // `lhs + rhs` on a struct is a call to `lhs.add(rhs)`
+                if matches!(lhs_node.typ, TyKind::Custom { .. }) && op.method_name().is_some() {
+                    self.check_operator_call(typed_fn_env, &lhs_node.typ, op, rhs, expr.span)?
+                        .map(ExprTyInfo::new_anon)
+                } else {
+                    let rhs_node = self
+                        .compute_type(rhs, typed_fn_env)?
+                        .expect("type-checker bug");
+
+                    if lhs_node.typ != rhs_node.typ {
+                        // only allow bigint mixed with field or integers
+                        match (&lhs_node.typ, &rhs_node.typ) {
+                            (TyKind::BigInt, TyKind::Field) | (TyKind::Field, TyKind::BigInt) => (),
+                            (TyKind::BigInt, TyKind::U32 | TyKind::U64)
+                            | (TyKind::U32 | TyKind::U64, TyKind::BigInt) => (),
+                            _ => {
+                                return Err(self.error(
+                                    ErrorKind::MismatchType(
+                                        lhs_node.typ.clone(),
+                                        rhs_node.typ.clone(),
+                                    ),
+                                    expr.span,
+                                ))
+                            }
                         }
                     }
-                }
 
-                let typ = match op {
-                    Op2::Equality => TyKind::Bool,
-                    Op2::Addition
-                    | Op2::Subtraction
-                    | Op2::Multiplication
-                    | Op2::Division
-                    | Op2::BoolAnd
-                    | Op2::BoolOr => lhs_node.typ,
-                };
+                    // `/` multiplies by the inverse in the field, which isn't an integer division
+                    let is_int =
+                        lhs_node.typ.num_bits().is_some() || rhs_node.typ.num_bits().is_some();
+                    if matches!(op, Op2::Division) && is_int {
+                        return Err(
+                            self.error(ErrorKind::Unsupported("`/` on integers"), expr.span)
+                        );
+                    }
 
-                Some(ExprTyInfo::new_anon(typ))
+                    let typ = match op {
+                        Op2::Equality => TyKind::Bool,
+                        // an integer mixed with a constant stays an integer
+                        Op2::Addition | Op2::Subtraction | Op2::Multiplication | Op2::Modulo
+                            if rhs_node.typ.num_bits().is_some() =>
+                        {
+                            rhs_node.typ
+                        }
+                        Op2::Addition
+                        | Op2::Subtraction
+                        | Op2::Multiplication
+                        | Op2::Division
+                        | Op2::Modulo
+                        | Op2::BoolAnd
+                        | Op2::BoolOr => lhs_node.typ,
+                    };
+
+                    Some(ExprTyInfo::new_anon(typ))
+                }
             }
 
             ExprKind::Negated(inner) => {
@@ -304,6 +413,10 @@ impl<B: Backend> TypeChecker<B> {
 
             ExprKind::BigInt(_) => Some(ExprTyInfo::new_anon(TyKind::BigInt)),
 
+            ExprKind::StringLiteral(_) => {
+                return Err(self.error(ErrorKind::UnexpectedStringLiteral, expr.span))
+            }
+
             ExprKind::Bool(_) => Some(ExprTyInfo::new_anon(TyKind::Bool)),
 
             // mod::path.of.var
@@ -360,10 +473,11 @@ impl<B: Backend> TypeChecker<B> {
                     return Err(self.error(ErrorKind::ArrayAccessOnNonArray, expr.span));
                 }
 
-                // check that expression is a bigint
+                // check that expression is a bigint,
+                // or a field element in which case the element gets selected at runtime
                 let idx_typ = self.compute_type(idx, typed_fn_env)?;
                 match idx_typ.map(|t| t.typ) {
-                    Some(TyKind::BigInt) => (),
+                    Some(TyKind::BigInt | TyKind::Field | TyKind::U32 | TyKind::U64) => (),
                     _ => return Err(self.error(ErrorKind::ExpectedConstant, expr.span)),
                 };
 
@@ -377,6 +491,22 @@ impl<B: Backend> TypeChecker<B> {
                 Some(res)
             }
 
+            ExprKind::ArraySlice { array, range } => {
+                // get type of lhs
+                let typ = self.compute_type(array, typed_fn_env)?.unwrap();
+
+                // check that it is an array, and that the slice is within it
+                let (el_typ, len) = match typ.typ {
+                    TyKind::Array(typkind, len) => (typkind, len),
+                    _ => return Err(self.error(ErrorKind::ArrayAccessOnNonArray, expr.span)),
+                };
+                let slice = self.slice_range(range, len)?;
+
+                // the slice is an array of its own
+                let res = ExprTyInfo::new(typ.var_name, TyKind::Array(el_typ, slice.len() as u32));
+                Some(res)
+            }
+
             ExprKind::ArrayDeclaration(items) => {
                 let len: u32 = items.len().try_into().expect("array too large");
 
@@ -405,49 +535,51 @@ impl<B: Backend> TypeChecker<B> {
                 Some(res)
             }
 
+            ExprKind::TupleDeclaration(items) => {
+                let mut typs = Vec::with_capacity(items.len());
+                for item in items {
+                    let item_typ = self
+                        .compute_type(item, typed_fn_env)?
+                        .expect("expected a value");
+                    typs.push(item_typ.typ);
+                }
+
+                Some(ExprTyInfo::new_anon(TyKind::Tuple(typs)))
+            }
+
             ExprKind::IfElse { cond, then_, else_ } => {
                 // cond can only be a boolean
                 let cond_node = self
                     .compute_type(cond, typed_fn_env)?
                     .expect("can't compute type of condition");
                 if !matches!(cond_node.typ, TyKind::Bool) {
-                    panic!("`if` must be followed by a boolean");
-                }
-
-                // then_ and else_ can only be variables, field accesses, or array accesses
-                if !matches!(
-                    &then_.kind,
-                    ExprKind::Variable { .. }
-                        | ExprKind::FieldAccess { .. }
-                        | ExprKind::ArrayAccess { .. }
-                ) {
-                    panic!("`if` branch must be a variable, a field access, or an array access. It can't be logic that creates constraints.");
-                }
-
-                if !matches!(
-                    &else_.kind,
-                    ExprKind::Variable { .. }
-                        | ExprKind::FieldAccess { .. }
-                        | ExprKind::ArrayAccess { .. }
-                ) {
-                    panic!("`else` branch must be a variable, a field access, or an array access. It can't be logic that creates constraints.");
+                    return Err(self.error(ErrorKind::IfConditionNotBool(cond_node.typ), cond.span));
                 }
 
                 // compute type of if/else branches
                 let then_node = self
                     .compute_type(then_, typed_fn_env)?
-                    .expect("can't compute type of first branch of `if/else`");
+                    .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, then_.span))?;
                 let else_node = self
                     .compute_type(else_, typed_fn_env)?
-                    .expect("can't compute type of first branch of `if/else`");
+                    .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, else_.span))?;
 
                 // make sure that the type of then_ and else_ match
-                if then_node.typ != else_node.typ {
-                    panic!("`if` branch and `else` branch must have matching types");
+                if !then_node.typ.same_as(&else_node.typ) {
+                    return Err(self.error(
+                        ErrorKind::IfElseTypeMismatch(then_node.typ, else_node.typ),
+                        else_.span,
+                    ));
                 }
 
-                //
-                Some(ExprTyInfo::new_anon(then_node.typ))
+                // a constant in one branch shouldn't make the whole expression look constant
+                let typ = if matches!(then_node.typ, TyKind::BigInt) {
+                    else_node.typ
+                } else {
+                    then_node.typ
+                };
+
+                Some(ExprTyInfo::new_anon(typ))
             }
 
             ExprKind::CustomTypeDeclaration { custom, fields } => {
@@ -490,6 +622,7 @@ impl<B: Backend> TypeChecker<B> {
                             expr.span,
                         ));
                     }
+                    self.check_int_coercion(&observed.1, &defined.1)?;
                 }
 
                 let res = ExprTyInfo::new_anon(TyKind::Custom {
@@ -559,21 +692,63 @@ impl<B: Backend> TypeChecker<B> {
         stmt: &Stmt,
     ) -> Result<Option<TyKind>> {
         match &stmt.kind {
-            StmtKind::Assign { mutable, lhs, rhs } => {
+            StmtKind::Assign {
+                mutable,
+                lhs,
+                typ,
+                rhs,
+            } => {
                 // inferance can be easy: we can do it the Golang way and just use the type that rhs has (in `let` assignments)
 
                 // but first we need to compute the type of the rhs expression
                 let node = self.compute_type(rhs, typed_fn_env)?.unwrap();
 
+                // if the variable is annotated, the rhs must have the annotated type,
+                // but the variable keeps the type of the rhs (like in the circuit writer),
+                // unless it is a constant bound to the annotated type
+                let typ = match typ {
+                    Some(typ) => {
+                        if !node.typ.match_expected(&typ.kind) {
+                            return Err(self.error(
+                                ErrorKind::MismatchType(typ.kind.clone(), node.typ),
+                                rhs.span,
+                            ));
+                        }
+                        match node.typ {
+                            TyKind::BigInt => typ.kind.clone(),
+                            inferred => inferred,
+                        }
+                    }
+                    None => node.typ,
+                };
+
                 let type_info = if *mutable {
-                    TypeInfo::new_mut(node.typ, lhs.span)
+                    TypeInfo::new_mut(typ, lhs.span)
                 } else {
-                    TypeInfo::new(node.typ, lhs.span)
+                    TypeInfo::new(typ, lhs.span)
                 };
 
                 // store the type of lhs in the env
                 typed_fn_env.store_type(lhs.value.clone(), type_info)?;
             }
+            StmtKind::AssignTuple { lhs, rhs } => {
+                let node = self.compute_type(rhs, typed_fn_env)?.unwrap();
+
+                // the rhs must be a tuple with as many members as there are variables
+                let typs = match node.typ {
+                    TyKind::Tuple(typs) if typs.len() == lhs.len() => typs,
+                    typ => {
+                        return Err(self.error(
+                            ErrorKind::MismatchTupleDestructuring(typ, lhs.len()),
+                            stmt.span,
+                        ))
+                    }
+                };
+
+                for (var, typ) in lhs.iter().zip(typs) {
+                    typed_fn_env.store_type(var.value.clone(), TypeInfo::new(typ, var.span))?;
+                }
+            }
             StmtKind::ForLoop { var, range, body } => {
                 // enter a new scope
                 typed_fn_env.nest();
@@ -583,9 +758,7 @@ impl<B: Backend> TypeChecker<B> {
                     .store_type(var.value.clone(), TypeInfo::new(TyKind::BigInt, var.span))?;
 
                 // ensure start..end makes sense
-                if range.end < range.start {
-                    panic!("end can't be smaller than start (TODO: better error)");
-                }
+                self.check_loop_range(range)?;
 
                 // check block
                 self.check_block(typed_fn_env, body, None)?;
@@ -593,6 +766,53 @@ impl<B: Backend> TypeChecker<B> {
                 // exit the scope
                 typed_fn_env.pop();
             }
+            StmtKind::If { cond, body } => {
+                // cond can only be a boolean
+                let cond_node = self
+                    .compute_type(cond, typed_fn_env)?
+                    .expect("can't compute type of condition");
+                if !matches!(cond_node.typ, TyKind::Bool) {
+                    return Err(self.error(ErrorKind::IfConditionNotBool(cond_node.typ), cond.span));
+                }
+
+                typed_fn_env.nest();
+
+                // the body can end with an early return,
+                // which is checked against the return type of the function later
+                for (ii, stmt) in body.iter().enumerate() {
+                    if let Some(typ) = self.check_stmt(typed_fn_env, stmt)? {
+                        if let Some(next) = body.get(ii + 1) {
+                            return Err(self.error(ErrorKind::StatementAfterReturn, next.span));
+                        }
+                        typed_fn_env.early_returns.push((typ, stmt.span));
+                    }
+                }
+
+                typed_fn_env.pop();
+            }
+            StmtKind::While {
+                cond,
+                max_iters,
+                body,
+            } => {
+                // cond can only be a boolean
+                let cond_node = self
+                    .compute_type(cond, typed_fn_env)?
+                    .expect("can't compute type of condition");
+                if !matches!(cond_node.typ, TyKind::Bool) {
+                    return Err(
+                        self.error(ErrorKind::WhileConditionNotBool(cond_node.typ), cond.span)
+                    );
+                }
+
+                // the loop is unrolled, so its number of iterations must be known
+                self.check_loop_bound(max_iters, stmt.span)?;
+
+                // like the body of a for loop, the body can't return
+                typed_fn_env.nest();
+                self.check_block(typed_fn_env, body, None)?;
+                typed_fn_env.pop();
+            }
             StmtKind::Expr(expr) => {
                 // make sure the expression does not return any type
                 // (it's a statement expression, it should only work via side effect)
@@ -613,6 +833,82 @@ impl<B: Backend> TypeChecker<B> {
         Ok(None)
     }
 
+    /// Type checks a binary operator on a custom struct as a call to the method overloading it
+    /// (see [Op2::method_name]), which must take `self` and the right operand.
+    fn check_operator_call(
+        &mut self,
+        typed_fn_env: &mut TypedFnEnv,
+        lhs_typ: &TyKind,
+        op: &Op2,
+        rhs: &Expr,
+        span: Span,
+    ) -> Result<Option<TyKind>> {
+        let missing_method = || {
+            let struct_name = match lhs_typ {
+                TyKind::Custom { name, .. } => name.clone(),
+                _ => unreachable!("type-checker bug: operator method on a non-struct type"),
+            };
+            let method_name = op.method_name().expect("the operator can't be overloaded");
+            self.error(
+                ErrorKind::MissingOperatorMethod(struct_name, method_name),
+                span,
+            )
+        };
+
+        let method = self
+            .operator_method(lhs_typ, op)
+            .ok_or_else(missing_method)?;
+        let is_method = method
+            .sig
+            .arguments
+            .first()
+            .is_some_and(|arg| arg.name.value == "self");
+        if !is_method {
+            return Err(missing_method());
+        }
+
+        let method_call = true;
+        let sig = method.sig.clone();
+        self.check_fn_call(
+            typed_fn_env,
+            method_call,
+            sig,
+            std::slice::from_ref(rhs),
+            span,
+        )
+    }
+
+    /// Checks that the literals of an expression used as a value of an integer type (e.g. a `U32` argument) fit in it,
+    /// as [TyKind::match_expected] lets any constant be used as an integer.
+    /// Literals bound to annotated variables are checked by the circuit writer instead.
+    fn check_int_coercion(&self, expr: &Expr, expected: &TyKind) -> Result<()> {
+        match (&expr.kind, expected) {
+            (ExprKind::BigInt(s), _) => {
+                let num_bits = match expected.num_bits() {
+                    Some(num_bits) => num_bits,
+                    None => return Ok(()),
+                };
+                let fits = parse_number(s).map_or(true, |value| value.bits() as usize <= num_bits);
+                if !fits {
+                    return Err(self.error(ErrorKind::IntegerOverflow(expected.clone()), expr.span));
+                }
+            }
+            (ExprKind::ArrayDeclaration(items), TyKind::Array(item_typ, _)) => {
+                for item in items {
+                    self.check_int_coercion(item, item_typ)?;
+                }
+            }
+            (ExprKind::TupleDeclaration(items), TyKind::Tuple(typs)) => {
+                for (item, typ) in items.iter().zip(typs) {
+                    self.check_int_coercion(item, typ)?;
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
     /// type checks a function call.
     /// Note that this can also be a method call.
     pub fn check_fn_call(
@@ -653,16 +949,205 @@ impl<B: Backend> TypeChecker<B> {
         }
 
         // compare argument types with the function signature
-        for (sig_arg, (typ, span)) in expected.iter().zip(observed) {
+        for ((sig_arg, (typ, span)), arg) in expected.iter().zip(observed).zip(args) {
             if !typ.match_expected(&sig_arg.typ.kind) {
                 return Err(self.error(
                     ErrorKind::ArgumentTypeMismatch(sig_arg.typ.kind.clone(), typ),
                     span,
                 ));
             }
+            self.check_int_coercion(arg, &sig_arg.typ.kind)?;
         }
 
         // return the return type of the function
         Ok(fn_sig.return_type.as_ref().map(|ty| ty.kind.clone()))
     }
+
+    /// Type checks a call to `fold(arr, init, f)`, and returns the type of the accumulator.
+    fn check_fold(
+        &mut self,
+        typed_fn_env: &mut TypedFnEnv,
+        args: &[Expr],
+        call: &Expr,
+    ) -> Result<TyKind> {
+        let (arr, init, func) = match args {
+            [arr, init, func] => (arr, init, func),
+            _ => {
+                return Err(self.error(
+                    ErrorKind::MismatchFunctionArguments(args.len(), 3),
+                    call.span,
+                ))
+            }
+        };
+
+        let elem_typ = match self.compute_type(arr, typed_fn_env)?.map(|node| node.typ) {
+            Some(TyKind::Array(elem_typ, _)) => *elem_typ,
+            Some(typ) => return Err(self.error(ErrorKind::FoldOnNonArray(typ), arr.span)),
+            None => return Err(self.error(ErrorKind::CannotComputeExpression, arr.span)),
+        };
+
+        let acc_typ = self
+            .compute_type(init, typed_fn_env)?
+            .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, init.span))?
+            .typ;
+
+        // the function is passed by name
+        let (module, fn_name) = match &func.kind {
+            ExprKind::Variable { module, name } => (module, name),
+            _ => return Err(self.error(ErrorKind::FoldFunctionNotNamed, func.span)),
+        };
+
+        let qualified = FullyQualified::new(module, &fn_name.value);
+        let fn_info = self.fn_info(&qualified).ok_or_else(|| {
+            self.error(
+                ErrorKind::UndefinedFunction(fn_name.value.clone()),
+                fn_name.span,
+            )
+        })?;
+        let fn_sig = fn_info.sig().clone();
+
+        // a generic function is instantiated for the accumulator and the elements
+        // (the circuit writer finds the instance through the call to `fold`)
+        let generic_fn = match &fn_info.kind {
+            FnKind::Native(function) if !function.sig.generics.is_empty() => Some(function.clone()),
+            _ => None,
+        };
+        let fn_sig = match generic_fn {
+            Some(function) => {
+                let observed = [(acc_typ.clone(), init.span), (elem_typ.clone(), arr.span)];
+                self.instantiate_generic_fn(&qualified, &function, &observed, HashMap::new(), call)?
+            }
+            None => fn_sig,
+        };
+
+        // `f(acc, elem) -> acc`
+        match (fn_sig.arguments.as_slice(), &fn_sig.return_type) {
+            ([acc_arg, elem_arg], Some(ret))
+                if acc_typ.match_expected(&acc_arg.typ.kind)
+                    && elem_typ.match_expected(&elem_arg.typ.kind)
+                    && ret.kind.match_expected(&acc_arg.typ.kind) =>
+            {
+                Ok(ret.kind.clone())
+            }
+            _ => Err(self.error(
+                ErrorKind::FoldSignatureMismatch(acc_typ, elem_typ),
+                func.span,
+            )),
+        }
+    }
+
+    /// Type checks a call to `crypto::merkle_verify_with(leaf, path, indices, root, hash)`:
+    /// the arguments before the hash are the ones of `crypto::merkle_verify`.
+    fn check_merkle_verify_with(
+        &mut self,
+        typed_fn_env: &mut TypedFnEnv,
+        args: &[Expr],
+        call: &Expr,
+    ) -> Result<()> {
+        let (hash, args) = match args.split_last() {
+            Some((hash, args)) if args.len() == 4 => (hash, args),
+            _ => {
+                return Err(self.error(
+                    ErrorKind::MismatchFunctionArguments(args.len(), 5),
+                    call.span,
+                ))
+            }
+        };
+
+        // instantiate the signature of `merkle_verify` for the depth of the tree
+        let qualified = FullyQualified::new(
+            &ModulePath::Absolute(UserRepo::new(QUALIFIED_CRYPTO)),
+            &"merkle_verify".to_string(),
+        );
+        let sig = self
+            .fn_info(&qualified)
+            .expect("type-checker bug: `merkle_verify` is not a builtin")
+            .sig()
+            .clone();
+        let values = self.const_generics(&sig, args)?;
+        let mut observed = Vec::with_capacity(args.len());
+        for arg in args {
+            let node = self
+                .compute_type(arg, typed_fn_env)?
+                .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, arg.span))?;
+            observed.push((node.typ, arg.span));
+        }
+        let sig = self.instantiate_generic_builtin(sig, &observed, values)?;
+
+        let method_call = false;
+        self.check_fn_call(typed_fn_env, method_call, sig, args, call.span)?;
+
+        // the hash is passed by name
+        let (module, fn_name) = match &hash.kind {
+            ExprKind::Variable { module, name } => (module, name),
+            _ => return Err(self.error(ErrorKind::InvalidMerkleHash, hash.span)),
+        };
+
+        let qualified = FullyQualified::new(module, &fn_name.value);
+        let fn_info = self.fn_info(&qualified).ok_or_else(|| {
+            self.error(
+                ErrorKind::UndefinedFunction(fn_name.value.clone()),
+                fn_name.span,
+            )
+        })?;
+
+        // `hash(left, right) -> parent`
+        let sig = fn_info.sig();
+        match (sig.arguments.as_slice(), &sig.return_type) {
+            ([left, right], Some(parent))
+                if sig.generics.is_empty()
+                    && [&left.typ.kind, &right.typ.kind, &parent.kind]
+                        .iter()
+                        .all(|typ| matches!(typ, TyKind::Field)) =>
+            {
+                Ok(())
+            }
+            _ => Err(self.error(ErrorKind::InvalidMerkleHash, hash.span)),
+        }
+    }
+
+    /// Type checks a call to `select(cond, a, b)`, and returns the type of `a` and `b`.
+    fn check_select(
+        &mut self,
+        typed_fn_env: &mut TypedFnEnv,
+        args: &[Expr],
+        call: &Expr,
+    ) -> Result<TyKind> {
+        let (cond, lhs, rhs) = match args {
+            [cond, lhs, rhs] => (cond, lhs, rhs),
+            _ => {
+                return Err(self.error(
+                    ErrorKind::MismatchFunctionArguments(args.len(), 3),
+                    call.span,
+                ))
+            }
+        };
+
+        let mut typ_of = |expr: &Expr| {
+            self.compute_type(expr, typed_fn_env)?
+                .map(|node| node.typ)
+                .ok_or_else(|| self.error(ErrorKind::CannotComputeExpression, expr.span))
+        };
+        let cond_typ = typ_of(cond)?;
+        let lhs_typ = typ_of(lhs)?;
+        let rhs_typ = typ_of(rhs)?;
+
+        if !matches!(cond_typ, TyKind::Bool) {
+            return Err(self.error(
+                ErrorKind::ArgumentTypeMismatch(TyKind::Bool, cond_typ),
+                cond.span,
+            ));
+        }
+
+        if !lhs_typ.same_as(&rhs_typ) {
+            return Err(self.error(ErrorKind::SelectTypeMismatch(lhs_typ, rhs_typ), rhs.span));
+        }
+
+        // like for `if`/`else` expressions, a constant shouldn't make the result look constant
+        if matches!(lhs_typ, TyKind::BigInt) {
+            Ok(rhs_typ)
+        } else {
+            Ok(lhs_typ)
+        }
+    }
 }
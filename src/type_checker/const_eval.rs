@@ -0,0 +1,778 @@
+//! A compile-time interpreter, used to compute the value of constants like `const mask: Field = pow2(8) - 1;`.
+//!
+//! It only supports a pure subset of the language:
+//! field and boolean arithmetic, arrays, `if`/`else` expressions,
+//! `for` loops with constant bounds, and calls to functions declared with `const fn`.
+//! Anything that needs a circuit (builtins, structs, tuples, other functions) is an error.
+
+use std::collections::{HashMap, HashSet};
+
+use num_bigint::BigUint;
+use num_traits::Zero as _;
+
+use crate::{
+    backends::Backend,
+    circuit_writer::DEFAULT_LOOP_BUDGET,
+    constants::Span,
+    error::{ErrorKind, Result},
+    imports::FnKind,
+    parser::{
+        types::{ConstDef, FunctionDef, RangeBound, Stmt, StmtKind, Ty, TyKind},
+        ConstValue, Expr, ExprKind, Op2,
+    },
+    syntax::parse_number,
+};
+
+use super::{ConstInfo, FullyQualified, TypeChecker};
+
+/// The maximum depth of nested `const fn` calls, so that a recursive `const fn` errors instead of overflowing the stack.
+const MAX_CALL_DEPTH: usize = 64;
+
+/// The maximum number of loop iterations run to compute a constant (including in the `const fn`s it calls),
+/// so that a huge loop errors instead of hanging, like the loops unrolled in a circuit (see [DEFAULT_LOOP_BUDGET]).
+const MAX_LOOP_ITERATIONS: usize = DEFAULT_LOOP_BUDGET;
+
+/// A value computed at compile time.
+#[derive(Debug, Clone, PartialEq)]
+enum ConstVal<F> {
+    Field(F),
+    Bool(bool),
+    Array(Vec<ConstVal<F>>),
+}
+
+impl<F> ConstVal<F> {
+    fn typ(&self) -> TyKind {
+        match self {
+            ConstVal::Field(_) => TyKind::Field,
+            ConstVal::Bool(_) => TyKind::Bool,
+            ConstVal::Array(items) => {
+                let item_typ = items.first().map(Self::typ).unwrap_or(TyKind::Field);
+                TyKind::Array(Box::new(item_typ), items.len() as u32)
+            }
+        }
+    }
+}
+
+/// A local variable of a `const fn` (or of a constant initializer).
+struct Local<F> {
+    value: ConstVal<F>,
+    mutable: bool,
+}
+
+/// The local variables in scope, innermost scope last.
+struct ConstEnv<F> {
+    scopes: Vec<HashMap<String, Local<F>>>,
+
+    /// The number of loop iterations run so far to compute the constant (see [MAX_LOOP_ITERATIONS]).
+    iterations: usize,
+}
+
+impl<F> ConstEnv<F> {
+    fn new(iterations: usize) -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            iterations,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&Local<F>> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Local<F>> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    fn store(&mut self, name: String, value: ConstVal<F>, mutable: bool) {
+        self.scopes
+            .last_mut()
+            .expect("no scope to store the variable in")
+            .insert(name, Local { value, mutable });
+    }
+}
+
+/// Collects the constants (and local variables) that an initializer refers to,
+/// along with the functions it calls (whose bodies can refer to other constants, see [referenced_constants_in_body]).
+pub(crate) fn referenced_constants(
+    expr: &Expr,
+    refs: &mut Vec<FullyQualified>,
+    calls: &mut Vec<FullyQualified>,
+) {
+    match &expr.kind {
+        ExprKind::Variable { module, name } => refs.push(FullyQualified::new(module, &name.value)),
+        ExprKind::FnCall {
+            module,
+            fn_name,
+            args,
+        } => {
+            calls.push(FullyQualified::new(module, &fn_name.value));
+            for arg in args {
+                referenced_constants(arg, refs, calls);
+            }
+        }
+        ExprKind::ArrayDeclaration(args) | ExprKind::TupleDeclaration(args) => {
+            for arg in args {
+                referenced_constants(arg, refs, calls);
+            }
+        }
+        ExprKind::MethodCall { lhs, args, .. } => {
+            referenced_constants(lhs, refs, calls);
+            for arg in args {
+                referenced_constants(arg, refs, calls);
+            }
+        }
+        ExprKind::Assignment { lhs, rhs }
+        | ExprKind::BinaryOp { lhs, rhs, .. }
+        | ExprKind::ArrayAccess {
+            array: lhs,
+            idx: rhs,
+        } => {
+            referenced_constants(lhs, refs, calls);
+            referenced_constants(rhs, refs, calls);
+        }
+        ExprKind::FieldAccess { lhs, .. } | ExprKind::Negated(lhs) | ExprKind::Not(lhs) => {
+            referenced_constants(lhs, refs, calls)
+        }
+        ExprKind::ArraySlice { array, range } => {
+            referenced_constants(array, refs, calls);
+            for bound in [&range.start, &range.end] {
+                if let RangeBound::Const { module, name } = bound {
+                    refs.push(FullyQualified::new(module, &name.value));
+                }
+            }
+        }
+        ExprKind::CustomTypeDeclaration { fields, .. } => {
+            for (_, field) in fields {
+                referenced_constants(field, refs, calls);
+            }
+        }
+        ExprKind::IfElse { cond, then_, else_ } => {
+            referenced_constants(cond, refs, calls);
+            referenced_constants(then_, refs, calls);
+            referenced_constants(else_, refs, calls);
+        }
+        ExprKind::BigInt(_) | ExprKind::StringLiteral(_) | ExprKind::Bool(_) => (),
+    }
+}
+
+/// Same as [referenced_constants], for the statements of the body of a function.
+fn referenced_constants_in_body(
+    body: &[Stmt],
+    refs: &mut Vec<FullyQualified>,
+    calls: &mut Vec<FullyQualified>,
+) {
+    let bound = |bound: &RangeBound, refs: &mut Vec<FullyQualified>| {
+        if let RangeBound::Const { module, name } = bound {
+            refs.push(FullyQualified::new(module, &name.value));
+        }
+    };
+
+    for stmt in body {
+        match &stmt.kind {
+            StmtKind::Assign { rhs, .. } | StmtKind::AssignTuple { rhs, .. } => {
+                referenced_constants(rhs, refs, calls)
+            }
+            StmtKind::Expr(expr) | StmtKind::Return(expr) => {
+                referenced_constants(expr, refs, calls)
+            }
+            StmtKind::Comment(_) => (),
+            StmtKind::ForLoop { range, body, .. } => {
+                bound(&range.start, refs);
+                bound(&range.end, refs);
+                referenced_constants_in_body(body, refs, calls);
+            }
+            StmtKind::If { cond, body } => {
+                referenced_constants(cond, refs, calls);
+                referenced_constants_in_body(body, refs, calls);
+            }
+            StmtKind::While {
+                cond,
+                max_iters,
+                body,
+            } => {
+                referenced_constants(cond, refs, calls);
+                bound(max_iters, refs);
+                referenced_constants_in_body(body, refs, calls);
+            }
+        }
+    }
+}
+
+impl<B: Backend> TypeChecker<B> {
+    /// Computes the values of the constants of a module, in any order of declaration:
+    /// each constant is evaluated after the constants that its initializer refers to,
+    /// and a constant that (transitively) refers to itself is an error.
+    pub(crate) fn eval_const_defs(&mut self, csts: &[&ConstDef<B::Field>]) -> Result<()> {
+        let mut indices = HashMap::new();
+        for (idx, cst) in csts.iter().enumerate() {
+            let qualified = FullyQualified::new(&cst.module, &cst.name.value);
+            if indices.insert(qualified, idx).is_some() {
+                return Err(self.error(
+                    ErrorKind::DuplicateDefinition(cst.name.value.clone()),
+                    cst.name.span,
+                ));
+            }
+        }
+
+        // `None` if not visited yet, `Some(false)` while its dependencies are evaluated
+        let mut evaluated = vec![None; csts.len()];
+        for idx in 0..csts.len() {
+            self.eval_const_def(csts, &indices, &mut evaluated, idx)?;
+        }
+
+        Ok(())
+    }
+
+    fn eval_const_def(
+        &mut self,
+        csts: &[&ConstDef<B::Field>],
+        indices: &HashMap<FullyQualified, usize>,
+        evaluated: &mut Vec<Option<bool>>,
+        idx: usize,
+    ) -> Result<()> {
+        let cst = csts[idx];
+        match evaluated[idx] {
+            Some(true) => return Ok(()),
+            Some(false) => {
+                return Err(self.error(
+                    ErrorKind::CyclicConstant(cst.name.value.clone()),
+                    cst.name.span,
+                ))
+            }
+            None => evaluated[idx] = Some(false),
+        }
+
+        let (value, typ) = match &cst.value {
+            ConstValue::Literal { value, typ } => (value.clone(), typ.clone()),
+            ConstValue::Computed { expr, annotation } => {
+                // evaluate the constants of the module it refers to first,
+                // including the ones read in the bodies of the `const fn`s it (transitively) calls
+                let mut refs = vec![];
+                let mut calls = vec![];
+                referenced_constants(expr, &mut refs, &mut calls);
+                let mut visited = HashSet::new();
+                while let Some(qualified) = calls.pop() {
+                    if !visited.insert(qualified.clone()) {
+                        continue;
+                    }
+                    if let Some(FnKind::Native(function)) =
+                        self.fn_info(&qualified).map(|fn_info| &fn_info.kind)
+                    {
+                        referenced_constants_in_body(&function.body, &mut refs, &mut calls);
+                    }
+                }
+                for qualified in refs {
+                    if let Some(&dep) = indices.get(&qualified) {
+                        self.eval_const_def(csts, indices, evaluated, dep)?;
+                    }
+                }
+
+                self.eval_const(expr, annotation.as_ref())?
+            }
+        };
+
+        let qualified = FullyQualified::new(&cst.module, &cst.name.value);
+        if self
+            .constants
+            .insert(qualified, ConstInfo { value, typ })
+            .is_some()
+        {
+            return Err(self.error(
+                ErrorKind::DuplicateDefinition(cst.name.value.clone()),
+                cst.name.span,
+            ));
+        }
+
+        evaluated[idx] = Some(true);
+        Ok(())
+    }
+
+    /// Evaluates the initializer of a constant, and returns its value along with its type.
+    /// The value must be a field element or an array of field elements, and match the type annotation (if any).
+    pub(crate) fn eval_const(
+        &self,
+        expr: &Expr,
+        annotation: Option<&Ty>,
+    ) -> Result<(Vec<B::Field>, Ty)> {
+        let mut env = ConstEnv::new(0);
+        let value = self.eval_expr(&mut env, expr, 0)?;
+
+        let kind = value.typ();
+        let value = match value {
+            ConstVal::Field(value) => vec![value],
+            ConstVal::Array(items) => items
+                .into_iter()
+                .map(|item| match item {
+                    ConstVal::Field(value) => Ok(value),
+                    _ => Err(self.error(ErrorKind::InvalidConstType, expr.span)),
+                })
+                .collect::<Result<_>>()?,
+            ConstVal::Bool(_) => return Err(self.error(ErrorKind::InvalidConstType, expr.span)),
+        };
+
+        if let Some(annotation) = annotation {
+            if !kind.same_as(&annotation.kind) {
+                return Err(self.error(
+                    ErrorKind::MismatchType(annotation.kind.clone(), kind),
+                    expr.span,
+                ));
+            }
+        }
+
+        let typ = Ty {
+            kind,
+            span: expr.span,
+        };
+        Ok((value, typ))
+    }
+
+    fn eval_expr(
+        &self,
+        env: &mut ConstEnv<B::Field>,
+        expr: &Expr,
+        depth: usize,
+    ) -> Result<ConstVal<B::Field>> {
+        match &expr.kind {
+            ExprKind::BigInt(s) => parse_number(s)
+                .and_then(|biguint| B::Field::try_from(biguint).ok())
+                .map(ConstVal::Field)
+                .ok_or_else(|| self.error(ErrorKind::CannotConvertToField(s.clone()), expr.span)),
+
+            ExprKind::Bool(b) => Ok(ConstVal::Bool(*b)),
+
+            ExprKind::Variable { module, name } => {
+                // like in the type checker, constants take precedence over local variables
+                let qualified = FullyQualified::new(module, &name.value);
+                if let Some(cst) = self.const_info(&qualified) {
+                    self.pin_constant(&qualified);
+                    let value = match cst.typ.kind {
+                        TyKind::Field => ConstVal::Field(cst.value[0]),
+                        _ => ConstVal::Array(
+                            cst.value.iter().copied().map(ConstVal::Field).collect(),
+                        ),
+                    };
+                    return Ok(value);
+                }
+
+                env.get(&name.value)
+                    .map(|local| local.value.clone())
+                    .ok_or_else(|| self.error(ErrorKind::UndefinedVariable, name.span))
+            }
+
+            ExprKind::BinaryOp { op, lhs, rhs, .. } => {
+                let lhs = self.eval_expr(env, lhs, depth)?;
+                let rhs = self.eval_expr(env, rhs, depth)?;
+
+                match (op, lhs, rhs) {
+                    (Op2::Equality, lhs, rhs) => {
+                        if !lhs.typ().same_as(&rhs.typ()) {
+                            return Err(self
+                                .error(ErrorKind::MismatchType(lhs.typ(), rhs.typ()), expr.span));
+                        }
+                        Ok(ConstVal::Bool(lhs == rhs))
+                    }
+                    (Op2::Addition, ConstVal::Field(lhs), ConstVal::Field(rhs)) => {
+                        Ok(ConstVal::Field(lhs + rhs))
+                    }
+                    (Op2::Subtraction, ConstVal::Field(lhs), ConstVal::Field(rhs)) => {
+                        Ok(ConstVal::Field(lhs - rhs))
+                    }
+                    (Op2::Multiplication, ConstVal::Field(lhs), ConstVal::Field(rhs)) => {
+                        Ok(ConstVal::Field(lhs * rhs))
+                    }
+                    (Op2::Division, ConstVal::Field(lhs), ConstVal::Field(rhs)) => {
+                        let inverse = rhs
+                            .inverse()
+                            .ok_or_else(|| self.error(ErrorKind::DivisionByZero, expr.span))?;
+                        Ok(ConstVal::Field(lhs * inverse))
+                    }
+                    (Op2::Modulo, ConstVal::Field(lhs), ConstVal::Field(rhs)) => {
+                        let (lhs, rhs): (BigUint, BigUint) = (lhs.into(), rhs.into());
+                        if rhs.is_zero() {
+                            return Err(self.error(ErrorKind::ZeroModulus, expr.span));
+                        }
+                        Ok(ConstVal::Field(B::Field::from(lhs % rhs)))
+                    }
+                    (Op2::BoolAnd, ConstVal::Bool(lhs), ConstVal::Bool(rhs)) => {
+                        Ok(ConstVal::Bool(lhs && rhs))
+                    }
+                    (Op2::BoolOr, ConstVal::Bool(lhs), ConstVal::Bool(rhs)) => {
+                        Ok(ConstVal::Bool(lhs || rhs))
+                    }
+                    (Op2::BoolAnd | Op2::BoolOr, lhs, rhs) => {
+                        let wrong = if matches!(lhs, ConstVal::Bool(_)) {
+                            rhs
+                        } else {
+                            lhs
+                        };
+                        Err(self.error(
+                            ErrorKind::MismatchType(TyKind::Bool, wrong.typ()),
+                            expr.span,
+                        ))
+                    }
+                    (_, lhs, rhs) => {
+                        let wrong = if matches!(lhs, ConstVal::Field(_)) {
+                            rhs
+                        } else {
+                            lhs
+                        };
+                        Err(self.error(
+                            ErrorKind::MismatchType(TyKind::Field, wrong.typ()),
+                            expr.span,
+                        ))
+                    }
+                }
+            }
+
+            ExprKind::Negated(inner) => match self.eval_expr(env, inner, depth)? {
+                ConstVal::Field(value) => Ok(ConstVal::Field(-value)),
+                other => Err(self.error(
+                    ErrorKind::MismatchType(TyKind::Field, other.typ()),
+                    inner.span,
+                )),
+            },
+
+            ExprKind::Not(inner) => match self.eval_expr(env, inner, depth)? {
+                ConstVal::Bool(value) => Ok(ConstVal::Bool(!value)),
+                other => Err(self.error(
+                    ErrorKind::MismatchType(TyKind::Bool, other.typ()),
+                    inner.span,
+                )),
+            },
+
+            ExprKind::ArrayDeclaration(items) => {
+                let mut values = Vec::with_capacity(items.len());
+                for item in items {
+                    values.push(self.eval_expr(env, item, depth)?);
+                }
+                Ok(ConstVal::Array(values))
+            }
+
+            ExprKind::ArrayAccess { array, idx } => {
+                let idx = self.eval_index(env, idx, depth)?;
+                match self.eval_expr(env, array, depth)? {
+                    ConstVal::Array(mut items) => {
+                        if idx >= items.len() {
+                            return Err(self.error(
+                                ErrorKind::ArrayIndexOutOfBounds(
+                                    idx,
+                                    items.len().saturating_sub(1),
+                                ),
+                                expr.span,
+                            ));
+                        }
+                        Ok(items.swap_remove(idx))
+                    }
+                    _ => Err(self.error(ErrorKind::ArrayAccessOnNonArray, array.span)),
+                }
+            }
+
+            ExprKind::ArraySlice { array, range } => match self.eval_expr(env, array, depth)? {
+                ConstVal::Array(items) => {
+                    let slice = self.slice_range(range, items.len() as u32)?;
+                    Ok(ConstVal::Array(
+                        items[slice.start as usize..slice.end as usize].to_vec(),
+                    ))
+                }
+                _ => Err(self.error(ErrorKind::ArrayAccessOnNonArray, array.span)),
+            },
+
+            ExprKind::IfElse { cond, then_, else_ } => {
+                let branch = match self.eval_expr(env, cond, depth)? {
+                    ConstVal::Bool(true) => then_,
+                    ConstVal::Bool(false) => else_,
+                    other => {
+                        return Err(self.error(
+                            ErrorKind::MismatchType(TyKind::Bool, other.typ()),
+                            cond.span,
+                        ))
+                    }
+                };
+                self.eval_expr(env, branch, depth)
+            }
+
+            ExprKind::FnCall {
+                module,
+                fn_name,
+                args,
+            } => {
+                let qualified = FullyQualified::new(module, &fn_name.value);
+                let fn_info = self.fn_info(&qualified).ok_or_else(|| {
+                    self.error(
+                        ErrorKind::UndefinedFunction(fn_name.value.clone()),
+                        fn_name.span,
+                    )
+                })?;
+
+                let function = match &fn_info.kind {
+                    FnKind::Native(function) if function.is_const => function,
+                    _ => {
+                        return Err(self.error(
+                            ErrorKind::NotConstEvaluable(
+                                "only functions declared with `const fn` can be called",
+                            ),
+                            expr.span,
+                        ))
+                    }
+                };
+
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.eval_expr(env, arg, depth)?);
+                }
+
+                self.eval_const_fn(env, function, values, depth + 1, expr.span)
+            }
+
+            ExprKind::MethodCall { .. } => Err(self.error(
+                ErrorKind::NotConstEvaluable("methods cannot be called"),
+                expr.span,
+            )),
+            ExprKind::Assignment { .. } => Err(self.error(
+                ErrorKind::NotConstEvaluable("an assignment is not an expression"),
+                expr.span,
+            )),
+            ExprKind::FieldAccess { .. } | ExprKind::CustomTypeDeclaration { .. } => Err(self
+                .error(
+                    ErrorKind::NotConstEvaluable("structs are not supported"),
+                    expr.span,
+                )),
+            ExprKind::TupleDeclaration(_) => Err(self.error(
+                ErrorKind::NotConstEvaluable("tuples are not supported"),
+                expr.span,
+            )),
+        }
+    }
+
+    /// Evaluates an array index.
+    fn eval_index(&self, env: &mut ConstEnv<B::Field>, idx: &Expr, depth: usize) -> Result<usize> {
+        match self.eval_expr(env, idx, depth)? {
+            ConstVal::Field(value) => {
+                let value: BigUint = value.into();
+                usize::try_from(value).map_err(|_| {
+                    self.error(
+                        ErrorKind::NotConstEvaluable("the array index is too large"),
+                        idx.span,
+                    )
+                })
+            }
+            other => Err(self.error(
+                ErrorKind::MismatchType(TyKind::Field, other.typ()),
+                idx.span,
+            )),
+        }
+    }
+
+    fn eval_const_fn(
+        &self,
+        caller_env: &mut ConstEnv<B::Field>,
+        function: &FunctionDef,
+        args: Vec<ConstVal<B::Field>>,
+        depth: usize,
+        span: Span,
+    ) -> Result<ConstVal<B::Field>> {
+        if depth > MAX_CALL_DEPTH {
+            return Err(self.error(
+                ErrorKind::NotConstEvaluable("too many nested function calls"),
+                span,
+            ));
+        }
+
+        let expected = function.sig.arguments.len();
+        if args.len() != expected {
+            return Err(self.error(
+                ErrorKind::MismatchFunctionArguments(args.len(), expected),
+                span,
+            ));
+        }
+
+        // the arguments are the only variables in scope
+        let mut env = ConstEnv::new(caller_env.iterations);
+        for (arg, value) in function.sig.arguments.iter().zip(args) {
+            if !value.typ().same_as(&arg.typ.kind) {
+                return Err(self.error(
+                    ErrorKind::ArgumentTypeMismatch(arg.typ.kind.clone(), value.typ()),
+                    span,
+                ));
+            }
+            env.store(arg.name.value.clone(), value, arg.mutable);
+        }
+
+        let res = self.eval_block(&mut env, &function.body, depth);
+        caller_env.iterations = env.iterations;
+
+        res?.ok_or_else(|| self.error(ErrorKind::MissingReturn, function.span))
+    }
+
+    /// Counts a loop iteration, and fails if too many iterations were run to compute the constant.
+    fn count_iteration(&self, env: &mut ConstEnv<B::Field>, span: Span) -> Result<()> {
+        env.iterations += 1;
+        if env.iterations > MAX_LOOP_ITERATIONS {
+            return Err(self.error(
+                ErrorKind::ConstLoopBudgetExceeded(MAX_LOOP_ITERATIONS),
+                span,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Evaluates statements, and returns the value of the first `return` statement reached (if any).
+    fn eval_block(
+        &self,
+        env: &mut ConstEnv<B::Field>,
+        stmts: &[Stmt],
+        depth: usize,
+    ) -> Result<Option<ConstVal<B::Field>>> {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Assign {
+                    mutable, lhs, rhs, ..
+                } => {
+                    let value = self.eval_expr(env, rhs, depth)?;
+                    env.store(lhs.value.clone(), value, *mutable);
+                }
+
+                StmtKind::AssignTuple { .. } => {
+                    return Err(self.error(
+                        ErrorKind::NotConstEvaluable("tuples are not supported"),
+                        stmt.span,
+                    ))
+                }
+
+                StmtKind::Expr(expr) => match &expr.kind {
+                    ExprKind::Assignment { lhs, rhs } => {
+                        let value = self.eval_expr(env, rhs, depth)?;
+                        let slot = self.eval_place(env, lhs, depth)?;
+                        *slot = value;
+                    }
+                    _ => {
+                        self.eval_expr(env, expr, depth)?;
+                    }
+                },
+
+                StmtKind::Return(expr) => return Ok(Some(self.eval_expr(env, expr, depth)?)),
+
+                StmtKind::Comment(_) => (),
+
+                StmtKind::ForLoop { var, range, body } => {
+                    self.pin_range(range);
+                    for ii in self.range_iterations(range)? {
+                        self.count_iteration(env, stmt.span)?;
+                        env.scopes.push(HashMap::new());
+                        env.store(var.value.clone(), ConstVal::Field(ii.into()), false);
+                        let res = self.eval_block(env, body, depth);
+                        env.scopes.pop();
+
+                        if let Some(value) = res? {
+                            return Ok(Some(value));
+                        }
+                    }
+                }
+
+                StmtKind::If { cond, body } => match self.eval_expr(env, cond, depth)? {
+                    ConstVal::Bool(true) => {
+                        env.scopes.push(HashMap::new());
+                        let res = self.eval_block(env, body, depth);
+                        env.scopes.pop();
+
+                        if let Some(value) = res? {
+                            return Ok(Some(value));
+                        }
+                    }
+                    ConstVal::Bool(false) => (),
+                    other => {
+                        return Err(self.error(
+                            ErrorKind::MismatchType(TyKind::Bool, other.typ()),
+                            cond.span,
+                        ))
+                    }
+                },
+
+                StmtKind::While {
+                    cond,
+                    max_iters,
+                    body,
+                } => {
+                    self.pin_bound(max_iters);
+                    let max_iters = self.bound_value(max_iters)?;
+
+                    let mut iterations = 0;
+                    loop {
+                        match self.eval_expr(env, cond, depth)? {
+                            ConstVal::Bool(true) if iterations < max_iters => (),
+                            ConstVal::Bool(true) => {
+                                return Err(self.error(
+                                    ErrorKind::WhileLoopNotTerminated(max_iters),
+                                    stmt.span,
+                                ))
+                            }
+                            ConstVal::Bool(false) => break,
+                            other => {
+                                return Err(self.error(
+                                    ErrorKind::MismatchType(TyKind::Bool, other.typ()),
+                                    cond.span,
+                                ))
+                            }
+                        }
+
+                        self.count_iteration(env, stmt.span)?;
+                        env.scopes.push(HashMap::new());
+                        let res = self.eval_block(env, body, depth);
+                        env.scopes.pop();
+
+                        if let Some(value) = res? {
+                            return Ok(Some(value));
+                        }
+                        iterations += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the value that the left-hand side of an assignment (`xx = ...` or `xx[ii] = ...`) refers to.
+    fn eval_place<'env>(
+        &self,
+        env: &'env mut ConstEnv<B::Field>,
+        lhs: &Expr,
+        depth: usize,
+    ) -> Result<&'env mut ConstVal<B::Field>> {
+        match &lhs.kind {
+            ExprKind::Variable { module: _, name } => {
+                let local = env
+                    .get_mut(&name.value)
+                    .ok_or_else(|| self.error(ErrorKind::UndefinedVariable, name.span))?;
+                if !local.mutable {
+                    return Err(self.error(ErrorKind::AssignmentToImmutableVariable, name.span));
+                }
+                Ok(&mut local.value)
+            }
+
+            ExprKind::ArrayAccess { array, idx } => {
+                let idx = self.eval_index(env, idx, depth)?;
+                match self.eval_place(env, array, depth)? {
+                    ConstVal::Array(items) => {
+                        let len = items.len();
+                        items.get_mut(idx).ok_or_else(|| {
+                            self.error(
+                                ErrorKind::ArrayIndexOutOfBounds(idx, len.saturating_sub(1)),
+                                lhs.span,
+                            )
+                        })
+                    }
+                    _ => Err(self.error(ErrorKind::ArrayAccessOnNonArray, array.span)),
+                }
+            }
+
+            _ => Err(self.error(
+                ErrorKind::NotConstEvaluable("only variables and array elements can be assigned"),
+                lhs.span,
+            )),
+        }
+    }
+}
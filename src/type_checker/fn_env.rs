@@ -65,6 +65,10 @@ pub struct TypedFnEnv {
     /// This needs to be garbage collected when we exit a scope.
     // TODO: there's an output_type field that's a reserved keyword?
     vars: HashMap<String, (usize, TypeInfo)>,
+
+    /// The types (and spans) of the values returned early, from the body of `if` statements.
+    /// They are checked against the return type of the function once its body is type checked.
+    pub early_returns: Vec<(TyKind, Span)>,
 }
 
 impl TypedFnEnv {
@@ -0,0 +1,337 @@
+//! Generic functions, like `fn sum[N](arr: [Field; N]) -> Field`.
+//!
+//! The generic parameters of a function can only be used as array sizes in its signature,
+//! and as constants in its body (for example, in `for ii in 0..N`).
+//! A generic function is not type checked on its own:
+//! each call infers the values of the generic parameters from the types of its arguments
+//! (or from the values of the constant arguments named after them, e.g. `const N: Field`),
+//! and refers to an instance of the function where the generic parameters are replaced by these values.
+//! Every distinct set of values produces a distinct instance (e.g. `sum[3]` and `sum[4]`),
+//! which is type checked once and compiled like any other function.
+//! Builtins can be generic too (e.g. `from_bits[N](bits: [Bool; N]) -> Field`):
+//! they have no body to instantiate, so only their signature is.
+
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+
+use crate::{
+    backends::Backend,
+    constants::Span,
+    error::{ErrorKind, Result},
+    imports::FnKind,
+    parser::{
+        types::{FnSig, FunctionDef, Range, RangeBound, Stmt, StmtKind, TyKind},
+        Expr, ExprKind,
+    },
+};
+
+use super::{FnInfo, FullyQualified, TypeChecker};
+
+impl<B: Backend> TypeChecker<B> {
+    /// Returns the values of the generic parameters given by constant arguments
+    /// (e.g. `N` in `to_bits[N](value: Field, const N: Field) -> [Bool; N]`),
+    /// which must be known at compile time.
+    pub(crate) fn const_generics(
+        &self,
+        sig: &FnSig,
+        args: &[Expr],
+    ) -> Result<HashMap<String, u32>> {
+        let mut values = HashMap::new();
+
+        for (arg, expr) in sig.arguments.iter().zip(args) {
+            let name = &arg.name.value;
+            if !arg.is_constant() || !sig.generics.iter().any(|generic| &generic.value == name) {
+                continue;
+            }
+
+            let err = || self.error(ErrorKind::GenericNotConstant(name.clone()), expr.span);
+            let (value, _typ) = self.eval_const(expr, None).map_err(|_| err())?;
+            let size = match value.as_slice() {
+                [value] => {
+                    let value: BigUint = (*value).into();
+                    u32::try_from(value).map_err(|_| err())?
+                }
+                _ => return Err(err()),
+            };
+            values.insert(name.clone(), size);
+        }
+
+        Ok(values)
+    }
+
+    /// Returns the signature of a generic builtin for the given arguments,
+    /// where the generic parameters are replaced by their values.
+    /// Like for [Self::instantiate_generic_fn], the signature is returned as is
+    /// if the arguments don't allow to infer all the generic parameters.
+    pub(crate) fn instantiate_generic_builtin(
+        &self,
+        mut sig: FnSig,
+        observed: &[(TyKind, Span)],
+        mut values: HashMap<String, u32>,
+    ) -> Result<FnSig> {
+        for (arg, (typ, span)) in sig.arguments.iter().zip(observed) {
+            self.infer_generics(&arg.typ.kind, typ, *span, &mut values)?;
+        }
+
+        if sig
+            .generics
+            .iter()
+            .all(|generic| values.contains_key(&generic.value))
+        {
+            instantiate_sig(&mut sig, &values);
+        }
+
+        Ok(sig)
+    }
+
+    /// Type checks a call to a generic function by instantiating the function for the given arguments,
+    /// and returns the signature of the instance.
+    /// The generic parameters given by constant arguments are passed in `values` (see [Self::const_generics]).
+    /// If the arguments don't allow to infer all the generic parameters,
+    /// the signature of the generic function is returned,
+    /// and the mismatch is reported when the call is type checked against it.
+    pub(crate) fn instantiate_generic_fn(
+        &mut self,
+        qualified: &FullyQualified,
+        function: &FunctionDef,
+        observed: &[(TyKind, Span)],
+        mut values: HashMap<String, u32>,
+        call: &Expr,
+    ) -> Result<FnSig> {
+        // infer the generic parameters from the types of the arguments
+        for (arg, (typ, span)) in function.sig.arguments.iter().zip(observed) {
+            self.infer_generics(&arg.typ.kind, typ, *span, &mut values)?;
+        }
+
+        let mut sizes = Vec::with_capacity(function.sig.generics.len());
+        for generic in &function.sig.generics {
+            match values.get(&generic.value) {
+                Some(size) => sizes.push(size.to_string()),
+                None => return Ok(function.sig.clone()),
+            }
+        }
+
+        // e.g. `sum[3]`
+        let instance = FullyQualified {
+            module: qualified.module.clone(),
+            name: format!("{}[{}]", qualified.name, sizes.join(", ")),
+        };
+
+        if self.fn_info(&instance).is_none() {
+            let instance_fn = self.instantiate_fn(function, &values);
+            let fn_info = FnInfo {
+                kind: FnKind::Native(instance_fn.clone()),
+                span: function.span,
+            };
+            self.functions.insert(instance.clone(), fn_info);
+
+            self.check_fn_body(&instance_fn, false)?;
+        }
+
+        let sig = self
+            .fn_info(&instance)
+            .expect("type-checker bug: instance of a generic function not found")
+            .sig()
+            .clone();
+
+        // the circuit writer compiles the instance that the call refers to
+        self.instances.insert(call.node_id, instance);
+
+        Ok(sig)
+    }
+
+    /// Infers the generic parameters used by the `expected` type of an argument from its `observed` type.
+    fn infer_generics(
+        &self,
+        expected: &TyKind,
+        observed: &TyKind,
+        span: Span,
+        values: &mut HashMap<String, u32>,
+    ) -> Result<()> {
+        match (expected, observed) {
+            (TyKind::GenericArray(expected, name), TyKind::Array(observed, size)) => {
+                if let Some(other) = values.insert(name.clone(), *size) {
+                    if other != *size {
+                        return Err(self.error(
+                            ErrorKind::ConflictingGeneric(name.clone(), other, *size),
+                            span,
+                        ));
+                    }
+                }
+
+                self.infer_generics(expected, observed, span, values)
+            }
+            (TyKind::Array(expected, _), TyKind::Array(observed, _)) => {
+                self.infer_generics(expected, observed, span, values)
+            }
+            (TyKind::Tuple(expected), TyKind::Tuple(observed)) => {
+                for (expected, observed) in expected.iter().zip(observed) {
+                    self.infer_generics(expected, observed, span, values)?;
+                }
+                Ok(())
+            }
+            // any other mismatch is reported when the call is type checked
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns a copy of the function where the generic parameters are replaced by their values.
+    fn instantiate_fn(
+        &mut self,
+        function: &FunctionDef,
+        values: &HashMap<String, u32>,
+    ) -> FunctionDef {
+        let mut instance = function.clone();
+        instantiate_sig(&mut instance.sig, values);
+
+        for stmt in &mut instance.body {
+            self.instantiate_stmt(stmt, values);
+        }
+
+        instance
+    }
+
+    fn instantiate_stmt(&mut self, stmt: &mut Stmt, values: &HashMap<String, u32>) {
+        match &mut stmt.kind {
+            StmtKind::Assign { rhs, .. } | StmtKind::AssignTuple { rhs, .. } => {
+                self.instantiate_expr(rhs, values)
+            }
+            StmtKind::Expr(expr) | StmtKind::Return(expr) => self.instantiate_expr(expr, values),
+            StmtKind::ForLoop { range, body, .. } => {
+                instantiate_range(range, values);
+
+                for stmt in body {
+                    self.instantiate_stmt(stmt, values);
+                }
+            }
+            StmtKind::If { cond, body } => {
+                self.instantiate_expr(cond, values);
+
+                for stmt in body {
+                    self.instantiate_stmt(stmt, values);
+                }
+            }
+            StmtKind::While {
+                cond,
+                max_iters,
+                body,
+            } => {
+                self.instantiate_expr(cond, values);
+                instantiate_bound(max_iters, values);
+
+                for stmt in body {
+                    self.instantiate_stmt(stmt, values);
+                }
+            }
+            StmtKind::Comment(_) => (),
+        }
+    }
+
+    fn instantiate_expr(&mut self, expr: &mut Expr, values: &HashMap<String, u32>) {
+        // the nodes of each instance are typed separately, so they need their own ids
+        self.node_id += 1;
+        expr.node_id = self.node_id;
+
+        match &mut expr.kind {
+            ExprKind::Variable { module: _, name } => {
+                if let Some(value) = values.get(&name.value) {
+                    expr.kind = ExprKind::BigInt(value.to_string());
+                }
+            }
+            ExprKind::FnCall { args, .. } => {
+                for arg in args {
+                    self.instantiate_expr(arg, values);
+                }
+            }
+            ExprKind::MethodCall { lhs, args, .. } => {
+                self.instantiate_expr(lhs, values);
+                for arg in args {
+                    self.instantiate_expr(arg, values);
+                }
+            }
+            ExprKind::Assignment { lhs, rhs } | ExprKind::BinaryOp { lhs, rhs, .. } => {
+                self.instantiate_expr(lhs, values);
+                self.instantiate_expr(rhs, values);
+            }
+            ExprKind::FieldAccess { lhs, .. } | ExprKind::Negated(lhs) | ExprKind::Not(lhs) => {
+                self.instantiate_expr(lhs, values)
+            }
+            ExprKind::ArrayAccess { array, idx } => {
+                self.instantiate_expr(array, values);
+                self.instantiate_expr(idx, values);
+            }
+            ExprKind::ArraySlice { array, range } => {
+                self.instantiate_expr(array, values);
+                instantiate_range(range, values);
+            }
+            ExprKind::ArrayDeclaration(items) | ExprKind::TupleDeclaration(items) => {
+                for item in items {
+                    self.instantiate_expr(item, values);
+                }
+            }
+            ExprKind::CustomTypeDeclaration { fields, .. } => {
+                for (_, field) in fields {
+                    self.instantiate_expr(field, values);
+                }
+            }
+            ExprKind::IfElse { cond, then_, else_ } => {
+                self.instantiate_expr(cond, values);
+                self.instantiate_expr(then_, values);
+                self.instantiate_expr(else_, values);
+            }
+            ExprKind::BigInt(_) | ExprKind::StringLiteral(_) | ExprKind::Bool(_) => (),
+        }
+    }
+}
+
+/// Replaces the bounds of a range that are generic parameters by their values.
+fn instantiate_range(range: &mut Range, values: &HashMap<String, u32>) {
+    instantiate_bound(&mut range.start, values);
+    instantiate_bound(&mut range.end, values);
+}
+
+/// Replaces a bound that is a generic parameter by its value.
+fn instantiate_bound(bound: &mut RangeBound, values: &HashMap<String, u32>) {
+    if let RangeBound::Const { module: _, name } = bound {
+        if let Some(&value) = values.get(&name.value) {
+            *bound = RangeBound::Literal(value);
+        }
+    }
+}
+
+/// Replaces the generic parameters of a signature by their values.
+fn instantiate_sig(sig: &mut FnSig, values: &HashMap<String, u32>) {
+    sig.generics.clear();
+
+    for arg in &mut sig.arguments {
+        instantiate_typ(&mut arg.typ.kind, values);
+    }
+    if let Some(return_type) = &mut sig.return_type {
+        instantiate_typ(&mut return_type.kind, values);
+    }
+}
+
+/// Replaces the generic array sizes of a type by their values.
+fn instantiate_typ(typ: &mut TyKind, values: &HashMap<String, u32>) {
+    match typ {
+        TyKind::GenericArray(elem_typ, name) => {
+            let size = values[name.as_str()];
+            let mut elem_typ = elem_typ.clone();
+            instantiate_typ(&mut elem_typ, values);
+            *typ = TyKind::Array(elem_typ, size);
+        }
+        TyKind::Array(elem_typ, _) => instantiate_typ(elem_typ, values),
+        TyKind::Tuple(typs) => {
+            for typ in typs {
+                instantiate_typ(typ, values);
+            }
+        }
+        TyKind::Field
+        | TyKind::Custom { .. }
+        | TyKind::BigInt
+        | TyKind::Bool
+        | TyKind::U32
+        | TyKind::U64 => (),
+    }
+}
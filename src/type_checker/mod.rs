@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
 
 use crate::{
     backends::Backend,
@@ -8,21 +11,32 @@ use crate::{
     imports::FnKind,
     name_resolution::NAST,
     parser::{
-        types::{FuncOrMethod, FunctionDef, ModulePath, RootKind, Ty, TyKind},
-        CustomType, Expr, StructDef,
+        types::{
+            Attribute, AttributeKind, FuncOrMethod, FunctionDef, ModulePath, Range, RangeBound,
+            RootKind, StmtKind, Ty, TyKind,
+        },
+        CustomType, Expr, Op2, StructDef,
+    },
+    stdlib::{
+        builtin_fns,
+        crypto::{crypto_fns, QUALIFIED_CRYPTO},
+        fp2::{fp2_fns, fp2_struct, FP2_STRUCT, QUALIFIED_FP2},
+        QUALIFIED_BUILTINS,
     },
-    stdlib::{builtin_fns, crypto::crypto_fns, QUALIFIED_BUILTINS},
 };
 
 use ark_ff::Field;
 pub use checker::{FnInfo, StructInfo};
 pub use fn_env::{TypeInfo, TypedFnEnv};
+use num_bigint::BigUint;
 
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
 pub mod checker;
+pub mod const_eval;
 pub mod fn_env;
+pub mod generics;
 
 const RESERVED_ARGS: [&str; 1] = ["public_output"];
 
@@ -37,7 +51,7 @@ where
     pub typ: Ty,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct FullyQualified {
     /// Set to `None` if the function is defined in the main module.
     pub module: Option<UserRepo>,
@@ -52,7 +66,7 @@ impl FullyQualified {
     pub fn new(module: &ModulePath, name: &String) -> Self {
         let module = match module {
             ModulePath::Local => None,
-            ModulePath::Alias(_) => unreachable!(),
+            ModulePath::Alias(_) | ModulePath::Qualified(_, _) => unreachable!(),
             ModulePath::Absolute(user_repo) => Some(user_repo.clone()),
         };
         Self {
@@ -82,6 +96,40 @@ where
     /// This can be used by the circuit-writer when it needs type information.
     // TODO: I think we should get rid of this if we can
     node_types: HashMap<usize, TyKind>,
+
+    /// Mapping from the node id of a call to a generic function
+    /// to the instance of the function that it calls (see [generics]).
+    instances: HashMap<usize, FullyQualified>,
+
+    /// The last node id used, by the parser or by the instances of generic functions.
+    node_id: usize,
+
+    /// The constants whose value was used during type checking,
+    /// which can't be overridden anymore (see [TypeChecker::override_constants]).
+    compile_time_constants: RefCell<HashSet<FullyQualified>>,
+
+    /// The ranges of the `for` loops and the bounds of the `while` loops that were checked during type checking,
+    /// which are checked again if one of their constants is overridden (see [TypeChecker::override_constants]).
+    loop_bounds: Vec<Range>,
+}
+
+/// The value given to a constant of the main module when generating a circuit,
+/// which takes precedence over the value in the source (see [TypeChecker::override_constants]).
+#[derive(Debug, Clone)]
+pub enum ConstOverride<F> {
+    Field(F),
+    Array(Vec<F>),
+}
+
+impl<F> ConstOverride<F> {
+    fn typ(&self) -> TyKind {
+        match self {
+            ConstOverride::Field(_) => TyKind::Field,
+            ConstOverride::Array(values) => {
+                TyKind::Array(Box::new(TyKind::Field), values.len() as u32)
+            }
+        }
+    }
 }
 
 impl<B: Backend> TypeChecker<B> {
@@ -98,14 +146,280 @@ impl<B: Backend> TypeChecker<B> {
         self.structs.get(qualified)
     }
 
+    /// Returns the method overloading a binary operator on the type of its left operand,
+    /// for example `Point.add` for `lhs + rhs` where `lhs` is a `Point`.
+    pub(crate) fn operator_method(&self, lhs_typ: &TyKind, op: &Op2) -> Option<&FunctionDef> {
+        match lhs_typ {
+            TyKind::Custom { module, name } => {
+                let method_name = op.method_name()?;
+                let qualified = FullyQualified::new(module, name);
+                self.struct_info(&qualified)?.methods.get(method_name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the function called by `lhs.method_name(args)` on an `lhs` of type `lhs_typ`,
+    /// and whether `lhs` is passed to it as first argument.
+    /// This is a method of the struct (static or taking `self`),
+    /// or else a (non-generic) function of the struct's module whose first argument has the type of the struct:
+    /// `pp.norm_sq()` is sugar for `norm_sq(pp)`.
+    pub(crate) fn method_info(
+        &self,
+        lhs_typ: &TyKind,
+        method_name: &str,
+    ) -> Option<(&FunctionDef, bool)> {
+        let (module, name) = match lhs_typ {
+            TyKind::Custom { module, name } => (module, name),
+            _ => return None,
+        };
+
+        let qualified = FullyQualified::new(module, name);
+        if let Some(method) = self.struct_info(&qualified)?.methods.get(method_name) {
+            let takes_self =
+                matches!(method.sig.arguments.first(), Some(arg) if arg.name.value == "self");
+            return Some((method, takes_self));
+        }
+
+        let qualified = FullyQualified::new(module, &method_name.to_string());
+        match &self.fn_info(&qualified)?.kind {
+            FnKind::Native(func) if func.sig.generics.is_empty() => {
+                let first_arg = func.sig.arguments.first()?;
+                lhs_typ
+                    .match_expected(&first_arg.typ.kind)
+                    .then_some((func, true))
+            }
+            FnKind::Native(_) | FnKind::BuiltIn(..) => None,
+        }
+    }
+
     pub(crate) fn fn_info(&self, qualified: &FullyQualified) -> Option<&FnInfo<B>> {
         self.functions.get(qualified)
     }
 
+    /// Returns the function called by a function call expression,
+    /// which is an instance of the function if it is generic.
+    pub(crate) fn called_fn(
+        &self,
+        call: &Expr,
+        module: &ModulePath,
+        fn_name: &String,
+    ) -> FullyQualified {
+        self.instances
+            .get(&call.node_id)
+            .cloned()
+            .unwrap_or_else(|| FullyQualified::new(module, fn_name))
+    }
+
     pub(crate) fn const_info(&self, qualified: &FullyQualified) -> Option<&ConstInfo<B::Field>> {
         self.constants.get(&qualified)
     }
 
+    /// Records that the value of a constant is used during type checking,
+    /// so that it can't be overridden (see [Self::override_constants]).
+    pub(crate) fn pin_constant(&self, qualified: &FullyQualified) {
+        self.compile_time_constants
+            .borrow_mut()
+            .insert(qualified.clone());
+    }
+
+    /// Records that the bounds of a range are used during type checking (see [Self::pin_constant]).
+    pub(crate) fn pin_range(&self, range: &Range) {
+        self.pin_bound(&range.start);
+        self.pin_bound(&range.end);
+    }
+
+    /// Records that a bound is used during type checking (see [Self::pin_constant]).
+    pub(crate) fn pin_bound(&self, bound: &RangeBound) {
+        if let RangeBound::Const { module, name } = bound {
+            self.pin_constant(&FullyQualified::new(module, &name.value));
+        }
+    }
+
+    /// Checks the range of a `for` loop, and records it to check it again if its bounds are overridden.
+    pub(crate) fn check_loop_range(&mut self, range: &Range) -> Result<()> {
+        self.loop_range_iterations(range)?;
+        self.loop_bounds.push(range.clone());
+        Ok(())
+    }
+
+    /// Checks the bound of the iterations of a `while` loop, and records it to check it again if it is overridden.
+    pub(crate) fn check_loop_bound(&mut self, bound: &RangeBound, span: Span) -> Result<()> {
+        self.check_loop_range(&Range {
+            start: RangeBound::Literal(0),
+            end: bound.clone(),
+            span,
+        })
+    }
+
+    /// Returns the iterations of the range of a loop, which must not end before it starts.
+    fn loop_range_iterations(&self, range: &Range) -> Result<std::ops::Range<u32>> {
+        let iterations = self.range_iterations(range)?;
+        if iterations.end < iterations.start {
+            return Err(Error::new(
+                "type-checker",
+                ErrorKind::DecreasingRange(iterations.start, iterations.end),
+                range.span,
+            ));
+        }
+        Ok(iterations)
+    }
+
+    /// Replaces the values of constants of the main module, once the program is type checked.
+    /// Each overridden constant must exist with the same type,
+    /// and its value must not have been used during type checking
+    /// (by another constant, a `const fn` or the bounds of a slice),
+    /// as these uses would still see the value of the source.
+    /// The loops whose bounds are overridden are checked again with the new values.
+    pub fn override_constants(
+        &mut self,
+        overrides: &HashMap<String, ConstOverride<B::Field>>,
+    ) -> Result<()> {
+        for (name, value) in overrides {
+            let qualified = FullyQualified::local(name.clone());
+
+            // the constant is missing from the main module, so the error points at its `main` function
+            let main_span = self
+                .functions
+                .get(&FullyQualified::local("main".to_string()))
+                .map_or(Span::default(), |fn_info| fn_info.sig().name.span);
+            let cst_info = self.constants.get_mut(&qualified).ok_or_else(|| {
+                Error::new(
+                    "type-checker",
+                    ErrorKind::UndefinedConstantOverride(name.clone()),
+                    main_span,
+                )
+            })?;
+
+            let typ = value.typ();
+            if !typ.same_as(&cst_info.typ.kind) {
+                return Err(Error::new(
+                    "type-checker",
+                    ErrorKind::MismatchType(cst_info.typ.kind.clone(), typ),
+                    cst_info.typ.span,
+                ));
+            }
+
+            if self.compile_time_constants.borrow().contains(&qualified) {
+                return Err(Error::new(
+                    "type-checker",
+                    ErrorKind::ConstantUsedAtCompileTime(name.clone()),
+                    cst_info.typ.span,
+                ));
+            }
+
+            cst_info.value = match value {
+                ConstOverride::Field(value) => vec![*value],
+                ConstOverride::Array(values) => values.clone(),
+            };
+        }
+
+        for range in &self.loop_bounds {
+            self.loop_range_iterations(range)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the test functions of the main module (see [AttributeKind::Test]),
+    /// in the order in which they are declared.
+    pub fn test_fns(&self) -> Vec<&FunctionDef> {
+        let mut tests: Vec<_> = self
+            .functions
+            .iter()
+            .filter_map(|(qualified, fn_info)| match &fn_info.kind {
+                FnKind::Native(function)
+                    if qualified.module.is_none() && function.test_case().is_some() =>
+                {
+                    Some(function)
+                }
+                FnKind::Native(_) | FnKind::BuiltIn(..) => None,
+            })
+            .collect();
+        tests.sort_by_key(|function| function.span.start);
+        tests
+    }
+
+    /// Makes a test function the entry point of the circuit, in place of the main function:
+    /// its arguments become private inputs, and its return value the public output.
+    pub fn use_test_as_main(&mut self, name: &str) -> Result<()> {
+        let qualified = FullyQualified::local(name.to_string());
+        let mut function = match self.fn_info(&qualified).map(|fn_info| &fn_info.kind) {
+            Some(FnKind::Native(function)) if function.test_case().is_some() => function.clone(),
+            _ => {
+                return Err(Error::new(
+                    "type-checker",
+                    ErrorKind::UndefinedFunction(name.to_string()),
+                    Span::default(),
+                ))
+            }
+        };
+
+        function.sig.name.value = "main".to_string();
+        let fn_info = FnInfo {
+            span: function.span,
+            kind: FnKind::Native(function),
+        };
+        self.functions
+            .insert(FullyQualified::local("main".to_string()), fn_info);
+
+        Ok(())
+    }
+
+    /// Returns the iterations of a `for` loop, resolving the bounds that are constants.
+    pub(crate) fn range_iterations(&self, range: &Range) -> Result<std::ops::Range<u32>> {
+        Ok(self.bound_value(&range.start)?..self.bound_value(&range.end)?)
+    }
+
+    /// Returns the value of a bound (of a range, or of the iterations of a `while` loop).
+    pub(crate) fn bound_value(&self, bound: &RangeBound) -> Result<u32> {
+        match bound {
+            RangeBound::Literal(value) => Ok(*value),
+            RangeBound::Const { module, name } => {
+                let err = || {
+                    Error::new(
+                        "type-checker",
+                        ErrorKind::InvalidRangeBound(name.value.clone()),
+                        name.span,
+                    )
+                };
+
+                let qualified = FullyQualified::new(module, &name.value);
+                let cst_info = self.const_info(&qualified).ok_or_else(err)?;
+                if !matches!(cst_info.typ.kind, TyKind::Field) {
+                    return Err(err());
+                }
+
+                let value: BigUint = cst_info.value[0].into();
+                u32::try_from(value).map_err(|_| err())
+            }
+        }
+    }
+
+    /// Returns the elements of an array of length `len` selected by the range of a slice (`array[start..end]`),
+    /// after checking that they exist.
+    pub(crate) fn slice_range(&self, range: &Range, len: u32) -> Result<std::ops::Range<u32>> {
+        // the length of the slice is part of its type
+        self.pin_range(range);
+
+        let slice = self.range_iterations(range)?;
+        if slice.start > slice.end {
+            return Err(Error::new(
+                "type-checker",
+                ErrorKind::ReversedSlice(slice.start, slice.end),
+                range.span,
+            ));
+        }
+        if slice.end > len {
+            return Err(Error::new(
+                "type-checker",
+                ErrorKind::SliceOutOfBounds(slice.start, slice.end, len),
+                range.span,
+            ));
+        }
+        Ok(slice)
+    }
+
     /// Returns the number of field elements contained in the given type.
     // TODO: might want to memoize that at some point
     pub(crate) fn size_of(&self, typ: &TyKind) -> usize {
@@ -127,7 +441,14 @@ impl<B: Backend> TypeChecker<B> {
             }
             TyKind::BigInt => 1,
             TyKind::Array(typ, len) => (*len as usize) * self.size_of(typ),
+            TyKind::GenericArray(..) => {
+                unreachable!(
+                    "generic arrays are replaced by arrays in the instances of generic functions"
+                )
+            }
             TyKind::Bool => 1,
+            TyKind::Tuple(typs) => typs.iter().map(|typ| self.size_of(typ)).sum(),
+            TyKind::U32 | TyKind::U64 => 1,
         }
     }
 }
@@ -140,6 +461,10 @@ impl<B: Backend> TypeChecker<B> {
             structs: HashMap::new(),
             constants: HashMap::new(),
             node_types: HashMap::new(),
+            instances: HashMap::new(),
+            node_id: 0,
+            compile_time_constants: RefCell::new(HashSet::new()),
+            loop_bounds: vec![],
         };
 
         // initialize it with the builtins
@@ -156,7 +481,7 @@ impl<B: Backend> TypeChecker<B> {
         }
 
         // initialize it with the standard library
-        let crypto_module = ModulePath::Absolute(UserRepo::new("std/crypto"));
+        let crypto_module = ModulePath::Absolute(UserRepo::new(QUALIFIED_CRYPTO));
         for fn_info in crypto_fns() {
             let qualified = FullyQualified::new(&crypto_module, &fn_info.sig().name.value);
             if type_checker
@@ -168,6 +493,22 @@ impl<B: Backend> TypeChecker<B> {
             }
         }
 
+        let fp2_module = ModulePath::Absolute(UserRepo::new(QUALIFIED_FP2));
+        for fn_info in fp2_fns() {
+            let qualified = FullyQualified::new(&fp2_module, &fn_info.sig().name.value);
+            if type_checker
+                .functions
+                .insert(qualified, fn_info.clone())
+                .is_some()
+            {
+                panic!("type-checker bug: global imports conflict");
+            }
+        }
+        type_checker.structs.insert(
+            FullyQualified::new(&fp2_module, &FP2_STRUCT.to_string()),
+            fp2_struct(),
+        );
+
         //
         type_checker
     }
@@ -179,13 +520,20 @@ impl<B: Backend> TypeChecker<B> {
     /// This takes the AST produced by the parser, and performs two things:
     /// - resolves imports
     /// - type checks
-    pub fn analyze(&mut self, nast: NAST<B>, is_lib: bool) -> Result<()> {
+    ///
+    /// `node_id` is the last node id used by the parser.
+    /// The nodes of the instances of generic functions are numbered after it,
+    /// and the last node id used is returned.
+    pub fn analyze(&mut self, nast: NAST<B>, is_lib: bool, node_id: usize) -> Result<usize> {
+        self.node_id = node_id;
+
         //
         // Process constants
         //
 
         // we detect struct or function definition
         let mut abort = None;
+        let mut csts = vec![];
 
         for root in &nast.ast.0 {
             match &root.kind {
@@ -199,37 +547,31 @@ impl<B: Backend> TypeChecker<B> {
                         ));
                     }
 
-                    let qualified = FullyQualified::new(&cst.module, &cst.name.value);
-
-                    if self
-                        .constants
-                        .insert(
-                            qualified,
-                            ConstInfo {
-                                value: vec![cst.value],
-                                typ: Ty {
-                                    kind: TyKind::Field,
-                                    span: cst.span,
-                                },
-                            },
-                        )
-                        .is_some()
-                    {
-                        return Err(Error::new(
-                            "type-checker",
-                            ErrorKind::DuplicateDefinition(cst.name.value.clone()),
-                            cst.name.span,
-                        ));
+                    csts.push(cst);
+                }
+
+                // `const fn`s can be declared among constants, to be used by the constants that follow
+                RootKind::FunctionDef(function) if function.is_const && abort.is_none() => {
+                    if let FuncOrMethod::Function(module) = &function.sig.kind {
+                        let qualified = FullyQualified::new(module, &function.sig.name.value);
+                        let fn_info = FnInfo {
+                            kind: FnKind::Native(function.clone()),
+                            span: function.span,
+                        };
+                        self.functions.insert(qualified, fn_info);
                     }
                 }
 
                 RootKind::FunctionDef(FunctionDef { span, .. })
                 | RootKind::StructDef(StructDef { span, .. }) => abort = Some(*span),
 
-                RootKind::Use(_) | RootKind::Comment(_) => (),
+                RootKind::Use(_) | RootKind::TypeAlias(_) | RootKind::Comment(_) => (),
             }
         }
 
+        // constants can refer to each other, whatever their order of declaration
+        self.eval_const_defs(&csts)?;
+
         //
         // Type check structs
         //
@@ -266,6 +608,7 @@ impl<B: Backend> TypeChecker<B> {
                 RootKind::ConstDef(_)
                 | RootKind::Use(_)
                 | RootKind::FunctionDef(_)
+                | RootKind::TypeAlias(_)
                 | RootKind::Comment(_) => (),
             }
         }
@@ -278,9 +621,6 @@ impl<B: Backend> TypeChecker<B> {
             match &root.kind {
                 // `fn main() { ... }`
                 RootKind::FunctionDef(function) => {
-                    // create a new typed fn environment to type check the function
-                    let mut typed_fn_env = TypedFnEnv::default();
-
                     // if we're expecting a library, this should not be the main function
                     let is_main = function.is_main();
                     if is_main && is_lib {
@@ -300,6 +640,13 @@ impl<B: Backend> TypeChecker<B> {
                         ));
                     }
 
+                    // a test function can be compiled in place of the main function
+                    if let Some(attribute) = &function.attribute {
+                        if attribute.kind.is_test() {
+                            check_test_fn(function, attribute)?;
+                        }
+                    }
+
                     // save the function in the typed global env
                     let fn_kind = FnKind::Native(function.clone());
                     let fn_info = FnInfo {
@@ -330,81 +677,174 @@ impl<B: Backend> TypeChecker<B> {
                         }
                     };
 
-                    // store variables and their types in the fn_env
-                    for arg in &function.sig.arguments {
-                        // public_output is a reserved name,
-                        // associated automatically to the public output of the main function
-                        if RESERVED_ARGS.contains(&arg.name.value.as_str()) {
-                            return Err(Error::new(
-                                "type-checker",
-                                ErrorKind::PublicOutputReserved(arg.name.value.to_string()),
-                                arg.name.span,
-                            ));
-                        }
-
-                        // `pub` arguments are only for the main function
-                        if !is_main && arg.is_public() {
-                            return Err(Error::new(
-                                "type-checker",
-                                ErrorKind::PubArgumentOutsideMain,
-                                arg.attribute.as_ref().unwrap().span,
-                            ));
-                        }
-
-                        // `const` arguments are only for non-main functions
-                        if is_main && arg.is_constant() {
-                            return Err(Error::new(
-                                "type-checker",
-                                ErrorKind::ConstArgumentNotForMain,
-                                arg.name.span,
-                            ));
-                        }
-
-                        // store the args' type in the fn environment
-                        let arg_typ = arg.typ.kind.clone();
-
-                        if arg.is_constant() {
-                            typed_fn_env.store_type(
-                                arg.name.value.clone(),
-                                TypeInfo::new_cst(arg_typ, arg.span),
-                            )?;
-                        } else {
-                            typed_fn_env.store_type(
-                                arg.name.value.clone(),
-                                TypeInfo::new(arg_typ, arg.span),
-                            )?;
-                        }
-                    }
-
-                    // the output value returned by the main function is also a main_args with a special name (public_output)
-                    if let Some(typ) = &function.sig.return_type {
-                        if is_main {
-                            if !matches!(typ.kind, TyKind::Field) {
-                                unimplemented!();
-                            }
-
-                            typed_fn_env.store_type(
-                                "public_output".to_string(),
-                                TypeInfo::new_mut(typ.kind.clone(), typ.span),
-                            )?;
-                        }
-                    }
-
-                    // type system pass on the function body
-                    self.check_block(
-                        &mut typed_fn_env,
-                        &function.body,
-                        function.sig.return_type.as_ref(),
-                    )?;
+                    // type check the arguments and the body
+                    self.check_fn_body(function, is_main)?;
                 }
 
                 RootKind::Use(_)
                 | RootKind::ConstDef(_)
                 | RootKind::StructDef(_)
+                | RootKind::TypeAlias(_)
                 | RootKind::Comment(_) => (),
             };
         }
 
+        Ok(self.node_id)
+    }
+
+    /// Type checks the arguments and the body of a function.
+    /// The body of a generic function is only type checked in its instances (see [generics]).
+    fn check_fn_body(&mut self, function: &FunctionDef, is_main: bool) -> Result<()> {
+        // create a new typed fn environment to type check the function
+        let mut typed_fn_env = TypedFnEnv::default();
+
+        // store variables and their types in the fn_env
+        for arg in &function.sig.arguments {
+            // public_output is a reserved name,
+            // associated automatically to the public output of the main function
+            if RESERVED_ARGS.contains(&arg.name.value.as_str()) {
+                return Err(Error::new(
+                    "type-checker",
+                    ErrorKind::PublicOutputReserved(arg.name.value.to_string()),
+                    arg.name.span,
+                ));
+            }
+
+            // `pub` arguments are only for the main function
+            if !is_main && arg.is_public() {
+                return Err(Error::new(
+                    "type-checker",
+                    ErrorKind::PubArgumentOutsideMain,
+                    arg.attribute.as_ref().unwrap().span,
+                ));
+            }
+
+            // the validity of private inputs must always be checked in the circuit
+            if let Some(unchecked_span) = arg.unchecked {
+                if !is_main || !arg.is_public() {
+                    return Err(Error::new(
+                        "type-checker",
+                        ErrorKind::UncheckedNotPublicInput,
+                        unchecked_span,
+                    ));
+                }
+            }
+
+            // `const` arguments are only for non-main functions
+            if is_main && arg.is_constant() {
+                return Err(Error::new(
+                    "type-checker",
+                    ErrorKind::ConstArgumentNotForMain,
+                    arg.name.span,
+                ));
+            }
+
+            // store the args' type in the fn environment
+            let arg_typ = arg.typ.kind.clone();
+
+            if arg.is_constant() {
+                typed_fn_env
+                    .store_type(arg.name.value.clone(), TypeInfo::new_cst(arg_typ, arg.span))?;
+            } else if arg.mutable {
+                typed_fn_env
+                    .store_type(arg.name.value.clone(), TypeInfo::new_mut(arg_typ, arg.span))?;
+            } else {
+                typed_fn_env
+                    .store_type(arg.name.value.clone(), TypeInfo::new(arg_typ, arg.span))?;
+            }
+        }
+
+        // the output value returned by the main function is also a main_args with a special name (public_output)
+        if let Some(typ) = &function.sig.return_type {
+            if is_main {
+                typed_fn_env.store_type(
+                    "public_output".to_string(),
+                    TypeInfo::new_mut(typ.kind.clone(), typ.span),
+                )?;
+            }
+        }
+
+        // the body can return early (in an `if` statement),
+        // but it must end with a `return` so that every path returns a value
+        if function.sig.return_type.is_some()
+            && !matches!(
+                function.body.last().map(|stmt| &stmt.kind),
+                Some(StmtKind::Return(_))
+            )
+        {
+            return Err(self.error(ErrorKind::MissingReturn, function.span));
+        }
+
+        // generic functions are type checked through their instances
+        if !function.sig.generics.is_empty() {
+            return Ok(());
+        }
+
+        // type system pass on the function body
+        self.check_block(
+            &mut typed_fn_env,
+            &function.body,
+            function.sig.return_type.as_ref(),
+        )?;
+
+        // the values returned early must have the same type as the value returned at the end
+        for (observed, span) in &typed_fn_env.early_returns {
+            match &function.sig.return_type {
+                None => return Err(self.error(ErrorKind::NoReturnExpected, *span)),
+                Some(expected) if !observed.match_expected(&expected.kind) => {
+                    return Err(self.error(
+                        ErrorKind::ReturnTypeMismatch(observed.clone(), expected.kind.clone()),
+                        *span,
+                    ))
+                }
+                Some(_) => (),
+            }
+        }
+
         Ok(())
     }
 }
+
+/// Checks that a test function can be compiled as the main function of a circuit,
+/// with one input per argument and an output if (and only if) it returns something.
+fn check_test_fn(function: &FunctionDef, attribute: &Attribute) -> Result<()> {
+    let err = |msg| {
+        Err(Error::new(
+            "type-checker",
+            ErrorKind::InvalidTestFunction(msg),
+            attribute.span,
+        ))
+    };
+
+    let (inputs, output) = match &attribute.kind {
+        AttributeKind::Test { inputs, output } => (inputs, output),
+        AttributeKind::Pub | AttributeKind::Const | AttributeKind::NoDoubleGate => {
+            return Err(Error::new(
+                "type-checker",
+                ErrorKind::InvalidAttribute(attribute.kind.clone()),
+                attribute.span,
+            ))
+        }
+    };
+
+    if function.is_main() {
+        return err("the main function cannot be a test");
+    }
+    if matches!(function.sig.kind, FuncOrMethod::Method(_)) {
+        return err("methods cannot be tests");
+    }
+    if !function.sig.generics.is_empty() {
+        return err("generic functions cannot be tests");
+    }
+    if function.sig.arguments.iter().any(|arg| arg.is_constant()) {
+        return err("the arguments of a test cannot be constants");
+    }
+    if inputs.len() != function.sig.arguments.len() {
+        return err("expected one input per argument");
+    }
+    if output.is_some() != function.sig.return_type.is_some() {
+        return err("expected an output if, and only if, the function returns a value");
+    }
+
+    Ok(())
+}
@@ -9,7 +9,7 @@ pub fn display_source(
     sources: &crate::compiler::Sources,
     debug_infos: &[crate::circuit_writer::DebugInfo],
 ) {
-    for crate::circuit_writer::DebugInfo { span, note: _ } in debug_infos {
+    for crate::circuit_writer::DebugInfo { span, .. } in debug_infos {
         // find filename and source
         let (file, source) = sources.get(&span.filename_id).expect("source not found");
 
@@ -41,6 +41,15 @@ pub fn display_source(
     res.push('\n');
 }
 
+/// Returns the location of the start of a span, as `file:line:column` (both starting at 1).
+pub fn span_location(sources: &crate::compiler::Sources, span: crate::constants::Span) -> String {
+    let (file, source) = sources.get(&span.filename_id).expect("source not found");
+    let (line_number, start, _line) = find_exact_line(source, span);
+    let column = source[start..span.start].chars().count() + 1;
+
+    format!("{file}:{line_number}:{column}")
+}
+
 fn find_exact_line(source: &str, span: crate::constants::Span) -> (usize, usize, &str) {
     let ss = source.as_bytes();
     let mut start = span.start;
@@ -85,4 +94,15 @@ yz
             (2, 5, "efgh\nijkl")
         );
     }
+
+    #[test]
+    fn span_locations() {
+        let mut sources = crate::compiler::Sources::new();
+        let id = sources.add("lines.no".to_string(), "abcd\nefgh\n".to_string());
+
+        let location = |start| span_location(&sources, crate::constants::Span::new(id, start, 1));
+        assert_eq!(location(0), "lines.no:1:1");
+        assert_eq!(location(3), "lines.no:1:4");
+        assert_eq!(location(7), "lines.no:2:3");
+    }
 }
@@ -21,7 +21,10 @@ pub enum Value<B>
 where
     B: Backend,
 {
-    /// Either it's a hint and can be computed from the outside.
+    /// Either it's a hint and can be computed from the outside,
+    /// by a function of the values of other cells (see [Backend::new_hint]).
+    /// A hint is not a constraint: it must be fully pinned by the constraints that use it,
+    /// as the prover is free to replace it by any other value.
     #[serde(skip)]
     // TODO: outch, remove hints? or https://docs.rs/serde_closure/latest/serde_closure/ ?
     // TODO: changed to Arc from Box because Value needs to be cloneable, because of cloneable backend and FnInfo
@@ -71,7 +74,7 @@ impl<B: Backend> std::fmt::Debug for Value<B> {
 }
 
 /// Represents a cell in the execution trace.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ConstOrCell<F, C>
 where
     F: Field,
@@ -6,9 +6,9 @@ use itertools::chain;
 
 use crate::{
     backends::{Backend, BackendVar},
-    circuit_writer::CircuitWriter,
+    circuit_writer::{CircuitWriter, Constraint},
     compiler::Sources,
-    error::{Error, ErrorKind, Result},
+    error::{Error, ErrorKind, Result, Warning},
     inputs::JsonInputs,
     type_checker::FnInfo,
 };
@@ -21,6 +21,10 @@ where
     pub var_values: HashMap<String, Vec<F>>,
 
     pub cached_values: HashMap<usize, F>,
+
+    /// The values of the arguments and local variables of the main function,
+    /// only filled by [CompiledCircuit::witness_env].
+    pub named_values: HashMap<String, Vec<F>>,
 }
 
 impl<F: Field> WitnessEnv<F> {
@@ -32,17 +36,35 @@ impl<F: Field> WitnessEnv<F> {
         // TODO: return an error instead of crashing
         self.var_values.get(name).unwrap().clone()
     }
+
+    /// Returns the value of an argument or local variable of the main function,
+    /// if it exists and is made of a single field element.
+    pub fn value_of(&self, name: &str) -> Option<F> {
+        match self.values_of(name)? {
+            [value] => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the field elements of an argument or local variable of the main function
+    /// (a mutable variable has the last value assigned to it).
+    pub fn values_of(&self, name: &str) -> Option<&[F]> {
+        self.named_values.get(name).map(Vec::as_slice)
+    }
 }
 
 /// The compiled circuit.
 //#[derive(Serialize, Deserialize)]
 pub struct CompiledCircuit<B: Backend> {
     pub circuit: CircuitWriter<B>,
+
+    /// The warnings emitted while compiling the circuit.
+    pub warnings: Vec<Warning>,
 }
 
 impl<B: Backend> CompiledCircuit<B> {
-    pub(crate) fn new(circuit: CircuitWriter<B>) -> Self {
-        Self { circuit }
+    pub(crate) fn new(circuit: CircuitWriter<B>, warnings: Vec<Warning>) -> Self {
+        Self { circuit, warnings }
     }
 
     pub fn main_info(&self) -> &FnInfo<B> {
@@ -55,11 +77,44 @@ impl<B: Backend> CompiledCircuit<B> {
         self.circuit.backend.generate_asm(sources, debug)
     }
 
+    /// Returns the constraints of the circuit, as polynomials over its variables
+    /// (e.g. to export the relation to an SMT solver, see [Backend::constraints]).
+    pub fn constraints(&self) -> Result<Vec<Constraint<B::Field>>> {
+        self.circuit.backend.constraints()
+    }
+
+    /// Generates the witness of the circuit for the given inputs.
+    /// This is deterministic: no randomness is used outside of proving,
+    /// and the values (including the ones computed by hints) are always computed in the same order,
+    /// so the same inputs always produce the same witness (or the same error).
     pub fn generate_witness(
+        &self,
+        public_inputs: JsonInputs,
+        private_inputs: JsonInputs,
+    ) -> Result<B::GeneratedWitness> {
+        let mut env = self.init_witness_env(public_inputs, private_inputs)?;
+        self.circuit.generate_witness(&mut env)
+    }
+
+    /// Computes the values of the arguments and local variables of the main function,
+    /// which can then be queried with [WitnessEnv::value_of].
+    /// No constraint is checked, so this works even if the witness generation fails.
+    pub fn witness_env(
+        &self,
+        public_inputs: JsonInputs,
+        private_inputs: JsonInputs,
+    ) -> Result<WitnessEnv<B::Field>> {
+        let mut env = self.init_witness_env(public_inputs, private_inputs)?;
+        self.circuit.compute_named_values(&mut env)?;
+        Ok(env)
+    }
+
+    /// Creates a witness environment containing the inputs of the main function.
+    pub(crate) fn init_witness_env(
         &self,
         mut public_inputs: JsonInputs,
         mut private_inputs: JsonInputs,
-    ) -> Result<B::GeneratedWitness> {
+    ) -> Result<WitnessEnv<B::Field>> {
         let mut env = WitnessEnv::default();
 
         // get info on main
@@ -91,9 +146,10 @@ impl<B: Backend> CompiledCircuit<B> {
                 })?
             };
 
-            let fields = self
-                .parse_single_input(input, &arg.typ.kind)
-                .map_err(|e| Error::new("runtime", ErrorKind::ParsingError(e), arg.span))?;
+            let fields = self.parse_single_input(input, &arg.typ.kind).map_err(|e| {
+                let e = e.with_key_prefix(name);
+                Error::new("runtime", ErrorKind::ParsingError(e), arg.span)
+            })?;
 
             env.add_value(name.clone(), fields.clone());
         }
@@ -107,6 +163,6 @@ impl<B: Backend> CompiledCircuit<B> {
             ));
         }
 
-        self.circuit.generate_witness(&mut env)
+        Ok(env)
     }
 }